@@ -0,0 +1,82 @@
+/*
+ * C interface of the haversine crate, declaring the functions of its `ffi`
+ * module. Link against the static library built by
+ *
+ *     cargo rustc --lib --release --crate-type staticlib
+ *
+ * Values borrowed from a document are valid until it is released. Errors
+ * are returned as a null pointer or NaN, with the message read from
+ * haversine_last_error().
+ */
+
+#ifndef HAVERSINE_H
+#define HAVERSINE_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct haversine_document haversine_document;
+typedef struct haversine_value haversine_value;
+
+/* Kernels of haversine_batch_kernel(). */
+#define HAVERSINE_KERNEL_HAVERSINE 0u
+#define HAVERSINE_KERNEL_CACHED_TRIG 1u
+#define HAVERSINE_KERNEL_DOT 2u
+#define HAVERSINE_KERNEL_FMA 3u
+#define HAVERSINE_KERNEL_FAST_MATH 4u
+#define HAVERSINE_KERNEL_VINCENTY 5u
+#define HAVERSINE_KERNEL_COSINE 6u
+
+typedef enum haversine_kind {
+    HAVERSINE_NULL = 0,
+    HAVERSINE_BOOL = 1,
+    HAVERSINE_NUMBER = 2,
+    HAVERSINE_STRING = 3,
+    HAVERSINE_ARRAY = 4,
+    HAVERSINE_OBJECT = 5,
+} haversine_kind;
+
+/* Message of the last error of the calling thread. */
+const char *haversine_last_error(void);
+
+/* Distance of two points, longitude before latitude, in degrees. */
+double haversine_distance(double radius, double x0, double y0, double x1, double y1);
+/* Average distance of `count` pairs of four coordinates each, x0 y0 x1 y1,
+ * writing each distance to `out` unless it is null. NaN if `coordinates`
+ * is null or `count` too large for a buffer. */
+double haversine_distance_buffer(const double *coordinates, size_t count, double radius, double *out);
+/* Average distance of `len` pairs held in four columns, writing each
+ * distance to `out` unless it is null. */
+double haversine_batch(const double *x0, const double *y0, const double *x1, const double *y1,
+                       size_t len, double radius, double *out);
+/* haversine_batch() with a kernel, on `threads` threads or all cores for 0. */
+double haversine_batch_kernel(const double *x0, const double *y0, const double *x1, const double *y1,
+                              size_t len, double radius, uint32_t kernel, size_t threads, double *out);
+
+haversine_document *haversine_document_parse(const uint8_t *bytes, size_t len);
+void haversine_document_free(haversine_document *document);
+const haversine_value *haversine_document_root(const haversine_document *document);
+
+haversine_kind haversine_value_kind(const haversine_value *value);
+/* NaN if the value is not a number. */
+double haversine_value_number(const haversine_value *value);
+/* 1 or 0, or -1 if the value is not a boolean. */
+int32_t haversine_value_bool(const haversine_value *value);
+/* Bytes of a string, not NUL-terminated; null if the value is not one. */
+const uint8_t *haversine_value_string(const haversine_value *value, size_t *len);
+size_t haversine_value_len(const haversine_value *value);
+const haversine_value *haversine_value_index(const haversine_value *value, size_t index);
+const uint8_t *haversine_value_key(const haversine_value *value, size_t index, size_t *len);
+const haversine_value *haversine_value_get(const haversine_value *value, const uint8_t *key, size_t key_len);
+/* Average distance of the pairs of a pairs document. */
+double haversine_value_average(const haversine_value *value, double radius);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
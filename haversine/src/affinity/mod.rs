@@ -0,0 +1,134 @@
+//! Placement of threads on cores and of memory on NUMA nodes, for the
+//! measurements of memory bandwidth. Unsupported systems do nothing and
+//! report it.
+
+/// Pins the calling thread to `core`, returning whether the system did.
+pub fn pin_current_thread(core: usize) -> bool {
+    sys::pin_current_thread(core)
+}
+
+/// Spreads the pages of `buffer` over the NUMA nodes in turn, moving those
+/// already placed, so threads on any node share the bandwidth of all of
+/// them. Whole pages move, with whatever else is on the first and last.
+/// Returns whether the system did; only Linux does.
+pub fn interleave<T>(buffer: &[T]) -> bool {
+    buffer.is_empty() || sys::interleave(buffer.as_ptr().cast(), size_of_val(buffer))
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+    use std::ffi::{c_int, c_long, c_ulong};
+
+    /// Cores a `cpu_set_t` holds.
+    const CPU_SETSIZE: usize = 1024;
+    const BITS: usize = c_ulong::BITS as usize;
+    const SC_PAGESIZE: c_int = 30;
+    const MPOL_INTERLEAVE: c_ulong = 3;
+    const MPOL_MF_MOVE: c_ulong = 1 << 1;
+
+    #[cfg(target_arch = "x86_64")]
+    const SYS_MBIND: Option<c_long> = Some(237);
+    #[cfg(target_arch = "aarch64")]
+    const SYS_MBIND: Option<c_long> = Some(235);
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    const SYS_MBIND: Option<c_long> = None;
+
+    unsafe extern "C" {
+        fn sched_setaffinity(pid: c_int, size: usize, mask: *const c_ulong) -> c_int;
+        fn sysconf(name: c_int) -> c_long;
+        fn syscall(number: c_long, ...) -> c_long;
+    }
+
+    pub(super) fn pin_current_thread(core: usize) -> bool {
+        if core >= CPU_SETSIZE {
+            return false;
+        }
+        let mut mask = [0 as c_ulong; CPU_SETSIZE / BITS];
+        mask[core / BITS] |= 1 << (core % BITS);
+        // SAFETY: `mask` is a `cpu_set_t` of the size passed, and pid 0 is
+        // the calling thread.
+        unsafe { sched_setaffinity(0, size_of_val(&mask), mask.as_ptr()) == 0 }
+    }
+
+    /// The online NUMA nodes, from a list such as `0-1,3`.
+    fn online_nodes() -> Option<Vec<usize>> {
+        let list = std::fs::read_to_string("/sys/devices/system/node/online").ok()?;
+        let mut nodes = Vec::new();
+        for range in list.trim().split(',') {
+            let (first, last) = range.split_once('-').unwrap_or((range, range));
+            let (first, last): (usize, usize) = (first.parse().ok()?, last.parse().ok()?);
+            nodes.extend(first..=last);
+        }
+        Some(nodes)
+    }
+
+    pub(super) fn interleave(start: *const u8, len: usize) -> bool {
+        let (Some(number), Some(nodes)) = (SYS_MBIND, online_nodes()) else {
+            return false;
+        };
+        let Some(&max_node) = nodes.iter().max() else {
+            return false;
+        };
+        let mut mask = vec![0 as c_ulong; max_node / BITS + 1];
+        for node in nodes {
+            mask[node / BITS] |= 1 << (node % BITS);
+        }
+        // SAFETY: `sysconf` only reads a configuration value.
+        let page = unsafe { sysconf(SC_PAGESIZE) }.max(1) as usize;
+        let address = start as usize;
+        let aligned = address - address % page;
+        // SAFETY: `mbind` changes the policy of the pages of the range, all
+        // mapped since they hold the buffer, and reads `mask`, whose bits
+        // the node count passed covers. The kernel reads one node less than
+        // it is given.
+        let result = unsafe {
+            syscall(
+                number,
+                aligned as c_ulong,
+                (address + len - aligned) as c_ulong,
+                MPOL_INTERLEAVE,
+                mask.as_ptr(),
+                (mask.len() * BITS + 1) as c_ulong,
+                MPOL_MF_MOVE,
+            )
+        };
+        result == 0
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    use std::ffi::c_void;
+
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn GetCurrentThread() -> *mut c_void;
+        fn SetThreadAffinityMask(thread: *mut c_void, mask: usize) -> usize;
+    }
+
+    pub(super) fn pin_current_thread(core: usize) -> bool {
+        if core >= usize::BITS as usize {
+            return false;
+        }
+        // SAFETY: the pseudo handle of the current thread needs no closing.
+        unsafe { SetThreadAffinityMask(GetCurrentThread(), 1 << core) != 0 }
+    }
+
+    pub(super) fn interleave(_start: *const u8, _len: usize) -> bool {
+        false
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+mod sys {
+    pub(super) fn pin_current_thread(_core: usize) -> bool {
+        false
+    }
+
+    pub(super) fn interleave(_start: *const u8, _len: usize) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,22 @@
+use super::{interleave, pin_current_thread};
+use std::thread;
+
+#[test]
+fn test_pin_current_thread() {
+    // On a thread of its own, so the test threads stay unpinned.
+    let pinned = thread::spawn(|| pin_current_thread(0)).join().unwrap();
+    assert_eq!(cfg!(any(target_os = "linux", windows)), pinned);
+    assert!(
+        !thread::spawn(|| pin_current_thread(1 << 20))
+            .join()
+            .unwrap()
+    );
+}
+
+#[test]
+fn test_interleave_keeps_contents() {
+    assert!(interleave::<f64>(&[]));
+    let buffer: Vec<f64> = (0..100_000).map(f64::from).collect();
+    interleave(&buffer);
+    assert!(buffer.iter().enumerate().all(|(i, &x)| x == i as f64));
+}
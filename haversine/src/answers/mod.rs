@@ -0,0 +1,110 @@
+//! The answers of a pairs file, to check a computation pair by pair: the
+//! distance of every pair and then their average, as raw little-endian
+//! `f64`s with no header, the layout of the course's reference generator.
+
+use crate::compute::ComputeDriver;
+use crate::dd::DoubleDouble;
+use crate::geo::reference;
+use crate::pairs::Pairs;
+use crate::validate::relative_error;
+use std::io::{self, Read, Write};
+
+/// Distances of the pairs of a file, in order, and their average.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Answers {
+    pub distances: Vec<f64>,
+    pub average: f64,
+}
+
+/// A distance that differs from its answer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mismatch {
+    /// Index of the pair.
+    pub index: usize,
+    /// The answer, or `None` past the last one.
+    pub expected: Option<f64>,
+    /// The distance checked, or `None` past the last one.
+    pub computed: Option<f64>,
+}
+
+impl Answers {
+    /// Computes the answers of `pairs` on a sphere of `radius`.
+    pub fn compute(pairs: &Pairs, radius: f64) -> Self {
+        let result = ComputeDriver::new(radius).keep_distances(true).run(pairs);
+        Self {
+            distances: result.distances,
+            average: result.average,
+        }
+    }
+
+    /// Computes the answers of `pairs` in double-double arithmetic, each
+    /// distance and the average rounded once to an `f64`. Far slower than
+    /// `compute`, for checking it on datasets that stress it.
+    pub fn reference(pairs: &Pairs, radius: f64) -> Self {
+        let mut sum = DoubleDouble::default();
+        let distances = pairs
+            .iter()
+            .map(|pair| {
+                let (p0, p1) = (pair.p0, pair.p1);
+                let distance = reference::distance(radius, p0.lon, p0.lat, p1.lon, p1.lat);
+                sum = sum.add_dd(distance);
+                distance.to_f64()
+            })
+            .collect();
+        Self {
+            distances,
+            average: sum.div_f64(pairs.len() as f64).to_f64(),
+        }
+    }
+
+    pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        for value in self.distances.iter().chain([&self.average]) {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        writer.flush()
+    }
+
+    /// Reads answers written by `write`. Input that is not a whole number
+    /// of `f64`s, at least one, is `InvalidData`.
+    pub fn read(mut reader: impl Read) -> io::Result<Self> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        if bytes.is_empty() || bytes.len() % size_of::<f64>() != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} bytes is not a whole number of f64s", bytes.len()),
+            ));
+        }
+        let mut distances: Vec<f64> = bytes
+            .chunks_exact(size_of::<f64>())
+            .map(|word| f64::from_le_bytes(word.try_into().expect("Eight bytes")))
+            .collect();
+        let average = distances.pop().expect("At least one value");
+        Ok(Self { distances, average })
+    }
+
+    /// Checks `distances` against the answers, returning how many are off
+    /// by more than `tolerance`, relative to the answer, and the first of
+    /// them. Missing or extra distances count as off.
+    pub fn check(&self, distances: &[f64], tolerance: f64) -> (usize, Option<Mismatch>) {
+        let len = self.distances.len().max(distances.len());
+        let mut off = (0..len).filter_map(|index| {
+            let expected = self.distances.get(index).copied();
+            let computed = distances.get(index).copied();
+            let matches = match (expected, computed) {
+                (Some(expected), Some(computed)) => relative_error(expected, computed) <= tolerance,
+                _ => false,
+            };
+            (!matches).then_some(Mismatch {
+                index,
+                expected,
+                computed,
+            })
+        });
+        let first = off.next();
+        (first.map_or(0, |_| 1 + off.count()), first)
+    }
+}
+
+#[cfg(test)]
+mod tests;
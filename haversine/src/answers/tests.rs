@@ -0,0 +1,56 @@
+use super::{Answers, Mismatch};
+use crate::generate::{Distribution, generate_pairs};
+use crate::rng::Xoshiro256PlusPlus;
+use std::io;
+
+#[test]
+fn test_answers_round_trip() {
+    let pairs = generate_pairs(&mut Xoshiro256PlusPlus::new(3), 100, Distribution::Uniform);
+    let answers = Answers::compute(&pairs, 6372.8);
+    assert_eq!(100, answers.distances.len());
+    let mut bytes = Vec::new();
+    answers.write(&mut bytes).unwrap();
+    assert_eq!(101 * 8, bytes.len());
+    assert_eq!(answers.average.to_le_bytes(), bytes[100 * 8..]);
+    assert_eq!(answers, Answers::read(&bytes[..]).unwrap());
+}
+
+#[test]
+fn test_read_invalid_answers() {
+    for bytes in [&[][..], &[0; 12][..]] {
+        let error = Answers::read(bytes).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, error.kind());
+    }
+}
+
+#[test]
+fn test_check_answers() {
+    let answers = Answers {
+        distances: vec![1.0, 2.0, 3.0],
+        average: 2.0,
+    };
+    assert_eq!((0, None), answers.check(&[1.0, 2.0 + 1e-12, 3.0], 1e-9));
+    assert_eq!(
+        (
+            2,
+            Some(Mismatch {
+                index: 1,
+                expected: Some(2.0),
+                computed: Some(2.5),
+            })
+        ),
+        answers.check(&[1.0, 2.5, f64::NAN], 1e-9)
+    );
+    let (off, first) = answers.check(&[1.0, 2.0], 1e-9);
+    assert_eq!(1, off);
+    assert_eq!(None, first.unwrap().computed);
+}
+
+#[test]
+fn test_reference_close_to_computed() {
+    let pairs = generate_pairs(&mut Xoshiro256PlusPlus::new(8), 200, Distribution::Uniform);
+    let reference = Answers::reference(&pairs, 6372.8);
+    let computed = Answers::compute(&pairs, 6372.8);
+    assert_eq!((0, None), reference.check(&computed.distances, 1e-9));
+    assert!((reference.average - computed.average).abs() < 1e-9 * reference.average);
+}
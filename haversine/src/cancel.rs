@@ -0,0 +1,57 @@
+//! Cancellation of long runs, checked every so often by the streaming
+//! parser and the compute loop so they stop cleanly with what they have.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Token shared by the code that cancels a run and the run itself. Clones
+/// share the same state, so cancelling one cancels them all.
+///
+/// ```
+/// use haversine::cancel::CancelToken;
+///
+/// let token = CancelToken::new();
+/// let run = token.clone();
+/// assert!(!run.is_cancelled());
+/// token.cancel();
+/// assert!(run.is_cancelled());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a token that cancels by itself once `timeout` has passed.
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self {
+            cancelled: Arc::default(),
+            deadline: Instant::now().checked_add(timeout),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns whether the token was cancelled or its deadline has passed.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+/// Tokens are equal if they are clones of each other.
+impl PartialEq for CancelToken {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.cancelled, &other.cancelled) && self.deadline == other.deadline
+    }
+}
@@ -0,0 +1,155 @@
+//! State of a long computation saved to a file, so it can resume after an
+//! interruption rather than start over.
+
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+/// First line of a checkpoint file, naming its format version.
+const HEADER: &str = "haversine checkpoint 1";
+
+/// Progress of a computation. The sums are stored bit for bit, so a resumed
+/// run ends with exactly the result of an uninterrupted one.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Checkpoint {
+    /// `fingerprint` of the input, which must match to resume.
+    pub fingerprint: u64,
+    /// Pairs, or rows of a matrix, done.
+    pub position: usize,
+    /// Sum of the distances done.
+    pub sum: f64,
+    /// Sum of the distances done in double-double arithmetic, high and low
+    /// parts.
+    pub wide_sum: (f64, f64),
+    /// Bytes of output written for the work done.
+    pub output_len: u64,
+}
+
+/// Why a checkpoint could not be read.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum CheckpointError {
+    Io(io::Error),
+    /// A line that is missing or does not hold the expected field, by
+    /// number from 1.
+    Malformed(usize),
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckpointError::Io(_) => write!(f, "failed to read the checkpoint"),
+            CheckpointError::Malformed(line) => {
+                write!(f, "malformed checkpoint at line {line}")
+            }
+        }
+    }
+}
+
+impl Error for CheckpointError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CheckpointError::Io(error) => Some(error),
+            CheckpointError::Malformed(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for CheckpointError {
+    fn from(error: io::Error) -> Self {
+        CheckpointError::Io(error)
+    }
+}
+
+impl Checkpoint {
+    /// Reads the checkpoint at `path`, or `None` if there is no file.
+    pub fn read(path: impl AsRef<Path>) -> Result<Option<Self>, CheckpointError> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error.into()),
+        };
+        Self::parse(&text).map(Some)
+    }
+
+    fn parse(text: &str) -> Result<Self, CheckpointError> {
+        let mut lines = text.lines();
+        if lines.next() != Some(HEADER) {
+            return Err(CheckpointError::Malformed(1));
+        }
+        let mut number = 1;
+        let mut field = |name: &str| {
+            number += 1;
+            lines
+                .next()
+                .and_then(|line| line.strip_prefix(name)?.strip_prefix(' '))
+                .map(|value| (value, number))
+                .ok_or(CheckpointError::Malformed(number))
+        };
+        let fingerprint = hex(field("fingerprint")?)?;
+        let position = decimal(field("position")?)?;
+        let sum = f64::from_bits(hex(field("sum")?)?);
+        let (value, line) = field("wide_sum")?;
+        let (hi, lo) = value
+            .split_once(' ')
+            .ok_or(CheckpointError::Malformed(line))?;
+        let wide_sum = (
+            f64::from_bits(hex((hi, line))?),
+            f64::from_bits(hex((lo, line))?),
+        );
+        let output_len = decimal(field("output_len")?)?;
+        Ok(Self {
+            fingerprint,
+            position,
+            sum,
+            wide_sum,
+            output_len,
+        })
+    }
+
+    /// Writes the checkpoint to `path` through a temporary file renamed over
+    /// it, so an interruption leaves the previous checkpoint whole.
+    pub fn write(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+        let mut writer = BufWriter::new(File::create(&temporary)?);
+        writeln!(writer, "{HEADER}")?;
+        writeln!(writer, "fingerprint {:016x}", self.fingerprint)?;
+        writeln!(writer, "position {}", self.position)?;
+        writeln!(writer, "sum {:016x}", self.sum.to_bits())?;
+        writeln!(
+            writer,
+            "wide_sum {:016x} {:016x}",
+            self.wide_sum.0.to_bits(),
+            self.wide_sum.1.to_bits()
+        )?;
+        writeln!(writer, "output_len {}", self.output_len)?;
+        writer.into_inner()?.sync_all()?;
+        fs::rename(temporary, path)
+    }
+}
+
+fn hex((value, line): (&str, usize)) -> Result<u64, CheckpointError> {
+    u64::from_str_radix(value, 16).map_err(|_| CheckpointError::Malformed(line))
+}
+
+fn decimal<T: FromStr>((value, line): (&str, usize)) -> Result<T, CheckpointError> {
+    value.parse().map_err(|_| CheckpointError::Malformed(line))
+}
+
+/// Hashes the numbers a computation depends on, with 64-bit FNV-1a taking
+/// their bits a word at a time, to tell whether a checkpoint belongs to it.
+pub fn fingerprint(values: impl IntoIterator<Item = f64>) -> u64 {
+    values
+        .into_iter()
+        .fold(0xcbf2_9ce4_8422_2325, |hash: u64, value| {
+            (hash ^ value.to_bits()).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+#[cfg(test)]
+mod tests;
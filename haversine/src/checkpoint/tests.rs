@@ -0,0 +1,47 @@
+use super::{Checkpoint, CheckpointError, fingerprint};
+use std::fs;
+
+fn checkpoint() -> Checkpoint {
+    Checkpoint {
+        fingerprint: 0x0123_4567_89ab_cdef,
+        position: 1 << 40,
+        sum: 0.1 + 0.2,
+        wide_sum: (0.30000000000000004, -2.7755575615628914e-17),
+        output_len: 12_345,
+    }
+}
+
+#[test]
+fn test_round_trip_is_exact() {
+    let path = std::env::temp_dir().join(format!("checkpoint-{}.txt", std::process::id()));
+    checkpoint().write(&path).unwrap();
+    assert_eq!(Some(checkpoint()), Checkpoint::read(&path).unwrap());
+    let mut temporary = path.clone().into_os_string();
+    temporary.push(".tmp");
+    assert!(fs::metadata(temporary).is_err());
+    fs::remove_file(&path).unwrap();
+    assert_eq!(None, Checkpoint::read(&path).unwrap());
+}
+
+#[test]
+fn test_malformed_lines() {
+    let text = "haversine checkpoint 1\nfingerprint 00000000000000ff\nposition 3\n\
+                sum 3ff0000000000000\nwide_sum 3ff0000000000000 0\noutput_len 0\n";
+    assert_eq!(3, Checkpoint::parse(text).unwrap().position);
+    let malformed = |text: &str| match Checkpoint::parse(text) {
+        Err(CheckpointError::Malformed(line)) => line,
+        other => panic!("expected a malformed checkpoint, got {other:?}"),
+    };
+    assert_eq!(1, malformed(&text.replace("checkpoint 1", "checkpoint 2")));
+    assert_eq!(3, malformed(&text.replace("position 3", "position x")));
+    assert_eq!(5, malformed(&text.replace(" 0\n", "\n")));
+    assert_eq!(6, malformed(text.trim_end_matches("output_len 0\n")));
+}
+
+#[test]
+fn test_fingerprint() {
+    assert_eq!(0xcbf2_9ce4_8422_2325, fingerprint([]));
+    assert_eq!(fingerprint([1.0, 2.0]), fingerprint([1.0, 2.0]));
+    assert_ne!(fingerprint([1.0, 2.0]), fingerprint([2.0, 1.0]));
+    assert_ne!(fingerprint([0.0]), fingerprint([-0.0]));
+}
@@ -0,0 +1,59 @@
+use super::{FIELD, Sha256, checksum, seal, sha256};
+use crate::parse::{Parser, Value};
+
+fn hex(digest: [u8; 32]) -> String {
+    digest.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn document(input: &str) -> Value {
+    Parser::new(input).parse().unwrap().unwrap()
+}
+
+#[test]
+fn test_sha256_vectors() {
+    assert_eq!(
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+        hex(sha256(b""))
+    );
+    assert_eq!(
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+        hex(sha256(b"abc"))
+    );
+    assert_eq!(
+        "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+        hex(sha256(
+            b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"
+        ))
+    );
+}
+
+#[test]
+fn test_sha256_incremental() {
+    let message: Vec<u8> = (0..1000u32).map(|i| (i * 7) as u8).collect();
+    for split in [0, 1, 55, 56, 63, 64, 65, 500] {
+        let mut hash = Sha256::new();
+        hash.update(&message[..split]);
+        hash.update(&message[split..]);
+        assert_eq!(sha256(&message), hash.finish(), "{split}");
+    }
+}
+
+#[test]
+fn test_checksum_ignores_formatting() {
+    let a = document(r#"{"pairs": [{"x0": 1.50, "y0": 2}], "radius": 1}"#);
+    let b = document(r#"{ "radius": 1.0, "pairs": [ {"y0": 2, "x0": 15e-1} ] }"#);
+    assert_eq!(checksum(&a), checksum(&b));
+    assert!(checksum(&a).starts_with("sha256:"));
+    let c = document(r#"{"pairs": [{"x0": 1.5, "y0": 2.1}], "radius": 1}"#);
+    assert_ne!(checksum(&a), checksum(&c));
+}
+
+#[test]
+fn test_seal() {
+    let mut sealed = document(r#"{"pairs": [], "radius": 1}"#);
+    let expected = checksum(&sealed);
+    seal(&mut sealed);
+    assert_eq!(Value::from(expected.clone()), sealed[FIELD]);
+    // The checksum leaves itself out, so sealing again changes nothing.
+    assert_eq!(expected, checksum(&sealed));
+}
@@ -0,0 +1,2893 @@
+use crate::log::{Level, LogFormat};
+use crate::reptest::bandwidth::Access;
+use haversine::compute::{Precision, Reduction};
+use haversine::dump::DumpFormat;
+use haversine::generate::{Distribution, Preset};
+use haversine::geo::{AngleUnit, Body, BoundingBox, DistanceUnit, Kernel, RadiusModel};
+use haversine::math::MAX_DEGREE;
+use haversine::matrix::{MatrixFormat, MatrixShape};
+use haversine::pairs::SampleSize;
+use haversine::parse::{
+    DEFAULT_TREE_ITEMS, DuplicateKeyPolicy, Limits, LoneSurrogatePolicy, NumberRangePolicy,
+};
+use haversine::per_pair::PerPairFormat;
+use haversine::pipeline::InputFormat;
+use haversine::reader::{DEFAULT_BUFFER_SIZE, IoStrategy};
+use haversine::rng::Algorithm;
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
+
+const DEFAULT_PATH: &str = "../gendata/pairs.json";
+
+/// Seconds between two checkpoints by default.
+const DEFAULT_CHECKPOINT_INTERVAL: f64 = 60.0;
+
+/// Degree of the polynomials of `--math poly` by default.
+const DEFAULT_POLY_DEGREE: usize = 13;
+
+/// Largest relative error of the stored average accepted by default.
+const DEFAULT_TOLERANCE: f64 = 1e-6;
+
+/// An option of a subcommand, described for the help and the completions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct OptionSpec {
+    pub(crate) name: &'static str,
+    /// A one-letter alias, like `q` of `-q` for `--quiet`.
+    pub(crate) short: Option<char>,
+    /// Name of the value the option takes, if it takes one.
+    pub(crate) value: Option<&'static str>,
+    /// The values accepted, if they can be listed.
+    pub(crate) choices: &'static [&'static str],
+    /// The value is a path.
+    pub(crate) file: bool,
+    pub(crate) help: &'static str,
+}
+
+impl OptionSpec {
+    const fn flag(name: &'static str, help: &'static str) -> Self {
+        Self {
+            name,
+            short: None,
+            value: None,
+            choices: &[],
+            file: false,
+            help,
+        }
+    }
+
+    const fn value(name: &'static str, value: &'static str, help: &'static str) -> Self {
+        Self {
+            value: Some(value),
+            ..Self::flag(name, help)
+        }
+    }
+
+    const fn path(name: &'static str, help: &'static str) -> Self {
+        Self {
+            file: true,
+            ..Self::value(name, "PATH", help)
+        }
+    }
+
+    const fn choice(
+        name: &'static str,
+        choices: &'static [&'static str],
+        help: &'static str,
+    ) -> Self {
+        Self {
+            choices,
+            ..Self::value(name, "", help)
+        }
+    }
+
+    const fn short(self, short: char) -> Self {
+        Self {
+            short: Some(short),
+            ..self
+        }
+    }
+
+    /// The option as the help shows it, like `--io <sync|async|mmap>`.
+    fn usage(&self) -> String {
+        let mut usage = match self.short {
+            Some(short) => format!("-{short}, {}", self.name),
+            None => self.name.to_string(),
+        };
+        match self.value {
+            Some(_) if !self.choices.is_empty() => {
+                usage += &format!(" <{}>", self.choices.join("|"));
+            }
+            Some(value) => usage += &format!(" <{value}>"),
+            None => {}
+        }
+        usage
+    }
+}
+
+/// A subcommand, described for the help and the completions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct CommandSpec {
+    pub(crate) name: &'static str,
+    pub(crate) about: &'static str,
+    /// The arguments besides the options, as the usage line shows them.
+    pub(crate) arguments: &'static str,
+    /// The words the first argument is one of, if it is not a path.
+    pub(crate) choices: &'static [&'static str],
+    pub(crate) options: &'static [OptionSpec],
+}
+
+/// Options accepted before or after any subcommand.
+pub(crate) const GLOBAL_OPTIONS: &[OptionSpec] = &[
+    OptionSpec::flag("--quiet", "Write only errors to stderr").short('q'),
+    OptionSpec::flag("--verbose", "Write debug messages; -vv adds trace ones").short('v'),
+    OptionSpec::choice(
+        "--log-format",
+        &["plain", "json"],
+        "How messages are written",
+    ),
+    OptionSpec::path(
+        "--config",
+        "Read the defaults of the options from this file",
+    ),
+    OptionSpec::flag("--no-config", "Read no config file"),
+    OptionSpec::flag("--help", "Print the help of the command").short('h'),
+];
+
+const RADIUS_MODELS: &[&str] = &["mean", "equatorial", "polar", "authalic"];
+const BODIES: &[&str] = &["mercury", "venus", "earth", "moon", "mars", "jupiter"];
+const UNITS: &[&str] = &["km", "m", "mi", "nmi"];
+
+/// The subcommands, `compute` first as the one run without a name.
+pub(crate) const COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        name: "compute",
+        about: "Parse a pairs file and compute the average distance",
+        arguments: "[PATH]",
+        choices: &[],
+        options: &[
+            OptionSpec::choice("--mode", &["pairs", "route"], "What the input holds"),
+            OptionSpec::choice(
+                "--format",
+                &["json", "ndjson", "jsonl", "columnar"],
+                "How the pairs are laid out",
+            ),
+            OptionSpec::flag(
+                "--per-segment",
+                "Print the length of every segment of a route",
+            ),
+            OptionSpec::flag("--stream", "Parse the input in chunks as it is read"),
+            OptionSpec::flag("--tape", "Read the pairs from a tape of the input"),
+            OptionSpec::flag("--simd", "Find the tokens of the tape in a vectorized pass"),
+            OptionSpec::flag("--fast-schema", "Parse with the reader of the pairs schema"),
+            OptionSpec::flag("--progress", "Print the progress to stderr"),
+            OptionSpec::flag("--watch", "Run again whenever the input changes"),
+            OptionSpec::value(
+                "--sample",
+                "SIZE",
+                "Compute over a sample of N or P% of the pairs",
+            ),
+            OptionSpec::value("--sample-seed", "SEED", "Seed of the sample"),
+            OptionSpec::value(
+                "--read-buffer",
+                "SIZE",
+                "Size of each read buffer, as in 64K",
+            ),
+            OptionSpec::choice("--io", &["sync", "async", "mmap"], "How the input is read"),
+            OptionSpec::choice(
+                "--duplicate-keys",
+                &["error", "first-wins", "last-wins"],
+                "How duplicate object keys are handled",
+            ),
+            OptionSpec::flag("--lossy-surrogates", "Replace escaped lone surrogates"),
+            OptionSpec::choice(
+                "--out-of-range",
+                &["error", "clamp", "infinite"],
+                "How numbers out of the range of f64 are handled",
+            ),
+            OptionSpec::value("--max-size", "SIZE", "Largest input accepted, as in 64M"),
+            OptionSpec::value("--max-string-len", "N", "Longest string accepted, in bytes"),
+            OptionSpec::value("--max-array-len", "N", "Most elements of an array accepted"),
+            OptionSpec::value("--max-values", "N", "Most values of the input accepted"),
+            OptionSpec::flag(
+                "--allow-control-chars",
+                "Accept unescaped control characters",
+            ),
+            OptionSpec::flag(
+                "--strict-coords",
+                "Reject coordinates out of range rather than fix them",
+            ),
+            OptionSpec::flag("--transcode", "Convert UTF-16 or UTF-32 input to UTF-8"),
+            OptionSpec::flag("--emit-bearings", "Print the bearings of every pair"),
+            OptionSpec::flag("--wide-accumulator", "Also sum in double-double arithmetic"),
+            OptionSpec::choice(
+                "--math",
+                &["std", "poly"],
+                "Which sine, cosine and arc sine",
+            ),
+            OptionSpec::value(
+                "--poly-degree",
+                "N",
+                "Degree of the polynomials of --math poly",
+            ),
+            OptionSpec::choice(
+                "--kernel",
+                &[
+                    "haversine",
+                    "cached-trig",
+                    "dot",
+                    "fma",
+                    "fast-math",
+                    "vincenty",
+                    "cosine",
+                ],
+                "How the distance of a pair is computed",
+            ),
+            OptionSpec::path("--checkpoint", "Save the progress to this file"),
+            OptionSpec::value("--checkpoint-interval", "SECONDS", "Time between two saves"),
+            OptionSpec::flag("--resume", "Start from the saved progress"),
+            OptionSpec::value(
+                "--timeout",
+                "DURATION",
+                "Stop after this long, as in 60s, with partial results",
+            ),
+            OptionSpec::choice("--precision", &["f64", "f32"], "Precision of the distances"),
+            OptionSpec::value(
+                "--threads",
+                "N",
+                "Number of threads computing the distances",
+            ),
+            OptionSpec::flag("--pin-threads", "Pin the threads to cores"),
+            OptionSpec::flag("--interleave", "Spread the coordinates over the NUMA nodes"),
+            OptionSpec::flag("--huge-pages", "Back the buffers with huge pages"),
+            OptionSpec::choice(
+                "--reduction",
+                &["running", "tree"],
+                "How the distances are summed",
+            ),
+            OptionSpec::path(
+                "--per-pair",
+                "Write every distance to a .json, .csv or .f64 file",
+            ),
+            OptionSpec::flag(
+                "--per-pair-coords",
+                "Add the coordinates to the per-pair output",
+            ),
+            OptionSpec::flag(
+                "--per-pair-midpoints",
+                "Add the midpoint of each pair to the per-pair output",
+            ),
+            OptionSpec::path("--export-geojson", "Write every pair as a GeoJSON line"),
+            OptionSpec::path(
+                "--answers",
+                "Check every distance against this answers file",
+            ),
+            OptionSpec::flag(
+                "--stats",
+                "Print statistics of the distances and the centroid",
+            ),
+            OptionSpec::flag("--stats-json", "Print the statistics as JSON"),
+            OptionSpec::value(
+                "--histogram",
+                "BUCKETS",
+                "Print a histogram of the distances",
+            ),
+            OptionSpec::choice(
+                "--radius-model",
+                RADIUS_MODELS,
+                "Radius to use, or custom=KM",
+            ),
+            OptionSpec::choice(
+                "--body",
+                BODIES,
+                "Body whose mean radius to use, or custom=KM",
+            ),
+            OptionSpec::choice("--units", UNITS, "Unit of the distances"),
+            OptionSpec::choice(
+                "--angle-unit",
+                &["degrees", "radians", "gradians"],
+                "Unit of the coordinates of the input",
+            ),
+            OptionSpec::value(
+                "--bbox",
+                "LAT,LON,LAT,LON",
+                "Skip pairs outside the box, in degrees",
+            ),
+            OptionSpec::value(
+                "--tolerance",
+                "ERROR",
+                "Largest relative error of the average",
+            ),
+        ],
+    },
+    CommandSpec {
+        name: "bench",
+        about: "Run a micro-benchmark",
+        arguments: "<BENCHMARK> [PATH]",
+        choices: &[
+            "bandwidth",
+            "traversal",
+            "structural",
+            "parse",
+            "io",
+            "kernels",
+            "cache",
+        ],
+        options: &[
+            OptionSpec::value(
+                "--seconds",
+                "SECONDS",
+                "Time without a new minimum to stop after",
+            ),
+            OptionSpec::choice(
+                "--access",
+                &["read", "write", "both"],
+                "Accesses of bandwidth",
+            ),
+            OptionSpec::value("--sizes", "SIZE,...", "Buffer sizes of bandwidth"),
+            OptionSpec::value("--stride", "N", "Stride of the accesses of bandwidth"),
+            OptionSpec::value("--total", "SIZE", "Bytes accessed per size of bandwidth"),
+            OptionSpec::value("--read-buffer", "SIZE", "Size of each read buffer of io"),
+            OptionSpec::value("--runs", "N", "Runs of cache, the first of them cold"),
+            OptionSpec::flag(
+                "--drop-caches-hint",
+                "Advise dropping the file from the page cache before cache",
+            ),
+        ],
+    },
+    CommandSpec {
+        name: "validate",
+        about: "Check a pairs file and its stored average",
+        arguments: "<PATH>",
+        choices: &[],
+        options: &[
+            OptionSpec::value(
+                "--tolerance",
+                "ERROR",
+                "Largest relative error of the average",
+            ),
+            OptionSpec::flag(
+                "--reference",
+                "Check every distance against double-double arithmetic",
+            ),
+        ],
+    },
+    CommandSpec {
+        name: "fmt",
+        about: "Reformat a JSON document",
+        arguments: "<PATH>",
+        choices: &[],
+        options: &[
+            OptionSpec::value("--indent", "N", "Spaces per level of indentation"),
+            OptionSpec::flag("--compact", "Write no whitespace"),
+            OptionSpec::flag("--sort-keys", "Order the members of objects by key"),
+            OptionSpec::flag("--raw-numbers", "Write numbers as they appear in the input"),
+            OptionSpec::flag("--canonical", "Write the canonical form of RFC 8785"),
+        ],
+    },
+    CommandSpec {
+        name: "fuzz",
+        about: "Check the parser on generated and mutated documents",
+        arguments: "",
+        choices: &[],
+        options: &[
+            OptionSpec::value("--seed", "SEED", "Seed of the documents"),
+            OptionSpec::value("--iterations", "N", "Number of documents checked"),
+        ],
+    },
+    CommandSpec {
+        name: "matrix",
+        about: "Compute the distances between every two of a list of points",
+        arguments: "<PATH>",
+        choices: &[],
+        options: &[
+            OptionSpec::path("--output", "Write the matrix to a .csv or .f64 file"),
+            OptionSpec::flag("--upper", "Write only the upper triangle"),
+            OptionSpec::choice(
+                "--kernel",
+                &[
+                    "haversine",
+                    "cached-trig",
+                    "dot",
+                    "fma",
+                    "fast-math",
+                    "vincenty",
+                    "cosine",
+                ],
+                "How the distance of two points is computed",
+            ),
+            OptionSpec::path("--checkpoint", "Save the rows written to this file"),
+            OptionSpec::value("--checkpoint-interval", "SECONDS", "Time between two saves"),
+            OptionSpec::flag("--resume", "Start from the saved rows"),
+            OptionSpec::value(
+                "--threads",
+                "N",
+                "Number of threads, one per core by default",
+            ),
+            OptionSpec::choice(
+                "--radius-model",
+                RADIUS_MODELS,
+                "Radius to use, or custom=KM",
+            ),
+            OptionSpec::choice(
+                "--body",
+                BODIES,
+                "Body whose mean radius to use, or custom=KM",
+            ),
+            OptionSpec::choice("--units", UNITS, "Unit of the distances"),
+        ],
+    },
+    CommandSpec {
+        name: "area",
+        about: "Compute the area of a polygon of points",
+        arguments: "<PATH>",
+        choices: &[],
+        options: &[
+            OptionSpec::choice(
+                "--radius-model",
+                RADIUS_MODELS,
+                "Radius to use, or custom=KM",
+            ),
+            OptionSpec::choice(
+                "--body",
+                BODIES,
+                "Body whose mean radius to use, or custom=KM",
+            ),
+            OptionSpec::choice("--units", UNITS, "Unit of the area, squared"),
+        ],
+    },
+    CommandSpec {
+        name: "compare-formulas",
+        about: "Compare the distance formulas on the pairs of a file",
+        arguments: "<PATH>",
+        choices: &[],
+        options: &[
+            OptionSpec::choice(
+                "--radius-model",
+                RADIUS_MODELS,
+                "Radius to use, or custom=KM",
+            ),
+            OptionSpec::choice(
+                "--body",
+                BODIES,
+                "Body whose mean radius to use, or custom=KM",
+            ),
+            OptionSpec::choice("--units", UNITS, "Unit of the deviations"),
+        ],
+    },
+    CommandSpec {
+        name: "generate",
+        about: "Write a file of random pairs",
+        arguments: "<PAIRS>",
+        choices: &[],
+        options: &[
+            OptionSpec::value("--radius", "KM", "Radius of the sphere"),
+            OptionSpec::choice(
+                "--body",
+                BODIES,
+                "Body whose mean radius to use, or custom=KM",
+            ),
+            OptionSpec::value(
+                "--clusters",
+                "N",
+                "Draw the points around this many centers",
+            ),
+            OptionSpec::value(
+                "--spread",
+                "DEGREES",
+                "Distance of the points from the centers",
+            ),
+            OptionSpec::choice(
+                "--preset",
+                &["antipodal", "poles", "dateline", "identical", "tiny-deltas"],
+                "Draw pairs at an edge case, with exact answers",
+            ),
+            OptionSpec::value("--seed", "SEED", "Seed of the random numbers"),
+            OptionSpec::choice(
+                "--rng",
+                &["xoshiro256++", "pcg64"],
+                "Random number generator",
+            ),
+            OptionSpec::path("--output", "Path of the pairs file, or - for stdout"),
+            OptionSpec::value(
+                "--digits",
+                "N",
+                "Decimal places of the coordinates, all by default",
+            ),
+            OptionSpec::flag("--compact", "Write the JSON without whitespace"),
+            OptionSpec::flag("--pretty", "Write the JSON indented, the default"),
+            OptionSpec::path(
+                "--answers",
+                "Path of the answers file, by default the output's with .answers",
+            ),
+            OptionSpec::flag("--no-answers", "Write no answers file"),
+        ],
+    },
+    CommandSpec {
+        name: "split",
+        about: "Divide a pairs file into shards",
+        arguments: "[PATH]",
+        choices: &[],
+        options: &[
+            OptionSpec::value("--shards", "N", "Number of shards"),
+            OptionSpec::value("--prefix", "PREFIX", "Start of the paths of the shards"),
+        ],
+    },
+    CommandSpec {
+        name: "merge",
+        about: "Join shards of a pairs file into one",
+        arguments: "<SHARD>...",
+        choices: &[],
+        options: &[OptionSpec::path(
+            "--output",
+            "Path of the merged file, or - for stdout",
+        )],
+    },
+    CommandSpec {
+        name: "export-columnar",
+        about: "Write the pairs of a pairs file in the columnar binary layout",
+        arguments: "[PATH]",
+        choices: &[],
+        options: &[OptionSpec::path(
+            "--output",
+            "Path of the columnar file, or - for stdout",
+        )],
+    },
+    CommandSpec {
+        name: "diff",
+        about: "Compare two JSON documents",
+        arguments: "<PATH> <PATH>",
+        choices: &[],
+        options: &[
+            OptionSpec::value(
+                "--tolerance",
+                "ERROR",
+                "Largest relative difference of floats",
+            ),
+            OptionSpec::value("--limit", "N", "Number of differences printed"),
+        ],
+    },
+    CommandSpec {
+        name: "dist",
+        about: "Compute the distance of two points",
+        arguments: "<LAT0> <LON0> <LAT1> <LON1>",
+        choices: &[],
+        options: &[
+            OptionSpec::value(
+                "--radius",
+                "KM|MODEL",
+                "Radius of the sphere, or a radius model",
+            ),
+            OptionSpec::choice(
+                "--body",
+                BODIES,
+                "Body whose mean radius to use, or custom=KM",
+            ),
+            OptionSpec::choice(
+                "--formula",
+                &["haversine", "cosine", "vincenty"],
+                "Formula of the distance",
+            ),
+            OptionSpec::choice("--units", UNITS, "Unit of the distance"),
+        ],
+    },
+    CommandSpec {
+        name: "repl",
+        about: "Compute distances interactively",
+        arguments: "",
+        choices: &[],
+        options: &[
+            OptionSpec::choice(
+                "--radius-model",
+                RADIUS_MODELS,
+                "Radius to use, or custom=KM",
+            ),
+            OptionSpec::choice(
+                "--body",
+                BODIES,
+                "Body whose mean radius to use, or custom=KM",
+            ),
+            OptionSpec::choice("--units", UNITS, "Unit of the distances"),
+        ],
+    },
+    CommandSpec {
+        name: "inspect",
+        about: "Print the size and structure of a JSON document",
+        arguments: "<PATH>",
+        choices: &[],
+        options: &[],
+    },
+    CommandSpec {
+        name: "extract",
+        about: "Copy the value at a path, or a range of an array, to a file",
+        arguments: "<PATH> <POINTER>",
+        choices: &[],
+        options: &[OptionSpec::path("--output", "Path of the extract, or - for stdout").short('o')],
+    },
+    CommandSpec {
+        name: "tokens",
+        about: "Print the tokens of a JSON document with their spans",
+        arguments: "<PATH>",
+        choices: &[],
+        options: &[OptionSpec::flag("--json", "Print a JSON object per token")],
+    },
+    CommandSpec {
+        name: "tree",
+        about: "Print a JSON document as an indented tree",
+        arguments: "<PATH>",
+        choices: &[],
+        options: &[
+            OptionSpec::value("--depth", "N", "Deepest level shown"),
+            OptionSpec::value("--items", "N", "Items shown of each array and object"),
+        ],
+    },
+    CommandSpec {
+        name: "completions",
+        about: "Print a completion script for a shell",
+        arguments: "<SHELL>",
+        choices: &["bash", "zsh", "fish"],
+        options: &[],
+    },
+];
+
+/// The subcommand named `name`.
+pub(crate) fn find_command(name: &str) -> Option<&'static CommandSpec> {
+    COMMANDS.iter().find(|command| command.name == name)
+}
+
+/// A shell the completions are written for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "bash" => Some(Shell::Bash),
+            "zsh" => Some(Shell::Zsh),
+            "fish" => Some(Shell::Fish),
+            _ => None,
+        }
+    }
+}
+
+/// The help of a subcommand, or of the program without one.
+pub(crate) fn help(command: Option<&CommandSpec>) -> String {
+    let mut out = String::new();
+    let options = |out: &mut String, title: &str, options: &[OptionSpec]| {
+        let width = options.iter().map(|option| option.usage().len()).max();
+        let Some(width) = width else {
+            return;
+        };
+        *out += &format!("\n{title}:\n");
+        for option in options {
+            *out += &format!("  {:width$}  {}\n", option.usage(), option.help);
+        }
+    };
+    match command {
+        Some(command) => {
+            out += &format!("{}\n\n{}\n", command.about, usage(Some(command)));
+            if !command.choices.is_empty() {
+                out += &format!(
+                    "\n{} is one of {}\n",
+                    command.arguments.split(' ').next().unwrap_or(""),
+                    command.choices.join(", ")
+                );
+            }
+            options(&mut out, "Options", command.options);
+            options(&mut out, "Global options", GLOBAL_OPTIONS);
+        }
+        None => {
+            out += "Parse pairs files and compute great-circle distances.\n\n";
+            out += &format!("{}\n\nCommands:\n", usage(None));
+            let width = COMMANDS
+                .iter()
+                .map(|command| command.name.len())
+                .max()
+                .unwrap_or(0);
+            for command in COMMANDS {
+                out += &format!("  {:width$}  {}\n", command.name, command.about);
+            }
+            out += "\nWithout a command, the arguments are those of compute.\n";
+            options(&mut out, "Global options", GLOBAL_OPTIONS);
+            out += "\nRun `haversine <COMMAND> --help` for the options of a command.\n";
+        }
+    }
+    out
+}
+
+/// The usage line of a subcommand, or of the program without one.
+pub(crate) fn usage(command: Option<&CommandSpec>) -> String {
+    let Some(command) = command else {
+        return "Usage: haversine [COMMAND] [OPTIONS] [ARGS]".to_string();
+    };
+    let mut usage = format!("Usage: haversine {} [OPTIONS]", command.name);
+    if !command.arguments.is_empty() {
+        usage += &format!(" {}", command.arguments);
+    }
+    usage
+}
+
+/// A script completing the subcommands, options and their values.
+pub(crate) fn completions(shell: Shell) -> String {
+    match shell {
+        Shell::Bash => bash_completions(),
+        Shell::Zsh => zsh_completions(),
+        Shell::Fish => fish_completions(),
+    }
+}
+
+fn bash_completions() -> String {
+    let names: Vec<_> = COMMANDS.iter().map(|command| command.name).collect();
+    let globals: Vec<_> = GLOBAL_OPTIONS.iter().flat_map(option_names).collect();
+    let mut values = String::new();
+    let mut options = String::new();
+    for command in COMMANDS {
+        // The options of compute are also those without a command.
+        let names = match command.name {
+            "compute" => vec!["compute", "\"\""],
+            name => vec![name],
+        };
+        for option in command.options.iter().chain(GLOBAL_OPTIONS) {
+            let reply = match option.value {
+                None => continue,
+                Some(_) if !option.choices.is_empty() => {
+                    format!("compgen -W \"{}\" -- \"$cur\"", option.choices.join(" "))
+                }
+                Some(_) if option.file => "compgen -f -- \"$cur\"".to_string(),
+                Some(_) => "true".to_string(),
+            };
+            let patterns: Vec<_> = names
+                .iter()
+                .map(|name| format!("{name}:{}", option.name))
+                .collect();
+            values += &format!(
+                "        {}) COMPREPLY=($({reply})); return ;;\n",
+                patterns.join("|")
+            );
+        }
+        let words: Vec<_> = command.options.iter().flat_map(option_names).collect();
+        options += &format!(
+            "        {}) opts=\"{}\" ;;\n",
+            names.join("|"),
+            words.join(" ")
+        );
+    }
+    let arguments: String = COMMANDS
+        .iter()
+        .filter(|command| !command.choices.is_empty())
+        .map(|command| {
+            format!(
+                "        {}) COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")); return ;;\n",
+                command.name,
+                command.choices.join(" ")
+            )
+        })
+        .collect();
+    format!(
+        r#"_haversine() {{
+    local cur="${{COMP_WORDS[COMP_CWORD]}}" prev="${{COMP_WORDS[COMP_CWORD-1]}}"
+    local command="" word i opts="" arguments=0
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        word="${{COMP_WORDS[i]}}"
+        case "$word" in
+            {names}) [[ -z "$command" ]] && command="$word" ;;
+            -*) ;;
+            *) ((arguments++)) ;;
+        esac
+    done
+    case "$command:$prev" in
+{values}    esac
+    if [[ "$cur" == -* ]]; then
+        case "$command" in
+{options}        esac
+        COMPREPLY=($(compgen -W "$opts {globals}" -- "$cur"))
+        return
+    fi
+    if [[ -z "$command" ]]; then
+        COMPREPLY=($(compgen -W "{commands}" -- "$cur") $(compgen -f -- "$cur"))
+        return
+    fi
+    if ((arguments == 0)); then
+        case "$command" in
+{arguments}        esac
+    fi
+    COMPREPLY=($(compgen -f -- "$cur"))
+}}
+complete -o filenames -F _haversine haversine
+"#,
+        names = names.join("|"),
+        globals = globals.join(" "),
+        commands = names.join(" "),
+    )
+}
+
+fn zsh_completions() -> String {
+    let spec = |option: &OptionSpec| {
+        let help = zsh_escape(option.help);
+        let value = match option.value {
+            None => String::new(),
+            Some(_) if !option.choices.is_empty() => {
+                format!(":value:({})", option.choices.join(" "))
+            }
+            Some(_) if option.file => ":path:_files".to_string(),
+            Some(value) => format!(":{}:", zsh_escape(value)),
+        };
+        let mut specs = vec![format!("'{}[{help}]{value}'", option.name)];
+        if let Some(short) = option.short {
+            specs.push(format!("'-{short}[{help}]{value}'"));
+        }
+        specs
+    };
+    let globals: Vec<_> = GLOBAL_OPTIONS.iter().flat_map(spec).collect();
+    let commands: Vec<_> = COMMANDS
+        .iter()
+        .map(|command| {
+            let about = zsh_escape(command.about).replace('"', "\\\"");
+            format!("{}\\:\"{about}\"", command.name)
+        })
+        .collect();
+    let mut cases = String::new();
+    for command in COMMANDS {
+        let mut specs: Vec<_> = command.options.iter().flat_map(spec).collect();
+        specs.extend(globals.iter().cloned());
+        specs.push(match command.choices {
+            [] => "'*:file:_files'".to_string(),
+            choices => format!("'1:argument:({})' '*:file:_files'", choices.join(" ")),
+        });
+        cases += &format!(
+            "            {}) _arguments {} ;;\n",
+            command.name,
+            specs.join(" \\\n                ")
+        );
+    }
+    let compute = COMMANDS[0]
+        .options
+        .iter()
+        .flat_map(spec)
+        .collect::<Vec<_>>();
+    format!(
+        r#"#compdef haversine
+
+_haversine() {{
+    local context state state_descr line
+    typeset -A opt_args
+    _arguments -C {globals} \
+        '1: :->command' \
+        '*:: :->arguments'
+    case $state in
+        command)
+            _alternative 'commands:command:(({commands}))' 'files:file:_files' ;;
+        arguments)
+            case $line[1] in
+{cases}            *) _arguments {compute} \
+                '*:file:_files' ;;
+            esac ;;
+    esac
+}}
+
+_haversine "$@"
+"#,
+        globals = globals.join(" \\\n        "),
+        commands = commands.join(" "),
+        compute = compute.join(" \\\n                "),
+    )
+}
+
+fn fish_completions() -> String {
+    let names: Vec<_> = COMMANDS.iter().map(|command| command.name).collect();
+    let mut out = String::new();
+    let complete = |out: &mut String, condition: &str, option: &OptionSpec| {
+        *out += &format!("complete -c haversine -n '{condition}'");
+        match option.name.strip_prefix("--") {
+            Some(long) => *out += &format!(" -l {long}"),
+            None => *out += &format!(" -o {}", &option.name[1..]),
+        }
+        if let Some(short) = option.short {
+            *out += &format!(" -s {short}");
+        }
+        match option.value {
+            None => {}
+            Some(_) if !option.choices.is_empty() => {
+                *out += &format!(" -x -a '{}'", option.choices.join(" "));
+            }
+            Some(_) if option.file => *out += " -r -F",
+            Some(_) => *out += " -x",
+        }
+        *out += &format!(" -d '{}'\n", fish_escape(option.help));
+    };
+    for command in COMMANDS {
+        out += &format!(
+            "complete -c haversine -n __fish_use_subcommand -a {} -d '{}'\n",
+            command.name,
+            fish_escape(command.about)
+        );
+    }
+    for option in GLOBAL_OPTIONS {
+        complete(&mut out, "true", option);
+    }
+    for command in COMMANDS {
+        // The options of compute are also those without a command.
+        let condition = match command.name {
+            "compute" => {
+                let others: Vec<_> = names
+                    .iter()
+                    .filter(|name| **name != "compute")
+                    .copied()
+                    .collect();
+                format!("not __fish_seen_subcommand_from {}", others.join(" "))
+            }
+            name => format!("__fish_seen_subcommand_from {name}"),
+        };
+        for option in command.options {
+            complete(&mut out, &condition, option);
+        }
+        if !command.choices.is_empty() {
+            out += &format!(
+                "complete -c haversine -n '__fish_seen_subcommand_from {}' -f -a '{}'\n",
+                command.name,
+                command.choices.join(" ")
+            );
+        }
+    }
+    out
+}
+
+/// The names the option is written by, like `-q` and `--quiet`.
+fn option_names(option: &OptionSpec) -> Vec<String> {
+    let mut names = vec![option.name.to_string()];
+    names.extend(option.short.map(|short| format!("-{short}")));
+    names
+}
+
+/// Escapes text for the description of a zsh `_arguments` spec within
+/// single quotes.
+fn zsh_escape(text: &str) -> String {
+    text.replace('\'', "'\\''")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+        .replace(':', "\\:")
+}
+
+/// Escapes text for a fish argument within single quotes.
+fn fish_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\'', "\\'")
+}
+
+/// Subcommand selected on the command line.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Command {
+    /// Parse a pairs file and compute the average distance.
+    Compute(Box<ComputeArgs>),
+    /// Run a micro-benchmark.
+    Bench(BenchArgs),
+    /// Check a pairs file and its stored average.
+    Validate(ValidateArgs),
+    /// Reformat a JSON document.
+    Fmt(FmtArgs),
+    /// Check the parser on generated and mutated documents.
+    Fuzz(FuzzArgs),
+    /// Compute the distances between every two of a list of points.
+    Matrix(MatrixArgs),
+    /// Compute the area of a polygon of points.
+    Area(AreaArgs),
+    /// Compare the distance formulas on the pairs of a file.
+    CompareFormulas(CompareFormulasArgs),
+    /// Write a file of random pairs.
+    Generate(GenerateArgs),
+    /// Divide a pairs file into shards.
+    Split(SplitArgs),
+    /// Join shards of a pairs file into one.
+    Merge(MergeArgs),
+    /// Write the pairs of a pairs file in the columnar binary layout.
+    ExportColumnar(ExportColumnarArgs),
+    /// Compare two JSON documents.
+    Diff(DiffArgs),
+    /// Print the help of a subcommand, or of the program without one.
+    Help(Option<&'static CommandSpec>),
+    /// Print a completion script.
+    Completions(Shell),
+    /// Compute distances interactively.
+    Repl(ReplArgs),
+    /// Compute the distance of two points given on the command line.
+    Dist(DistArgs),
+    /// Print the size and structure of a JSON document.
+    Inspect(InspectArgs),
+    /// Copy the value at a path of a document to a file.
+    Extract(ExtractArgs),
+    /// Print the tokens of a JSON document.
+    Tokens(TokensArgs),
+    /// Print a JSON document as a tree.
+    Tree(TreeArgs),
+}
+
+/// Arguments of the `compute` subcommand.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ComputeArgs {
+    /// Path to the pairs file, or `-` for stdin.
+    pub(crate) path: String,
+    /// What the input holds and what is computed from it.
+    pub(crate) mode: ComputeMode,
+    /// How the pairs are laid out.
+    pub(crate) format: InputFormat,
+    /// Print the length of every segment of a route.
+    pub(crate) per_segment: bool,
+    /// Parse the input in chunks as it is read, rather than reading it whole
+    /// first.
+    pub(crate) stream: bool,
+    /// Read the pairs from a tape of the input rather than from a `Value`
+    /// per pair. Ignored when streaming.
+    pub(crate) tape: bool,
+    /// Find where the tokens start with a vectorized first pass before
+    /// building the tape. Only used with `tape`.
+    pub(crate) simd: bool,
+    /// Read the input with the parser specialized for the known pairs
+    /// schema, falling back to the generic one for other documents. Ignored
+    /// when streaming.
+    pub(crate) fast_schema: bool,
+    /// Print the progress of reading and computing to stderr.
+    pub(crate) progress: bool,
+    /// Size of each of the two buffers the input is read into ahead of the
+    /// parser.
+    pub(crate) read_buffer: usize,
+    /// How the input file is read.
+    pub(crate) io: IoStrategy,
+    /// How duplicate object keys in the input are handled.
+    pub(crate) duplicate_keys: DuplicateKeyPolicy,
+    /// How escaped lone surrogates in strings are handled.
+    pub(crate) lone_surrogates: LoneSurrogatePolicy,
+    /// How numbers out of the range of `f64` are handled. Not applied with
+    /// `tape` or `fast_schema`, which convert numbers as they are read.
+    pub(crate) out_of_range: NumberRangePolicy,
+    /// Bounds on the input, for untrusted files. With JSON Lines they apply
+    /// to each line, and with `tape` only the size is checked.
+    pub(crate) limits: Limits,
+    /// Accept control characters written unescaped in strings.
+    pub(crate) allow_control_chars: bool,
+    /// Fail on coordinates out of range rather than wrap the longitudes
+    /// and clamp the latitudes.
+    pub(crate) strict_coords: bool,
+    /// Convert input in UTF-16 or UTF-32 to UTF-8 rather than reject it.
+    /// Not supported when streaming.
+    pub(crate) transcode: bool,
+    /// Print the distance and bearings of every pair, or add the bearings
+    /// to the per-pair output.
+    pub(crate) emit_bearings: bool,
+    /// Write the distance of every pair to a file, in the given format.
+    pub(crate) per_pair: Option<(String, PerPairFormat)>,
+    /// Add the input coordinates to the per-pair output.
+    pub(crate) per_pair_coords: bool,
+    /// Add the great-circle midpoint of every pair to the per-pair output.
+    pub(crate) per_pair_midpoints: bool,
+    /// GeoJSON file to write every pair to, as a line with its distance.
+    pub(crate) export_geojson: Option<String>,
+    /// Answers file to check the distance of every pair against, within
+    /// `tolerance`.
+    pub(crate) answers: Option<String>,
+    /// Print statistics of the distances.
+    pub(crate) stats: bool,
+    /// Print the statistics as JSON.
+    pub(crate) stats_json: bool,
+    /// Number of buckets of the distance histogram, if one is printed.
+    pub(crate) histogram: Option<usize>,
+    /// Radius to use instead of the one in the file.
+    pub(crate) radius_model: Option<RadiusModel>,
+    /// Unit of the reported distances. The radius in the file is taken to
+    /// be in kilometers.
+    pub(crate) units: Option<DistanceUnit>,
+    /// Unit of the coordinates of the input, which the kernels convert to
+    /// radians. The encoded routes and the box are in degrees whatever it
+    /// is.
+    pub(crate) angle_unit: AngleUnit,
+    /// Skip the pairs with a point outside the box.
+    pub(crate) bbox: Option<BoundingBox>,
+    /// Also accumulate the sum in double-double arithmetic.
+    pub(crate) wide_accumulator: bool,
+    /// Precision of the distance computation. Below `f64`, the `f64` result
+    /// is also computed, to compare the time and accuracy.
+    pub(crate) precision: Precision,
+    /// Number of threads computing the distances.
+    pub(crate) threads: usize,
+    /// How the distances are summed.
+    pub(crate) reduction: Reduction,
+    /// Pin the threads computing the distances to cores.
+    pub(crate) pin_threads: bool,
+    /// Spread the coordinates over the NUMA nodes before computing.
+    pub(crate) interleave: bool,
+    /// Back the read buffers and the coordinates with huge pages.
+    pub(crate) huge_pages: bool,
+    /// Compute with polynomial approximations of the sine, cosine and arc
+    /// sine rather than those of the standard library.
+    pub(crate) poly_math: bool,
+    /// Degree of the polynomials, within `1..=MAX_DEGREE`.
+    pub(crate) poly_degree: usize,
+    /// How the distance of a pair is computed.
+    pub(crate) kernel: Kernel,
+    pub(crate) checkpoint: CheckpointArgs,
+    /// Stop reading or computing after this long, reporting the pairs
+    /// computed so far.
+    pub(crate) timeout: Option<Duration>,
+    /// Run again, with a fresh report, whenever the input file changes.
+    pub(crate) watch: bool,
+    /// Compute over a random sample of the pairs, drawn as they are read,
+    /// and estimate the average of all of them.
+    pub(crate) sample: Option<SampleSize>,
+    /// Seed of the sample.
+    pub(crate) sample_seed: u64,
+    /// Largest accepted relative error of the stored average.
+    pub(crate) tolerance: f64,
+}
+
+/// What the input of `compute` holds.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub(crate) enum ComputeMode {
+    /// Independent pairs, whose average distance is computed.
+    #[default]
+    Pairs,
+    /// An ordered list of points, whose path length is computed.
+    Route,
+}
+
+/// Options saving the progress of a long run to resume it after an
+/// interruption.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct CheckpointArgs {
+    /// File the progress is saved to.
+    pub(crate) path: Option<String>,
+    /// Time between two saves.
+    pub(crate) interval: Duration,
+    /// Start from the saved progress, if there is any.
+    pub(crate) resume: bool,
+}
+
+/// Arguments of the `validate` subcommand.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ValidateArgs {
+    /// Path to the pairs file, or `-` for stdin.
+    pub(crate) path: String,
+    /// Largest accepted relative error of the stored average.
+    pub(crate) tolerance: f64,
+    /// Recompute every distance in double-double arithmetic and report the
+    /// errors of the computed ones.
+    pub(crate) reference: bool,
+}
+
+/// Arguments of the `fmt` subcommand.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct FmtArgs {
+    /// Path to the JSON document, or `-` for stdin.
+    pub(crate) path: String,
+    /// Spaces per level of indentation, or `None` for compact output.
+    pub(crate) indent: Option<usize>,
+    /// Order the members of every object by key.
+    pub(crate) sort_keys: bool,
+    /// Write numbers exactly as they appear in the input.
+    pub(crate) raw_numbers: bool,
+    /// Write the canonical form, in place of indentation and key order.
+    pub(crate) canonical: bool,
+}
+
+/// Arguments of the `fuzz` subcommand.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct FuzzArgs {
+    pub(crate) seed: u64,
+    pub(crate) iterations: usize,
+}
+
+/// Arguments of the `matrix` subcommand.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct MatrixArgs {
+    /// Path to the points file, or `-` for stdin.
+    pub(crate) path: String,
+    /// File to write the matrix to, in the given format, instead of CSV on
+    /// stdout.
+    pub(crate) output: Option<(String, MatrixFormat)>,
+    pub(crate) shape: MatrixShape,
+    /// How the distance of two points is computed.
+    pub(crate) kernel: Kernel,
+    /// Save the rows written to `output`, which it requires.
+    pub(crate) checkpoint: CheckpointArgs,
+    /// Number of threads, or `None` for one per core.
+    pub(crate) threads: Option<usize>,
+    /// Radius to use instead of the one in the file.
+    pub(crate) radius_model: Option<RadiusModel>,
+    /// Unit of the distances. The radius in the file is taken to be in
+    /// kilometers.
+    pub(crate) units: Option<DistanceUnit>,
+}
+
+/// Arguments of the `area` subcommand.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct AreaArgs {
+    /// Path to the points file, or `-` for stdin.
+    pub(crate) path: String,
+    /// Radius to use instead of the one in the file.
+    pub(crate) radius_model: Option<RadiusModel>,
+    /// Unit of the area, squared. The radius in the file is taken to be in
+    /// kilometers.
+    pub(crate) units: Option<DistanceUnit>,
+}
+
+/// Arguments of the `compare-formulas` subcommand.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct CompareFormulasArgs {
+    /// Path to the pairs file, or `-` for stdin.
+    pub(crate) path: String,
+    /// Radius to use instead of the one in the file.
+    pub(crate) radius_model: Option<RadiusModel>,
+    /// Unit of the deviations. The radius in the file is taken to be in
+    /// kilometers.
+    pub(crate) units: Option<DistanceUnit>,
+}
+
+/// Arguments of the `generate` subcommand.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct GenerateArgs {
+    pub(crate) pairs: usize,
+    pub(crate) radius: f64,
+    pub(crate) distribution: Distribution,
+    /// Edge case the pairs are drawn at rather than from `distribution`,
+    /// with the average and answers computed in double-double arithmetic.
+    pub(crate) preset: Option<Preset>,
+    pub(crate) seed: u64,
+    pub(crate) rng: Algorithm,
+    /// Path of the pairs file, or `-` for stdout.
+    pub(crate) output: String,
+    /// Decimal places the coordinates are rounded to.
+    pub(crate) digits: Option<usize>,
+    /// Write the JSON indented rather than without whitespace.
+    pub(crate) pretty: bool,
+    /// Path of the answers file, the distance of every pair and their
+    /// average as binary `f64`s. None is written by default when the pairs
+    /// go to stdout.
+    pub(crate) answers: Option<String>,
+}
+
+/// Arguments of the `split` subcommand.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SplitArgs {
+    /// Path to the pairs file, or `-` for stdin.
+    pub(crate) path: String,
+    pub(crate) shards: usize,
+    /// Start of the paths of the shards, which end in their index and
+    /// `.json`. By default the path of the input without `.json`.
+    pub(crate) prefix: String,
+}
+
+/// Arguments of the `merge` subcommand.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct MergeArgs {
+    /// Paths to the shards, in order.
+    pub(crate) paths: Vec<String>,
+    /// Path of the merged pairs file, or `-` for stdout.
+    pub(crate) output: String,
+}
+
+/// Arguments of the `export-columnar` subcommand.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ExportColumnarArgs {
+    /// Path to the pairs file, or `-` for stdin.
+    pub(crate) path: String,
+    /// Path of the columnar file, or `-` for stdout. By default the path
+    /// of the input with `.col` for `.json`.
+    pub(crate) output: String,
+}
+
+/// Arguments of the `diff` subcommand.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DiffArgs {
+    /// Paths to the documents, either of which can be `-` for stdin.
+    pub(crate) paths: [String; 2],
+    /// Largest relative difference of two numbers, one of them a float,
+    /// that is not reported.
+    pub(crate) tolerance: f64,
+    /// Number of differences printed before they are only counted.
+    pub(crate) limit: usize,
+}
+
+/// Arguments of the `dist` subcommand.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DistArgs {
+    /// Latitude and longitude of the first point, then of the second, in
+    /// degrees.
+    pub(crate) coordinates: [f64; 4],
+    /// Radius of the sphere in kilometers, the mean radius of the Earth by
+    /// default.
+    pub(crate) radius: f64,
+    pub(crate) formula: Formula,
+    /// Unit of the distance.
+    pub(crate) units: DistanceUnit,
+}
+
+/// Formula of the great-circle distance of `dist`.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub(crate) enum Formula {
+    #[default]
+    Haversine,
+    /// The spherical law of cosines.
+    Cosine,
+    Vincenty,
+}
+
+/// Arguments of the `repl` subcommand.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct ReplArgs {
+    /// Radius of the sphere, the mean radius of the Earth by default.
+    pub(crate) radius_model: Option<RadiusModel>,
+    /// Unit of the distances.
+    pub(crate) units: Option<DistanceUnit>,
+}
+
+/// Arguments of the `inspect` subcommand.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct InspectArgs {
+    /// Path to the JSON document, or `-` for stdin.
+    pub(crate) path: String,
+}
+
+/// Arguments of the `extract` subcommand.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ExtractArgs {
+    /// Path to the JSON document, or `-` for stdin.
+    pub(crate) path: String,
+    /// JSON pointer to the value, whose last segment may be a range such as
+    /// `1000..2000`.
+    pub(crate) pointer: String,
+    /// Path of the extract, or `-` for stdout.
+    pub(crate) output: String,
+}
+
+/// Arguments of the `tokens` subcommand.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TokensArgs {
+    /// Path to the JSON document, or `-` for stdin.
+    pub(crate) path: String,
+    /// How each token is printed.
+    pub(crate) format: DumpFormat,
+}
+
+/// Arguments of the `tree` subcommand.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TreeArgs {
+    /// Path to the JSON document, or `-` for stdin.
+    pub(crate) path: String,
+    /// Deepest level shown, the whole document if not given.
+    pub(crate) depth: Option<usize>,
+    /// Items shown of each array and members of each object.
+    pub(crate) items: usize,
+}
+
+/// Arguments of the `bench` subcommand.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct BenchArgs {
+    pub(crate) mode: BenchMode,
+    /// Seconds without a new minimum before a repetition test stops.
+    pub(crate) seconds: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum BenchMode {
+    /// Memory bandwidth over working sets of increasing size.
+    Bandwidth {
+        access: Vec<Access>,
+        sizes: Vec<usize>,
+        stride: usize,
+        total_bytes: usize,
+    },
+    /// Walking the `Value` DOM versus scanning the SoA arrays of a pairs file.
+    Traversal { path: String },
+    /// Finding the token starts of a file with each available instruction
+    /// set, and building its tape with and without them.
+    Structural { path: String },
+    /// Parsing a file with each parser of the crate, and with those of other
+    /// crates enabled as features.
+    Parse { path: String },
+    /// Reading a file with each strategy of `compute --io`.
+    Io { path: String, read_buffer: usize },
+    /// Computing the distances of a pairs file with each kernel.
+    Kernels { path: String },
+    /// Reading, parsing and computing a file a number of times, the first
+    /// (cold) run reported apart from the others (warm).
+    Cache {
+        path: String,
+        runs: usize,
+        drop_cache: bool,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ArgsError {
+    MissingValue(String),
+    MissingArgument(&'static str),
+    InvalidValue(String, String),
+    UnknownOption(String),
+    UnexpectedArgument(String),
+    /// Two options that cannot be used together.
+    Conflict(&'static str, &'static str),
+}
+
+impl fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArgsError::MissingValue(option) => write!(f, "{option} needs a value"),
+            ArgsError::MissingArgument(name) => write!(f, "missing the {name} argument"),
+            ArgsError::InvalidValue(option, value) => {
+                write!(f, "invalid value {value:?} for {option}")
+            }
+            ArgsError::UnknownOption(option) => write!(f, "unknown option {option}"),
+            ArgsError::UnexpectedArgument(arg) => write!(f, "unexpected argument {arg:?}"),
+            ArgsError::Conflict(a, b) => write!(f, "{a} cannot be used with {b}"),
+        }
+    }
+}
+
+impl Default for ComputeArgs {
+    fn default() -> Self {
+        Self {
+            path: DEFAULT_PATH.into(),
+            mode: ComputeMode::default(),
+            format: InputFormat::default(),
+            per_segment: false,
+            stream: false,
+            tape: false,
+            simd: false,
+            fast_schema: false,
+            progress: false,
+            read_buffer: DEFAULT_BUFFER_SIZE,
+            io: IoStrategy::default(),
+            duplicate_keys: DuplicateKeyPolicy::default(),
+            lone_surrogates: LoneSurrogatePolicy::default(),
+            out_of_range: NumberRangePolicy::default(),
+            limits: Limits::default(),
+            allow_control_chars: false,
+            strict_coords: false,
+            transcode: false,
+            emit_bearings: false,
+            wide_accumulator: false,
+            precision: Precision::default(),
+            threads: 1,
+            reduction: Reduction::default(),
+            pin_threads: false,
+            interleave: false,
+            huge_pages: false,
+            poly_math: false,
+            poly_degree: DEFAULT_POLY_DEGREE,
+            kernel: Kernel::default(),
+            checkpoint: CheckpointArgs::new(),
+            timeout: None,
+            watch: false,
+            sample: None,
+            sample_seed: 0,
+            per_pair: None,
+            per_pair_coords: false,
+            per_pair_midpoints: false,
+            export_geojson: None,
+            answers: None,
+            stats: false,
+            stats_json: false,
+            histogram: None,
+            radius_model: None,
+            bbox: None,
+            units: None,
+            angle_unit: AngleUnit::default(),
+            tolerance: DEFAULT_TOLERANCE,
+        }
+    }
+}
+
+/// Options of the diagnostics, accepted before or after any subcommand.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub(crate) struct LogArgs {
+    /// Most verbose level written: `Info` by default, lowered by `-q` and
+    /// raised by each `v` of `-v` and `-vv`.
+    pub(crate) level: Level,
+    pub(crate) format: LogFormat,
+}
+
+impl LogArgs {
+    /// Takes the logging options out of the arguments, returning them and
+    /// the arguments left for `Command::parse`.
+    pub(crate) fn extract(
+        args: impl IntoIterator<Item = String>,
+    ) -> Result<(Self, Vec<String>), ArgsError> {
+        let mut parsed = LogArgs::default();
+        let (mut verbose, mut quiet) = (0, false);
+        let mut rest = Vec::new();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-q" | "--quiet" => quiet = true,
+                "--verbose" => verbose += 1,
+                flags
+                    if flags
+                        .strip_prefix('-')
+                        .is_some_and(|vs| !vs.is_empty() && vs.bytes().all(|b| b == b'v')) =>
+                {
+                    verbose += flags.len() - 1;
+                }
+                "--log-format" => {
+                    let value = next_value(&mut args, &arg)?;
+                    parsed.format = match value.as_str() {
+                        "plain" => LogFormat::Plain,
+                        "json" => LogFormat::Json,
+                        _ => return Err(ArgsError::InvalidValue(arg, value)),
+                    };
+                }
+                _ => rest.push(arg),
+            }
+        }
+        parsed.level = match (quiet, verbose) {
+            (true, 0) => Level::Error,
+            (true, _) => return Err(ArgsError::Conflict("-q", "-v")),
+            (false, 0) => Level::Info,
+            (false, 1) => Level::Debug,
+            (false, _) => Level::Trace,
+        };
+        Ok((parsed, rest))
+    }
+
+    /// The arguments `extract` reads back as these options.
+    pub(crate) fn to_args(self) -> Vec<String> {
+        let mut args = Vec::new();
+        match self.level {
+            Level::Error | Level::Warn => args.push("-q".to_string()),
+            Level::Info => {}
+            Level::Debug => args.push("-v".to_string()),
+            Level::Trace => args.push("-vv".to_string()),
+        }
+        if self.format == LogFormat::Json {
+            args.extend(["--log-format".to_string(), "json".to_string()]);
+        }
+        args
+    }
+}
+
+/// Where the defaults of the options are read from, accepted before or
+/// after any subcommand.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct ConfigArgs {
+    /// Config file given with `--config`, read instead of the default one.
+    pub(crate) path: Option<String>,
+    /// Read no config file, with `--no-config`.
+    pub(crate) disabled: bool,
+}
+
+impl ConfigArgs {
+    /// Takes the config options out of the arguments, returning them and
+    /// the arguments left.
+    pub(crate) fn extract(
+        args: impl IntoIterator<Item = String>,
+    ) -> Result<(Self, Vec<String>), ArgsError> {
+        let mut parsed = ConfigArgs::default();
+        let mut rest = Vec::new();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--config" => parsed.path = Some(next_value(&mut args, &arg)?),
+                "--no-config" => parsed.disabled = true,
+                _ => rest.push(arg),
+            }
+        }
+        if parsed.disabled && parsed.path.is_some() {
+            return Err(ArgsError::Conflict("--config", "--no-config"));
+        }
+        Ok((parsed, rest))
+    }
+}
+
+impl Command {
+    /// Parses the arguments, excluding the program name. Without a known
+    /// subcommand the arguments are those of `compute`.
+    pub(crate) fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, ArgsError> {
+        let args: Vec<String> = args.into_iter().collect();
+        if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+            let command = args.first().and_then(|name| find_command(name));
+            return Ok(Command::Help(command));
+        }
+        let mut args = args.into_iter().peekable();
+        match args.peek().map(String::as_str) {
+            Some("compute") => {
+                args.next();
+                ComputeArgs::parse(args).map(|args| Command::Compute(Box::new(args)))
+            }
+            Some("bench") => {
+                args.next();
+                BenchArgs::parse(args).map(Command::Bench)
+            }
+            Some("validate") => {
+                args.next();
+                ValidateArgs::parse(args).map(Command::Validate)
+            }
+            Some("fmt") => {
+                args.next();
+                FmtArgs::parse(args).map(Command::Fmt)
+            }
+            Some("fuzz") => {
+                args.next();
+                FuzzArgs::parse(args).map(Command::Fuzz)
+            }
+            Some("matrix") => {
+                args.next();
+                MatrixArgs::parse(args).map(Command::Matrix)
+            }
+            Some("area") => {
+                args.next();
+                AreaArgs::parse(args).map(Command::Area)
+            }
+            Some("compare-formulas") => {
+                args.next();
+                CompareFormulasArgs::parse(args).map(Command::CompareFormulas)
+            }
+            Some("generate") => {
+                args.next();
+                GenerateArgs::parse(args).map(Command::Generate)
+            }
+            Some("split") => {
+                args.next();
+                SplitArgs::parse(args).map(Command::Split)
+            }
+            Some("merge") => {
+                args.next();
+                MergeArgs::parse(args).map(Command::Merge)
+            }
+            Some("diff") => {
+                args.next();
+                DiffArgs::parse(args).map(Command::Diff)
+            }
+            Some("export-columnar") => {
+                args.next();
+                ExportColumnarArgs::parse(args).map(Command::ExportColumnar)
+            }
+            Some("dist") => {
+                args.next();
+                DistArgs::parse(args).map(Command::Dist)
+            }
+            Some("repl") => {
+                args.next();
+                ReplArgs::parse(args).map(Command::Repl)
+            }
+            Some("inspect") => {
+                args.next();
+                InspectArgs::parse(args).map(Command::Inspect)
+            }
+            Some("extract") => {
+                args.next();
+                ExtractArgs::parse(args).map(Command::Extract)
+            }
+            Some("tokens") => {
+                args.next();
+                TokensArgs::parse(args).map(Command::Tokens)
+            }
+            Some("tree") => {
+                args.next();
+                TreeArgs::parse(args).map(Command::Tree)
+            }
+            Some("completions") => {
+                args.next();
+                let name = args.next().ok_or(ArgsError::MissingArgument("shell"))?;
+                let shell = Shell::from_name(&name)
+                    .ok_or_else(|| ArgsError::InvalidValue("shell".into(), name))?;
+                match args.next() {
+                    Some(arg) => Err(ArgsError::UnexpectedArgument(arg)),
+                    None => Ok(Command::Completions(shell)),
+                }
+            }
+            _ => ComputeArgs::parse(args).map(|args| Command::Compute(Box::new(args))),
+        }
+    }
+}
+
+impl ComputeArgs {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut parsed = ComputeArgs::default();
+        let mut path = None;
+        let mut body = None;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--duplicate-keys" => {
+                    let value = next_value(&mut args, &arg)?;
+                    parsed.duplicate_keys = match value.as_str() {
+                        "error" => DuplicateKeyPolicy::Error,
+                        "first-wins" => DuplicateKeyPolicy::FirstWins,
+                        "last-wins" => DuplicateKeyPolicy::LastWins,
+                        _ => return Err(ArgsError::InvalidValue(arg, value)),
+                    };
+                }
+                "--mode" => {
+                    let value = next_value(&mut args, &arg)?;
+                    parsed.mode = match value.as_str() {
+                        "pairs" => ComputeMode::Pairs,
+                        "route" => ComputeMode::Route,
+                        _ => return Err(ArgsError::InvalidValue(arg, value)),
+                    };
+                }
+                "--format" => {
+                    let value = next_value(&mut args, &arg)?;
+                    parsed.format = match value.as_str() {
+                        "json" => InputFormat::Json,
+                        "ndjson" | "jsonl" => InputFormat::Ndjson,
+                        "columnar" => InputFormat::Columnar,
+                        _ => return Err(ArgsError::InvalidValue(arg, value)),
+                    };
+                }
+                "--per-segment" => parsed.per_segment = true,
+                "--stream" => parsed.stream = true,
+                "--tape" => parsed.tape = true,
+                "--simd" => parsed.simd = true,
+                "--fast-schema" => parsed.fast_schema = true,
+                "--progress" => parsed.progress = true,
+                "--watch" => parsed.watch = true,
+                "--sample" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let size = SampleSize::from_spec(&value)
+                        .ok_or_else(|| ArgsError::InvalidValue(arg, value.clone()))?;
+                    parsed.sample = Some(size);
+                }
+                "--sample-seed" => {
+                    let value = next_value(&mut args, &arg)?;
+                    parsed.sample_seed = value
+                        .parse()
+                        .map_err(|_| ArgsError::InvalidValue(arg, value))?;
+                }
+                "--read-buffer" => {
+                    let value = next_value(&mut args, &arg)?;
+                    parsed.read_buffer = parse_size(&value)
+                        .filter(|&size| size > 0)
+                        .ok_or(ArgsError::InvalidValue(arg, value))?;
+                }
+                "--io" => {
+                    let value = next_value(&mut args, &arg)?;
+                    parsed.io = IoStrategy::from_name(&value)
+                        .ok_or_else(|| ArgsError::InvalidValue(arg, value.clone()))?;
+                }
+                "--lossy-surrogates" => parsed.lone_surrogates = LoneSurrogatePolicy::Replace,
+                "--max-size" => {
+                    let value = next_value(&mut args, &arg)?;
+                    parsed.limits.max_size =
+                        parse_size(&value).ok_or(ArgsError::InvalidValue(arg, value))?;
+                }
+                "--max-string-len" | "--max-array-len" | "--max-values" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let limit = match arg.as_str() {
+                        "--max-string-len" => &mut parsed.limits.max_string_len,
+                        "--max-array-len" => &mut parsed.limits.max_array_len,
+                        _ => &mut parsed.limits.max_values,
+                    };
+                    *limit = value
+                        .parse()
+                        .map_err(|_| ArgsError::InvalidValue(arg, value))?;
+                }
+                "--out-of-range" => {
+                    let value = next_value(&mut args, &arg)?;
+                    parsed.out_of_range = match value.as_str() {
+                        "error" => NumberRangePolicy::Error,
+                        "clamp" => NumberRangePolicy::Clamp,
+                        "infinite" => NumberRangePolicy::KeepInfinite,
+                        _ => return Err(ArgsError::InvalidValue(arg, value)),
+                    };
+                }
+                "--allow-control-chars" => parsed.allow_control_chars = true,
+                "--strict-coords" => parsed.strict_coords = true,
+                "--transcode" => parsed.transcode = true,
+                "--emit-bearings" => parsed.emit_bearings = true,
+                "--wide-accumulator" => parsed.wide_accumulator = true,
+                "--math" => {
+                    let value = next_value(&mut args, &arg)?;
+                    parsed.poly_math = match value.as_str() {
+                        "std" => false,
+                        "poly" => true,
+                        _ => return Err(ArgsError::InvalidValue(arg, value)),
+                    };
+                }
+                "--poly-degree" => {
+                    let value = next_value(&mut args, &arg)?;
+                    parsed.poly_degree = value
+                        .parse()
+                        .ok()
+                        .filter(|degree| (1..=MAX_DEGREE).contains(degree))
+                        .ok_or(ArgsError::InvalidValue(arg, value))?;
+                }
+                "--kernel" => {
+                    let value = next_value(&mut args, &arg)?;
+                    parsed.kernel = Kernel::from_name(&value)
+                        .ok_or_else(|| ArgsError::InvalidValue(arg, value.clone()))?;
+                }
+                "--checkpoint" | "--checkpoint-interval" | "--resume" => {
+                    parsed.checkpoint.parse_arg(arg, &mut args)?;
+                }
+                "--timeout" => {
+                    let value = next_value(&mut args, &arg)?;
+                    parsed.timeout =
+                        Some(parse_duration(&value).ok_or(ArgsError::InvalidValue(arg, value))?);
+                }
+                "--precision" => {
+                    let value = next_value(&mut args, &arg)?;
+                    parsed.precision = Precision::from_name(&value)
+                        .ok_or_else(|| ArgsError::InvalidValue(arg, value.clone()))?;
+                }
+                "--threads" => {
+                    let value = next_value(&mut args, &arg)?;
+                    parsed.threads = value
+                        .parse()
+                        .ok()
+                        .filter(|&threads| threads > 0)
+                        .ok_or(ArgsError::InvalidValue(arg, value))?;
+                }
+                "--pin-threads" => parsed.pin_threads = true,
+                "--interleave" => parsed.interleave = true,
+                "--huge-pages" => parsed.huge_pages = true,
+                "--reduction" => {
+                    let value = next_value(&mut args, &arg)?;
+                    parsed.reduction = Reduction::from_name(&value)
+                        .ok_or_else(|| ArgsError::InvalidValue(arg, value.clone()))?;
+                }
+                "--per-pair" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let format = PerPairFormat::from_path(&value)
+                        .ok_or_else(|| ArgsError::InvalidValue(arg, value.clone()))?;
+                    parsed.per_pair = Some((value, format));
+                }
+                "--per-pair-coords" => parsed.per_pair_coords = true,
+                "--per-pair-midpoints" => parsed.per_pair_midpoints = true,
+                "--export-geojson" => {
+                    parsed.export_geojson = Some(next_value(&mut args, &arg)?);
+                }
+                "--answers" => parsed.answers = Some(next_value(&mut args, &arg)?),
+                "--stats" => parsed.stats = true,
+                "--stats-json" => parsed.stats_json = true,
+                "--radius-model" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let model = RadiusModel::from_name(&value)
+                        .ok_or_else(|| ArgsError::InvalidValue(arg, value.clone()))?;
+                    parsed.radius_model = Some(model);
+                }
+                "--body" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let model = Body::from_name(&value)
+                        .ok_or_else(|| ArgsError::InvalidValue(arg, value.clone()))?;
+                    body = Some(model);
+                }
+                "--units" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let units = DistanceUnit::from_name(&value)
+                        .ok_or_else(|| ArgsError::InvalidValue(arg, value.clone()))?;
+                    parsed.units = Some(units);
+                }
+                "--angle-unit" => {
+                    let value = next_value(&mut args, &arg)?;
+                    parsed.angle_unit = AngleUnit::from_name(&value)
+                        .ok_or_else(|| ArgsError::InvalidValue(arg, value.clone()))?;
+                }
+                "--bbox" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let bbox = BoundingBox::from_spec(&value)
+                        .ok_or_else(|| ArgsError::InvalidValue(arg, value.clone()))?;
+                    parsed.bbox = Some(bbox);
+                }
+                "--tolerance" => {
+                    let value = next_value(&mut args, &arg)?;
+                    parsed.tolerance = value
+                        .parse()
+                        .map_err(|_| ArgsError::InvalidValue(arg, value))?;
+                }
+                "--histogram" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let buckets = value
+                        .parse()
+                        .ok()
+                        .filter(|&buckets| buckets > 0)
+                        .ok_or(ArgsError::InvalidValue(arg, value))?;
+                    parsed.histogram = Some(buckets);
+                }
+                opt if opt.starts_with("--") => return Err(ArgsError::UnknownOption(arg)),
+                _ if path.is_none() => path = Some(arg),
+                _ => return Err(ArgsError::UnexpectedArgument(arg)),
+            }
+        }
+
+        if let Some(path) = path {
+            parsed.path = path;
+        }
+        parsed.radius_model = body_radius_model(parsed.radius_model, body)?;
+        parsed.checkpoint.check()?;
+        // A resumed run has not computed the distances before the
+        // checkpoint, and the f32 runs are not checkpointed.
+        if parsed.checkpoint.resume {
+            let distances = [
+                ("--per-pair", parsed.per_pair.is_some()),
+                ("--export-geojson", parsed.export_geojson.is_some()),
+                ("--answers", parsed.answers.is_some()),
+                ("--stats", parsed.stats),
+                ("--stats-json", parsed.stats_json),
+                ("--histogram", parsed.histogram.is_some()),
+            ];
+            if let Some((option, _)) = distances.iter().find(|(_, used)| *used) {
+                return Err(ArgsError::Conflict("--resume", option));
+            }
+        }
+        if parsed.checkpoint.path.is_some() && parsed.precision == Precision::F32 {
+            return Err(ArgsError::Conflict("--checkpoint", "--precision f32"));
+        }
+        // Checkpoints record a running sum, taken on one thread.
+        if parsed.checkpoint.path.is_some() {
+            if parsed.threads > 1 {
+                return Err(ArgsError::Conflict("--checkpoint", "--threads"));
+            }
+            if parsed.reduction == Reduction::Tree {
+                return Err(ArgsError::Conflict("--checkpoint", "--reduction tree"));
+            }
+            if parsed.pin_threads {
+                return Err(ArgsError::Conflict("--checkpoint", "--pin-threads"));
+            }
+        }
+        // Only files can be mapped or read at offsets.
+        if parsed.path == "-" && parsed.io != IoStrategy::Sync {
+            return Err(ArgsError::Conflict("--io", "-"));
+        }
+        // JSON Lines are always streamed, a line at a time, and the columnar
+        // layout is not JSON at all.
+        if parsed.format != InputFormat::Json {
+            let format = match parsed.format {
+                InputFormat::Ndjson => "--format ndjson",
+                _ => "--format columnar",
+            };
+            let options = [
+                ("--mode route", parsed.mode == ComputeMode::Route),
+                ("--tape", parsed.tape),
+                ("--fast-schema", parsed.fast_schema),
+                ("--transcode", parsed.transcode),
+            ];
+            if let Some((option, _)) = options.iter().find(|(_, used)| *used) {
+                return Err(ArgsError::Conflict(format, option));
+            }
+        }
+        if parsed.transcode && parsed.stream {
+            return Err(ArgsError::Conflict("--transcode", "--stream"));
+        }
+        // The answers are those of every pair of the file, in order.
+        if parsed.answers.is_some() {
+            if parsed.sample.is_some() {
+                return Err(ArgsError::Conflict("--answers", "--sample"));
+            }
+            if parsed.bbox.is_some() {
+                return Err(ArgsError::Conflict("--answers", "--bbox"));
+            }
+        }
+        // Stdin cannot be read again, and every run of a changed input
+        // starts over.
+        if parsed.watch {
+            if parsed.path == "-" {
+                return Err(ArgsError::Conflict("--watch", "-"));
+            }
+            if parsed.checkpoint.path.is_some() {
+                return Err(ArgsError::Conflict("--watch", "--checkpoint"));
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+impl CheckpointArgs {
+    fn new() -> Self {
+        Self {
+            path: None,
+            interval: Duration::from_secs_f64(DEFAULT_CHECKPOINT_INTERVAL),
+            resume: false,
+        }
+    }
+
+    /// Parses `--checkpoint`, `--checkpoint-interval` or `--resume`.
+    fn parse_arg(
+        &mut self,
+        arg: String,
+        args: &mut impl Iterator<Item = String>,
+    ) -> Result<(), ArgsError> {
+        match arg.as_str() {
+            "--checkpoint" => self.path = Some(next_value(args, &arg)?),
+            "--checkpoint-interval" => {
+                let value = next_value(args, &arg)?;
+                self.interval = value
+                    .parse()
+                    .ok()
+                    .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+                    .ok_or(ArgsError::InvalidValue(arg, value))?;
+            }
+            "--resume" => self.resume = true,
+            _ => return Err(ArgsError::UnknownOption(arg)),
+        }
+        Ok(())
+    }
+
+    /// Checks that `--resume` comes with a checkpoint file.
+    fn check(&self) -> Result<(), ArgsError> {
+        if self.resume && self.path.is_none() {
+            return Err(ArgsError::MissingArgument("checkpoint"));
+        }
+        Ok(())
+    }
+}
+
+impl ValidateArgs {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut path = None;
+        let mut tolerance = DEFAULT_TOLERANCE;
+        let mut reference = false;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--tolerance" => {
+                    let value = next_value(&mut args, &arg)?;
+                    tolerance = value
+                        .parse()
+                        .map_err(|_| ArgsError::InvalidValue(arg, value))?;
+                }
+                "--reference" => reference = true,
+                opt if opt.starts_with("--") => return Err(ArgsError::UnknownOption(arg)),
+                _ if path.is_none() => path = Some(arg),
+                _ => return Err(ArgsError::UnexpectedArgument(arg)),
+            }
+        }
+
+        Ok(ValidateArgs {
+            path: path.ok_or(ArgsError::MissingArgument("path"))?,
+            tolerance,
+            reference,
+        })
+    }
+}
+
+impl FmtArgs {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut path = None;
+        let mut indent = Some(2);
+        let mut sort_keys = false;
+        let mut raw_numbers = false;
+        let mut canonical = false;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--indent" => {
+                    let value = next_value(&mut args, &arg)?;
+                    indent = Some(
+                        value
+                            .parse()
+                            .map_err(|_| ArgsError::InvalidValue(arg, value))?,
+                    );
+                }
+                "--compact" => indent = None,
+                "--sort-keys" => sort_keys = true,
+                "--raw-numbers" => raw_numbers = true,
+                "--canonical" => canonical = true,
+                "-" if path.is_none() => path = Some(arg),
+                opt if opt.starts_with('-') => return Err(ArgsError::UnknownOption(arg)),
+                _ if path.is_none() => path = Some(arg),
+                _ => return Err(ArgsError::UnexpectedArgument(arg)),
+            }
+        }
+
+        if canonical && raw_numbers {
+            return Err(ArgsError::Conflict("--canonical", "--raw-numbers"));
+        }
+
+        Ok(FmtArgs {
+            path: path.ok_or(ArgsError::MissingArgument("path"))?,
+            indent,
+            sort_keys,
+            raw_numbers,
+            canonical,
+        })
+    }
+}
+
+impl FuzzArgs {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut parsed = FuzzArgs {
+            seed: 0,
+            iterations: 100_000,
+        };
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--seed" => {
+                    let value = next_value(&mut args, &arg)?;
+                    parsed.seed = value
+                        .parse()
+                        .map_err(|_| ArgsError::InvalidValue(arg, value))?;
+                }
+                "--iterations" => {
+                    let value = next_value(&mut args, &arg)?;
+                    parsed.iterations = value
+                        .parse()
+                        .map_err(|_| ArgsError::InvalidValue(arg, value))?;
+                }
+                opt if opt.starts_with("--") => return Err(ArgsError::UnknownOption(arg)),
+                _ => return Err(ArgsError::UnexpectedArgument(arg)),
+            }
+        }
+
+        Ok(parsed)
+    }
+}
+
+impl MatrixArgs {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut parsed = MatrixArgs {
+            path: String::new(),
+            output: None,
+            shape: MatrixShape::Full,
+            kernel: Kernel::default(),
+            checkpoint: CheckpointArgs::new(),
+            threads: None,
+            radius_model: None,
+            units: None,
+        };
+        let mut path = None;
+        let mut body = None;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--output" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let format = MatrixFormat::from_path(&value)
+                        .ok_or_else(|| ArgsError::InvalidValue(arg, value.clone()))?;
+                    parsed.output = Some((value, format));
+                }
+                "--upper" => parsed.shape = MatrixShape::Upper,
+                "--kernel" => {
+                    let value = next_value(&mut args, &arg)?;
+                    parsed.kernel = Kernel::from_name(&value)
+                        .ok_or_else(|| ArgsError::InvalidValue(arg, value.clone()))?;
+                }
+                "--checkpoint" | "--checkpoint-interval" | "--resume" => {
+                    parsed.checkpoint.parse_arg(arg, &mut args)?;
+                }
+                "--threads" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let threads = value
+                        .parse()
+                        .ok()
+                        .filter(|&threads| threads > 0)
+                        .ok_or(ArgsError::InvalidValue(arg, value))?;
+                    parsed.threads = Some(threads);
+                }
+                "--radius-model" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let model = RadiusModel::from_name(&value)
+                        .ok_or_else(|| ArgsError::InvalidValue(arg, value.clone()))?;
+                    parsed.radius_model = Some(model);
+                }
+                "--body" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let model = Body::from_name(&value)
+                        .ok_or_else(|| ArgsError::InvalidValue(arg, value.clone()))?;
+                    body = Some(model);
+                }
+                "--units" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let units = DistanceUnit::from_name(&value)
+                        .ok_or_else(|| ArgsError::InvalidValue(arg, value.clone()))?;
+                    parsed.units = Some(units);
+                }
+                "-" if path.is_none() => path = Some(arg),
+                opt if opt.starts_with('-') => return Err(ArgsError::UnknownOption(arg)),
+                _ if path.is_none() => path = Some(arg),
+                _ => return Err(ArgsError::UnexpectedArgument(arg)),
+            }
+        }
+
+        parsed.path = path.ok_or(ArgsError::MissingArgument("path"))?;
+        parsed.radius_model = body_radius_model(parsed.radius_model, body)?;
+        parsed.checkpoint.check()?;
+        if parsed.checkpoint.path.is_some() && parsed.output.is_none() {
+            return Err(ArgsError::MissingArgument("output"));
+        }
+        Ok(parsed)
+    }
+}
+
+impl AreaArgs {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut radius_model = None;
+        let mut body = None;
+        let mut units = None;
+        let mut path = None;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--radius-model" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let model = RadiusModel::from_name(&value)
+                        .ok_or_else(|| ArgsError::InvalidValue(arg, value.clone()))?;
+                    radius_model = Some(model);
+                }
+                "--body" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let parsed = Body::from_name(&value)
+                        .ok_or_else(|| ArgsError::InvalidValue(arg, value.clone()))?;
+                    body = Some(parsed);
+                }
+                "--units" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let parsed = DistanceUnit::from_name(&value)
+                        .ok_or_else(|| ArgsError::InvalidValue(arg, value.clone()))?;
+                    units = Some(parsed);
+                }
+                "-" if path.is_none() => path = Some(arg),
+                opt if opt.starts_with('-') => return Err(ArgsError::UnknownOption(arg)),
+                _ if path.is_none() => path = Some(arg),
+                _ => return Err(ArgsError::UnexpectedArgument(arg)),
+            }
+        }
+
+        Ok(AreaArgs {
+            path: path.ok_or(ArgsError::MissingArgument("path"))?,
+            radius_model: body_radius_model(radius_model, body)?,
+            units,
+        })
+    }
+}
+
+impl CompareFormulasArgs {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut radius_model = None;
+        let mut body = None;
+        let mut units = None;
+        let mut path = None;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--radius-model" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let model = RadiusModel::from_name(&value)
+                        .ok_or_else(|| ArgsError::InvalidValue(arg, value.clone()))?;
+                    radius_model = Some(model);
+                }
+                "--body" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let parsed = Body::from_name(&value)
+                        .ok_or_else(|| ArgsError::InvalidValue(arg, value.clone()))?;
+                    body = Some(parsed);
+                }
+                "--units" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let parsed = DistanceUnit::from_name(&value)
+                        .ok_or_else(|| ArgsError::InvalidValue(arg, value.clone()))?;
+                    units = Some(parsed);
+                }
+                "-" if path.is_none() => path = Some(arg),
+                opt if opt.starts_with('-') => return Err(ArgsError::UnknownOption(arg)),
+                _ if path.is_none() => path = Some(arg),
+                _ => return Err(ArgsError::UnexpectedArgument(arg)),
+            }
+        }
+
+        Ok(CompareFormulasArgs {
+            path: path.ok_or(ArgsError::MissingArgument("path"))?,
+            radius_model: body_radius_model(radius_model, body)?,
+            units,
+        })
+    }
+}
+
+impl GenerateArgs {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut pairs = None;
+        let mut radius = None;
+        let mut body = None;
+        let mut clusters = None;
+        let mut spread = None;
+        let mut preset = None;
+        let mut seed = 0;
+        let mut rng = Algorithm::default();
+        let mut output = "pairs.json".to_string();
+        let mut digits = None;
+        let mut compact = false;
+        let mut pretty = false;
+        let mut answers = None;
+        let mut no_answers = false;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--radius" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let km = value
+                        .parse()
+                        .ok()
+                        .filter(|&radius: &f64| radius > 0.0)
+                        .ok_or(ArgsError::InvalidValue(arg, value))?;
+                    radius = Some(km);
+                }
+                "--body" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let parsed = Body::from_name(&value)
+                        .ok_or_else(|| ArgsError::InvalidValue(arg, value.clone()))?;
+                    body = Some(parsed);
+                }
+                "--clusters" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let count = value
+                        .parse()
+                        .ok()
+                        .filter(|&clusters| clusters > 0)
+                        .ok_or(ArgsError::InvalidValue(arg, value))?;
+                    clusters = Some(count);
+                }
+                "--spread" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let degrees = value
+                        .parse()
+                        .ok()
+                        .filter(|&spread: &f64| spread >= 0.0)
+                        .ok_or(ArgsError::InvalidValue(arg, value))?;
+                    spread = Some(degrees);
+                }
+                "--preset" => {
+                    let value = next_value(&mut args, &arg)?;
+                    preset = Some(
+                        Preset::from_name(&value)
+                            .ok_or_else(|| ArgsError::InvalidValue(arg, value.clone()))?,
+                    );
+                }
+                "--seed" => {
+                    let value = next_value(&mut args, &arg)?;
+                    seed = value
+                        .parse()
+                        .map_err(|_| ArgsError::InvalidValue(arg, value))?;
+                }
+                "--rng" => {
+                    let value = next_value(&mut args, &arg)?;
+                    rng = Algorithm::from_name(&value)
+                        .ok_or_else(|| ArgsError::InvalidValue(arg, value.clone()))?;
+                }
+                "--output" => output = next_value(&mut args, &arg)?,
+                "--digits" => {
+                    let value = next_value(&mut args, &arg)?;
+                    digits = Some(
+                        value
+                            .parse()
+                            .map_err(|_| ArgsError::InvalidValue(arg, value))?,
+                    );
+                }
+                "--compact" => compact = true,
+                "--pretty" => pretty = true,
+                "--answers" => answers = Some(next_value(&mut args, &arg)?),
+                "--no-answers" => no_answers = true,
+                opt if opt.starts_with("--") => return Err(ArgsError::UnknownOption(arg)),
+                _ if pairs.is_none() => {
+                    pairs = Some(
+                        arg.parse()
+                            .map_err(|_| ArgsError::InvalidValue("pairs".into(), arg))?,
+                    );
+                }
+                _ => return Err(ArgsError::UnexpectedArgument(arg)),
+            }
+        }
+
+        if preset.is_some() {
+            if clusters.is_some() {
+                return Err(ArgsError::Conflict("--preset", "--clusters"));
+            }
+            if spread.is_some() {
+                return Err(ArgsError::Conflict("--preset", "--spread"));
+            }
+        }
+        // A spread alone means a single cluster.
+        let distribution = match (clusters, spread) {
+            (None, None) => Distribution::Uniform,
+            (Some(clusters), None) => Distribution::clustered(clusters),
+            (clusters, Some(spread)) => Distribution::Clustered {
+                clusters: clusters.unwrap_or(1),
+                spread,
+            },
+        };
+        if compact && pretty {
+            return Err(ArgsError::Conflict("--compact", "--pretty"));
+        }
+        if radius.is_some() && body.is_some() {
+            return Err(ArgsError::Conflict("--body", "--radius"));
+        }
+        if no_answers && answers.is_some() {
+            return Err(ArgsError::Conflict("--no-answers", "--answers"));
+        }
+        let answers = match answers {
+            Some(path) => Some(path),
+            None if no_answers || output == "-" => None,
+            None => Some(
+                Path::new(&output)
+                    .with_extension("answers")
+                    .to_string_lossy()
+                    .into_owned(),
+            ),
+        };
+        Ok(GenerateArgs {
+            pairs: pairs.ok_or(ArgsError::MissingArgument("pairs"))?,
+            radius: radius.or(body.map(Body::kilometers)).unwrap_or(1.0),
+            distribution,
+            preset,
+            seed,
+            rng,
+            output,
+            digits,
+            pretty: !compact,
+            answers,
+        })
+    }
+}
+
+impl SplitArgs {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut path = None;
+        let mut shards = None;
+        let mut prefix = None;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--shards" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let count = value
+                        .parse()
+                        .ok()
+                        .filter(|&shards| shards > 0)
+                        .ok_or(ArgsError::InvalidValue(arg, value))?;
+                    shards = Some(count);
+                }
+                "--prefix" => prefix = Some(next_value(&mut args, &arg)?),
+                opt if opt.starts_with("--") => return Err(ArgsError::UnknownOption(arg)),
+                _ if path.is_none() => path = Some(arg),
+                _ => return Err(ArgsError::UnexpectedArgument(arg)),
+            }
+        }
+
+        let path = path.unwrap_or_else(|| DEFAULT_PATH.into());
+        let prefix = prefix.unwrap_or_else(|| match path.as_str() {
+            "-" => "pairs".into(),
+            path => path.strip_suffix(".json").unwrap_or(path).into(),
+        });
+        Ok(SplitArgs {
+            path,
+            shards: shards.ok_or(ArgsError::MissingArgument("--shards"))?,
+            prefix,
+        })
+    }
+}
+
+impl MergeArgs {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut paths = Vec::new();
+        let mut output = "-".to_string();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--output" => output = next_value(&mut args, &arg)?,
+                opt if opt.starts_with("--") => return Err(ArgsError::UnknownOption(arg)),
+                _ => paths.push(arg),
+            }
+        }
+
+        if paths.is_empty() {
+            return Err(ArgsError::MissingArgument("shards"));
+        }
+        Ok(MergeArgs { paths, output })
+    }
+}
+
+impl DiffArgs {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut paths = Vec::new();
+        let mut tolerance = 0.0;
+        let mut limit = 20;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--tolerance" => {
+                    let value = next_value(&mut args, &arg)?;
+                    tolerance = value
+                        .parse()
+                        .ok()
+                        .filter(|tolerance: &f64| *tolerance >= 0.0)
+                        .ok_or(ArgsError::InvalidValue(arg, value))?;
+                }
+                "--limit" => {
+                    let value = next_value(&mut args, &arg)?;
+                    limit = value
+                        .parse()
+                        .map_err(|_| ArgsError::InvalidValue(arg, value))?;
+                }
+                opt if opt.starts_with("--") => return Err(ArgsError::UnknownOption(arg)),
+                _ if paths.len() < 2 => paths.push(arg),
+                _ => return Err(ArgsError::UnexpectedArgument(arg)),
+            }
+        }
+
+        let paths: [String; 2] = paths
+            .try_into()
+            .map_err(|_| ArgsError::MissingArgument("two documents"))?;
+        if paths.iter().all(|path| path == "-") {
+            return Err(ArgsError::Conflict("-", "-"));
+        }
+        Ok(DiffArgs {
+            paths,
+            tolerance,
+            limit,
+        })
+    }
+}
+
+impl ExportColumnarArgs {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut path = None;
+        let mut output = None;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--output" => output = Some(next_value(&mut args, &arg)?),
+                opt if opt.starts_with("--") => return Err(ArgsError::UnknownOption(arg)),
+                _ if path.is_none() => path = Some(arg),
+                _ => return Err(ArgsError::UnexpectedArgument(arg)),
+            }
+        }
+
+        let path = path.unwrap_or_else(|| DEFAULT_PATH.into());
+        let output = output.unwrap_or_else(|| match path.as_str() {
+            "-" => "-".into(),
+            path => format!("{}.col", path.strip_suffix(".json").unwrap_or(path)),
+        });
+        Ok(ExportColumnarArgs { path, output })
+    }
+}
+
+impl DistArgs {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut coordinates = Vec::new();
+        let mut radius = None;
+        let mut body = None;
+        let mut formula = Formula::default();
+        let mut units = DistanceUnit::default();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--radius" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let km = RadiusModel::from_name(&value)
+                        .map(RadiusModel::kilometers)
+                        .or_else(|| value.parse().ok().filter(|&radius: &f64| radius > 0.0))
+                        .ok_or(ArgsError::InvalidValue(arg, value))?;
+                    radius = Some(km);
+                }
+                "--body" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let parsed = Body::from_name(&value)
+                        .ok_or_else(|| ArgsError::InvalidValue(arg, value.clone()))?;
+                    body = Some(parsed);
+                }
+                "--formula" => {
+                    let value = next_value(&mut args, &arg)?;
+                    formula = match value.as_str() {
+                        "haversine" => Formula::Haversine,
+                        "cosine" => Formula::Cosine,
+                        "vincenty" => Formula::Vincenty,
+                        _ => return Err(ArgsError::InvalidValue(arg, value)),
+                    };
+                }
+                "--units" => {
+                    let value = next_value(&mut args, &arg)?;
+                    units = DistanceUnit::from_name(&value)
+                        .ok_or_else(|| ArgsError::InvalidValue(arg, value.clone()))?;
+                }
+                // Negative coordinates start with a single dash.
+                opt if opt.starts_with("--") => return Err(ArgsError::UnknownOption(arg)),
+                _ if coordinates.len() < 4 => {
+                    let coordinate = arg
+                        .parse()
+                        .map_err(|_| ArgsError::InvalidValue("coordinate".into(), arg))?;
+                    coordinates.push(coordinate);
+                }
+                _ => return Err(ArgsError::UnexpectedArgument(arg)),
+            }
+        }
+
+        let coordinates = coordinates
+            .try_into()
+            .map_err(|_| ArgsError::MissingArgument("four coordinates"))?;
+        if radius.is_some() && body.is_some() {
+            return Err(ArgsError::Conflict("--body", "--radius"));
+        }
+        let radius = radius.or(body.map(Body::kilometers));
+        Ok(DistArgs {
+            coordinates,
+            radius: radius.unwrap_or(RadiusModel::Mean.kilometers()),
+            formula,
+            units,
+        })
+    }
+}
+
+impl ReplArgs {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut parsed = ReplArgs::default();
+        let mut body = None;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--radius-model" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let model = RadiusModel::from_name(&value)
+                        .ok_or_else(|| ArgsError::InvalidValue(arg, value.clone()))?;
+                    parsed.radius_model = Some(model);
+                }
+                "--body" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let model = Body::from_name(&value)
+                        .ok_or_else(|| ArgsError::InvalidValue(arg, value.clone()))?;
+                    body = Some(model);
+                }
+                "--units" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let units = DistanceUnit::from_name(&value)
+                        .ok_or_else(|| ArgsError::InvalidValue(arg, value.clone()))?;
+                    parsed.units = Some(units);
+                }
+                opt if opt.starts_with("--") => return Err(ArgsError::UnknownOption(arg)),
+                _ => return Err(ArgsError::UnexpectedArgument(arg)),
+            }
+        }
+
+        parsed.radius_model = body_radius_model(parsed.radius_model, body)?;
+        Ok(parsed)
+    }
+}
+
+impl InspectArgs {
+    fn parse(args: impl Iterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut path = None;
+        for arg in args {
+            match arg.as_str() {
+                opt if opt.starts_with("--") => return Err(ArgsError::UnknownOption(arg)),
+                _ if path.is_none() => path = Some(arg),
+                _ => return Err(ArgsError::UnexpectedArgument(arg)),
+            }
+        }
+        Ok(InspectArgs {
+            path: path.ok_or(ArgsError::MissingArgument("path"))?,
+        })
+    }
+}
+
+impl ExtractArgs {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut path = None;
+        let mut pointer = None;
+        let mut output = "-".to_string();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-o" | "--output" => output = next_value(&mut args, &arg)?,
+                opt if opt.starts_with("--") => return Err(ArgsError::UnknownOption(arg)),
+                _ if path.is_none() => path = Some(arg),
+                _ if pointer.is_none() => pointer = Some(arg),
+                _ => return Err(ArgsError::UnexpectedArgument(arg)),
+            }
+        }
+
+        Ok(ExtractArgs {
+            path: path.ok_or(ArgsError::MissingArgument("path"))?,
+            pointer: pointer.ok_or(ArgsError::MissingArgument("pointer"))?,
+            output,
+        })
+    }
+}
+
+impl TokensArgs {
+    fn parse(args: impl Iterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut path = None;
+        let mut format = DumpFormat::Text;
+        for arg in args {
+            match arg.as_str() {
+                "--json" => format = DumpFormat::Json,
+                opt if opt.starts_with("--") => return Err(ArgsError::UnknownOption(arg)),
+                _ if path.is_none() => path = Some(arg),
+                _ => return Err(ArgsError::UnexpectedArgument(arg)),
+            }
+        }
+        Ok(TokensArgs {
+            path: path.ok_or(ArgsError::MissingArgument("path"))?,
+            format,
+        })
+    }
+}
+
+impl TreeArgs {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut path = None;
+        let mut depth = None;
+        let mut items = DEFAULT_TREE_ITEMS;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--depth" => {
+                    let value = next_value(&mut args, &arg)?;
+                    depth = Some(
+                        value
+                            .parse()
+                            .map_err(|_| ArgsError::InvalidValue(arg, value))?,
+                    );
+                }
+                "--items" => {
+                    let value = next_value(&mut args, &arg)?;
+                    items = value
+                        .parse()
+                        .map_err(|_| ArgsError::InvalidValue(arg, value))?;
+                }
+                opt if opt.starts_with("--") => return Err(ArgsError::UnknownOption(arg)),
+                _ if path.is_none() => path = Some(arg),
+                _ => return Err(ArgsError::UnexpectedArgument(arg)),
+            }
+        }
+
+        Ok(TreeArgs {
+            path: path.ok_or(ArgsError::MissingArgument("path"))?,
+            depth,
+            items,
+        })
+    }
+}
+
+impl BenchArgs {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut mode = match args.next().as_deref() {
+            Some("bandwidth") => BenchMode::Bandwidth {
+                access: vec![Access::Read, Access::Write],
+                sizes: (10..=30).step_by(2).map(|shift| 1 << shift).collect(),
+                stride: 1,
+                total_bytes: 1 << 30,
+            },
+            Some("traversal") => BenchMode::Traversal {
+                path: DEFAULT_PATH.into(),
+            },
+            Some("structural") => BenchMode::Structural {
+                path: DEFAULT_PATH.into(),
+            },
+            Some("parse") => BenchMode::Parse {
+                path: DEFAULT_PATH.into(),
+            },
+            Some("io") => BenchMode::Io {
+                path: DEFAULT_PATH.into(),
+                read_buffer: DEFAULT_BUFFER_SIZE,
+            },
+            Some("kernels") => BenchMode::Kernels {
+                path: DEFAULT_PATH.into(),
+            },
+            Some("cache") => BenchMode::Cache {
+                path: DEFAULT_PATH.into(),
+                runs: 5,
+                drop_cache: false,
+            },
+            Some(other) => return Err(ArgsError::UnexpectedArgument(other.into())),
+            None => return Err(ArgsError::MissingArgument("benchmark")),
+        };
+        let mut seconds = 10.0;
+        let mut has_path = false;
+
+        while let Some(arg) = args.next() {
+            match (arg.as_str(), &mut mode) {
+                ("--seconds", _) => {
+                    let value = next_value(&mut args, &arg)?;
+                    seconds = value
+                        .parse()
+                        .map_err(|_| ArgsError::InvalidValue(arg, value))?;
+                }
+                ("--access", BenchMode::Bandwidth { access, .. }) => {
+                    let value = next_value(&mut args, &arg)?;
+                    *access = match value.as_str() {
+                        "read" => vec![Access::Read],
+                        "write" => vec![Access::Write],
+                        "both" => vec![Access::Read, Access::Write],
+                        _ => return Err(ArgsError::InvalidValue(arg, value)),
+                    };
+                }
+                ("--sizes", BenchMode::Bandwidth { sizes, .. }) => {
+                    let value = next_value(&mut args, &arg)?;
+                    *sizes = value
+                        .split(',')
+                        .map(parse_size)
+                        .collect::<Option<_>>()
+                        .ok_or(ArgsError::InvalidValue(arg, value))?;
+                }
+                ("--stride", BenchMode::Bandwidth { stride, .. }) => {
+                    let value = next_value(&mut args, &arg)?;
+                    *stride = value
+                        .parse()
+                        .map_err(|_| ArgsError::InvalidValue(arg, value))?;
+                }
+                ("--total", BenchMode::Bandwidth { total_bytes, .. }) => {
+                    let value = next_value(&mut args, &arg)?;
+                    *total_bytes = parse_size(&value).ok_or(ArgsError::InvalidValue(arg, value))?;
+                }
+                ("--read-buffer", BenchMode::Io { read_buffer, .. }) => {
+                    let value = next_value(&mut args, &arg)?;
+                    *read_buffer = parse_size(&value)
+                        .filter(|&size| size > 0)
+                        .ok_or(ArgsError::InvalidValue(arg, value))?;
+                }
+                ("--runs", BenchMode::Cache { runs, .. }) => {
+                    let value = next_value(&mut args, &arg)?;
+                    *runs = value
+                        .parse()
+                        .ok()
+                        .filter(|&runs| runs > 0)
+                        .ok_or(ArgsError::InvalidValue(arg, value))?;
+                }
+                ("--drop-caches-hint", BenchMode::Cache { drop_cache, .. }) => *drop_cache = true,
+                (opt, _) if opt.starts_with("--") => return Err(ArgsError::UnknownOption(arg)),
+                (
+                    _,
+                    BenchMode::Traversal { path }
+                    | BenchMode::Structural { path }
+                    | BenchMode::Parse { path }
+                    | BenchMode::Io { path, .. }
+                    | BenchMode::Kernels { path }
+                    | BenchMode::Cache { path, .. },
+                ) if !has_path => {
+                    *path = arg;
+                    has_path = true;
+                }
+                _ => return Err(ArgsError::UnexpectedArgument(arg)),
+            }
+        }
+
+        Ok(BenchArgs { mode, seconds })
+    }
+}
+
+/// Takes the value that follows the option `name`.
+fn next_value(args: &mut impl Iterator<Item = String>, name: &str) -> Result<String, ArgsError> {
+    args.next()
+        .ok_or_else(|| ArgsError::MissingValue(name.into()))
+}
+
+/// The radius model given with `--radius-model`, else that of the body
+/// given with `--body`. Both is a conflict.
+fn body_radius_model(
+    radius_model: Option<RadiusModel>,
+    body: Option<Body>,
+) -> Result<Option<RadiusModel>, ArgsError> {
+    match (radius_model, body) {
+        (Some(_), Some(_)) => Err(ArgsError::Conflict("--body", "--radius-model")),
+        (radius_model, body) => Ok(radius_model.or(body.map(Body::radius_model))),
+    }
+}
+
+/// Parses a byte count with an optional `K`, `M` or `G` binary suffix.
+fn parse_size(value: &str) -> Option<usize> {
+    let (digits, shift) = match value.as_bytes().last()? {
+        b'K' | b'k' => (&value[..value.len() - 1], 10),
+        b'M' | b'm' => (&value[..value.len() - 1], 20),
+        b'G' | b'g' => (&value[..value.len() - 1], 30),
+        _ => (value, 0),
+    };
+    digits.parse::<usize>().ok()?.checked_mul(1 << shift)
+}
+
+/// Parses a duration with a `ms`, `s`, `m` or `h` suffix, in seconds
+/// without one.
+fn parse_duration(value: &str) -> Option<Duration> {
+    let (number, seconds) = if let Some(number) = value.strip_suffix("ms") {
+        (number, 1e-3)
+    } else if let Some(number) = value.strip_suffix('s') {
+        (number, 1.0)
+    } else if let Some(number) = value.strip_suffix('m') {
+        (number, 60.0)
+    } else if let Some(number) = value.strip_suffix('h') {
+        (number, 3600.0)
+    } else {
+        (value, 1.0)
+    };
+    Duration::try_from_secs_f64(number.parse::<f64>().ok()? * seconds).ok()
+}
+
+#[cfg(test)]
+mod tests;
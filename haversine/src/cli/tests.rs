@@ -0,0 +1,337 @@
+use super::{ArgsError, COMMANDS, Command, ComputeArgs, LogArgs, OptionSpec};
+use crate::log::{Level, LogFormat};
+use haversine::geo::{AngleUnit, BoundingBox, RadiusModel};
+use haversine::pairs::SampleSize;
+use std::time::Duration;
+
+/// A value `option` accepts, if it takes one.
+fn value(option: &OptionSpec) -> Option<&'static str> {
+    option.value?;
+    Some(option.choices.first().copied().unwrap_or("1"))
+}
+
+/// Whether the parser takes `name` as `option`, and its value if it has one,
+/// after the arguments in `before`. A value it rejects still counts.
+fn parses(before: &[&str], name: &str, option: &OptionSpec) -> bool {
+    let mut line: Vec<String> = before.iter().map(|arg| arg.to_string()).collect();
+    line.push(name.to_string());
+    line.extend(value(option).map(str::to_string));
+    match Command::parse(line) {
+        Err(ArgsError::UnknownOption(unknown)) => unknown != name,
+        Err(ArgsError::UnexpectedArgument(arg)) => Some(arg.as_str()) != value(option),
+        _ => true,
+    }
+}
+
+#[test]
+fn test_every_option_spec_is_parsed() {
+    for command in COMMANDS {
+        for option in command.options {
+            let mut names = vec![option.name.to_string()];
+            names.extend(option.short.map(|short| format!("-{short}")));
+            for name in names {
+                // Options of a mode, like those of `bench`, after any mode.
+                let parsed = match command.choices {
+                    [] => parses(&[command.name], &name, option),
+                    choices => choices
+                        .iter()
+                        .any(|choice| parses(&[command.name, choice], &name, option)),
+                };
+                assert!(parsed, "{} {name} is not parsed", command.name);
+            }
+        }
+        let first = command.choices.first().copied();
+        let line = [command.name].into_iter().chain(first);
+        let line = line.chain(["--no-such-option"]).map(str::to_string);
+        assert!(
+            Command::parse(line).is_err(),
+            "{} accepts any option",
+            command.name
+        );
+    }
+}
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+#[test]
+fn test_log_args_levels() {
+    let level = |line: &[&str]| LogArgs::extract(args(line)).unwrap().0.level;
+    assert_eq!(Level::Info, level(&["compute", "in.json"]));
+    assert_eq!(Level::Error, level(&["-q", "compute", "in.json"]));
+    assert_eq!(Level::Error, level(&["compute", "--quiet", "in.json"]));
+    assert_eq!(Level::Debug, level(&["compute", "in.json", "-v"]));
+    assert_eq!(Level::Debug, level(&["--verbose", "compute"]));
+    assert_eq!(Level::Trace, level(&["-vv", "compute"]));
+    assert_eq!(Level::Trace, level(&["-v", "compute", "--verbose"]));
+    assert_eq!(Level::Trace, level(&["-vvv", "compute"]));
+    assert_eq!(
+        Err(ArgsError::Conflict("-q", "-v")),
+        LogArgs::extract(args(&["-q", "compute", "-v"]))
+    );
+}
+
+#[test]
+fn test_log_args_leave_the_rest() {
+    let (parsed, rest) = LogArgs::extract(args(&[
+        "-v",
+        "compute",
+        "--log-format",
+        "json",
+        "in.json",
+        "-x",
+    ]))
+    .unwrap();
+    assert_eq!(LogFormat::Json, parsed.format);
+    assert_eq!(args(&["compute", "in.json", "-x"]), rest);
+    // A lone `-` is the standard input, not a flag.
+    assert_eq!(
+        args(&["compute", "-"]),
+        LogArgs::extract(args(&["compute", "-"])).unwrap().1
+    );
+    assert_eq!(
+        Err(ArgsError::InvalidValue("--log-format".into(), "xml".into())),
+        LogArgs::extract(args(&["--log-format", "xml"]))
+    );
+    assert!(matches!(
+        LogArgs::extract(args(&["compute", "--log-format"])),
+        Err(ArgsError::MissingValue(_))
+    ));
+}
+
+#[test]
+fn test_log_args_round_trip() {
+    for line in [&[][..], &["-q"], &["-v"], &["-vv", "--log-format", "json"]] {
+        let (parsed, _) = LogArgs::extract(args(line)).unwrap();
+        assert_eq!(parsed, LogArgs::extract(parsed.to_args()).unwrap().0);
+    }
+}
+
+#[test]
+fn test_watch_conflicts() {
+    let parse = |line: &[&str]| Command::parse(args(line));
+    assert!(matches!(
+        parse(&["compute", "--watch", "in.json"]),
+        Ok(Command::Compute(compute)) if compute.watch
+    ));
+    assert_eq!(
+        Err(ArgsError::Conflict("--watch", "-")),
+        parse(&["compute", "--watch", "-"])
+    );
+    assert_eq!(
+        Err(ArgsError::Conflict("--watch", "--checkpoint")),
+        parse(&["compute", "--watch", "in.json", "--checkpoint", "run.ckpt"])
+    );
+}
+
+/// The `compute` arguments of `line`, which follows `compute`.
+fn compute(line: &[&str]) -> Result<ComputeArgs, ArgsError> {
+    let line = ["compute"].iter().chain(line).map(|arg| arg.to_string());
+    match Command::parse(line)? {
+        Command::Compute(compute) => Ok(*compute),
+        command => panic!("parsed as {command:?}"),
+    }
+}
+
+fn invalid(option: &str, value: &str) -> ArgsError {
+    ArgsError::InvalidValue(option.into(), value.into())
+}
+
+#[test]
+fn test_checkpoint_conflicts() {
+    let checkpoint = ["in.json", "--checkpoint", "run.ckpt"];
+    let with = |options: &[&str]| compute(&[&checkpoint[..], options].concat());
+    assert!(with(&[]).is_ok());
+    assert_eq!(
+        Err(ArgsError::Conflict("--checkpoint", "--threads")),
+        with(&["--threads", "2"])
+    );
+    // One thread sums in order, as the checkpoints do.
+    assert!(with(&["--threads", "1"]).is_ok());
+    assert_eq!(
+        Err(ArgsError::Conflict("--checkpoint", "--reduction tree")),
+        with(&["--reduction", "tree"])
+    );
+    assert_eq!(
+        Err(ArgsError::Conflict("--checkpoint", "--pin-threads")),
+        with(&["--pin-threads"])
+    );
+    assert_eq!(
+        Err(ArgsError::Conflict("--checkpoint", "--precision f32")),
+        with(&["--precision", "f32"])
+    );
+}
+
+#[test]
+fn test_resume_conflicts() {
+    assert_eq!(
+        Err(ArgsError::MissingArgument("checkpoint")),
+        compute(&["in.json", "--resume"])
+    );
+    let resume = ["in.json", "--checkpoint", "run.ckpt", "--resume"];
+    assert!(compute(&resume).is_ok());
+    for (option, value) in [
+        ("--per-pair", Some("out.csv")),
+        ("--export-geojson", Some("out.geojson")),
+        ("--answers", Some("answers.f64")),
+        ("--stats", None),
+        ("--stats-json", None),
+        ("--histogram", Some("10")),
+    ] {
+        let line = [&resume[..], &[option], value.as_slice()].concat();
+        assert_eq!(Err(ArgsError::Conflict("--resume", option)), compute(&line));
+    }
+}
+
+#[test]
+fn test_checkpoint_interval() {
+    let interval = |value| {
+        compute(&["in.json", "--checkpoint-interval", value]).map(|args| args.checkpoint.interval)
+    };
+    assert_eq!(Ok(Duration::from_millis(2500)), interval("2.5"));
+    assert_eq!(Ok(Duration::ZERO), interval("0"));
+    for value in ["-1", "soon", "1s", "inf"] {
+        assert_eq!(
+            Err(invalid("--checkpoint-interval", value)),
+            interval(value)
+        );
+    }
+}
+
+#[test]
+fn test_angle_unit() {
+    let unit = |value| compute(&["in.json", "--angle-unit", value]).map(|args| args.angle_unit);
+    assert_eq!(Ok(AngleUnit::Radians), unit("radians"));
+    assert_eq!(Ok(AngleUnit::Gradians), unit("gradians"));
+    assert_eq!(Err(invalid("--angle-unit", "turns")), unit("turns"));
+    // Every option taking coordinates converts them from the unit.
+    for options in [
+        &["--bbox", "0,0,10,10"][..],
+        &["--emit-bearings"],
+        &["--per-pair", "out.csv", "--per-pair-midpoints"],
+        &["--export-geojson", "out.geojson"],
+        &["--mode", "route"],
+        &["--precision", "f32"],
+    ] {
+        let line = [&["in.json", "--angle-unit", "radians"][..], options].concat();
+        assert_eq!(AngleUnit::Radians, compute(&line).unwrap().angle_unit);
+    }
+}
+
+#[test]
+fn test_answers_conflicts() {
+    let answers = ["in.json", "--answers", "answers.f64"];
+    let with = |options: &[&str]| compute(&[&answers[..], options].concat());
+    assert!(with(&[]).is_ok());
+    assert_eq!(
+        Err(ArgsError::Conflict("--answers", "--sample")),
+        with(&["--sample", "10"])
+    );
+    assert_eq!(
+        Err(ArgsError::Conflict("--answers", "--bbox")),
+        with(&["--bbox", "0,0,10,10"])
+    );
+}
+
+#[test]
+fn test_sample_spec() {
+    let sample = |value| compute(&["in.json", "--sample", value]).map(|args| args.sample);
+    assert_eq!(Ok(Some(SampleSize::Count(1000))), sample("1000"));
+    assert_eq!(Ok(Some(SampleSize::Fraction(0.25))), sample("25%"));
+    assert_eq!(Ok(Some(SampleSize::Fraction(1.0))), sample("100%"));
+    for value in ["0", "-5", "0%", "101%", "%", "ten", "1.5"] {
+        assert_eq!(Err(invalid("--sample", value)), sample(value));
+    }
+    assert_eq!(
+        Ok(42),
+        compute(&["in.json", "--sample-seed", "42"]).map(|args| args.sample_seed)
+    );
+    assert_eq!(
+        Err(invalid("--sample-seed", "-1")),
+        compute(&["in.json", "--sample-seed", "-1"])
+    );
+}
+
+#[test]
+fn test_input_conflicts() {
+    assert_eq!(
+        Err(ArgsError::Conflict("--io", "-")),
+        compute(&["-", "--io", "mmap"])
+    );
+    for (format, conflict) in [
+        ("ndjson", "--format ndjson"),
+        ("columnar", "--format columnar"),
+    ] {
+        for options in [
+            &["--mode", "route"][..],
+            &["--tape"],
+            &["--fast-schema"],
+            &["--transcode"],
+        ] {
+            let line = [&["in.json", "--format", format][..], options].concat();
+            let option = match options[0] {
+                "--mode" => "--mode route",
+                option => option,
+            };
+            assert_eq!(Err(ArgsError::Conflict(conflict, option)), compute(&line));
+        }
+    }
+    assert_eq!(
+        Err(ArgsError::Conflict("--transcode", "--stream")),
+        compute(&["in.json", "--transcode", "--stream"])
+    );
+    assert_eq!(
+        Err(ArgsError::Conflict("--body", "--radius-model")),
+        compute(&["in.json", "--body", "mars", "--radius-model", "mean"])
+    );
+}
+
+#[test]
+fn test_compute_values() {
+    let args = compute(&[
+        "in.json",
+        "--read-buffer",
+        "64K",
+        "--timeout",
+        "1.5m",
+        "--threads",
+        "4",
+        "--histogram",
+        "20",
+        "--bbox",
+        "35,-10,60,30",
+        "--radius-model",
+        "mean",
+    ])
+    .unwrap();
+    assert_eq!(64 << 10, args.read_buffer);
+    assert_eq!(Some(Duration::from_secs(90)), args.timeout);
+    assert_eq!(4, args.threads);
+    assert_eq!(Some(20), args.histogram);
+    assert_eq!(BoundingBox::new(35.0, -10.0, 60.0, 30.0), args.bbox);
+    assert_eq!(Some(RadiusModel::Mean), args.radius_model);
+    for (option, value) in [
+        ("--read-buffer", "0"),
+        ("--read-buffer", "1X"),
+        ("--timeout", "-1s"),
+        ("--timeout", "soon"),
+        ("--threads", "0"),
+        ("--histogram", "0"),
+        ("--bbox", "35,-10,60"),
+        ("--bbox", "95,0,10,10"),
+        ("--per-pair", "out.txt"),
+        ("--io", "carrier-pigeon"),
+        ("--kernel", "abacus"),
+        ("--precision", "f16"),
+        ("--reduction", "random"),
+        ("--format", "xml"),
+        ("--mode", "polygon"),
+    ] {
+        assert_eq!(
+            Err(invalid(option, value)),
+            compute(&["in.json", option, value]),
+            "{option} {value}"
+        );
+    }
+}
@@ -0,0 +1,195 @@
+//! A binary layout of pairs that loads without parsing: a small header,
+//! then the four coordinate columns as contiguous little-endian `f64`
+//! blocks, each starting on a 64-byte boundary. Loading a column reads
+//! straight into the array the compute kernels scan.
+
+use crate::pairs::Pairs;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+/// First bytes of a columnar file.
+pub const MAGIC: [u8; 6] = *b"HVCOLS";
+/// Version of the layout written.
+pub const VERSION: u16 = 1;
+/// Bytes of the header, and the alignment of the columns.
+pub const HEADER_LEN: usize = 64;
+
+/// Values read into a column at a time, so a corrupt count in the header
+/// runs out of input before it runs out of memory.
+const READ_CHUNK: usize = 1 << 16;
+
+/// The `avg_dist` field holds an average.
+const HAS_AVERAGE: u32 = 1;
+
+/// What a columnar file holds besides the coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColumnarHeader {
+    /// Number of pairs, the length of each column.
+    pub count: usize,
+    pub radius: f64,
+    /// Average distance of the pairs, if it was known when writing.
+    pub avg_dist: Option<f64>,
+}
+
+impl ColumnarHeader {
+    fn to_bytes(self) -> [u8; HEADER_LEN] {
+        let mut bytes = [0; HEADER_LEN];
+        bytes[0..6].copy_from_slice(&MAGIC);
+        bytes[6..8].copy_from_slice(&VERSION.to_le_bytes());
+        bytes[8..16].copy_from_slice(&(self.count as u64).to_le_bytes());
+        bytes[16..24].copy_from_slice(&self.radius.to_le_bytes());
+        bytes[24..32].copy_from_slice(&self.avg_dist.unwrap_or(0.0).to_le_bytes());
+        let flags = if self.avg_dist.is_some() {
+            HAS_AVERAGE
+        } else {
+            0
+        };
+        bytes[32..36].copy_from_slice(&flags.to_le_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8; HEADER_LEN]) -> Result<Self, ColumnarError> {
+        let word = |range: std::ops::Range<usize>| -> [u8; 8] {
+            bytes[range].try_into().expect("Eight bytes")
+        };
+        if bytes[0..6] != MAGIC {
+            return Err(ColumnarError::NotColumnar);
+        }
+        let version = u16::from_le_bytes([bytes[6], bytes[7]]);
+        if version != VERSION {
+            return Err(ColumnarError::UnsupportedVersion(version));
+        }
+        let count = u64::from_le_bytes(word(8..16));
+        let count = usize::try_from(count)
+            .ok()
+            // The four columns must fit in memory together.
+            .filter(|count| {
+                count
+                    .checked_mul(4 * size_of::<f64>())
+                    .is_some_and(|bytes| bytes <= isize::MAX as usize)
+            })
+            .ok_or(ColumnarError::TooLarge(count))?;
+        let flags = u32::from_le_bytes(bytes[32..36].try_into().expect("Four bytes"));
+        Ok(Self {
+            count,
+            radius: f64::from_le_bytes(word(16..24)),
+            avg_dist: (flags & HAS_AVERAGE != 0).then(|| f64::from_le_bytes(word(24..32))),
+        })
+    }
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ColumnarError {
+    Io(io::Error),
+    /// The input does not start with `MAGIC`.
+    NotColumnar,
+    UnsupportedVersion(u16),
+    /// More pairs than this machine can address.
+    TooLarge(u64),
+}
+
+impl fmt::Display for ColumnarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColumnarError::Io(error) => write!(f, "{error}"),
+            ColumnarError::NotColumnar => write!(f, "not a columnar pairs file"),
+            ColumnarError::UnsupportedVersion(version) => {
+                write!(f, "unsupported columnar version {version}")
+            }
+            ColumnarError::TooLarge(count) => write!(f, "too many pairs to load: {count}"),
+        }
+    }
+}
+
+impl std::error::Error for ColumnarError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ColumnarError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ColumnarError {
+    fn from(error: io::Error) -> Self {
+        ColumnarError::Io(error)
+    }
+}
+
+/// Zero bytes after a column of `count` values, up to the next boundary.
+fn padding(count: usize) -> usize {
+    (count * size_of::<f64>()).next_multiple_of(HEADER_LEN) - count * size_of::<f64>()
+}
+
+/// Writes `pairs` in the columnar layout, with the radius of their sphere
+/// and, if known, their average distance.
+pub fn write(
+    mut writer: impl Write,
+    pairs: &Pairs,
+    radius: f64,
+    avg_dist: Option<f64>,
+) -> io::Result<()> {
+    let header = ColumnarHeader {
+        count: pairs.len(),
+        radius,
+        avg_dist,
+    };
+    writer.write_all(&header.to_bytes())?;
+    let zeros = [0; HEADER_LEN];
+    for column in [&pairs.x0, &pairs.y0, &pairs.x1, &pairs.y1] {
+        if cfg!(target_endian = "little") {
+            writer.write_all(as_bytes(column))?;
+        } else {
+            for value in column {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+        }
+        writer.write_all(&zeros[..padding(column.len())])?;
+    }
+    writer.flush()
+}
+
+/// Reads pairs written by `write`, returning the header and the pairs.
+pub fn read(mut reader: impl Read) -> Result<(ColumnarHeader, Pairs), ColumnarError> {
+    let mut bytes = [0; HEADER_LEN];
+    reader.read_exact(&mut bytes)?;
+    let header = ColumnarHeader::from_bytes(&bytes)?;
+
+    let mut read_column = || -> io::Result<Vec<f64>> {
+        let mut column = Vec::with_capacity(header.count.min(READ_CHUNK));
+        while column.len() < header.count {
+            let start = column.len();
+            column.resize(start + (header.count - start).min(READ_CHUNK), 0.0);
+            reader.read_exact(as_bytes_mut(&mut column[start..]))?;
+        }
+        if cfg!(target_endian = "big") {
+            for value in &mut column {
+                *value = f64::from_bits(u64::from_le(value.to_bits()));
+            }
+        }
+        reader.read_exact(&mut bytes[..padding(header.count)])?;
+        Ok(column)
+    };
+    let pairs = Pairs {
+        x0: read_column()?,
+        y0: read_column()?,
+        x1: read_column()?,
+        y1: read_column()?,
+    };
+    Ok((header, pairs))
+}
+
+fn as_bytes(values: &[f64]) -> &[u8] {
+    // SAFETY: the bytes of `f64`s are all initialized, bytes need no
+    // alignment, and the slice covers exactly the values.
+    unsafe { std::slice::from_raw_parts(values.as_ptr().cast(), size_of_val(values)) }
+}
+
+fn as_bytes_mut(values: &mut [f64]) -> &mut [u8] {
+    // SAFETY: as in `as_bytes`, and every bit pattern is a valid `f64`.
+    unsafe { std::slice::from_raw_parts_mut(values.as_mut_ptr().cast(), size_of_val(values)) }
+}
+
+#[cfg(test)]
+mod tests;
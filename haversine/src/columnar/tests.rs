@@ -0,0 +1,81 @@
+use super::{ColumnarError, ColumnarHeader, HEADER_LEN, MAGIC, read, write};
+use crate::pairs::Pairs;
+
+fn pairs(n: usize) -> Pairs {
+    let mut pairs = Pairs::default();
+    for i in 0..n {
+        let i = i as f64;
+        pairs.push(i, -i / 2.0, 180.0 - i, i.sqrt());
+    }
+    pairs
+}
+
+#[test]
+fn test_round_trip() {
+    for n in [0, 1, 7, 8, 9, 1000] {
+        let pairs = pairs(n);
+        let mut bytes = Vec::new();
+        write(&mut bytes, &pairs, 6372.8, Some(1234.5)).unwrap();
+        assert!(bytes.starts_with(&MAGIC));
+        let (header, read_pairs) = read(bytes.as_slice()).unwrap();
+        let expected = ColumnarHeader {
+            count: n,
+            radius: 6372.8,
+            avg_dist: Some(1234.5),
+        };
+        assert_eq!(expected, header);
+        assert_eq!(pairs, read_pairs);
+    }
+}
+
+#[test]
+fn test_columns_are_aligned() {
+    let mut bytes = Vec::new();
+    write(&mut bytes, &pairs(9), 1.0, None).unwrap();
+    // Each column of 72 bytes is padded to 128.
+    assert_eq!(HEADER_LEN + 4 * 128, bytes.len());
+    for column in 0..4 {
+        let start = HEADER_LEN + column * 128;
+        let first = f64::from_le_bytes(bytes[start..start + 8].try_into().unwrap());
+        assert_eq!([0.0, -0.0, 180.0, 0.0][column], first);
+    }
+    let (header, _) = read(bytes.as_slice()).unwrap();
+    assert_eq!(None, header.avg_dist);
+}
+
+#[test]
+fn test_errors() {
+    assert!(matches!(
+        read(&b"{\"pairs\": []}"[..]),
+        Err(ColumnarError::Io(_))
+    ));
+    assert!(matches!(
+        read(&[b' '; HEADER_LEN][..]),
+        Err(ColumnarError::NotColumnar)
+    ));
+
+    let mut bytes = Vec::new();
+    write(&mut bytes, &pairs(3), 1.0, None).unwrap();
+    let mut future = bytes.clone();
+    future[6] = 2;
+    assert!(matches!(
+        read(future.as_slice()),
+        Err(ColumnarError::UnsupportedVersion(2))
+    ));
+    let mut huge = bytes.clone();
+    huge[8..16].copy_from_slice(&u64::MAX.to_le_bytes());
+    assert!(matches!(
+        read(huge.as_slice()),
+        Err(ColumnarError::TooLarge(u64::MAX))
+    ));
+    huge[8..16].copy_from_slice(&(1u64 << 60).to_le_bytes());
+    assert!(matches!(
+        read(huge.as_slice()),
+        Err(ColumnarError::TooLarge(_))
+    ));
+    // A count that fits in memory but not in the input ends with the input.
+    huge[8..16].copy_from_slice(&(1u64 << 33).to_le_bytes());
+    assert!(matches!(read(huge.as_slice()), Err(ColumnarError::Io(_))));
+    bytes.truncate(bytes.len() - 1);
+    assert!(matches!(read(bytes.as_slice()), Err(ColumnarError::Io(_))));
+}
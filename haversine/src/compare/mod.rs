@@ -0,0 +1,157 @@
+//! Comparison of the great-circle distance formulas on the same pairs, to
+//! weigh how far apart they are against how long each takes.
+
+use crate::geo::{
+    UnitVector, cosine_distance, haversine_distance, haversine_distance_fast,
+    haversine_distance_fma, reference, vincenty_distance,
+};
+use crate::pairs::Pairs;
+use std::time::{Duration, Instant};
+
+/// A way of computing the great-circle distance of a pair.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Formula {
+    Haversine,
+    /// The spherical law of cosines.
+    Cosine,
+    Vincenty,
+    /// The chord between the unit vectors of the points.
+    Chord,
+    /// The dot and cross products of the unit vectors of the points.
+    Dot,
+    /// The haversine formula with fused multiply-adds.
+    Fma,
+    /// The haversine formula from the cosines of the differences.
+    FastMath,
+    /// The haversine formula in double-double arithmetic, far slower than
+    /// the others and closer to the exact distance than any of them.
+    Reference,
+}
+
+impl Formula {
+    /// Every formula, in the order they are reported.
+    pub const ALL: [Formula; 8] = [
+        Formula::Haversine,
+        Formula::Cosine,
+        Formula::Vincenty,
+        Formula::Chord,
+        Formula::Dot,
+        Formula::Fma,
+        Formula::FastMath,
+        Formula::Reference,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Formula::Haversine => "haversine",
+            Formula::Cosine => "cosine",
+            Formula::Vincenty => "vincenty",
+            Formula::Chord => "chord",
+            Formula::Dot => "dot",
+            Formula::Fma => "fma",
+            Formula::FastMath => "fast-math",
+            Formula::Reference => "reference",
+        }
+    }
+
+    /// Distance between two points given in degrees, on a sphere of the
+    /// given radius.
+    pub fn distance(self, radius: f64, phi_0: f64, theta_0: f64, phi_1: f64, theta_1: f64) -> f64 {
+        match self {
+            Formula::Haversine => haversine_distance(radius, phi_0, theta_0, phi_1, theta_1),
+            Formula::Cosine => cosine_distance(radius, phi_0, theta_0, phi_1, theta_1),
+            Formula::Vincenty => vincenty_distance(radius, phi_0, theta_0, phi_1, theta_1),
+            Formula::Chord => {
+                UnitVector::new(phi_0, theta_0).distance(&UnitVector::new(phi_1, theta_1), radius)
+            }
+            Formula::Dot => UnitVector::new(phi_0, theta_0)
+                .dot_distance(&UnitVector::new(phi_1, theta_1), radius),
+            Formula::Fma => haversine_distance_fma(radius, phi_0, theta_0, phi_1, theta_1),
+            Formula::FastMath => haversine_distance_fast(radius, phi_0, theta_0, phi_1, theta_1),
+            Formula::Reference => {
+                reference::distance(radius, phi_0, theta_0, phi_1, theta_1).to_f64()
+            }
+        }
+    }
+}
+
+/// How far apart the distances of two formulas are over the same pairs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Deviation {
+    pub first: Formula,
+    pub second: Formula,
+    /// Largest absolute difference of the distance of a pair.
+    pub max: f64,
+    /// Mean absolute difference of the distance of a pair.
+    pub mean: f64,
+    /// Index of the pair with the largest difference.
+    pub worst_pair: usize,
+}
+
+/// The result of `compare_formulas`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    pub n_pairs: usize,
+    /// Time each formula took over all the pairs, in the order of
+    /// `Formula::ALL`.
+    pub times: Vec<(Formula, Duration)>,
+    /// Every two formulas, each pair of them once.
+    pub deviations: Vec<Deviation>,
+}
+
+/// Computes the distance of every pair with every formula and compares
+/// them two by two.
+pub fn compare_formulas(pairs: &Pairs, radius: f64) -> Comparison {
+    let mut times = Vec::new();
+    let distances: Vec<Vec<f64>> = Formula::ALL
+        .iter()
+        .map(|&formula| {
+            let start = Instant::now();
+            let distances: Vec<f64> = pairs
+                .iter()
+                .map(|pair| {
+                    let (p0, p1) = (pair.p0, pair.p1);
+                    formula.distance(radius, p0.lon, p0.lat, p1.lon, p1.lat)
+                })
+                .collect();
+            times.push((formula, start.elapsed()));
+            distances
+        })
+        .collect();
+
+    let mut deviations = Vec::new();
+    for (i, &first) in Formula::ALL.iter().enumerate() {
+        for (j, &second) in Formula::ALL.iter().enumerate().skip(i + 1) {
+            let mut deviation = Deviation {
+                first,
+                second,
+                max: 0.0,
+                mean: 0.0,
+                worst_pair: 0,
+            };
+            let mut sum = 0.0;
+            for (index, (a, b)) in distances[i].iter().zip(&distances[j]).enumerate() {
+                let difference = (a - b).abs();
+                sum += difference;
+                if difference > deviation.max {
+                    deviation.max = difference;
+                    deviation.worst_pair = index;
+                }
+            }
+            if !pairs.is_empty() {
+                deviation.mean = sum / pairs.len() as f64;
+            }
+            deviations.push(deviation);
+        }
+    }
+
+    Comparison {
+        n_pairs: pairs.len(),
+        times,
+        deviations,
+    }
+}
+
+#[cfg(test)]
+mod tests;
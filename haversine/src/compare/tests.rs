@@ -0,0 +1,51 @@
+use super::{Formula, compare_formulas};
+use crate::generate::{Distribution, generate_pairs};
+use crate::pairs::Pairs;
+use crate::rng::Xoshiro256PlusPlus;
+
+#[test]
+fn test_every_two_formulas_compared_once() {
+    let pairs = generate_pairs(&mut Xoshiro256PlusPlus::new(3), 200, Distribution::Uniform);
+    let comparison = compare_formulas(&pairs, 6372.8);
+    assert_eq!(200, comparison.n_pairs);
+    assert_eq!(Formula::ALL.len(), comparison.times.len());
+    assert_eq!(28, comparison.deviations.len());
+    for deviation in &comparison.deviations {
+        assert_ne!(deviation.first, deviation.second);
+        assert!(deviation.mean <= deviation.max, "{deviation:?}");
+        // Every formula is within a meter of the others on random pairs.
+        assert!(deviation.max < 1e-3, "{deviation:?}");
+    }
+}
+
+#[test]
+fn test_cosine_deviates_on_nearby_pairs() {
+    let mut pairs = Pairs::default();
+    pairs.push(10.0, 20.0, 10.0 + 1e-7, 20.0);
+    pairs.push(10.0, 20.0, 30.0, 40.0);
+    let comparison = compare_formulas(&pairs, 6372.8);
+    let deviation = |first, second| {
+        comparison
+            .deviations
+            .iter()
+            .find(|deviation| deviation.first == first && deviation.second == second)
+            .copied()
+            .unwrap()
+    };
+    let cosine = deviation(Formula::Cosine, Formula::Reference);
+    let haversine = deviation(Formula::Haversine, Formula::Reference);
+    assert_eq!(0, cosine.worst_pair);
+    assert!(haversine.max < cosine.max, "{haversine:?} {cosine:?}");
+}
+
+#[test]
+fn test_no_pairs() {
+    let comparison = compare_formulas(&Pairs::default(), 1.0);
+    assert_eq!(0, comparison.n_pairs);
+    assert!(
+        comparison
+            .deviations
+            .iter()
+            .all(|d| d.max == 0.0 && d.mean == 0.0)
+    );
+}
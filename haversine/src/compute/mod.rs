@@ -0,0 +1,620 @@
+use crate::affinity;
+use crate::cancel::CancelToken;
+use crate::checkpoint::{self, Checkpoint};
+use crate::dd::DoubleDouble;
+use crate::geo::{
+    AngleUnit, Chord, Cosine, DistanceFn, Haversine, Kernel, Point, UnitVector, Vincenty,
+    cosine_distance_radians, haversine_distance_f32, haversine_distance_f32_radians,
+    haversine_distance_fast, haversine_distance_fast_radians, haversine_distance_fma,
+    haversine_distance_fma_radians, haversine_distance_radians_with, haversine_distance_with,
+    vincenty_distance_radians,
+};
+use crate::math::{Math, StdMath};
+use crate::pairs::{Pairs, PairsF32};
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Pairs computed between updates of the progress counter.
+const PROGRESS_INTERVAL: usize = 1 << 16;
+
+/// Pairs a thread takes at a time, and whose distances a tree reduction
+/// sums before combining.
+const CHUNK_PAIRS: usize = 1 << 14;
+
+/// Floating-point precision of the distance computation.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum Precision {
+    #[default]
+    F64,
+    /// Distances computed in `f32` from `PairsF32`, and summed in `f64`.
+    F32,
+}
+
+impl Precision {
+    /// Parses `f32` or `f64`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "f64" => Some(Precision::F64),
+            "f32" => Some(Precision::F32),
+            _ => None,
+        }
+    }
+}
+
+/// How the distances are summed.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum Reduction {
+    /// On one thread, in the order of the pairs. On several, the sums of
+    /// chunks of pairs are added as the threads finish them, so the last
+    /// bits of the sum can change from run to run.
+    #[default]
+    Running,
+    /// The sums of fixed chunks of pairs added pairwise, neighbours first,
+    /// so the sum is the same bit for bit whatever the number of threads
+    /// and however they are scheduled.
+    Tree,
+}
+
+impl Reduction {
+    /// Parses `running` or `tree`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "running" => Some(Reduction::Running),
+            "tree" => Some(Reduction::Tree),
+            _ => None,
+        }
+    }
+}
+
+/// Computes the distances of pairs and their average.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComputeDriver {
+    radius: f64,
+    wide_accumulator: bool,
+    keep_distances: bool,
+    math: Math,
+    kernel: Kernel,
+    threads: usize,
+    reduction: Reduction,
+    pin_threads: bool,
+    angle_unit: AngleUnit,
+    cancel: Option<CancelToken>,
+}
+
+/// Outcome of a `ComputeDriver` run.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct ComputeResult {
+    pub n_pairs: usize,
+    pub sum: f64,
+    pub average: f64,
+    /// Average accumulated in double-double arithmetic, if requested.
+    pub wide_average: Option<f64>,
+    /// Distance of every pair, if requested.
+    pub distances: Vec<f64>,
+    /// Whether the run was cancelled before all the pairs were computed.
+    /// The other fields then cover the pairs computed, `n_pairs` of them.
+    pub cancelled: bool,
+}
+
+impl ComputeDriver {
+    /// Creates a driver for a sphere of the given radius.
+    pub fn new(radius: f64) -> Self {
+        Self {
+            radius,
+            wide_accumulator: false,
+            keep_distances: false,
+            math: Math::Std,
+            kernel: Kernel::Haversine,
+            threads: 1,
+            reduction: Reduction::Running,
+            pin_threads: false,
+            angle_unit: AngleUnit::Degrees,
+            cancel: None,
+        }
+    }
+
+    /// Sets the number of threads, at least one, or `0` for as many as the
+    /// machine runs in parallel. Checkpointed runs use one.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = match threads {
+            0 => thread::available_parallelism().map_or(1, NonZeroUsize::get),
+            threads => threads,
+        };
+        self
+    }
+
+    /// Pins the threads to cores, the `i`th to the `i`th core, for steadier
+    /// measurements of memory bandwidth. The calling thread only waits for
+    /// them, so it stays unpinned.
+    pub fn pin_threads(mut self, enabled: bool) -> Self {
+        self.pin_threads = enabled;
+        self
+    }
+
+    /// Sets how the distances are summed.
+    pub fn reduction(mut self, reduction: Reduction) -> Self {
+        self.reduction = reduction;
+        self
+    }
+
+    /// Also accumulates the sum in double-double arithmetic.
+    pub fn wide_accumulator(mut self, enabled: bool) -> Self {
+        self.wide_accumulator = enabled;
+        self
+    }
+
+    /// Keeps the distance of every pair in the result.
+    pub fn keep_distances(mut self, enabled: bool) -> Self {
+        self.keep_distances = enabled;
+        self
+    }
+
+    /// Computes the distances with the functions of `math`. The `f32` runs
+    /// always use the standard library.
+    pub fn math(mut self, math: Math) -> Self {
+        self.math = math;
+        self
+    }
+
+    /// Sets how the distances are computed. The kernels other than
+    /// `Kernel::Haversine` take the functions of the standard library
+    /// whatever `math`, and the `f32` runs always use `Kernel::Haversine`.
+    pub fn kernel(mut self, kernel: Kernel) -> Self {
+        self.kernel = kernel;
+        self
+    }
+
+    /// Sets the unit of the coordinates. The kernels convert them straight
+    /// to radians, and `run_distance` to the degrees of its points.
+    pub fn angle_unit(mut self, unit: AngleUnit) -> Self {
+        self.angle_unit = unit;
+        self
+    }
+
+    /// Stops the run once `token` is cancelled. It is checked every few
+    /// thousand pairs, and by each thread between its chunks.
+    pub fn cancel(mut self, token: CancelToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    fn cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(CancelToken::is_cancelled)
+    }
+
+    pub fn run(&self, pairs: &Pairs) -> ComputeResult {
+        self.run_with_progress(pairs, &AtomicUsize::new(0))
+    }
+
+    /// Runs, storing the number of pairs computed so far in `processed`
+    /// every few thousand pairs, for another thread to report.
+    pub fn run_with_progress(&self, pairs: &Pairs, processed: &AtomicUsize) -> ComputeResult {
+        self.run_pairs(pairs.columns(), processed, None)
+    }
+
+    /// Runs with `distance` in place of the kernel, for metrics of the
+    /// caller's own, in the same way as the kernels. It is given the points
+    /// in degrees, whatever the angle unit, and measures on a sphere of its
+    /// own: the radius, kernel and math of the driver are left to it.
+    pub fn run_distance(
+        &self,
+        pairs: &Pairs,
+        distance: &(impl DistanceFn + ?Sized),
+    ) -> ComputeResult {
+        self.run_distance_with_progress(pairs, distance, &AtomicUsize::new(0))
+    }
+
+    /// `run_distance`, reporting progress like `run_with_progress`.
+    pub fn run_distance_with_progress(
+        &self,
+        pairs: &Pairs,
+        distance: &(impl DistanceFn + ?Sized),
+        processed: &AtomicUsize,
+    ) -> ComputeResult {
+        self.sum_distances(pairs.columns(), processed, None, distance)
+    }
+
+    /// Runs on the coordinates of pairs held in four columns of equal
+    /// length, as `x0`, `y0`, `x1` and `y1`, without copying them. Panics if
+    /// their lengths differ.
+    pub fn run_columns(&self, x0: &[f64], y0: &[f64], x1: &[f64], y1: &[f64]) -> ComputeResult {
+        assert!(
+            [y0.len(), x1.len(), y1.len()]
+                .iter()
+                .all(|&n| n == x0.len()),
+            "Columns of equal length"
+        );
+        self.run_pairs([x0, y0, x1, y1], &AtomicUsize::new(0), None)
+    }
+
+    /// Runs like `run_with_progress`, passing `save` the state of the run
+    /// about every `interval`. Starts from `resume`, if any, a checkpoint of
+    /// a run of the same pairs with the same driver, whose `fingerprint`
+    /// the caller has checked. The distances done before it are not kept.
+    pub fn run_checkpointed(
+        &self,
+        pairs: &Pairs,
+        processed: &AtomicUsize,
+        resume: Option<&Checkpoint>,
+        interval: Duration,
+        save: &mut dyn FnMut(&Checkpoint),
+    ) -> ComputeResult {
+        let checkpoints = Checkpoints {
+            start: resume.copied().unwrap_or(Checkpoint {
+                fingerprint: self.fingerprint(pairs),
+                ..Checkpoint::default()
+            }),
+            interval,
+            save,
+        };
+        self.run_pairs(pairs.columns(), processed, Some(checkpoints))
+    }
+
+    /// Identifies the pairs and radius of a run, for its checkpoints.
+    pub fn fingerprint(&self, pairs: &Pairs) -> u64 {
+        let coordinates = [&pairs.x0, &pairs.y0, &pairs.x1, &pairs.y1];
+        // Degrees add nothing, so the checkpoints from before the unit could
+        // be set still match.
+        let unit = (self.angle_unit != AngleUnit::Degrees).then(|| self.angle_unit.half_turn());
+        checkpoint::fingerprint(
+            std::iter::once(self.radius)
+                .chain(unit)
+                .chain(coordinates.into_iter().flatten().copied()),
+        )
+    }
+
+    fn run_pairs(
+        &self,
+        columns: [&[f64]; 4],
+        processed: &AtomicUsize,
+        checkpoints: Option<Checkpoints>,
+    ) -> ComputeResult {
+        if self.angle_unit != AngleUnit::Degrees {
+            return self.run_pairs_radians(columns, processed, checkpoints);
+        }
+        let radius = self.radius;
+        match (self.kernel, &self.math) {
+            (Kernel::Haversine, Math::Std) => {
+                self.sum_distances(columns, processed, checkpoints, &Haversine { radius })
+            }
+            (Kernel::Haversine, Math::Poly(poly)) => {
+                let distance = |p0: Point, p1: Point| {
+                    haversine_distance_with(poly, radius, p0.lon, p0.lat, p1.lon, p1.lat)
+                };
+                self.sum_distances(columns, processed, checkpoints, &distance)
+            }
+            (Kernel::CachedTrig, _) => {
+                self.sum_distances(columns, processed, checkpoints, &Chord { radius })
+            }
+            (Kernel::Dot, _) => {
+                let distance = |p0: Point, p1: Point| {
+                    let to = UnitVector::new(p1.lon, p1.lat);
+                    UnitVector::new(p0.lon, p0.lat).dot_distance(&to, radius)
+                };
+                self.sum_distances(columns, processed, checkpoints, &distance)
+            }
+            (Kernel::Fma, _) => {
+                let distance = |p0: Point, p1: Point| {
+                    haversine_distance_fma(radius, p0.lon, p0.lat, p1.lon, p1.lat)
+                };
+                self.sum_distances(columns, processed, checkpoints, &distance)
+            }
+            (Kernel::FastMath, _) => {
+                let distance = |p0: Point, p1: Point| {
+                    haversine_distance_fast(radius, p0.lon, p0.lat, p1.lon, p1.lat)
+                };
+                self.sum_distances(columns, processed, checkpoints, &distance)
+            }
+            (Kernel::Vincenty, _) => {
+                self.sum_distances(columns, processed, checkpoints, &Vincenty { radius })
+            }
+            (Kernel::Cosine, _) => {
+                self.sum_distances(columns, processed, checkpoints, &Cosine { radius })
+            }
+        }
+    }
+
+    /// The kernels for coordinates in another unit than degrees, which are
+    /// converted to radians once rather than to degrees and back.
+    fn run_pairs_radians(
+        &self,
+        [x0, y0, x1, y1]: [&[f64]; 4],
+        processed: &AtomicUsize,
+        checkpoints: Option<Checkpoints>,
+    ) -> ComputeResult {
+        let (n, radius, unit) = (x0.len(), self.radius, self.angle_unit);
+        let radians = |i: usize| [x0[i], y0[i], x1[i], y1[i]].map(|angle| unit.to_radians(angle));
+        let unit_vectors = |i: usize| {
+            let [phi_0, theta_0, phi_1, theta_1] = radians(i);
+            (
+                UnitVector::from_radians(phi_0, theta_0),
+                UnitVector::from_radians(phi_1, theta_1),
+            )
+        };
+        match (self.kernel, &self.math) {
+            (Kernel::Haversine, Math::Std) => self.accumulate(n, processed, checkpoints, |i| {
+                let [phi_0, theta_0, phi_1, theta_1] = radians(i);
+                haversine_distance_radians_with(&StdMath, radius, phi_0, theta_0, phi_1, theta_1)
+            }),
+            (Kernel::Haversine, Math::Poly(poly)) => {
+                self.accumulate(n, processed, checkpoints, |i| {
+                    let [phi_0, theta_0, phi_1, theta_1] = radians(i);
+                    haversine_distance_radians_with(poly, radius, phi_0, theta_0, phi_1, theta_1)
+                })
+            }
+            (Kernel::CachedTrig, _) => self.accumulate(n, processed, checkpoints, |i| {
+                let (from, to) = unit_vectors(i);
+                from.distance(&to, radius)
+            }),
+            (Kernel::Dot, _) => self.accumulate(n, processed, checkpoints, |i| {
+                let (from, to) = unit_vectors(i);
+                from.dot_distance(&to, radius)
+            }),
+            (Kernel::Fma, _) => self.accumulate(n, processed, checkpoints, |i| {
+                let [phi_0, theta_0, phi_1, theta_1] = radians(i);
+                haversine_distance_fma_radians(radius, phi_0, theta_0, phi_1, theta_1)
+            }),
+            (Kernel::FastMath, _) => self.accumulate(n, processed, checkpoints, |i| {
+                let [phi_0, theta_0, phi_1, theta_1] = radians(i);
+                haversine_distance_fast_radians(radius, phi_0, theta_0, phi_1, theta_1)
+            }),
+            (Kernel::Vincenty, _) => self.accumulate(n, processed, checkpoints, |i| {
+                let [phi_0, theta_0, phi_1, theta_1] = radians(i);
+                vincenty_distance_radians(radius, phi_0, theta_0, phi_1, theta_1)
+            }),
+            (Kernel::Cosine, _) => self.accumulate(n, processed, checkpoints, |i| {
+                let [phi_0, theta_0, phi_1, theta_1] = radians(i);
+                cosine_distance_radians(radius, phi_0, theta_0, phi_1, theta_1)
+            }),
+        }
+    }
+
+    /// Sums `distance` over the pairs of the columns, whose coordinates in
+    /// the angle unit of the driver are converted to degrees for it. The
+    /// kernels in degrees and `run_distance` go through here.
+    fn sum_distances<D: DistanceFn + ?Sized>(
+        &self,
+        [x0, y0, x1, y1]: [&[f64]; 4],
+        processed: &AtomicUsize,
+        checkpoints: Option<Checkpoints>,
+        distance: &D,
+    ) -> ComputeResult {
+        let unit = self.angle_unit;
+        let point = |lon: f64, lat: f64| match unit {
+            AngleUnit::Degrees => Point::new(lon, lat),
+            unit => Point::new(unit.to_degrees(lon), unit.to_degrees(lat)),
+        };
+        self.accumulate(x0.len(), processed, checkpoints, |i| {
+            distance.dist(point(x0[i], y0[i]), point(x1[i], y1[i]))
+        })
+    }
+
+    /// Runs with the distances computed in single precision. They are summed
+    /// in `f64`, so the result differs from `run` by the error of the kernel
+    /// alone.
+    pub fn run_f32(&self, pairs: &PairsF32) -> ComputeResult {
+        self.run_f32_with_progress(pairs, &AtomicUsize::new(0))
+    }
+
+    /// `run_f32`, reporting progress like `run_with_progress`.
+    pub fn run_f32_with_progress(
+        &self,
+        pairs: &PairsF32,
+        processed: &AtomicUsize,
+    ) -> ComputeResult {
+        let (radius, unit) = (self.radius as f32, self.angle_unit);
+        if unit != AngleUnit::Degrees {
+            let radians = |angle: f32| unit.to_radians(angle as f64) as f32;
+            return self.accumulate(pairs.len(), processed, None, |i| {
+                let [phi_0, theta_0, phi_1, theta_1] =
+                    [pairs.x0[i], pairs.y0[i], pairs.x1[i], pairs.y1[i]].map(radians);
+                haversine_distance_f32_radians(radius, phi_0, theta_0, phi_1, theta_1) as f64
+            });
+        }
+        self.accumulate(pairs.len(), processed, None, |i| {
+            haversine_distance_f32(radius, pairs.x0[i], pairs.y0[i], pairs.x1[i], pairs.y1[i])
+                as f64
+        })
+    }
+
+    fn accumulate(
+        &self,
+        n_pairs: usize,
+        processed: &AtomicUsize,
+        mut checkpoints: Option<Checkpoints>,
+        distance: impl Fn(usize) -> f64 + Sync,
+    ) -> ComputeResult {
+        let chunked = self.threads > 1 || self.reduction == Reduction::Tree || self.pin_threads;
+        if checkpoints.is_none() && chunked {
+            return self.accumulate_chunks(n_pairs, processed, distance);
+        }
+        let start = checkpoints
+            .as_ref()
+            .map_or(Checkpoint::default(), |c| c.start);
+        let mut sum = start.sum;
+        let (hi, lo) = start.wide_sum;
+        let mut wide_sum = DoubleDouble { hi, lo };
+        let mut distances = Vec::with_capacity(if self.keep_distances { n_pairs } else { 0 });
+        // Only read the clock when saving: it panics on wasm32.
+        let mut last_saved = checkpoints.is_some().then(Instant::now);
+        let mut end = n_pairs;
+
+        for i in start.position..n_pairs {
+            let distance = distance(i);
+            sum += distance;
+            if self.wide_accumulator {
+                wide_sum = wide_sum.add_f64(distance);
+            }
+            if self.keep_distances {
+                distances.push(distance);
+            }
+            if (i + 1) % PROGRESS_INTERVAL == 0 {
+                processed.store(i + 1, Ordering::Relaxed);
+                let cancelled = self.cancelled() && i + 1 < n_pairs;
+                // A cancelled run saves where it stopped, to be resumed.
+                if let Some(checkpoints) = &mut checkpoints
+                    && (cancelled
+                        || last_saved.is_some_and(|saved| saved.elapsed() >= checkpoints.interval))
+                {
+                    (checkpoints.save)(&Checkpoint {
+                        position: i + 1,
+                        sum,
+                        wide_sum: (wide_sum.hi, wide_sum.lo),
+                        ..checkpoints.start
+                    });
+                    last_saved = Some(Instant::now());
+                }
+                if cancelled {
+                    end = i + 1;
+                    break;
+                }
+            }
+        }
+        processed.store(end, Ordering::Relaxed);
+
+        ComputeResult {
+            n_pairs: end,
+            sum,
+            average: sum / end as f64,
+            wide_average: self
+                .wide_accumulator
+                .then(|| wide_sum.div_f64(end as f64).to_f64()),
+            distances,
+            cancelled: end < n_pairs,
+        }
+    }
+
+    /// Sums the distances in chunks of `CHUNK_PAIRS` pairs, which the
+    /// threads take in turn, and reduces the sums of the chunks.
+    fn accumulate_chunks(
+        &self,
+        n_pairs: usize,
+        processed: &AtomicUsize,
+        distance: impl Fn(usize) -> f64 + Sync,
+    ) -> ComputeResult {
+        let mut distances = vec![0.0; if self.keep_distances { n_pairs } else { 0 }];
+        let mut slices = distances.chunks_mut(CHUNK_PAIRS);
+        let chunks = (0..n_pairs.div_ceil(CHUNK_PAIRS)).map(|chunk| (chunk, slices.next()));
+        let chunks = Mutex::new(chunks);
+        // The sums of the chunks with their index, in the order finished.
+        let sums = Mutex::new(Vec::new());
+
+        let work = || {
+            while !self.cancelled() {
+                let next = chunks.lock().expect("Chunks lock").next();
+                let Some((chunk, mut slice)) = next else {
+                    break;
+                };
+                let start = chunk * CHUNK_PAIRS;
+                let end = (start + CHUNK_PAIRS).min(n_pairs);
+                let mut sum = 0.0;
+                let mut wide_sum = DoubleDouble::default();
+                for i in start..end {
+                    let distance = distance(i);
+                    sum += distance;
+                    if self.wide_accumulator {
+                        wide_sum = wide_sum.add_f64(distance);
+                    }
+                    if let Some(slice) = &mut slice {
+                        slice[i - start] = distance;
+                    }
+                }
+                processed.fetch_add(end - start, Ordering::Relaxed);
+                sums.lock().expect("Sums lock").push((chunk, sum, wide_sum));
+            }
+        };
+        thread::scope(|scope| {
+            if self.pin_threads {
+                let cores = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+                for thread in 0..self.threads {
+                    scope.spawn(move || {
+                        affinity::pin_current_thread(thread % cores);
+                        work();
+                    });
+                }
+            } else {
+                for _ in 1..self.threads {
+                    scope.spawn(work);
+                }
+                work();
+            }
+        });
+
+        let mut sums = sums.into_inner().expect("Sums lock");
+        let computed = sums
+            .iter()
+            .map(|&(chunk, _, _)| (n_pairs - chunk * CHUNK_PAIRS).min(CHUNK_PAIRS))
+            .sum();
+        let cancelled = computed < n_pairs;
+        if cancelled && self.keep_distances {
+            // Only the distances of the chunks computed are kept, in order.
+            sums.sort_unstable_by_key(|&(chunk, _, _)| chunk);
+            distances = sums
+                .iter()
+                .flat_map(|&(chunk, _, _)| distances.chunks(CHUNK_PAIRS).nth(chunk))
+                .flatten()
+                .copied()
+                .collect();
+        }
+        let (sum, wide_sum) = match self.reduction {
+            Reduction::Running => sums.iter().fold(
+                (0.0, DoubleDouble::default()),
+                |(sum, wide_sum), &(_, chunk_sum, chunk_wide_sum)| {
+                    (sum + chunk_sum, wide_sum.add_dd(chunk_wide_sum))
+                },
+            ),
+            Reduction::Tree => {
+                sums.sort_unstable_by_key(|&(chunk, _, _)| chunk);
+                let sum = tree_sum(sums.iter().map(|&(_, sum, _)| sum).collect(), |a, b| a + b);
+                let wide_sum = tree_sum(
+                    sums.iter().map(|&(_, _, wide_sum)| wide_sum).collect(),
+                    DoubleDouble::add_dd,
+                );
+                (sum.unwrap_or(0.0), wide_sum.unwrap_or_default())
+            }
+        };
+
+        ComputeResult {
+            n_pairs: computed,
+            sum,
+            average: sum / computed as f64,
+            wide_average: self
+                .wide_accumulator
+                .then(|| wide_sum.div_f64(computed as f64).to_f64()),
+            distances,
+            cancelled,
+        }
+    }
+}
+
+/// Adds `values` pairwise, neighbours first and then their sums likewise,
+/// so the order of the additions depends only on how many there are.
+fn tree_sum<T: Copy>(mut values: Vec<T>, add: impl Fn(T, T) -> T) -> Option<T> {
+    while values.len() > 1 {
+        values = values
+            .chunks(2)
+            .map(|pair| match *pair {
+                [a, b] => add(a, b),
+                [a] => a,
+                _ => unreachable!("Chunks of one or two"),
+            })
+            .collect();
+    }
+    values.pop()
+}
+
+/// Where a checkpointed run starts, and how it saves its state.
+struct Checkpoints<'a> {
+    start: Checkpoint,
+    interval: Duration,
+    save: &'a mut dyn FnMut(&Checkpoint),
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,427 @@
+use super::{CHUNK_PAIRS, ComputeDriver, PROGRESS_INTERVAL, Precision, Reduction};
+use crate::cancel::CancelToken;
+use crate::generate::{Distribution, generate_pairs};
+use crate::geo::{
+    AngleUnit, Chord, Cosine, DistanceFn, Haversine, Kernel, Point, UnitVector, Vincenty,
+    cosine_distance_radians, haversine_distance, haversine_distance_fast_radians,
+    haversine_distance_fma_radians, haversine_distance_radians_with, vincenty_distance_radians,
+};
+use crate::math::{Math, PolyMath, StdMath};
+use crate::pairs::{Pair, Pairs, PairsF32};
+use crate::rng::Xoshiro256PlusPlus;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+fn pairs() -> Pairs {
+    let mut pairs = Pairs::default();
+    pairs.push_pair(Pair::new(Point::new(0.0, 0.0), Point::new(90.0, 0.0)));
+    pairs.push_pair(Pair::new(Point::new(0.0, 0.0), Point::new(0.0, 0.0)));
+    pairs
+}
+
+#[test]
+fn test_average() {
+    let result = ComputeDriver::new(2.0).run(&pairs());
+    let half_circle = pairs().get(0).unwrap().distance(2.0);
+    assert_eq!(2, result.n_pairs);
+    assert_eq!(half_circle / 2.0, result.average);
+    assert_eq!(None, result.wide_average);
+    assert!(result.distances.is_empty());
+}
+
+#[test]
+fn test_run_columns() {
+    let pairs = pairs();
+    let [x0, y0, x1, y1] = pairs.columns();
+    let driver = ComputeDriver::new(2.0).threads(2);
+    assert_eq!(driver.run(&pairs), driver.run_columns(x0, y0, x1, y1));
+}
+
+#[test]
+fn test_wide_average_and_distances() {
+    let result = ComputeDriver::new(2.0)
+        .wide_accumulator(true)
+        .keep_distances(true)
+        .run(&pairs());
+    assert_eq!(Some(result.average), result.wide_average);
+    assert_eq!(2, result.distances.len());
+    assert!((result.distances[0] - std::f64::consts::PI).abs() < 1e-12);
+    assert_eq!(0.0, result.distances[1]);
+}
+
+#[test]
+fn test_pair_distance_matches_driver() {
+    let pairs = pairs();
+    let result = ComputeDriver::new(6372.8).keep_distances(true).run(&pairs);
+    let distances: Vec<f64> = pairs.iter().map(|pair| pair.distance(6372.8)).collect();
+    assert_eq!(distances, result.distances);
+}
+
+#[test]
+fn test_progress_counts_every_pair() {
+    let processed = AtomicUsize::new(0);
+    let result = ComputeDriver::new(1.0).run_with_progress(&pairs(), &processed);
+    assert_eq!(result.n_pairs, processed.load(Ordering::Relaxed));
+}
+
+#[test]
+fn test_f32_run_close_to_f64() {
+    let pairs = pairs();
+    let driver = ComputeDriver::new(6372.8).keep_distances(true);
+    let f64_result = driver.run(&pairs);
+    let f32_result = driver.run_f32(&PairsF32::from(&pairs));
+    assert_eq!(2, f32_result.n_pairs);
+    assert_eq!(0.0, f32_result.distances[1]);
+    let error = (f32_result.average - f64_result.average) / f64_result.average;
+    assert!(error.abs() < 1e-6, "relative error {error}");
+}
+
+#[test]
+fn test_f32_run_in_angle_unit() {
+    let degrees = generated(100);
+    let expected = ComputeDriver::new(6372.8).run_f32(&PairsF32::from(&degrees));
+    for unit in [AngleUnit::Radians, AngleUnit::Gradians] {
+        let mut pairs = Pairs::default();
+        for pair in degrees.iter() {
+            let [x0, y0, x1, y1] = [pair.p0.lon, pair.p0.lat, pair.p1.lon, pair.p1.lat]
+                .map(|angle| unit.from_degrees(angle));
+            pairs.push(x0, y0, x1, y1);
+        }
+        let result = ComputeDriver::new(6372.8)
+            .angle_unit(unit)
+            .run_f32(&PairsF32::from(&pairs));
+        let error = (result.average - expected.average) / expected.average;
+        assert!(error.abs() < 1e-5, "{unit:?}: relative error {error}");
+    }
+}
+
+#[test]
+fn test_precision_from_name() {
+    assert_eq!(Some(Precision::F32), Precision::from_name("f32"));
+    assert_eq!(Some(Precision::F64), Precision::from_name("f64"));
+    assert_eq!(None, Precision::from_name("f16"));
+}
+
+#[test]
+fn test_poly_math_close_to_std() {
+    let pairs = pairs();
+    let std = ComputeDriver::new(6372.8).run(&pairs);
+    let poly = ComputeDriver::new(6372.8)
+        .math(Math::Poly(PolyMath::new(17)))
+        .run(&pairs);
+    assert!((poly.average - std.average).abs() < 1e-9 * std.average);
+}
+
+#[test]
+fn test_cached_trig_kernel_close_to_haversine() {
+    let pairs = pairs();
+    let haversine = ComputeDriver::new(6372.8).keep_distances(true).run(&pairs);
+    let cached = ComputeDriver::new(6372.8)
+        .keep_distances(true)
+        .kernel(Kernel::CachedTrig)
+        .run(&pairs);
+    for (a, b) in haversine.distances.iter().zip(&cached.distances) {
+        assert!((a - b).abs() < 1e-9, "{a} versus {b}");
+    }
+}
+
+#[test]
+fn test_angle_units_match_degrees() {
+    let degrees = generated(100);
+    let expected = ComputeDriver::new(6372.8)
+        .keep_distances(true)
+        .run(&degrees);
+    for unit in [AngleUnit::Radians, AngleUnit::Gradians] {
+        let mut pairs = Pairs::default();
+        for pair in degrees.iter() {
+            let [x0, y0, x1, y1] = [pair.p0.lon, pair.p0.lat, pair.p1.lon, pair.p1.lat]
+                .map(|angle| unit.from_degrees(angle));
+            pairs.push(x0, y0, x1, y1);
+        }
+        for kernel in [
+            Kernel::Haversine,
+            Kernel::CachedTrig,
+            Kernel::Dot,
+            Kernel::Fma,
+            Kernel::FastMath,
+            Kernel::Vincenty,
+        ] {
+            let result = ComputeDriver::new(6372.8)
+                .keep_distances(true)
+                .kernel(kernel)
+                .angle_unit(unit)
+                .run(&pairs);
+            for (a, b) in expected.distances.iter().zip(&result.distances) {
+                assert!((a - b).abs() < 1e-9, "{unit:?} {kernel:?}: {a} versus {b}");
+            }
+        }
+    }
+}
+
+#[test]
+fn test_radians_go_straight_to_the_kernels() {
+    let mut pairs = Pairs::default();
+    for pair in generated(100).iter() {
+        let [x0, y0, x1, y1] =
+            [pair.p0.lon, pair.p0.lat, pair.p1.lon, pair.p1.lat].map(|angle| angle.to_radians());
+        pairs.push(x0, y0, x1, y1);
+    }
+    // The kernel on the coordinates as read, with no trip through degrees.
+    let distance = |kernel, [x0, y0, x1, y1]: [f64; 4]| {
+        let (from, to) = (
+            UnitVector::from_radians(x0, y0),
+            UnitVector::from_radians(x1, y1),
+        );
+        match kernel {
+            Kernel::Haversine => haversine_distance_radians_with(&StdMath, 2.0, x0, y0, x1, y1),
+            Kernel::CachedTrig => from.distance(&to, 2.0),
+            Kernel::Dot => from.dot_distance(&to, 2.0),
+            Kernel::Fma => haversine_distance_fma_radians(2.0, x0, y0, x1, y1),
+            Kernel::FastMath => haversine_distance_fast_radians(2.0, x0, y0, x1, y1),
+            Kernel::Vincenty => vincenty_distance_radians(2.0, x0, y0, x1, y1),
+            Kernel::Cosine => cosine_distance_radians(2.0, x0, y0, x1, y1),
+        }
+    };
+    for kernel in [
+        Kernel::Haversine,
+        Kernel::CachedTrig,
+        Kernel::Dot,
+        Kernel::Fma,
+        Kernel::FastMath,
+        Kernel::Vincenty,
+        Kernel::Cosine,
+    ] {
+        let result = ComputeDriver::new(2.0)
+            .kernel(kernel)
+            .angle_unit(AngleUnit::Radians)
+            .run(&pairs);
+        let sum: f64 = pairs
+            .iter()
+            .map(|pair| distance(kernel, [pair.p0.lon, pair.p0.lat, pair.p1.lon, pair.p1.lat]))
+            .sum();
+        assert_eq!(sum / 100.0, result.average, "{kernel:?}");
+    }
+}
+
+#[test]
+fn test_resumed_run_matches_uninterrupted() {
+    let mut pairs = Pairs::default();
+    for i in 0..200_000 {
+        let i = i as f64;
+        pairs.push(
+            (i * 0.37) % 360.0 - 180.0,
+            (i * 0.11) % 180.0 - 90.0,
+            10.0,
+            i % 90.0,
+        );
+    }
+    let driver = ComputeDriver::new(6372.8).wide_accumulator(true);
+    let mut checkpoints = Vec::new();
+    let full = driver.run_checkpointed(
+        &pairs,
+        &AtomicUsize::new(0),
+        None,
+        Duration::ZERO,
+        &mut |checkpoint| checkpoints.push(*checkpoint),
+    );
+    assert_eq!(full, driver.run(&pairs));
+    let positions: Vec<_> = checkpoints.iter().map(|c| c.position).collect();
+    assert_eq!(vec![1 << 16, 2 << 16, 3 << 16], positions);
+    assert!(
+        checkpoints
+            .iter()
+            .all(|c| c.fingerprint == driver.fingerprint(&pairs))
+    );
+
+    let processed = AtomicUsize::new(0);
+    let resumed = driver.run_checkpointed(
+        &pairs,
+        &processed,
+        Some(&checkpoints[1]),
+        Duration::MAX,
+        &mut |_| panic!("saved before the interval"),
+    );
+    assert_eq!(full, resumed);
+    assert_eq!(pairs.len(), processed.load(Ordering::Relaxed));
+    assert_ne!(
+        driver.fingerprint(&pairs),
+        ComputeDriver::new(1.0).fingerprint(&pairs)
+    );
+}
+
+fn generated(n: usize) -> Pairs {
+    generate_pairs(&mut Xoshiro256PlusPlus::new(5), n, Distribution::Uniform)
+}
+
+#[test]
+fn test_tree_reduction_same_for_any_threads() {
+    // Several chunks, the last of them partial.
+    let pairs = generated(5 * CHUNK_PAIRS + 123);
+    let run = |threads| {
+        ComputeDriver::new(6372.8)
+            .wide_accumulator(true)
+            .reduction(Reduction::Tree)
+            .threads(threads)
+            .run(&pairs)
+    };
+    let single = run(1);
+    for threads in [2, 3, 8] {
+        let result = run(threads);
+        assert_eq!(single.sum.to_bits(), result.sum.to_bits(), "{threads}");
+        assert_eq!(single.wide_average, result.wide_average, "{threads}");
+    }
+    let running = ComputeDriver::new(6372.8)
+        .wide_accumulator(true)
+        .run(&pairs);
+    assert!((single.average - running.average).abs() <= 1e-12 * running.average);
+    assert_eq!(running.wide_average, single.wide_average);
+}
+
+#[test]
+fn test_threads_keep_distances_in_order() {
+    let pairs = generated(2 * CHUNK_PAIRS + 1);
+    let driver = ComputeDriver::new(1.0).keep_distances(true);
+    let processed = AtomicUsize::new(0);
+    let parallel = driver
+        .clone()
+        .threads(3)
+        .run_with_progress(&pairs, &processed);
+    assert_eq!(driver.run(&pairs).distances, parallel.distances);
+    assert_eq!(pairs.len(), processed.load(Ordering::Relaxed));
+}
+
+#[test]
+fn test_tree_reduction_of_no_pairs() {
+    let result = ComputeDriver::new(1.0)
+        .reduction(Reduction::Tree)
+        .run(&Pairs::default());
+    assert_eq!(0, result.n_pairs);
+    assert_eq!(0.0, result.sum);
+}
+
+#[test]
+fn test_reduction_from_name() {
+    assert_eq!(Some(Reduction::Tree), Reduction::from_name("tree"));
+    assert_eq!(Some(Reduction::Running), Reduction::from_name("running"));
+    assert_eq!(None, Reduction::from_name("pairwise"));
+}
+
+#[test]
+fn test_pinned_threads_match_unpinned() {
+    let pairs = generated(3 * CHUNK_PAIRS);
+    let driver = ComputeDriver::new(6372.8)
+        .reduction(Reduction::Tree)
+        .threads(2);
+    let pinned = driver.clone().pin_threads(true).run(&pairs);
+    assert_eq!(driver.run(&pairs).sum.to_bits(), pinned.sum.to_bits());
+}
+
+#[test]
+fn test_cancelled_run_keeps_partial_results() {
+    let pairs = generated(3 * PROGRESS_INTERVAL);
+    let token = CancelToken::new();
+    let driver = ComputeDriver::new(1.0).keep_distances(true);
+    let full = driver.clone().cancel(token.clone()).run(&pairs);
+    assert!(!full.cancelled);
+    assert_eq!(driver.run(&pairs), full);
+
+    // The sequential loop stops at the first check.
+    token.cancel();
+    let partial = driver.clone().cancel(token.clone()).run(&pairs);
+    assert!(partial.cancelled);
+    assert_eq!(PROGRESS_INTERVAL, partial.n_pairs);
+    assert_eq!(full.distances[..PROGRESS_INTERVAL], partial.distances);
+    let sum: f64 = partial.distances.iter().sum();
+    assert_eq!(sum / PROGRESS_INTERVAL as f64, partial.average);
+
+    // The threads take no chunk.
+    let threaded = driver.threads(2).cancel(token).run(&pairs);
+    assert!(threaded.cancelled);
+    assert_eq!(0, threaded.n_pairs);
+    assert!(threaded.distances.is_empty());
+}
+
+#[test]
+fn test_cancelled_run_saves_checkpoint() {
+    let pairs = generated(2 * PROGRESS_INTERVAL);
+    let token = CancelToken::new();
+    token.cancel();
+    let mut checkpoints = Vec::new();
+    let result = ComputeDriver::new(1.0).cancel(token).run_checkpointed(
+        &pairs,
+        &AtomicUsize::new(0),
+        None,
+        Duration::MAX,
+        &mut |checkpoint| checkpoints.push(*checkpoint),
+    );
+    assert!(result.cancelled);
+    let positions: Vec<_> = checkpoints.iter().map(|c| c.position).collect();
+    assert_eq!(vec![PROGRESS_INTERVAL], positions);
+    assert_eq!(result.sum, checkpoints[0].sum);
+}
+
+#[test]
+fn test_distance_fns_match_kernels() {
+    let pairs = generated(500);
+    let haversine = ComputeDriver::new(2.0).threads(3).run(&pairs).average;
+    let driver = ComputeDriver::new(2.0).threads(3);
+    assert_eq!(
+        haversine,
+        driver
+            .run_distance(&pairs, &Haversine { radius: 2.0 })
+            .average
+    );
+    let distances: [&dyn DistanceFn; 3] = [
+        &Vincenty { radius: 2.0 },
+        &Cosine { radius: 2.0 },
+        &Chord { radius: 2.0 },
+    ];
+    for distance in distances {
+        let average = driver.run_distance(&pairs, distance).average;
+        assert!((average - haversine).abs() < 1e-9, "{average} {haversine}");
+    }
+    for (kernel, distance) in [
+        (Kernel::Vincenty, distances[0]),
+        (Kernel::Cosine, distances[1]),
+    ] {
+        let driver = driver.clone().kernel(kernel);
+        assert_eq!(
+            driver.run(&pairs).average,
+            driver.run_distance(&pairs, distance).average
+        );
+    }
+}
+
+#[test]
+fn test_run_distance_in_angle_unit() {
+    let degrees = generated(100);
+    let mut radians = Pairs::default();
+    for pair in degrees.iter() {
+        let [x0, y0, x1, y1] = [pair.p0.lon, pair.p0.lat, pair.p1.lon, pair.p1.lat];
+        radians.push(
+            x0.to_radians(),
+            y0.to_radians(),
+            x1.to_radians(),
+            y1.to_radians(),
+        );
+    }
+    let expected = ComputeDriver::new(1.0).run(&degrees).average;
+    let result = ComputeDriver::new(1.0)
+        .angle_unit(AngleUnit::Radians)
+        .run_distance(&radians, &Haversine { radius: 1.0 });
+    assert!((result.average - expected).abs() < 1e-12);
+}
+
+#[test]
+fn test_closure_distance() {
+    let pairs = generated(100);
+    let doubled =
+        |p0: Point, p1: Point| 2.0 * haversine_distance(1.0, p0.lon, p0.lat, p1.lon, p1.lat);
+    let result = ComputeDriver::new(1.0)
+        .keep_distances(true)
+        .run_distance(&pairs, &doubled);
+    let expected = ComputeDriver::new(2.0).keep_distances(true).run(&pairs);
+    assert_eq!(100, result.n_pairs);
+    assert!((result.average - expected.average).abs() < 1e-12);
+    assert_eq!(100, result.distances.len());
+}
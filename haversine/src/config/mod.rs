@@ -0,0 +1,112 @@
+//! Defaults of the options read from a JSON config file, so recurring
+//! workflows need no long command lines. The file holds an object per
+//! subcommand, mapping option names to values:
+//!
+//! ```json
+//! { "compute": { "threads": 4, "kernel": "cached-trig", "radius-model": "polar" } }
+//! ```
+//!
+//! Each member stands for its option written before those of the command
+//! line, which therefore override it. The options of the diagnostics and
+//! of the config file itself are read before the config, so they cannot be
+//! set in it.
+
+use std::fmt;
+use std::io;
+
+use haversine::parse::{Map, Parser, Value};
+
+use crate::cli::find_command;
+
+/// Config file read from the working directory when no other is given.
+pub(crate) const DEFAULT_PATH: &str = "haversine.json";
+
+/// Options taken out of the arguments before the config is applied.
+const COMMAND_LINE_ONLY: [&str; 5] = [
+    "--verbose",
+    "--quiet",
+    "--log-format",
+    "--config",
+    "--no-config",
+];
+
+#[derive(Debug)]
+pub(crate) enum ConfigError {
+    Io(io::Error),
+    /// The file is not valid JSON.
+    Invalid(String),
+    /// The file, or its section of a subcommand, is not an object.
+    NotAnObject(String),
+    /// An option has a value other than a string, a number or a boolean.
+    InvalidValue(String),
+    /// An option that is read before the config file.
+    Unsupported(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(error) => write!(f, "{error}"),
+            ConfigError::Invalid(message) => write!(f, "invalid JSON: {message}"),
+            ConfigError::NotAnObject(section) if section.is_empty() => {
+                write!(f, "expected an object of subcommands")
+            }
+            ConfigError::NotAnObject(section) => {
+                write!(f, "expected an object of options at {section:?}")
+            }
+            ConfigError::InvalidValue(option) => {
+                write!(f, "expected a string, a number or a boolean at {option:?}")
+            }
+            ConfigError::Unsupported(option) => {
+                write!(f, "{option:?} can only be given on the command line")
+            }
+        }
+    }
+}
+
+/// Reads and parses a config file.
+pub(crate) fn load(path: &str) -> Result<Map, ConfigError> {
+    let text = std::fs::read_to_string(path).map_err(ConfigError::Io)?;
+    match Parser::new(&text).parse() {
+        Ok(Some(Value::Object(config))) => Ok(config),
+        Ok(Some(_)) => Err(ConfigError::NotAnObject(String::new())),
+        Ok(None) => Err(ConfigError::Invalid("the file is empty".into())),
+        Err(error) => Err(ConfigError::Invalid(error.to_string())),
+    }
+}
+
+/// Inserts the options of the config for the subcommand of `args` after
+/// its name, before the options given on the command line. Without a known
+/// subcommand the section is that of `compute`.
+pub(crate) fn apply(config: &Map, mut args: Vec<String>) -> Result<Vec<String>, ConfigError> {
+    let (name, at) = match args.first() {
+        Some(name) if find_command(name).is_some() => (name.clone(), 1),
+        _ => ("compute".to_string(), 0),
+    };
+    let options = match config.get(&name) {
+        Some(Value::Object(options)) => options,
+        Some(_) => return Err(ConfigError::NotAnObject(name)),
+        None => return Ok(args),
+    };
+
+    let mut defaults = Vec::new();
+    for (key, value) in options.iter() {
+        // Accepts `radius_model` as well as `radius-model`.
+        let option = format!("--{}", key.replace('_', "-"));
+        if COMMAND_LINE_ONLY.contains(&option.as_str()) {
+            return Err(ConfigError::Unsupported(format!("{name}.{key}")));
+        }
+        match value {
+            Value::Bool(true) => defaults.push(option),
+            Value::Bool(false) => {}
+            Value::String(value) => defaults.extend([option, value.clone()]),
+            Value::Number(_) => defaults.extend([option, value.to_json()]),
+            _ => return Err(ConfigError::InvalidValue(format!("{name}.{key}"))),
+        }
+    }
+    args.splice(at..at, defaults);
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests;
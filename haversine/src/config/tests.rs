@@ -0,0 +1,75 @@
+use super::{ConfigError, apply};
+use haversine::parse::{Map, Value, parse};
+
+fn config(json: &str) -> Map {
+    match parse(json).unwrap() {
+        Some(Value::Object(config)) => config,
+        _ => panic!("Expected an object"),
+    }
+}
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+#[test]
+fn test_apply_inserts_before_command_line() {
+    let config = config(
+        r#"{"compute": {"threads": 4, "radius_model": "polar", "stats": true, "wide": false}}"#,
+    );
+    assert_eq!(
+        args(&[
+            "compute",
+            "--threads",
+            "4",
+            "--radius-model",
+            "polar",
+            "--stats",
+            "in.json",
+            "--threads",
+            "2",
+        ]),
+        apply(&config, args(&["compute", "in.json", "--threads", "2"])).unwrap()
+    );
+    // Without a subcommand, the arguments are those of `compute`.
+    assert_eq!(
+        args(&[
+            "--threads",
+            "4",
+            "--radius-model",
+            "polar",
+            "--stats",
+            "in.json"
+        ]),
+        apply(&config, args(&["in.json"])).unwrap()
+    );
+    // Other subcommands have no section.
+    assert_eq!(
+        args(&["validate", "in.json"]),
+        apply(&config, args(&["validate", "in.json"])).unwrap()
+    );
+}
+
+#[test]
+fn test_apply_errors() {
+    let invalid = |json: &str| apply(&config(json), args(&["compute", "in.json"])).unwrap_err();
+    assert!(matches!(
+        invalid(r#"{"compute": [1]}"#),
+        ConfigError::NotAnObject(section) if section == "compute"
+    ));
+    assert!(matches!(
+        invalid(r#"{"compute": {"threads": [4]}}"#),
+        ConfigError::InvalidValue(option) if option == "compute.threads"
+    ));
+    assert!(matches!(
+        invalid(r#"{"compute": {"threads": null}}"#),
+        ConfigError::InvalidValue(_)
+    ));
+    for key in ["verbose", "quiet", "log_format", "config", "no-config"] {
+        let json = format!(r#"{{"compute": {{"{key}": true}}}}"#);
+        assert!(matches!(
+            invalid(&json),
+            ConfigError::Unsupported(option) if option == format!("compute.{key}")
+        ));
+    }
+}
@@ -0,0 +1,182 @@
+//! Double-double arithmetic: numbers kept as the unevaluated sum of two
+//! `f64`s, for about 30 significant digits where `f64` rounding is too
+//! coarse, as when checking the `f64` formulas against exact distances.
+//!
+//! ```
+//! use haversine::dd::DoubleDouble;
+//!
+//! let third = DoubleDouble::from(1.0).div_f64(3.0);
+//! let one = third.mul_f64(3.0);
+//! assert_eq!(1.0, one.to_f64());
+//! assert!(one.sub_dd(DoubleDouble::from(1.0)).to_f64().abs() < 1e-30);
+//! ```
+
+use std::ops::Neg;
+
+/// A double-double number: the unevaluated sum of two `f64`s, where `lo`
+/// holds the rounding error of `hi`, giving about 32 significant digits.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct DoubleDouble {
+    pub hi: f64,
+    pub lo: f64,
+}
+
+impl DoubleDouble {
+    /// Pi to double-double precision.
+    pub const PI: Self = Self {
+        hi: std::f64::consts::PI,
+        lo: 1.224_646_799_147_353_2e-16,
+    };
+
+    /// Adds an `f64` without losing the rounding error of the addition.
+    pub fn add_f64(self, value: f64) -> Self {
+        let (sum, error) = two_sum(self.hi, value);
+        let (hi, lo) = fast_two_sum(sum, error + self.lo);
+        Self { hi, lo }
+    }
+
+    /// Adds another double-double, keeping the rounding errors of both.
+    pub fn add_dd(self, other: Self) -> Self {
+        let (sum, error) = two_sum(self.hi, other.hi);
+        let (hi, lo) = fast_two_sum(sum, error + self.lo + other.lo);
+        Self { hi, lo }
+    }
+
+    /// Adds another double-double one part at a time, slower than `add_dd`
+    /// but keeping its digits when the two nearly cancel.
+    pub(crate) fn add_parts(self, other: Self) -> Self {
+        self.add_f64(other.hi).add_f64(other.lo)
+    }
+
+    pub fn sub_dd(self, other: Self) -> Self {
+        self.add_parts(-other)
+    }
+
+    pub fn mul_f64(self, value: f64) -> Self {
+        let (product, error) = two_prod(self.hi, value);
+        Self::from(product).add_f64(error + self.lo * value)
+    }
+
+    pub fn mul_dd(self, other: Self) -> Self {
+        let (product, error) = two_prod(self.hi, other.hi);
+        Self::from(product).add_f64(error + (self.hi * other.lo + self.lo * other.hi))
+    }
+
+    /// Divides by an `f64`.
+    pub fn div_f64(self, value: f64) -> Self {
+        let q = self.hi / value;
+        let (product, error) = two_prod(q, value);
+        let remainder = ((self.hi - product) - error + self.lo) / value;
+        let (hi, lo) = fast_two_sum(q, remainder);
+        Self { hi, lo }
+    }
+
+    /// Divides by another double-double, by one Newton step from the `f64`
+    /// quotient.
+    pub fn div_dd(self, other: Self) -> Self {
+        let q = Self::from(self.hi / other.hi);
+        let remainder = self.sub_dd(q.mul_dd(other)).to_f64();
+        q.add_f64(remainder / other.hi)
+    }
+
+    /// Square root, by one Newton step from the `f64` root. Zero for
+    /// numbers that are not positive.
+    pub fn sqrt(self) -> Self {
+        if self.hi <= 0.0 {
+            return Self::default();
+        }
+        let s = Self::from(self.hi.sqrt());
+        let remainder = self.sub_dd(s.mul_dd(s)).to_f64();
+        s.add_f64(remainder / (2.0 * s.hi))
+    }
+
+    /// Sine and cosine of an angle in radians. The angle is split into a
+    /// multiple of a quarter turn and a remainder within an eighth of a
+    /// turn, whose Taylor series converge fast, so large angles lose only
+    /// the digits of the multiple.
+    pub fn sin_cos(self) -> (Self, Self) {
+        let half_pi = Self::PI.div_f64(2.0);
+        let quarters = (self.hi / half_pi.hi).round();
+        let r = self.sub_dd(half_pi.mul_dd(Self::from(quarters)));
+
+        let r2 = r.mul_dd(r);
+        let (mut sin, mut cos) = (Self::default(), Self::default());
+        let (mut sin_term, mut cos_term) = (r, Self::from(1.0));
+        for n in 1..=15 {
+            sin = sin.add_parts(sin_term);
+            cos = cos.add_parts(cos_term);
+            let k = 2.0 * n as f64;
+            sin_term = -sin_term.mul_dd(r2).div_f64(k * (k + 1.0));
+            cos_term = -cos_term.mul_dd(r2).div_f64((k - 1.0) * k);
+        }
+        match (quarters as i64).rem_euclid(4) {
+            0 => (sin, cos),
+            1 => (cos, -sin),
+            2 => (-sin, -cos),
+            _ => (-cos, sin),
+        }
+    }
+
+    /// Angle of `(x, y)`, with `self` as `y`, by Newton steps from the
+    /// `f64` angle. At the origin, where there is nothing to refine, that
+    /// is the `f64` angle, as for `f64::atan2`.
+    pub fn atan2(self, x: Self) -> Self {
+        let y = self;
+        let mut angle = Self::from(y.hi.atan2(x.hi));
+        for _ in 0..2 {
+            let (sin, cos) = angle.sin_cos();
+            let across = y.mul_dd(cos).sub_dd(x.mul_dd(sin));
+            let along = x.mul_dd(cos).add_parts(y.mul_dd(sin));
+            if along.hi == 0.0 {
+                break;
+            }
+            angle = angle.add_parts(across.div_dd(along));
+        }
+        angle
+    }
+
+    /// Rounds to the nearest `f64`.
+    pub fn to_f64(self) -> f64 {
+        self.hi + self.lo
+    }
+}
+
+impl Neg for DoubleDouble {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self {
+            hi: -self.hi,
+            lo: -self.lo,
+        }
+    }
+}
+
+impl From<f64> for DoubleDouble {
+    fn from(value: f64) -> Self {
+        Self { hi: value, lo: 0.0 }
+    }
+}
+
+/// Sum of `a` and `b` with the exact rounding error of the sum.
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let sum = a + b;
+    let b_virtual = sum - a;
+    let a_virtual = sum - b_virtual;
+    (sum, (a - a_virtual) + (b - b_virtual))
+}
+
+/// Like `two_sum`, but requires `|a| >= |b|`.
+fn fast_two_sum(a: f64, b: f64) -> (f64, f64) {
+    let sum = a + b;
+    (sum, b - (sum - a))
+}
+
+/// Product of `a` and `b` with the exact rounding error of the product.
+fn two_prod(a: f64, b: f64) -> (f64, f64) {
+    let product = a * b;
+    (product, a.mul_add(b, -product))
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,64 @@
+use super::DoubleDouble;
+
+fn close(expected: DoubleDouble, actual: DoubleDouble, tolerance: f64) -> bool {
+    actual.sub_dd(expected).to_f64().abs() <= tolerance * expected.to_f64().abs().max(1.0)
+}
+
+#[test]
+fn test_add_keeps_rounding_error() {
+    let sum = DoubleDouble::from(1.0).add_f64(1e-20);
+    assert_eq!(1.0, sum.hi);
+    assert_eq!(1e-20, sum.lo);
+    assert_eq!(1e-20, sum.sub_dd(DoubleDouble::from(1.0)).to_f64());
+    let twice = sum.add_dd(sum);
+    assert_eq!((2.0, 2e-20), (twice.hi, twice.lo));
+}
+
+#[test]
+fn test_mul_div_round_trip() {
+    let third = DoubleDouble::from(1.0).div_f64(3.0);
+    assert!(close(DoubleDouble::from(1.0), third.mul_f64(3.0), 1e-31));
+    let x = DoubleDouble::from(2.0).sqrt();
+    assert!(close(DoubleDouble::from(2.0), x.mul_dd(x), 1e-31));
+    assert!(close(x, DoubleDouble::from(2.0).div_dd(x), 1e-31));
+    assert_eq!(DoubleDouble::default(), DoubleDouble::from(-1.0).sqrt());
+}
+
+#[test]
+fn test_sin_cos() {
+    let (sin, cos) = DoubleDouble::PI.div_f64(6.0).sin_cos();
+    assert!(close(DoubleDouble::from(0.5), sin, 1e-30));
+    assert!(close(
+        DoubleDouble::from(3.0).sqrt().div_f64(2.0),
+        cos,
+        1e-30
+    ));
+
+    let (sin, cos) = DoubleDouble::PI.mul_f64(2.5).sin_cos();
+    assert!(close(DoubleDouble::from(1.0), sin, 1e-30));
+    assert!(cos.to_f64().abs() < 1e-30);
+
+    for x in [-4.0, -0.3, 0.0, 0.7, 2.0, 100.0] {
+        let (sin, cos) = DoubleDouble::from(x).sin_cos();
+        assert!((sin.to_f64() - x.sin()).abs() < 1e-15, "{x}");
+        assert!((cos.to_f64() - x.cos()).abs() < 1e-15, "{x}");
+        let one = sin.mul_dd(sin).add_dd(cos.mul_dd(cos));
+        assert!(close(DoubleDouble::from(1.0), one, 1e-30), "{x}");
+    }
+}
+
+#[test]
+fn test_atan2() {
+    let one = DoubleDouble::from(1.0);
+    assert!(close(DoubleDouble::PI.div_f64(4.0), one.atan2(one), 1e-31));
+    assert!(close(
+        DoubleDouble::PI.mul_f64(0.75),
+        one.atan2(-one),
+        1e-31
+    ));
+    let (sin, cos) = DoubleDouble::from(0.4).sin_cos();
+    assert!(close(DoubleDouble::from(0.4), sin.atan2(cos), 1e-31));
+    let zero = DoubleDouble::from(0.0);
+    assert_eq!(DoubleDouble::from(0.0), zero.atan2(zero));
+    assert!(close(-DoubleDouble::PI, (-zero).atan2(-one), 1e-31));
+}
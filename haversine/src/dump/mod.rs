@@ -0,0 +1,149 @@
+//! Dump of the tokens of a JSON document, one per line with its span, to
+//! debug the tokenizer on inputs it gets wrong.
+
+use crate::Result;
+use crate::parse::{BOM, Span, Token, Tokenizer, Value};
+use std::io::Write;
+
+/// How each token is written.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DumpFormat {
+    /// The line and column, the byte range, the kind and the source text,
+    /// separated by tabs.
+    #[default]
+    Text,
+    /// An object of the same fields per line, as JSON Lines.
+    Json,
+}
+
+/// Name of the kind of a token in the dump.
+pub fn token_kind(token: &Token) -> &'static str {
+    match token {
+        Token::Eof => "eof",
+        Token::OpenBrace => "open-brace",
+        Token::CloseBrace => "close-brace",
+        Token::OpenBracket => "open-bracket",
+        Token::CloseBracket => "close-bracket",
+        Token::Comma => "comma",
+        Token::Colon => "colon",
+        Token::Null => "null",
+        Token::Bool(_) => "bool",
+        Token::String(_) => "string",
+        Token::Number(_) => "number",
+    }
+}
+
+/// Line and column, both starting at 1, of the offsets of the input, which
+/// must be asked for in increasing order so the input is scanned once.
+struct Position<'a> {
+    input: &'a str,
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Position<'a> {
+    /// A byte order mark takes no column, as in `Diagnostic::line_column`.
+    fn new(input: &'a str) -> Self {
+        let offset = if input.starts_with(BOM) {
+            BOM.len_utf8()
+        } else {
+            0
+        };
+        Self {
+            input,
+            offset,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn at(&mut self, offset: usize) -> (usize, usize) {
+        for c in self.input[self.offset..offset].chars() {
+            if c == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
+        self.offset = offset;
+        (self.line, self.column)
+    }
+}
+
+/// Writes the tokens of `input` to `output`, one per line, returning how
+/// many were written.
+///
+/// A tokenizer error is written as a last line with where it starts, so the
+/// dump shows the tokens read up to it, and then returned.
+///
+/// ```
+/// use haversine::dump::{DumpFormat, dump_tokens};
+///
+/// let mut output = Vec::new();
+/// assert_eq!(3, dump_tokens("[1.5]", DumpFormat::Text, &mut output).unwrap());
+/// let lines = String::from_utf8(output).unwrap();
+/// assert_eq!(Some("1:2\t1..4\tnumber\t1.5"), lines.lines().nth(1));
+/// ```
+pub fn dump_tokens(input: &str, format: DumpFormat, output: &mut impl Write) -> Result<usize> {
+    let mut position = Position::new(input);
+    let mut end = position.offset;
+    let mut written = 0;
+    for spanned in Tokenizer::new(input).skip_bom() {
+        match spanned {
+            Ok((token, span)) => {
+                let (line, column) = position.at(span.start);
+                let kind = token_kind(&token);
+                let source = span.source(input);
+                match format {
+                    DumpFormat::Text => writeln!(
+                        output,
+                        "{line}:{column}\t{}..{}\t{kind}\t{source}",
+                        span.start, span.end
+                    )?,
+                    DumpFormat::Json => {
+                        let value = located(line, column, span)
+                            .insert("kind", kind)
+                            .insert("source", source);
+                        writeln!(output, "{}", value.to_json())?;
+                    }
+                }
+                end = span.end;
+                written += 1;
+            }
+            Err(error) => {
+                // The error starts at the first character after the
+                // whitespace that follows the last token.
+                let rest = input[end..].trim_start_matches([' ', '\t', '\n', '\r']);
+                let start = input.len() - rest.len();
+                let (line, column) = position.at(start);
+                match format {
+                    DumpFormat::Text => {
+                        writeln!(output, "{line}:{column}\t{start}..\terror\t{error}")?
+                    }
+                    DumpFormat::Json => {
+                        let value = located(line, column, Span::new(start, start))
+                            .insert("kind", "error")
+                            .insert("error", error.to_string());
+                        writeln!(output, "{}", value.to_json())?;
+                    }
+                }
+                return Err(error.into());
+            }
+        }
+    }
+    Ok(written)
+}
+
+/// Object of the position fields of a line of the JSON dump.
+fn located(line: usize, column: usize, span: Span) -> Value {
+    Value::object()
+        .insert("line", line as u64)
+        .insert("column", column as u64)
+        .insert("start", span.start as u64)
+        .insert("end", span.end as u64)
+}
+
+#[cfg(test)]
+mod tests;
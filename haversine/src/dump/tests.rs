@@ -0,0 +1,54 @@
+use super::*;
+use crate::Error;
+use crate::parse::TokenizeError;
+
+fn dump(input: &str, format: DumpFormat) -> (Result<usize>, String) {
+    let mut output = Vec::new();
+    let result = dump_tokens(input, format, &mut output);
+    (result, String::from_utf8(output).unwrap())
+}
+
+#[test]
+fn test_dump_text() {
+    let (written, lines) = dump("{\"a\": [true,\n  null]}", DumpFormat::Text);
+    assert_eq!(9, written.unwrap());
+    let expected = [
+        "1:1\t0..1\topen-brace\t{",
+        "1:2\t1..4\tstring\t\"a\"",
+        "1:5\t4..5\tcolon\t:",
+        "1:7\t6..7\topen-bracket\t[",
+        "1:8\t7..11\tbool\ttrue",
+        "1:12\t11..12\tcomma\t,",
+        "2:3\t15..19\tnull\tnull",
+        "2:7\t19..20\tclose-bracket\t]",
+        "2:8\t20..21\tclose-brace\t}",
+    ];
+    assert_eq!(expected.join("\n") + "\n", lines);
+}
+
+#[test]
+fn test_dump_json() {
+    let (written, lines) = dump("\u{feff} 1e3", DumpFormat::Json);
+    assert_eq!(1, written.unwrap());
+    let value = crate::parse::parse(lines.trim_end()).unwrap().unwrap();
+    let expected = Value::object()
+        .insert("line", 1u64)
+        .insert("column", 2u64)
+        .insert("start", 4u64)
+        .insert("end", 7u64)
+        .insert("kind", "number")
+        .insert("source", "1e3");
+    assert_eq!(expected, value);
+}
+
+#[test]
+fn test_dump_error() {
+    let (result, lines) = dump("[1,\n  nul]", DumpFormat::Text);
+    assert!(matches!(
+        result,
+        Err(Error::Tokenize(TokenizeError::InvalidNull))
+    ));
+    let last = lines.lines().last().unwrap();
+    assert_eq!("2:3\t6..\terror\tinvalid literal, expected null", last);
+    assert_eq!(4, lines.lines().count());
+}
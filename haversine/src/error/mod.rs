@@ -0,0 +1,169 @@
+//! One error type for everything the library can fail at, so callers can
+//! use `?` across parsing, reading and pair extraction.
+
+use crate::checkpoint::CheckpointError;
+use crate::columnar::ColumnarError;
+use crate::extract::ExtractError;
+use crate::matrix::PointsError;
+use crate::pairs::{PairsError, StreamError};
+use crate::parse::{Encoding, ParsingError, TokenizeError, ValueError};
+use crate::pipeline::RunError;
+use crate::shard::ShardError;
+use std::{fmt, io};
+
+/// Result with the crate's `Error` by default.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Any error of the library. The variants wrap the errors of the modules,
+/// whose `From` conversions let `?` lift them into this type.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    Io(io::Error),
+    Tokenize(TokenizeError),
+    Parsing(ParsingError),
+    Value(ValueError),
+    Pairs(PairsError),
+    Points(PointsError),
+    Checkpoint(CheckpointError),
+    Shard(ShardError),
+    Columnar(ColumnarError),
+    Extract(ExtractError),
+    Run(RunError),
+    /// The document is not an object.
+    NotAnObject,
+    /// A member of the document is missing where it is required, or is not
+    /// of its type.
+    MissingField(&'static str),
+    /// The input is in this encoding rather than UTF-8, and was not to be
+    /// transcoded.
+    Encoding(Encoding),
+    /// An error on a line of JSON Lines input, counted from 1.
+    Line(usize, Box<Error>),
+    /// A run was cancelled before it finished.
+    Cancelled,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(error) => write!(f, "{error}"),
+            Error::Tokenize(error) => write!(f, "{error}"),
+            Error::Parsing(error) => write!(f, "{error}"),
+            Error::Value(error) => write!(f, "{error}"),
+            Error::Pairs(error) => write!(f, "{error}"),
+            Error::Points(error) => write!(f, "{error}"),
+            Error::Checkpoint(error) => write!(f, "{error}"),
+            Error::Shard(error) => write!(f, "{error}"),
+            Error::Columnar(error) => write!(f, "{error}"),
+            Error::Extract(error) => write!(f, "{error}"),
+            Error::Run(error) => write!(f, "{error}"),
+            Error::NotAnObject => write!(f, "the document is not an object"),
+            Error::MissingField(field) => write!(f, "missing or invalid field {field:?}"),
+            Error::Encoding(encoding) => write!(f, "the input is {encoding}, not UTF-8"),
+            Error::Line(line, error) => write!(f, "line {line}: {error}"),
+            Error::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    /// The wrapped error is formatted by `Display` already, so only its own
+    /// source is returned.
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(error) => error.source(),
+            Error::Checkpoint(error) => error.source(),
+            Error::Columnar(error) => error.source(),
+            Error::Run(error) => error.source(),
+            Error::Line(_, error) => error.source(),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl From<TokenizeError> for Error {
+    fn from(error: TokenizeError) -> Self {
+        Error::Tokenize(error)
+    }
+}
+
+impl From<ParsingError> for Error {
+    fn from(error: ParsingError) -> Self {
+        match error {
+            ParsingError::Cancelled => Error::Cancelled,
+            error => Error::Parsing(error),
+        }
+    }
+}
+
+impl From<ValueError> for Error {
+    fn from(error: ValueError) -> Self {
+        Error::Value(error)
+    }
+}
+
+impl From<CheckpointError> for Error {
+    fn from(error: CheckpointError) -> Self {
+        Error::Checkpoint(error)
+    }
+}
+
+impl From<PairsError> for Error {
+    fn from(error: PairsError) -> Self {
+        Error::Pairs(error)
+    }
+}
+
+impl From<PointsError> for Error {
+    fn from(error: PointsError) -> Self {
+        Error::Points(error)
+    }
+}
+
+impl From<ShardError> for Error {
+    fn from(error: ShardError) -> Self {
+        Error::Shard(error)
+    }
+}
+
+impl From<ColumnarError> for Error {
+    fn from(error: ColumnarError) -> Self {
+        Error::Columnar(error)
+    }
+}
+
+impl From<ExtractError> for Error {
+    fn from(error: ExtractError) -> Self {
+        Error::Extract(error)
+    }
+}
+
+impl From<RunError> for Error {
+    fn from(error: RunError) -> Self {
+        Error::Run(error)
+    }
+}
+
+impl From<StreamError> for Error {
+    /// Unwraps the error of the stage the stream failed at.
+    fn from(error: StreamError) -> Self {
+        match error {
+            StreamError::Io(error) => Error::Io(error),
+            StreamError::Parsing(error) => Error::Parsing(error),
+            StreamError::Pairs(error) => Error::Pairs(error),
+            StreamError::NotAnObject => Error::NotAnObject,
+            StreamError::Line(line, error) => Error::Line(line, Box::new((*error).into())),
+            StreamError::Cancelled => Error::Cancelled,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,57 @@
+use super::{Error, Result};
+use crate::pairs::{Pairs, PairsReader};
+use crate::parse::{Value, parse};
+
+/// Reads the first longitude of a pairs array, failing through `?` on
+/// every kind of error.
+fn first_x0(input: &str) -> Result<f64> {
+    let Some(Value::Array(values)) = parse(input)? else {
+        return Err(Error::NotAnObject);
+    };
+    Pairs::from_values(&values)?;
+    Ok((&values[0]["x0"]).try_into()?)
+}
+
+#[test]
+fn test_question_mark_converts() {
+    let pairs = r#"[{"x0": 1, "y0": 0, "x1": 0, "y1": 0}, {"x0": 3, "y0": 0, "x1": 0, "y1": 0}]"#;
+    assert_eq!(1.0, first_x0(pairs).unwrap());
+    assert!(matches!(first_x0("[tru]"), Err(Error::Parsing(_))));
+    assert!(matches!(first_x0(r#"[{"x0": 1}]"#), Err(Error::Pairs(_))));
+}
+
+#[test]
+fn test_display() {
+    let error = first_x0(r#"{"a": {"b": tru}}"#).unwrap_err();
+    assert_eq!(
+        r#"invalid literal, expected true at "a"."b""#,
+        error.to_string()
+    );
+    let error = first_x0(r#"[{"x0": 1, "y0": 0, "x1": 0}]"#).unwrap_err();
+    assert_eq!(r#"expected a number at "pairs"[0]."y1""#, error.to_string());
+}
+
+#[test]
+fn test_from_stream_error() {
+    let read = |input: &str| -> Result<usize> {
+        let (_, pairs) = PairsReader::new().read(input.as_bytes())?;
+        Ok(pairs.len())
+    };
+    assert_eq!(0, read(r#"{"pairs": []}"#).unwrap());
+    assert!(matches!(read("[]"), Err(Error::NotAnObject)));
+    assert!(matches!(read(r#"{"pairs": [1]}"#), Err(Error::Pairs(_))));
+    assert!(matches!(
+        read(r#"{"pairs": [tru]}"#),
+        Err(Error::Parsing(_))
+    ));
+}
+
+#[test]
+fn test_from_line_error() {
+    let error: Error = PairsReader::new()
+        .read_lines("\n{\"x0\": 1}".as_bytes())
+        .unwrap_err()
+        .into();
+    assert!(matches!(&error, Error::Line(2, inner) if matches!(**inner, Error::Pairs(_))));
+    assert_eq!(r#"line 2: expected a number at "y0""#, error.to_string());
+}
@@ -0,0 +1,331 @@
+//! Extraction of one value of a document, or of a range of the elements of
+//! an array, for carving test cases out of large datasets. The document is
+//! read in chunks and the extract written as its events arrive, so neither
+//! is held in memory, and reading stops once the extract is complete.
+
+use crate::Result;
+use crate::parse::{Event, FeedParser, Value, unescape};
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::ops::Range;
+
+/// Size of the chunks read from the input.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum ExtractError {
+    /// The path is not a JSON pointer, or its range is not `START..END`.
+    InvalidPath(String),
+    /// The document has no value at the path.
+    NotFound(String),
+    /// The value at a path with a range is not an array.
+    NotAnArray(String),
+}
+
+impl fmt::Display for ExtractError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtractError::InvalidPath(path) => write!(f, "invalid path {path:?}"),
+            ExtractError::NotFound(path) => write!(f, "no value at {path:?}"),
+            ExtractError::NotAnArray(path) => write!(f, "no array at {path:?}"),
+        }
+    }
+}
+
+impl std::error::Error for ExtractError {}
+
+/// Location of an extract: a JSON pointer such as `/pairs/3`, whose last
+/// segment may instead be a range of indices such as `1000..2000`, with
+/// either end left out for the start or the end of the array.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selector {
+    path: String,
+    segments: Vec<String>,
+    range: Option<Range<usize>>,
+}
+
+impl Selector {
+    pub fn parse(path: &str) -> Result<Self, ExtractError> {
+        let invalid = || ExtractError::InvalidPath(path.into());
+        let mut segments: Vec<_> = match path.strip_prefix('/') {
+            Some(segments) => segments.split('/').map(unescape).collect(),
+            None if path.is_empty() => Vec::new(),
+            None => return Err(invalid()),
+        };
+        let range = match segments.last().and_then(|last| last.split_once("..")) {
+            Some((start, end)) => {
+                let start = match start {
+                    "" => 0,
+                    start => start.parse().map_err(|_| invalid())?,
+                };
+                let end = match end {
+                    "" => usize::MAX,
+                    end => end.parse().map_err(|_| invalid())?,
+                };
+                if start > end {
+                    return Err(invalid());
+                }
+                segments.pop();
+                Some(start..end)
+            }
+            None => None,
+        };
+        Ok(Self {
+            path: path.into(),
+            segments,
+            range,
+        })
+    }
+}
+
+/// An array or object open on the way through the document.
+struct Frame {
+    /// Whether the container is at a prefix of the selector's segments.
+    on_path: bool,
+    /// Whether the container is the array whose range is extracted.
+    is_target: bool,
+    /// Key of the current member, or index of the current element.
+    child: Child,
+}
+
+enum Child {
+    Key(Option<String>),
+    Index(usize),
+}
+
+/// Writes events as compact JSON.
+struct EventWriter<W> {
+    out: W,
+    /// Whether each open container has no members yet.
+    empty: Vec<bool>,
+    after_key: bool,
+}
+
+impl<W: Write> EventWriter<W> {
+    fn write(&mut self, event: &Event) -> io::Result<()> {
+        match event {
+            Event::EndObject | Event::EndArray => {
+                self.empty.pop();
+                let close = if *event == Event::EndObject {
+                    b"}"
+                } else {
+                    b"]"
+                };
+                return self.out.write_all(close);
+            }
+            _ if self.after_key => self.after_key = false,
+            _ => {
+                if let Some(empty) = self.empty.last_mut() {
+                    if !*empty {
+                        self.out.write_all(b",")?;
+                    }
+                    *empty = false;
+                }
+            }
+        }
+        match event {
+            Event::StartObject => {
+                self.empty.push(true);
+                self.out.write_all(b"{")
+            }
+            Event::StartArray => {
+                self.empty.push(true);
+                self.out.write_all(b"[")
+            }
+            Event::Key(key) => {
+                self.after_key = true;
+                write!(self.out, "{}:", Value::String(key.clone()).to_json())
+            }
+            Event::Null => self.out.write_all(b"null"),
+            Event::Bool(b) => write!(self.out, "{b}"),
+            Event::Number(n) => self
+                .out
+                .write_all(Value::Number(n.clone()).to_json().as_bytes()),
+            Event::String(s) => self
+                .out
+                .write_all(Value::String(s.clone()).to_json().as_bytes()),
+            Event::EndObject | Event::EndArray => unreachable!("Closed above"),
+        }
+    }
+}
+
+/// Follows the events of a document to the selected value.
+struct Extractor<'a, W> {
+    selector: &'a Selector,
+    writer: EventWriter<W>,
+    frames: Vec<Frame>,
+    /// Depth within the value being copied, if one is.
+    copying: Option<usize>,
+    /// Values written, the elements of the range if there is one.
+    written: usize,
+}
+
+impl<W: Write> Extractor<'_, W> {
+    /// Handles an event, returning whether the extract is complete.
+    fn event(&mut self, event: Event) -> Result<bool> {
+        if let Some(depth) = self.copying {
+            self.writer.write(&event)?;
+            let depth = match event {
+                Event::StartObject | Event::StartArray => depth + 1,
+                Event::EndObject | Event::EndArray => depth - 1,
+                _ => depth,
+            };
+            if depth > 0 {
+                self.copying = Some(depth);
+                return Ok(false);
+            }
+            return self.copied();
+        }
+
+        let depth = self.frames.len();
+        match event {
+            Event::Key(key) => {
+                if let Some(Frame { child, .. }) = self.frames.last_mut() {
+                    *child = Child::Key(Some(key));
+                }
+                return Ok(false);
+            }
+            Event::EndObject | Event::EndArray => {
+                let frame = self.frames.pop().expect("An open container");
+                if frame.is_target {
+                    self.writer.write(&Event::EndArray)?;
+                    return Ok(true);
+                }
+                if frame.on_path {
+                    return Err(ExtractError::NotFound(self.selector.path.clone()).into());
+                }
+                self.advance();
+                return Ok(false);
+            }
+            _ => {}
+        }
+
+        let segments = &self.selector.segments;
+        let (on_path, in_range) = match self.frames.last() {
+            None => (true, false),
+            Some(parent) => {
+                let on_path = parent.on_path
+                    && segments
+                        .get(depth - 1)
+                        .is_some_and(|segment| match &parent.child {
+                            Child::Key(key) => key.as_deref() == Some(segment),
+                            Child::Index(index) => segment.parse() == Ok(*index),
+                        });
+                let in_range = match (&parent.child, &self.selector.range) {
+                    (Child::Index(index), Some(range)) => parent.is_target && range.contains(index),
+                    _ => false,
+                };
+                (on_path, in_range)
+            }
+        };
+        let at_target = on_path && depth == segments.len();
+
+        if in_range || (at_target && self.selector.range.is_none()) {
+            self.copying = Some(0);
+            return self.event(event);
+        }
+        if at_target && event != Event::StartArray {
+            return Err(ExtractError::NotAnArray(self.selector.path.clone()).into());
+        }
+        let child = match event {
+            Event::StartObject => Child::Key(None),
+            Event::StartArray => Child::Index(0),
+            _ => {
+                self.advance();
+                return Ok(false);
+            }
+        };
+        if at_target {
+            self.writer.write(&Event::StartArray)?;
+            if self.selector.range.as_ref().is_some_and(Range::is_empty) {
+                self.writer.write(&Event::EndArray)?;
+                return Ok(true);
+            }
+        }
+        self.frames.push(Frame {
+            on_path,
+            is_target: at_target,
+            child,
+        });
+        Ok(false)
+    }
+
+    /// Ends the value being copied.
+    fn copied(&mut self) -> Result<bool> {
+        self.copying = None;
+        self.written += 1;
+        let Some(range) = &self.selector.range else {
+            return Ok(true);
+        };
+        let index = match self.frames.last() {
+            Some(Frame {
+                child: Child::Index(index),
+                ..
+            }) => *index,
+            _ => unreachable!("Elements of a range are in an array"),
+        };
+        self.advance();
+        if index + 1 >= range.end {
+            self.writer.write(&Event::EndArray)?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    fn finish(mut self) -> Result<usize> {
+        self.writer.out.flush()?;
+        Ok(self.written)
+    }
+
+    /// Moves past a complete value of the innermost container.
+    fn advance(&mut self) {
+        match self.frames.last_mut().map(|frame| &mut frame.child) {
+            Some(Child::Index(index)) => *index += 1,
+            Some(Child::Key(key)) => *key = None,
+            None => {}
+        }
+    }
+}
+
+/// Copies the value at `selector` in the document read from `reader` to
+/// `writer` as compact JSON, or the elements in its range as an array,
+/// returning the number of values copied. Only the document up to the end
+/// of the extract is read.
+pub fn extract(mut reader: impl Read, selector: &Selector, writer: impl Write) -> Result<usize> {
+    let mut parser = FeedParser::new().raw_numbers(true);
+    let mut extractor = Extractor {
+        selector,
+        writer: EventWriter {
+            out: writer,
+            empty: Vec::new(),
+            after_key: false,
+        },
+        frames: Vec::new(),
+        copying: None,
+        written: 0,
+    };
+    let mut chunk = vec![0; CHUNK_SIZE];
+    loop {
+        let n = match reader.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error.into()),
+        };
+        for event in parser.feed(&chunk[..n])? {
+            if extractor.event(event)? {
+                return extractor.finish();
+            }
+        }
+    }
+    for event in parser.finish()? {
+        if extractor.event(event)? {
+            return extractor.finish();
+        }
+    }
+    Err(ExtractError::NotFound(selector.path.clone()).into())
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,90 @@
+use super::*;
+use crate::Error;
+
+fn run(json: &str, path: &str) -> Result<(String, usize)> {
+    let selector = Selector::parse(path)?;
+    let mut out = Vec::new();
+    let written = extract(json.as_bytes(), &selector, &mut out)?;
+    Ok((String::from_utf8(out).unwrap(), written))
+}
+
+const DOCUMENT: &str = r#"{
+    "pairs": [{"x0": 1.50, "y0": 2}, {"x0": 3, "y0": [4, {"z": null}]}, {"x0": 5e0}, 6],
+    "a/b": {"c~d": "é\"", "e": []},
+    "radius": 6372.8
+}"#;
+
+#[test]
+fn test_extract_value() {
+    let (json, written) = run(DOCUMENT, "/pairs/1").unwrap();
+    assert_eq!(r#"{"x0":3,"y0":[4,{"z":null}]}"#, json);
+    assert_eq!(1, written);
+    assert_eq!("1.50", run(DOCUMENT, "/pairs/0/x0").unwrap().0);
+    assert_eq!(r#""é\"""#, run(DOCUMENT, "/a~1b/c~0d").unwrap().0);
+    assert_eq!("6372.8", run(DOCUMENT, "/radius").unwrap().0);
+    assert_eq!("[]", run(DOCUMENT, "/a~1b/e").unwrap().0);
+    let (json, _) = run(DOCUMENT, "").unwrap();
+    assert_eq!(
+        crate::parse::parse(DOCUMENT).unwrap(),
+        crate::parse::parse(&json).unwrap()
+    );
+}
+
+#[test]
+fn test_extract_range() {
+    let (json, written) = run(DOCUMENT, "/pairs/1..3").unwrap();
+    assert_eq!(r#"[{"x0":3,"y0":[4,{"z":null}]},{"x0":5e0}]"#, json);
+    assert_eq!(2, written);
+    assert_eq!(
+        (r#"[{"x0":5e0},6]"#.into(), 2),
+        run(DOCUMENT, "/pairs/2..").unwrap()
+    );
+    assert_eq!(
+        r#"[{"x0":1.50,"y0":2}]"#,
+        run(DOCUMENT, "/pairs/..1").unwrap().0
+    );
+    assert_eq!(("[]".into(), 0), run(DOCUMENT, "/pairs/2..2").unwrap());
+    assert_eq!(("[]".into(), 0), run(DOCUMENT, "/pairs/9..").unwrap());
+    assert_eq!("[4]", run(DOCUMENT, "/pairs/1/y0/0..1").unwrap().0);
+}
+
+#[test]
+fn test_stops_after_extract() {
+    // The chunks past the extract are never read, so their errors go
+    // unnoticed.
+    let padding = "x".repeat(CHUNK_SIZE);
+    let json = format!(r#"{{"pairs": [1, 2, "{padding}", oops"#);
+    assert_eq!("[1,2]", run(&json, "/pairs/0..2").unwrap().0);
+    assert!(matches!(run(&json, "/pairs/0..4"), Err(Error::Parsing(_))));
+}
+
+#[test]
+fn test_errors() {
+    for path in ["pairs", "/pairs/2..1", "/pairs/x..", "/pairs/1..-1"] {
+        assert!(
+            matches!(
+                Selector::parse(path),
+                Err(ExtractError::InvalidPath(p)) if p == path
+            ),
+            "{path}"
+        );
+    }
+    let not_found = |path: &str| {
+        matches!(
+            run(DOCUMENT, path),
+            Err(Error::Extract(ExtractError::NotFound(p))) if p == path
+        )
+    };
+    assert!(not_found("/pairs/4"));
+    assert!(not_found("/missing"));
+    assert!(not_found("/radius/x"));
+    assert!(matches!(
+        run(DOCUMENT, "/radius/0..1"),
+        Err(Error::Extract(ExtractError::NotAnArray(_)))
+    ));
+    assert!(matches!(
+        run(DOCUMENT, "/a~1b/0..1"),
+        Err(Error::Extract(ExtractError::NotAnArray(_)))
+    ));
+    assert!(matches!(run("[1, ", "/1"), Err(Error::Parsing(_))));
+}
@@ -0,0 +1,456 @@
+//! Functions exported with the C ABI, for benchmark harnesses written in C
+//! or C++ to parse documents and compute distances with this crate. They
+//! are declared in `include/haversine.h`, and linked from a static library:
+//!
+//! ```text
+//! cargo rustc --lib --release --crate-type staticlib
+//! cc harness.c -Iinclude target/release/libhaversine.a -lm
+//! ```
+//!
+//! A document is parsed into a handle owning its values, which are then
+//! borrowed as `const haversine_value *` until the handle is released.
+//! Errors are returned as a null pointer or NaN, with the message read from
+//! `haversine_last_error`.
+//!
+//! The batch functions take the coordinates as four columns, which Python
+//! can pass from NumPy arrays through `ctypes` without copying them, from a
+//! shared library built with `--crate-type cdylib`:
+//!
+//! ```text
+//! lib = ctypes.CDLL("target/release/libhaversine.so")
+//! lib.haversine_batch.restype = ctypes.c_double
+//! ptr = lambda a: a.ctypes.data_as(ctypes.POINTER(ctypes.c_double))
+//! out = numpy.empty(len(x0))
+//! average = lib.haversine_batch(ptr(x0), ptr(y0), ptr(x1), ptr(y1),
+//!                               ctypes.c_size_t(len(x0)), ctypes.c_double(6372.8), ptr(out))
+//! ```
+
+use crate::compute::ComputeDriver;
+use crate::geo::{Kernel, haversine_distance as distance};
+use crate::pairs::Pairs;
+use crate::parse::{Parser, Value};
+use std::cell::RefCell;
+use std::ffi::{CString, c_char};
+
+thread_local! {
+    /// Message of the last error.
+    static ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+/// A parsed JSON document.
+pub struct Document {
+    root: Value,
+}
+
+/// Kind of a value, as returned by `haversine_value_kind`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueKind {
+    Null = 0,
+    Bool = 1,
+    Number = 2,
+    String = 3,
+    Array = 4,
+    Object = 5,
+}
+
+/// Largest number of `f64`s a slice can hold, of at most `isize::MAX`
+/// bytes.
+const MAX_NUMBERS: usize = isize::MAX as usize / size_of::<f64>();
+
+/// Records the message of an error for `haversine_last_error`.
+pub(crate) fn set_error(message: impl ToString) {
+    let message = message.to_string().replace('\0', "");
+    let message = CString::new(message).expect("No NUL bytes");
+    ERROR.with(|error| *error.borrow_mut() = message);
+}
+
+/// Calls `f` with the message of the last error.
+pub(crate) fn with_error<T>(f: impl FnOnce(&CString) -> T) -> T {
+    ERROR.with(|error| f(&error.borrow()))
+}
+
+/// The NUL-terminated message of the last error of the calling thread,
+/// valid until its next error.
+#[unsafe(no_mangle)]
+pub extern "C" fn haversine_last_error() -> *const c_char {
+    with_error(|error| error.as_ptr())
+}
+
+/// The great-circle distance of two points on a sphere of `radius`, with
+/// the longitude of each point before its latitude, in degrees.
+#[unsafe(no_mangle)]
+pub extern "C" fn haversine_distance(radius: f64, x0: f64, y0: f64, x1: f64, y1: f64) -> f64 {
+    distance(radius, x0, y0, x1, y1)
+}
+
+/// Computes the distances of `count` pairs of `coordinates`, four numbers
+/// `x0`, `y0`, `x1` and `y1` per pair, on a sphere of `radius`, and returns
+/// their average, NaN if there are none, if `coordinates` is null or if no
+/// buffer can hold `count` pairs. Each distance is also written to `out`
+/// unless it is null.
+///
+/// # Safety
+///
+/// `coordinates` must point to `4 * count` readable numbers, and `out` to
+/// room for `count` numbers or be null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn haversine_distance_buffer(
+    coordinates: *const f64,
+    count: usize,
+    radius: f64,
+    out: *mut f64,
+) -> f64 {
+    if count == 0 {
+        set_error("the buffer holds no pairs");
+        return f64::NAN;
+    }
+    let Some(numbers) = count.checked_mul(4).filter(|&n| n <= MAX_NUMBERS) else {
+        set_error(format!("no buffer can hold {count} pairs"));
+        return f64::NAN;
+    };
+    if coordinates.is_null() {
+        set_error("the buffer is null");
+        return f64::NAN;
+    }
+    // SAFETY: the caller guarantees four numbers per pair.
+    let coordinates = unsafe { std::slice::from_raw_parts(coordinates, numbers) };
+    let mut out = (!out.is_null()).then(|| {
+        // SAFETY: the caller guarantees room for a distance per pair.
+        unsafe { std::slice::from_raw_parts_mut(out, count) }
+    });
+    let mut sum = 0.0;
+    for (i, pair) in coordinates.chunks_exact(4).enumerate() {
+        let d = distance(radius, pair[0], pair[1], pair[2], pair[3]);
+        if let Some(out) = out.as_deref_mut() {
+            out[i] = d;
+        }
+        sum += d;
+    }
+    sum / count as f64
+}
+
+/// Computes the distances of `len` pairs held in the columns `x0`, `y0`,
+/// `x1` and `y1`, on a sphere of `radius`, and returns their average, NaN
+/// if there are none. Each distance is also written to `out` unless it is
+/// null.
+///
+/// # Safety
+///
+/// Each column must point to `len` readable numbers, and `out` to room for
+/// `len` numbers or be null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn haversine_batch(
+    x0: *const f64,
+    y0: *const f64,
+    x1: *const f64,
+    y1: *const f64,
+    len: usize,
+    radius: f64,
+    out: *mut f64,
+) -> f64 {
+    // SAFETY: the caller passes the columns and output of `len` pairs.
+    unsafe { haversine_batch_kernel(x0, y0, x1, y1, len, radius, 0, 1, out) }
+}
+
+/// Like `haversine_batch`, computing the distances with `kernel`, 0 for the
+/// haversine formula, 1 for cached sines and cosines, 2 for the dot
+/// product of unit vectors, 3 for fused multiply-adds, 4 for the fast
+/// formulation, 5 for Vincenty's formula and 6 for the spherical law of
+/// cosines, on `threads` threads, or as many as the machine runs in
+/// parallel for 0. Returns NaN for an unknown kernel.
+///
+/// # Safety
+///
+/// Each column must point to `len` readable numbers, and `out` to room for
+/// `len` numbers or be null.
+#[unsafe(no_mangle)]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn haversine_batch_kernel(
+    x0: *const f64,
+    y0: *const f64,
+    x1: *const f64,
+    y1: *const f64,
+    len: usize,
+    radius: f64,
+    kernel: u32,
+    threads: usize,
+    out: *mut f64,
+) -> f64 {
+    let kernel = match kernel {
+        0 => Kernel::Haversine,
+        1 => Kernel::CachedTrig,
+        2 => Kernel::Dot,
+        3 => Kernel::Fma,
+        4 => Kernel::FastMath,
+        5 => Kernel::Vincenty,
+        6 => Kernel::Cosine,
+        kernel => {
+            set_error(format!("unknown kernel {kernel}"));
+            return f64::NAN;
+        }
+    };
+    if len == 0 {
+        set_error("the columns hold no pairs");
+        return f64::NAN;
+    }
+    if len > MAX_NUMBERS {
+        set_error(format!("no column can hold {len} pairs"));
+        return f64::NAN;
+    }
+    // SAFETY: the caller guarantees `len` numbers per column.
+    let [x0, y0, x1, y1] =
+        [x0, y0, x1, y1].map(|column| unsafe { std::slice::from_raw_parts(column, len) });
+    let result = ComputeDriver::new(radius)
+        .kernel(kernel)
+        .threads(threads)
+        .keep_distances(!out.is_null())
+        .run_columns(x0, y0, x1, y1);
+    if !out.is_null() {
+        // SAFETY: the caller guarantees room for a distance per pair.
+        unsafe { std::slice::from_raw_parts_mut(out, len) }.copy_from_slice(&result.distances);
+    }
+    result.average
+}
+
+/// The average distance of the pairs of a document, the driver summing them
+/// as `compute` does, or NaN if the value is not a pairs document.
+///
+/// # Safety
+///
+/// `value` must be borrowed from a live document.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn haversine_value_average(value: *const Value, radius: f64) -> f64 {
+    // SAFETY: the caller passes a borrowed value.
+    let Some(Value::Array(values)) = member(unsafe { &*value }, "pairs") else {
+        set_error("expected an object with a pairs array");
+        return f64::NAN;
+    };
+    let pairs = match Pairs::from_values(values) {
+        Ok(pairs) if pairs.is_empty() => {
+            set_error("the pairs array is empty");
+            return f64::NAN;
+        }
+        Ok(pairs) => pairs,
+        Err(error) => {
+            set_error(error);
+            return f64::NAN;
+        }
+    };
+    ComputeDriver::new(radius).run(&pairs).average
+}
+
+/// Parses the JSON document of `len` UTF-8 bytes at `bytes`, returning a
+/// handle to be released with `haversine_document_free`, or null if it is
+/// not valid JSON.
+///
+/// # Safety
+///
+/// `bytes` must point to `len` readable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn haversine_document_parse(bytes: *const u8, len: usize) -> *mut Document {
+    // SAFETY: the caller guarantees `len` readable bytes.
+    let bytes = unsafe { std::slice::from_raw_parts(bytes, len) };
+    match parse(bytes) {
+        Ok(root) => Box::into_raw(Box::new(Document { root })),
+        Err(message) => {
+            set_error(message);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+fn parse(bytes: &[u8]) -> Result<Value, String> {
+    let text = std::str::from_utf8(bytes).map_err(|error| error.to_string())?;
+    match Parser::new(text).parse() {
+        Ok(Some(root)) => Ok(root),
+        Ok(None) => Err("the document is empty".into()),
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+/// Releases a document returned by `haversine_document_parse`, and with it
+/// every value borrowed from it.
+///
+/// # Safety
+///
+/// `document` must be a handle not yet released, or null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn haversine_document_free(document: *mut Document) {
+    if !document.is_null() {
+        // SAFETY: the handle was boxed by `haversine_document_parse`.
+        drop(unsafe { Box::from_raw(document) });
+    }
+}
+
+/// The root value of a document.
+///
+/// # Safety
+///
+/// `document` must be a live handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn haversine_document_root(document: *const Document) -> *const Value {
+    // SAFETY: the caller passes a live handle.
+    &unsafe { &*document }.root
+}
+
+/// The kind of a value.
+///
+/// # Safety
+///
+/// `value` must be borrowed from a live document.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn haversine_value_kind(value: *const Value) -> ValueKind {
+    // SAFETY: the caller passes a borrowed value.
+    match unsafe { &*value } {
+        Value::Null => ValueKind::Null,
+        Value::Bool(_) => ValueKind::Bool,
+        Value::Number(_) => ValueKind::Number,
+        Value::String(_) => ValueKind::String,
+        Value::Array(_) => ValueKind::Array,
+        Value::Object(_) => ValueKind::Object,
+    }
+}
+
+/// A number, or NaN for another kind of value.
+///
+/// # Safety
+///
+/// `value` must be borrowed from a live document.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn haversine_value_number(value: *const Value) -> f64 {
+    // SAFETY: the caller passes a borrowed value.
+    match unsafe { &*value } {
+        Value::Number(number) => number.as_f64(),
+        _ => f64::NAN,
+    }
+}
+
+/// A boolean as 1 or 0, or -1 for another kind of value.
+///
+/// # Safety
+///
+/// `value` must be borrowed from a live document.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn haversine_value_bool(value: *const Value) -> i32 {
+    // SAFETY: the caller passes a borrowed value.
+    match unsafe { &*value } {
+        Value::Bool(b) => i32::from(*b),
+        _ => -1,
+    }
+}
+
+/// The UTF-8 bytes of a string, not NUL-terminated, with their number
+/// written to `len`; null for another kind of value.
+///
+/// # Safety
+///
+/// `value` must be borrowed from a live document, and `len` must point to
+/// writable room.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn haversine_value_string(value: *const Value, len: *mut usize) -> *const u8 {
+    // SAFETY: the caller passes a borrowed value.
+    match unsafe { &*value } {
+        Value::String(string) => {
+            // SAFETY: the caller passes writable room for the length.
+            unsafe { *len = string.len() };
+            string.as_ptr()
+        }
+        _ => std::ptr::null(),
+    }
+}
+
+/// Number of elements of an array or members of an object, 0 for another
+/// kind of value.
+///
+/// # Safety
+///
+/// `value` must be borrowed from a live document.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn haversine_value_len(value: *const Value) -> usize {
+    // SAFETY: the caller passes a borrowed value.
+    match unsafe { &*value } {
+        Value::Array(values) => values.len(),
+        Value::Object(map) => map.len(),
+        _ => 0,
+    }
+}
+
+/// Element `index` of an array, or the value of member `index` of an object
+/// in the order of the document; null if there is none.
+///
+/// # Safety
+///
+/// `value` must be borrowed from a live document.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn haversine_value_index(value: *const Value, index: usize) -> *const Value {
+    // SAFETY: the caller passes a borrowed value.
+    let element = match unsafe { &*value } {
+        Value::Array(values) => values.get(index),
+        Value::Object(map) => map.get_index(index).map(|(_, value)| value),
+        _ => None,
+    };
+    element.map_or(std::ptr::null(), std::ptr::from_ref)
+}
+
+/// Key of member `index` of an object, as `haversine_value_string` returns
+/// a string; null if there is none.
+///
+/// # Safety
+///
+/// `value` must be borrowed from a live document, and `len` must point to
+/// writable room.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn haversine_value_key(
+    value: *const Value,
+    index: usize,
+    len: *mut usize,
+) -> *const u8 {
+    // SAFETY: the caller passes a borrowed value.
+    let Value::Object(map) = (unsafe { &*value }) else {
+        return std::ptr::null();
+    };
+    match map.get_index(index) {
+        Some((key, _)) => {
+            // SAFETY: the caller passes writable room for the length.
+            unsafe { *len = key.len() };
+            key.as_ptr()
+        }
+        None => std::ptr::null(),
+    }
+}
+
+/// The member of an object under the key of `key_len` UTF-8 bytes at
+/// `key`; null if there is none or the value is not an object.
+///
+/// # Safety
+///
+/// `value` must be borrowed from a live document, and `key` must point to
+/// `key_len` readable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn haversine_value_get(
+    value: *const Value,
+    key: *const u8,
+    key_len: usize,
+) -> *const Value {
+    // SAFETY: the caller guarantees `key_len` readable bytes.
+    let key = unsafe { std::slice::from_raw_parts(key, key_len) };
+    let Ok(key) = std::str::from_utf8(key) else {
+        return std::ptr::null();
+    };
+    // SAFETY: the caller passes a borrowed value.
+    match member(unsafe { &*value }, key) {
+        Some(member) => member,
+        None => std::ptr::null(),
+    }
+}
+
+fn member<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    match value {
+        Value::Object(map) => map.get(key),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,223 @@
+use super::*;
+use std::ffi::CStr;
+
+fn parse(json: &str) -> *mut Document {
+    // SAFETY: the string holds `json.len()` bytes.
+    unsafe { haversine_document_parse(json.as_ptr(), json.len()) }
+}
+
+fn last_error() -> String {
+    // SAFETY: the message stays valid until the next error.
+    unsafe { CStr::from_ptr(haversine_last_error()) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Reads the bytes and length returned by a string query.
+///
+/// # Safety
+///
+/// `f` must return null or `len` bytes valid for `'a`.
+unsafe fn string<'a>(f: impl FnOnce(*mut usize) -> *const u8) -> Option<&'a str> {
+    let mut len = 0;
+    let ptr = f(&mut len);
+    // SAFETY: the query returned `len` bytes.
+    (!ptr.is_null())
+        .then(|| std::str::from_utf8(unsafe { std::slice::from_raw_parts(ptr, len) }).unwrap())
+}
+
+#[test]
+fn test_query_values() {
+    let document = parse(r#"{"name": "pairs", "flags": [true, null], "n": 2.5}"#);
+    assert!(!document.is_null());
+    // SAFETY: the document is live until released at the end.
+    unsafe {
+        let root = haversine_document_root(document);
+        assert_eq!(ValueKind::Object, haversine_value_kind(root));
+        assert_eq!(3, haversine_value_len(root));
+        assert_eq!(
+            Some("flags"),
+            string(|len| haversine_value_key(root, 1, len))
+        );
+        assert!(string(|len| haversine_value_key(root, 3, len)).is_none());
+
+        let name = haversine_value_get(root, "name".as_ptr(), 4);
+        assert_eq!(ValueKind::String, haversine_value_kind(name));
+        assert_eq!(
+            Some("pairs"),
+            string(|len| haversine_value_string(name, len))
+        );
+        assert!(haversine_value_number(name).is_nan());
+        assert!(haversine_value_get(root, "x".as_ptr(), 1).is_null());
+
+        let n = haversine_value_index(root, 2);
+        assert_eq!(2.5, haversine_value_number(n));
+
+        let flags = haversine_value_get(root, "flags".as_ptr(), 5);
+        assert_eq!(ValueKind::Array, haversine_value_kind(flags));
+        assert_eq!(1, haversine_value_bool(haversine_value_index(flags, 0)));
+        let null = haversine_value_index(flags, 1);
+        assert_eq!(ValueKind::Null, haversine_value_kind(null));
+        assert_eq!(-1, haversine_value_bool(null));
+        assert!(haversine_value_index(flags, 2).is_null());
+        haversine_document_free(document);
+    }
+}
+
+#[test]
+fn test_distance_buffer() {
+    let coordinates = [0.0, 0.0, 90.0, 0.0, 10.0, 20.0, 30.0, 40.0];
+    let mut out = [0.0; 2];
+    // SAFETY: the buffers hold two pairs.
+    let average =
+        unsafe { haversine_distance_buffer(coordinates.as_ptr(), 2, 2.0, out.as_mut_ptr()) };
+    assert_eq!(distance(2.0, 0.0, 0.0, 90.0, 0.0), out[0]);
+    assert_eq!(haversine_distance(2.0, 10.0, 20.0, 30.0, 40.0), out[1]);
+    assert_eq!((out[0] + out[1]) / 2.0, average);
+    // SAFETY: the output may be null.
+    let without_out =
+        unsafe { haversine_distance_buffer(coordinates.as_ptr(), 2, 2.0, std::ptr::null_mut()) };
+    assert_eq!(average, without_out);
+
+    let document = parse(r#"{"pairs": [{"x0": 0, "y0": 0, "x1": 90, "y1": 0}]}"#);
+    // SAFETY: the document is live until released.
+    unsafe {
+        let average = haversine_value_average(haversine_document_root(document), 2.0);
+        assert!((out[0] - average).abs() < 1e-12);
+        haversine_document_free(document);
+    }
+}
+
+#[test]
+fn test_batch() {
+    let (x0, y0, x1, y1) = ([0.0, 10.0], [0.0, 20.0], [90.0, 30.0], [0.0, 40.0]);
+    let expected = [
+        distance(2.0, 0.0, 0.0, 90.0, 0.0),
+        distance(2.0, 10.0, 20.0, 30.0, 40.0),
+    ];
+    let mut out = [0.0; 2];
+    // SAFETY: the columns and output hold two pairs.
+    let average = unsafe {
+        haversine_batch(
+            x0.as_ptr(),
+            y0.as_ptr(),
+            x1.as_ptr(),
+            y1.as_ptr(),
+            2,
+            2.0,
+            out.as_mut_ptr(),
+        )
+    };
+    assert_eq!(expected, out);
+    assert_eq!((expected[0] + expected[1]) / 2.0, average);
+
+    for (kernel, threads) in [(0, 2), (1, 1), (1, 0)] {
+        // SAFETY: the columns hold two pairs, and the output may be null.
+        let average = unsafe {
+            haversine_batch_kernel(
+                x0.as_ptr(),
+                y0.as_ptr(),
+                x1.as_ptr(),
+                y1.as_ptr(),
+                2,
+                2.0,
+                kernel,
+                threads,
+                std::ptr::null_mut(),
+            )
+        };
+        assert!((average - (expected[0] + expected[1]) / 2.0).abs() < 1e-12);
+    }
+}
+
+#[test]
+fn test_errors() {
+    assert!(parse("{").is_null());
+    assert!(!last_error().is_empty());
+    assert!(parse("").is_null());
+    assert_eq!("the document is empty", last_error());
+    // SAFETY: no pairs are read.
+    assert!(
+        unsafe { haversine_distance_buffer(std::ptr::null(), 0, 1.0, std::ptr::null_mut()) }
+            .is_nan()
+    );
+    assert_eq!("the buffer holds no pairs", last_error());
+    let coordinates = [0.0; 4];
+    // The number of coordinates overflows, or is more than a slice can
+    // hold, so none are read.
+    for count in [usize::MAX / 2, usize::MAX / 8] {
+        // SAFETY: the count is rejected before the buffer is read.
+        let average = unsafe {
+            haversine_distance_buffer(coordinates.as_ptr(), count, 1.0, std::ptr::null_mut())
+        };
+        assert!(average.is_nan());
+        assert_eq!(format!("no buffer can hold {count} pairs"), last_error());
+    }
+    // SAFETY: a null buffer is rejected before it is read.
+    assert!(
+        unsafe { haversine_distance_buffer(std::ptr::null(), 1, 1.0, std::ptr::null_mut()) }
+            .is_nan()
+    );
+    assert_eq!("the buffer is null", last_error());
+    let column = [0.0];
+    // SAFETY: the column holds a pair, and the kernel is rejected first.
+    let average = unsafe {
+        haversine_batch_kernel(
+            column.as_ptr(),
+            column.as_ptr(),
+            column.as_ptr(),
+            column.as_ptr(),
+            1,
+            1.0,
+            7,
+            1,
+            std::ptr::null_mut(),
+        )
+    };
+    assert!(average.is_nan());
+    assert_eq!("unknown kernel 7", last_error());
+    let len = usize::MAX / 4;
+    // SAFETY: the length is rejected before the columns are read.
+    let average = unsafe {
+        haversine_batch_kernel(
+            column.as_ptr(),
+            column.as_ptr(),
+            column.as_ptr(),
+            column.as_ptr(),
+            len,
+            1.0,
+            0,
+            1,
+            std::ptr::null_mut(),
+        )
+    };
+    assert!(average.is_nan());
+    assert_eq!(format!("no column can hold {len} pairs"), last_error());
+
+    let document = parse("[1]");
+    // SAFETY: the document is live until released.
+    unsafe {
+        let root = haversine_document_root(document);
+        assert!(haversine_value_average(root, 1.0).is_nan());
+        assert_eq!("expected an object with a pairs array", last_error());
+        haversine_document_free(document);
+        haversine_document_free(std::ptr::null_mut());
+    }
+}
+
+#[test]
+fn test_header_declares_every_function() {
+    let header = include_str!("../../include/haversine.h");
+    let exported: Vec<_> = include_str!("mod.rs")
+        .lines()
+        .filter_map(|line| line.split(r#"extern "C" fn "#).nth(1))
+        .map(|rest| rest.split('(').next().unwrap())
+        .collect();
+    assert_eq!(17, exported.len(), "{exported:?}");
+    for name in exported {
+        let declared = [" ", "*"]
+            .iter()
+            .any(|before| header.contains(&format!("{before}{name}(")));
+        assert!(declared, "{name} is not in the header");
+    }
+}
@@ -0,0 +1,51 @@
+use super::{arbitrary_value, check_input, check_round_trip, run};
+use crate::parse::Value;
+use crate::rng::SplitMix64;
+
+#[test]
+fn test_randomized() {
+    run(0x4a53_4f4e, 2000);
+}
+
+#[test]
+fn test_generator_is_reproducible() {
+    let value = |seed| arbitrary_value(&mut SplitMix64::new(seed), 4);
+    assert_eq!(value(7), value(7));
+    assert_ne!(value(7), value(8));
+}
+
+#[test]
+fn test_round_trip_extreme_numbers() {
+    let numbers = [
+        0.0,
+        -0.0,
+        5e-324,
+        f64::MIN_POSITIVE,
+        f64::MAX,
+        f64::MIN,
+        0.1,
+    ];
+    check_round_trip(&Value::Array(numbers.map(Value::from).to_vec()));
+
+    let integers = [
+        Value::from(u64::MAX),
+        Value::from(i64::MIN),
+        Value::from(0u64),
+    ];
+    check_round_trip(&Value::Array(integers.to_vec()));
+}
+
+#[test]
+fn test_former_panics() {
+    for input in [
+        &b"{\"a\":"[..],
+        b"[1,",
+        b"[",
+        b"{\"a\"",
+        b"\"\\u",
+        b"\"\\ud800\\u",
+    ] {
+        check_input(input);
+    }
+    check_input(&[b'['; 10_000]);
+}
@@ -0,0 +1,196 @@
+//! Random pairs files, the input of `compute`.
+
+use crate::answers::Answers;
+use crate::checksum;
+use crate::compute::ComputeDriver;
+use crate::pairs::Pairs;
+use crate::parse::Value;
+use crate::rng::Rng;
+
+const LON_RANGE: (f64, f64) = (-180.0, 180.0);
+const LAT_RANGE: (f64, f64) = (-90.0, 90.0);
+
+/// How the points of the pairs are spread over the sphere.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Distribution {
+    /// Longitudes and latitudes drawn uniformly from their whole range.
+    Uniform,
+    /// Points drawn around `clusters` random centers, at most `spread`
+    /// degrees away from their center in longitude and in latitude, so the
+    /// distances are not uniformly distributed.
+    Clustered { clusters: usize, spread: f64 },
+}
+
+impl Distribution {
+    /// Clusters spread as far as the course's generator spreads them: the
+    /// more clusters, the tighter each one.
+    pub fn clustered(clusters: usize) -> Self {
+        Distribution::Clustered {
+            clusters,
+            spread: (LAT_RANGE.1 - LAT_RANGE.0) / clusters as f64,
+        }
+    }
+}
+
+/// Datasets of pairs at the edge cases of the distance formulas, whose
+/// expected results `Answers::reference` computes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    /// Each point and its antipode, half a turn apart.
+    Antipodal,
+    /// A point at a pole, of any longitude, and one anywhere, every other
+    /// one at a pole too.
+    Poles,
+    /// Points within a degree of the antimeridian, on either side of it.
+    Dateline,
+    /// The same point twice.
+    Identical,
+    /// Points between `1e-12` and `1e-6` degrees apart in longitude and
+    /// in latitude.
+    TinyDeltas,
+}
+
+impl Preset {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "antipodal" => Some(Preset::Antipodal),
+            "poles" => Some(Preset::Poles),
+            "dateline" => Some(Preset::Dateline),
+            "identical" => Some(Preset::Identical),
+            "tiny-deltas" => Some(Preset::TinyDeltas),
+            _ => None,
+        }
+    }
+}
+
+/// Draws `n` pairs of the preset.
+pub fn generate_preset(rng: &mut dyn Rng, n: usize, preset: Preset) -> Pairs {
+    let mut pairs = Pairs::with_capacity(n);
+    for i in 0..n {
+        let lon = rng.uniform(LON_RANGE.0, LON_RANGE.1);
+        let lat = rng.uniform(LAT_RANGE.0, LAT_RANGE.1);
+        match preset {
+            Preset::Antipodal => {
+                let antipode = if lon < 0.0 { lon + 180.0 } else { lon - 180.0 };
+                pairs.push(lon, lat, antipode, -lat);
+            }
+            Preset::Poles => {
+                let pole_lat = pole(rng);
+                let other_lat = if i % 2 == 0 { lat } else { pole(rng) };
+                let pole_lon = rng.uniform(LON_RANGE.0, LON_RANGE.1);
+                pairs.push(pole_lon, pole_lat, lon, other_lat);
+            }
+            Preset::Dateline => {
+                let east = LON_RANGE.1 - rng.uniform(0.0, 1.0);
+                let west = LON_RANGE.0 + rng.uniform(0.0, 1.0);
+                let other_lat = rng.uniform(LAT_RANGE.0, LAT_RANGE.1);
+                match i % 2 {
+                    0 => pairs.push(east, lat, west, other_lat),
+                    _ => pairs.push(west, lat, east, other_lat),
+                }
+            }
+            Preset::Identical => pairs.push(lon, lat, lon, lat),
+            Preset::TinyDeltas => {
+                let mut delta = || {
+                    let delta = 10f64.powf(rng.uniform(-12.0, -6.0));
+                    if rng.below(2) == 0 { delta } else { -delta }
+                };
+                let (delta_lon, delta_lat) = (delta(), delta());
+                // Kept within the poles, and within the range of longitudes.
+                let lat = lat.clamp(LAT_RANGE.0 + 1e-5, LAT_RANGE.1 - 1e-5);
+                let lon = lon.clamp(LON_RANGE.0 + 1e-5, LON_RANGE.1 - 1e-5);
+                pairs.push(lon, lat, lon + delta_lon, lat + delta_lat);
+            }
+        }
+    }
+    pairs
+}
+
+/// Latitude of either pole.
+fn pole(rng: &mut dyn Rng) -> f64 {
+    match rng.below(2) {
+        0 => LAT_RANGE.1,
+        _ => LAT_RANGE.0,
+    }
+}
+
+/// Draws `n` pairs. Clusters get an equal share of the pairs, and both
+/// points of a pair come from the same cluster.
+pub fn generate_pairs(rng: &mut dyn Rng, n: usize, distribution: Distribution) -> Pairs {
+    let mut pairs = Pairs::with_capacity(n);
+    match distribution {
+        Distribution::Uniform => {
+            for _ in 0..n {
+                let mut point = || {
+                    (
+                        rng.uniform(LON_RANGE.0, LON_RANGE.1),
+                        rng.uniform(LAT_RANGE.0, LAT_RANGE.1),
+                    )
+                };
+                let ((x0, y0), (x1, y1)) = (point(), point());
+                pairs.push(x0, y0, x1, y1);
+            }
+        }
+        Distribution::Clustered { clusters, spread } => {
+            let clusters = clusters.max(1);
+            for cluster in 0..clusters {
+                let lon = rng.uniform(LON_RANGE.0, LON_RANGE.1);
+                let lat = rng.uniform(LAT_RANGE.0, LAT_RANGE.1);
+                let mut point = || {
+                    let x = lon + rng.uniform(-spread, spread);
+                    let y = lat + rng.uniform(-spread, spread);
+                    (
+                        x.clamp(LON_RANGE.0, LON_RANGE.1),
+                        y.clamp(LAT_RANGE.0, LAT_RANGE.1),
+                    )
+                };
+                let share = n * (cluster + 1) / clusters - n * cluster / clusters;
+                for _ in 0..share {
+                    let ((x0, y0), (x1, y1)) = (point(), point());
+                    pairs.push(x0, y0, x1, y1);
+                }
+            }
+        }
+    }
+    pairs
+}
+
+/// Rounds the coordinates of `pairs` to `digits` decimal places, so they
+/// are written with at most that many and the average of the document is
+/// that of the pairs written.
+pub fn round_coordinates(pairs: &mut Pairs, digits: usize) {
+    for column in [&mut pairs.x0, &mut pairs.y0, &mut pairs.x1, &mut pairs.y1] {
+        for value in column {
+            *value = format!("{value:.digits$}").parse().expect("Formatted f64");
+        }
+    }
+}
+
+/// Builds the pairs document, with the average distance of the pairs on a
+/// sphere of `radius` and the checksum of the whole.
+pub fn document(pairs: &Pairs, radius: f64) -> Value {
+    document_with_average(pairs, radius, ComputeDriver::new(radius).run(pairs).average)
+}
+
+/// Builds the pairs document like `document`, with its answers.
+pub fn document_with_answers(pairs: &Pairs, radius: f64) -> (Value, Answers) {
+    let answers = Answers::compute(pairs, radius);
+    (
+        document_with_average(pairs, radius, answers.average),
+        answers,
+    )
+}
+
+/// Builds the pairs document like `document`, with a given average, such
+/// as that of `Answers::reference`.
+pub fn document_with_average(pairs: &Pairs, radius: f64, average: f64) -> Value {
+    let mut document = Value::object()
+        .insert("pairs", pairs.to_values())
+        .insert("avg_dist", average)
+        .insert("radius", radius);
+    checksum::seal(&mut document);
+    document
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,158 @@
+use super::{
+    Distribution, Preset, document, document_with_answers, generate_pairs, generate_preset,
+    round_coordinates,
+};
+use crate::answers::Answers;
+use crate::pairs::Pairs;
+use crate::parse::{Parser, Value};
+use crate::rng::Xoshiro256PlusPlus;
+
+fn range(values: &[f64]) -> f64 {
+    let max = values.iter().copied().fold(f64::MIN, f64::max);
+    let min = values.iter().copied().fold(f64::MAX, f64::min);
+    max - min
+}
+
+#[test]
+fn test_uniform_within_bounds() {
+    let pairs = generate_pairs(&mut Xoshiro256PlusPlus::new(1), 1000, Distribution::Uniform);
+    assert_eq!(1000, pairs.len());
+    for pair in pairs.iter() {
+        for point in [pair.p0, pair.p1] {
+            assert!((-180.0..180.0).contains(&point.lon), "{point:?}");
+            assert!((-90.0..90.0).contains(&point.lat), "{point:?}");
+        }
+    }
+    assert!(range(&pairs.x0) > 300.0);
+}
+
+#[test]
+fn test_single_cluster_within_spread() {
+    let distribution = Distribution::Clustered {
+        clusters: 1,
+        spread: 2.5,
+    };
+    let pairs = generate_pairs(&mut Xoshiro256PlusPlus::new(3), 500, distribution);
+    assert_eq!(500, pairs.len());
+    let lons = [pairs.x0.as_slice(), pairs.x1.as_slice()].concat();
+    let lats = [pairs.y0.as_slice(), pairs.y1.as_slice()].concat();
+    assert!(range(&lons) <= 5.0);
+    assert!(range(&lats) <= 5.0);
+}
+
+#[test]
+fn test_clusters_share_pairs() {
+    for clusters in [1, 3, 7, 20] {
+        let pairs = generate_pairs(
+            &mut Xoshiro256PlusPlus::new(5),
+            10,
+            Distribution::clustered(clusters),
+        );
+        assert_eq!(10, pairs.len(), "{clusters} clusters");
+    }
+}
+
+#[test]
+fn test_generation_is_reproducible() {
+    let pairs = |seed| {
+        generate_pairs(
+            &mut Xoshiro256PlusPlus::new(seed),
+            50,
+            Distribution::clustered(4),
+        )
+    };
+    assert_eq!(pairs(9), pairs(9));
+    assert_ne!(pairs(9), pairs(10));
+}
+
+#[test]
+fn test_document_reads_back() {
+    let pairs = generate_pairs(&mut Xoshiro256PlusPlus::new(2), 20, Distribution::Uniform);
+    let json = document(&pairs, 6372.8).to_json();
+    let parsed = Parser::new(&json).parse().unwrap().unwrap();
+    let Value::Array(values) = &parsed["pairs"] else {
+        panic!("Expected the pairs array");
+    };
+    assert_eq!(pairs, Pairs::from_values(values).unwrap());
+    assert_eq!(Value::from(6372.8), parsed["radius"]);
+    assert!(matches!(parsed["avg_dist"], Value::Number(_)));
+}
+
+#[test]
+fn test_seed_pins_coordinates() {
+    // Integer arithmetic and exact scaling make these the same everywhere.
+    let pairs = generate_pairs(&mut Xoshiro256PlusPlus::new(42), 1, Distribution::Uniform);
+    let json = document(&pairs, 1.0)["pairs"].to_json();
+    assert_eq!(
+        r#"[{"x0":113.14985224424754,"y0":-32.612212788901,"x1":174.20190054389593,"y1":36.204407664255996}]"#,
+        json
+    );
+}
+
+#[test]
+fn test_document_with_answers() {
+    let pairs = generate_pairs(&mut Xoshiro256PlusPlus::new(4), 50, Distribution::Uniform);
+    let (json, answers) = document_with_answers(&pairs, 6372.8);
+    assert_eq!(document(&pairs, 6372.8), json);
+    assert_eq!(Value::from(answers.average), json["avg_dist"]);
+    assert_eq!(pairs.len(), answers.distances.len());
+}
+
+#[test]
+fn test_round_coordinates() {
+    let mut pairs = Pairs::default();
+    pairs.push(12.345678, -0.0004, 179.99999, -89.5);
+    round_coordinates(&mut pairs, 3);
+    assert_eq!(
+        (12.346, -0.0, 180.0, -89.5),
+        pairs
+            .get(0)
+            .map(|pair| { (pair.p0.lon, pair.p0.lat, pair.p1.lon, pair.p1.lat) })
+            .unwrap()
+    );
+    let json = document(&pairs, 1.0)["pairs"].to_json();
+    assert!(json.contains(r#""x0":12.346"#), "{json}");
+}
+
+#[test]
+fn test_presets() {
+    let preset = |preset| {
+        let pairs = generate_preset(&mut Xoshiro256PlusPlus::new(6), 100, preset);
+        assert_eq!(100, pairs.len());
+        let answers = Answers::reference(&pairs, 1.0);
+        (pairs, answers.distances)
+    };
+
+    let (_, distances) = preset(Preset::Antipodal);
+    assert!(
+        distances.iter().all(|&d| d == std::f64::consts::PI),
+        "{distances:?}"
+    );
+    let (_, distances) = preset(Preset::Identical);
+    assert!(distances.iter().all(|&d| d == 0.0), "{distances:?}");
+
+    let (pairs, distances) = preset(Preset::Poles);
+    for (pair, distance) in pairs.iter().zip(distances) {
+        assert_eq!(90.0, pair.p0.lat.abs(), "{pair:?}");
+        let expected = (pair.p0.lat - pair.p1.lat).abs().to_radians();
+        assert!((distance - expected).abs() < 1e-15, "{pair:?}");
+    }
+
+    let (pairs, _) = preset(Preset::Dateline);
+    for pair in pairs.iter() {
+        assert!(pair.p0.lon.abs() >= 179.0 && pair.p1.lon.abs() >= 179.0);
+        assert_ne!(pair.p0.lon.signum(), pair.p1.lon.signum(), "{pair:?}");
+    }
+
+    let (pairs, distances) = preset(Preset::TinyDeltas);
+    for (pair, distance) in pairs.iter().zip(distances) {
+        assert!(distance > 0.0 && distance < 1e-7, "{pair:?}");
+        assert!((-90.0..=90.0).contains(&pair.p1.lat), "{pair:?}");
+    }
+}
+
+#[test]
+fn test_preset_from_name() {
+    assert_eq!(Some(Preset::TinyDeltas), Preset::from_name("tiny-deltas"));
+    assert_eq!(None, Preset::from_name("equator"));
+}
@@ -0,0 +1,60 @@
+use super::degrees_to_radians;
+use std::f64::consts::PI;
+
+/// Unit of the angles of the coordinates of a pairs file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AngleUnit {
+    #[default]
+    Degrees,
+    Radians,
+    /// A four-hundredth of a turn.
+    Gradians,
+}
+
+impl AngleUnit {
+    /// Parses `degrees`, `radians` or `gradians`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "degrees" => Some(AngleUnit::Degrees),
+            "radians" => Some(AngleUnit::Radians),
+            "gradians" => Some(AngleUnit::Gradians),
+            _ => None,
+        }
+    }
+
+    /// Half a turn in the unit, the largest longitude.
+    pub fn half_turn(self) -> f64 {
+        match self {
+            AngleUnit::Degrees => 180.0,
+            AngleUnit::Radians => PI,
+            AngleUnit::Gradians => 200.0,
+        }
+    }
+
+    /// Converts an angle in the unit to radians, as `degrees_to_radians`
+    /// does for degrees.
+    pub fn to_radians(self, angle: f64) -> f64 {
+        match self {
+            AngleUnit::Degrees => degrees_to_radians(angle),
+            AngleUnit::Radians => angle,
+            AngleUnit::Gradians => angle * PI / 200.0,
+        }
+    }
+
+    /// Converts an angle in the unit to degrees.
+    pub fn to_degrees(self, angle: f64) -> f64 {
+        match self {
+            AngleUnit::Degrees => angle,
+            _ => angle / self.half_turn() * 180.0,
+        }
+    }
+
+    /// Converts an angle in degrees to the unit.
+    pub fn from_degrees(self, angle: f64) -> f64 {
+        match self {
+            AngleUnit::Degrees => angle,
+            _ => angle / 180.0 * self.half_turn(),
+        }
+    }
+}
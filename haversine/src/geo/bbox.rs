@@ -0,0 +1,54 @@
+use super::Point;
+
+/// Region between two parallels and two meridians, in degrees. A box whose
+/// minimum longitude is larger than its maximum crosses the antimeridian.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min_lat: f64,
+    pub min_lon: f64,
+    pub max_lat: f64,
+    pub max_lon: f64,
+}
+
+impl BoundingBox {
+    /// Creates the box, or `None` if a latitude is outside `[-90, 90]`, a
+    /// longitude outside `[-180, 180]`, or the minimum latitude is above the
+    /// maximum.
+    pub fn new(min_lat: f64, min_lon: f64, max_lat: f64, max_lon: f64) -> Option<Self> {
+        let latitude = -90.0..=90.0;
+        let longitude = -180.0..=180.0;
+        let valid = latitude.contains(&min_lat)
+            && latitude.contains(&max_lat)
+            && longitude.contains(&min_lon)
+            && longitude.contains(&max_lon)
+            && min_lat <= max_lat;
+        valid.then_some(Self {
+            min_lat,
+            min_lon,
+            max_lat,
+            max_lon,
+        })
+    }
+
+    /// Parses `minLat,minLon,maxLat,maxLon`.
+    pub fn from_spec(spec: &str) -> Option<Self> {
+        let bounds: Vec<f64> = spec
+            .split(',')
+            .map(|bound| bound.trim().parse().ok())
+            .collect::<Option<_>>()?;
+        let [min_lat, min_lon, max_lat, max_lon] = bounds[..] else {
+            return None;
+        };
+        Self::new(min_lat, min_lon, max_lat, max_lon)
+    }
+
+    /// Whether the point is inside the box or on its edges.
+    pub fn contains(&self, point: Point) -> bool {
+        let lon = if self.min_lon <= self.max_lon {
+            self.min_lon <= point.lon && point.lon <= self.max_lon
+        } else {
+            self.min_lon <= point.lon || point.lon <= self.max_lon
+        };
+        lon && self.min_lat <= point.lat && point.lat <= self.max_lat
+    }
+}
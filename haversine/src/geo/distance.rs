@@ -0,0 +1,96 @@
+use super::{Point, UnitVector, cosine_distance, haversine_distance, vincenty_distance};
+
+/// A distance between two points given in degrees. The kernels of
+/// `ComputeDriver` are summed as distances, and `run_distance` sums any
+/// other, such as a closure taking the two points, in the same way.
+///
+/// ```
+/// use haversine::compute::ComputeDriver;
+/// use haversine::geo::{DistanceFn, Haversine, Point};
+/// use haversine::pairs::Pairs;
+///
+/// /// The length of the loxodrome, which crosses every meridian at the
+/// /// same angle, on a sphere of `radius`.
+/// struct RhumbLine {
+///     radius: f64,
+/// }
+///
+/// impl DistanceFn for RhumbLine {
+///     fn dist(&self, p0: Point, p1: Point) -> f64 {
+///         let (lat_0, lat_1) = (p0.lat.to_radians(), p1.lat.to_radians());
+///         let d_lat = lat_1 - lat_0;
+///         let d_lon = (p1.lon - p0.lon).to_radians();
+///         let stretched = ((lat_1 / 2.0 + std::f64::consts::FRAC_PI_4).tan()
+///             / (lat_0 / 2.0 + std::f64::consts::FRAC_PI_4).tan())
+///         .ln();
+///         let q = if stretched.abs() > 1e-12 { d_lat / stretched } else { lat_0.cos() };
+///         self.radius * (d_lat * d_lat + q * q * d_lon * d_lon).sqrt()
+///     }
+/// }
+///
+/// let mut pairs = Pairs::default();
+/// pairs.push_route(&[Point::new(0.0, 0.0), Point::new(0.0, 60.0)]);
+/// let driver = ComputeDriver::new(1.0);
+/// let rhumb = driver.run_distance(&pairs, &RhumbLine { radius: 1.0 }).average;
+/// let great_circle = driver.run_distance(&pairs, &Haversine { radius: 1.0 }).average;
+/// // Along a meridian, the loxodrome is the great circle.
+/// assert!((rhumb - great_circle).abs() < 1e-12);
+/// ```
+pub trait DistanceFn: Sync {
+    fn dist(&self, p0: Point, p1: Point) -> f64;
+}
+
+impl<F: Fn(Point, Point) -> f64 + Sync> DistanceFn for F {
+    fn dist(&self, p0: Point, p1: Point) -> f64 {
+        self(p0, p1)
+    }
+}
+
+/// `haversine_distance` on a sphere of `radius`, as `Kernel::Haversine`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Haversine {
+    pub radius: f64,
+}
+
+impl DistanceFn for Haversine {
+    fn dist(&self, p0: Point, p1: Point) -> f64 {
+        haversine_distance(self.radius, p0.lon, p0.lat, p1.lon, p1.lat)
+    }
+}
+
+/// `vincenty_distance` on a sphere of `radius`, as `Kernel::Vincenty`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Vincenty {
+    pub radius: f64,
+}
+
+impl DistanceFn for Vincenty {
+    fn dist(&self, p0: Point, p1: Point) -> f64 {
+        vincenty_distance(self.radius, p0.lon, p0.lat, p1.lon, p1.lat)
+    }
+}
+
+/// `cosine_distance` on a sphere of `radius`, as `Kernel::Cosine`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cosine {
+    pub radius: f64,
+}
+
+impl DistanceFn for Cosine {
+    fn dist(&self, p0: Point, p1: Point) -> f64 {
+        cosine_distance(self.radius, p0.lon, p0.lat, p1.lon, p1.lat)
+    }
+}
+
+/// `UnitVector::distance`, from the chord between the unit vectors of the
+/// points, on a sphere of `radius`, as `Kernel::CachedTrig`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Chord {
+    pub radius: f64,
+}
+
+impl DistanceFn for Chord {
+    fn dist(&self, p0: Point, p1: Point) -> f64 {
+        UnitVector::new(p0.lon, p0.lat).distance(&UnitVector::new(p1.lon, p1.lat), self.radius)
+    }
+}
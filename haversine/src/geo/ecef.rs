@@ -0,0 +1,30 @@
+use super::{UnitVector, radians_to_degrees};
+
+/// Converts a point given in degrees to Earth-centered, Earth-fixed
+/// coordinates on a sphere of the given radius: `x` towards longitude 0 on
+/// the equator, `y` towards longitude 90 and `z` towards the north pole.
+///
+/// ```
+/// use haversine::geo::{from_ecef, to_ecef};
+///
+/// let [x, y, z] = to_ecef(90.0, 0.0, 2.0);
+/// assert!(x.abs() < 1e-12 && (y - 2.0).abs() < 1e-12 && z == 0.0);
+/// let (phi, theta, radius) = from_ecef([x, y, z]);
+/// assert!((phi - 90.0).abs() < 1e-12 && theta == 0.0 && radius == 2.0);
+/// ```
+pub fn to_ecef(phi: f64, theta: f64, radius: f64) -> [f64; 3] {
+    let vector = UnitVector::new(phi, theta);
+    [radius * vector.x, radius * vector.y, radius * vector.z]
+}
+
+/// Converts Earth-centered, Earth-fixed coordinates back to `(phi, theta,
+/// radius)`, the angles in degrees and the radius the distance from the
+/// center. The origin has both angles 0.
+pub fn from_ecef([x, y, z]: [f64; 3]) -> (f64, f64, f64) {
+    let horizontal = x.hypot(y);
+    (
+        radians_to_degrees(y.atan2(x)),
+        radians_to_degrees(z.atan2(horizontal)),
+        horizontal.hypot(z),
+    )
+}
@@ -0,0 +1,529 @@
+use crate::math::{StdMath, Trig};
+
+mod angle;
+pub use angle::AngleUnit;
+
+mod bbox;
+pub use bbox::BoundingBox;
+
+mod distance;
+pub use distance::{Chord, Cosine, DistanceFn, Haversine, Vincenty};
+
+mod ecef;
+pub use ecef::{from_ecef, to_ecef};
+
+mod polyline;
+pub use polyline::{PolylineError, decode_polyline, encode_polyline};
+
+mod radius;
+pub use radius::{Body, DistanceUnit, RadiusModel};
+
+/// A point on the sphere, in degrees.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Point {
+    /// Longitude, the `x` of a pairs file.
+    pub lon: f64,
+    /// Latitude, the `y` of a pairs file.
+    pub lat: f64,
+}
+
+impl Point {
+    pub fn new(lon: f64, lat: f64) -> Self {
+        Self { lon, lat }
+    }
+}
+
+/// Wraps a longitude outside [-180, 180] into [-180, 180), the same
+/// meridian. Longitudes within the range, and those not finite, are
+/// returned unchanged.
+pub fn wrap_longitude(lon: f64) -> f64 {
+    wrap_longitude_in(AngleUnit::Degrees, lon)
+}
+
+/// `wrap_longitude` for a longitude in `unit`.
+pub fn wrap_longitude_in(unit: AngleUnit, lon: f64) -> f64 {
+    let half_turn = unit.half_turn();
+    match (-half_turn..=half_turn).contains(&lon) || !lon.is_finite() {
+        true => lon,
+        false => (lon + half_turn).rem_euclid(2.0 * half_turn) - half_turn,
+    }
+}
+
+/// How the distance of two points is computed.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum Kernel {
+    /// `haversine_distance`, with four sines or cosines and an arc sine per
+    /// pair.
+    #[default]
+    Haversine,
+    /// `UnitVector::distance`, with the sines and cosines taken once per
+    /// point and an arc sine per pair.
+    CachedTrig,
+    /// `UnitVector::dot_distance`, with the sines and cosines taken once per
+    /// point and an arc tangent per pair.
+    Dot,
+    /// `haversine_distance_fma`.
+    Fma,
+    /// `haversine_distance_fast`.
+    FastMath,
+    /// `vincenty_distance`, accurate for nearby and antipodal points alike.
+    Vincenty,
+    /// `cosine_distance`, which loses precision for nearby points.
+    Cosine,
+}
+
+impl Kernel {
+    /// Parses `haversine`, `cached-trig`, `dot`, `fma`, `fast-math`,
+    /// `vincenty` or `cosine`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "haversine" => Some(Kernel::Haversine),
+            "cached-trig" => Some(Kernel::CachedTrig),
+            "dot" => Some(Kernel::Dot),
+            "fma" => Some(Kernel::Fma),
+            "fast-math" => Some(Kernel::FastMath),
+            "vincenty" => Some(Kernel::Vincenty),
+            "cosine" => Some(Kernel::Cosine),
+            _ => None,
+        }
+    }
+}
+
+/// A point as the unit vector from the center of the sphere to it.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct UnitVector {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl UnitVector {
+    /// Converts a point given in degrees.
+    pub fn new(phi: f64, theta: f64) -> Self {
+        Self::from_radians(degrees_to_radians(phi), degrees_to_radians(theta))
+    }
+
+    /// Converts a point given in radians.
+    pub fn from_radians(phi: f64, theta: f64) -> Self {
+        let (sin_phi, cos_phi) = phi.sin_cos();
+        let (sin_theta, cos_theta) = theta.sin_cos();
+        Self {
+            x: cos_theta * cos_phi,
+            y: cos_theta * sin_phi,
+            z: sin_theta,
+        }
+    }
+
+    /// Great-circle distance on a sphere of the given radius. The haversine
+    /// of the angle between two points is a quarter of the squared chord
+    /// between them, so this is `haversine_distance` without its sines and
+    /// cosines.
+    pub fn distance(&self, other: &UnitVector, radius: f64) -> f64 {
+        let (dx, dy, dz) = (self.x - other.x, self.y - other.y, self.z - other.z);
+        let chord = (dx * dx + dy * dy + dz * dz).sqrt();
+        2.0 * radius * (chord / 2.0).min(1.0).asin()
+    }
+
+    /// Great-circle distance on a sphere of the given radius, from the dot
+    /// product of the vectors and the length of their cross product. Their
+    /// arc tangent keeps the angle accurate where the arc cosine of the dot
+    /// product alone would not, near 0 and half a turn.
+    pub fn dot_distance(&self, other: &UnitVector, radius: f64) -> f64 {
+        let dot = self.x * other.x + self.y * other.y + self.z * other.z;
+        let cross = (
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        );
+        let sin = (cross.0 * cross.0 + cross.1 * cross.1 + cross.2 * cross.2).sqrt();
+        radius * sin.atan2(dot)
+    }
+}
+
+pub fn degrees_to_radians(angle: f64) -> f64 {
+    angle * std::f64::consts::PI / 180.0
+}
+
+pub fn radians_to_degrees(angle: f64) -> f64 {
+    angle * 180.0 / std::f64::consts::PI
+}
+
+/// Calculates the great-circle distance between two points given in
+/// degrees, on a sphere of the given radius.
+pub fn haversine_distance(radius: f64, phi_0: f64, theta_0: f64, phi_1: f64, theta_1: f64) -> f64 {
+    haversine_distance_with(&StdMath, radius, phi_0, theta_0, phi_1, theta_1)
+}
+
+/// `haversine_distance` with the sines, cosines and arc sine of `math`.
+pub fn haversine_distance_with(
+    math: &impl Trig,
+    radius: f64,
+    phi_0: f64,
+    theta_0: f64,
+    phi_1: f64,
+    theta_1: f64,
+) -> f64 {
+    haversine_distance_radians_with(
+        math,
+        radius,
+        degrees_to_radians(phi_0),
+        degrees_to_radians(theta_0),
+        degrees_to_radians(phi_1),
+        degrees_to_radians(theta_1),
+    )
+}
+
+/// `haversine_distance_with` for points given in radians.
+pub fn haversine_distance_radians_with(
+    math: &impl Trig,
+    radius: f64,
+    phi_0: f64,
+    theta_0: f64,
+    phi_1: f64,
+    theta_1: f64,
+) -> f64 {
+    let delta_thetas = theta_1 - theta_0;
+    let delta_phis = phi_1 - phi_0;
+    let root_term_1 = math.sin(delta_thetas / 2.0).powi(2);
+    let root_term_2 = math.cos(theta_0) * math.cos(theta_1) * math.sin(delta_phis / 2.0).powi(2);
+    let root_term = root_term_1 + root_term_2;
+    2.0 * radius * math.asin(root_term.sqrt())
+}
+
+/// `haversine_distance` with fused multiply-adds, which round once where
+/// the plain formula rounds a product and then a sum. Faster where the CPU
+/// has FMA instructions and much slower where `mul_add` is emulated.
+pub fn haversine_distance_fma(
+    radius: f64,
+    phi_0: f64,
+    theta_0: f64,
+    phi_1: f64,
+    theta_1: f64,
+) -> f64 {
+    haversine_distance_fma_radians(
+        radius,
+        degrees_to_radians(phi_0),
+        degrees_to_radians(theta_0),
+        degrees_to_radians(phi_1),
+        degrees_to_radians(theta_1),
+    )
+}
+
+/// `haversine_distance_fma` for points given in radians.
+pub fn haversine_distance_fma_radians(
+    radius: f64,
+    phi_0: f64,
+    theta_0: f64,
+    phi_1: f64,
+    theta_1: f64,
+) -> f64 {
+    let sin_thetas = ((theta_1 - theta_0) / 2.0).sin();
+    let sin_phis = ((phi_1 - phi_0) / 2.0).sin();
+    let cosines = theta_0.cos() * theta_1.cos();
+    let root_term = sin_thetas.mul_add(sin_thetas, cosines * sin_phis * sin_phis);
+    // Rounding once can take the term of antipodal points just past 1.
+    2.0 * radius * root_term.sqrt().min(1.0).asin()
+}
+
+/// `haversine_distance` with the squared half-angle sines written as
+/// `(1 - cos x) / 2`, so its terms are independent cosines joined by a
+/// fused multiply-add. Fewer operations, but `1 - cos x` cancels for
+/// points a few meters apart or closer and loses their distance.
+pub fn haversine_distance_fast(
+    radius: f64,
+    phi_0: f64,
+    theta_0: f64,
+    phi_1: f64,
+    theta_1: f64,
+) -> f64 {
+    haversine_distance_fast_radians(
+        radius,
+        degrees_to_radians(phi_0),
+        degrees_to_radians(theta_0),
+        degrees_to_radians(phi_1),
+        degrees_to_radians(theta_1),
+    )
+}
+
+/// `haversine_distance_fast` for points given in radians.
+pub fn haversine_distance_fast_radians(
+    radius: f64,
+    phi_0: f64,
+    theta_0: f64,
+    phi_1: f64,
+    theta_1: f64,
+) -> f64 {
+    let cosines = theta_0.cos() * theta_1.cos();
+    let versine_thetas = 1.0 - (theta_1 - theta_0).cos();
+    let versine_phis = 1.0 - (phi_1 - phi_0).cos();
+    let root_term = 0.5 * cosines.mul_add(versine_phis, versine_thetas);
+    2.0 * radius * root_term.sqrt().min(1.0).asin()
+}
+
+/// Single-precision `haversine_distance`, for comparing the throughput and
+/// accuracy of `f32` arithmetic with `f64`.
+pub fn haversine_distance_f32(
+    radius: f32,
+    phi_0: f32,
+    theta_0: f32,
+    phi_1: f32,
+    theta_1: f32,
+) -> f32 {
+    let to_radians = std::f32::consts::PI / 180.0;
+    let delta_phis = (phi_1 - phi_0) * to_radians;
+    haversine_f32(
+        radius,
+        theta_0 * to_radians,
+        theta_1 * to_radians,
+        delta_phis,
+    )
+}
+
+/// `haversine_distance_f32` for points given in radians.
+pub fn haversine_distance_f32_radians(
+    radius: f32,
+    phi_0: f32,
+    theta_0: f32,
+    phi_1: f32,
+    theta_1: f32,
+) -> f32 {
+    haversine_f32(radius, theta_0, theta_1, phi_1 - phi_0)
+}
+
+fn haversine_f32(radius: f32, theta_0: f32, theta_1: f32, delta_phis: f32) -> f32 {
+    let delta_thetas = theta_1 - theta_0;
+    let root_term_1 = (delta_thetas / 2.0).sin().powi(2);
+    let root_term_2 = theta_0.cos() * theta_1.cos() * (delta_phis / 2.0).sin().powi(2);
+    let root_term = root_term_1 + root_term_2;
+    2.0 * radius * root_term.sqrt().asin()
+}
+
+/// Calculates the great-circle distance with the spherical law of cosines.
+/// Cheaper than `haversine_distance`, but it loses precision for points a
+/// few kilometers apart or closer.
+pub fn cosine_distance(radius: f64, phi_0: f64, theta_0: f64, phi_1: f64, theta_1: f64) -> f64 {
+    let theta_0 = degrees_to_radians(theta_0);
+    let theta_1 = degrees_to_radians(theta_1);
+    let delta_phis = degrees_to_radians(phi_1 - phi_0);
+    radius * cosine_angle(theta_0, theta_1, delta_phis)
+}
+
+/// `cosine_distance` for points given in radians.
+pub fn cosine_distance_radians(
+    radius: f64,
+    phi_0: f64,
+    theta_0: f64,
+    phi_1: f64,
+    theta_1: f64,
+) -> f64 {
+    radius * cosine_angle(theta_0, theta_1, phi_1 - phi_0)
+}
+
+fn cosine_angle(theta_0: f64, theta_1: f64, delta_phis: f64) -> f64 {
+    let cosine = theta_0.sin() * theta_1.sin() + theta_0.cos() * theta_1.cos() * delta_phis.cos();
+    cosine.clamp(-1.0, 1.0).acos()
+}
+
+/// Calculates the great-circle distance with Vincenty's formula for the
+/// sphere, which keeps its precision for both nearby and antipodal points.
+pub fn vincenty_distance(radius: f64, phi_0: f64, theta_0: f64, phi_1: f64, theta_1: f64) -> f64 {
+    let theta_0 = degrees_to_radians(theta_0);
+    let theta_1 = degrees_to_radians(theta_1);
+    let delta_phis = degrees_to_radians(phi_1 - phi_0);
+    radius * vincenty_angle(theta_0, theta_1, delta_phis)
+}
+
+/// `vincenty_distance` for points given in radians.
+pub fn vincenty_distance_radians(
+    radius: f64,
+    phi_0: f64,
+    theta_0: f64,
+    phi_1: f64,
+    theta_1: f64,
+) -> f64 {
+    radius * vincenty_angle(theta_0, theta_1, phi_1 - phi_0)
+}
+
+fn vincenty_angle(theta_0: f64, theta_1: f64, delta_phis: f64) -> f64 {
+    let (sin_0, cos_0) = theta_0.sin_cos();
+    let (sin_1, cos_1) = theta_1.sin_cos();
+    let (sin_delta, cos_delta) = delta_phis.sin_cos();
+    let y = (cos_1 * sin_delta).hypot(cos_0 * sin_1 - sin_0 * cos_1 * cos_delta);
+    let x = sin_0 * sin_1 + cos_0 * cos_1 * cos_delta;
+    y.atan2(x)
+}
+
+/// Calculates the angle, in radians, between two points given in degrees, as
+/// seen from the center of the sphere.
+pub fn angular_distance(phi_0: f64, theta_0: f64, phi_1: f64, theta_1: f64) -> f64 {
+    haversine_distance(1.0, phi_0, theta_0, phi_1, theta_1)
+}
+
+/// Calculates the bearing, in degrees clockwise from north within `[0, 360)`,
+/// at which the great circle from the first to the second point departs.
+pub fn initial_bearing(phi_0: f64, theta_0: f64, phi_1: f64, theta_1: f64) -> f64 {
+    let theta_0 = degrees_to_radians(theta_0);
+    let theta_1 = degrees_to_radians(theta_1);
+    let delta_phis = degrees_to_radians(phi_1 - phi_0);
+
+    let y = delta_phis.sin() * theta_1.cos();
+    let x = theta_0.cos() * theta_1.sin() - theta_0.sin() * theta_1.cos() * delta_phis.cos();
+    radians_to_degrees(y.atan2(x)).rem_euclid(360.0)
+}
+
+/// Calculates the bearing, in degrees clockwise from north within `[0, 360)`,
+/// at which the great circle from the first to the second point arrives.
+pub fn final_bearing(phi_0: f64, theta_0: f64, phi_1: f64, theta_1: f64) -> f64 {
+    (initial_bearing(phi_1, theta_1, phi_0, theta_0) + 180.0).rem_euclid(360.0)
+}
+
+/// Calculates the point at `fraction` of the way along the great circle from
+/// the first to the second point, as `(phi, theta)` in degrees.
+pub fn intermediate_point(
+    phi_0: f64,
+    theta_0: f64,
+    phi_1: f64,
+    theta_1: f64,
+    fraction: f64,
+) -> (f64, f64) {
+    let delta = haversine_distance(1.0, phi_0, theta_0, phi_1, theta_1);
+    if delta == 0.0 {
+        return (phi_0, theta_0);
+    }
+
+    let (phi_0, theta_0) = (degrees_to_radians(phi_0), degrees_to_radians(theta_0));
+    let (phi_1, theta_1) = (degrees_to_radians(phi_1), degrees_to_radians(theta_1));
+    let a = ((1.0 - fraction) * delta).sin() / delta.sin();
+    let b = (fraction * delta).sin() / delta.sin();
+
+    let x = a * theta_0.cos() * phi_0.cos() + b * theta_1.cos() * phi_1.cos();
+    let y = a * theta_0.cos() * phi_0.sin() + b * theta_1.cos() * phi_1.sin();
+    let z = a * theta_0.sin() + b * theta_1.sin();
+    let theta = z.atan2(x.hypot(y));
+    let phi = y.atan2(x);
+    (radians_to_degrees(phi), radians_to_degrees(theta))
+}
+
+/// Calculates the midpoint of the great-circle arc between two points, as
+/// `(phi, theta)` in degrees: the direction of the sum of their unit
+/// vectors. It is arbitrary for antipodal points, which have no single arc.
+pub fn midpoint(phi_0: f64, theta_0: f64, phi_1: f64, theta_1: f64) -> (f64, f64) {
+    let (a, b) = (
+        UnitVector::new(phi_0, theta_0),
+        UnitVector::new(phi_1, theta_1),
+    );
+    direction(a.x + b.x, a.y + b.y, a.z + b.z)
+}
+
+/// Calculates the spherical centroid of the points: the direction of the
+/// mean of their unit vectors. Returns `None` for no points or points that
+/// balance out, such as two antipodal ones.
+pub fn spherical_centroid(points: impl IntoIterator<Item = Point>) -> Option<Point> {
+    let (mut x, mut y, mut z, mut n) = (0.0, 0.0, 0.0, 0);
+    for point in points {
+        let vector = UnitVector::new(point.lon, point.lat);
+        (x, y, z, n) = (x + vector.x, y + vector.y, z + vector.z, n + 1);
+    }
+    let length = (x * x + y * y + z * z).sqrt();
+    if n == 0 || length <= 1e-12 * n as f64 {
+        return None;
+    }
+    let (phi, theta) = direction(x, y, z);
+    Some(Point::new(phi, theta))
+}
+
+/// Longitude and latitude in degrees of a nonzero vector.
+fn direction(x: f64, y: f64, z: f64) -> (f64, f64) {
+    (
+        radians_to_degrees(y.atan2(x)),
+        radians_to_degrees(z.atan2(x.hypot(y))),
+    )
+}
+
+/// Calculates the point reached by travelling `distance` along the great
+/// circle leaving the given point at `bearing` degrees clockwise from north,
+/// on a sphere of the given radius. Returns `(phi, theta)` in degrees, with
+/// `phi` within `[-180, 180)`.
+///
+/// The arguments are in the order of the other functions of this module,
+/// not `(lat, lon, bearing, distance, radius)`: the radius comes first, and
+/// the longitude `phi_0` before the latitude `theta_0`, as the point
+/// returned has its longitude first.
+///
+/// ```
+/// use haversine::geo::destination;
+/// use std::f64::consts::FRAC_PI_4;
+///
+/// // An eighth of the way round the unit sphere, north from longitude 10
+/// // on the equator.
+/// let (lon, lat) = destination(1.0, 10.0, 0.0, 0.0, FRAC_PI_4);
+/// assert!((lon - 10.0).abs() < 1e-9);
+/// assert!((lat - 45.0).abs() < 1e-9);
+/// ```
+pub fn destination(
+    radius: f64,
+    phi_0: f64,
+    theta_0: f64,
+    bearing: f64,
+    distance: f64,
+) -> (f64, f64) {
+    let phi_0 = degrees_to_radians(phi_0);
+    let theta_0 = degrees_to_radians(theta_0);
+    let bearing = degrees_to_radians(bearing);
+    let delta = distance / radius;
+
+    let theta = (theta_0.sin() * delta.cos() + theta_0.cos() * delta.sin() * bearing.cos()).asin();
+    let y = bearing.sin() * delta.sin() * theta_0.cos();
+    let x = delta.cos() - theta_0.sin() * theta.sin();
+    let phi = radians_to_degrees(phi_0 + y.atan2(x));
+    (
+        (phi + 180.0).rem_euclid(360.0) - 180.0,
+        radians_to_degrees(theta),
+    )
+}
+
+/// Sums the great-circle distances between consecutive points of a path.
+pub fn path_length(radius: f64, points: &[Point]) -> f64 {
+    points
+        .windows(2)
+        .map(|segment| {
+            let (from, to) = (segment[0], segment[1]);
+            haversine_distance(radius, from.lon, from.lat, to.lon, to.lat)
+        })
+        .sum()
+}
+
+/// Calculates the area of the polygon whose edges are the great-circle arcs
+/// between consecutive points of `ring`, and from the last back to the first,
+/// on a sphere of the given radius. The ring may repeat its first point at
+/// the end, and may wind either way; of the two regions it bounds, the
+/// smaller one is measured.
+pub fn spherical_polygon_area(ring: &[Point], radius: f64) -> f64 {
+    use std::f64::consts::PI;
+
+    // The spherical excess of the polygon, summed over the signed areas
+    // between each edge and the north pole. A ring winding around the pole
+    // sweeps a full turn of longitude, which counts the pole's hemisphere in
+    // those areas once too many.
+    let mut excess = 0.0;
+    let mut winding = 0.0;
+    for (i, from) in ring.iter().enumerate() {
+        let to = ring[(i + 1) % ring.len()];
+        let delta_lon = degrees_to_radians(to.lon - from.lon);
+        let delta_lon = (delta_lon + PI).rem_euclid(2.0 * PI) - PI;
+        let tan_0 = (degrees_to_radians(from.lat) / 2.0).tan();
+        let tan_1 = (degrees_to_radians(to.lat) / 2.0).tan();
+        excess += 2.0 * ((delta_lon / 2.0).tan() * (tan_0 + tan_1)).atan2(1.0 + tan_0 * tan_1);
+        winding += delta_lon;
+    }
+    if winding.abs() > PI {
+        excess -= 2.0 * PI * winding.signum();
+    }
+    let excess = excess.rem_euclid(4.0 * PI);
+    excess.min(4.0 * PI - excess) * radius * radius
+}
+
+pub(crate) mod reference;
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,102 @@
+use super::Point;
+use std::fmt;
+
+/// Why an encoded polyline could not be decoded.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum PolylineError {
+    /// A character outside `?` to `~` at the byte offset.
+    InvalidChar(usize),
+    /// The text ends inside a number, or after a latitude without its
+    /// longitude.
+    Truncated,
+    /// A number with more chunks than fit in 64 bits, at the byte offset.
+    Overflow(usize),
+}
+
+impl fmt::Display for PolylineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolylineError::InvalidChar(offset) => {
+                write!(f, "invalid polyline character at offset {offset}")
+            }
+            PolylineError::Truncated => write!(f, "truncated polyline"),
+            PolylineError::Overflow(offset) => {
+                write!(f, "polyline number too long at offset {offset}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PolylineError {}
+
+/// Decodes a polyline in Google's encoded format, whose coordinates are
+/// rounded to `precision` decimal digits, 5 for Google's own.
+pub fn decode_polyline(encoded: &str, precision: u32) -> Result<Vec<Point>, PolylineError> {
+    let factor = 10f64.powi(precision as i32);
+    let mut numbers = Vec::new();
+    let (mut value, mut shift) = (0i64, 0);
+    for (offset, byte) in encoded.bytes().enumerate() {
+        let chunk = match byte {
+            b'?'..=b'~' => i64::from(byte - b'?'),
+            _ => return Err(PolylineError::InvalidChar(offset)),
+        };
+        if shift > 60 {
+            return Err(PolylineError::Overflow(offset));
+        }
+        value |= (chunk & 0x1f) << shift;
+        shift += 5;
+        if chunk & 0x20 == 0 {
+            numbers.push(if value & 1 == 1 {
+                !(value >> 1)
+            } else {
+                value >> 1
+            });
+            (value, shift) = (0, 0);
+        }
+    }
+    if shift != 0 || numbers.len() % 2 != 0 {
+        return Err(PolylineError::Truncated);
+    }
+
+    // Every coordinate is the difference from the previous one.
+    let (mut lat, mut lon) = (0i64, 0i64);
+    let points = numbers
+        .chunks_exact(2)
+        .map(|delta| {
+            lat += delta[0];
+            lon += delta[1];
+            Point::new(lon as f64 / factor, lat as f64 / factor)
+        })
+        .collect();
+    Ok(points)
+}
+
+/// Encodes points in Google's polyline format, rounding the coordinates to
+/// `precision` decimal digits.
+pub fn encode_polyline(points: &[Point], precision: u32) -> String {
+    let factor = 10f64.powi(precision as i32);
+    let mut encoded = String::new();
+    let (mut previous_lat, mut previous_lon) = (0i64, 0i64);
+    for point in points {
+        let lat = (point.lat * factor).round() as i64;
+        let lon = (point.lon * factor).round() as i64;
+        encode_number(lat - previous_lat, &mut encoded);
+        encode_number(lon - previous_lon, &mut encoded);
+        (previous_lat, previous_lon) = (lat, lon);
+    }
+    encoded
+}
+
+fn encode_number(number: i64, encoded: &mut String) {
+    let mut value = if number < 0 {
+        !(number << 1)
+    } else {
+        number << 1
+    } as u64;
+    while value >= 0x20 {
+        encoded.push(char::from((0x20 | (value & 0x1f)) as u8 + b'?'));
+        value >>= 5;
+    }
+    encoded.push(char::from(value as u8 + b'?'));
+}
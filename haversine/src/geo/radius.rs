@@ -0,0 +1,136 @@
+/// Radius convention for the Earth.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum RadiusModel {
+    /// IUGG mean radius, `(2a + b) / 3`.
+    Mean,
+    /// WGS 84 semi-major axis.
+    Equatorial,
+    /// WGS 84 semi-minor axis.
+    Polar,
+    /// Radius of the sphere with the same surface area as the WGS 84 ellipsoid.
+    Authalic,
+    /// A radius in kilometers.
+    Custom(f64),
+}
+
+impl RadiusModel {
+    pub fn kilometers(self) -> f64 {
+        match self {
+            RadiusModel::Mean => 6371.0088,
+            RadiusModel::Equatorial => 6378.137,
+            RadiusModel::Polar => 6356.752314245,
+            RadiusModel::Authalic => 6371.0071809,
+            RadiusModel::Custom(radius) => radius,
+        }
+    }
+
+    /// Parses `mean`, `equatorial`, `polar`, `authalic` or `custom=X`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "mean" => Some(RadiusModel::Mean),
+            "equatorial" => Some(RadiusModel::Equatorial),
+            "polar" => Some(RadiusModel::Polar),
+            "authalic" => Some(RadiusModel::Authalic),
+            _ => {
+                let radius: f64 = name.strip_prefix("custom=")?.parse().ok()?;
+                (radius > 0.0).then_some(RadiusModel::Custom(radius))
+            }
+        }
+    }
+}
+
+/// A body of the solar system, approximated by a sphere of its mean
+/// radius.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum Body {
+    Mercury,
+    Venus,
+    #[default]
+    Earth,
+    Moon,
+    Mars,
+    Jupiter,
+    /// A sphere of a radius in kilometers.
+    Custom(f64),
+}
+
+impl Body {
+    /// Mean radius in kilometers, the IAU's for the planets and the Moon.
+    pub fn kilometers(self) -> f64 {
+        self.radius_model().kilometers()
+    }
+
+    /// The radius as a model, the IUGG mean radius for the Earth.
+    pub fn radius_model(self) -> RadiusModel {
+        match self {
+            Body::Mercury => RadiusModel::Custom(2439.4),
+            Body::Venus => RadiusModel::Custom(6051.8),
+            Body::Earth => RadiusModel::Mean,
+            Body::Moon => RadiusModel::Custom(1737.4),
+            Body::Mars => RadiusModel::Custom(3389.5),
+            Body::Jupiter => RadiusModel::Custom(69911.0),
+            Body::Custom(radius) => RadiusModel::Custom(radius),
+        }
+    }
+
+    /// Parses the lowercase name of a body or `custom=X`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "mercury" => Some(Body::Mercury),
+            "venus" => Some(Body::Venus),
+            "earth" => Some(Body::Earth),
+            "moon" => Some(Body::Moon),
+            "mars" => Some(Body::Mars),
+            "jupiter" => Some(Body::Jupiter),
+            _ => match RadiusModel::from_name(name)? {
+                RadiusModel::Custom(radius) => Some(Body::Custom(radius)),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// Unit the distances are expressed in.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum DistanceUnit {
+    #[default]
+    Kilometers,
+    Meters,
+    Miles,
+    NauticalMiles,
+}
+
+impl DistanceUnit {
+    /// Parses `km`, `m`, `mi` or `nmi`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "km" => Some(DistanceUnit::Kilometers),
+            "m" => Some(DistanceUnit::Meters),
+            "mi" => Some(DistanceUnit::Miles),
+            "nmi" => Some(DistanceUnit::NauticalMiles),
+            _ => None,
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            DistanceUnit::Kilometers => "km",
+            DistanceUnit::Meters => "m",
+            DistanceUnit::Miles => "mi",
+            DistanceUnit::NauticalMiles => "nmi",
+        }
+    }
+
+    /// Converts a distance in kilometers to this unit.
+    pub fn convert_kilometers(self, kilometers: f64) -> f64 {
+        match self {
+            DistanceUnit::Kilometers => kilometers,
+            DistanceUnit::Meters => kilometers * 1000.0,
+            DistanceUnit::Miles => kilometers / 1.609344,
+            DistanceUnit::NauticalMiles => kilometers / 1.852,
+        }
+    }
+}
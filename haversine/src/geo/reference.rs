@@ -0,0 +1,61 @@
+//! Great-circle distances in double-double arithmetic, against which the
+//! `f64` formulas are checked.
+
+use crate::dd::DoubleDouble;
+
+fn radians(degrees: f64) -> DoubleDouble {
+    DoubleDouble::PI
+        .mul_dd(DoubleDouble::from(degrees))
+        .div_f64(180.0)
+}
+
+/// Angle between two points given in degrees, rounded to an `f64`.
+#[cfg(test)]
+pub(super) fn angular_distance(phi_0: f64, theta_0: f64, phi_1: f64, theta_1: f64) -> f64 {
+    angular_distance_dd(phi_0, theta_0, phi_1, theta_1).to_f64()
+}
+
+/// Distance between two points on a sphere of `radius`, for the expected
+/// results of datasets that stress the `f64` formulas.
+pub(crate) fn distance(
+    radius: f64,
+    phi_0: f64,
+    theta_0: f64,
+    phi_1: f64,
+    theta_1: f64,
+) -> DoubleDouble {
+    angular_distance_dd(phi_0, theta_0, phi_1, theta_1).mul_dd(DoubleDouble::from(radius))
+}
+
+/// Angle between two points given in degrees, from the cross and dot
+/// products of their unit vectors, to about 30 significant digits.
+fn angular_distance_dd(phi_0: f64, theta_0: f64, phi_1: f64, theta_1: f64) -> DoubleDouble {
+    let unit = |phi, theta| {
+        let (sin_phi, cos_phi) = radians(phi).sin_cos();
+        let (sin_theta, cos_theta) = radians(theta).sin_cos();
+        [
+            cos_theta.mul_dd(cos_phi),
+            cos_theta.mul_dd(sin_phi),
+            sin_theta,
+        ]
+    };
+    let [x0, y0, z0] = unit(phi_0, theta_0);
+    let [x1, y1, z1] = unit(phi_1, theta_1);
+
+    let cross = [
+        y0.mul_dd(z1).sub_dd(z0.mul_dd(y1)),
+        z0.mul_dd(x1).sub_dd(x0.mul_dd(z1)),
+        x0.mul_dd(y1).sub_dd(y0.mul_dd(x1)),
+    ];
+    let sine = cross
+        .iter()
+        .fold(DoubleDouble::default(), |sum, &c| {
+            sum.add_parts(c.mul_dd(c))
+        })
+        .sqrt();
+    let cosine = x0
+        .mul_dd(x1)
+        .add_parts(y0.mul_dd(y1))
+        .add_parts(z0.mul_dd(z1));
+    sine.atan2(cosine)
+}
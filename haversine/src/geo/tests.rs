@@ -0,0 +1,590 @@
+use super::{
+    AngleUnit, Body, BoundingBox, DistanceUnit, Kernel, Point, PolylineError, RadiusModel,
+    UnitVector, angular_distance, cosine_distance, cosine_distance_radians, decode_polyline,
+    degrees_to_radians, destination, encode_polyline, final_bearing, from_ecef, haversine_distance,
+    haversine_distance_f32, haversine_distance_f32_radians, haversine_distance_fast,
+    haversine_distance_fma, initial_bearing, intermediate_point, midpoint, path_length,
+    radians_to_degrees, reference, spherical_centroid, spherical_polygon_area, to_ecef,
+    vincenty_distance, vincenty_distance_radians, wrap_longitude,
+};
+use crate::rng::{Rng, Xoshiro256PlusPlus};
+
+const EPSILON: f64 = 1e-9;
+
+fn assert_close(expected: f64, actual: f64) {
+    assert!(
+        (expected - actual).abs() < EPSILON,
+        "expected {expected}, got {actual}"
+    );
+}
+
+#[test]
+fn test_haversine_quarter_circle() {
+    let distance = haversine_distance(1.0, 0.0, 0.0, 90.0, 0.0);
+    assert_close(std::f64::consts::FRAC_PI_2, distance);
+}
+
+#[test]
+fn test_haversine_identical_points() {
+    assert_close(0.0, haversine_distance(6372.8, 12.5, -40.0, 12.5, -40.0));
+}
+
+#[test]
+fn test_haversine_across_antimeridian() {
+    let one_degree = degrees_to_radians(1.0);
+    assert_close(one_degree, haversine_distance(1.0, 179.5, 0.0, -179.5, 0.0));
+    assert_close(one_degree, haversine_distance(1.0, -179.5, 0.0, 179.5, 0.0));
+    assert_close(0.0, haversine_distance(1.0, 180.0, 10.0, -180.0, 10.0));
+    // Longitudes outside the range name the same meridians.
+    assert_close(
+        haversine_distance(1.0, 10.0, 20.0, -170.0, 30.0),
+        haversine_distance(1.0, 370.0, 20.0, 190.0, 30.0),
+    );
+}
+
+#[test]
+fn test_haversine_at_poles() {
+    // Every longitude of a pole is the same point.
+    assert_close(0.0, haversine_distance(1.0, -120.0, 90.0, 45.0, 90.0));
+    assert_close(
+        std::f64::consts::PI,
+        haversine_distance(1.0, 0.0, 90.0, 77.0, -90.0),
+    );
+    assert_close(
+        degrees_to_radians(30.0),
+        haversine_distance(1.0, 15.0, 90.0, -100.0, 60.0),
+    );
+    // Over the pole, along a meridian and its opposite.
+    assert_close(
+        degrees_to_radians(20.0),
+        haversine_distance(1.0, 0.0, 80.0, 180.0, 80.0),
+    );
+}
+
+#[test]
+fn test_wrap_longitude() {
+    assert_eq!(180.0, wrap_longitude(180.0));
+    assert_eq!(-180.0, wrap_longitude(-180.0));
+    assert_eq!(-170.0, wrap_longitude(190.0));
+    assert_eq!(170.0, wrap_longitude(-190.0));
+    assert_eq!(10.0, wrap_longitude(730.0));
+    assert_eq!(-180.0, wrap_longitude(540.0));
+    assert!(wrap_longitude(f64::NAN).is_nan());
+    assert_eq!(f64::INFINITY, wrap_longitude(f64::INFINITY));
+}
+
+#[test]
+fn test_bearing_along_equator() {
+    assert_close(90.0, initial_bearing(0.0, 0.0, 10.0, 0.0));
+    assert_close(90.0, final_bearing(0.0, 0.0, 10.0, 0.0));
+    assert_close(270.0, initial_bearing(10.0, 0.0, 0.0, 0.0));
+}
+
+#[test]
+fn test_bearing_along_meridian() {
+    assert_close(0.0, initial_bearing(20.0, 0.0, 20.0, 45.0));
+    assert_close(180.0, initial_bearing(20.0, 45.0, 20.0, 0.0));
+}
+
+#[test]
+fn test_bearing_changes_along_great_circle() {
+    // Leaving the equator heading north-east, the route arrives heading east
+    // at the highest latitude it reaches.
+    let initial = initial_bearing(0.0, 0.0, 90.0, 45.0);
+    let final_ = final_bearing(0.0, 0.0, 90.0, 45.0);
+    assert_close(45.0, initial);
+    assert_close(90.0, final_);
+}
+
+#[test]
+fn test_intermediate_point_endpoints() {
+    let (phi, theta) = intermediate_point(-30.0, 10.0, 50.0, 40.0, 0.0);
+    assert_close(-30.0, phi);
+    assert_close(10.0, theta);
+
+    let (phi, theta) = intermediate_point(-30.0, 10.0, 50.0, 40.0, 1.0);
+    assert_close(50.0, phi);
+    assert_close(40.0, theta);
+}
+
+#[test]
+fn test_intermediate_point_midway_on_equator() {
+    let (phi, theta) = intermediate_point(0.0, 0.0, 60.0, 0.0, 0.5);
+    assert_close(30.0, phi);
+    assert_close(0.0, theta);
+}
+
+#[test]
+fn test_intermediate_point_splits_distance() {
+    let (p0, t0, p1, t1) = (-73.9, 40.7, 2.35, 48.85);
+    let (phi, theta) = intermediate_point(p0, t0, p1, t1, 0.25);
+    let total = haversine_distance(1.0, p0, t0, p1, t1);
+    assert_close(0.25 * total, haversine_distance(1.0, p0, t0, phi, theta));
+    assert_close(0.75 * total, haversine_distance(1.0, phi, theta, p1, t1));
+}
+
+#[test]
+fn test_destination_quarter_circle() {
+    let quarter = std::f64::consts::FRAC_PI_2;
+    let (phi, theta) = destination(1.0, 0.0, 0.0, 90.0, quarter);
+    assert_close(90.0, phi);
+    assert_close(0.0, theta);
+
+    let (_, theta) = destination(1.0, 0.0, 0.0, 0.0, quarter);
+    assert_close(90.0, theta);
+}
+
+#[test]
+fn test_destination_wraps_longitude() {
+    let (phi, theta) = destination(1.0, 170.0, 0.0, 90.0, degrees_to_radians(20.0));
+    assert_close(-170.0, phi);
+    assert_close(0.0, theta);
+}
+
+#[test]
+fn test_destination_inverts_distance_and_bearing() {
+    let (p0, t0, p1, t1) = (-73.9, 40.7, 2.35, 48.85);
+    let radius = 6372.8;
+    let distance = haversine_distance(radius, p0, t0, p1, t1);
+    let bearing = initial_bearing(p0, t0, p1, t1);
+    let (phi, theta) = destination(radius, p0, t0, bearing, distance);
+    assert_close(p1, phi);
+    assert_close(t1, theta);
+}
+
+#[test]
+fn test_radius_model_from_name() {
+    assert_eq!(Some(RadiusModel::Mean), RadiusModel::from_name("mean"));
+    assert_eq!(Some(RadiusModel::Polar), RadiusModel::from_name("polar"));
+    assert_eq!(
+        Some(RadiusModel::Custom(6372.8)),
+        RadiusModel::from_name("custom=6372.8")
+    );
+    assert_eq!(None, RadiusModel::from_name("custom=-1"));
+    assert_eq!(None, RadiusModel::from_name("custom"));
+    assert_eq!(None, RadiusModel::from_name("flat"));
+}
+
+#[test]
+fn test_radius_models_ordering() {
+    let polar = RadiusModel::Polar.kilometers();
+    let mean = RadiusModel::Mean.kilometers();
+    let authalic = RadiusModel::Authalic.kilometers();
+    let equatorial = RadiusModel::Equatorial.kilometers();
+    assert!(polar < authalic && authalic < mean && mean < equatorial);
+}
+
+#[test]
+fn test_body_from_name() {
+    assert_eq!(Some(Body::Moon), Body::from_name("moon"));
+    assert_eq!(Some(Body::Custom(100.0)), Body::from_name("custom=100"));
+    assert_eq!(None, Body::from_name("custom=-1"));
+    assert_eq!(None, Body::from_name("mean"));
+    assert_eq!(RadiusModel::Mean, Body::Earth.radius_model());
+    assert_eq!(3389.5, Body::Mars.kilometers());
+}
+
+#[test]
+fn test_distance_unit_conversion() {
+    assert_close(1852.0, DistanceUnit::Meters.convert_kilometers(1.852));
+    assert_close(1.0, DistanceUnit::NauticalMiles.convert_kilometers(1.852));
+    assert_close(1.0, DistanceUnit::Miles.convert_kilometers(1.609344));
+    assert_close(5.0, DistanceUnit::Kilometers.convert_kilometers(5.0));
+}
+
+#[test]
+fn test_bounding_box_from_spec() {
+    let bbox = BoundingBox::from_spec("35, -10, 60.5, 30").unwrap();
+    assert_eq!(BoundingBox::new(35.0, -10.0, 60.5, 30.0), Some(bbox));
+    for spec in [
+        "35,-10,60",
+        "35,-10,60,30,1",
+        "a,b,c,d",
+        "60,-10,35,30",
+        "-91,0,0,0",
+        "0,0,0,181",
+    ] {
+        assert_eq!(None, BoundingBox::from_spec(spec), "{spec}");
+    }
+}
+
+#[test]
+fn test_bounding_box_contains() {
+    let europe = BoundingBox::from_spec("35,-10,60,30").unwrap();
+    assert!(europe.contains(Point::new(2.35, 48.85)));
+    assert!(europe.contains(Point::new(30.0, 60.0)));
+    assert!(!europe.contains(Point::new(-74.0, 40.7)));
+    assert!(!europe.contains(Point::new(2.35, 61.0)));
+
+    let pacific = BoundingBox::from_spec("-50,170,0,-170").unwrap();
+    assert!(pacific.contains(Point::new(175.0, -20.0)));
+    assert!(pacific.contains(Point::new(-175.0, -20.0)));
+    assert!(!pacific.contains(Point::new(0.0, -20.0)));
+}
+
+#[test]
+fn test_decode_polyline() {
+    // The example of Google's documentation.
+    let points = decode_polyline("_p~iF~ps|U_ulLnnqC_mqNvxq`@", 5).unwrap();
+    let expected = [(-120.2, 38.5), (-120.95, 40.7), (-126.453, 43.252)];
+    assert_eq!(expected.len(), points.len());
+    for ((lon, lat), point) in expected.into_iter().zip(points) {
+        assert_close(lon, point.lon);
+        assert_close(lat, point.lat);
+    }
+    assert_eq!(Ok(Vec::new()), decode_polyline("", 5));
+}
+
+#[test]
+fn test_encode_polyline_round_trips() {
+    let points = [
+        Point::new(-120.2, 38.5),
+        Point::new(-120.95, 40.7),
+        Point::new(179.999999, -89.5),
+    ];
+    assert_eq!("_p~iF~ps|U_ulLnnqC", encode_polyline(&points[..2], 5));
+    let decoded = decode_polyline(&encode_polyline(&points, 6), 6).unwrap();
+    for (point, decoded) in points.iter().zip(decoded) {
+        assert_close(point.lon, decoded.lon);
+        assert_close(point.lat, decoded.lat);
+    }
+}
+
+#[test]
+fn test_decode_polyline_errors() {
+    assert_eq!(
+        Err(PolylineError::InvalidChar(2)),
+        decode_polyline("_p iF", 5)
+    );
+    assert_eq!(Err(PolylineError::Truncated), decode_polyline("_p~iF", 5));
+    assert_eq!(Err(PolylineError::Truncated), decode_polyline("_p~", 5));
+    assert_eq!(
+        Err(PolylineError::Overflow(13)),
+        decode_polyline(&"~".repeat(14), 5)
+    );
+}
+
+#[test]
+fn test_path_length() {
+    let points = [
+        Point::new(0.0, 0.0),
+        Point::new(90.0, 0.0),
+        Point::new(90.0, 90.0),
+    ];
+    assert_close(std::f64::consts::PI, path_length(1.0, &points));
+    assert_eq!(0.0, path_length(1.0, &points[..1]));
+}
+
+#[test]
+fn test_angular_distance() {
+    assert_close(
+        std::f64::consts::FRAC_PI_2,
+        angular_distance(0.0, 0.0, 0.0, 90.0),
+    );
+    assert_close(
+        haversine_distance(6371.0, 10.0, 20.0, -30.0, 40.0) / 6371.0,
+        angular_distance(10.0, 20.0, -30.0, 40.0),
+    );
+}
+
+#[test]
+fn test_spherical_polygon_area() {
+    use std::f64::consts::{FRAC_PI_2, PI};
+    let octant = [
+        Point::new(0.0, 0.0),
+        Point::new(90.0, 0.0),
+        Point::new(0.0, 90.0),
+    ];
+    assert_close(FRAC_PI_2, spherical_polygon_area(&octant, 1.0));
+    let mut reversed = octant;
+    reversed.reverse();
+    assert_close(FRAC_PI_2, spherical_polygon_area(&reversed, 1.0));
+    let closed = [octant[0], octant[1], octant[2], octant[0]];
+    assert_close(4.0 * FRAC_PI_2, spherical_polygon_area(&closed, 2.0));
+
+    // A triangle from the south pole across the antimeridian.
+    let wedge = [
+        Point::new(170.0, 0.0),
+        Point::new(-160.0, 0.0),
+        Point::new(0.0, -90.0),
+    ];
+    assert_close(PI / 6.0, spherical_polygon_area(&wedge, 1.0));
+    // A cap around the north pole, either way round.
+    let cap = [
+        Point::new(0.0, 0.0),
+        Point::new(120.0, 0.0),
+        Point::new(-120.0, 0.0),
+    ];
+    assert_close(2.0 * PI, spherical_polygon_area(&cap, 1.0));
+    let cap = cap.map(|point| Point::new(point.lon, 60.0));
+    let mut reversed = cap;
+    reversed.reverse();
+    assert_close(
+        spherical_polygon_area(&cap, 1.0),
+        spherical_polygon_area(&reversed, 1.0),
+    );
+    // From L'Huilier's theorem on the sides of the triangle.
+    assert_close(0.386422967695612, spherical_polygon_area(&cap, 1.0));
+    assert_eq!(0.0, spherical_polygon_area(&octant[..2], 1.0));
+    assert_eq!(0.0, spherical_polygon_area(&[], 1.0));
+}
+
+/// A point uniformly distributed on the sphere, as `(phi, theta)`.
+fn random_point(rng: &mut impl Rng) -> (f64, f64) {
+    let theta = radians_to_degrees(rng.uniform(-1.0, 1.0).asin());
+    (rng.uniform(-180.0, 180.0), theta)
+}
+
+/// Largest errors of the haversine, Vincenty and cosine distances on the
+/// unit sphere between the pairs, against the double-double reference,
+/// relative to it or absolute.
+fn max_errors(pairs: &[[f64; 4]], relative: bool) -> [f64; 3] {
+    let mut worst = [0.0f64; 3];
+    for &[phi_0, theta_0, phi_1, theta_1] in pairs {
+        let expected = reference::angular_distance(phi_0, theta_0, phi_1, theta_1);
+        let actual = [
+            haversine_distance(1.0, phi_0, theta_0, phi_1, theta_1),
+            vincenty_distance(1.0, phi_0, theta_0, phi_1, theta_1),
+            cosine_distance(1.0, phi_0, theta_0, phi_1, theta_1),
+        ];
+        let scale = if relative { expected } else { 1.0 };
+        for (worst, actual) in worst.iter_mut().zip(actual) {
+            *worst = worst.max(((actual - expected) / scale).abs());
+        }
+    }
+    worst
+}
+
+#[test]
+fn test_reference_exact_angles() {
+    use std::f64::consts::{FRAC_PI_2, FRAC_PI_3, PI};
+    let assert_exact = |expected: f64, [phi_0, theta_0, phi_1, theta_1]: [f64; 4]| {
+        let actual = reference::angular_distance(phi_0, theta_0, phi_1, theta_1);
+        assert!(
+            (actual - expected).abs() <= f64::EPSILON * expected,
+            "expected {expected}, got {actual}"
+        );
+    };
+    assert_exact(FRAC_PI_2, [0.0, 0.0, 90.0, 0.0]);
+    assert_exact(FRAC_PI_3, [-45.0, 30.0, 0.0, 90.0]);
+    assert_exact(FRAC_PI_3, [10.0, 0.0, 70.0, 0.0]);
+    assert_exact(PI, [10.0, 20.0, -170.0, -20.0]);
+    assert_exact(0.0, [10.0, 20.0, 10.0, 20.0]);
+}
+
+#[test]
+fn test_formulas_match_reference_on_random_pairs() {
+    let mut rng = Xoshiro256PlusPlus::new(7);
+    let pairs: Vec<_> = (0..10_000)
+        .map(|_| {
+            let (phi_0, theta_0) = random_point(&mut rng);
+            let (phi_1, theta_1) = random_point(&mut rng);
+            [phi_0, theta_0, phi_1, theta_1]
+        })
+        .collect();
+    let [haversine, vincenty, cosine] = max_errors(&pairs, true);
+    assert!(haversine < 1e-12, "haversine relative error {haversine}");
+    assert!(vincenty < 1e-14, "Vincenty relative error {vincenty}");
+    assert!(cosine < 1e-11, "cosine relative error {cosine}");
+}
+
+#[test]
+fn test_formulas_match_reference_on_nearby_pairs() {
+    let mut rng = Xoshiro256PlusPlus::new(11);
+    let pairs: Vec<_> = (0..1_000)
+        .map(|_| {
+            let (phi, theta) = random_point(&mut rng);
+            let theta = theta.clamp(-89.0, 89.0);
+            let (d_phi, d_theta) = (rng.uniform(-1e-5, 1e-5), rng.uniform(-1e-5, 1e-5));
+            [phi, theta, phi + d_phi, theta + d_theta]
+        })
+        .collect();
+    // The coordinates are rounded to radians before they are subtracted, so
+    // the errors are bounded in absolute terms. The law of cosines takes the
+    // arc cosine of a number within rounding of one, which loses half the
+    // digits.
+    let [haversine, vincenty, cosine] = max_errors(&pairs, false);
+    assert!(haversine < 1e-15, "haversine error {haversine}");
+    assert!(vincenty < 1e-15, "Vincenty error {vincenty}");
+    assert!(cosine < 1e-7, "cosine error {cosine}");
+}
+
+#[test]
+fn test_formulas_match_reference_on_nearly_antipodal_pairs() {
+    let mut rng = Xoshiro256PlusPlus::new(13);
+    let pairs: Vec<_> = (0..1_000)
+        .map(|_| {
+            let (phi, theta) = random_point(&mut rng);
+            let (d_phi, d_theta) = (rng.uniform(-1e-4, 1e-4), rng.uniform(-1e-4, 1e-4));
+            [phi, theta, phi + 180.0 + d_phi, -theta + d_theta]
+        })
+        .collect();
+    // Near pi the haversine and the law of cosines take the arc sine and arc
+    // cosine of numbers within rounding of one, which loses half the digits.
+    let [haversine, vincenty, cosine] = max_errors(&pairs, true);
+    assert!(haversine < 1e-7, "haversine relative error {haversine}");
+    assert!(vincenty < 1e-14, "Vincenty relative error {vincenty}");
+    assert!(cosine < 1e-7, "cosine relative error {cosine}");
+}
+
+#[test]
+fn test_unit_vector_distance_matches_haversine() {
+    let mut rng = Xoshiro256PlusPlus::new(17);
+    for _ in 0..1_000 {
+        let ((phi_0, theta_0), (phi_1, theta_1)) = (random_point(&mut rng), random_point(&mut rng));
+        let from = UnitVector::new(phi_0, theta_0);
+        let to = UnitVector::new(phi_1, theta_1);
+        let expected = haversine_distance(6372.8, phi_0, theta_0, phi_1, theta_1);
+        let distance = from.distance(&to, 6372.8);
+        assert!(
+            (distance - expected).abs() < 1e-9,
+            "{distance} versus {expected}"
+        );
+    }
+    let pole = UnitVector::new(0.0, 90.0);
+    assert_eq!(0.0, pole.distance(&pole, 1.0));
+    let antipode = UnitVector::new(0.0, -90.0);
+    assert_close(std::f64::consts::PI, pole.distance(&antipode, 1.0));
+}
+
+#[test]
+fn test_dot_distance_matches_haversine() {
+    let mut rng = Xoshiro256PlusPlus::new(11);
+    for _ in 0..1000 {
+        let ((phi_0, theta_0), (phi_1, theta_1)) = (random_point(&mut rng), random_point(&mut rng));
+        let a = UnitVector::new(phi_0, theta_0);
+        let b = UnitVector::new(phi_1, theta_1);
+        let expected = haversine_distance(6372.8, phi_0, theta_0, phi_1, theta_1);
+        assert!((expected - a.dot_distance(&b, 6372.8)).abs() < 1e-8);
+    }
+    let a = UnitVector::new(10.0, 20.0);
+    assert_eq!(0.0, a.dot_distance(&a, 1.0));
+    let b = UnitVector::new(-170.0, -20.0);
+    assert_close(std::f64::consts::PI, a.dot_distance(&b, 1.0));
+}
+
+#[test]
+fn test_ecef_round_trips() {
+    assert_eq!(
+        [0.0, 0.0, 3.0],
+        to_ecef(45.0, 90.0, 3.0).map(|c| c.abs().round())
+    );
+    let mut rng = Xoshiro256PlusPlus::new(12);
+    for _ in 0..100 {
+        let (phi, theta) = (rng.uniform(-180.0, 180.0), rng.uniform(-89.0, 89.0));
+        let (phi_back, theta_back, radius) = from_ecef(to_ecef(phi, theta, 6371.0));
+        assert_close(phi, phi_back);
+        assert_close(theta, theta_back);
+        assert!((radius - 6371.0).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn test_fma_and_fast_distances_match_haversine() {
+    let mut rng = Xoshiro256PlusPlus::new(13);
+    for _ in 0..1000 {
+        let ((phi_0, theta_0), (phi_1, theta_1)) = (random_point(&mut rng), random_point(&mut rng));
+        let expected = haversine_distance(6372.8, phi_0, theta_0, phi_1, theta_1);
+        let fma = haversine_distance_fma(6372.8, phi_0, theta_0, phi_1, theta_1);
+        let fast = haversine_distance_fast(6372.8, phi_0, theta_0, phi_1, theta_1);
+        assert!((expected - fma).abs() < 1e-9, "{expected} versus {fma}");
+        assert!((expected - fast).abs() < 1e-7, "{expected} versus {fast}");
+    }
+    // Antipodal points, whose rounded term may pass 1.
+    assert!(!haversine_distance_fma(1.0, 0.0, 0.0, 180.0, 0.0).is_nan());
+    assert!(!haversine_distance_fast(1.0, 30.0, 45.0, -150.0, -45.0).is_nan());
+}
+
+#[test]
+fn test_radians_distances_match_degrees() {
+    let mut rng = Xoshiro256PlusPlus::new(17);
+    for _ in 0..1000 {
+        let ((phi_0, theta_0), (phi_1, theta_1)) = (random_point(&mut rng), random_point(&mut rng));
+        let radians = [phi_0, theta_0, phi_1, theta_1].map(degrees_to_radians);
+        let [r_phi_0, r_theta_0, r_phi_1, r_theta_1] = radians;
+        assert_close(
+            vincenty_distance(1.0, phi_0, theta_0, phi_1, theta_1),
+            vincenty_distance_radians(1.0, r_phi_0, r_theta_0, r_phi_1, r_theta_1),
+        );
+        // The law of cosines loses nearby points, so only roughly.
+        let cosine = cosine_distance(1.0, phi_0, theta_0, phi_1, theta_1);
+        let cosine_radians = cosine_distance_radians(1.0, r_phi_0, r_theta_0, r_phi_1, r_theta_1);
+        assert!((cosine - cosine_radians).abs() < 1e-6);
+        let [f_phi_0, f_theta_0, f_phi_1, f_theta_1] = radians.map(|angle| angle as f32);
+        let f32_degrees = haversine_distance_f32(
+            1.0,
+            phi_0 as f32,
+            theta_0 as f32,
+            phi_1 as f32,
+            theta_1 as f32,
+        );
+        let f32_radians =
+            haversine_distance_f32_radians(1.0, f_phi_0, f_theta_0, f_phi_1, f_theta_1);
+        assert!((f32_degrees - f32_radians).abs() < 1e-3);
+    }
+}
+
+#[test]
+fn test_fast_distance_loses_nearby_points() {
+    let expected = haversine_distance(6372.8, 10.0, 20.0, 10.0, 20.0 + 1e-9);
+    let fast = haversine_distance_fast(6372.8, 10.0, 20.0, 10.0, 20.0 + 1e-9);
+    assert!(expected > 0.0);
+    assert!(
+        (expected - fast).abs() > 1e-3 * expected,
+        "{expected} versus {fast}"
+    );
+}
+
+#[test]
+fn test_kernel_from_name() {
+    assert_eq!(Some(Kernel::Haversine), Kernel::from_name("haversine"));
+    assert_eq!(Some(Kernel::CachedTrig), Kernel::from_name("cached-trig"));
+    assert_eq!(Some(Kernel::Dot), Kernel::from_name("dot"));
+    assert_eq!(Some(Kernel::FastMath), Kernel::from_name("fast-math"));
+    assert_eq!(Some(Kernel::Vincenty), Kernel::from_name("vincenty"));
+    assert_eq!(Some(Kernel::Cosine), Kernel::from_name("cosine"));
+    assert_eq!(None, Kernel::from_name("chord"));
+}
+
+#[test]
+fn test_angle_unit_conversions() {
+    assert_eq!(Some(AngleUnit::Gradians), AngleUnit::from_name("gradians"));
+    assert_eq!(None, AngleUnit::from_name("turns"));
+    for unit in [AngleUnit::Degrees, AngleUnit::Radians, AngleUnit::Gradians] {
+        assert_close(std::f64::consts::PI, unit.to_radians(unit.half_turn()));
+        assert_close(
+            degrees_to_radians(-37.5),
+            unit.to_radians(unit.from_degrees(-37.5)),
+        );
+    }
+    assert_close(-100.0, AngleUnit::Gradians.from_degrees(-90.0));
+}
+
+#[test]
+fn test_midpoint_matches_intermediate_point() {
+    let (phi, theta) = midpoint(-30.0, 10.0, 100.0, 60.0);
+    let (expected_phi, expected_theta) = intermediate_point(-30.0, 10.0, 100.0, 60.0, 0.5);
+    assert_close(expected_phi, phi);
+    assert_close(expected_theta, theta);
+    let (phi, theta) = midpoint(170.0, 0.0, -170.0, 0.0);
+    assert_close(180.0, phi.abs());
+    assert_close(0.0, theta);
+}
+
+#[test]
+fn test_spherical_centroid() {
+    assert_eq!(None, spherical_centroid([]));
+    assert_eq!(
+        None,
+        spherical_centroid([Point::new(0.0, 0.0), Point::new(180.0, 0.0)])
+    );
+    let centroid = spherical_centroid([
+        Point::new(10.0, 45.0),
+        Point::new(10.0, 55.0),
+        Point::new(5.0, 50.0),
+        Point::new(15.0, 50.0),
+    ])
+    .unwrap();
+    assert_close(10.0, centroid.lon);
+    assert!((centroid.lat - 50.0).abs() < 0.1, "{}", centroid.lat);
+}
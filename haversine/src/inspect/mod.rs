@@ -0,0 +1,110 @@
+//! Size and structure statistics of a JSON document, gathered from the
+//! events of a `FeedParser` so the document is never held in memory.
+
+use crate::Result;
+use crate::parse::{Event, FeedParser};
+use crate::sink::OutputSink;
+use std::io::{self, Read};
+
+/// Size of the chunks read from the input.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Counts of the values of a document, and where its top-level keys are.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[non_exhaustive]
+pub struct DocumentStats {
+    pub bytes: usize,
+    pub objects: usize,
+    pub arrays: usize,
+    pub strings: usize,
+    pub numbers: usize,
+    pub bools: usize,
+    pub nulls: usize,
+    /// Keys of object members, at every depth.
+    pub keys: usize,
+    /// Deepest nesting of arrays and objects, 0 for a scalar document.
+    pub max_depth: usize,
+    /// UTF-8 bytes of the strings and keys, once unescaped.
+    pub string_bytes: usize,
+    /// Keys of the top-level object with the byte offset of each.
+    pub top_level_keys: Vec<(String, usize)>,
+}
+
+impl DocumentStats {
+    /// Writes the counts to `sink`, one per line.
+    pub fn write(&self, mut sink: impl OutputSink) -> io::Result<()> {
+        writeln!(sink, "Bytes: {}", self.bytes)?;
+        writeln!(sink, "Objects: {}", self.objects)?;
+        writeln!(sink, "Arrays: {}", self.arrays)?;
+        writeln!(sink, "Strings: {}", self.strings)?;
+        writeln!(sink, "Numbers: {}", self.numbers)?;
+        writeln!(sink, "Booleans: {}", self.bools)?;
+        writeln!(sink, "Nulls: {}", self.nulls)?;
+        writeln!(sink, "Keys: {}", self.keys)?;
+        writeln!(sink, "Max depth: {}", self.max_depth)?;
+        writeln!(sink, "String bytes: {}", self.string_bytes)?;
+        if !self.top_level_keys.is_empty() {
+            writeln!(sink, "Top-level keys:")?;
+            for (key, offset) in &self.top_level_keys {
+                writeln!(sink, "  {key:?} at byte {offset}")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn event(&mut self, depth: &mut usize, offset: usize, event: Event) {
+        match event {
+            Event::StartObject => {
+                self.objects += 1;
+                *depth += 1;
+            }
+            Event::StartArray => {
+                self.arrays += 1;
+                *depth += 1;
+            }
+            Event::EndObject | Event::EndArray => *depth -= 1,
+            Event::Key(key) => {
+                self.keys += 1;
+                self.string_bytes += key.len();
+                if *depth == 1 {
+                    self.top_level_keys.push((key, offset));
+                }
+            }
+            Event::String(string) => {
+                self.strings += 1;
+                self.string_bytes += string.len();
+            }
+            Event::Number(_) => self.numbers += 1,
+            Event::Bool(_) => self.bools += 1,
+            Event::Null => self.nulls += 1,
+        }
+        self.max_depth = self.max_depth.max(*depth);
+    }
+}
+
+/// Reads a document in chunks and gathers its statistics.
+pub fn inspect(mut reader: impl Read) -> Result<DocumentStats> {
+    let mut parser = FeedParser::new();
+    let mut stats = DocumentStats::default();
+    let mut depth = 0;
+    let mut chunk = vec![0; CHUNK_SIZE];
+    loop {
+        let n = match reader.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error.into()),
+        };
+        stats.bytes += n;
+        for (offset, event) in parser.feed_spanned(&chunk[..n])? {
+            stats.event(&mut depth, offset, event);
+        }
+    }
+    for (offset, event) in parser.finish_spanned()? {
+        stats.event(&mut depth, offset, event);
+    }
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,46 @@
+use super::*;
+use crate::Error;
+
+#[test]
+fn test_inspect() {
+    let json = r#"{"pairs": [{"x0": 1.5, "y0": -2}], "name": "café", "ok": [true, null, []]}"#;
+    let stats = inspect(json.as_bytes()).unwrap();
+    assert_eq!(json.len(), stats.bytes);
+    assert_eq!(2, stats.objects);
+    assert_eq!(3, stats.arrays);
+    assert_eq!(1, stats.strings);
+    assert_eq!(2, stats.numbers);
+    assert_eq!(1, stats.bools);
+    assert_eq!(1, stats.nulls);
+    assert_eq!(5, stats.keys);
+    assert_eq!(3, stats.max_depth);
+    assert_eq!("pairsx0y0namecaféok".len(), stats.string_bytes);
+    let keys: Vec<_> = ["pairs", "name", "ok"]
+        .iter()
+        .map(|key| (key.to_string(), json.find(&format!("\"{key}\"")).unwrap()))
+        .collect();
+    assert_eq!(keys, stats.top_level_keys);
+}
+
+#[test]
+fn test_offsets_across_chunks() {
+    // Keys past the first chunk, and the first one after a byte order mark.
+    let padding = "x".repeat(CHUNK_SIZE);
+    let json = format!("\u{feff}{{\"a\": \"{padding}\",\n  \"b\": 1}}");
+    let stats = inspect(json.as_bytes()).unwrap();
+    let offsets: Vec<_> = stats.top_level_keys.iter().map(|(_, o)| *o).collect();
+    assert_eq!(
+        vec![json.find("\"a\"").unwrap(), json.find("\"b\"").unwrap()],
+        offsets
+    );
+    assert_eq!(1, stats.max_depth);
+}
+
+#[test]
+fn test_scalar_and_invalid() {
+    let stats = inspect(&b" 42 "[..]).unwrap();
+    assert_eq!(1, stats.numbers);
+    assert_eq!(0, stats.max_depth);
+    assert!(stats.top_level_keys.is_empty());
+    assert!(matches!(inspect(&b"[1, "[..]), Err(Error::Parsing(_))));
+}
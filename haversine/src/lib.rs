@@ -0,0 +1,43 @@
+//! Parsing of pairs files and great-circle distance computation.
+//!
+//! The [`prelude`] holds the stable, commonly used surface of the crate.
+
+pub mod affinity;
+pub mod answers;
+pub mod cancel;
+pub mod checkpoint;
+pub mod checksum;
+pub mod columnar;
+pub mod compare;
+pub mod compute;
+pub mod dd;
+pub mod dump;
+mod error;
+pub use error::{Error, Result};
+pub mod extract;
+pub mod ffi;
+pub mod fuzz;
+pub mod generate;
+pub mod geo;
+pub mod inspect;
+pub mod math;
+pub mod matrix;
+pub mod memory;
+pub mod pairs;
+pub mod parse;
+pub mod per_pair;
+pub mod pipeline;
+pub mod prelude;
+pub mod reader;
+pub mod rng;
+pub mod schema;
+pub mod shard;
+pub mod sink;
+pub mod stats;
+pub mod timer;
+pub mod validate;
+#[cfg(any(target_family = "wasm", test))]
+pub mod wasm;
+
+#[cfg(feature = "serde")]
+mod serde_impl;
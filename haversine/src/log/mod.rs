@@ -0,0 +1,137 @@
+//! Diagnostics of the program on stderr, filtered by level and written as
+//! plain lines or JSON objects. The reports stay on stdout.
+
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use haversine::parse::{Map, Value};
+
+/// Importance of a message, least verbose first.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Level {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    fn name(self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug",
+            Level::Trace => "trace",
+        }
+    }
+}
+
+/// How the messages are written.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub(crate) enum LogFormat {
+    /// A timestamp, the level and the message on a line.
+    #[default]
+    Plain,
+    /// A JSON object per line, with `time`, `level` and `message`.
+    Json,
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(Level::Info as u8);
+static JSON: AtomicU8 = AtomicU8::new(0);
+
+/// Sets the most verbose level written and the format, for the rest of the
+/// program.
+pub(crate) fn init(level: Level, format: LogFormat) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+    JSON.store((format == LogFormat::Json) as u8, Ordering::Relaxed);
+}
+
+/// Whether messages of `level` are written.
+pub(crate) fn enabled(level: Level) -> bool {
+    level as u8 <= LEVEL.load(Ordering::Relaxed)
+}
+
+/// Writes `message` at `level`, if enabled.
+pub(crate) fn log(level: Level, message: impl Display) {
+    if !enabled(level) {
+        return;
+    }
+    let format = match JSON.load(Ordering::Relaxed) {
+        1 => LogFormat::Json,
+        _ => LogFormat::Plain,
+    };
+    eprintln!("{}", line(format, level, SystemTime::now(), message));
+}
+
+/// The line of `message` at `level` written at `time`, without its newline.
+fn line(format: LogFormat, level: Level, time: SystemTime, message: impl Display) -> String {
+    let time = timestamp(time);
+    match format {
+        LogFormat::Json => {
+            let mut object = Map::new();
+            object.insert("time".into(), Value::String(time));
+            object.insert("level".into(), Value::String(level.name().into()));
+            object.insert("message".into(), Value::String(message.to_string()));
+            Value::Object(object).to_json()
+        }
+        LogFormat::Plain => format!("{time} {:<5} {message}", level.name().to_uppercase()),
+    }
+}
+
+pub(crate) fn error(message: impl Display) {
+    log(Level::Error, message);
+}
+
+pub(crate) fn warn(message: impl Display) {
+    log(Level::Warn, message);
+}
+
+pub(crate) fn info(message: impl Display) {
+    log(Level::Info, message);
+}
+
+pub(crate) fn debug(message: impl Display) {
+    log(Level::Debug, message);
+}
+
+pub(crate) fn trace(message: impl Display) {
+    log(Level::Trace, message);
+}
+
+/// Formats `time` as an RFC 3339 UTC timestamp to the millisecond.
+fn timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs();
+    let (days, of_day) = (seconds / 86_400, seconds % 86_400);
+
+    // Days to a civil date, after Howard Hinnant's `civil_from_days`, with
+    // years counted from March so the leap day ends them.
+    let days = days as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let of_era = days.rem_euclid(146_097);
+    let year_of_era = (of_era - of_era / 1460 + of_era / 36_524 - of_era / 146_096) / 365;
+    let of_year = of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * of_year + 2) / 153;
+    let day = of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        of_day / 3600,
+        of_day / 60 % 60,
+        of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,63 @@
+use super::{Level, LogFormat, enabled, init, line, timestamp};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// 2024-02-29 12:34:56.789 UTC.
+fn leap_day() -> SystemTime {
+    UNIX_EPOCH + Duration::from_millis(1_709_210_096_789)
+}
+
+#[test]
+fn test_enabled_up_to_the_level() {
+    let levels = [
+        Level::Error,
+        Level::Warn,
+        Level::Info,
+        Level::Debug,
+        Level::Trace,
+    ];
+    for (most_verbose, &level) in levels.iter().enumerate() {
+        init(level, LogFormat::Plain);
+        for (index, &message) in levels.iter().enumerate() {
+            assert_eq!(
+                index <= most_verbose,
+                enabled(message),
+                "{message:?} at {level:?}"
+            );
+        }
+    }
+    init(Level::default(), LogFormat::default());
+}
+
+#[test]
+fn test_timestamp() {
+    assert_eq!("1970-01-01T00:00:00.000Z", timestamp(UNIX_EPOCH));
+    assert_eq!("2024-02-29T12:34:56.789Z", timestamp(leap_day()));
+    // The day after the leap day of a year divisible by 400.
+    let march = UNIX_EPOCH + Duration::from_secs(951_868_800);
+    assert_eq!("2000-03-01T00:00:00.000Z", timestamp(march));
+}
+
+#[test]
+fn test_plain_line() {
+    assert_eq!(
+        "2024-02-29T12:34:56.789Z WARN  Read 3 bytes",
+        line(LogFormat::Plain, Level::Warn, leap_day(), "Read 3 bytes")
+    );
+    assert_eq!(
+        "2024-02-29T12:34:56.789Z ERROR Failed",
+        line(LogFormat::Plain, Level::Error, leap_day(), "Failed")
+    );
+}
+
+#[test]
+fn test_json_line() {
+    assert_eq!(
+        r#"{"time":"2024-02-29T12:34:56.789Z","level":"debug","message":"Parsed 2 pairs"}"#,
+        line(LogFormat::Json, Level::Debug, leap_day(), "Parsed 2 pairs")
+    );
+    // The message is escaped, so every line is one object.
+    assert_eq!(
+        r#"{"time":"2024-02-29T12:34:56.789Z","level":"info","message":"a \"b\"\nc"}"#,
+        line(LogFormat::Json, Level::Info, leap_day(), "a \"b\"\nc")
+    );
+}
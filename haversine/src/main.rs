@@ -1,120 +1,1630 @@
-use std::fs::File;
-use std::io::Read;
-use std::time::Instant;
+use std::borrow::Cow;
+use std::fmt::Display;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::process;
+use std::sync::atomic::AtomicUsize;
+use std::thread;
+use std::time::{Duration, Instant};
 
-mod parse;
-use parse::{Parser, Value};
+use haversine::answers::Answers;
+use haversine::cancel::CancelToken;
+use haversine::checkpoint::{Checkpoint, CheckpointError};
+use haversine::columnar::{self, ColumnarError};
+use haversine::compare;
+use haversine::dump;
+use haversine::extract::{self, Selector};
+use haversine::generate::{self as generator, generate_pairs};
+use haversine::geo::{
+    AngleUnit, DistanceUnit, Point, RadiusModel, cosine_distance, final_bearing,
+    haversine_distance, haversine_distance_radians_with, initial_bearing, path_length,
+    spherical_polygon_area, vincenty_distance,
+};
+use haversine::inspect;
+use haversine::math::{Math, PolyMath, StdMath};
+use haversine::matrix::{
+    MatrixDriver, MatrixFormat, point_from_value, points_from_values, write_rows,
+};
+use haversine::memory::{self, MemoryStats};
+use haversine::pairs::{Pairs, PairsError, PairsReader, parse_fast_schema};
+use haversine::parse::{
+    DuplicateKeyPolicy, Encoding, LoneSurrogatePolicy, Map, Parser, ParsingError, Value, decode,
+    detect_encoding,
+};
+use haversine::per_pair::PerPairOptions;
+use haversine::pipeline::{
+    AnswersCheck, AverageCheck, Coordinates, InputFormat, Loaded, Pipeline, RunError, RunResult,
+    Warning,
+};
+use haversine::reader::IoSource;
+use haversine::shard;
+use haversine::sink::{self, FileSink, OutputSink, StdoutSink};
+use haversine::timer::{CYCLE_TIMER, read_cpu_timer};
+use haversine::validate;
 
-fn degrees_to_radians(angle: f64) -> f64 {
-    angle * std::f64::consts::PI / 180.0
+mod cli;
+mod config;
+use config::ConfigError;
+mod log;
+mod progress;
+mod repl;
+mod route;
+use progress::{Progress, Unit};
+use route::Route;
+
+use cli::{
+    AreaArgs, ArgsError, BenchArgs, BenchMode, CheckpointArgs, Command, CompareFormulasArgs,
+    ComputeArgs, ComputeMode, ConfigArgs, DiffArgs, DistArgs, ExportColumnarArgs, ExtractArgs,
+    FmtArgs, Formula, FuzzArgs, GenerateArgs, InspectArgs, LogArgs, MatrixArgs, MergeArgs,
+    SplitArgs, TokensArgs, TreeArgs, ValidateArgs,
+};
+
+mod reptest;
+
+#[cfg(feature = "alloc-stats")]
+#[global_allocator]
+static ALLOCATOR: memory::CountingAllocator = memory::CountingAllocator;
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (logging, args) = LogArgs::extract(args.clone()).unwrap_or_else(|error| {
+        invalid_arguments(error, &args);
+    });
+    log::init(logging.level, logging.format);
+    let (config, args) = ConfigArgs::extract(args.clone()).unwrap_or_else(|error| {
+        invalid_arguments(error, &args);
+    });
+    let args = with_config(&config, args);
+    let command = Command::parse(args.clone()).unwrap_or_else(|error| {
+        invalid_arguments(error, &args);
+    });
+    match command {
+        Command::Compute(compute) if compute.watch => {
+            report(|output| watch(output, *compute, logging, args))
+        }
+        Command::Compute(args) => report(|output| compute(output, *args)),
+        Command::Bench(args) => report(|output| bench(output, args)),
+        Command::Validate(args) => report(|output| validate(output, args)),
+        Command::Fmt(args) => report(|output| fmt(output, args)),
+        Command::Fuzz(args) => report(|output| fuzz(output, args)),
+        Command::Matrix(args) => report(|output| matrix(output, args)),
+        Command::Area(args) => report(|output| area(output, args)),
+        Command::CompareFormulas(args) => report(|output| compare_formulas(output, args)),
+        Command::Generate(args) => generate(args),
+        Command::Split(args) => split(args),
+        Command::Merge(args) => merge(args),
+        Command::ExportColumnar(args) => export_columnar(args),
+        Command::Diff(args) => report(|output| diff(output, args)),
+        Command::Help(command) => report(|output| write!(output, "{}", cli::help(command))),
+        Command::Completions(shell) => {
+            report(|output| write!(output, "{}", cli::completions(shell)))
+        }
+        Command::Repl(args) => report(|output| repl::run(output, args)),
+        Command::Dist(args) => report(|output| dist(output, args)),
+        Command::Inspect(args) => report(|output| inspect(output, args)),
+        Command::Extract(args) => extract(args),
+        Command::Tokens(args) => report(|output| tokens(output, args)),
+        Command::Tree(args) => report(|output| tree(output, args)),
+    }
 }
 
-fn calculate_haversine_distance(
-    radius: f64,
-    phi_0: f64,
-    theta_0: f64,
-    phi_1: f64,
-    theta_1: f64,
-) -> f64 {
-    let phi_0_radians = degrees_to_radians(phi_0);
-    let phi_1_radians = degrees_to_radians(phi_1);
-    let theta_0_radians = degrees_to_radians(theta_0);
-    let theta_1_radians = degrees_to_radians(theta_1);
+/// Runs a subcommand writing its report to standard output, exiting if the
+/// report cannot be written.
+fn report(command: impl FnOnce(&mut dyn OutputSink) -> io::Result<()>) {
+    let mut output = StdoutSink;
+    if let Err(error) = command(&mut output).and_then(|()| output.flush()) {
+        io_failure("Failed to write the report", error);
+    }
+}
+
+/// Exit code when the stored average does not match the computed one.
+const EXIT_MISMATCH: i32 = 1;
+/// Exit code when the input is not valid JSON or not a valid pairs file.
+const EXIT_INVALID: i32 = 2;
+/// Exit code when a file cannot be read or written.
+const EXIT_IO: i32 = 3;
+/// Exit code when a run is stopped by its timeout.
+const EXIT_TIMEOUT: i32 = 4;
 
-    let delta_thetas = theta_1_radians - theta_0_radians;
-    let delta_phis = phi_1_radians - phi_0_radians;
-    let root_term_1 = (delta_thetas / 2.0).sin().powi(2);
-    let root_term_2 =
-        theta_0_radians.cos() * theta_1_radians.cos() * (delta_phis / 2.0).sin().powi(2);
-    let root_term = root_term_1 + root_term_2;
-    2.0 * radius * root_term.sqrt().asin()
+/// Logs `message` as an error and exits with `code`.
+fn fail(code: i32, message: impl Display) -> ! {
+    log::error(message);
+    std::process::exit(code)
 }
 
-fn main() {
-    let start_parsing = Instant::now();
+/// Exits on arguments that do not parse, with the usage of the subcommand
+/// of `args`.
+fn invalid_arguments(error: ArgsError, args: &[String]) -> ! {
+    let command = args
+        .first()
+        .and_then(|name| cli::find_command(name))
+        .unwrap_or(&cli::COMMANDS[0]);
+    log::error(format_args!("Invalid arguments: {error}"));
+    eprintln!(
+        "{}\n\nRun `haversine {} --help` for its options.",
+        cli::usage(Some(command)),
+        command.name
+    );
+    std::process::exit(EXIT_INVALID)
+}
 
-    let file_name = "../gendata/pairs.json";
-    let mut file = File::open(file_name).expect("Failed to open file");
-    let mut string = String::new();
-    let _n = file
-        .read_to_string(&mut string)
-        .expect("Failed to read file");
+/// Exits on an I/O error, with the code of invalid input if the error is
+/// that the input is not UTF-8.
+fn io_failure(context: &str, error: io::Error) -> ! {
+    let code = match error.kind() {
+        io::ErrorKind::InvalidData => EXIT_INVALID,
+        _ => EXIT_IO,
+    };
+    fail(code, format!("{context}: {error}"))
+}
 
-    let json = match Parser::new(&string).parse().unwrap().unwrap() {
-        Value::Object(object) => object,
-        _ => panic!("Invalid pairs file"),
+/// Adds the defaults of the config file to the arguments: the file given
+/// with `--config`, or else `haversine.json` in the working directory if
+/// there is one.
+fn with_config(args: &ConfigArgs, rest: Vec<String>) -> Vec<String> {
+    let path = match &args.path {
+        _ if args.disabled => return rest,
+        Some(path) => path.as_str(),
+        None if Path::new(config::DEFAULT_PATH).is_file() => config::DEFAULT_PATH,
+        None => return rest,
     };
+    let applied = config::load(path).and_then(|defaults| config::apply(&defaults, rest));
+    match applied {
+        Ok(args) => {
+            log::debug(format_args!("Read the defaults of the options from {path}"));
+            args
+        }
+        Err(ConfigError::Io(error)) => io_failure(&format!("Failed to read {path}"), error),
+        Err(error) => fail(EXIT_INVALID, format!("Invalid config file {path}: {error}")),
+    }
+}
 
-    let end_parsing = Instant::now();
+/// Largest number of JSON errors `validate` reports.
+const MAX_PRINTED_DIAGNOSTICS: usize = 20;
 
-    let average_distance = match json.get("avg_dist").expect("Expected to exist") {
-        Value::Number(avg) => avg,
-        _ => panic!("Invalid pairs file"),
-    };
-
-    let radius = json
-        .get("radius")
-        .expect("Expected to exist")
-        .try_into()
-        .unwrap();
-
-    let pairs = match json.get("pairs").expect("Expected to exist") {
-        Value::Array(array) => array,
-        _ => panic!("Invalid pairs file"),
-    };
-
-    let start_computing = Instant::now();
-
-    let mut sum = 0.0;
-    let n_pairs = pairs.len();
-    println!("Number of pairs: {n_pairs}");
-    println!("Radius: {radius}");
-    for pair in pairs {
-        let (phi_0, theta_0, phi_1, theta_1) = match pair {
-            Value::Object(obj) => {
-                let phi_0 = obj
-                    .get("x0")
-                    .expect("Expected to exist")
-                    .try_into()
-                    .expect("Is number");
-                let phi_1 = obj
-                    .get("x1")
-                    .expect("Expected to exist")
-                    .try_into()
-                    .expect("Is number");
-                let theta_0 = obj
-                    .get("y0")
-                    .expect("Expected to exist")
-                    .try_into()
-                    .expect("Is number");
-                let theta_1 = obj
-                    .get("y1")
-                    .expect("Expected to exist")
-                    .try_into()
-                    .expect("Is number");
-
-                (phi_0, theta_0, phi_1, theta_1)
-            }
-            _ => panic!("Invalid pair"),
+/// Validates a pairs file, exiting with `EXIT_INVALID` if it is invalid and
+/// `EXIT_MISMATCH` if only its average is off. Every JSON error in the file
+/// is reported, not just the first.
+fn validate(output: &mut dyn OutputSink, args: ValidateArgs) -> io::Result<()> {
+    let string = read_input(&args.path);
+    let (document, diagnostics) = Parser::new(&string).parse_recovering();
+    for diagnostic in diagnostics.iter().take(MAX_PRINTED_DIAGNOSTICS) {
+        let (line, column) = diagnostic.line_column(&string);
+        writeln!(
+            output,
+            "Invalid JSON at {line}:{column}: {}",
+            diagnostic.error
+        )?;
+        write!(output, "{}", diagnostic.snippet(&string))?;
+    }
+    if diagnostics.len() > MAX_PRINTED_DIAGNOSTICS {
+        writeln!(
+            output,
+            "... and {} more errors",
+            diagnostics.len() - MAX_PRINTED_DIAGNOSTICS
+        )?;
+    }
+    let document = match document {
+        Some(document) if diagnostics.is_empty() => document,
+        Some(_) => std::process::exit(EXIT_INVALID),
+        None => {
+            writeln!(output, "Invalid JSON: the file is empty")?;
+            std::process::exit(EXIT_INVALID);
+        }
+    };
+
+    let report = match args.reference {
+        true => validate::validate_reference(&document),
+        false => validate::validate(&document),
+    };
+    report.write(&mut *output, args.tolerance)?;
+    if report.has_errors() {
+        std::process::exit(EXIT_INVALID);
+    }
+    if !report.passed(args.tolerance) {
+        std::process::exit(EXIT_MISMATCH);
+    }
+    Ok(())
+}
+
+/// Writes the distance of the two points of the arguments.
+fn dist(output: &mut dyn OutputSink, args: DistArgs) -> io::Result<()> {
+    let [lat_0, lon_0, lat_1, lon_1] = args.coordinates;
+    let distance = match args.formula {
+        Formula::Haversine => haversine_distance,
+        Formula::Cosine => cosine_distance,
+        Formula::Vincenty => vincenty_distance,
+    };
+    let kilometers = distance(args.radius, lon_0, lat_0, lon_1, lat_1);
+    writeln!(
+        output,
+        "{} {}",
+        args.units.convert_kilometers(kilometers),
+        args.units.symbol()
+    )
+}
+
+/// Writes the statistics of a document, read in chunks so it may be larger
+/// than memory.
+fn inspect(output: &mut dyn OutputSink, args: InspectArgs) -> io::Result<()> {
+    match inspect::inspect(open_input(&args.path)) {
+        Ok(stats) => stats.write(output),
+        Err(haversine::Error::Io(error)) => io_failure("Failed to read file", error),
+        Err(error) => fail(EXIT_INVALID, format!("Invalid JSON: {error}")),
+    }
+}
+
+/// Copies the value at a path of a document, or a range of an array, to
+/// the output, reading the document only up to the end of it.
+fn extract(args: ExtractArgs) {
+    let selector = Selector::parse(&args.pointer)
+        .unwrap_or_else(|error| fail(EXIT_INVALID, format!("Invalid pointer: {error}")));
+    let mut output = sink::open(&args.output)
+        .unwrap_or_else(|error| io_failure(&format!("Failed to create {}", args.output), error));
+    let written = match extract::extract(open_input(&args.path), &selector, &mut output) {
+        Ok(written) => written,
+        Err(haversine::Error::Io(error)) => io_failure("Failed to extract", error),
+        Err(error) => fail(EXIT_INVALID, format!("Failed to extract: {error}")),
+    };
+    if let Err(error) = writeln!(output).and_then(|()| output.flush()) {
+        io_failure("Failed to write extract", error);
+    }
+    if args.output != "-" {
+        let values = match written {
+            1 => "1 value".to_string(),
+            n => format!("{n} values"),
         };
-        sum += calculate_haversine_distance(radius, phi_0, theta_0, phi_1, theta_1);
+        log::info(format_args!("Saved {values} in {}", args.output));
+    }
+}
+
+/// Writes the tokens of a document with their spans, up to the first
+/// tokenizer error.
+fn tokens(output: &mut dyn OutputSink, args: TokensArgs) -> io::Result<()> {
+    let input = read_input(&args.path);
+    let mut output = BufWriter::new(output);
+    let result = dump::dump_tokens(&input, args.format, &mut output);
+    output.flush()?;
+    match result {
+        Ok(_) => Ok(()),
+        Err(haversine::Error::Io(error)) => Err(error),
+        Err(error) => fail(EXIT_INVALID, format!("Invalid JSON: {error}")),
+    }
+}
+
+/// Writes a document as an indented tree, down to `--depth` if given.
+fn tree(output: &mut dyn OutputSink, args: TreeArgs) -> io::Result<()> {
+    let input = read_input(&args.path);
+    let document = parse_input(Parser::new(&input), &input)
+        .unwrap_or_else(|message| fail(EXIT_INVALID, format!("Invalid JSON: {message}")));
+    let mut tree = document.display_tree().max_items(args.items);
+    if let Some(depth) = args.depth {
+        tree = tree.max_depth(depth);
+    }
+    write!(output, "{tree}")
+}
+
+/// Writes the differences between two documents, exiting with
+/// `EXIT_MISMATCH` if there are any.
+fn diff(output: &mut dyn OutputSink, args: DiffArgs) -> io::Result<()> {
+    let [a, b] = args.paths.each_ref().map(|path| {
+        let input = read_input(path);
+        parse_input(Parser::new(&input), &input).unwrap_or_else(|message| {
+            fail(EXIT_INVALID, format!("Invalid JSON in {path}: {message}"))
+        })
+    });
+    let changes = a.diff_within(&b, args.tolerance);
+    for change in changes.iter().take(args.limit) {
+        writeln!(output, "{change}")?;
+    }
+    match changes.len() {
+        0 => writeln!(output, "No differences"),
+        n if n > args.limit => writeln!(output, "... {n} differences in all"),
+        n => writeln!(output, "{n} difference{}", if n == 1 { "" } else { "s" }),
+    }?;
+    if !changes.is_empty() {
+        process::exit(EXIT_MISMATCH);
+    }
+    Ok(())
+}
+
+/// Parses a whole document, describing why if it is not valid JSON with
+/// the lines of `source` that lead to the error.
+fn parse_input(parser: Parser, source: &str) -> Result<Value, String> {
+    match parser.parse_located() {
+        Ok(Some(document)) => Ok(document),
+        Ok(None) => Err("the file is empty".into()),
+        Err(diagnostic) => {
+            let (line, column) = diagnostic.line_column(source);
+            let snippet = diagnostic.snippet(source);
+            Err(format!(
+                "line {line}, column {column}: {}\n{}",
+                diagnostic.error,
+                snippet.trim_end()
+            ))
+        }
+    }
+}
+
+/// Writes the document reformatted, exiting with `EXIT_INVALID` if it is
+/// not valid JSON.
+fn fmt(output: &mut dyn OutputSink, args: FmtArgs) -> io::Result<()> {
+    let input = read_input(&args.path);
+    let parser = Parser::new(&input).raw_numbers(args.raw_numbers);
+    let mut document = match parse_input(parser, &input) {
+        Ok(document) => document,
+        Err(message) => fail(EXIT_INVALID, format!("Invalid JSON: {message}")),
+    };
+
+    if args.canonical {
+        return writeln!(output, "{}", document.to_canonical_json());
+    }
+    if args.sort_keys {
+        document.sort_keys();
+    }
+    match args.indent {
+        Some(indent) => writeln!(output, "{}", document.to_json_pretty(indent)),
+        None => writeln!(output, "{}", document.to_json()),
+    }
+}
+
+/// Runs the randomized parser checks, panicking on the first failure.
+fn fuzz(output: &mut dyn OutputSink, args: FuzzArgs) -> io::Result<()> {
+    haversine::fuzz::run(args.seed, args.iterations);
+    writeln!(
+        output,
+        "Checked {} generated documents with seed {}",
+        args.iterations, args.seed
+    )
+}
+
+/// Computes the distance matrix of a points file, an object with a `points`
+/// array of `x`, `y` objects and optionally a `radius`.
+fn matrix(output: &mut dyn OutputSink, args: MatrixArgs) -> io::Result<()> {
+    let json = read_document(
+        &args.path,
+        DuplicateKeyPolicy::default(),
+        LoneSurrogatePolicy::default(),
+    );
+    let points = points_of(&json);
+    let radius = points_radius(&json, args.radius_model, args.units);
+
+    let mut driver = MatrixDriver::new(radius)
+        .shape(args.shape)
+        .kernel(args.kernel);
+    if let Some(threads) = args.threads {
+        driver = driver.threads(threads);
+    }
+    if let (Some(checkpoint), Some(path)) = (&args.checkpoint.path, &args.output) {
+        matrix_checkpointed(&driver, &points, path, checkpoint, &args.checkpoint);
+        return Ok(());
     }
+    log::debug(format_args!(
+        "Computing the {:?} matrix of {} points with the {:?} kernel",
+        args.shape,
+        points.len(),
+        args.kernel
+    ));
+    let matrix = driver.run(&points);
 
-    let avg = sum / (n_pairs as f64);
+    let Some((path, format)) = &args.output else {
+        return matrix.write(output, MatrixFormat::Csv);
+    };
+    let file = FileSink::create(path)
+        .unwrap_or_else(|error| io_failure("Failed to create matrix file", error));
+    if let Err(error) = matrix.write(file, *format) {
+        io_failure("Failed to write matrix", error);
+    }
+    Ok(())
+}
 
-    let end_computing = Instant::now();
+/// Rows of a matrix computed and written between two checks for a
+/// checkpoint.
+const CHECKPOINT_ROWS: usize = 256;
+
+/// Computes a matrix a few rows at a time, appending them to the output
+/// file and saving the rows done to the checkpoint file.
+fn matrix_checkpointed(
+    driver: &MatrixDriver,
+    points: &[Point],
+    (output, format): &(String, MatrixFormat),
+    checkpoint_path: &str,
+    args: &CheckpointArgs,
+) {
+    let fingerprint = driver.fingerprint(points);
+    let resume = resume_checkpoint(checkpoint_path, args, || fingerprint);
+    let mut checkpoint = resume.unwrap_or(Checkpoint {
+        fingerprint,
+        ..Checkpoint::default()
+    });
+    if resume.is_some() {
+        log::info(format_args!("Resuming from row {}", checkpoint.position));
+    }
 
-    println!(
-        "Difference between read and computed value: {}",
-        average_distance - avg
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(resume.is_none())
+        .open(output)
+        .unwrap_or_else(|error| io_failure("Failed to create matrix file", error));
+    let written = append_rows(
+        driver,
+        points,
+        file,
+        *format,
+        &mut checkpoint,
+        |checkpoint| save_checkpoint(checkpoint_path, checkpoint),
+        args.interval,
     );
+    if let Err(error) = written {
+        io_failure("Failed to write matrix", error);
+    }
+    remove_checkpoint(checkpoint_path);
+}
+
+/// Appends the rows of the matrix after those of `checkpoint` to `file`,
+/// first cutting it to the length they were saved with, and passes `save`
+/// the rows done about every `interval`.
+fn append_rows(
+    driver: &MatrixDriver,
+    points: &[Point],
+    mut file: File,
+    format: MatrixFormat,
+    checkpoint: &mut Checkpoint,
+    save: impl Fn(&Checkpoint),
+    interval: Duration,
+) -> io::Result<()> {
+    file.set_len(checkpoint.output_len)?;
+    file.seek(SeekFrom::End(0))?;
+    let mut last_saved = Instant::now();
+    let len = points.len();
+    while checkpoint.position < len {
+        let rows = checkpoint.position..(checkpoint.position + CHECKPOINT_ROWS).min(len);
+        let distances = driver.run_rows(points, rows.clone());
+        let shape = driver.shape_of();
+        write_rows(
+            BufWriter::new(&file),
+            shape,
+            len,
+            rows.clone(),
+            &distances,
+            format,
+        )?;
+        checkpoint.position = rows.end;
+        checkpoint.output_len = file.stream_position()?;
+        if last_saved.elapsed() >= interval {
+            file.sync_data()?;
+            save(checkpoint);
+            last_saved = Instant::now();
+        }
+    }
+    Ok(())
+}
+
+/// Reads the checkpoint to resume from, if resuming and there is one,
+/// exiting if it belongs to another run, whose `fingerprint` is only
+/// computed then.
+fn resume_checkpoint(
+    path: &str,
+    args: &CheckpointArgs,
+    fingerprint: impl FnOnce() -> u64,
+) -> Option<Checkpoint> {
+    if !args.resume {
+        return None;
+    }
+    match Checkpoint::read(path) {
+        Ok(Some(checkpoint)) if checkpoint.fingerprint == fingerprint() => Some(checkpoint),
+        Ok(Some(_)) => fail(
+            EXIT_INVALID,
+            format!("The checkpoint {path} belongs to another input or settings"),
+        ),
+        Ok(None) => {
+            log::warn(format_args!(
+                "No checkpoint at {path}, starting from the beginning"
+            ));
+            None
+        }
+        Err(CheckpointError::Io(error)) => io_failure("Failed to read checkpoint", error),
+        Err(error) => fail(EXIT_INVALID, format!("Invalid checkpoint: {error}")),
+    }
+}
+
+fn save_checkpoint(path: &str, checkpoint: &Checkpoint) {
+    if let Err(error) = checkpoint.write(path) {
+        io_failure("Failed to write checkpoint", error);
+    }
+    log::trace(format_args!(
+        "Saved a checkpoint at {} to {path}",
+        checkpoint.position
+    ));
+}
 
-    println!(
-        "Parsing time: {}",
-        end_parsing.duration_since(start_parsing).as_secs_f64()
+/// Removes the checkpoint of a finished run, so it is not resumed.
+fn remove_checkpoint(path: &str) {
+    match std::fs::remove_file(path) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => {
+            io_failure("Failed to remove checkpoint", error)
+        }
+        _ => {}
+    }
+}
+
+/// Computes the area of the polygon through the points of a points file, in
+/// order.
+fn area(output: &mut dyn OutputSink, args: AreaArgs) -> io::Result<()> {
+    let json = read_document(
+        &args.path,
+        DuplicateKeyPolicy::default(),
+        LoneSurrogatePolicy::default(),
     );
-    println!(
+    let points = points_of(&json);
+    let radius = points_radius(&json, args.radius_model, args.units);
+    let unit = args.units.unwrap_or_default().symbol();
+    writeln!(output, "Number of points: {}", points.len())?;
+    writeln!(output, "Radius: {radius}")?;
+    writeln!(
+        output,
+        "Area: {} {unit}²",
+        spherical_polygon_area(&points, radius)
+    )
+}
+
+/// Writes how long each distance formula takes on the pairs of a file and
+/// how far apart their distances are.
+fn compare_formulas(output: &mut dyn OutputSink, args: CompareFormulasArgs) -> io::Result<()> {
+    let (header, pairs) = pairs_document(&args.path);
+    let radius = points_radius(&header, args.radius_model, args.units);
+    let unit = args.units.unwrap_or_default().symbol();
+    let comparison = compare::compare_formulas(&pairs, radius);
+    writeln!(output, "Number of pairs: {}", comparison.n_pairs)?;
+    writeln!(output, "Radius: {radius}")?;
+    for (formula, time) in &comparison.times {
+        writeln!(output, "{}: {} s", formula.name(), time.as_secs_f64())?;
+    }
+    for deviation in &comparison.deviations {
+        writeln!(
+            output,
+            "{} versus {}: max {:e} {unit} (pair {}), mean {:e} {unit}",
+            deviation.first.name(),
+            deviation.second.name(),
+            deviation.max,
+            deviation.worst_pair,
+            deviation.mean,
+        )?;
+    }
+    Ok(())
+}
+
+/// Reads the `points` array of a points file, exiting if it is invalid.
+fn points_of(json: &Map) -> Vec<Point> {
+    match json.get("points") {
+        Some(Value::Array(array)) => match points_from_values(array) {
+            Ok(points) => points,
+            Err(error) => fail(EXIT_INVALID, format!("Invalid points file: {error}")),
+        },
+        _ => fail(EXIT_INVALID, "Expected a points array"),
+    }
+}
+
+/// Radius of a points file in `units`: that of `radius_model` if given,
+/// else its `radius`, else the mean radius of the Earth.
+fn points_radius(
+    json: &Map,
+    radius_model: Option<RadiusModel>,
+    units: Option<DistanceUnit>,
+) -> f64 {
+    let file_radius = json.get("radius").map(number_field("radius"));
+    let radius_km = match (radius_model, file_radius) {
+        (Some(model), _) => model.kilometers(),
+        (None, Some(radius)) => radius,
+        (None, None) => RadiusModel::Mean.kilometers(),
+    };
+    units.unwrap_or_default().convert_kilometers(radius_km)
+}
+
+/// Writes a pairs file of random pairs with their average distance.
+fn generate(args: GenerateArgs) {
+    let mut rng = args.rng.seeded(args.seed);
+    let mut pairs = match args.preset {
+        Some(preset) => generator::generate_preset(rng.as_mut(), args.pairs, preset),
+        None => generate_pairs(rng.as_mut(), args.pairs, args.distribution),
+    };
+    if let Some(digits) = args.digits {
+        generator::round_coordinates(&mut pairs, digits);
+    }
+    let (document, answers) = match args.preset {
+        Some(_) => {
+            let answers = Answers::reference(&pairs, args.radius);
+            let document = generator::document_with_average(&pairs, args.radius, answers.average);
+            (document, answers)
+        }
+        None => generator::document_with_answers(&pairs, args.radius),
+    };
+    write_pairs_file(&args.output, &document, args.pretty);
+    if args.output != "-" {
+        log::info(format_args!(
+            "Saved {} pairs in {}",
+            pairs.len(),
+            args.output
+        ));
+    }
+    if let Some(path) = &args.answers {
+        let written = FileSink::create(path).and_then(|file| answers.write(file));
+        if let Err(error) = written {
+            io_failure("Failed to write answers file", error);
+        }
+        log::info(format_args!("Saved the answers in {path}"));
+    }
+}
+
+/// Writes a pairs document to `path`, or stdout if the path is `-`,
+/// indented if `pretty`.
+fn write_pairs_file(path: &str, document: &Value, pretty: bool) {
+    let json = match pretty {
+        true => document.to_json_pretty(2),
+        false => document.to_json(),
+    };
+    let written = sink::open(path).and_then(|mut output| {
+        writeln!(output, "{json}")?;
+        output.flush()
+    });
+    if let Err(error) = written {
+        io_failure("Failed to write pairs file", error);
+    }
+}
+
+fn split(args: SplitArgs) {
+    let (header, pairs) = pairs_document(&args.path);
+    let shards = shard::split(&header, &pairs, args.shards)
+        .unwrap_or_else(|error| fail(EXIT_INVALID, format!("Invalid pairs file: {error}")));
+    // Zero-padded, so the shards sort in order.
+    let width = (shards.len() - 1).to_string().len();
+    for (index, document) in shards.iter().enumerate() {
+        let path = format!("{}.{index:0width$}.json", args.prefix);
+        write_pairs_file(&path, document, true);
+        log::debug(format_args!("Saved shard {path}"));
+    }
+    log::info(format_args!(
+        "Saved {} pairs in {} shards",
+        pairs.len(),
+        shards.len()
+    ));
+}
+
+fn merge(args: MergeArgs) {
+    let shards: Vec<_> = args.paths.iter().map(|path| pairs_document(path)).collect();
+    let document = shard::merge(&shards)
+        .unwrap_or_else(|error| fail(EXIT_INVALID, format!("Invalid shards: {error}")));
+    write_pairs_file(&args.output, &document, true);
+    if args.output != "-" {
+        let pairs: usize = shards.iter().map(|(_, pairs)| pairs.len()).sum();
+        log::info(format_args!("Saved {pairs} pairs in {}", args.output));
+    }
+}
+
+fn export_columnar(args: ExportColumnarArgs) {
+    let (header, pairs) = pairs_document(&args.path);
+    let radius = required_number(&header, "radius");
+    let average = header.get("avg_dist").map(number_field("avg_dist"));
+    let written = sink::open(&args.output)
+        .and_then(|output| columnar::write(output, &pairs, radius, average));
+    if let Err(error) = written {
+        io_failure("Failed to write columnar file", error);
+    }
+    if args.output != "-" {
+        log::info(format_args!(
+            "Saved {} pairs in {}",
+            pairs.len(),
+            args.output
+        ));
+    }
+}
+
+/// Reads the pairs document at `path`, with the fast schema if it fits.
+fn pairs_document(path: &str) -> (Map, Pairs) {
+    let string = read_input(path);
+    parse_fast_schema(&string).unwrap_or_else(|| {
+        PairsReader::new()
+            .read(string.as_bytes())
+            .unwrap_or_else(|error| load_failure(error.into()))
+    })
+}
+
+fn bench(output: &mut dyn OutputSink, args: BenchArgs) -> io::Result<()> {
+    match args.mode {
+        BenchMode::Bandwidth {
+            access,
+            sizes,
+            stride,
+            total_bytes,
+        } => {
+            for access in access {
+                reptest::bandwidth::run(output, access, &sizes, stride, total_bytes, args.seconds)?;
+            }
+            Ok(())
+        }
+        BenchMode::Traversal { path } => {
+            let json = read_document(
+                &path,
+                DuplicateKeyPolicy::default(),
+                LoneSurrogatePolicy::default(),
+            );
+            let radius = required_number(&json, "radius");
+            let pairs = match json.get("pairs") {
+                Some(Value::Array(array)) => array,
+                _ => fail(EXIT_INVALID, "Expected a pairs array"),
+            };
+            reptest::traversal::run(output, pairs, radius, args.seconds)
+        }
+        BenchMode::Structural { path } => {
+            let input = read_input(&path);
+            if u32::try_from(input.len()).is_err() {
+                fail(EXIT_INVALID, "Input too long for the structural scan");
+            }
+            reptest::structural::run(output, &input, args.seconds)
+        }
+        BenchMode::Parse { path } => {
+            let input = read_input(&path);
+            reptest::parse::run(output, &input, args.seconds)
+        }
+        BenchMode::Io { path, read_buffer } => {
+            let bytes = std::fs::metadata(&path)
+                .unwrap_or_else(|error| io_failure("Failed to read file", error))
+                .len();
+            reptest::io::run(output, &path, bytes, read_buffer, args.seconds)
+        }
+        BenchMode::Kernels { path } => {
+            let (header, pairs) = pairs_document(&path);
+            let radius = required_number(&header, "radius");
+            reptest::kernels::run(output, &pairs, radius, args.seconds)
+        }
+        BenchMode::Cache {
+            path,
+            runs,
+            drop_cache,
+        } => match reptest::cache::run(&path, runs, drop_cache) {
+            Ok(runs) => runs.write(output),
+            Err(haversine::Error::Io(error)) => io_failure("Failed to read file", error),
+            Err(error) => fail(EXIT_INVALID, format!("Invalid pairs file: {error}")),
+        },
+    }
+}
+
+/// Opens the file at `path`, or stdin if the path is `-`.
+fn open_input(path: &str) -> Box<dyn Read + Send> {
+    match path {
+        "-" => Box::new(io::stdin()),
+        path => Box::new(
+            File::open(path).unwrap_or_else(|error| io_failure("Failed to open file", error)),
+        ),
+    }
+}
+
+/// Size of the file at `path`, unknown for stdin.
+fn input_size(path: &str) -> Option<usize> {
+    match path {
+        "-" => None,
+        path => std::fs::metadata(path).ok().map(|meta| meta.len() as usize),
+    }
+}
+
+/// Reads the whole file at `path`, or stdin if the path is `-`.
+fn read_input(path: &str) -> String {
+    read_all(open_input(path), false)
+}
+
+/// Reads all of `reader` as UTF-8, exiting if it is in another encoding
+/// unless asked to `transcode` it.
+fn read_all(mut reader: impl Read, transcode: bool) -> String {
+    let mut bytes = Vec::new();
+    if let Err(error) = reader.read_to_end(&mut bytes) {
+        io_failure("Failed to read file", error);
+    }
+    text(Cow::Owned(bytes), transcode).into_owned()
+}
+
+/// Checks that `bytes` are UTF-8, exiting if they are in another encoding
+/// unless asked to `transcode` them.
+fn text(bytes: Cow<'_, [u8]>, transcode: bool) -> Cow<'_, str> {
+    match detect_encoding(&bytes) {
+        Encoding::Utf8 => match bytes {
+            Cow::Borrowed(bytes) => std::str::from_utf8(bytes).ok().map(Cow::Borrowed),
+            Cow::Owned(bytes) => String::from_utf8(bytes).ok().map(Cow::Owned),
+        }
+        .unwrap_or_else(|| fail(EXIT_INVALID, "Failed to read file: not valid UTF-8")),
+        encoding if transcode => {
+            log::debug(format_args!("Transcoding the input from {encoding}"));
+            let string = decode(bytes.into_owned()).unwrap_or_else(|error| {
+                fail(EXIT_INVALID, format!("Failed to transcode file: {error}"))
+            });
+            Cow::Owned(string)
+        }
+        encoding => fail(
+            EXIT_INVALID,
+            format!("The input is {encoding}, not UTF-8; convert it or use --transcode"),
+        ),
+    }
+}
+
+/// Reads and parses the JSON document at `path`, which must be an object.
+fn read_document(
+    path: &str,
+    duplicate_keys: DuplicateKeyPolicy,
+    lone_surrogates: LoneSurrogatePolicy,
+) -> Map {
+    parse_document(&read_input(path), duplicate_keys, lone_surrogates)
+}
+
+/// Parses a JSON document, which must be an object.
+fn parse_document(
+    string: &str,
+    duplicate_keys: DuplicateKeyPolicy,
+    lone_surrogates: LoneSurrogatePolicy,
+) -> Map {
+    let parser = Parser::new(string)
+        .duplicate_keys(duplicate_keys)
+        .lone_surrogates(lone_surrogates);
+    match parse_input(parser, string) {
+        Ok(Value::Object(object)) => object,
+        Ok(_) => fail(EXIT_INVALID, "Invalid pairs file: expected an object"),
+        Err(message) => fail(EXIT_INVALID, format!("Invalid JSON: {message}")),
+    }
+}
+
+/// Reads the number at `key` of a document, exiting if it is missing.
+fn required_number(json: &Map, key: &str) -> f64 {
+    match json.get(key) {
+        Some(value) => number_field(key)(value),
+        None => fail(EXIT_INVALID, format!("Expected a number at {key}")),
+    }
+}
+
+/// Converts the value at `key` to a number, exiting if it is not one.
+fn number_field(key: &str) -> impl Fn(&Value) -> f64 {
+    move |value| {
+        value
+            .try_into()
+            .unwrap_or_else(|_| fail(EXIT_INVALID, format!("Expected a number at {key}")))
+    }
+}
+
+fn compute(output: &mut dyn OutputSink, args: ComputeArgs) -> io::Result<()> {
+    if args.mode == ComputeMode::Route {
+        return route(output, args);
+    }
+    let cancel = args.timeout.map(CancelToken::with_timeout);
+    let poly = args.poly_math.then(|| PolyMath::new(args.poly_degree));
+    let reading = args.progress.then(|| {
+        let streamed = args.stream || args.format == InputFormat::Ndjson;
+        let label = if streamed { "Parsed" } else { "Read" };
+        Progress::start(label, Unit::Bytes, input_size(&args.path))
+    });
+    let mut pipeline = pipeline(&args, poly.clone(), cancel.as_ref(), reading.as_ref());
+
+    memory::reset_peak_live();
+    let memory_parsing = MemoryStats::now();
+    let start_parsing = Instant::now();
+    let start_parsing_cycles = read_cpu_timer();
+    let loaded = pipeline.load().unwrap_or_else(|error| load_failure(error));
+    if let Some(reading) = reading {
+        reading.finish();
+    }
+    for warning in &loaded.warnings {
+        log::warn(warning);
+    }
+    // Only JSON Lines have no average to check the distances against.
+    if args.format == InputFormat::Json && loaded.stored_average.is_none() {
+        fail(EXIT_INVALID, "Expected a number at avg_dist");
+    }
+    let radius = loaded.radius;
+    let radius_from = match (args.radius_model, args.format) {
+        (Some(_), _) => "model",
+        (None, InputFormat::Ndjson) => "mean of the Earth",
+        (None, _) => "file",
+    };
+    log::debug(format_args!(
+        "Read {} bytes from {}",
+        loaded.bytes, args.path
+    ));
+    log::debug(format_args!(
+        "Parsed {} pairs, radius {radius} {} from the {radius_from}",
+        loaded.pairs.len(),
+        args.units.unwrap_or_default().symbol()
+    ));
+
+    let end_parsing = Instant::now();
+    let parsing_cycles = read_cpu_timer() - start_parsing_cycles;
+    let memory_computing = MemoryStats::now();
+
+    write_loaded(output, &loaded, poly.as_ref(), &args)?;
+    log::debug(format_args!(
+        "Computing with the {:?} kernel in {:?} on {} threads, {:?} reduction",
+        args.kernel, args.precision, args.threads, args.reduction
+    ));
+    let result = if args.progress {
+        let computing = Progress::start("Computed", Unit::Pairs, Some(loaded.pairs.len()));
+        let result = pipeline.run_loaded(&loaded, computing.counter());
+        computing.finish();
+        result
+    } else {
+        pipeline.run_loaded(&loaded, &AtomicUsize::new(0))
+    };
+    let result = result.unwrap_or_else(|error| run_failure(error, &args));
+    if let Some(position) = result.resumed_from {
+        log::info(format_args!("Resuming from pair {position}"));
+    } else if let Some(path) = args
+        .checkpoint
+        .path
+        .as_deref()
+        .filter(|_| args.checkpoint.resume)
+    {
+        log::warn(format_args!(
+            "No checkpoint at {path}, starting from the beginning"
+        ));
+    }
+    if result.saved_checkpoints > 0 {
+        log::trace(format_args!(
+            "Saved {} checkpoints to {}",
+            result.saved_checkpoints,
+            args.checkpoint.path.as_deref().unwrap_or_default()
+        ));
+    }
+
+    write_outcome(output, &loaded, &result, &args)?;
+    let parsing_memory = memory_computing.since(&memory_parsing);
+    let parsing = (end_parsing.duration_since(start_parsing), parsing_cycles);
+    write_timings(output, &loaded, &result, parsing, &parsing_memory, &args)?;
+
+    if result.mismatch() {
+        fail(
+            EXIT_MISMATCH,
+            format!(
+                "The computed average differs from the stored one by more than the tolerance {}",
+                args.tolerance
+            ),
+        );
+    }
+    if result.answers.is_some_and(|answers| answers.off > 0) {
+        fail(
+            EXIT_MISMATCH,
+            format!(
+                "Computed distances differ from the answers by more than the tolerance {}",
+                args.tolerance
+            ),
+        );
+    }
+    if result.cancelled {
+        process::exit(EXIT_TIMEOUT);
+    }
+    Ok(())
+}
+
+/// Writes the number of pairs loaded, the radius, the routes and the error
+/// of the polynomial math, if used.
+fn write_loaded(
+    output: &mut dyn OutputSink,
+    loaded: &Loaded,
+    poly: Option<&PolyMath>,
+    args: &ComputeArgs,
+) -> io::Result<()> {
+    let n_pairs = loaded.pairs.len();
+    writeln!(output, "Number of pairs: {n_pairs}")?;
+    if let Some(excluded) = loaded.excluded {
+        writeln!(
+            output,
+            "Bounding box: {n_pairs} pairs included, {excluded} excluded"
+        )?;
+    }
+    let radius = loaded.radius;
+    writeln!(output, "Radius: {radius}")?;
+    if !loaded.routes.is_empty() {
+        let length: f64 = loaded
+            .routes
+            .iter()
+            .map(|route| path_length(radius, route))
+            .sum();
+        let unit = args.units.unwrap_or_default().symbol();
+        writeln!(
+            output,
+            "Routes: {}, total length {length} {unit}",
+            loaded.routes.len()
+        )?;
+    }
+    if let Some(poly) = poly {
+        let errors = poly.max_errors();
+        writeln!(
+            output,
+            "Polynomial math of degree {}: largest error {:e} for sin, {:e} for cos, {:e} for asin",
+            poly.degree(),
+            errors.sin,
+            errors.cos,
+            errors.asin
+        )?;
+    }
+    Ok(())
+}
+
+/// Writes the averages of a run and what was checked and computed of its
+/// distances.
+fn write_outcome(
+    output: &mut dyn OutputSink,
+    loaded: &Loaded,
+    result: &RunResult,
+    args: &ComputeArgs,
+) -> io::Result<()> {
+    let avg = result.average;
+    if result.cancelled {
+        log::warn(format_args!(
+            "Timed out: computed {} of {} pairs, the results cover those only",
+            result.n_pairs,
+            loaded.pairs.len()
+        ));
+    } else if let Some(baseline) = &result.baseline {
+        writeln!(
+            output,
+            "f32 speedup: {:.2}x over f64 ({}s versus {}s)",
+            baseline.speedup,
+            result.compute_time.as_secs_f64(),
+            baseline.compute_time.as_secs_f64()
+        )?;
+        writeln!(
+            output,
+            "f32 accuracy: average off by {}, a relative error of {:e}",
+            avg - baseline.average,
+            baseline.relative_error
+        )?;
+    }
+
+    if let Some(population) = result.population {
+        write_estimate(output, result, population)?;
+    } else if let AverageCheck::Within(stored) | AverageCheck::Outside(stored) = result.check {
+        writeln!(
+            output,
+            "Difference between read and computed value: {}",
+            stored - avg
+        )?;
+    } else {
+        let unit = args.units.unwrap_or_default().symbol();
+        writeln!(output, "Average distance: {avg} {unit}")?;
+    }
+    if let Some(answers) = &result.answers {
+        write_answers(output, answers)?;
+    }
+
+    if let Some(wide_avg) = result.wide_average {
+        writeln!(output, "Wide accumulator average: {wide_avg}")?;
+        writeln!(
+            output,
+            "Difference between f64 and wide accumulation: {}",
+            avg - wide_avg
+        )?;
+    }
+
+    if args.stats || args.stats_json || args.histogram.is_some() {
+        write_stats(output, result, args.stats_json)?;
+    }
+
+    // The distances of a cancelled run may not be those of the first pairs.
+    if result.cancelled {
+        if args.per_pair.is_some() || args.export_geojson.is_some() {
+            log::warn("Timed out, so no per-pair output is written");
+        }
+    } else if args.per_pair.is_none() && args.emit_bearings {
+        write_bearings(output, &loaded.pairs, loaded.radius, args.angle_unit)?;
+    }
+    Ok(())
+}
+
+/// Writes the time, throughput and memory of parsing, taking `parsing` time
+/// and cycles, and of computing.
+fn write_timings(
+    output: &mut dyn OutputSink,
+    loaded: &Loaded,
+    result: &RunResult,
+    parsing: (Duration, u64),
+    parsing_memory: &MemoryStats,
+    args: &ComputeArgs,
+) -> io::Result<()> {
+    let (parsing_time, parsing_cycles) = parsing;
+    writeln!(output, "Parsing time: {}", parsing_time.as_secs_f64())?;
+    write_parsing_throughput(output, loaded.bytes as usize, parsing_time, parsing_cycles)?;
+    writeln!(
+        output,
         "Computing time: {}",
-        end_computing.duration_since(start_computing).as_secs_f64()
+        result.compute_time.as_secs_f64()
+    )?;
+    write_throughput(
+        output,
+        result.n_pairs,
+        result.compute_time,
+        result.compute_cycles,
+        args,
+    )?;
+    // JSON Lines and streamed documents are parsed as they are read.
+    if let Some(strategy) = loaded.io.filter(|_| !loaded.read_time.is_zero()) {
+        writeln!(
+            output,
+            "Reading throughput: {}/s with {} I/O",
+            progress::bytes(loaded.bytes as f64 / loaded.read_time.as_secs_f64()),
+            strategy.name()
+        )?;
+    }
+    if let Some(overlap) = loaded.overlap {
+        writeln!(
+            output,
+            "Read overlap: {:.1}% of {:.3}s reading hidden behind parsing",
+            100.0 * overlap.efficiency(),
+            overlap.reading.as_secs_f64()
+        )?;
+    }
+    let memory_end = result.compute_memory.unwrap_or_default();
+    write_memory(output, "Parsing", parsing_memory)?;
+    write_memory(output, "Computing", &memory_end)?;
+    if let Some(peak) = memory_end.peak_rss {
+        writeln!(output, "Peak RSS: {}", progress::bytes(peak as f64))?;
+    }
+    Ok(())
+}
+
+/// The pipeline reading, parsing and computing the input of `args`, with
+/// `math` if not the standard one, adding the bytes read to `reading`.
+fn pipeline(
+    args: &ComputeArgs,
+    math: Option<PolyMath>,
+    cancel: Option<&CancelToken>,
+    reading: Option<&Progress>,
+) -> Pipeline {
+    let coordinates = match args.strict_coords {
+        true => Coordinates::Strict,
+        false => Coordinates::Canonicalize,
+    };
+    let pipeline = match args.path.as_str() {
+        "-" => Pipeline::new().stdin(),
+        path => Pipeline::new().input(path),
+    };
+    let mut pipeline = pipeline
+        .format(args.format)
+        .io(args.io)
+        .read_buffer(args.read_buffer)
+        .huge_pages(args.huge_pages)
+        .stream(args.stream)
+        .tape(args.tape)
+        .simd(args.simd)
+        .fast_schema(args.fast_schema)
+        .transcode(args.transcode)
+        .limits(args.limits)
+        .duplicate_keys(args.duplicate_keys)
+        .lone_surrogates(args.lone_surrogates)
+        .out_of_range(args.out_of_range)
+        .allow_control_chars(args.allow_control_chars)
+        .angle_unit(args.angle_unit)
+        .coordinates(coordinates)
+        .interleave(args.interleave)
+        .precision(args.precision)
+        .kernel(args.kernel)
+        .math(math.map_or(Math::Std, Math::Poly))
+        .threads(args.threads)
+        .reduction(args.reduction)
+        .pin_threads(args.pin_threads)
+        .wide_accumulator(args.wide_accumulator)
+        .tolerance(args.tolerance)
+        .stats(args.stats || args.stats_json)
+        .memory_stats(true);
+    if let Some(units) = args.units {
+        pipeline = pipeline.units(units);
+    }
+    if let Some(size) = args.sample {
+        pipeline = pipeline.sample(size, args.sample_seed);
+    }
+    if let Some(model) = args.radius_model {
+        pipeline = pipeline.radius(model.kilometers());
+    }
+    if let Some(bbox) = args.bbox {
+        pipeline = pipeline.bbox(bbox);
+    }
+    if let Some(token) = cancel {
+        pipeline = pipeline.cancel(token.clone());
+    }
+    if let Some(reading) = reading {
+        pipeline = pipeline.read_progress(reading.shared_counter());
+    }
+    if let Some(path) = &args.checkpoint.path {
+        pipeline = pipeline
+            .checkpoint(path, args.checkpoint.interval)
+            .resume(args.checkpoint.resume);
+    }
+    if let Some(path) = &args.answers {
+        pipeline = pipeline.answers(path);
+    }
+    if let Some(buckets) = args.histogram {
+        pipeline = pipeline.histogram(buckets);
+    }
+    if let Some((path, format)) = &args.per_pair {
+        let options = PerPairOptions {
+            format: *format,
+            coordinates: args.per_pair_coords,
+            bearings: args.emit_bearings,
+            midpoints: args.per_pair_midpoints,
+            angle_unit: args.angle_unit,
+        };
+        pipeline = pipeline.per_pair(path, options);
+    }
+    if let Some(path) = &args.export_geojson {
+        pipeline = pipeline.export_geojson(path);
+    }
+    pipeline
+}
+
+/// Exits with the message of an input the pipeline failed to load.
+fn load_failure(error: haversine::Error) -> ! {
+    use haversine::Error;
+    match error {
+        Error::Io(error) if error.kind() == io::ErrorKind::InvalidData => {
+            fail(EXIT_INVALID, format!("Failed to read file: {error}"))
+        }
+        Error::Io(error) => io_failure("Failed to read file", error),
+        Error::Encoding(encoding) => fail(
+            EXIT_INVALID,
+            format!("The input is {encoding}, not UTF-8; convert it or use --transcode"),
+        ),
+        Error::Cancelled => fail(EXIT_TIMEOUT, "Timed out reading the input"),
+        Error::Parsing(ParsingError::NoArrayAt(_)) => fail(EXIT_INVALID, "Expected a pairs array"),
+        Error::Parsing(error) => fail(EXIT_INVALID, format!("Invalid JSON: {error}")),
+        Error::Pairs(error) => pairs_error(error),
+        Error::Columnar(ColumnarError::Io(error)) => {
+            io_failure("Failed to read columnar file", error)
+        }
+        Error::Columnar(error) => fail(EXIT_INVALID, format!("Invalid columnar file: {error}")),
+        Error::NotAnObject => fail(EXIT_INVALID, "Invalid pairs file: expected an object"),
+        Error::MissingField("routes") => fail(EXIT_INVALID, "Expected a routes array"),
+        Error::MissingField(key) => fail(EXIT_INVALID, format!("Expected a number at {key}")),
+        Error::Line(line, error) => match *error {
+            Error::Io(error) => io_failure(&format!("Failed to read line {line}"), error),
+            Error::Parsing(error) => fail(
+                EXIT_INVALID,
+                format!("Invalid JSON on line {line}: {error}"),
+            ),
+            Error::Pairs(error) => fail(
+                EXIT_INVALID,
+                format!("Invalid pair on line {line}: {error}"),
+            ),
+            error => fail(EXIT_INVALID, format!("Invalid line {line}: {error}")),
+        },
+        error => fail(EXIT_INVALID, format!("Invalid pairs file: {error}")),
+    }
+}
+
+/// Exits with the message of a file other than the input the run failed
+/// to read or write.
+fn run_failure(error: RunError, args: &ComputeArgs) -> ! {
+    let path = args.checkpoint.path.as_deref().unwrap_or_default();
+    match error {
+        RunError::Resume(CheckpointError::Io(error)) => {
+            io_failure("Failed to read checkpoint", error)
+        }
+        RunError::Resume(error) => fail(EXIT_INVALID, format!("Invalid checkpoint: {error}")),
+        RunError::ForeignCheckpoint => fail(
+            EXIT_INVALID,
+            format!("The checkpoint {path} belongs to another input or settings"),
+        ),
+        RunError::SaveCheckpoint(error) => io_failure("Failed to write checkpoint", error),
+        RunError::RemoveCheckpoint(error) => io_failure("Failed to remove checkpoint", error),
+        RunError::Answers(error) => io_failure("Failed to read answers file", error),
+        RunError::PerPair(error) => io_failure("Failed to write per-pair file", error),
+        RunError::GeoJson(error) => io_failure("Failed to write GeoJSON file", error),
+        error => fail(EXIT_INVALID, error),
+    }
+}
+
+/// Writes how the distances and their average compare with the answers.
+fn write_answers(output: &mut dyn OutputSink, answers: &AnswersCheck) -> io::Result<()> {
+    let total = answers.total;
+    match answers.first {
+        None => writeln!(output, "Answers: all {total} distances match"),
+        Some(first) => {
+            let show = |value: Option<f64>| value.map_or("none".into(), |v| v.to_string());
+            writeln!(
+                output,
+                "Answers: {} of {total} distances off, the first at pair {}: expected {}, computed {}",
+                answers.off,
+                first.index,
+                show(first.expected),
+                show(first.computed)
+            )
+        }
+    }?;
+    writeln!(
+        output,
+        "Difference between answered and computed average: {}",
+        answers.average_difference
+    )
+}
+
+/// How often a watched input is checked for changes.
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Runs `compute` again whenever the input changes, each time in a child
+/// process, so an input caught half written only fails that run. The
+/// children get `arguments`, those of this run with its config applied.
+fn watch(
+    output: &mut dyn OutputSink,
+    args: ComputeArgs,
+    logging: LogArgs,
+    arguments: Vec<String>,
+) -> ! {
+    let program = std::env::current_exe()
+        .unwrap_or_else(|error| io_failure("Failed to find the executable", error));
+    // The config is already in `arguments`, and may hold the `--watch`
+    // taken out of them, so the runs read none.
+    let arguments: Vec<String> = logging
+        .to_args()
+        .into_iter()
+        .chain(["--no-config".to_string()])
+        .chain(arguments.into_iter().filter(|arg| arg != "--watch"))
+        .collect();
+    let stamp = || {
+        std::fs::metadata(&args.path)
+            .ok()
+            .map(|meta| (meta.modified().ok(), meta.len()))
+    };
+
+    let mut seen = stamp();
+    loop {
+        match process::Command::new(&program).args(&arguments).status() {
+            Ok(status) if !status.success() => {
+                log::warn(format_args!("The run ended with {status}"));
+            }
+            Ok(_) => {}
+            Err(error) => io_failure("Failed to run compute", error),
+        }
+        log::info(format_args!(
+            "Watching {} for changes, Ctrl-C to stop",
+            args.path
+        ));
+
+        // Wait for a change, then for the writer to be done with it.
+        loop {
+            thread::sleep(WATCH_INTERVAL);
+            let now = stamp();
+            if now != seen {
+                log::debug(format_args!("{} changed", args.path));
+                seen = now;
+                break;
+            }
+        }
+        loop {
+            thread::sleep(WATCH_INTERVAL);
+            let now = stamp();
+            if now == seen {
+                break;
+            }
+            seen = now;
+        }
+        if let Err(error) = writeln!(output) {
+            io_failure("Failed to write the report", error);
+        }
+    }
+}
+
+/// Computes the length of the path through the points of a points file, in
+/// order, reading one point at a time.
+fn route(output: &mut dyn OutputSink, args: ComputeArgs) -> io::Result<()> {
+    let mut source = match args.path.as_str() {
+        "-" => IoSource::stdin(args.read_buffer, args.huge_pages),
+        path => IoSource::open(path, args.io, args.read_buffer, args.huge_pages)
+            .unwrap_or_else(|error| io_failure("Failed to open file", error)),
+    };
+    if let Some(error) = source.take_fallback() {
+        log::warn(Warning::IoFallback(args.io, error));
+    }
+    // The size of a file is checked before reading it, as it may be too
+    // large to hold. The parser checks it again, for stdin.
+    if source
+        .size()
+        .is_some_and(|size| size > args.limits.max_size as u64)
+    {
+        fail(
+            EXIT_INVALID,
+            format!(
+                "Invalid JSON: {}",
+                ParsingError::DocumentTooLarge(args.limits.max_size)
+            ),
+        );
+    }
+    let bytes = source
+        .read_all()
+        .unwrap_or_else(|error| io_failure("Failed to read file", error));
+    let string = text(bytes, args.transcode);
+    let mut values = Parser::new(&string)
+        .duplicate_keys(args.duplicate_keys)
+        .lone_surrogates(args.lone_surrogates)
+        .out_of_range(args.out_of_range)
+        .limits(args.limits)
+        .allow_control_chars(args.allow_control_chars)
+        .iter_array_at("/points");
+
+    let mut route = Route::new(args.per_segment, args.angle_unit);
+    for (index, value) in values.by_ref().enumerate() {
+        let point = match value {
+            Ok(value) => point_from_value(&value, index).unwrap_or_else(|error| {
+                fail(EXIT_INVALID, format!("Invalid points file: {error}"))
+            }),
+            Err(ParsingError::NoArrayAt(_)) => fail(EXIT_INVALID, "Expected a points array"),
+            Err(error) => fail(EXIT_INVALID, format!("Invalid JSON: {error}")),
+        };
+        route.push(point);
+    }
+    let Some(Value::Object(json)) = values.into_document() else {
+        unreachable!("The pointer reached into an object")
+    };
+
+    let radius = points_radius(&json, args.radius_model, args.units);
+    route.write(output, radius, args.units.unwrap_or_default().symbol())
+}
+
+/// Writes the bytes of input parsed per second, and the cycles per byte
+/// where the CPU timer counts cycles.
+fn write_parsing_throughput(
+    output: &mut dyn OutputSink,
+    bytes: usize,
+    time: Duration,
+    cycles: u64,
+) -> io::Result<()> {
+    let cycles = CYCLE_TIMER.then_some(cycles);
+    writeln!(
+        output,
+        "{}",
+        progress::parsing_throughput(bytes, time, cycles)
+    )
+}
+
+/// Writes the bytes of coordinates read and the pairs computed per second
+/// of computing, with the cycles per pair where the CPU timer counts
+/// cycles, and how the threads and memory were placed.
+fn write_throughput(
+    output: &mut dyn OutputSink,
+    n_pairs: usize,
+    time: Duration,
+    cycles: u64,
+    args: &ComputeArgs,
+) -> io::Result<()> {
+    let cycles = CYCLE_TIMER.then_some(cycles);
+    let mut line = progress::computing_throughput(n_pairs, time, cycles);
+    line += &format!(
+        " on {} thread{}",
+        args.threads,
+        if args.threads == 1 { "" } else { "s" }
     );
+    if args.pin_threads {
+        line += ", pinned";
+    }
+    if args.interleave {
+        line += ", interleaved";
+    }
+    writeln!(output, "{line}")
+}
+
+/// Writes the average of all the pairs estimated from the distances of a
+/// sample of them, and whether the stored average, if it was checked, is
+/// within its interval. A sample cannot disprove the stored average, so it
+/// only warns.
+fn write_estimate(
+    output: &mut dyn OutputSink,
+    result: &RunResult,
+    population: usize,
+) -> io::Result<()> {
+    writeln!(
+        output,
+        "Sample: {} of {population} pairs",
+        result.distances.len()
+    )?;
+    let Some(estimate) = &result.estimate else {
+        log::warn("The sample is empty, nothing to estimate");
+        return Ok(());
+    };
+    writeln!(
+        output,
+        "Estimated average distance: {} ± {} (95% confidence)",
+        estimate.mean, estimate.margin
+    )?;
+    match result.check {
+        AverageCheck::Within(stored) => {
+            writeln!(output, "Stored average {stored} is within the interval")?;
+        }
+        AverageCheck::Outside(stored) => log::warn(format_args!(
+            "The stored average {stored} is outside the 95% interval of the sample"
+        )),
+        _ => {}
+    }
+    Ok(())
+}
+
+fn pairs_error(error: PairsError) -> ! {
+    fail(EXIT_INVALID, format!("Invalid pairs file: {error}"))
+}
+
+/// Writes the page faults of a phase, and its allocations if they are
+/// counted.
+fn write_memory(output: &mut dyn OutputSink, phase: &str, memory: &MemoryStats) -> io::Result<()> {
+    let mut parts = Vec::new();
+    if let Some(allocations) = memory.allocations {
+        parts.push(format!(
+            "{} allocations of {}, peak live {}",
+            allocations.count,
+            progress::bytes(allocations.bytes as f64),
+            progress::bytes(allocations.peak_live as f64)
+        ));
+    }
+    if let Some(faults) = memory.page_faults {
+        let mut part = format!("{faults} page faults");
+        if let Some(major) = memory.major_faults {
+            part += &format!(" ({major} major)");
+        }
+        parts.push(part);
+    }
+    if let Some(misses) = memory.tlb_misses {
+        parts.push(format!("{misses} dTLB misses"));
+    }
+    if !parts.is_empty() {
+        writeln!(output, "{phase} memory: {}", parts.join(", "))?;
+    }
+    Ok(())
+}
+
+/// Writes the distance, initial and final bearing of every pair.
+fn write_bearings(
+    output: &mut dyn OutputSink,
+    pairs: &Pairs,
+    radius: f64,
+    unit: AngleUnit,
+) -> io::Result<()> {
+    for i in 0..pairs.len() {
+        let coordinates = [pairs.x0[i], pairs.y0[i], pairs.x1[i], pairs.y1[i]];
+        let [phi_0, theta_0, phi_1, theta_1] = coordinates.map(|angle| unit.to_radians(angle));
+        let [x0, y0, x1, y1] = coordinates.map(|angle| unit.to_degrees(angle));
+        writeln!(
+            output,
+            "{i}: distance {} initial bearing {} final bearing {}",
+            haversine_distance_radians_with(&StdMath, radius, phi_0, theta_0, phi_1, theta_1),
+            unit.from_degrees(initial_bearing(x0, y0, x1, y1)),
+            unit.from_degrees(final_bearing(x0, y0, x1, y1)),
+        )?;
+    }
+    Ok(())
+}
+
+/// Writes the statistics and histogram of the distances and the centroid
+/// of the points, as text or JSON.
+fn write_stats(output: &mut dyn OutputSink, result: &RunResult, json: bool) -> io::Result<()> {
+    let Some(stats) = &result.stats else {
+        return writeln!(output, "No distances to report statistics for");
+    };
+
+    if json {
+        let mut value = stats.to_value();
+        if let Some(centroid) = result.centroid {
+            let point = Value::object()
+                .insert("lon", centroid.lon)
+                .insert("lat", centroid.lat);
+            value = value.insert("centroid", point);
+        }
+        if let Some(histogram) = &result.histogram {
+            value = value.insert("histogram", histogram.to_value());
+        }
+        writeln!(output, "{}", value.to_json_pretty(2))?;
+    } else {
+        stats.write(&mut *output)?;
+        if let Some(centroid) = result.centroid {
+            writeln!(
+                output,
+                "Centroid: lat {} lon {}",
+                centroid.lat, centroid.lon
+            )?;
+        }
+        if let Some(histogram) = &result.histogram {
+            writeln!(output, "{}", histogram.render())?;
+        }
+    }
+    Ok(())
 }
@@ -0,0 +1,184 @@
+//! The transcendental functions of the distance computation, from the
+//! standard library or from in-crate polynomial approximations, to study
+//! the cost of the former.
+
+use std::f64::consts::{FRAC_PI_2, PI};
+
+/// Highest degree of the polynomials of `PolyMath`. Above it, fitting them
+/// in `f64` loses more than the added terms gain.
+pub const MAX_DEGREE: usize = 21;
+
+/// Points sampled over each domain by `PolyMath::max_errors`.
+const ERROR_SAMPLES: usize = 100_000;
+
+/// Source of the transcendental functions of `ComputeDriver`.
+#[derive(Debug, Default, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Math {
+    #[default]
+    Std,
+    Poly(PolyMath),
+}
+
+/// Sine, cosine and arc sine of the distance computation.
+pub trait Trig {
+    fn sin(&self, x: f64) -> f64;
+    fn cos(&self, x: f64) -> f64;
+    fn asin(&self, x: f64) -> f64;
+}
+
+/// The functions of the standard library.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct StdMath;
+
+impl Trig for StdMath {
+    fn sin(&self, x: f64) -> f64 {
+        x.sin()
+    }
+
+    fn cos(&self, x: f64) -> f64 {
+        x.cos()
+    }
+
+    fn asin(&self, x: f64) -> f64 {
+        x.asin()
+    }
+}
+
+/// Odd polynomials fitted at Chebyshev nodes: the sine on `[-pi/2, pi/2]`,
+/// folded to `[-pi, pi]`, and the arc sine on `[0, 1/2]`, extended to
+/// `[-1, 1]` with a square root. The cosine is a shifted sine.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolyMath {
+    degree: usize,
+    /// Coefficients of `sin(x) / x` in powers of `x^2`.
+    sin: Vec<f64>,
+    /// Coefficients of `asin(x) / x` in powers of `x^2`.
+    asin: Vec<f64>,
+}
+
+/// Largest absolute errors of `PolyMath` against the standard library.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MathErrors {
+    /// Over `[-pi, pi]`.
+    pub sin: f64,
+    /// Over `[-pi, pi]`.
+    pub cos: f64,
+    /// Over `[-1, 1]`.
+    pub asin: f64,
+}
+
+impl PolyMath {
+    /// Fits polynomials of the given degree in `x`, rounded down to an odd
+    /// one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the degree is 0 or above `MAX_DEGREE`.
+    pub fn new(degree: usize) -> Self {
+        assert!(
+            (1..=MAX_DEGREE).contains(&degree),
+            "degree {degree} outside 1..={MAX_DEGREE}"
+        );
+        let terms = degree.div_ceil(2);
+        Self {
+            degree: 2 * terms - 1,
+            sin: fit_odd(f64::sin, FRAC_PI_2, terms),
+            asin: fit_odd(f64::asin, 0.5, terms),
+        }
+    }
+
+    /// Degree of the polynomials in `x`.
+    pub fn degree(&self) -> usize {
+        self.degree
+    }
+
+    /// Measures the largest errors over evenly spaced points of the domains.
+    pub fn max_errors(&self) -> MathErrors {
+        let max_error = |low: f64, high: f64, f: &dyn Fn(f64) -> f64, g: fn(f64) -> f64| {
+            (0..=ERROR_SAMPLES)
+                .map(|i| low + (high - low) * i as f64 / ERROR_SAMPLES as f64)
+                .map(|x| (f(x) - g(x)).abs())
+                .fold(0.0, f64::max)
+        };
+        MathErrors {
+            sin: max_error(-PI, PI, &|x| self.sin(x), f64::sin),
+            cos: max_error(-PI, PI, &|x| self.cos(x), f64::cos),
+            asin: max_error(-1.0, 1.0, &|x| self.asin(x), f64::asin),
+        }
+    }
+}
+
+impl Trig for PolyMath {
+    /// Accurate on `[-pi, pi]`, the angles of the distance computation.
+    fn sin(&self, x: f64) -> f64 {
+        // sin(x) = sin(pi - x) brings the angle within a quarter turn.
+        let x = if x > FRAC_PI_2 {
+            PI - x
+        } else if x < -FRAC_PI_2 {
+            -PI - x
+        } else {
+            x
+        };
+        x * horner(&self.sin, x * x)
+    }
+
+    /// Accurate on `[-pi, pi]`.
+    fn cos(&self, x: f64) -> f64 {
+        self.sin(FRAC_PI_2 - x.abs())
+    }
+
+    /// Clamps `x` to `[-1, 1]`, which the approximate sines feeding it can
+    /// overshoot.
+    fn asin(&self, x: f64) -> f64 {
+        let y = x.abs().min(1.0);
+        let asin = if y <= 0.5 {
+            y * horner(&self.asin, y * y)
+        } else {
+            // asin(y) = pi/2 - 2 asin(sqrt((1 - y) / 2)), whose argument is
+            // at most 1/2.
+            let z = ((1.0 - y) / 2.0).sqrt();
+            FRAC_PI_2 - 2.0 * z * horner(&self.asin, z * z)
+        };
+        asin.copysign(x)
+    }
+}
+
+/// Evaluates the polynomial with the coefficients, lowest power first.
+fn horner(coefficients: &[f64], t: f64) -> f64 {
+    coefficients.iter().rev().fold(0.0, |sum, &c| sum * t + c)
+}
+
+/// Fits the odd function `f` on `[0, bound]` as `x P(x^2)`, with `terms`
+/// coefficients of `P`, by interpolating `f(x) / x` at Chebyshev nodes of
+/// `x^2`.
+fn fit_odd(f: fn(f64) -> f64, bound: f64, terms: usize) -> Vec<f64> {
+    let high = bound * bound;
+    let nodes: Vec<f64> = (0..terms)
+        .map(|k| {
+            let angle = PI * (2 * k + 1) as f64 / (2 * terms) as f64;
+            high / 2.0 * (1.0 - angle.cos())
+        })
+        .collect();
+
+    // Newton's divided differences, then its nested form expanded into
+    // powers of t.
+    let mut differences: Vec<f64> = nodes.iter().map(|&t| f(t.sqrt()) / t.sqrt()).collect();
+    for order in 1..terms {
+        for i in (order..terms).rev() {
+            differences[i] = (differences[i] - differences[i - 1]) / (nodes[i] - nodes[i - order]);
+        }
+    }
+    let mut coefficients = vec![0.0; terms];
+    for i in (0..terms).rev() {
+        // coefficients = coefficients * (t - nodes[i]) + differences[i]
+        for j in (1..terms).rev() {
+            coefficients[j] = coefficients[j - 1] - nodes[i] * coefficients[j];
+        }
+        coefficients[0] = differences[i] - nodes[i] * coefficients[0];
+    }
+    coefficients
+}
+
+#[cfg(test)]
+mod tests;
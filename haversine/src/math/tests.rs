@@ -0,0 +1,50 @@
+use super::{MAX_DEGREE, PolyMath, StdMath, Trig};
+use std::f64::consts::{FRAC_PI_2, PI};
+
+#[test]
+fn test_std_math_is_std() {
+    for x in [-PI, -1.0, 0.0, 0.25, 3.0] {
+        assert_eq!(x.sin(), StdMath.sin(x));
+        assert_eq!(x.cos(), StdMath.cos(x));
+    }
+    assert_eq!(0.3f64.asin(), StdMath.asin(0.3));
+}
+
+#[test]
+fn test_errors_fall_with_degree() {
+    let errors: Vec<_> = [5, 9, 13, 17]
+        .map(|degree| PolyMath::new(degree).max_errors())
+        .to_vec();
+    for pair in errors.windows(2) {
+        assert!(pair[1].sin < pair[0].sin, "{pair:?}");
+        assert!(pair[1].cos < pair[0].cos, "{pair:?}");
+        assert!(pair[1].asin < pair[0].asin, "{pair:?}");
+    }
+
+    let errors = PolyMath::new(MAX_DEGREE).max_errors();
+    assert!(errors.sin < 1e-15, "{errors:?}");
+    assert!(errors.cos < 1e-15, "{errors:?}");
+    assert!(errors.asin < 1e-14, "{errors:?}");
+}
+
+#[test]
+fn test_degree_rounds_down_to_odd() {
+    assert_eq!(1, PolyMath::new(1).degree());
+    assert_eq!(7, PolyMath::new(8).degree());
+    assert_eq!(MAX_DEGREE, PolyMath::new(MAX_DEGREE).degree());
+}
+
+#[test]
+fn test_poly_symmetries() {
+    let math = PolyMath::new(13);
+    for x in [0.1, 1.0, 2.5, PI] {
+        assert_eq!(-math.sin(x), math.sin(-x));
+        assert_eq!(math.cos(x), math.cos(-x));
+    }
+    for x in [0.1, 0.5, 0.75, 1.0] {
+        assert_eq!(-math.asin(x), math.asin(-x));
+    }
+    assert_eq!(FRAC_PI_2, math.asin(1.0));
+    assert_eq!(FRAC_PI_2, math.asin(1.0 + 1e-9));
+    assert_eq!(0.0, math.sin(PI));
+}
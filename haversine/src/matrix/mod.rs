@@ -0,0 +1,466 @@
+//! Distances between every two of a list of points.
+
+use crate::checkpoint;
+use crate::geo::{
+    Kernel, Point, UnitVector, cosine_distance, degrees_to_radians,
+    haversine_distance_fast_radians, haversine_distance_fma_radians, vincenty_distance,
+};
+use crate::parse::{KeyPath, PathSegment, Value};
+use std::fmt;
+use std::io::{self, Write};
+use std::num::NonZeroUsize;
+use std::ops::Range;
+use std::thread;
+
+/// Rows and columns of the blocks the matrix is filled in, so the points of
+/// a block stay in L1 while it is computed.
+const BLOCK_SIZE: usize = 64;
+
+/// Which distances of the matrix are kept.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum MatrixShape {
+    /// Every distance, row by row.
+    #[default]
+    Full,
+    /// Only the distances above the diagonal, as the matrix is symmetric
+    /// with zeros on the diagonal.
+    Upper,
+}
+
+/// File format of the matrix output.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum MatrixFormat {
+    /// One line per row, without a header.
+    Csv,
+    /// Raw little-endian `f64`s of the kept distances, row by row.
+    F64,
+}
+
+impl MatrixFormat {
+    /// Picks the format from the extension of `path`.
+    pub fn from_path(path: &str) -> Option<Self> {
+        match path.rsplit_once('.')?.1 {
+            "csv" => Some(MatrixFormat::Csv),
+            "f64" => Some(MatrixFormat::F64),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum PointsError {
+    InvalidPoint(KeyPath),
+    MissingCoordinate(KeyPath),
+}
+
+impl fmt::Display for PointsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PointsError::InvalidPoint(path) => write!(f, "expected a point object at {path}"),
+            PointsError::MissingCoordinate(path) => write!(f, "expected a number at {path}"),
+        }
+    }
+}
+
+impl std::error::Error for PointsError {}
+
+/// Reads the elements of a `points` array, objects with an `x` longitude
+/// and a `y` latitude.
+pub fn points_from_values(values: &[Value]) -> Result<Vec<Point>, PointsError> {
+    values
+        .iter()
+        .enumerate()
+        .map(|(index, value)| point_from_value(value, index))
+        .collect()
+}
+
+/// Reads the element at `index` of a `points` array.
+pub fn point_from_value(value: &Value, index: usize) -> Result<Point, PointsError> {
+    let path = |key: Option<&str>| {
+        let mut segments = vec![PathSegment::Key("points".into()), PathSegment::Index(index)];
+        segments.extend(key.map(|key| PathSegment::Key(key.into())));
+        KeyPath(segments)
+    };
+    let Value::Object(object) = value else {
+        return Err(PointsError::InvalidPoint(path(None)));
+    };
+    let coordinate = |key| {
+        object
+            .get(key)
+            .and_then(|value| value.try_into().ok())
+            .ok_or_else(|| PointsError::MissingCoordinate(path(Some(key))))
+    };
+    Ok(Point::new(coordinate("x")?, coordinate("y")?))
+}
+
+/// Distances between every two points, computed by `MatrixDriver`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DistanceMatrix {
+    len: usize,
+    shape: MatrixShape,
+    distances: Vec<f64>,
+}
+
+impl DistanceMatrix {
+    /// Number of points, the rows and columns of the matrix.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn shape(&self) -> MatrixShape {
+        self.shape
+    }
+
+    /// Distance between the points at `i` and `j`, whatever the shape.
+    pub fn get(&self, i: usize, j: usize) -> f64 {
+        assert!(i < self.len && j < self.len, "Index out of the matrix");
+        let (i, j) = match self.shape {
+            MatrixShape::Full => (i, j),
+            MatrixShape::Upper if i == j => return 0.0,
+            MatrixShape::Upper => (i.min(j), i.max(j)),
+        };
+        self.distances[row_offset(self.shape, self.len, i) + j - first_column(self.shape, i)]
+    }
+
+    /// Kept distances of the row `i`: all of them for a full matrix, those
+    /// right of the diagonal for an upper one.
+    pub fn row(&self, i: usize) -> &[f64] {
+        let start = row_offset(self.shape, self.len, i);
+        &self.distances[start..start + row_len(self.shape, self.len, i)]
+    }
+
+    /// Kept distances, row by row.
+    pub fn distances(&self) -> &[f64] {
+        &self.distances
+    }
+
+    /// Writes the matrix. The CSV of an upper matrix has empty fields on and
+    /// below the diagonal, so its columns line up with those of a full one.
+    pub fn write(&self, writer: impl Write, format: MatrixFormat) -> io::Result<()> {
+        write_rows(
+            writer,
+            self.shape,
+            self.len,
+            0..self.len,
+            &self.distances,
+            format,
+        )
+    }
+}
+
+/// Writes `rows` of a matrix of `len` points like `DistanceMatrix::write`,
+/// from their kept distances, as given by `MatrixDriver::run_rows`. The
+/// rows of a matrix written in turn make up the whole of it.
+pub fn write_rows(
+    mut writer: impl Write,
+    shape: MatrixShape,
+    len: usize,
+    rows: Range<usize>,
+    distances: &[f64],
+    format: MatrixFormat,
+) -> io::Result<()> {
+    match format {
+        MatrixFormat::Csv => {
+            let mut start = 0;
+            for i in rows {
+                let empty = first_column(shape, i);
+                let row = &distances[start..start + row_len(shape, len, i)];
+                start += row.len();
+                let mut line = ",".repeat(empty.saturating_sub(1));
+                for (k, distance) in row.iter().enumerate() {
+                    if k > 0 || empty > 0 {
+                        line.push(',');
+                    }
+                    line.push_str(&distance.to_string());
+                }
+                writeln!(writer, "{line}")?;
+            }
+        }
+        MatrixFormat::F64 => {
+            for distance in distances {
+                writer.write_all(&distance.to_le_bytes())?;
+            }
+        }
+    }
+    writer.flush()
+}
+
+fn first_column(shape: MatrixShape, i: usize) -> usize {
+    match shape {
+        MatrixShape::Full => 0,
+        MatrixShape::Upper => i + 1,
+    }
+}
+
+fn row_len(shape: MatrixShape, len: usize, i: usize) -> usize {
+    len - first_column(shape, i)
+}
+
+/// Index of the first kept distance of the row `i`.
+fn row_offset(shape: MatrixShape, len: usize, i: usize) -> usize {
+    match shape {
+        MatrixShape::Full => i * len,
+        MatrixShape::Upper => i * len - i * (i + 1) / 2,
+    }
+}
+
+/// A point in radians, with the cosine of its latitude computed once.
+#[derive(Debug, Clone, Copy)]
+struct Prepared {
+    lon: f64,
+    lat: f64,
+    cos_lat: f64,
+}
+
+impl Prepared {
+    fn new(point: &Point) -> Self {
+        let lat = degrees_to_radians(point.lat);
+        Self {
+            lon: degrees_to_radians(point.lon),
+            lat,
+            cos_lat: lat.cos(),
+        }
+    }
+}
+
+/// A point converted once for the distances to every other point.
+trait Prepare: Sync {
+    fn distance(&self, other: &Self, radius: f64) -> f64;
+}
+
+impl Prepare for Prepared {
+    /// Same as `haversine_distance`, with the conversions already done.
+    fn distance(&self, other: &Prepared, radius: f64) -> f64 {
+        let root_term_1 = ((other.lat - self.lat) / 2.0).sin().powi(2);
+        let root_term_2 =
+            self.cos_lat * other.cos_lat * ((other.lon - self.lon) / 2.0).sin().powi(2);
+        2.0 * radius * (root_term_1 + root_term_2).sqrt().asin()
+    }
+}
+
+impl Prepare for UnitVector {
+    fn distance(&self, other: &UnitVector, radius: f64) -> f64 {
+        UnitVector::distance(self, other, radius)
+    }
+}
+
+/// A point whose distances are taken by `haversine_distance_fma`.
+struct Fma(Prepared);
+
+impl Prepare for Fma {
+    fn distance(&self, other: &Fma, radius: f64) -> f64 {
+        let (a, b) = (&self.0, &other.0);
+        haversine_distance_fma_radians(radius, a.lon, a.lat, b.lon, b.lat)
+    }
+}
+
+/// A point whose distances are taken by `haversine_distance_fast`.
+struct FastMath(Prepared);
+
+impl Prepare for FastMath {
+    fn distance(&self, other: &FastMath, radius: f64) -> f64 {
+        let (a, b) = (&self.0, &other.0);
+        haversine_distance_fast_radians(radius, a.lon, a.lat, b.lon, b.lat)
+    }
+}
+
+/// A unit vector whose distances are taken by `UnitVector::dot_distance`.
+struct Dot(UnitVector);
+
+impl Prepare for Dot {
+    fn distance(&self, other: &Dot, radius: f64) -> f64 {
+        self.0.dot_distance(&other.0, radius)
+    }
+}
+
+/// A point whose distances are taken by `vincenty_distance`, with nothing
+/// to convert ahead.
+struct Vincenty(Point);
+
+impl Prepare for Vincenty {
+    fn distance(&self, other: &Vincenty, radius: f64) -> f64 {
+        let (a, b) = (&self.0, &other.0);
+        vincenty_distance(radius, a.lon, a.lat, b.lon, b.lat)
+    }
+}
+
+/// A point whose distances are taken by `cosine_distance`.
+struct Cosine(Point);
+
+impl Prepare for Cosine {
+    fn distance(&self, other: &Cosine, radius: f64) -> f64 {
+        let (a, b) = (&self.0, &other.0);
+        cosine_distance(radius, a.lon, a.lat, b.lon, b.lat)
+    }
+}
+
+/// Computes distance matrices, splitting the rows among threads.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatrixDriver {
+    radius: f64,
+    shape: MatrixShape,
+    threads: usize,
+    kernel: Kernel,
+}
+
+impl MatrixDriver {
+    /// Creates a driver for a sphere of the given radius, using as many
+    /// threads as the machine runs in parallel.
+    pub fn new(radius: f64) -> Self {
+        Self {
+            radius,
+            shape: MatrixShape::default(),
+            threads: thread::available_parallelism().map_or(1, NonZeroUsize::get),
+            kernel: Kernel::Haversine,
+        }
+    }
+
+    /// Sets which distances are kept.
+    pub fn shape(mut self, shape: MatrixShape) -> Self {
+        self.shape = shape;
+        self
+    }
+
+    /// Sets the number of threads, at least one.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Which distances are kept.
+    pub fn shape_of(&self) -> MatrixShape {
+        self.shape
+    }
+
+    /// Sets how the distances are computed.
+    pub fn kernel(mut self, kernel: Kernel) -> Self {
+        self.kernel = kernel;
+        self
+    }
+
+    pub fn run(&self, points: &[Point]) -> DistanceMatrix {
+        DistanceMatrix {
+            len: points.len(),
+            shape: self.shape,
+            distances: self.run_rows(points, 0..points.len()),
+        }
+    }
+
+    /// Computes the kept distances of `rows` alone, row by row, for a
+    /// matrix too large to hold or to compute at once.
+    pub fn run_rows(&self, points: &[Point], rows: Range<usize>) -> Vec<f64> {
+        match self.kernel {
+            Kernel::CachedTrig => self.run_prepared(
+                points
+                    .iter()
+                    .map(|point| UnitVector::new(point.lon, point.lat))
+                    .collect(),
+                rows,
+            ),
+            Kernel::Dot => self.run_prepared(
+                points
+                    .iter()
+                    .map(|point| Dot(UnitVector::new(point.lon, point.lat)))
+                    .collect(),
+                rows,
+            ),
+            Kernel::Fma => self.run_prepared(
+                points
+                    .iter()
+                    .map(|point| Fma(Prepared::new(point)))
+                    .collect(),
+                rows,
+            ),
+            Kernel::FastMath => self.run_prepared(
+                points
+                    .iter()
+                    .map(|point| FastMath(Prepared::new(point)))
+                    .collect(),
+                rows,
+            ),
+            Kernel::Haversine => {
+                self.run_prepared(points.iter().map(Prepared::new).collect(), rows)
+            }
+            Kernel::Vincenty => {
+                self.run_prepared(points.iter().copied().map(Vincenty).collect(), rows)
+            }
+            Kernel::Cosine => self.run_prepared(points.iter().copied().map(Cosine).collect(), rows),
+        }
+    }
+
+    /// Identifies the points, radius and shape of a run, for its
+    /// checkpoints.
+    pub fn fingerprint(&self, points: &[Point]) -> u64 {
+        let shape = match self.shape {
+            MatrixShape::Full => 0.0,
+            MatrixShape::Upper => 1.0,
+        };
+        let coordinates = points.iter().flat_map(|point| [point.lon, point.lat]);
+        checkpoint::fingerprint([self.radius, shape].into_iter().chain(coordinates))
+    }
+
+    fn run_prepared<P: Prepare>(&self, prepared: Vec<P>, rows: Range<usize>) -> Vec<f64> {
+        let len = prepared.len();
+        let offset = |row| row_offset(self.shape, len, row);
+        let total = offset(rows.end) - offset(rows.start);
+        let mut distances = vec![0.0; total];
+
+        // Give every thread about the same number of distances, which for an
+        // upper matrix is not the same number of rows.
+        let mut chunks = Vec::with_capacity(self.threads);
+        let mut rest = distances.as_mut_slice();
+        let mut start = rows.start;
+        for thread in 1..=self.threads {
+            let target = offset(rows.start) + total * thread / self.threads;
+            let mut end = start;
+            while end < rows.end && offset(end) < target {
+                end += 1;
+            }
+            let (chunk, tail) = rest.split_at_mut(offset(end) - offset(start));
+            chunks.push((start..end, chunk));
+            rest = tail;
+            start = end;
+        }
+
+        thread::scope(|scope| {
+            for (rows, chunk) in chunks {
+                if !rows.is_empty() {
+                    let prepared = &prepared;
+                    scope.spawn(move || self.fill(prepared, rows, chunk));
+                }
+            }
+        });
+
+        distances
+    }
+
+    /// Fills the distances of `rows` in blocks of `BLOCK_SIZE` rows and
+    /// columns.
+    fn fill<P: Prepare>(&self, points: &[P], rows: Range<usize>, out: &mut [f64]) {
+        let len = points.len();
+        let base = row_offset(self.shape, len, rows.start);
+        for block_rows in rows.clone().step_by(BLOCK_SIZE) {
+            let block_end = (block_rows + BLOCK_SIZE).min(rows.end);
+            let first = first_column(self.shape, block_rows);
+            for block_columns in (first..len).step_by(BLOCK_SIZE) {
+                let columns_end = (block_columns + BLOCK_SIZE).min(len);
+                for i in block_rows..block_end {
+                    let first = first_column(self.shape, i);
+                    let offset = row_offset(self.shape, len, i) - base;
+                    for j in block_columns.max(first)..columns_end {
+                        out[offset + j - first] = points[i].distance(&points[j], self.radius);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,188 @@
+use super::{
+    DistanceMatrix, MatrixDriver, MatrixFormat, MatrixShape, PointsError, points_from_values,
+    write_rows,
+};
+use crate::geo::{Kernel, Point, haversine_distance};
+use crate::parse::{KeyPath, Parser, PathSegment, Value};
+
+fn points(n: usize) -> Vec<Point> {
+    (0..n)
+        .map(|i| {
+            let i = i as f64;
+            Point::new((i * 37.0) % 360.0 - 180.0, (i * 13.0) % 180.0 - 90.0)
+        })
+        .collect()
+}
+
+fn written(matrix: &DistanceMatrix, format: MatrixFormat) -> Vec<u8> {
+    let mut output = Vec::new();
+    matrix.write(&mut output, format).unwrap();
+    output
+}
+
+#[test]
+fn test_full_matrix_matches_haversine() {
+    let points = points(150);
+    let matrix = MatrixDriver::new(6372.8).threads(3).run(&points);
+    assert_eq!(150 * 150, matrix.distances().len());
+    for (i, p) in points.iter().enumerate() {
+        for (j, q) in points.iter().enumerate() {
+            let expected = haversine_distance(6372.8, p.lon, p.lat, q.lon, q.lat);
+            assert_eq!(expected, matrix.get(i, j), "({i}, {j})");
+        }
+    }
+}
+
+#[test]
+fn test_upper_matrix_matches_full() {
+    let points = points(150);
+    let full = MatrixDriver::new(1.0).threads(1).run(&points);
+    let upper = MatrixDriver::new(1.0)
+        .shape(MatrixShape::Upper)
+        .threads(4)
+        .run(&points);
+    assert_eq!(150 * 149 / 2, upper.distances().len());
+    assert_eq!(&full.row(10)[11..], upper.row(10));
+    for i in 0..points.len() {
+        for j in 0..points.len() {
+            assert_eq!(full.get(i, j), upper.get(i, j), "({i}, {j})");
+        }
+    }
+}
+
+#[test]
+fn test_threads_do_not_change_result() {
+    let points = points(70);
+    for shape in [MatrixShape::Full, MatrixShape::Upper] {
+        let one = MatrixDriver::new(1.0).shape(shape).threads(1).run(&points);
+        for threads in [2, 5, 100] {
+            let many = MatrixDriver::new(1.0)
+                .shape(shape)
+                .threads(threads)
+                .run(&points);
+            assert_eq!(one, many, "{threads} threads");
+        }
+    }
+}
+
+#[test]
+fn test_empty_and_single_point() {
+    assert!(MatrixDriver::new(1.0).run(&[]).is_empty());
+    let upper = MatrixDriver::new(1.0)
+        .shape(MatrixShape::Upper)
+        .run(&points(1));
+    assert_eq!(0.0, upper.get(0, 0));
+    assert!(upper.distances().is_empty());
+}
+
+#[test]
+fn test_write_csv() {
+    let points = [
+        Point::new(0.0, 0.0),
+        Point::new(90.0, 0.0),
+        Point::new(180.0, 0.0),
+    ];
+    let full = MatrixDriver::new(1.0).run(&points);
+    let (a, b, c) = (full.get(0, 1), full.get(0, 2), full.get(1, 2));
+    assert_eq!(
+        format!("0,{a},{b}\n{a},0,{c}\n{b},{c},0\n"),
+        String::from_utf8(written(&full, MatrixFormat::Csv)).unwrap()
+    );
+
+    let upper = MatrixDriver::new(1.0)
+        .shape(MatrixShape::Upper)
+        .run(&points);
+    assert_eq!(
+        format!(",{a},{b}\n,,{c}\n,,\n"),
+        String::from_utf8(written(&upper, MatrixFormat::Csv)).unwrap()
+    );
+}
+
+#[test]
+fn test_write_f64() {
+    let matrix = MatrixDriver::new(1.0)
+        .shape(MatrixShape::Upper)
+        .run(&points(4));
+    let output = written(&matrix, MatrixFormat::F64);
+    assert_eq!(6 * 8, output.len());
+    assert_eq!(matrix.get(1, 3).to_le_bytes(), output[4 * 8..5 * 8]);
+}
+
+#[test]
+fn test_format_from_path() {
+    assert_eq!(Some(MatrixFormat::Csv), MatrixFormat::from_path("m.csv"));
+    assert_eq!(Some(MatrixFormat::F64), MatrixFormat::from_path("m.f64"));
+    assert_eq!(None, MatrixFormat::from_path("m.json"));
+}
+
+#[test]
+fn test_points_from_values() {
+    let document = Parser::new(r#"[{"x": 1.5, "y": -2}, {"x": 3, "z": 0}, 4]"#)
+        .parse()
+        .unwrap()
+        .unwrap();
+    let Value::Array(values) = document else {
+        panic!("Expected an array");
+    };
+    assert_eq!(
+        Ok(vec![Point::new(1.5, -2.0)]),
+        points_from_values(&values[..1])
+    );
+    let path = |segments: &[PathSegment]| {
+        let mut path = vec![PathSegment::Key("points".into())];
+        path.extend_from_slice(segments);
+        KeyPath(path)
+    };
+    assert_eq!(
+        Err(PointsError::MissingCoordinate(path(&[
+            PathSegment::Index(1),
+            PathSegment::Key("y".into())
+        ]))),
+        points_from_values(&values[..2])
+    );
+    assert_eq!(
+        Err(PointsError::InvalidPoint(path(&[PathSegment::Index(0)]))),
+        points_from_values(&values[2..])
+    );
+}
+
+#[test]
+fn test_cached_trig_kernel_matches_haversine() {
+    let points = points(100);
+    let haversine = MatrixDriver::new(6372.8).threads(2).run(&points);
+    for kernel in [
+        Kernel::CachedTrig,
+        Kernel::Dot,
+        Kernel::Fma,
+        Kernel::FastMath,
+        // Not `Kernel::Cosine`, which is off by far more between a point and
+        // itself.
+        Kernel::Vincenty,
+    ] {
+        let cached = MatrixDriver::new(6372.8)
+            .threads(2)
+            .kernel(kernel)
+            .run(&points);
+        for (a, b) in haversine.distances().iter().zip(cached.distances()) {
+            assert!((a - b).abs() < 1e-9, "{kernel:?}: {a} versus {b}");
+        }
+    }
+}
+
+#[test]
+fn test_rows_written_in_turn_make_the_matrix() {
+    let points = points(70);
+    for shape in [MatrixShape::Full, MatrixShape::Upper] {
+        let driver = MatrixDriver::new(1.0).shape(shape).threads(3);
+        let matrix = driver.run(&points);
+        for format in [MatrixFormat::Csv, MatrixFormat::F64] {
+            let mut output = Vec::new();
+            for start in (0..points.len()).step_by(16) {
+                let rows = start..(start + 16).min(points.len());
+                let distances = driver.run_rows(&points, rows.clone());
+                write_rows(&mut output, shape, points.len(), rows, &distances, format).unwrap();
+            }
+            assert_eq!(written(&matrix, format), output, "{shape:?} {format:?}");
+        }
+    }
+}
@@ -0,0 +1,421 @@
+//! Memory counters of the process: peak resident set size and page faults
+//! from the OS, misses of the data TLB from the CPU, and the allocations
+//! made through `CountingAllocator`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fs::File;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static ALLOCATED: AtomicU64 = AtomicU64::new(0);
+static LIVE: AtomicU64 = AtomicU64::new(0);
+static PEAK_LIVE: AtomicU64 = AtomicU64::new(0);
+
+/// Global allocator counting the allocations made through it, for programs
+/// that install it with `#[global_allocator]`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CountingAllocator;
+
+impl CountingAllocator {
+    fn grow(bytes: u64) {
+        let live = LIVE.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        PEAK_LIVE.fetch_max(live, Ordering::Relaxed);
+    }
+}
+
+// SAFETY: every call is forwarded to the system allocator unchanged.
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            ALLOCATED.fetch_add(layout.size() as u64, Ordering::Relaxed);
+            Self::grow(layout.size() as u64);
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc_zeroed(layout) };
+        if !ptr.is_null() {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            ALLOCATED.fetch_add(layout.size() as u64, Ordering::Relaxed);
+            Self::grow(layout.size() as u64);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE.fetch_sub(layout.size() as u64, Ordering::Relaxed);
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    /// Counts as one allocation, of the growth of the block only, as the
+    /// bytes it already had were counted when they were allocated.
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = unsafe { System.realloc(ptr, layout, new_size) };
+        if !new_ptr.is_null() {
+            let (old, new) = (layout.size() as u64, new_size as u64);
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            ALLOCATED.fetch_add(new.saturating_sub(old), Ordering::Relaxed);
+            if new >= old {
+                Self::grow(new - old);
+            } else {
+                LIVE.fetch_sub(old - new, Ordering::Relaxed);
+            }
+        }
+        new_ptr
+    }
+}
+
+/// Counters of `CountingAllocator`.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct AllocationStats {
+    /// Calls that allocated or reallocated a block.
+    pub count: u64,
+    /// Bytes allocated, counting only the growth of reallocated blocks.
+    pub bytes: u64,
+    /// Largest number of bytes allocated and not yet freed at once, since
+    /// the program started or the last `reset_peak_live`.
+    pub peak_live: u64,
+}
+
+impl AllocationStats {
+    /// Reads the counters, `None` if `CountingAllocator` is not the global
+    /// allocator, which is known from its having counted nothing.
+    pub fn now() -> Option<Self> {
+        let count = ALLOCATIONS.load(Ordering::Relaxed);
+        (count > 0).then(|| AllocationStats {
+            count,
+            bytes: ALLOCATED.load(Ordering::Relaxed),
+            peak_live: PEAK_LIVE.load(Ordering::Relaxed),
+        })
+    }
+}
+
+/// Lowers the peak live bytes to the bytes live now, so the next peak read
+/// is that of the phase starting now.
+pub fn reset_peak_live() {
+    PEAK_LIVE.store(LIVE.load(Ordering::Relaxed), Ordering::Relaxed);
+}
+
+/// Memory counters at one point in time, or over a phase when returned by
+/// `since`. The counters are `None` where they cannot be read.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct MemoryStats {
+    /// Largest resident set size of the process so far, in bytes.
+    pub peak_rss: Option<u64>,
+    /// Page faults of any kind.
+    pub page_faults: Option<u64>,
+    /// Page faults that had to read from disk. Windows does not tell them
+    /// apart.
+    pub major_faults: Option<u64>,
+    /// Counters of `CountingAllocator`, if it is the global allocator.
+    pub allocations: Option<AllocationStats>,
+    /// Misses of the data TLB, counted from the first snapshot on, for the
+    /// threads started after it too once they end. Only Linux counts them,
+    /// where the CPU and permissions allow.
+    pub tlb_misses: Option<u64>,
+}
+
+impl MemoryStats {
+    pub fn now() -> Self {
+        let mut stats = sys::read();
+        stats.allocations = AllocationStats::now();
+        stats.tlb_misses = hints::tlb_misses();
+        stats
+    }
+
+    /// Counters of the phase from `start` to this snapshot. The peaks are
+    /// those at the end of the phase, as peaks cannot be subtracted.
+    pub fn since(&self, start: &MemoryStats) -> MemoryStats {
+        let delta = |end: Option<u64>, start: Option<u64>| Some(end?.saturating_sub(start?));
+        let allocations = match (self.allocations, start.allocations) {
+            (Some(end), Some(start)) => Some(AllocationStats {
+                count: end.count.saturating_sub(start.count),
+                bytes: end.bytes.saturating_sub(start.bytes),
+                peak_live: end.peak_live,
+            }),
+            _ => None,
+        };
+        MemoryStats {
+            peak_rss: self.peak_rss,
+            page_faults: delta(self.page_faults, start.page_faults),
+            major_faults: delta(self.major_faults, start.major_faults),
+            allocations,
+            tlb_misses: delta(self.tlb_misses, start.tlb_misses),
+        }
+    }
+}
+
+/// Asks for the memory of `buffer`, up to its capacity, to be backed by
+/// huge pages, which take fewer TLB entries. Only the pages touched after
+/// the advice get them, so it is best given before filling the buffer.
+/// Returns whether the system took it; only Linux does, with transparent
+/// huge pages enabled.
+#[allow(clippy::ptr_arg)]
+pub fn advise_huge_pages<T>(buffer: &Vec<T>) -> bool {
+    let bytes = buffer.capacity() * size_of::<T>();
+    bytes > 0 && hints::advise_huge_pages(buffer.as_ptr().cast(), bytes)
+}
+
+/// Advises the system to drop the cached pages of `file` from the page
+/// cache, so the next read of it comes from the disk. Dirty pages and
+/// those in use elsewhere stay. Returns whether the system took it; only
+/// Linux does.
+pub fn advise_drop_from_page_cache(file: &File) -> bool {
+    hints::advise_drop_from_page_cache(file)
+}
+
+#[cfg(target_os = "linux")]
+mod hints {
+    use std::ffi::{c_int, c_long, c_ulong, c_void};
+    use std::fs::File;
+    use std::io::Read;
+    use std::os::fd::{AsRawFd, FromRawFd};
+    use std::sync::OnceLock;
+
+    const SC_PAGESIZE: c_int = 30;
+    const MADV_HUGEPAGE: c_int = 14;
+    const POSIX_FADV_DONTNEED: c_int = 4;
+
+    const PERF_TYPE_HW_CACHE: u32 = 3;
+    /// Read misses of the data TLB: the cache id 3, with the id of reads,
+    /// 0, in the second byte and that of misses, 1, in the third.
+    const DTLB_READ_MISS: u64 = 3 | (1 << 16);
+    const INHERIT: u64 = 1 << 1;
+    const EXCLUDE_KERNEL: u64 = 1 << 5;
+    const EXCLUDE_HV: u64 = 1 << 6;
+    const PERF_FLAG_FD_CLOEXEC: c_ulong = 1 << 3;
+
+    #[cfg(target_arch = "x86_64")]
+    const SYS_PERF_EVENT_OPEN: Option<c_long> = Some(298);
+    #[cfg(target_arch = "aarch64")]
+    const SYS_PERF_EVENT_OPEN: Option<c_long> = Some(241);
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    const SYS_PERF_EVENT_OPEN: Option<c_long> = None;
+
+    /// `struct perf_event_attr` up to its first published size, with the
+    /// bit fields as one word.
+    #[repr(C)]
+    #[derive(Default)]
+    struct PerfEventAttr {
+        kind: u32,
+        size: u32,
+        config: u64,
+        sample_period: u64,
+        sample_type: u64,
+        read_format: u64,
+        flags: u64,
+        wakeup_events: u32,
+        bp_type: u32,
+        config1: u64,
+    }
+
+    unsafe extern "C" {
+        fn madvise(address: *mut c_void, length: usize, advice: c_int) -> c_int;
+        fn posix_fadvise(fd: c_int, offset: c_long, length: c_long, advice: c_int) -> c_int;
+        fn sysconf(name: c_int) -> c_long;
+        fn syscall(number: c_long, ...) -> c_long;
+    }
+
+    pub(super) fn advise_huge_pages(start: *const u8, len: usize) -> bool {
+        // SAFETY: `sysconf` only reads a configuration value.
+        let page = unsafe { sysconf(SC_PAGESIZE) }.max(1) as usize;
+        // Only the pages wholly inside the buffer, to leave its neighbours.
+        let first = (start as usize).next_multiple_of(page);
+        let end = (start as usize + len) / page * page;
+        if end <= first {
+            return false;
+        }
+        // SAFETY: the range is mapped, as it lies within the buffer, and the
+        // advice does not change its contents.
+        unsafe { madvise(first as *mut c_void, end - first, MADV_HUGEPAGE) == 0 }
+    }
+
+    pub(super) fn advise_drop_from_page_cache(file: &File) -> bool {
+        // SAFETY: the descriptor is that of an open file, and a length of 0
+        // advises to its end. The advice does not change its contents.
+        unsafe { posix_fadvise(file.as_raw_fd(), 0, 0, POSIX_FADV_DONTNEED) == 0 }
+    }
+
+    /// The counter of the process, opened by the first read.
+    fn counter() -> Option<&'static File> {
+        static COUNTER: OnceLock<Option<File>> = OnceLock::new();
+        COUNTER
+            .get_or_init(|| {
+                let attr = PerfEventAttr {
+                    kind: PERF_TYPE_HW_CACHE,
+                    size: size_of::<PerfEventAttr>() as u32,
+                    config: DTLB_READ_MISS,
+                    flags: INHERIT | EXCLUDE_KERNEL | EXCLUDE_HV,
+                    ..Default::default()
+                };
+                // SAFETY: `attr` is a valid attribute structure of the size
+                // it declares; pid 0 and cpu -1 count the calling thread on
+                // any CPU.
+                let fd = unsafe {
+                    syscall(
+                        SYS_PERF_EVENT_OPEN?,
+                        &attr as *const PerfEventAttr,
+                        0 as c_long,
+                        -1 as c_long,
+                        -1 as c_long,
+                        PERF_FLAG_FD_CLOEXEC,
+                    )
+                };
+                // SAFETY: a non-negative result is a new descriptor owned by
+                // nothing else.
+                (fd >= 0).then(|| unsafe { File::from_raw_fd(fd as c_int) })
+            })
+            .as_ref()
+    }
+
+    pub(super) fn tlb_misses() -> Option<u64> {
+        let mut count = [0; 8];
+        let mut counter = counter()?;
+        counter.read_exact(&mut count).ok()?;
+        Some(u64::from_ne_bytes(count))
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod hints {
+    use std::fs::File;
+
+    pub(super) fn advise_huge_pages(_start: *const u8, _len: usize) -> bool {
+        false
+    }
+
+    pub(super) fn advise_drop_from_page_cache(_file: &File) -> bool {
+        false
+    }
+
+    pub(super) fn tlb_misses() -> Option<u64> {
+        None
+    }
+}
+
+#[cfg(unix)]
+mod sys {
+    use super::MemoryStats;
+    use std::ffi::{c_int, c_long};
+
+    const RUSAGE_SELF: c_int = 0;
+
+    /// `struct rusage`, with the two `timeval`s as pairs of longs, which
+    /// have their size and alignment on the supported targets.
+    #[repr(C)]
+    #[derive(Default)]
+    struct Rusage {
+        ru_utime: [c_long; 2],
+        ru_stime: [c_long; 2],
+        ru_maxrss: c_long,
+        ru_ixrss: c_long,
+        ru_idrss: c_long,
+        ru_isrss: c_long,
+        ru_minflt: c_long,
+        ru_majflt: c_long,
+        ru_nswap: c_long,
+        ru_inblock: c_long,
+        ru_oublock: c_long,
+        ru_msgsnd: c_long,
+        ru_msgrcv: c_long,
+        ru_nsignals: c_long,
+        ru_nvcsw: c_long,
+        ru_nivcsw: c_long,
+    }
+
+    unsafe extern "C" {
+        fn getrusage(who: c_int, usage: *mut Rusage) -> c_int;
+    }
+
+    /// Apple reports the peak RSS in bytes, the other Unixes in KiB.
+    const MAXRSS_UNIT: u64 = if cfg!(target_vendor = "apple") {
+        1
+    } else {
+        1024
+    };
+
+    pub(super) fn read() -> MemoryStats {
+        let mut usage = Rusage::default();
+        // SAFETY: `usage` is a valid `struct rusage` to write to.
+        if unsafe { getrusage(RUSAGE_SELF, &mut usage) } != 0 {
+            return MemoryStats::default();
+        }
+        let (minor, major) = (usage.ru_minflt as u64, usage.ru_majflt as u64);
+        MemoryStats {
+            peak_rss: Some(usage.ru_maxrss as u64 * MAXRSS_UNIT),
+            page_faults: Some(minor + major),
+            major_faults: Some(major),
+            allocations: None,
+            tlb_misses: None,
+        }
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    use super::MemoryStats;
+    use std::ffi::c_void;
+
+    /// `PROCESS_MEMORY_COUNTERS`.
+    #[repr(C)]
+    #[derive(Default)]
+    struct ProcessMemoryCounters {
+        cb: u32,
+        page_fault_count: u32,
+        peak_working_set_size: usize,
+        working_set_size: usize,
+        quota_peak_paged_pool_usage: usize,
+        quota_paged_pool_usage: usize,
+        quota_peak_non_paged_pool_usage: usize,
+        quota_non_paged_pool_usage: usize,
+        pagefile_usage: usize,
+        peak_pagefile_usage: usize,
+    }
+
+    // `GetProcessMemoryInfo` of psapi, which kernel32 exports under this
+    // name since Windows 7.
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn GetCurrentProcess() -> *mut c_void;
+        fn K32GetProcessMemoryInfo(
+            process: *mut c_void,
+            counters: *mut ProcessMemoryCounters,
+            cb: u32,
+        ) -> i32;
+    }
+
+    pub(super) fn read() -> MemoryStats {
+        let size = size_of::<ProcessMemoryCounters>() as u32;
+        let mut counters = ProcessMemoryCounters {
+            cb: size,
+            ..Default::default()
+        };
+        // SAFETY: `counters` is a valid structure of the size passed, and the
+        // pseudo handle of the current process needs no closing.
+        if unsafe { K32GetProcessMemoryInfo(GetCurrentProcess(), &mut counters, size) } == 0 {
+            return MemoryStats::default();
+        }
+        MemoryStats {
+            peak_rss: Some(counters.peak_working_set_size as u64),
+            page_faults: Some(counters.page_fault_count as u64),
+            major_faults: None,
+            allocations: None,
+            tlb_misses: None,
+        }
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod sys {
+    use super::MemoryStats;
+
+    pub(super) fn read() -> MemoryStats {
+        MemoryStats::default()
+    }
+}
+
+#[cfg(test)]
+mod tests;
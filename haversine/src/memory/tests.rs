@@ -0,0 +1,105 @@
+use super::{
+    AllocationStats, CountingAllocator, MemoryStats, advise_drop_from_page_cache,
+    advise_huge_pages, reset_peak_live,
+};
+use std::alloc::{GlobalAlloc, Layout};
+use std::hint::black_box;
+
+#[test]
+fn test_since_subtracts_counters() {
+    let start = MemoryStats {
+        peak_rss: Some(100),
+        page_faults: Some(10),
+        major_faults: Some(1),
+        allocations: Some(AllocationStats {
+            count: 3,
+            bytes: 1000,
+            peak_live: 800,
+        }),
+        tlb_misses: Some(1000),
+    };
+    let end = MemoryStats {
+        peak_rss: Some(300),
+        page_faults: Some(25),
+        major_faults: None,
+        allocations: Some(AllocationStats {
+            count: 5,
+            bytes: 1500,
+            peak_live: 700,
+        }),
+        tlb_misses: Some(1500),
+    };
+    let expected = MemoryStats {
+        peak_rss: Some(300),
+        page_faults: Some(15),
+        major_faults: None,
+        allocations: Some(AllocationStats {
+            count: 2,
+            bytes: 500,
+            peak_live: 700,
+        }),
+        tlb_misses: Some(500),
+    };
+    assert_eq!(expected, end.since(&start));
+}
+
+#[test]
+fn test_counting_allocator() {
+    // The only test using the allocator, so the counters change by its
+    // calls alone, starting from nothing live.
+    let allocator = CountingAllocator;
+    let layout = Layout::from_size_align(1000, 8).unwrap();
+    // SAFETY: the layouts are valid and every block is freed with the
+    // layout it has.
+    unsafe {
+        let small = allocator.alloc(layout);
+        let start = MemoryStats::now();
+        let grown = allocator.realloc(small, layout, 3000);
+        let other = allocator.alloc_zeroed(layout);
+        allocator.dealloc(grown, Layout::from_size_align(3000, 8).unwrap());
+        allocator.dealloc(other, layout);
+        let phase = MemoryStats::now().since(&start);
+        let expected = AllocationStats {
+            count: 2,
+            bytes: 3000,
+            peak_live: 4000,
+        };
+        assert_eq!(Some(expected), phase.allocations);
+    }
+    reset_peak_live();
+    assert_eq!(0, AllocationStats::now().unwrap().peak_live);
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos", windows))]
+#[test]
+fn test_touching_memory_faults() {
+    const SIZE: usize = 64 * 1024 * 1024;
+    let start = MemoryStats::now();
+    let mut buffer = vec![0u8; SIZE];
+    for i in (0..SIZE).step_by(4096) {
+        buffer[i] = 1;
+    }
+    black_box(&buffer);
+    let phase = MemoryStats::now().since(&start);
+    assert!(phase.page_faults.unwrap() > 0, "{phase:?}");
+    assert!(phase.peak_rss.unwrap() >= SIZE as u64, "{phase:?}");
+}
+
+#[test]
+fn test_advise_huge_pages() {
+    assert!(!advise_huge_pages(&Vec::<u8>::new()));
+    let mut buffer = Vec::with_capacity(8 << 20);
+    advise_huge_pages(&buffer);
+    buffer.extend((0..8 << 20).map(|i| i as u8));
+    assert!(buffer.iter().enumerate().all(|(i, &b)| b == i as u8));
+}
+
+#[test]
+fn test_advise_drop_from_page_cache() {
+    let path = std::env::temp_dir().join(format!("memory-cache-{}", std::process::id()));
+    std::fs::write(&path, b"cached").unwrap();
+    let advised = advise_drop_from_page_cache(&std::fs::File::open(&path).unwrap());
+    assert_eq!(cfg!(target_os = "linux"), advised);
+    assert_eq!(b"cached".as_slice(), std::fs::read(&path).unwrap());
+    std::fs::remove_file(path).unwrap();
+}
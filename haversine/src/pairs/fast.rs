@@ -0,0 +1,152 @@
+use super::Pairs;
+use crate::parse::{BOM, Map, Number, Value, is_json_number};
+
+/// Reads a pairs document of the known schema straight into `Pairs`,
+/// without tokens or values, returning its other members and the pairs. The
+/// schema is an object whose members are numbers, but for `pairs`, an array
+/// of objects with exactly the number members `x0`, `y0`, `x1` and `y1`, in
+/// any order. Keys are matched byte for byte.
+///
+/// Returns `None` for any other document, valid or not, which the generic
+/// parser then reads, with its errors and duplicate key policies.
+pub fn parse_fast_schema(input: &str) -> Option<(Map, Pairs)> {
+    let input = input.strip_prefix(BOM).unwrap_or(input);
+    let mut reader = FastReader {
+        bytes: input.as_bytes(),
+        input,
+        position: 0,
+    };
+    let document = reader.document()?;
+    reader.skip_whitespace();
+    (reader.position == input.len()).then_some(document)
+}
+
+struct FastReader<'a> {
+    input: &'a str,
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> FastReader<'a> {
+    fn document(&mut self) -> Option<(Map, Pairs)> {
+        let mut members = Map::new();
+        let mut pairs = None;
+        self.expect(b'{')?;
+        if self.eat(b'}') {
+            return None;
+        }
+        loop {
+            let key = self.key()?;
+            if key == "pairs" {
+                if pairs.is_some() {
+                    return None;
+                }
+                pairs = Some(self.pairs()?);
+            } else {
+                let number = Value::Number(Number::from_literal(self.number_literal()?)?);
+                if members.insert(key.to_string(), number).is_some() {
+                    return None;
+                }
+            }
+            if !self.separator(b'}')? {
+                break;
+            }
+        }
+        Some((members, pairs?))
+    }
+
+    fn pairs(&mut self) -> Option<Pairs> {
+        let mut pairs = Pairs::default();
+        self.expect(b'[')?;
+        if self.eat(b']') {
+            return Some(pairs);
+        }
+        loop {
+            let [x0, y0, x1, y1] = self.pair()?;
+            pairs.push(x0, y0, x1, y1);
+            if !self.separator(b']')? {
+                return Some(pairs);
+            }
+        }
+    }
+
+    /// Reads a pair object into its coordinates, in the order x0, y0, x1, y1.
+    fn pair(&mut self) -> Option<[f64; 4]> {
+        let mut coordinates = [f64::NAN; 4];
+        let mut seen = 0u8;
+        self.expect(b'{')?;
+        for i in 0..4 {
+            let slot = match self.key()? {
+                "x0" => 0,
+                "y0" => 1,
+                "x1" => 2,
+                "y1" => 3,
+                _ => return None,
+            };
+            if seen & 1 << slot != 0 {
+                return None;
+            }
+            seen |= 1 << slot;
+            coordinates[slot] = Number::from_literal(self.number_literal()?)?.as_f64();
+            if self.separator(b'}')? != (i < 3) {
+                return None;
+            }
+        }
+        Some(coordinates)
+    }
+
+    /// Reads a key without escapes or control characters and the colon
+    /// after it.
+    fn key(&mut self) -> Option<&'a str> {
+        self.expect(b'"')?;
+        let start = self.position;
+        let length = self.bytes[start..].iter().position(|&b| b == b'"')?;
+        let key = &self.input[start..start + length];
+        if key.bytes().any(|b| b == b'\\' || b < b' ') {
+            return None;
+        }
+        self.position += length + 1;
+        self.expect(b':')?;
+        Some(key)
+    }
+
+    fn number_literal(&mut self) -> Option<&'a str> {
+        self.skip_whitespace();
+        let start = self.position;
+        let length = self.bytes[start..]
+            .iter()
+            .position(|b| !matches!(b, b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E'))
+            .unwrap_or(self.bytes.len() - start);
+        let literal = &self.input[start..start + length];
+        self.position += length;
+        is_json_number(literal).then_some(literal)
+    }
+
+    /// Reads a comma, returning `true`, or the closing `close`, returning
+    /// `false`.
+    fn separator(&mut self, close: u8) -> Option<bool> {
+        if self.eat(b',') {
+            Some(true)
+        } else {
+            self.expect(close).map(|()| false)
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        self.eat(byte).then_some(())
+    }
+
+    /// Skips whitespace, then `byte` if it is next.
+    fn eat(&mut self, byte: u8) -> bool {
+        self.skip_whitespace();
+        let found = self.bytes.get(self.position) == Some(&byte);
+        self.position += usize::from(found);
+        found
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.bytes.get(self.position) {
+            self.position += 1;
+        }
+    }
+}
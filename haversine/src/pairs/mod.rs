@@ -0,0 +1,451 @@
+use crate::geo::{
+    AngleUnit, BoundingBox, Point, PolylineError, decode_polyline, haversine_distance,
+    wrap_longitude_in,
+};
+use crate::memory;
+use crate::parse::{Cursor, KeyPath, Map, PathSegment, Value};
+use std::fmt;
+use std::ops::Range;
+
+mod fast;
+pub use fast::parse_fast_schema;
+
+mod sample;
+pub use sample::{SampleSize, Sampler};
+
+mod stream;
+pub use stream::{PairsReader, StreamError};
+
+/// Two points whose distance is computed.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Pair {
+    pub p0: Point,
+    pub p1: Point,
+}
+
+impl Pair {
+    pub fn new(p0: Point, p1: Point) -> Self {
+        Self { p0, p1 }
+    }
+
+    /// Reads a pair object, the element at `index` of the `pairs` array.
+    pub fn from_value(value: &Value, index: usize) -> Result<Self, PairsError> {
+        Self::from_object(value, |key| pair_path(index, key))
+    }
+
+    /// Reads a pair object, with `path` giving where its members are in
+    /// the errors.
+    fn from_object(
+        value: &Value,
+        path: impl Fn(Option<&str>) -> KeyPath,
+    ) -> Result<Self, PairsError> {
+        let Value::Object(object) = value else {
+            return Err(PairsError::InvalidPair(path(None)));
+        };
+        let coordinate = |key| {
+            object
+                .get(key)
+                .and_then(|value| value.try_into().ok())
+                .ok_or_else(|| PairsError::MissingCoordinate(path(Some(key))))
+        };
+        Ok(Pair::new(
+            Point::new(coordinate("x0")?, coordinate("y0")?),
+            Point::new(coordinate("x1")?, coordinate("y1")?),
+        ))
+    }
+
+    /// Great-circle distance between the points on a sphere of `radius`.
+    pub fn distance(&self, radius: f64) -> f64 {
+        haversine_distance(radius, self.p0.lon, self.p0.lat, self.p1.lon, self.p1.lat)
+    }
+}
+
+/// Coordinates of the pairs, stored as one array per coordinate so the
+/// compute loop scans memory linearly.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Pairs {
+    pub x0: Vec<f64>,
+    pub y0: Vec<f64>,
+    pub x1: Vec<f64>,
+    pub y1: Vec<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum PairsError {
+    InvalidPair(KeyPath),
+    MissingCoordinate(KeyPath),
+    /// A route that is not a string.
+    InvalidRoute(KeyPath),
+    InvalidPolyline(KeyPath, PolylineError),
+    /// A coordinate outside its range, or not finite: the index of the
+    /// pair, the name of the coordinate and its value.
+    CoordinateOutOfRange(usize, &'static str, f64),
+}
+
+impl fmt::Display for PairsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            // A pair on a line of its own is at the root.
+            PairsError::InvalidPair(path) if path.0.is_empty() => {
+                write!(f, "expected a pair object")
+            }
+            PairsError::InvalidPair(path) => write!(f, "expected a pair object at {path}"),
+            PairsError::MissingCoordinate(path) => write!(f, "expected a number at {path}"),
+            PairsError::InvalidRoute(path) => write!(f, "expected an encoded polyline at {path}"),
+            PairsError::InvalidPolyline(path, error) => write!(f, "{error} at {path}"),
+            PairsError::CoordinateOutOfRange(index, name, value) => {
+                let kind = if name.starts_with('x') {
+                    "longitude"
+                } else {
+                    "latitude"
+                };
+                write!(
+                    f,
+                    "{name} of pair {index} is {value}, out of the range of a {kind}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for PairsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PairsError::InvalidPolyline(_, error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Disagreement between the `count` field of a document and the number of
+/// pairs actually in it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CountMismatch {
+    pub declared: usize,
+    pub parsed: usize,
+}
+
+/// Compares the `count` hint of the document, if it has one, with the number
+/// of parsed pairs. The hint is never trusted over the pairs themselves.
+pub fn check_count(document: &Map, pairs: &Pairs) -> Option<CountMismatch> {
+    let declared = match document.get("count")? {
+        Value::Number(n) => match n.as_u64() {
+            Some(n) => n as usize,
+            None if n.as_f64() >= 0.0 && n.as_f64().fract() == 0.0 => n.as_f64() as usize,
+            None => return None,
+        },
+        _ => return None,
+    };
+    (declared != pairs.len()).then_some(CountMismatch {
+        declared,
+        parsed: pairs.len(),
+    })
+}
+
+impl Pairs {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            x0: Vec::with_capacity(capacity),
+            y0: Vec::with_capacity(capacity),
+            x1: Vec::with_capacity(capacity),
+            y1: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Extracts the coordinates from the `pairs` array of a parsed document.
+    pub fn from_values(values: &[Value]) -> Result<Self, PairsError> {
+        let mut pairs = Pairs::with_capacity(values.len());
+        for value in values {
+            pairs.push_value(value)?;
+        }
+        Ok(pairs)
+    }
+
+    /// Extracts the coordinates from the `pairs` array of a tape, reading
+    /// only the coordinates of every pair object.
+    pub fn from_tape(array: Cursor<'_>) -> Result<Self, PairsError> {
+        let mut pairs = Pairs::with_capacity(array.iter().count());
+        for pair in array.iter() {
+            let index = pairs.len();
+            if !pair.is_object() {
+                return Err(PairsError::InvalidPair(pair_path(index, None)));
+            }
+            let coordinate = |key| {
+                pair.get(key)
+                    .and_then(|value| value.as_f64())
+                    .ok_or_else(|| PairsError::MissingCoordinate(pair_path(index, Some(key))))
+            };
+            pairs.push(
+                coordinate("x0")?,
+                coordinate("y0")?,
+                coordinate("x1")?,
+                coordinate("y1")?,
+            );
+        }
+        Ok(pairs)
+    }
+
+    /// Appends the coordinates of a pair object, the next element of the
+    /// `pairs` array.
+    pub fn push_value(&mut self, value: &Value) -> Result<(), PairsError> {
+        self.push_pair(Pair::from_value(value, self.len())?);
+        Ok(())
+    }
+
+    pub fn push(&mut self, x0: f64, y0: f64, x1: f64, y1: f64) {
+        self.x0.push(x0);
+        self.y0.push(y0);
+        self.x1.push(x1);
+        self.y1.push(y1);
+    }
+
+    pub fn push_pair(&mut self, pair: Pair) {
+        self.push(pair.p0.lon, pair.p0.lat, pair.p1.lon, pair.p1.lat);
+    }
+
+    /// Appends a pair for every segment of a route, from each point to the
+    /// next.
+    pub fn push_route(&mut self, points: &[Point]) {
+        for segment in points.windows(2) {
+            self.push_pair(Pair::new(segment[0], segment[1]));
+        }
+    }
+
+    /// Appends the pairs of `other`, in order.
+    pub fn extend_from(&mut self, other: &Pairs) {
+        self.x0.extend_from_slice(&other.x0);
+        self.y0.extend_from_slice(&other.y0);
+        self.x1.extend_from_slice(&other.x1);
+        self.y1.extend_from_slice(&other.y1);
+    }
+
+    /// Copies the pairs into buffers advised to be backed by huge pages,
+    /// returning whether the system took the advice for all of them.
+    pub fn to_huge_pages(&self) -> (Pairs, bool) {
+        let mut copy = Pairs::with_capacity(self.len());
+        let coordinates = [
+            (&mut copy.x0, &self.x0),
+            (&mut copy.y0, &self.y0),
+            (&mut copy.x1, &self.x1),
+            (&mut copy.y1, &self.y1),
+        ];
+        let mut advised = true;
+        for (buffer, values) in coordinates {
+            advised &= memory::advise_huge_pages(buffer);
+            buffer.extend_from_slice(values);
+        }
+        (copy, advised)
+    }
+
+    /// Copies the pairs in `range`.
+    pub fn slice(&self, range: Range<usize>) -> Pairs {
+        Pairs {
+            x0: self.x0[range.clone()].to_vec(),
+            y0: self.y0[range.clone()].to_vec(),
+            x1: self.x1[range.clone()].to_vec(),
+            y1: self.y1[range].to_vec(),
+        }
+    }
+
+    /// Wraps the longitudes into [-180, 180] and clamps the latitudes into
+    /// [-90, 90], so that every point is one of the sphere. Coordinates that
+    /// are not finite are left as they are, and counted.
+    pub fn canonicalize(&mut self) -> Canonicalized {
+        self.canonicalize_in(AngleUnit::Degrees)
+    }
+
+    /// `canonicalize` for coordinates in `unit`.
+    pub fn canonicalize_in(&mut self, unit: AngleUnit) -> Canonicalized {
+        let (max_lon, max_lat) = (unit.half_turn(), unit.half_turn() / 2.0);
+        let mut counts = Canonicalized::default();
+        for lon in self.x0.iter_mut().chain(&mut self.x1) {
+            if !lon.is_finite() {
+                counts.non_finite += 1;
+            } else if !(-max_lon..=max_lon).contains(lon) {
+                *lon = wrap_longitude_in(unit, *lon);
+                counts.wrapped += 1;
+            }
+        }
+        for lat in self.y0.iter_mut().chain(&mut self.y1) {
+            if !lat.is_finite() {
+                counts.non_finite += 1;
+            } else if !(-max_lat..=max_lat).contains(lat) {
+                *lat = lat.clamp(-max_lat, max_lat);
+                counts.clamped += 1;
+            }
+        }
+        counts
+    }
+
+    /// Checks that every coordinate is finite and within its range, so that
+    /// `canonicalize` would change nothing, returning the first that is not.
+    pub fn check_coordinates(&self) -> Result<(), PairsError> {
+        self.check_coordinates_in(AngleUnit::Degrees)
+    }
+
+    /// `check_coordinates` for coordinates in `unit`.
+    pub fn check_coordinates_in(&self, unit: AngleUnit) -> Result<(), PairsError> {
+        let (max_lon, max_lat) = (unit.half_turn(), unit.half_turn() / 2.0);
+        for index in 0..self.len() {
+            let coordinates = [
+                ("x0", self.x0[index], max_lon),
+                ("y0", self.y0[index], max_lat),
+                ("x1", self.x1[index], max_lon),
+                ("y1", self.y1[index], max_lat),
+            ];
+            for (name, value, range) in coordinates {
+                if !(-range..=range).contains(&value) {
+                    return Err(PairsError::CoordinateOutOfRange(index, name, value));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Keeps only the pairs with both points inside `bbox`, returning how
+    /// many were removed.
+    pub fn retain_within(&mut self, bbox: &BoundingBox) -> usize {
+        self.retain_within_in(bbox, AngleUnit::Degrees)
+    }
+
+    /// `retain_within` for coordinates in `unit`. The box is in degrees.
+    pub fn retain_within_in(&mut self, bbox: &BoundingBox, unit: AngleUnit) -> usize {
+        let contains = |lon: f64, lat: f64| {
+            bbox.contains(Point::new(unit.to_degrees(lon), unit.to_degrees(lat)))
+        };
+        let mut kept = 0;
+        for index in 0..self.len() {
+            let (x0, y0, x1, y1) = (
+                self.x0[index],
+                self.y0[index],
+                self.x1[index],
+                self.y1[index],
+            );
+            if contains(x0, y0) && contains(x1, y1) {
+                self.x0[kept] = x0;
+                self.y0[kept] = y0;
+                self.x1[kept] = x1;
+                self.y1[kept] = y1;
+                kept += 1;
+            }
+        }
+        let removed = self.len() - kept;
+        for coordinates in [&mut self.x0, &mut self.y0, &mut self.x1, &mut self.y1] {
+            coordinates.truncate(kept);
+        }
+        removed
+    }
+
+    pub fn len(&self) -> usize {
+        self.x0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.x0.is_empty()
+    }
+
+    /// The columns `x0`, `y0`, `x1` and `y1`, in that order.
+    pub fn columns(&self) -> [&[f64]; 4] {
+        [&self.x0, &self.y0, &self.x1, &self.y1]
+    }
+
+    pub fn get(&self, index: usize) -> Option<Pair> {
+        (index < self.len()).then(|| {
+            Pair::new(
+                Point::new(self.x0[index], self.y0[index]),
+                Point::new(self.x1[index], self.y1[index]),
+            )
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Pair> + '_ {
+        (0..self.len()).filter_map(|index| self.get(index))
+    }
+
+    /// The pair objects of a pairs document.
+    pub fn to_values(&self) -> Vec<Value> {
+        self.iter()
+            .map(|pair| {
+                Value::object()
+                    .insert("x0", pair.p0.lon)
+                    .insert("y0", pair.p0.lat)
+                    .insert("x1", pair.p1.lon)
+                    .insert("y1", pair.p1.lat)
+            })
+            .collect()
+    }
+}
+
+/// Coordinates changed by `Pairs::canonicalize`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Canonicalized {
+    /// Longitudes wrapped into [-180, 180].
+    pub wrapped: usize,
+    /// Latitudes clamped into [-90, 90].
+    pub clamped: usize,
+    /// Coordinates left as they are, not being finite.
+    pub non_finite: usize,
+}
+
+/// Single-precision copy of `Pairs`, the input of the `f32` compute kernel.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PairsF32 {
+    pub x0: Vec<f32>,
+    pub y0: Vec<f32>,
+    pub x1: Vec<f32>,
+    pub y1: Vec<f32>,
+}
+
+impl PairsF32 {
+    pub fn len(&self) -> usize {
+        self.x0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.x0.is_empty()
+    }
+}
+
+impl From<&Pairs> for PairsF32 {
+    /// Rounds every coordinate to the nearest `f32`.
+    fn from(pairs: &Pairs) -> Self {
+        let round = |coordinates: &[f64]| coordinates.iter().map(|&c| c as f32).collect();
+        Self {
+            x0: round(&pairs.x0),
+            y0: round(&pairs.y0),
+            x1: round(&pairs.x1),
+            y1: round(&pairs.y1),
+        }
+    }
+}
+
+/// Decodes the `routes` array of a document, whose elements are polylines
+/// in Google's encoded format with `precision` decimal digits.
+pub fn routes_from_values(values: &[Value], precision: u32) -> Result<Vec<Vec<Point>>, PairsError> {
+    let path = |index| {
+        KeyPath(vec![
+            PathSegment::Key("routes".into()),
+            PathSegment::Index(index),
+        ])
+    };
+    values
+        .iter()
+        .enumerate()
+        .map(|(index, value)| match value {
+            Value::String(encoded) => decode_polyline(encoded, precision)
+                .map_err(|error| PairsError::InvalidPolyline(path(index), error)),
+            _ => Err(PairsError::InvalidRoute(path(index))),
+        })
+        .collect()
+}
+
+/// Path of the pair at `index` of the `pairs` array, or of its `key`.
+fn pair_path(index: usize, key: Option<&str>) -> KeyPath {
+    let mut segments = vec![PathSegment::Key("pairs".into()), PathSegment::Index(index)];
+    segments.extend(key.map(|key| PathSegment::Key(key.into())));
+    KeyPath(segments)
+}
+
+#[cfg(test)]
+mod tests;
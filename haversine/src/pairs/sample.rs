@@ -0,0 +1,76 @@
+use super::{Pair, Pairs};
+use crate::rng::Rng;
+
+/// How many pairs a sample keeps.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SampleSize {
+    /// Each pair with this probability, within `(0, 1]`.
+    Fraction(f64),
+    /// This many pairs, or all of them if there are fewer.
+    Count(usize),
+}
+
+impl SampleSize {
+    /// Parses a percentage such as `1%` or `0.5%`, or a number of pairs.
+    pub fn from_spec(spec: &str) -> Option<Self> {
+        match spec.strip_suffix('%') {
+            Some(percent) => {
+                let percent: f64 = percent.parse().ok()?;
+                (percent > 0.0 && percent <= 100.0).then_some(SampleSize::Fraction(percent / 100.0))
+            }
+            None => spec.parse().ok().filter(|&n| n > 0).map(SampleSize::Count),
+        }
+    }
+}
+
+/// Draws a uniformly random sample of the pairs offered to it one at a
+/// time, holding no more than the sample. A count is kept by reservoir
+/// sampling, so every pair is equally likely to end in it whatever the
+/// number of pairs.
+pub struct Sampler {
+    size: SampleSize,
+    rng: Box<dyn Rng>,
+    seen: usize,
+    pairs: Pairs,
+}
+
+impl Sampler {
+    pub fn new(size: SampleSize, rng: Box<dyn Rng>) -> Self {
+        Self {
+            size,
+            rng,
+            seen: 0,
+            pairs: Pairs::default(),
+        }
+    }
+
+    pub fn offer(&mut self, pair: Pair) {
+        self.seen += 1;
+        match self.size {
+            SampleSize::Fraction(fraction) => {
+                if self.rng.uniform(0.0, 1.0) < fraction {
+                    self.pairs.push_pair(pair);
+                }
+            }
+            SampleSize::Count(count) if self.pairs.len() < count => self.pairs.push_pair(pair),
+            SampleSize::Count(count) => {
+                let index = self.rng.below(self.seen);
+                if index < count {
+                    self.pairs.x0[index] = pair.p0.lon;
+                    self.pairs.y0[index] = pair.p0.lat;
+                    self.pairs.x1[index] = pair.p1.lon;
+                    self.pairs.y1[index] = pair.p1.lat;
+                }
+            }
+        }
+    }
+
+    /// Number of pairs offered so far.
+    pub fn seen(&self) -> usize {
+        self.seen
+    }
+
+    pub fn into_pairs(self) -> Pairs {
+        self.pairs
+    }
+}
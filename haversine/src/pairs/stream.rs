@@ -0,0 +1,352 @@
+use super::{Pair, Pairs, PairsError};
+use crate::cancel::CancelToken;
+use crate::parse::{
+    BOM, DuplicateKeyPolicy, Event, FeedParser, KeyPath, Limits, LoneSurrogatePolicy, Map,
+    NumberRangePolicy, Parser, ParsingError, PathSegment, Value,
+};
+use crate::reader::InputSource;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read};
+
+/// Size of the chunks read from the input.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum StreamError {
+    Io(io::Error),
+    Parsing(ParsingError),
+    Pairs(PairsError),
+    /// The document is not an object.
+    NotAnObject,
+    /// An error on a line of JSON Lines input, counted from 1.
+    Line(usize, Box<StreamError>),
+    /// The token of the reader was cancelled before the end of the input.
+    /// The pairs passed on so far were read whole.
+    Cancelled,
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamError::Io(_) => write!(f, "failed to read the document"),
+            StreamError::Parsing(_) => write!(f, "invalid JSON"),
+            StreamError::Pairs(_) => write!(f, "invalid pairs"),
+            StreamError::NotAnObject => write!(f, "the document is not an object"),
+            StreamError::Line(line, _) => write!(f, "invalid line {line}"),
+            StreamError::Cancelled => write!(f, "cancelled before the end of the input"),
+        }
+    }
+}
+
+impl Error for StreamError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            StreamError::Io(error) => Some(error),
+            StreamError::Parsing(error) => Some(error),
+            StreamError::Pairs(error) => Some(error),
+            StreamError::NotAnObject | StreamError::Cancelled => None,
+            StreamError::Line(_, error) => Some(error.as_ref()),
+        }
+    }
+}
+
+impl From<io::Error> for StreamError {
+    fn from(error: io::Error) -> Self {
+        StreamError::Io(error)
+    }
+}
+
+impl From<ParsingError> for StreamError {
+    fn from(error: ParsingError) -> Self {
+        StreamError::Parsing(error)
+    }
+}
+
+impl From<PairsError> for StreamError {
+    fn from(error: PairsError) -> Self {
+        StreamError::Pairs(error)
+    }
+}
+
+/// Reads a pairs document in chunks, for input such as a pipe that should
+/// not be buffered whole.
+///
+/// The pairs go straight into `Pairs`, one pair object at a time; the other
+/// members of the document are kept as values.
+#[derive(Debug, Default)]
+pub struct PairsReader {
+    duplicate_keys: DuplicateKeyPolicy,
+    lone_surrogates: LoneSurrogatePolicy,
+    out_of_range: NumberRangePolicy,
+    limits: Limits,
+    allow_control_chars: bool,
+    cancel: Option<CancelToken>,
+}
+
+impl PairsReader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how duplicate object keys are handled.
+    pub fn duplicate_keys(mut self, policy: DuplicateKeyPolicy) -> Self {
+        self.duplicate_keys = policy;
+        self
+    }
+
+    /// Sets how escaped lone surrogates in strings are handled.
+    pub fn lone_surrogates(mut self, policy: LoneSurrogatePolicy) -> Self {
+        self.lone_surrogates = policy;
+        self
+    }
+
+    /// Sets how numbers out of the range of `f64` are handled.
+    pub fn out_of_range(mut self, policy: NumberRangePolicy) -> Self {
+        self.out_of_range = policy;
+        self
+    }
+
+    /// Sets the limits of the document, or of each line of JSON Lines.
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Accepts control characters written unescaped in strings.
+    pub fn allow_control_chars(mut self, allow: bool) -> Self {
+        self.allow_control_chars = allow;
+        self
+    }
+
+    /// Stops reading with `StreamError::Cancelled` once `token` is
+    /// cancelled. It is checked before each chunk, or each line of JSON
+    /// Lines.
+    pub fn cancel(mut self, token: CancelToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    fn check_cancelled(&self) -> Result<(), StreamError> {
+        match self.cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+            true => Err(StreamError::Cancelled),
+            false => Ok(()),
+        }
+    }
+
+    /// Reads the document, returning its members other than `pairs`, and
+    /// the pairs.
+    pub fn read(self, reader: impl Read) -> Result<(Map, Pairs), StreamError> {
+        let mut pairs = Pairs::default();
+        let (document, _) = self.read_each(reader, |pair| pairs.push_pair(pair))?;
+        Ok((document, pairs))
+    }
+
+    /// Reads the document of `source` as `read` does.
+    pub fn read_source(self, source: &mut dyn InputSource) -> Result<(Map, Pairs), StreamError> {
+        self.read(source.open()?)
+    }
+
+    /// Reads the document, passing each pair to `each` as soon as it is
+    /// read rather than keeping it. Returns the members other than `pairs`,
+    /// and the number of pairs.
+    pub fn read_each(
+        self,
+        mut reader: impl Read,
+        each: impl FnMut(Pair),
+    ) -> Result<(Map, usize), StreamError> {
+        let mut parser = FeedParser::new()
+            .lone_surrogates(self.lone_surrogates)
+            .out_of_range(self.out_of_range)
+            .limits(self.limits)
+            .allow_control_chars(self.allow_control_chars);
+        let mut collector = Collector::new(self.duplicate_keys, each);
+        let mut chunk = vec![0; CHUNK_SIZE];
+        loop {
+            self.check_cancelled()?;
+            let n = match reader.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error.into()),
+            };
+            for event in parser.feed(&chunk[..n])? {
+                collector.event(event)?;
+            }
+        }
+        for event in parser.finish()? {
+            collector.event(event)?;
+        }
+        match collector.document {
+            Some(document) => Ok((document, collector.count)),
+            None => Err(StreamError::NotAnObject),
+        }
+    }
+}
+
+impl PairsReader {
+    /// Reads JSON Lines, also known as NDJSON: one pair object per line,
+    /// with the lines that are blank skipped. There are no other members.
+    pub fn read_lines(self, reader: impl Read) -> Result<Pairs, StreamError> {
+        let mut pairs = Pairs::default();
+        self.read_lines_each(reader, |pair| pairs.push_pair(pair))?;
+        Ok(pairs)
+    }
+
+    /// Reads JSON Lines like `read_lines`, passing each pair to `each` as
+    /// soon as its line is read. Returns the number of pairs.
+    pub fn read_lines_each(
+        self,
+        reader: impl Read,
+        mut each: impl FnMut(Pair),
+    ) -> Result<usize, StreamError> {
+        let mut reader = BufReader::with_capacity(CHUNK_SIZE, reader);
+        let mut line = String::new();
+        let mut count = 0;
+        for number in 1.. {
+            self.check_cancelled()?;
+            line.clear();
+            let at_line = |error: StreamError| StreamError::Line(number, Box::new(error));
+            if reader.read_line(&mut line).map_err(|e| at_line(e.into()))? == 0 {
+                break;
+            }
+            let mut text = line.trim_end_matches(['\n', '\r']);
+            if number == 1 {
+                text = text.strip_prefix(BOM).unwrap_or(text);
+            }
+            if text.trim_ascii().is_empty() {
+                continue;
+            }
+            let value = Parser::new(text)
+                .duplicate_keys(self.duplicate_keys)
+                .lone_surrogates(self.lone_surrogates)
+                .out_of_range(self.out_of_range)
+                .limits(self.limits)
+                .allow_control_chars(self.allow_control_chars)
+                .parse()
+                .map_err(|error| at_line(error.into()))?
+                .unwrap_or(Value::Null);
+            let pair = Pair::from_object(&value, line_path).map_err(|e| at_line(e.into()))?;
+            each(pair);
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+/// Path of the member `key` of a pair alone on its line.
+fn line_path(key: Option<&str>) -> KeyPath {
+    KeyPath(
+        key.map(|key| PathSegment::Key(key.into()))
+            .into_iter()
+            .collect(),
+    )
+}
+
+/// Builds the document from the parser events, passing on the pairs.
+struct Collector<F> {
+    duplicate_keys: DuplicateKeyPolicy,
+    /// Top-level members, once the document has started.
+    document: Option<Map>,
+    each: F,
+    /// Number of pairs passed on.
+    count: usize,
+    /// Inside the top-level `pairs` array.
+    in_pairs: bool,
+    /// Containers being built, with the key they go under in their parent.
+    partial: Vec<(Option<String>, Value)>,
+    /// Key of the next member.
+    key: Option<String>,
+}
+
+impl<F: FnMut(Pair)> Collector<F> {
+    fn new(duplicate_keys: DuplicateKeyPolicy, each: F) -> Self {
+        Self {
+            duplicate_keys,
+            document: None,
+            each,
+            count: 0,
+            in_pairs: false,
+            partial: Vec::new(),
+            key: None,
+        }
+    }
+
+    fn event(&mut self, event: Event) -> Result<(), StreamError> {
+        let value = match event {
+            Event::StartObject if self.document.is_none() => {
+                self.document = Some(Map::new());
+                return Ok(());
+            }
+            _ if self.document.is_none() => return Err(StreamError::NotAnObject),
+            Event::StartArray
+                if self.partial.is_empty()
+                    && !self.in_pairs
+                    && self.key.as_deref() == Some("pairs") =>
+            {
+                self.key = None;
+                self.in_pairs = true;
+                return Ok(());
+            }
+            Event::EndArray if self.partial.is_empty() && self.in_pairs => {
+                self.in_pairs = false;
+                return Ok(());
+            }
+            Event::EndObject if self.partial.is_empty() && !self.in_pairs => return Ok(()),
+            Event::StartObject | Event::StartArray => {
+                let container = match event {
+                    Event::StartObject => Value::object(),
+                    _ => Value::array(),
+                };
+                self.partial.push((self.key.take(), container));
+                return Ok(());
+            }
+            Event::EndObject | Event::EndArray => {
+                let (key, value) = self.partial.pop().expect("Balanced events");
+                self.key = key;
+                value
+            }
+            Event::Key(key) => {
+                self.key = Some(key);
+                return Ok(());
+            }
+            Event::Null => Value::Null,
+            Event::Bool(b) => Value::Bool(b),
+            Event::Number(n) => Value::Number(n),
+            Event::String(s) => Value::String(s),
+        };
+        self.complete(value)
+    }
+
+    /// Places a complete value in its parent.
+    fn complete(&mut self, value: Value) -> Result<(), StreamError> {
+        let map = match self.partial.last_mut() {
+            Some((_, Value::Object(map))) => map,
+            Some((_, array)) => {
+                array.push(value);
+                return Ok(());
+            }
+            None if self.in_pairs => {
+                (self.each)(Pair::from_value(&value, self.count)?);
+                self.count += 1;
+                return Ok(());
+            }
+            None => self.document.as_mut().expect("Document started"),
+        };
+        let key = self.key.take().expect("Object member has a key");
+        match map.get_mut(&key) {
+            None => {
+                map.insert(key, value);
+            }
+            Some(existing) => match self.duplicate_keys {
+                DuplicateKeyPolicy::Error => {
+                    return Err(ParsingError::DuplicateObjectKey(key).into());
+                }
+                DuplicateKeyPolicy::FirstWins => {}
+                DuplicateKeyPolicy::LastWins => *existing = value,
+            },
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,500 @@
+use super::{
+    Canonicalized, CountMismatch, Pair, Pairs, PairsError, PairsReader, SampleSize, Sampler,
+    StreamError, check_count, parse_fast_schema, routes_from_values,
+};
+use crate::cancel::CancelToken;
+use crate::geo::{AngleUnit, BoundingBox, Point, PolylineError};
+use crate::parse::{
+    DuplicateKeyPolicy, KeyPath, Map, Number, Parser, ParsingError, PathSegment, Value,
+};
+use crate::rng::Xoshiro256PlusPlus;
+use std::io::Read;
+
+fn document(input: &str) -> Map {
+    match Parser::new(input).parse() {
+        Ok(Some(Value::Object(object))) => object,
+        other => panic!("Expected an object, got {other:?}"),
+    }
+}
+
+fn pairs(document: &Map) -> Result<Pairs, PairsError> {
+    match document.get("pairs") {
+        Some(Value::Array(values)) => Pairs::from_values(values),
+        other => panic!("Expected an array, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_from_values() {
+    let document = document(r#"{"pairs": [{"x0": 1, "y0": 2, "x1": 3, "y1": 4}]}"#);
+    let pairs = pairs(&document).unwrap();
+    assert_eq!(1, pairs.len());
+    assert_eq!(
+        (1.0, 2.0, 3.0, 4.0),
+        (pairs.x0[0], pairs.y0[0], pairs.x1[0], pairs.y1[0])
+    );
+}
+
+#[test]
+fn test_from_values_missing_coordinate() {
+    let document = document(r#"{"pairs": [{"x0": 1, "y0": 2, "x1": 3, "y1": "4"}]}"#);
+    let path = KeyPath(vec![
+        PathSegment::Key("pairs".into()),
+        PathSegment::Index(0),
+        PathSegment::Key("y1".into()),
+    ]);
+    assert_eq!(Err(PairsError::MissingCoordinate(path)), pairs(&document));
+}
+
+#[test]
+fn test_from_tape_matches_from_values() {
+    let input = r#"{"pairs": [
+        {"x0": 1, "y0": 2, "x1": 3, "y1": 4},
+        {"y1": -4.5, "x1": 3.5, "extra": [1, {"x0": 9}], "y0": 2.5, "x0": 1.5}
+    ]}"#;
+    let tape = Parser::new(input).parse_tape().unwrap().unwrap();
+    let from_tape = Pairs::from_tape(tape.get("pairs").unwrap());
+    assert_eq!(pairs(&document(input)), from_tape);
+
+    let input = r#"{"pairs": [{"x0": 1, "y0": 2, "x1": 3, "y1": 4}, 5]}"#;
+    let tape = Parser::new(input).parse_tape().unwrap().unwrap();
+    let from_tape = Pairs::from_tape(tape.get("pairs").unwrap());
+    assert_eq!(pairs(&document(input)), from_tape);
+    assert!(matches!(from_tape, Err(PairsError::InvalidPair(_))));
+}
+
+#[test]
+fn test_count_hint_matches() {
+    let document = document(r#"{"count": 1, "pairs": [{"x0": 1, "y0": 2, "x1": 3, "y1": 4}]}"#);
+    assert_eq!(None, check_count(&document, &pairs(&document).unwrap()));
+}
+
+#[test]
+fn test_count_hint_mismatch() {
+    let document = document(r#"{"count": 3, "pairs": [{"x0": 1, "y0": 2, "x1": 3, "y1": 4}]}"#);
+    let mismatch = CountMismatch {
+        declared: 3,
+        parsed: 1,
+    };
+    assert_eq!(
+        Some(mismatch),
+        check_count(&document, &pairs(&document).unwrap())
+    );
+}
+
+#[test]
+fn test_count_hint_missing_or_invalid() {
+    for input in [
+        r#"{"pairs": []}"#,
+        r#"{"count": -1, "pairs": []}"#,
+        r#"{"count": "2", "pairs": []}"#,
+    ] {
+        let document = document(input);
+        assert_eq!(None, check_count(&document, &pairs(&document).unwrap()));
+    }
+}
+
+/// Reader handing out its input one byte per read.
+struct Trickle<'a>(&'a [u8]);
+
+impl Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let Some((first, rest)) = self.0.split_first() else {
+            return Ok(0);
+        };
+        buf[0] = *first;
+        self.0 = rest;
+        Ok(1)
+    }
+}
+
+#[test]
+fn test_reader_matches_from_values() {
+    let input = r#"{"radius": 6372.8, "pairs": [
+        {"x0": 1, "y0": 2, "x1": 3, "y1": 4},
+        {"y1": -4.5, "x1": 3.5, "extra": [1, {"a": null}], "y0": 2.5, "x0": 1.5}
+    ], "meta": {"count": 2, "tags": ["a", "b"]}}"#;
+    let (members, streamed) = PairsReader::new().read(Trickle(input.as_bytes())).unwrap();
+
+    let mut expected = document(input);
+    assert_eq!(pairs(&expected).unwrap(), streamed);
+    expected = expected
+        .iter()
+        .filter(|(key, _)| *key != "pairs")
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    assert_eq!(expected, members);
+}
+
+#[test]
+fn test_reader_errors() {
+    let read = |input: &str| PairsReader::new().read(input.as_bytes());
+    assert!(matches!(read("[1, 2]"), Err(StreamError::NotAnObject)));
+    assert!(matches!(read(""), Err(StreamError::NotAnObject)));
+    assert!(matches!(
+        read(r#"{"pairs": [{"x0": 1}]}"#),
+        Err(StreamError::Pairs(PairsError::MissingCoordinate(_)))
+    ));
+    assert!(matches!(
+        read(r#"{"radius": 1, "radius": 2}"#),
+        Err(StreamError::Parsing(ParsingError::DuplicateObjectKey(_)))
+    ));
+}
+
+/// Reader of one byte at a time that cancels `token` once `after` bytes
+/// are read.
+struct Cancelling<'a> {
+    input: Trickle<'a>,
+    after: usize,
+    token: CancelToken,
+}
+
+impl Read for Cancelling<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self.after {
+            0 => self.token.cancel(),
+            _ => self.after -= 1,
+        }
+        self.input.read(buf)
+    }
+}
+
+#[test]
+fn test_reader_cancelled() {
+    let input = r#"{"pairs": [{"x0": 1, "y0": 2, "x1": 3, "y1": 4}, {"x0": 5"#;
+    let token = CancelToken::new();
+    let reader = Cancelling {
+        input: Trickle(input.as_bytes()),
+        after: input.find(", {").unwrap(),
+        token: token.clone(),
+    };
+    let mut count = 0;
+    let read = PairsReader::new()
+        .cancel(token)
+        .read_each(reader, |_| count += 1);
+    assert!(matches!(read, Err(StreamError::Cancelled)));
+    assert_eq!(1, count);
+
+    let token = CancelToken::new();
+    token.cancel();
+    let lines = PairsReader::new()
+        .cancel(token)
+        .read_lines(&b"{\"x0\": 1, \"y0\": 2, \"x1\": 3, \"y1\": 4}"[..]);
+    assert!(matches!(lines, Err(StreamError::Cancelled)));
+}
+
+#[test]
+fn test_read_lines() {
+    let input = "\u{feff}{\"x0\": 1, \"y0\": 2, \"x1\": 3, \"y1\": 4}\r\n\n  \n{\"y1\": -4, \"x1\": -3, \"y0\": -2, \"x0\": -1}";
+    let pairs = PairsReader::new().read_lines(input.as_bytes()).unwrap();
+    let mut expected = Pairs::default();
+    expected.push(1.0, 2.0, 3.0, 4.0);
+    expected.push(-1.0, -2.0, -3.0, -4.0);
+    assert_eq!(expected, pairs);
+
+    let mut count = 0;
+    let read = PairsReader::new().read_lines_each(input.as_bytes(), |_| count += 1);
+    assert_eq!(2, read.unwrap());
+    assert_eq!(2, count);
+    assert_eq!(
+        Pairs::default(),
+        PairsReader::new().read_lines(&b""[..]).unwrap()
+    );
+}
+
+#[test]
+fn test_read_lines_errors() {
+    let read = |input: &str| PairsReader::new().read_lines(input.as_bytes());
+    let pair = r#"{"x0": 1, "y0": 2, "x1": 3, "y1": 4}"#;
+    let error = read(&format!("{pair}\n\n{{\"x0\": 1}}\n")).unwrap_err();
+    let StreamError::Line(3, error) = error else {
+        panic!("Expected an error on line 3, got {error:?}");
+    };
+    let path = KeyPath(vec![PathSegment::Key("y0".into())]);
+    assert!(matches!(
+        *error,
+        StreamError::Pairs(PairsError::MissingCoordinate(ref at)) if *at == path
+    ));
+    assert!(matches!(
+        read(&format!("{pair}\n[1]")),
+        Err(StreamError::Line(2, error)) if matches!(*error, StreamError::Pairs(PairsError::InvalidPair(_)))
+    ));
+    assert!(matches!(
+        read(&format!("{pair} {pair}")),
+        Err(StreamError::Line(1, error)) if matches!(*error, StreamError::Parsing(_))
+    ));
+    assert_eq!(
+        "expected a pair object",
+        PairsError::InvalidPair(KeyPath(Vec::new())).to_string()
+    );
+}
+
+#[test]
+fn test_reader_duplicate_keys_last_wins() {
+    let (members, _) = PairsReader::new()
+        .duplicate_keys(DuplicateKeyPolicy::LastWins)
+        .read(r#"{"radius": 1, "radius": 2, "pairs": []}"#.as_bytes())
+        .unwrap();
+    assert_eq!(Some(&Value::Number(Number::U64(2))), members.get("radius"));
+}
+
+#[test]
+fn test_fast_schema_matches_generic_parser() {
+    let input = r#" {
+        "pairs": [
+            {"x0": -131.62887211953557, "y0": 62.5, "x1": 1e2, "y1": -0},
+            {"y1": 4, "x1": 3, "y0": 2, "x0": 18446744073709551617}
+        ],
+        "avg_dist": 10010.618678826571,
+        "radius": 6372
+    } "#;
+    let (members, fast) = parse_fast_schema(input).unwrap();
+    let expected = document(input);
+    assert_eq!(pairs(&expected).unwrap(), fast);
+    let others: Map = expected
+        .iter()
+        .filter(|(key, _)| *key != "pairs")
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    assert_eq!(others, members);
+    let (_, empty) = parse_fast_schema(r#"{"pairs": []}"#).unwrap();
+    assert!(empty.is_empty());
+    let (_, with_bom) = parse_fast_schema(&format!("\u{feff}{input}")).unwrap();
+    assert_eq!(fast, with_bom);
+}
+
+#[test]
+fn test_fast_schema_declines_other_documents() {
+    for input in [
+        r#"{"pairs": [{"x0": 1, "y0": 2, "x1": 3}]}"#,
+        r#"{"pairs": [{"x0": 1, "y0": 2, "x1": 3, "y1": 4, "z": 5}]}"#,
+        r#"{"pairs": [{"x0": 1, "x0": 2, "x1": 3, "y1": 4}]}"#,
+        r#"{"pairs": [{"x0": "1", "y0": 2, "x1": 3, "y1": 4}]}"#,
+        r#"{"pairs": [{"x\u0030": 1, "y0": 2, "x1": 3, "y1": 4}]}"#,
+        r#"{"pairs": [{"x0": 01, "y0": 2, "x1": 3, "y1": 4}]}"#,
+        r#"{"pairs": [], "name": "file"}"#,
+        r#"{"pairs": [], "pairs": []}"#,
+        r#"{"radius": 1, "radius": 2, "pairs": []}"#,
+        "{\"radius\n\": 1, \"pairs\": []}",
+        r#"{"radius": 1}"#,
+        r#"{"pairs": [],}"#,
+        r#"{"pairs": []} 1"#,
+        r#"[]"#,
+        "",
+    ] {
+        assert!(parse_fast_schema(input).is_none(), "{input}");
+    }
+}
+
+#[test]
+fn test_retain_within() {
+    let mut pairs = Pairs::default();
+    pairs.push(2.35, 48.85, 13.4, 52.5);
+    pairs.push(2.35, 48.85, -74.0, 40.7);
+    pairs.push(-3.7, 40.4, 12.5, 41.9);
+    let bbox = BoundingBox::from_spec("35,-10,60,30").unwrap();
+    assert_eq!(1, pairs.retain_within(&bbox));
+    assert_eq!(vec![2.35, -3.7], pairs.x0);
+    assert_eq!(vec![52.5, 41.9], pairs.y1);
+    assert_eq!(2, pairs.len());
+}
+
+#[test]
+fn test_retain_within_in_angle_unit() {
+    let mut pairs = Pairs::default();
+    for [x0, y0, x1, y1] in [
+        [2.35_f64, 48.85, 13.4, 52.5],
+        [2.35, 48.85, -74.0, 40.7],
+        [-3.7, 40.4, 12.5, 41.9],
+    ] {
+        pairs.push(
+            x0.to_radians(),
+            y0.to_radians(),
+            x1.to_radians(),
+            y1.to_radians(),
+        );
+    }
+    // The box stays in degrees.
+    let bbox = BoundingBox::from_spec("35,-10,60,30").unwrap();
+    assert_eq!(1, pairs.retain_within_in(&bbox, AngleUnit::Radians));
+    assert_eq!(vec![2.35_f64.to_radians(), -3.7_f64.to_radians()], pairs.x0);
+    assert_eq!(2, pairs.len());
+}
+
+#[test]
+fn test_canonicalize() {
+    let mut pairs = Pairs::default();
+    pairs.push(190.0, 95.0, -180.0, -90.0);
+    pairs.push(-540.0, -91.0, 180.0, f64::NAN);
+    assert!(matches!(
+        pairs.check_coordinates(),
+        Err(PairsError::CoordinateOutOfRange(0, "x0", 190.0))
+    ));
+
+    let counts = pairs.canonicalize();
+    let expected = Canonicalized {
+        wrapped: 2,
+        clamped: 2,
+        non_finite: 1,
+    };
+    assert_eq!(expected, counts);
+    assert_eq!(
+        Some(Pair::new(
+            Point::new(-170.0, 90.0),
+            Point::new(-180.0, -90.0)
+        )),
+        pairs.get(0)
+    );
+    assert_eq!(Point::new(-180.0, -90.0), pairs.get(1).unwrap().p0);
+    assert!(matches!(
+        pairs.check_coordinates(),
+        Err(PairsError::CoordinateOutOfRange(1, "y1", value)) if value.is_nan()
+    ));
+    let again = Canonicalized {
+        non_finite: 1,
+        ..Canonicalized::default()
+    };
+    assert_eq!(again, pairs.canonicalize());
+
+    let mut pairs = Pairs::default();
+    pairs.push(180.0, 90.0, -180.0, -90.0);
+    assert!(pairs.check_coordinates().is_ok());
+    assert_eq!(Canonicalized::default(), pairs.canonicalize());
+
+    let mut pairs = Pairs::default();
+    pairs.push(4.0, 2.0, -3.0, -1.0);
+    assert!(pairs.check_coordinates_in(AngleUnit::Radians).is_err());
+    let counts = pairs.canonicalize_in(AngleUnit::Radians);
+    assert_eq!((1, 1), (counts.wrapped, counts.clamped));
+    let pair = pairs.get(0).unwrap();
+    assert!((pair.p0.lon - (4.0 - 2.0 * std::f64::consts::PI)).abs() < 1e-12);
+    assert_eq!(std::f64::consts::FRAC_PI_2, pair.p0.lat);
+}
+
+#[test]
+fn test_routes_from_values() {
+    let document = document(r#"{"routes": ["_p~iF~ps|U_ulLnnqC_mqNvxq`@", "", 5, "_p~"]}"#);
+    let Some(Value::Array(values)) = document.get("routes") else {
+        panic!("Expected routes");
+    };
+    let routes = routes_from_values(&values[..2], 5).unwrap();
+    assert_eq!(vec![3, 0], routes.iter().map(Vec::len).collect::<Vec<_>>());
+    let mut pairs = Pairs::default();
+    pairs.push_route(&routes[0]);
+    pairs.push_route(&routes[1]);
+    assert_eq!(2, pairs.len());
+    assert_eq!(
+        (routes[0][1].lon, routes[0][1].lat),
+        (pairs.x0[1], pairs.y0[1])
+    );
+
+    let path = |index| {
+        KeyPath(vec![
+            PathSegment::Key("routes".into()),
+            PathSegment::Index(index),
+        ])
+    };
+    assert_eq!(
+        Err(PairsError::InvalidRoute(path(2))),
+        routes_from_values(values, 5)
+    );
+    assert_eq!(
+        Err(PairsError::InvalidPolyline(
+            path(0),
+            PolylineError::Truncated
+        )),
+        routes_from_values(&values[3..], 5)
+    );
+}
+
+#[test]
+fn test_reader_read_each() {
+    let input = r#"{"pairs": [
+        {"x0": 1, "y0": 2, "x1": 3, "y1": 4},
+        {"x0": 5, "y0": 6, "x1": 7, "y1": 8}
+    ], "radius": 1}"#;
+    let mut longitudes = Vec::new();
+    let (members, count) = PairsReader::new()
+        .read_each(input.as_bytes(), |pair| longitudes.push(pair.p0.lon))
+        .unwrap();
+    assert_eq!(2, count);
+    assert_eq!(vec![1.0, 5.0], longitudes);
+    assert_eq!(Some(&Value::Number(Number::U64(1))), members.get("radius"));
+
+    let invalid = r#"{"pairs": [{"x0": 1, "y0": 2, "x1": 3, "y1": 4}, 5]}"#;
+    let error = PairsReader::new()
+        .read_each(invalid.as_bytes(), |_| {})
+        .unwrap_err();
+    let StreamError::Pairs(PairsError::InvalidPair(path)) = error else {
+        panic!("Expected an invalid pair, got {error:?}");
+    };
+    assert_eq!(r#""pairs"[1]"#, path.to_string());
+}
+
+#[test]
+fn test_sample_size_from_spec() {
+    assert_eq!(
+        Some(SampleSize::Fraction(0.01)),
+        SampleSize::from_spec("1%")
+    );
+    assert_eq!(
+        Some(SampleSize::Fraction(1.0)),
+        SampleSize::from_spec("100%")
+    );
+    assert_eq!(
+        Some(SampleSize::Count(100_000)),
+        SampleSize::from_spec("100000")
+    );
+    for invalid in ["0%", "101%", "0", "-5", "%", "1.5", "ten"] {
+        assert_eq!(None, SampleSize::from_spec(invalid), "{invalid}");
+    }
+}
+
+fn numbered_pairs(n: usize) -> impl Iterator<Item = Pair> {
+    (0..n).map(|i| Pair::new(Point::new(i as f64, 0.0), Point::new(0.0, 0.0)))
+}
+
+#[test]
+fn test_sampler_count() {
+    let mut sampler = Sampler::new(SampleSize::Count(10), Box::new(Xoshiro256PlusPlus::new(1)));
+    numbered_pairs(1000).for_each(|pair| sampler.offer(pair));
+    assert_eq!(1000, sampler.seen());
+    let sample = sampler.into_pairs();
+    assert_eq!(10, sample.len());
+    let mut indices = sample.x0.clone();
+    indices.sort_by(f64::total_cmp);
+    indices.dedup();
+    assert_eq!(10, indices.len());
+    assert!(sample.x0.iter().any(|&x| x >= 10.0), "{:?}", sample.x0);
+
+    let mut few = Sampler::new(SampleSize::Count(10), Box::new(Xoshiro256PlusPlus::new(1)));
+    numbered_pairs(3).for_each(|pair| few.offer(pair));
+    assert_eq!(vec![0.0, 1.0, 2.0], few.into_pairs().x0);
+}
+
+#[test]
+fn test_sampler_fraction_is_seeded() {
+    let sample = |seed| {
+        let rng = Box::new(Xoshiro256PlusPlus::new(seed));
+        let mut sampler = Sampler::new(SampleSize::Fraction(0.1), rng);
+        numbered_pairs(10_000).for_each(|pair| sampler.offer(pair));
+        sampler.into_pairs()
+    };
+    let first = sample(7);
+    assert!((900..1100).contains(&first.len()), "{}", first.len());
+    assert_eq!(first, sample(7));
+    assert_ne!(first, sample(8));
+}
+
+#[test]
+fn test_to_huge_pages_copies() {
+    let pairs = Pairs::from_values(&[Value::object()
+        .insert("x0", 1.0)
+        .insert("y0", 2.0)
+        .insert("x1", 3.0)
+        .insert("y1", 4.0)])
+    .unwrap();
+    let (copy, _) = pairs.to_huge_pages();
+    assert_eq!(pairs, copy);
+    assert_eq!(Pairs::default(), Pairs::default().to_huge_pages().0);
+}
@@ -0,0 +1,143 @@
+use super::{KeyPath, Number, PathSegment, Value};
+use std::fmt;
+
+/// A difference between two values, found by `Value::diff`.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Change {
+    /// A member or element only in the other value.
+    Added { path: KeyPath, value: Value },
+    /// A member or element only in this value.
+    Removed { path: KeyPath, value: Value },
+    /// A value that is different in the other value.
+    Modified {
+        path: KeyPath,
+        from: Value,
+        to: Value,
+    },
+}
+
+impl Change {
+    /// Location of the value that differs, from the root of the documents.
+    pub fn path(&self) -> &KeyPath {
+        match self {
+            Change::Added { path, .. }
+            | Change::Removed { path, .. }
+            | Change::Modified { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    /// Formats the change like `~ "radius": 6371 -> 6372.8`, with `+` for
+    /// additions and `-` for removals.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = |path: &KeyPath| {
+            if path.0.is_empty() {
+                "the root".to_string()
+            } else {
+                path.to_string()
+            }
+        };
+        match self {
+            Change::Added { path: at, value } => write!(f, "+ {}: {}", path(at), value.to_json()),
+            Change::Removed { path: at, value } => {
+                write!(f, "- {}: {}", path(at), value.to_json())
+            }
+            Change::Modified { path: at, from, to } => {
+                write!(f, "~ {}: {} -> {}", path(at), from.to_json(), to.to_json())
+            }
+        }
+    }
+}
+
+impl Value {
+    /// Lists the differences that turn `self` into `other`: the members and
+    /// elements one has and the other lacks, and the values that differ.
+    /// Arrays are compared index by index, objects key by key. Numbers are
+    /// equal as `Number`s are, so an integer never equals a float.
+    pub fn diff(&self, other: &Value) -> Vec<Change> {
+        self.diff_within(other, 0.0)
+    }
+
+    /// Lists the differences like `diff`, with two numbers equal when one
+    /// of them is a float and they are within `tolerance` of each other,
+    /// relative to the larger in magnitude.
+    pub fn diff_within(&self, other: &Value, tolerance: f64) -> Vec<Change> {
+        let mut changes = Vec::new();
+        diff(self, other, tolerance, &mut Vec::new(), &mut changes);
+        changes
+    }
+}
+
+fn diff(
+    from: &Value,
+    to: &Value,
+    tolerance: f64,
+    path: &mut Vec<PathSegment>,
+    changes: &mut Vec<Change>,
+) {
+    let at = |path: &[PathSegment]| KeyPath(path.to_vec());
+    match (from, to) {
+        (Value::Object(from), Value::Object(to)) => {
+            for (key, value) in from.iter() {
+                path.push(PathSegment::Key(key.clone()));
+                match to.get(key) {
+                    Some(other) => diff(value, other, tolerance, path, changes),
+                    None => changes.push(Change::Removed {
+                        path: at(path),
+                        value: value.clone(),
+                    }),
+                }
+                path.pop();
+            }
+            for (key, value) in to.iter().filter(|(key, _)| from.get(key).is_none()) {
+                path.push(PathSegment::Key(key.clone()));
+                changes.push(Change::Added {
+                    path: at(path),
+                    value: value.clone(),
+                });
+                path.pop();
+            }
+        }
+        (Value::Array(from), Value::Array(to)) => {
+            for (index, value) in from.iter().enumerate() {
+                path.push(PathSegment::Index(index));
+                match to.get(index) {
+                    Some(other) => diff(value, other, tolerance, path, changes),
+                    None => changes.push(Change::Removed {
+                        path: at(path),
+                        value: value.clone(),
+                    }),
+                }
+                path.pop();
+            }
+            for (index, value) in to.iter().enumerate().skip(from.len()) {
+                path.push(PathSegment::Index(index));
+                changes.push(Change::Added {
+                    path: at(path),
+                    value: value.clone(),
+                });
+                path.pop();
+            }
+        }
+        (Value::Number(a), Value::Number(b)) if numbers_match(a, b, tolerance) => {}
+        (from, to) if from == to => {}
+        (from, to) => changes.push(Change::Modified {
+            path: at(path),
+            from: from.clone(),
+            to: to.clone(),
+        }),
+    }
+}
+
+fn numbers_match(a: &Number, b: &Number, tolerance: f64) -> bool {
+    if a == b {
+        return true;
+    }
+    if tolerance == 0.0 || !(a.resolve().is_f64() || b.resolve().is_f64()) {
+        return false;
+    }
+    let (a, b) = (a.as_f64(), b.as_f64());
+    (a - b).abs() <= tolerance * a.abs().max(b.abs())
+}
@@ -0,0 +1,146 @@
+use std::fmt;
+
+/// The byte order mark, which some tools write at the start of a file.
+pub const BOM: char = '\u{feff}';
+
+/// Unicode encoding of a JSON text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Utf32Le,
+    Utf32Be,
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::Utf32Le => "UTF-32LE",
+            Encoding::Utf32Be => "UTF-32BE",
+        };
+        f.write_str(name)
+    }
+}
+
+impl Encoding {
+    /// Bytes per code unit.
+    fn unit_len(self) -> usize {
+        match self {
+            Encoding::Utf8 => 1,
+            Encoding::Utf16Le | Encoding::Utf16Be => 2,
+            Encoding::Utf32Le | Encoding::Utf32Be => 4,
+        }
+    }
+
+    fn bom(self) -> &'static [u8] {
+        match self {
+            Encoding::Utf8 => b"\xef\xbb\xbf",
+            Encoding::Utf16Le => b"\xff\xfe",
+            Encoding::Utf16Be => b"\xfe\xff",
+            Encoding::Utf32Le => b"\xff\xfe\0\0",
+            Encoding::Utf32Be => b"\0\0\xfe\xff",
+        }
+    }
+}
+
+/// Input that is not valid in the encoding it was detected as.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodeError {
+    pub encoding: Encoding,
+    /// Byte offset of the first invalid code unit.
+    pub offset: usize,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid {} at byte {}", self.encoding, self.offset)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Detects the encoding of a JSON text from its byte order mark or, without
+/// one, from where the first code units have zero bytes, as RFC 4627 did.
+/// Needs the first four bytes to tell them all apart.
+pub fn detect_encoding(bytes: &[u8]) -> Encoding {
+    // UTF-32LE before UTF-16LE, whose mark starts it.
+    let marked = [
+        Encoding::Utf8,
+        Encoding::Utf32Le,
+        Encoding::Utf32Be,
+        Encoding::Utf16Le,
+        Encoding::Utf16Be,
+    ];
+    if let Some(&encoding) = marked.iter().find(|e| bytes.starts_with(e.bom())) {
+        return encoding;
+    }
+    // The text starts with an ASCII character, so its other bytes are zero.
+    match bytes {
+        [0, 0, 0, b, ..] if *b != 0 => Encoding::Utf32Be,
+        [a, 0, 0, 0, ..] if *a != 0 => Encoding::Utf32Le,
+        [0, b, ..] if *b != 0 => Encoding::Utf16Be,
+        [a, 0, ..] if *a != 0 => Encoding::Utf16Le,
+        _ => Encoding::Utf8,
+    }
+}
+
+/// Decodes a JSON text in any of the encodings of `detect_encoding` to a
+/// string, without its byte order mark.
+pub fn decode(bytes: Vec<u8>) -> Result<String, DecodeError> {
+    let encoding = detect_encoding(&bytes);
+    let start = if bytes.starts_with(encoding.bom()) {
+        encoding.bom().len()
+    } else {
+        0
+    };
+    let invalid = |offset| DecodeError { encoding, offset };
+    let partial = (bytes.len() - start) % encoding.unit_len();
+    if partial != 0 {
+        return Err(invalid(bytes.len() - partial));
+    }
+
+    let units = bytes[start..].chunks_exact(encoding.unit_len());
+    match encoding {
+        Encoding::Utf8 => {
+            let mut bytes = bytes;
+            bytes.drain(..start);
+            String::from_utf8(bytes)
+                .map_err(|error| invalid(start + error.utf8_error().valid_up_to()))
+        }
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            let code_units = units.map(|unit| {
+                let unit = [unit[0], unit[1]];
+                if encoding == Encoding::Utf16Le {
+                    u16::from_le_bytes(unit)
+                } else {
+                    u16::from_be_bytes(unit)
+                }
+            });
+            let mut string = String::with_capacity(bytes.len() / 2);
+            let mut offset = start;
+            for c in char::decode_utf16(code_units) {
+                let c = c.map_err(|_| invalid(offset))?;
+                string.push(c);
+                offset += 2 * c.len_utf16();
+            }
+            Ok(string)
+        }
+        Encoding::Utf32Le | Encoding::Utf32Be => units
+            .enumerate()
+            .map(|(index, unit)| {
+                let unit = [unit[0], unit[1], unit[2], unit[3]];
+                let code = if encoding == Encoding::Utf32Le {
+                    u32::from_le_bytes(unit)
+                } else {
+                    u32::from_be_bytes(unit)
+                };
+                char::from_u32(code).ok_or(invalid(start + 4 * index))
+            })
+            .collect(),
+    }
+}
@@ -0,0 +1,408 @@
+use super::parser::DEFAULT_MAX_DEPTH;
+use super::{
+    BOM, Encoding, Limits, LoneSurrogatePolicy, Number, NumberRangePolicy, ParsingError, Token,
+    TokenizeError, Tokenizer, Value, ValueError, detect_encoding,
+};
+
+/// Event of a push-based parse, in document order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// Start of an object `{`.
+    StartObject,
+    /// End of an object `}`.
+    EndObject,
+    /// Start of an array `[`.
+    StartArray,
+    /// End of an array `]`.
+    EndArray,
+    /// Key of an object member. The member's value follows.
+    Key(String),
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(String),
+}
+
+/// What the parser accepts next.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Expect {
+    /// A value, at the top level or after a colon.
+    Value,
+    /// A value after a comma in an array.
+    Element,
+    /// A value or `]` right after `[`.
+    ElementOrEnd,
+    /// A key after a comma in an object.
+    Key,
+    /// A key or `}` right after `{`.
+    KeyOrEnd,
+    Colon,
+    CommaOrEnd,
+    /// The top-level value is complete.
+    Done,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Container {
+    Array,
+    Object,
+}
+
+/// Push-based parser, fed the input in chunks as they arrive, e.g. from a
+/// pipe or a socket.
+///
+/// Only the bytes of an incomplete token are kept between calls to `feed`.
+/// A byte order mark at the start is skipped, and input in UTF-16 or
+/// UTF-32 is rejected once its first four bytes are in.
+/// Duplicate keys are reported as they appear; it is up to the consumer of
+/// the events to resolve them.
+///
+/// ```
+/// use haversine::parse::{Event, FeedParser, Number};
+///
+/// let mut parser = FeedParser::new();
+/// let mut events = parser.feed(b"[1.").unwrap();
+/// events.extend(parser.feed(b"5, tr").unwrap());
+/// events.extend(parser.feed(b"ue]").unwrap());
+/// parser.finish().unwrap();
+///
+/// assert_eq!(
+///     events,
+///     [
+///         Event::StartArray,
+///         Event::Number(Number::F64(1.5)),
+///         Event::Bool(true),
+///         Event::EndArray,
+///     ],
+/// );
+/// ```
+#[derive(Debug)]
+pub struct FeedParser {
+    buffer: Vec<u8>,
+    /// Byte offset in the input of the start of `buffer`.
+    position: usize,
+    /// Whether the encoding of the input has been checked.
+    checked_encoding: bool,
+    stack: Vec<Container>,
+    /// Elements read of each open array, innermost last.
+    elements: Vec<usize>,
+    /// Values read so far, against `Limits::max_values`.
+    values: usize,
+    expect: Expect,
+    lone_surrogates: LoneSurrogatePolicy,
+    out_of_range: NumberRangePolicy,
+    allow_control_chars: bool,
+    raw_numbers: bool,
+    max_depth: usize,
+    limits: Limits,
+}
+
+impl Default for FeedParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FeedParser {
+    pub fn new() -> Self {
+        Self {
+            buffer: Vec::new(),
+            position: 0,
+            checked_encoding: false,
+            stack: Vec::new(),
+            elements: Vec::new(),
+            values: 0,
+            expect: Expect::Value,
+            lone_surrogates: LoneSurrogatePolicy::default(),
+            out_of_range: NumberRangePolicy::default(),
+            allow_control_chars: false,
+            raw_numbers: false,
+            max_depth: DEFAULT_MAX_DEPTH,
+            limits: Limits::default(),
+        }
+    }
+
+    /// Sets how escaped lone surrogates in strings are handled.
+    pub fn lone_surrogates(mut self, policy: LoneSurrogatePolicy) -> Self {
+        self.lone_surrogates = policy;
+        self
+    }
+
+    /// Sets how numbers out of the range of `f64` are handled.
+    pub fn out_of_range(mut self, policy: NumberRangePolicy) -> Self {
+        self.out_of_range = policy;
+        self
+    }
+
+    /// Accepts control characters written unescaped in strings.
+    pub fn allow_control_chars(mut self, allow: bool) -> Self {
+        self.allow_control_chars = allow;
+        self
+    }
+
+    /// Keeps numbers as the literals they were written as.
+    pub fn raw_numbers(mut self, raw: bool) -> Self {
+        self.raw_numbers = raw;
+        self
+    }
+
+    /// Sets how deep arrays and objects may be nested.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Sets the limits of the document. Its size is checked as it is fed.
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Parses the next chunk of input, returning the events of the tokens
+    /// it completes.
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<Vec<Event>, ParsingError> {
+        self.buffer.extend_from_slice(chunk);
+        self.drain(false, None)
+    }
+
+    /// Like `feed`, pairing each event with the byte offset in the whole
+    /// input of the token it comes from.
+    pub fn feed_spanned(&mut self, chunk: &[u8]) -> Result<Vec<(usize, Event)>, ParsingError> {
+        self.buffer.extend_from_slice(chunk);
+        let mut offsets = Vec::new();
+        let events = self.drain(false, Some(&mut offsets))?;
+        Ok(offsets.into_iter().zip(events).collect())
+    }
+
+    /// Ends the input, returning the events of the last tokens. Fails if the
+    /// document is incomplete.
+    pub fn finish(mut self) -> Result<Vec<Event>, ParsingError> {
+        let events = self.drain(true, None)?;
+        self.check_complete()?;
+        Ok(events)
+    }
+
+    /// Like `finish`, pairing each event with its offset as `feed_spanned`.
+    pub fn finish_spanned(mut self) -> Result<Vec<(usize, Event)>, ParsingError> {
+        let mut offsets = Vec::new();
+        let events = self.drain(true, Some(&mut offsets))?;
+        self.check_complete()?;
+        Ok(offsets.into_iter().zip(events).collect())
+    }
+
+    fn check_complete(&self) -> Result<(), ParsingError> {
+        match self.stack.last() {
+            Some(Container::Array) => Err(ParsingError::ReachedEOF(Token::OpenBracket)),
+            Some(Container::Object) => Err(ParsingError::ReachedEOF(Token::OpenBrace)),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks if the top-level value is complete.
+    pub fn is_done(&self) -> bool {
+        self.expect == Expect::Done
+    }
+
+    /// Turns the complete tokens in the buffer into events. Unless this is
+    /// the end of the input, a token that reaches the end of the buffer may
+    /// continue in the next chunk and is left in the buffer. The offset of
+    /// each event is pushed to `offsets`, if given.
+    fn drain(
+        &mut self,
+        at_end: bool,
+        offsets: Option<&mut Vec<usize>>,
+    ) -> Result<Vec<Event>, ParsingError> {
+        self.limits.check_size(self.position + self.buffer.len())?;
+        if !self.checked_encoding {
+            if self.buffer.len() < 4 && !at_end {
+                return Ok(Vec::new());
+            }
+            match detect_encoding(&self.buffer) {
+                Encoding::Utf8 => {
+                    if self
+                        .buffer
+                        .starts_with(BOM.encode_utf8(&mut [0; 3]).as_bytes())
+                    {
+                        self.buffer.drain(..BOM.len_utf8());
+                        self.position += BOM.len_utf8();
+                    }
+                }
+                encoding => return Err(TokenizeError::UnsupportedEncoding(encoding).into()),
+            }
+            self.checked_encoding = true;
+        }
+        let buffer = std::mem::take(&mut self.buffer);
+        let drained = self.drain_buffer(&buffer, at_end, offsets);
+        self.buffer = buffer;
+        let (events, consumed) = drained?;
+        self.buffer.drain(..consumed);
+        self.position += consumed;
+        Ok(events)
+    }
+
+    /// Returns the events of the complete tokens and the number of bytes
+    /// they take up.
+    fn drain_buffer(
+        &mut self,
+        buffer: &[u8],
+        at_end: bool,
+        mut offsets: Option<&mut Vec<usize>>,
+    ) -> Result<(Vec<Event>, usize), ParsingError> {
+        // Tokens before invalid UTF-8 are still parsed, so that the first
+        // error is the same however the input is split into chunks. A
+        // character cut at the end of the chunk may yet be completed.
+        let (text, invalid_utf8) = match std::str::from_utf8(buffer) {
+            Ok(text) => (text, false),
+            Err(error) => (
+                std::str::from_utf8(&buffer[..error.valid_up_to()]).expect("Valid prefix"),
+                error.error_len().is_some() || at_end,
+            ),
+        };
+        let at_end = at_end && !invalid_utf8;
+
+        let mut events = Vec::new();
+        let mut consumed = 0;
+        loop {
+            let mut tokenizer = Tokenizer::new(&text[consumed..])
+                .lone_surrogates(self.lone_surrogates)
+                .out_of_range(self.out_of_range)
+                .allow_control_chars(self.allow_control_chars)
+                .raw_numbers(self.raw_numbers);
+            let (token, span) = tokenizer.next_with_span();
+            let rest = tokenizer.rest();
+            let incomplete = match token {
+                Ok(Token::Eof) => break,
+                Ok(Token::Number(_)) | Err(_) => rest.is_empty() || tokenizer.bumped_past_end(),
+                Ok(_) => false,
+            };
+            if incomplete && !at_end {
+                break;
+            }
+            let start = self.position + consumed + span.start;
+            consumed = text.len() - rest.len();
+            self.accept(token?, &mut events)?;
+            if let Some(offsets) = offsets.as_deref_mut() {
+                offsets.resize(events.len(), start);
+            }
+        }
+
+        if invalid_utf8 {
+            return Err(TokenizeError::InvalidUtf8.into());
+        }
+
+        // Whitespace at the end needs no keeping.
+        let consumed = text.len()
+            - text[consumed..]
+                .trim_start_matches([' ', '\t', '\n', '\r'])
+                .len();
+        Ok((events, consumed))
+    }
+
+    /// Advances the state with the next token.
+    fn accept(&mut self, token: Token, events: &mut Vec<Event>) -> Result<(), ParsingError> {
+        match (self.expect, token) {
+            (Expect::Done, _) => Err(ParsingError::ExtraData),
+            (Expect::ElementOrEnd, Token::CloseBracket) => self.close(Event::EndArray, events),
+            (Expect::KeyOrEnd, Token::CloseBrace) => self.close(Event::EndObject, events),
+            (Expect::Element, Token::CloseBracket) | (Expect::Key, Token::CloseBrace) => {
+                Err(ParsingError::TrailingComma)
+            }
+            (Expect::Key | Expect::KeyOrEnd, Token::String(key)) => {
+                self.limits.count_value(&mut self.values)?;
+                self.limits.check_string(&key)?;
+                events.push(Event::Key(key));
+                self.expect = Expect::Colon;
+                Ok(())
+            }
+            (Expect::Key | Expect::KeyOrEnd, token) => match Value::try_from(token) {
+                Ok(value) => Err(ParsingError::InvalidKey(value)),
+                Err(ValueError::TryFromToken(token)) => Err(ParsingError::StartingToken(token)),
+                Err(_) => unreachable!("Only tokens fail to convert"),
+            },
+            (Expect::Colon, Token::Colon) => {
+                self.expect = Expect::Value;
+                Ok(())
+            }
+            (Expect::Colon, _) => Err(ParsingError::MissingColon),
+            (Expect::CommaOrEnd, Token::Comma) => {
+                self.expect = match self.stack.last() {
+                    Some(Container::Array) => Expect::Element,
+                    _ => Expect::Key,
+                };
+                Ok(())
+            }
+            (Expect::CommaOrEnd, Token::CloseBracket)
+                if self.stack.last() == Some(&Container::Array) =>
+            {
+                self.close(Event::EndArray, events)
+            }
+            (Expect::CommaOrEnd, Token::CloseBrace)
+                if self.stack.last() == Some(&Container::Object) =>
+            {
+                self.close(Event::EndObject, events)
+            }
+            (Expect::CommaOrEnd, token) => Err(ParsingError::TokenAfterValue(token)),
+            (Expect::Value | Expect::Element | Expect::ElementOrEnd, token) => {
+                self.value(token, events)
+            }
+        }
+    }
+
+    /// Accepts the token that starts a value.
+    fn value(&mut self, token: Token, events: &mut Vec<Event>) -> Result<(), ParsingError> {
+        if matches!(token, Token::OpenBracket | Token::OpenBrace)
+            && self.stack.len() == self.max_depth
+        {
+            return Err(ParsingError::TooDeep);
+        }
+        self.limits.count_value(&mut self.values)?;
+        if let Some(count) = self.elements.last_mut()
+            && self.stack.last() == Some(&Container::Array)
+        {
+            self.limits.check_element(*count)?;
+            *count += 1;
+        }
+        let event = match token {
+            Token::OpenBracket => {
+                self.stack.push(Container::Array);
+                self.elements.push(0);
+                self.expect = Expect::ElementOrEnd;
+                events.push(Event::StartArray);
+                return Ok(());
+            }
+            Token::OpenBrace => {
+                self.stack.push(Container::Object);
+                self.expect = Expect::KeyOrEnd;
+                events.push(Event::StartObject);
+                return Ok(());
+            }
+            Token::Null => Event::Null,
+            Token::Bool(b) => Event::Bool(b),
+            Token::Number(n) => Event::Number(n),
+            Token::String(s) => {
+                self.limits.check_string(&s)?;
+                Event::String(s)
+            }
+            token => return Err(ParsingError::StartingToken(token)),
+        };
+        events.push(event);
+        self.end_value();
+        Ok(())
+    }
+
+    fn close(&mut self, event: Event, events: &mut Vec<Event>) -> Result<(), ParsingError> {
+        if self.stack.pop() == Some(Container::Array) {
+            self.elements.pop();
+        }
+        events.push(event);
+        self.end_value();
+        Ok(())
+    }
+
+    fn end_value(&mut self) {
+        self.expect = match self.stack.is_empty() {
+            true => Expect::Done,
+            false => Expect::CommaOrEnd,
+        };
+    }
+}
@@ -0,0 +1,74 @@
+use super::ParsingError;
+
+/// Bounds on what a parser accepts, so documents from untrusted sources
+/// cannot make it use unbounded memory or time. Nothing is limited by
+/// default.
+///
+/// ```
+/// use haversine::parse::{Limits, Parser, ParsingError};
+///
+/// let limits = Limits {
+///     max_array_len: 2,
+///     ..Limits::default()
+/// };
+/// assert_eq!(
+///     Err(ParsingError::ArrayTooLong(2)),
+///     Parser::new("[1, 2, 3]").limits(limits).parse()
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Largest document, in bytes.
+    pub max_size: usize,
+    /// Longest string or object key, in bytes once unescaped.
+    pub max_string_len: usize,
+    /// Most elements of an array.
+    pub max_array_len: usize,
+    /// Most values in the document, counting arrays, objects, scalars and
+    /// object keys.
+    pub max_values: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_size: usize::MAX,
+            max_string_len: usize::MAX,
+            max_array_len: usize::MAX,
+            max_values: usize::MAX,
+        }
+    }
+}
+
+impl Limits {
+    pub(super) fn check_size(&self, size: usize) -> Result<(), ParsingError> {
+        match size > self.max_size {
+            true => Err(ParsingError::DocumentTooLarge(self.max_size)),
+            false => Ok(()),
+        }
+    }
+
+    pub(super) fn check_string(&self, string: &str) -> Result<(), ParsingError> {
+        match string.len() > self.max_string_len {
+            true => Err(ParsingError::StringTooLong(self.max_string_len)),
+            false => Ok(()),
+        }
+    }
+
+    /// Checks that an array may have an element at `index`.
+    pub(super) fn check_element(&self, index: usize) -> Result<(), ParsingError> {
+        match index >= self.max_array_len {
+            true => Err(ParsingError::ArrayTooLong(self.max_array_len)),
+            false => Ok(()),
+        }
+    }
+
+    /// Counts a value in `values`, the number read so far.
+    pub(super) fn count_value(&self, values: &mut usize) -> Result<(), ParsingError> {
+        if *values == self.max_values {
+            return Err(ParsingError::TooManyValues(self.max_values));
+        }
+        *values += 1;
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+use super::Value;
+use std::collections::HashMap;
+
+/// Up to this many entries, keys are looked up by scanning the entries,
+/// which is faster than hashing for the small objects of a pairs file.
+const LINEAR_SEARCH_LIMIT: usize = 8;
+
+/// Members of a JSON object, iterated in insertion order so serializing a
+/// parsed document reproduces the order of its keys.
+#[derive(Debug, Clone, Default)]
+pub struct Map {
+    entries: Vec<(String, Value)>,
+    /// Position of each key in `entries`, kept only past the linear search
+    /// limit.
+    indices: HashMap<String, usize>,
+}
+
+impl Map {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn position(&self, key: &str) -> Option<usize> {
+        if self.entries.len() <= LINEAR_SEARCH_LIMIT {
+            self.entries.iter().position(|(k, _)| k == key)
+        } else {
+            self.indices.get(key).copied()
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.position(key).map(|i| &self.entries[i].1)
+    }
+
+    /// The member at position `index` in insertion order.
+    pub fn get_index(&self, index: usize) -> Option<(&String, &Value)> {
+        self.entries.get(index).map(|(k, v)| (k, v))
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        self.position(key).map(|i| &mut self.entries[i].1)
+    }
+
+    /// Inserts `value` under `key`. An existing key keeps its position and
+    /// its previous value is returned.
+    pub fn insert(&mut self, key: String, value: Value) -> Option<Value> {
+        if let Some(existing) = self.get_mut(&key) {
+            return Some(std::mem::replace(existing, value));
+        }
+
+        if self.entries.len() == LINEAR_SEARCH_LIMIT {
+            for (i, (k, _)) in self.entries.iter().enumerate() {
+                self.indices.insert(k.clone(), i);
+            }
+        }
+        if self.entries.len() >= LINEAR_SEARCH_LIMIT {
+            self.indices.insert(key.clone(), self.entries.len());
+        }
+        self.entries.push((key, value));
+        None
+    }
+
+    /// Reorders the members by key.
+    pub fn sort_keys(&mut self) {
+        self.entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        if self.entries.len() > LINEAR_SEARCH_LIMIT {
+            for (i, (k, _)) in self.entries.iter().enumerate() {
+                self.indices.insert(k.clone(), i);
+            }
+        }
+    }
+
+    /// Iterates over the members in insertion order.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Value)> {
+        self.entries.iter().map(|(k, v)| (k, v))
+    }
+
+    /// Iterates over the values in insertion order.
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut Value> {
+        self.entries.iter_mut().map(|(_, v)| v)
+    }
+}
+
+/// Objects are equal if they have the same members, in any order.
+impl PartialEq for Map {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .all(|(key, value)| other.get(key) == Some(value))
+    }
+}
+
+impl FromIterator<(String, Value)> for Map {
+    fn from_iter<I: IntoIterator<Item = (String, Value)>>(iter: I) -> Self {
+        let mut map = Map::new();
+        for (key, value) in iter {
+            map.insert(key, value);
+        }
+        map
+    }
+}
+
+impl<'a> IntoIterator for &'a Map {
+    type Item = (&'a String, &'a Value);
+    type IntoIter = std::iter::Map<
+        std::slice::Iter<'a, (String, Value)>,
+        fn(&'a (String, Value)) -> (&'a String, &'a Value),
+    >;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter().map(|(k, v)| (k, v))
+    }
+}
@@ -1,11 +1,77 @@
-pub(crate) mod value;
-pub(crate) use value::Value;
+mod value;
+pub use value::{Value, ValueError};
 
-pub(crate) mod parser;
-pub(crate) use parser::Parser;
+mod map;
+pub use map::Map;
 
-pub(crate) mod tokenize;
-pub(crate) use tokenize::{Token, TokenizeError, Tokenizer};
+mod number;
+pub use number::Number;
+
+mod limits;
+pub use limits::Limits;
+
+mod parser;
+pub use parser::{DuplicateKeyPolicy, Parser, ParsingError};
+
+mod serialize;
+
+mod tree;
+pub use tree::{DEFAULT_TREE_ITEMS, Tree};
+
+mod feed;
+pub use feed::{Event, FeedParser};
+
+mod path;
+pub use path::{KeyPath, PathSegment};
+
+mod diff;
+pub use diff::Change;
+
+mod pointer;
+pub use pointer::ArrayValues;
+pub(crate) use pointer::unescape;
+
+mod recover;
+pub use recover::{Diagnostic, SNIPPET_CONTEXT};
+
+mod tape;
+pub use tape::{Cursor, Elements, Members, Tape};
+
+mod structural;
+pub use structural::{Simd, structural_indices};
+
+mod spanned;
+pub use spanned::{Member, Span, SpannedKind, SpannedValue};
+
+mod encoding;
+pub use encoding::{BOM, DecodeError, Encoding, decode, detect_encoding};
+
+mod tokenize;
+pub(crate) use tokenize::is_json_number;
+pub use tokenize::{
+    LoneSurrogatePolicy, NumberRangePolicy, Token, TokenizeError, Tokenizer, tokens,
+};
+
+use crate::reader::InputSource;
+
+/// Parses a JSON document with the default options. Returns `None` if the
+/// input holds no value.
+pub fn parse(input: &str) -> Result<Option<Value>, ParsingError> {
+    Parser::new(input).parse()
+}
+
+/// Parses the whole input of `source`, which may be UTF-8, UTF-16 or
+/// UTF-32.
+///
+/// ```
+/// use haversine::parse::{Value, parse_source};
+///
+/// let value = parse_source(&mut b"[1, 2]".as_slice()).unwrap().unwrap();
+/// assert!(matches!(value, Value::Array(items) if items.len() == 2));
+/// ```
+pub fn parse_source(source: &mut dyn InputSource) -> crate::Result<Option<Value>> {
+    Ok(parse(&source.read_text()?)?)
+}
 
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,133 @@
+use std::fmt;
+
+/// A JSON number. Integers are kept apart from floating point numbers, so
+/// `3` and `3.0` stay distinct and 64-bit integers are exact.
+///
+/// Integers parsed from JSON are `U64` when non-negative and `I64` when
+/// negative.
+#[derive(Debug, Clone)]
+pub enum Number {
+    I64(i64),
+    U64(u64),
+    F64(f64),
+    /// The literal as written, for `Parser::raw_numbers`. It is converted
+    /// on every access, and serialized unchanged. The text must follow the
+    /// JSON grammar.
+    Raw(String),
+}
+
+impl Number {
+    /// Returns the number as a float, rounding integers beyond 2^53.
+    pub fn as_f64(&self) -> f64 {
+        match *self {
+            Number::I64(n) => n as f64,
+            Number::U64(n) => n as f64,
+            Number::F64(n) => n,
+            Number::Raw(_) => self.resolve().as_f64(),
+        }
+    }
+
+    /// Returns the number if it is an integer that fits in an `i64`.
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Number::I64(n) => Some(n),
+            Number::U64(n) => i64::try_from(n).ok(),
+            Number::F64(_) => None,
+            Number::Raw(_) => self.resolve().as_i64(),
+        }
+    }
+
+    /// Returns the number if it is an integer that fits in a `u64`.
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            Number::I64(n) => u64::try_from(n).ok(),
+            Number::U64(n) => Some(n),
+            Number::F64(_) => None,
+            Number::Raw(_) => self.resolve().as_u64(),
+        }
+    }
+
+    /// Returns whether the number is a float, which for a raw literal means
+    /// it has a fraction or exponent or does not fit in 64 bits.
+    pub fn is_f64(&self) -> bool {
+        matches!(self.resolve(), Number::F64(_))
+    }
+
+    /// Converts a raw literal to the number it stands for. Text that is not
+    /// a number becomes NaN.
+    pub fn resolve(&self) -> Number {
+        match self {
+            Number::Raw(literal) => Number::from_literal(literal).unwrap_or(Number::F64(f64::NAN)),
+            number => number.clone(),
+        }
+    }
+
+    /// Converts a number literal that follows the JSON grammar. Integers
+    /// too large for 64 bits, and `-0`, become floats.
+    pub(crate) fn from_literal(literal: &str) -> Option<Number> {
+        if !literal.contains(['.', 'e', 'E']) {
+            let integer = match literal.strip_prefix('-') {
+                Some(_) => literal.parse().ok().filter(|&n| n != 0).map(Number::I64),
+                None => literal.parse().ok().map(Number::U64),
+            };
+            if integer.is_some() {
+                return integer;
+            }
+        }
+        literal.parse().ok().map(Number::F64)
+    }
+}
+
+/// Integers are equal by value, whatever their variant. An integer is never
+/// equal to a float. Raw literals compare by the number they stand for.
+impl PartialEq for Number {
+    fn eq(&self, other: &Self) -> bool {
+        match (self.resolve(), other.resolve()) {
+            (Number::F64(a), Number::F64(b)) => a == b,
+            (Number::F64(_), _) | (_, Number::F64(_)) => false,
+            (a, b) => a.as_i64() == b.as_i64() && a.as_u64() == b.as_u64(),
+        }
+    }
+}
+
+impl From<f64> for Number {
+    fn from(n: f64) -> Self {
+        Number::F64(n)
+    }
+}
+
+impl From<i64> for Number {
+    fn from(n: i64) -> Self {
+        match u64::try_from(n) {
+            Ok(n) => Number::U64(n),
+            Err(_) => Number::I64(n),
+        }
+    }
+}
+
+impl From<u64> for Number {
+    fn from(n: u64) -> Self {
+        Number::U64(n)
+    }
+}
+
+/// Formats the number as a JSON literal. Floats always have a fraction or
+/// exponent, so they read back as floats. Raw literals are written as is. Infinities and NaN, which JSON
+/// cannot represent, are formatted as by `f64`.
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Number::I64(n) => write!(f, "{n}"),
+            Number::U64(n) => write!(f, "{n}"),
+            Number::Raw(literal) => f.write_str(literal),
+            &Number::F64(n) => {
+                let literal = n.to_string();
+                if n.is_finite() && !literal.contains(['.', 'e']) {
+                    write!(f, "{literal}.0")
+                } else {
+                    f.write_str(&literal)
+                }
+            }
+        }
+    }
+}
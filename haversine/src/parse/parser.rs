@@ -1,8 +1,12 @@
-use super::{Token, TokenizeError, Tokenizer, Value};
-use std::collections::HashMap;
+use super::{
+    KeyPath, Limits, LoneSurrogatePolicy, Map, Member, NumberRangePolicy, PathSegment, Simd, Span,
+    SpannedKind, SpannedValue, Token, TokenizeError, Tokenizer, Value, ValueError,
+};
+use std::fmt;
 
 #[derive(Debug, Clone, PartialEq)]
-pub(crate) enum ParsingError {
+#[non_exhaustive]
+pub enum ParsingError {
     MissingColon,
     TrailingComma,
     ExtraData,
@@ -12,26 +16,199 @@ pub(crate) enum ParsingError {
     Tokenize(TokenizeError),
     TokenAfterValue(Token),
     DuplicateObjectKey(String),
+    /// Arrays and objects are nested deeper than the parser's limit.
+    TooDeep,
+    /// A number too large or too small in magnitude for an `f64`, with
+    /// `NumberRangePolicy::Error`.
+    NumberOutOfRange(String),
+    /// An error inside the value at the path.
+    Nested(KeyPath, Box<ParsingError>),
+    /// Nothing or no array is at the JSON pointer `iter_array_at` was given.
+    NoArrayAt(String),
+    /// The document is larger than `Limits::max_size`, given.
+    DocumentTooLarge(usize),
+    /// A string or key is longer than `Limits::max_string_len`, given.
+    StringTooLong(usize),
+    /// An array has more elements than `Limits::max_array_len`, given.
+    ArrayTooLong(usize),
+    /// The document has more values than `Limits::max_values`, given.
+    TooManyValues(usize),
+    /// The token of `ArrayValues::cancel` was cancelled before the end of
+    /// the array.
+    Cancelled,
 }
 
+impl ParsingError {
+    /// Records that the error happened inside the child at `segment`.
+    ///
+    /// The path is only built while an error propagates, so parsing valid
+    /// documents pays nothing for it.
+    pub(super) fn inside(self, segment: PathSegment) -> ParsingError {
+        match self {
+            ParsingError::Nested(mut path, error) => {
+                path.prepend(segment);
+                ParsingError::Nested(path, error)
+            }
+            error => ParsingError::Nested(KeyPath(vec![segment]), Box::new(error)),
+        }
+    }
+}
+
+impl fmt::Display for ParsingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParsingError::MissingColon => write!(f, "expected ':' after the object key"),
+            ParsingError::TrailingComma => write!(f, "trailing comma"),
+            ParsingError::ExtraData => write!(f, "data after the end of the document"),
+            ParsingError::InvalidKey(key) => write!(f, "invalid object key {}", key.to_json()),
+            ParsingError::ReachedEOF(open) => write!(f, "unexpected end of input inside {open}"),
+            ParsingError::StartingToken(token) => write!(f, "unexpected {token}"),
+            ParsingError::Tokenize(error) => write!(f, "{error}"),
+            ParsingError::TokenAfterValue(token) => {
+                write!(f, "unexpected {token} after a value")
+            }
+            ParsingError::DuplicateObjectKey(key) => write!(f, "duplicate object key {key:?}"),
+            ParsingError::TooDeep => write!(f, "arrays and objects nested too deep"),
+            ParsingError::NumberOutOfRange(number) => {
+                write!(f, "number {number} is out of the range of f64")
+            }
+            ParsingError::Nested(path, error) => write!(f, "{error} at {path}"),
+            ParsingError::NoArrayAt(pointer) => write!(f, "no array at {pointer:?}"),
+            ParsingError::DocumentTooLarge(max) => write!(f, "document larger than {max} bytes"),
+            ParsingError::StringTooLong(max) => write!(f, "string longer than {max} bytes"),
+            ParsingError::ArrayTooLong(max) => write!(f, "array of more than {max} elements"),
+            ParsingError::TooManyValues(max) => write!(f, "document of more than {max} values"),
+            ParsingError::Cancelled => write!(f, "cancelled before the end of the array"),
+        }
+    }
+}
+
+impl std::error::Error for ParsingError {}
+
+/// A number out of range is a parsing error of its own, although the
+/// tokenizer is what finds it.
 impl From<TokenizeError> for ParsingError {
     fn from(error: TokenizeError) -> ParsingError {
-        ParsingError::Tokenize(error)
+        match error {
+            TokenizeError::NumberOutOfRange(number) => ParsingError::NumberOutOfRange(number),
+            error => ParsingError::Tokenize(error),
+        }
     }
 }
 
-pub(crate) struct Parser<'a> {
-    tokenizer: Tokenizer<'a>,
+/// What the parser does when an object contains the same key more than once.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DuplicateKeyPolicy {
+    /// Fail with `ParsingError::DuplicateObjectKey`.
+    #[default]
+    Error,
+    /// Keep the value of the first occurrence.
+    FirstWins,
+    /// Keep the value of the last occurrence.
+    LastWins,
+}
+
+/// Default limit of nested arrays and objects, which keeps the recursive
+/// descent from overflowing the stack.
+pub(super) const DEFAULT_MAX_DEPTH: usize = 128;
+
+#[derive(Clone)]
+pub struct Parser<'a> {
+    pub(super) tokenizer: Tokenizer<'a>,
+    pub(super) duplicate_keys: DuplicateKeyPolicy,
+    pub(super) max_depth: usize,
+    pub(super) depth: usize,
+    pub(super) limits: Limits,
+    /// Values read so far, against `Limits::max_values`.
+    pub(super) values: usize,
+    pub(super) structural_scan: Option<Simd>,
 }
 
 impl<'a> Parser<'a> {
-    pub(crate) fn new(input: &'a str) -> Self {
+    /// Creates a parser of `input`, skipping a byte order mark at its start.
+    pub fn new(input: &'a str) -> Self {
         Self {
-            tokenizer: Tokenizer::new(input),
+            tokenizer: Tokenizer::new(input).skip_bom(),
+            duplicate_keys: DuplicateKeyPolicy::default(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            depth: 0,
+            limits: Limits::default(),
+            values: 0,
+            structural_scan: None,
+        }
+    }
+
+    /// Sets how escaped lone surrogates in strings are handled.
+    pub fn lone_surrogates(mut self, policy: LoneSurrogatePolicy) -> Self {
+        self.tokenizer = self.tokenizer.lone_surrogates(policy);
+        self
+    }
+
+    /// Accepts control characters written unescaped in strings, which JSON
+    /// forbids.
+    pub fn allow_control_chars(mut self, allow: bool) -> Self {
+        self.tokenizer = self.tokenizer.allow_control_chars(allow);
+        self
+    }
+
+    /// Keeps numbers as the literals they were written as, so serializing
+    /// the document writes them back unchanged. They are converted when
+    /// accessed.
+    pub fn raw_numbers(mut self, raw: bool) -> Self {
+        self.tokenizer = self.tokenizer.raw_numbers(raw);
+        self
+    }
+
+    /// Sets how numbers out of the range of `f64` are handled.
+    pub fn out_of_range(mut self, policy: NumberRangePolicy) -> Self {
+        self.tokenizer = self.tokenizer.out_of_range(policy);
+        self
+    }
+
+    /// Sets how duplicate object keys are handled.
+    pub fn duplicate_keys(mut self, policy: DuplicateKeyPolicy) -> Self {
+        self.duplicate_keys = policy;
+        self
+    }
+
+    /// Sets how deep arrays and objects may be nested.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Sets the limits of the document. `parse_tape` checks only its size,
+    /// and `parse_recovering` none of them.
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Makes `parse_tape` find where the tokens start with a vectorized
+    /// first pass over the input, which the other methods ignore. Inputs
+    /// longer than `u32::MAX` bytes are read without it.
+    pub fn structural_scan(mut self, simd: Simd) -> Self {
+        self.structural_scan = Some(simd);
+        self
+    }
+
+    /// Runs `parse` on the contents of an array or object one level deeper.
+    pub(super) fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, ParsingError>,
+    ) -> Result<T, ParsingError> {
+        if self.depth == self.max_depth {
+            return Err(ParsingError::TooDeep);
         }
+        self.depth += 1;
+        let parsed = parse(self);
+        self.depth -= 1;
+        parsed
     }
 
-    pub(crate) fn parse(mut self) -> Result<Option<Value>, ParsingError> {
+    pub fn parse(mut self) -> Result<Option<Value>, ParsingError> {
+        self.check_size()?;
         let parsed = self.parse_value()?;
         if self.tokenizer.next_token() != Ok(Token::Eof) {
             return Err(ParsingError::ExtraData);
@@ -39,57 +216,88 @@ impl<'a> Parser<'a> {
         Ok(parsed)
     }
 
+    /// Checks the size of the whole input against the limits.
+    pub(super) fn check_size(&self) -> Result<(), ParsingError> {
+        self.limits.check_size(self.tokenizer.input().len())
+    }
+
     pub(crate) fn parse_value(&mut self) -> Result<Option<Value>, ParsingError> {
-        match self.tokenizer.next_token() {
+        let token = self.tokenizer.next_token();
+        match &token {
+            Ok(Token::Eof) | Err(_) => {}
+            Ok(Token::String(s)) => {
+                self.limits.count_value(&mut self.values)?;
+                self.limits.check_string(s)?;
+            }
+            Ok(_) => self.limits.count_value(&mut self.values)?,
+        }
+        match token {
             Ok(Token::Eof) => Ok(None),
             Ok(Token::Null) => Ok(Some(Value::Null)),
             Ok(Token::Bool(b)) => Ok(Some(Value::Bool(b))),
             Ok(Token::String(s)) => Ok(Some(Value::String(s))),
             Ok(Token::Number(n)) => Ok(Some(Value::Number(n))),
-            Ok(Token::OpenBracket) => self.parse_array().map(Some),
-            Ok(Token::OpenBrace) => self.parse_object().map(Some),
+            Ok(Token::OpenBracket) => self.nested(Self::parse_array).map(Some),
+            Ok(Token::OpenBrace) => self.nested(Self::parse_object).map(Some),
             Ok(t) => Err(ParsingError::StartingToken(t)),
-            Err(e) => Err(ParsingError::Tokenize(e)),
+            Err(e) => Err(e.into()),
         }
     }
 
     fn parse_array(&mut self) -> Result<Value, ParsingError> {
-        let mut items = Vec::new();
-
         // Handle empty array right away: `[]`
         match self.tokenizer.peek_next()? {
             Token::CloseBracket => {
                 let t = self.tokenizer.next_token()?;
                 debug_assert_eq!(t, Token::CloseBracket);
-                return Ok(Value::Array(items));
+                return Ok(Value::Array(Vec::new()));
             }
             Token::Eof => return Err(ParsingError::ReachedEOF(Token::OpenBracket)),
             _ => {}
         }
 
-        loop {
-            let v = self.parse_value()?.expect("Guaranteed to not be EOF");
-            items.push(v);
+        let first = self.array_element(0)?;
+        self.array_rest(vec![first])
+    }
 
-            // After a value we must see either `,` (more) or `]` (end)
-            match self.tokenizer.next_token()? {
-                Token::Comma => {
-                    // Disallow trailing comma: `,]`
-                    match self.tokenizer.peek_next()? {
-                        Token::CloseBracket => return Err(ParsingError::TrailingComma),
-                        Token::Eof => return Err(ParsingError::ReachedEOF(Token::OpenBracket)),
-                        _ => {}
-                    }
+    /// Parses the element at `index` of an array.
+    pub(super) fn array_element(&mut self, index: usize) -> Result<Value, ParsingError> {
+        self.limits.check_element(index)?;
+        self.parse_value()
+            .map_err(|e| e.inside(PathSegment::Index(index)))?
+            .ok_or(ParsingError::ReachedEOF(Token::OpenBracket))
+    }
+
+    /// Parses the rest of an array after its elements `items`, up to its
+    /// closing bracket.
+    pub(super) fn array_rest(&mut self, mut items: Vec<Value>) -> Result<Value, ParsingError> {
+        // After a value we must see either `,` (more) or `]` (end)
+        while self.array_separator()? {
+            items.push(self.array_element(items.len())?);
+        }
+        Ok(Value::Array(items))
+    }
+
+    /// Reads what follows an array element, returning whether another
+    /// element follows rather than the closing bracket.
+    pub(super) fn array_separator(&mut self) -> Result<bool, ParsingError> {
+        match self.tokenizer.next_token()? {
+            Token::Comma => {
+                // Disallow trailing comma: `,]`
+                match self.tokenizer.peek_next()? {
+                    Token::CloseBracket => Err(ParsingError::TrailingComma),
+                    Token::Eof => Err(ParsingError::ReachedEOF(Token::OpenBracket)),
+                    _ => Ok(true),
                 }
-                Token::CloseBracket => return Ok(Value::Array(items)),
-                Token::Eof => return Err(ParsingError::ReachedEOF(Token::OpenBracket)),
-                tok => return Err(ParsingError::TokenAfterValue(tok)),
             }
+            Token::CloseBracket => Ok(false),
+            Token::Eof => Err(ParsingError::ReachedEOF(Token::OpenBracket)),
+            tok => Err(ParsingError::TokenAfterValue(tok)),
         }
     }
 
     fn parse_object(&mut self) -> Result<Value, ParsingError> {
-        let mut map = HashMap::<String, Value>::new();
+        let mut map = Map::new();
 
         // Empty object: `{}`
         match self.tokenizer.peek_next()? {
@@ -102,40 +310,198 @@ impl<'a> Parser<'a> {
             _ => {}
         }
 
+        let key = self.object_key()?;
+        let value = self.member_value(&key)?;
+        self.insert_member(&mut map, key, value)?;
+        self.object_rest(map)
+    }
+
+    /// Parses a key of an object and the colon after it.
+    pub(super) fn object_key(&mut self) -> Result<String, ParsingError> {
+        // Key must be a string
+        let key = match self.parse_value()? {
+            Some(Value::String(s)) => s,
+            Some(val) => return Err(ParsingError::InvalidKey(val)),
+            None => return Err(ParsingError::ReachedEOF(Token::OpenBrace)),
+        };
+
+        // Colon after key
+        match self.tokenizer.next_token()? {
+            Token::Colon => Ok(key),
+            Token::Eof => Err(ParsingError::ReachedEOF(Token::OpenBrace)),
+            _ => Err(ParsingError::MissingColon),
+        }
+    }
+
+    /// Parses the value of the member at `key`.
+    pub(super) fn member_value(&mut self, key: &str) -> Result<Value, ParsingError> {
+        self.parse_value()
+            .map_err(|e| e.inside(PathSegment::Key(key.into())))?
+            .ok_or(ParsingError::ReachedEOF(Token::OpenBrace))
+    }
+
+    /// Adds a member to `map`, resolving duplicate keys according to the
+    /// policy.
+    pub(super) fn insert_member(
+        &self,
+        map: &mut Map,
+        key: String,
+        value: Value,
+    ) -> Result<(), ParsingError> {
+        match map.get_mut(&key) {
+            None => {
+                map.insert(key, value);
+            }
+            Some(existing) => match self.duplicate_keys {
+                DuplicateKeyPolicy::Error => return Err(ParsingError::DuplicateObjectKey(key)),
+                DuplicateKeyPolicy::FirstWins => {}
+                DuplicateKeyPolicy::LastWins => *existing = value,
+            },
+        }
+        Ok(())
+    }
+
+    /// Parses the rest of an object after its members in `map`, up to its
+    /// closing brace.
+    pub(super) fn object_rest(&mut self, mut map: Map) -> Result<Value, ParsingError> {
+        // After a member, require `,` or `}`
+        while self.object_separator()? {
+            let key = self.object_key()?;
+            let value = self.member_value(&key)?;
+            self.insert_member(&mut map, key, value)?;
+        }
+        Ok(Value::Object(map))
+    }
+
+    /// Reads what follows an object member, returning whether another
+    /// member follows rather than the closing brace.
+    pub(super) fn object_separator(&mut self) -> Result<bool, ParsingError> {
+        match self.tokenizer.next_token()? {
+            Token::Comma => {
+                // Forbid trailing comma: `,}`
+                match self.tokenizer.peek_next()? {
+                    Token::CloseBrace => Err(ParsingError::TrailingComma),
+                    Token::Eof => Err(ParsingError::ReachedEOF(Token::OpenBrace)),
+                    _ => Ok(true),
+                }
+            }
+            Token::CloseBrace => Ok(false),
+            Token::Eof => Err(ParsingError::ReachedEOF(Token::OpenBrace)),
+            tok => Err(ParsingError::TokenAfterValue(tok)),
+        }
+    }
+
+    /// Parses the document keeping the source span of every value and key,
+    /// for tools that map values back to the text. Duplicate keys are all
+    /// kept, whatever the policy.
+    pub fn parse_with_spans(mut self) -> Result<Option<SpannedValue>, ParsingError> {
+        self.check_size()?;
+        let parsed = self.parse_spanned_value()?;
+        if self.tokenizer.next_token() != Ok(Token::Eof) {
+            return Err(ParsingError::ExtraData);
+        }
+        Ok(parsed)
+    }
+
+    fn parse_spanned_value(&mut self) -> Result<Option<SpannedValue>, ParsingError> {
+        let (token, span) = self.tokenizer.next_spanned()?;
+        if token != Token::Eof {
+            self.limits.count_value(&mut self.values)?;
+        }
+        let kind = match token {
+            Token::Eof => return Ok(None),
+            Token::Null => SpannedKind::Null,
+            Token::Bool(b) => SpannedKind::Bool(b),
+            Token::String(s) => {
+                self.limits.check_string(&s)?;
+                SpannedKind::String(s)
+            }
+            Token::Number(n) => SpannedKind::Number(n),
+            Token::OpenBracket => SpannedKind::Array(self.nested(Self::parse_spanned_array)?),
+            Token::OpenBrace => SpannedKind::Object(self.nested(Self::parse_spanned_object)?),
+            t => return Err(ParsingError::StartingToken(t)),
+        };
+        let span = Span::new(span.start, self.tokenizer.offset());
+        Ok(Some(SpannedValue { kind, span }))
+    }
+
+    /// Parses the elements and the closing bracket of an array.
+    fn parse_spanned_array(&mut self) -> Result<Vec<SpannedValue>, ParsingError> {
+        let mut items = Vec::new();
+        if self.tokenizer.peek_next()? == &Token::CloseBracket {
+            self.tokenizer.next_token()?;
+            return Ok(items);
+        }
+
+        loop {
+            self.limits.check_element(items.len())?;
+            let item = self
+                .parse_spanned_value()
+                .map_err(|e| e.inside(PathSegment::Index(items.len())))?
+                .ok_or(ParsingError::ReachedEOF(Token::OpenBracket))?;
+            items.push(item);
+
+            match self.tokenizer.next_token()? {
+                Token::Comma => {
+                    if self.tokenizer.peek_next()? == &Token::CloseBracket {
+                        return Err(ParsingError::TrailingComma);
+                    }
+                }
+                Token::CloseBracket => return Ok(items),
+                Token::Eof => return Err(ParsingError::ReachedEOF(Token::OpenBracket)),
+                tok => return Err(ParsingError::TokenAfterValue(tok)),
+            }
+        }
+    }
+
+    /// Parses the members and the closing brace of an object.
+    fn parse_spanned_object(&mut self) -> Result<Vec<Member>, ParsingError> {
+        let mut members = Vec::new();
+        if self.tokenizer.peek_next()? == &Token::CloseBrace {
+            self.tokenizer.next_token()?;
+            return Ok(members);
+        }
+
         loop {
-            // Key must be a string
-            let key = match self.parse_value()? {
-                Some(Value::String(s)) => s,
-                Some(val) => return Err(ParsingError::InvalidKey(val)),
-                None => return Err(ParsingError::ReachedEOF(Token::OpenBrace)),
+            let (key, key_span) = match self.tokenizer.next_spanned()? {
+                (Token::String(key), span) => {
+                    self.limits.count_value(&mut self.values)?;
+                    self.limits.check_string(&key)?;
+                    (key, span)
+                }
+                (Token::Eof, _) => return Err(ParsingError::ReachedEOF(Token::OpenBrace)),
+                (token, _) => match Value::try_from(token) {
+                    Ok(value) => return Err(ParsingError::InvalidKey(value)),
+                    Err(ValueError::TryFromToken(token)) => {
+                        return Err(ParsingError::StartingToken(token));
+                    }
+                    Err(_) => unreachable!("Only tokens fail to convert"),
+                },
             };
 
-            // Colon after key
             match self.tokenizer.next_token()? {
                 Token::Colon => {}
                 Token::Eof => return Err(ParsingError::ReachedEOF(Token::OpenBrace)),
                 _ => return Err(ParsingError::MissingColon),
             }
 
-            let value = self.parse_value()?.expect("Guaranteed to not be EOF");
-
-            // Forbit duplicate keys
-            if map.contains_key(&key) {
-                return Err(ParsingError::DuplicateObjectKey(key));
-            }
-            map.insert(key, value);
+            let value = self
+                .parse_spanned_value()
+                .map_err(|e| e.inside(PathSegment::Key(key.clone())))?
+                .ok_or(ParsingError::ReachedEOF(Token::OpenBrace))?;
+            members.push(Member {
+                key,
+                key_span,
+                value,
+            });
 
-            // After a member, require `,` or `}`
             match self.tokenizer.next_token()? {
                 Token::Comma => {
-                    // Forbid trailing comma: `,}`
-                    match self.tokenizer.peek_next()? {
-                        Token::CloseBrace => return Err(ParsingError::TrailingComma),
-                        Token::Eof => return Err(ParsingError::ReachedEOF(Token::OpenBrace)),
-                        _ => {} // continue parsing next member
+                    if self.tokenizer.peek_next()? == &Token::CloseBrace {
+                        return Err(ParsingError::TrailingComma);
                     }
                 }
-                Token::CloseBrace => return Ok(Value::Object(map)),
+                Token::CloseBrace => return Ok(members),
                 Token::Eof => return Err(ParsingError::ReachedEOF(Token::OpenBrace)),
                 tok => return Err(ParsingError::TokenAfterValue(tok)),
             }
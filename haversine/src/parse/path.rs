@@ -0,0 +1,35 @@
+use std::fmt;
+
+/// A step from a value to one of its children.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// Location of a value inside a document, as the keys and indices leading
+/// to it from the root.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KeyPath(pub Vec<PathSegment>);
+
+impl KeyPath {
+    /// Adds `segment` in front of the path, as errors are wrapped on their
+    /// way up from the innermost value.
+    pub(crate) fn prepend(&mut self, segment: PathSegment) {
+        self.0.insert(0, segment);
+    }
+}
+
+impl fmt::Display for KeyPath {
+    /// Formats the path like `"pairs"[41213]."y1"`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.0.iter().enumerate() {
+            match segment {
+                PathSegment::Key(key) if i == 0 => write!(f, "{key:?}")?,
+                PathSegment::Key(key) => write!(f, ".{key:?}")?,
+                PathSegment::Index(index) => write!(f, "[{index}]")?,
+            }
+        }
+        Ok(())
+    }
+}
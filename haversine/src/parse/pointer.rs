@@ -0,0 +1,256 @@
+use super::{Map, Parser, ParsingError, PathSegment, Token, Value};
+use crate::cancel::CancelToken;
+
+/// Elements parsed between two checks of the cancel token.
+const CANCEL_INTERVAL: usize = 256;
+
+/// A container on the way from the root to the array being iterated, with
+/// what has been parsed of it.
+enum Frame {
+    Object { map: Map, key: String },
+    Array { items: Vec<Value> },
+}
+
+impl Frame {
+    fn segment(&self) -> PathSegment {
+        match self {
+            Frame::Object { key, .. } => PathSegment::Key(key.clone()),
+            Frame::Array { items } => PathSegment::Index(items.len()),
+        }
+    }
+}
+
+enum State {
+    /// Not yet at the array.
+    Start,
+    /// Before the element at the index.
+    Elements(usize),
+    /// Past the array, or stopped at an error.
+    Done,
+}
+
+/// Iterator over the elements of the array at a JSON pointer, parsing one
+/// element per call, made by `Parser::iter_array_at`. It stops after the
+/// first error.
+pub struct ArrayValues<'a> {
+    parser: Parser<'a>,
+    pointer: String,
+    frames: Vec<Frame>,
+    state: State,
+    document: Option<Value>,
+    cancel: Option<CancelToken>,
+}
+
+impl<'a> Parser<'a> {
+    /// Iterates over the elements of the array at the JSON `pointer`, such
+    /// as `/pairs`, without holding more than one of them. The rest of the
+    /// document is parsed as the iterator reaches it, and kept for
+    /// `ArrayValues::document`. The elements are those of the first array
+    /// at the pointer if an object on the way has duplicate keys.
+    pub fn iter_array_at(self, pointer: &str) -> ArrayValues<'a> {
+        ArrayValues {
+            parser: self,
+            pointer: pointer.into(),
+            frames: Vec::new(),
+            state: State::Start,
+            document: None,
+            cancel: None,
+        }
+    }
+}
+
+impl ArrayValues<'_> {
+    /// Stops the iteration with `ParsingError::Cancelled` once `token` is
+    /// cancelled. It is checked every few elements, so a huge array can be
+    /// abandoned part way.
+    pub fn cancel(mut self, token: CancelToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// The document without the elements of the array, which is left
+    /// empty. Only there once the iterator has been run to the end without
+    /// an error.
+    pub fn document(&self) -> Option<&Value> {
+        self.document.as_ref()
+    }
+
+    pub fn into_document(self) -> Option<Value> {
+        self.document
+    }
+
+    /// Goes into a nested array or object.
+    fn enter(&mut self) -> Result<(), ParsingError> {
+        if self.parser.depth == self.parser.max_depth {
+            return Err(ParsingError::TooDeep);
+        }
+        self.parser.depth += 1;
+        Ok(())
+    }
+
+    /// Parses up to the first element of the array at the pointer,
+    /// returning whether it has any.
+    fn find_array(&mut self) -> Result<bool, ParsingError> {
+        self.parser.check_size()?;
+        let pointer = self.pointer.clone();
+        let missing = || ParsingError::NoArrayAt(pointer.clone());
+        let segments = match pointer.strip_prefix('/') {
+            Some(segments) => segments.split('/').map(unescape).collect(),
+            None if pointer.is_empty() => Vec::new(),
+            None => return Err(missing()),
+        };
+
+        for segment in segments {
+            match self.parser.tokenizer.next_token()? {
+                Token::OpenBrace => {
+                    self.enter()?;
+                    let map = self.find_member(&segment)?.ok_or_else(missing)?;
+                    self.frames.push(Frame::Object { map, key: segment });
+                }
+                Token::OpenBracket => {
+                    self.enter()?;
+                    let index = segment.parse().map_err(|_| missing())?;
+                    let items = self.find_element(index)?.ok_or_else(missing)?;
+                    self.frames.push(Frame::Array { items });
+                }
+                _ => return Err(missing()),
+            }
+        }
+
+        if self.parser.tokenizer.next_token()? != Token::OpenBracket {
+            return Err(missing());
+        }
+        self.enter()?;
+        match self.parser.tokenizer.peek_next()? {
+            Token::CloseBracket => {
+                self.parser.tokenizer.next_token()?;
+                Ok(false)
+            }
+            Token::Eof => Err(ParsingError::ReachedEOF(Token::OpenBracket)),
+            _ => Ok(true),
+        }
+    }
+
+    /// Parses the members of an object up to the colon after `key`,
+    /// returning the members before it, or `None` if it has no such key.
+    fn find_member(&mut self, key: &str) -> Result<Option<Map>, ParsingError> {
+        let mut map = Map::new();
+        if self.parser.tokenizer.peek_next()? == &Token::CloseBrace {
+            return Ok(None);
+        }
+        loop {
+            let found = self.parser.object_key()?;
+            if found == key {
+                return Ok(Some(map));
+            }
+            let value = self.parser.member_value(&found)?;
+            self.parser.insert_member(&mut map, found, value)?;
+            if !self.parser.object_separator()? {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Parses the elements of an array before the one at `index`, or
+    /// returns `None` if it has fewer.
+    fn find_element(&mut self, index: usize) -> Result<Option<Vec<Value>>, ParsingError> {
+        let mut items = Vec::new();
+        if self.parser.tokenizer.peek_next()? == &Token::CloseBracket {
+            return Ok(None);
+        }
+        while items.len() < index {
+            items.push(self.parser.array_element(items.len())?);
+            if !self.parser.array_separator()? {
+                return Ok(None);
+            }
+        }
+        Ok(Some(items))
+    }
+
+    /// Parses the rest of the document after the array.
+    fn finish(&mut self) -> Result<Value, ParsingError> {
+        let mut value = Value::Array(Vec::new());
+        self.parser.depth -= 1;
+        while let Some(frame) = self.frames.pop() {
+            value = match frame {
+                Frame::Object { mut map, key } => {
+                    map.insert(key, value);
+                    self.parser.object_rest(map)
+                }
+                Frame::Array { mut items } => {
+                    items.push(value);
+                    self.parser.array_rest(items)
+                }
+            }
+            .map_err(|error| self.at_frames(error))?;
+            self.parser.depth -= 1;
+        }
+        if self.parser.tokenizer.next_token()? != Token::Eof {
+            return Err(ParsingError::ExtraData);
+        }
+        Ok(value)
+    }
+
+    fn next_element(&mut self) -> Result<Option<Value>, ParsingError> {
+        let index = match self.state {
+            State::Start => {
+                if !self.find_array().map_err(|error| self.at_frames(error))? {
+                    self.document = Some(self.finish()?);
+                    return Ok(None);
+                }
+                0
+            }
+            State::Elements(index) => {
+                if index % CANCEL_INTERVAL == 0
+                    && self.cancel.as_ref().is_some_and(CancelToken::is_cancelled)
+                {
+                    return Err(ParsingError::Cancelled);
+                }
+                let more = self
+                    .parser
+                    .array_separator()
+                    .map_err(|error| self.at_frames(error))?;
+                if !more {
+                    self.document = Some(self.finish()?);
+                    return Ok(None);
+                }
+                index
+            }
+            State::Done => return Ok(None),
+        };
+        let element = self
+            .parser
+            .array_element(index)
+            .map_err(|error| self.at_frames(error))?;
+        self.state = State::Elements(index + 1);
+        Ok(Some(element))
+    }
+
+    /// Records that the error happened inside the containers entered.
+    fn at_frames(&self, error: ParsingError) -> ParsingError {
+        if let ParsingError::NoArrayAt(_) = error {
+            return error;
+        }
+        self.frames
+            .iter()
+            .rev()
+            .fold(error, |error, frame| error.inside(frame.segment()))
+    }
+}
+
+impl Iterator for ArrayValues<'_> {
+    type Item = Result<Value, ParsingError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let next = self.next_element();
+        if !matches!(next, Ok(Some(_))) {
+            self.state = State::Done;
+        }
+        next.transpose()
+    }
+}
+
+/// Decodes the `~1` and `~0` escapes of a JSON pointer segment.
+pub(crate) fn unescape(segment: &str) -> String {
+    segment.replace("~1", "/").replace("~0", "~")
+}
@@ -0,0 +1,373 @@
+use super::{BOM, DuplicateKeyPolicy, Map, Parser, ParsingError, Span, Token, Value, ValueError};
+use std::fmt::Write;
+
+/// Problem found by `Parser::parse_recovering` or `Parser::parse_located`,
+/// at the text of the token where it was found.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub error: ParsingError,
+    pub span: Span,
+}
+
+impl Diagnostic {
+    /// Returns the line and column, both starting at 1, where the problem
+    /// starts in `source`, the text that was parsed.
+    pub fn line_column(&self, source: &str) -> (usize, usize) {
+        let before = &source[..self.span.start];
+        let line = before.matches('\n').count() + 1;
+        let before = before.strip_prefix(BOM).unwrap_or(before);
+        let column = before.chars().rev().take_while(|&c| c != '\n').count() + 1;
+        (line, column)
+    }
+
+    /// Returns the line of `source` where the problem starts, after up to
+    /// `SNIPPET_CONTEXT` lines before it, with carets under the text of the
+    /// problem, in the style of rustc:
+    ///
+    /// ```
+    /// use haversine::parse::Parser;
+    ///
+    /// let source = "[\n  1,\n  2 3\n]";
+    /// let diagnostic = Parser::new(source).parse_located().unwrap_err();
+    /// let snippet = "  |\n1 | [\n2 |   1,\n3 |   2 3\n  |     ^\n";
+    /// assert_eq!(snippet, diagnostic.snippet(source));
+    /// ```
+    pub fn snippet(&self, source: &str) -> String {
+        let (line, column) = self.line_column(source);
+        let body = source.strip_prefix(BOM).unwrap_or(source);
+        let lines: Vec<&str> = body
+            .split('\n')
+            .map(|line| line.strip_suffix('\r').unwrap_or(line))
+            .collect();
+        let width = line.to_string().len();
+
+        let mut snippet = format!("{:width$} |\n", "");
+        let first = line.saturating_sub(SNIPPET_CONTEXT).max(1);
+        for number in first..=line {
+            let text = format!("{number:>width$} | {}", lines[number - 1]);
+            writeln!(snippet, "{}", text.trim_end()).expect("Writing to a String");
+        }
+        // Tabs before the problem are kept, so the carets line up with it.
+        let indent: String = lines[line - 1]
+            .chars()
+            .take(column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let text = self.span.source(source);
+        let carets = text.split('\n').next().unwrap_or("").chars().count().max(1);
+        writeln!(snippet, "{:width$} | {indent}{}", "", "^".repeat(carets))
+            .expect("Writing to a String");
+        snippet
+    }
+}
+
+/// Lines of the source shown before the line of a problem in its snippet.
+pub const SNIPPET_CONTEXT: usize = 2;
+
+/// Adds a diagnostic, unless one was already reported at the same place,
+/// as happens when recovery from one error runs into the next.
+fn report(diagnostics: &mut Vec<Diagnostic>, error: ParsingError, span: Span) {
+    if diagnostics
+        .last()
+        .is_none_or(|last| last.span.start != span.start)
+    {
+        diagnostics.push(Diagnostic { error, span });
+    }
+}
+
+impl Parser<'_> {
+    /// Parses the document like `parse`, returning an error with the span
+    /// of the text where it was found. The error is located by parsing the
+    /// input again with recovery, so documents that parse pay nothing for
+    /// it.
+    pub fn parse_located(self) -> Result<Option<Value>, Diagnostic> {
+        let retry = self.clone();
+        self.parse().map_err(|error| {
+            let end = retry.tokenizer.input().len();
+            let (_, diagnostics) = retry.parse_recovering();
+            let span = diagnostics
+                .first()
+                .map_or(Span::new(end, end), |diagnostic| diagnostic.span);
+            Diagnostic { error, span }
+        })
+    }
+
+    /// Parses the document without stopping at the first error. An invalid
+    /// token or member is reported and skipped up to the next comma or
+    /// closing delimiter, so one pass finds every problem of a hand-written
+    /// file. Returns what could be parsed along with the problems, which are
+    /// empty exactly when `parse` would succeed.
+    pub fn parse_recovering(mut self) -> (Option<Value>, Vec<Diagnostic>) {
+        let mut diagnostics = Vec::new();
+        let value = match self.tokenizer.peek_next() {
+            Ok(Token::Eof) => None,
+            _ => self.recover_value(&mut diagnostics),
+        };
+        let (token, span) = self.tokenizer.peek_with_span();
+        if token != Ok(&Token::Eof) {
+            report(&mut diagnostics, ParsingError::ExtraData, span);
+        }
+        (value, diagnostics)
+    }
+
+    /// Parses a value, or reports why there is none. Commas and closing
+    /// delimiters are left for the caller.
+    fn recover_value(&mut self, diagnostics: &mut Vec<Diagnostic>) -> Option<Value> {
+        let (token, span) = self.tokenizer.peek_with_span();
+        let error = match token {
+            Err(error) => {
+                self.tokenizer.skip_to_delimiter();
+                ParsingError::from(error)
+            }
+            Ok(Token::OpenBracket | Token::OpenBrace) if self.depth == self.max_depth => {
+                self.skip_nested();
+                ParsingError::TooDeep
+            }
+            Ok(Token::OpenBracket | Token::OpenBrace) => {
+                let open = self.tokenizer.next_token();
+                self.depth += 1;
+                let value = match open {
+                    Ok(Token::OpenBracket) => self.recover_array(diagnostics),
+                    _ => self.recover_object(diagnostics),
+                };
+                self.depth -= 1;
+                return Some(value);
+            }
+            Ok(
+                token @ (Token::Eof
+                | Token::Comma
+                | Token::Colon
+                | Token::CloseBracket
+                | Token::CloseBrace),
+            ) => ParsingError::StartingToken(token.clone()),
+            Ok(_) => {
+                let token = self.tokenizer.next_token().expect("Peeked token");
+                return Some(Value::try_from(token).expect("Peeked a value"));
+            }
+        };
+        report(diagnostics, error, span);
+        None
+    }
+
+    /// Parses the elements of an array after its `[`.
+    fn recover_array(&mut self, diagnostics: &mut Vec<Diagnostic>) -> Value {
+        let mut items = Vec::new();
+        loop {
+            let (token, span) = self.tokenizer.peek_with_span();
+            match token {
+                Ok(Token::CloseBracket) => {
+                    let _ = self.tokenizer.next_token();
+                    return Value::Array(items);
+                }
+                Ok(Token::Eof) => {
+                    report(
+                        diagnostics,
+                        ParsingError::ReachedEOF(Token::OpenBracket),
+                        span,
+                    );
+                    return Value::Array(items);
+                }
+                // A mismatched `}` closes the array.
+                Ok(Token::CloseBrace) => {
+                    let _ = self.tokenizer.next_token();
+                    report(
+                        diagnostics,
+                        ParsingError::StartingToken(Token::CloseBrace),
+                        span,
+                    );
+                    return Value::Array(items);
+                }
+                Ok(Token::Comma | Token::Colon) => {
+                    let token = self.tokenizer.next_token().expect("Peeked token");
+                    report(diagnostics, ParsingError::StartingToken(token), span);
+                    continue;
+                }
+                _ => items.extend(self.recover_value(diagnostics)),
+            }
+
+            if !self.recover_separator(Token::OpenBracket, Token::CloseBracket, diagnostics) {
+                return Value::Array(items);
+            }
+        }
+    }
+
+    /// Parses the members of an object after its `{`.
+    fn recover_object(&mut self, diagnostics: &mut Vec<Diagnostic>) -> Value {
+        let mut map = Map::new();
+        loop {
+            let (token, span) = self.tokenizer.peek_with_span();
+            let key_span = span;
+            let key = match token {
+                Ok(Token::CloseBrace) => {
+                    let _ = self.tokenizer.next_token();
+                    return Value::Object(map);
+                }
+                Ok(Token::Eof) => {
+                    report(
+                        diagnostics,
+                        ParsingError::ReachedEOF(Token::OpenBrace),
+                        span,
+                    );
+                    return Value::Object(map);
+                }
+                // A value where a key should be is most likely the next
+                // element after an object missing its `}`.
+                Ok(token @ (Token::OpenBrace | Token::OpenBracket)) => {
+                    report(
+                        diagnostics,
+                        ParsingError::StartingToken(token.clone()),
+                        span,
+                    );
+                    return Value::Object(map);
+                }
+                // A mismatched `]` closes the object.
+                Ok(Token::CloseBracket) => {
+                    let _ = self.tokenizer.next_token();
+                    report(
+                        diagnostics,
+                        ParsingError::StartingToken(Token::CloseBracket),
+                        span,
+                    );
+                    return Value::Object(map);
+                }
+                Ok(Token::String(_)) => match self.tokenizer.next_token() {
+                    Ok(Token::String(key)) => key,
+                    _ => unreachable!("Peeked a string"),
+                },
+                token => {
+                    let error = match token.map(|token| Value::try_from(token.clone())) {
+                        Err(error) => ParsingError::from(error),
+                        Ok(Ok(value)) => ParsingError::InvalidKey(value),
+                        Ok(Err(ValueError::TryFromToken(token))) => {
+                            ParsingError::StartingToken(token)
+                        }
+                        Ok(Err(_)) => unreachable!("Only tokens fail to convert"),
+                    };
+                    report(diagnostics, error, span);
+                    self.skip_member();
+                    continue;
+                }
+            };
+
+            let (token, span) = self.tokenizer.peek_with_span();
+            match token {
+                Ok(Token::Colon) => {
+                    let _ = self.tokenizer.next_token();
+                }
+                // Without a value either, go on with the next member.
+                Ok(Token::Comma | Token::CloseBrace | Token::Eof) => {
+                    report(diagnostics, ParsingError::MissingColon, span);
+                    if !self.recover_separator(Token::OpenBrace, Token::CloseBrace, diagnostics) {
+                        return Value::Object(map);
+                    }
+                    continue;
+                }
+                _ => report(diagnostics, ParsingError::MissingColon, span),
+            }
+
+            let value = match self.tokenizer.peek_next() {
+                Ok(Token::Eof) => None,
+                _ => self.recover_value(diagnostics),
+            };
+            if let Some(value) = value {
+                match map.get_mut(&key) {
+                    None => {
+                        map.insert(key, value);
+                    }
+                    Some(existing) => match self.duplicate_keys {
+                        DuplicateKeyPolicy::Error => {
+                            report(diagnostics, ParsingError::DuplicateObjectKey(key), key_span)
+                        }
+                        DuplicateKeyPolicy::FirstWins => {}
+                        DuplicateKeyPolicy::LastWins => *existing = value,
+                    },
+                }
+            }
+
+            if !self.recover_separator(Token::OpenBrace, Token::CloseBrace, diagnostics) {
+                return Value::Object(map);
+            }
+        }
+    }
+
+    /// Reads what follows an element or member of the container opened by
+    /// `open`. Returns whether another element or member follows.
+    fn recover_separator(
+        &mut self,
+        open: Token,
+        close: Token,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> bool {
+        let (token, span) = self.tokenizer.peek_with_span();
+        let (error, more) = match token {
+            Ok(Token::Comma) => {
+                let _ = self.tokenizer.next_token();
+                if self.tokenizer.peek_next() != Ok(&close) {
+                    return true;
+                }
+                (ParsingError::TrailingComma, true)
+            }
+            Ok(token) if *token == close => {
+                let _ = self.tokenizer.next_token();
+                return false;
+            }
+            Ok(Token::Eof) => (ParsingError::ReachedEOF(open), false),
+            // A mismatched closing delimiter closes the container.
+            Ok(Token::CloseBracket | Token::CloseBrace) => {
+                let token = self.tokenizer.next_token().expect("Peeked token");
+                (ParsingError::TokenAfterValue(token), false)
+            }
+            // Most likely a missing comma.
+            Ok(token) => (ParsingError::TokenAfterValue(token.clone()), true),
+            Err(error) => {
+                self.tokenizer.skip_to_delimiter();
+                if self.tokenizer.peek_next() == Ok(&Token::Comma) {
+                    let _ = self.tokenizer.next_token();
+                }
+                (ParsingError::from(error), true)
+            }
+        };
+        report(diagnostics, error, span);
+        more
+    }
+
+    /// Skips the rest of an object member, up to and including its comma.
+    fn skip_member(&mut self) {
+        let mut depth = 0usize;
+        loop {
+            match self.tokenizer.peek_next() {
+                Ok(Token::Eof) => return,
+                Ok(Token::CloseBrace | Token::CloseBracket) if depth == 0 => return,
+                Ok(Token::Comma) if depth == 0 => {
+                    let _ = self.tokenizer.next_token();
+                    return;
+                }
+                Ok(_) => match self.tokenizer.next_token().expect("Peeked token") {
+                    Token::OpenBrace | Token::OpenBracket => depth += 1,
+                    Token::CloseBrace | Token::CloseBracket => depth -= 1,
+                    _ => {}
+                },
+                Err(_) => self.tokenizer.skip_to_delimiter(),
+            }
+        }
+    }
+
+    /// Skips an array or object with everything nested in it.
+    fn skip_nested(&mut self) {
+        let mut depth = 0usize;
+        loop {
+            match self.tokenizer.next_token() {
+                Ok(Token::Eof) => return,
+                Ok(Token::OpenBrace | Token::OpenBracket) => depth += 1,
+                Ok(Token::CloseBrace | Token::CloseBracket) => {
+                    depth = depth.saturating_sub(1);
+                    if depth == 0 {
+                        return;
+                    }
+                }
+                Ok(_) => {}
+                Err(_) => self.tokenizer.skip_to_delimiter(),
+            }
+        }
+    }
+}
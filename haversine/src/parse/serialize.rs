@@ -0,0 +1,186 @@
+use super::{Number, Value};
+use std::fmt::Write;
+
+impl Value {
+    /// Serializes the value as compact JSON. Object members are written in
+    /// insertion order, so a parsed document serializes to the same bytes on
+    /// every run.
+    pub fn to_json(&self) -> String {
+        let mut serializer = Serializer::new(None);
+        serializer.value(self);
+        serializer.out
+    }
+
+    /// Serializes the value as JSON with every array item and object member
+    /// on its own line, indented by `indent` spaces per level.
+    pub fn to_json_pretty(&self, indent: usize) -> String {
+        let mut serializer = Serializer::new(Some(indent));
+        serializer.value(self);
+        serializer.out
+    }
+
+    /// Serializes the value in the canonical form of RFC 8785, so values
+    /// equal as JSON serialize to the same bytes, ready to be hashed: no
+    /// whitespace, members ordered by the UTF-16 code units of their keys,
+    /// and every number written as ECMAScript writes the nearest `f64`.
+    pub fn to_canonical_json(&self) -> String {
+        let mut serializer = Serializer::new(None);
+        serializer.canonical = true;
+        serializer.value(self);
+        serializer.out
+    }
+}
+
+struct Serializer {
+    out: String,
+    indent: Option<usize>,
+    depth: usize,
+    canonical: bool,
+}
+
+impl Serializer {
+    fn new(indent: Option<usize>) -> Self {
+        Self {
+            out: String::new(),
+            indent,
+            depth: 0,
+            canonical: false,
+        }
+    }
+
+    fn value(&mut self, value: &Value) {
+        match value {
+            Value::Null => self.out.push_str("null"),
+            Value::Bool(b) => write!(self.out, "{b}").expect("Writing to a String"),
+            Value::Number(n) => self.number(n),
+            Value::String(s) => self.string(s),
+            Value::Array(items) => {
+                self.out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    self.separator(i);
+                    self.value(item);
+                }
+                self.close(items.is_empty(), ']');
+            }
+            Value::Object(map) => {
+                self.out.push('{');
+                let mut members: Vec<_> = map.iter().collect();
+                if self.canonical {
+                    members.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+                }
+                for (i, (key, value)) in members.into_iter().enumerate() {
+                    self.separator(i);
+                    self.string(key);
+                    self.out
+                        .push_str(if self.indent.is_some() { ": " } else { ":" });
+                    self.value(value);
+                }
+                self.close(map.is_empty(), '}');
+            }
+        }
+    }
+
+    /// Writes the number in its shortest round-trip form, and a raw literal
+    /// as it was read. JSON has no infinities or NaN, so they are written as
+    /// `null`.
+    fn number(&mut self, n: &Number) {
+        match n {
+            n if self.canonical => self.canonical_number(n.as_f64()),
+            Number::Raw(literal) => self.out.push_str(literal),
+            Number::F64(n) if !n.is_finite() => self.out.push_str("null"),
+            n => write!(self.out, "{n}").expect("Writing to a String"),
+        }
+    }
+
+    /// Writes the number as ECMAScript's `Number.prototype.toString` does:
+    /// the shortest digits that round-trip, in plain notation for exponents
+    /// from -7 up to 21 and in scientific notation beyond.
+    fn canonical_number(&mut self, n: f64) {
+        if !n.is_finite() {
+            self.out.push_str("null");
+            return;
+        }
+        if n == 0.0 {
+            // Negative zero too.
+            self.out.push('0');
+            return;
+        }
+        if n < 0.0 {
+            self.out.push('-');
+        }
+        // Rust's scientific notation has the same shortest digits.
+        let scientific = format!("{:e}", n.abs());
+        let (mantissa, exponent) = scientific.split_once('e').expect("Scientific notation");
+        let digits = mantissa.replace('.', "");
+        let k = digits.len() as i32;
+        // The value is 0.digits times ten to the `n`.
+        let n = exponent.parse::<i32>().expect("Integer exponent") + 1;
+        let out = &mut self.out;
+        if k <= n && n <= 21 {
+            out.push_str(&digits);
+            out.extend(std::iter::repeat_n('0', (n - k) as usize));
+        } else if 0 < n && n <= 21 {
+            let (integer, fraction) = digits.split_at(n as usize);
+            write!(out, "{integer}.{fraction}").expect("Writing to a String");
+        } else if -6 < n && n <= 0 {
+            out.push_str("0.");
+            out.extend(std::iter::repeat_n('0', -n as usize));
+            out.push_str(&digits);
+        } else {
+            let (first, rest) = digits.split_at(1);
+            out.push_str(first);
+            if !rest.is_empty() {
+                write!(out, ".{rest}").expect("Writing to a String");
+            }
+            let sign = if n > 0 { '+' } else { '-' };
+            write!(out, "e{sign}{}", (n - 1).abs()).expect("Writing to a String");
+        }
+    }
+
+    fn string(&mut self, s: &str) {
+        self.out.push('"');
+        for c in s.chars() {
+            match c {
+                '"' => self.out.push_str("\\\""),
+                '\\' => self.out.push_str("\\\\"),
+                '\n' => self.out.push_str("\\n"),
+                '\r' => self.out.push_str("\\r"),
+                '\t' => self.out.push_str("\\t"),
+                '\u{8}' => self.out.push_str("\\b"),
+                '\u{c}' => self.out.push_str("\\f"),
+                c if c < ' ' => {
+                    write!(self.out, "\\u{:04x}", c as u32).expect("Writing to a String")
+                }
+                c => self.out.push(c),
+            }
+        }
+        self.out.push('"');
+    }
+
+    /// Starts the `i`-th item of an array or object.
+    fn separator(&mut self, i: usize) {
+        if i > 0 {
+            self.out.push(',');
+        } else {
+            self.depth += 1;
+        }
+        self.newline();
+    }
+
+    /// Ends an array or object with `delimiter`.
+    fn close(&mut self, empty: bool, delimiter: char) {
+        if !empty {
+            self.depth -= 1;
+            self.newline();
+        }
+        self.out.push(delimiter);
+    }
+
+    fn newline(&mut self) {
+        if let Some(indent) = self.indent {
+            self.out.push('\n');
+            self.out
+                .extend(std::iter::repeat_n(' ', indent * self.depth));
+        }
+    }
+}
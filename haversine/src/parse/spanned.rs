@@ -0,0 +1,69 @@
+use super::{Map, Number, Value};
+
+/// Byte range of a token or value in the source text.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// Returns the text of the span in `source`, which must be the text the
+    /// span was taken from.
+    pub fn source<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.start..self.end]
+    }
+}
+
+/// A JSON value with the span of its source text, from the first character
+/// of the value to its last.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedValue {
+    pub kind: SpannedKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpannedKind {
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(String),
+    Array(Vec<SpannedValue>),
+    /// Members in source order. Duplicate keys are all kept.
+    Object(Vec<Member>),
+}
+
+/// Member of an object with the span of its key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Member {
+    pub key: String,
+    pub key_span: Span,
+    pub value: SpannedValue,
+}
+
+impl SpannedValue {
+    /// Drops the spans. Of duplicate keys, the last value is kept, at the
+    /// position of the first.
+    pub fn to_value(&self) -> Value {
+        match &self.kind {
+            SpannedKind::Null => Value::Null,
+            SpannedKind::Bool(b) => Value::Bool(*b),
+            SpannedKind::Number(n) => Value::Number(n.clone()),
+            SpannedKind::String(s) => Value::String(s.clone()),
+            SpannedKind::Array(items) => {
+                Value::Array(items.iter().map(SpannedValue::to_value).collect())
+            }
+            SpannedKind::Object(members) => Value::Object(
+                members
+                    .iter()
+                    .map(|member| (member.key.clone(), member.value.to_value()))
+                    .collect::<Map>(),
+            ),
+        }
+    }
+}
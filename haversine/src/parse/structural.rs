@@ -0,0 +1,400 @@
+//! Vectorized first pass over the input, which finds where every token
+//! starts so the tape builder can jump from token to token over whitespace
+//! and string contents.
+//!
+//! Every 64 bytes are classified into bitmasks with byte comparisons, then
+//! quotes escaped by an odd run of backslashes are discarded and the bytes
+//! inside strings masked out with a prefix XOR of the remaining quotes, as
+//! simdjson does.
+
+use super::tokenize::Skipped;
+use super::{LoneSurrogatePolicy, Span, TokenizeError, Tokenizer};
+
+const BLOCK: usize = 64;
+
+/// Instructions the structural scan classifies bytes with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Simd {
+    /// Byte by byte, on any machine.
+    Scalar,
+    /// 16 bytes at a time, on every x86_64 processor.
+    Sse2,
+    /// 32 bytes at a time, on x86_64 processors that have it.
+    Avx2,
+    /// 16 bytes at a time, on every aarch64 processor.
+    Neon,
+}
+
+impl Simd {
+    /// The widest instructions this machine has.
+    pub fn detect() -> Self {
+        [Simd::Avx2, Simd::Sse2, Simd::Neon]
+            .into_iter()
+            .find(|simd| simd.is_available())
+            .unwrap_or(Simd::Scalar)
+    }
+
+    pub fn is_available(self) -> bool {
+        match self {
+            Simd::Scalar => true,
+            #[cfg(target_arch = "x86_64")]
+            Simd::Sse2 => true,
+            #[cfg(target_arch = "x86_64")]
+            Simd::Avx2 => std::arch::is_x86_feature_detected!("avx2"),
+            #[cfg(target_arch = "aarch64")]
+            Simd::Neon => true,
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+
+    /// Every available choice, narrowest first.
+    pub fn available() -> Vec<Self> {
+        [Simd::Scalar, Simd::Sse2, Simd::Avx2, Simd::Neon]
+            .into_iter()
+            .filter(|simd| simd.is_available())
+            .collect()
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Simd::Scalar => "scalar",
+            Simd::Sse2 => "sse2",
+            Simd::Avx2 => "avx2",
+            Simd::Neon => "neon",
+        }
+    }
+}
+
+/// Bytes of one block that are of each class, one bit per byte.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Masks {
+    quote: u64,
+    backslash: u64,
+    /// `{`, `}`, `[`, `]`, `,` and `:`.
+    op: u64,
+    whitespace: u64,
+}
+
+/// Returns the offsets where tokens start: structural characters outside
+/// strings, every unescaped quote, opening and closing, and the first byte
+/// of every other run of bytes outside strings. An unavailable `simd` falls
+/// back to the scalar scan.
+///
+/// # Panics
+///
+/// If the input is longer than `u32::MAX` bytes.
+pub fn structural_indices(input: &str, simd: Simd) -> Vec<u32> {
+    assert!(u32::try_from(input.len()).is_ok(), "Input too long");
+    let classify = match simd {
+        #[cfg(target_arch = "x86_64")]
+        Simd::Sse2 => classify_sse2,
+        #[cfg(target_arch = "x86_64")]
+        Simd::Avx2 if simd.is_available() => classify_avx2,
+        #[cfg(target_arch = "aarch64")]
+        Simd::Neon => classify_neon,
+        _ => classify_scalar,
+    };
+
+    let bytes = input.as_bytes();
+    let mut indices = Vec::with_capacity(bytes.len() / 8);
+    let mut scanner = Scanner::default();
+    let mut chunks = bytes.chunks_exact(BLOCK);
+    let mut base = 0;
+    for chunk in &mut chunks {
+        let block = chunk.try_into().expect("Chunks of a block");
+        push_bits(&mut indices, base, scanner.starts(classify(block)));
+        base += BLOCK;
+    }
+    let rest = chunks.remainder();
+    if !rest.is_empty() {
+        // Spaces pad the last block, as whitespace starts no token.
+        let mut block = [b' '; BLOCK];
+        block[..rest.len()].copy_from_slice(rest);
+        push_bits(&mut indices, base, scanner.starts(classify(&block)));
+    }
+    indices
+}
+
+fn push_bits(indices: &mut Vec<u32>, base: usize, mut bits: u64) {
+    while bits != 0 {
+        indices.push((base + bits.trailing_zeros() as usize) as u32);
+        bits &= bits - 1;
+    }
+}
+
+/// State carried from one block to the next.
+#[derive(Debug, Default)]
+struct Scanner {
+    /// Whether the first byte of the block is escaped by a backslash at the
+    /// end of the previous one.
+    escaped: u64,
+    /// All ones if the block starts inside a string.
+    in_string: u64,
+    /// Whether the previous block ends with a byte of a scalar.
+    scalar: u64,
+}
+
+impl Scanner {
+    /// Returns the bits of the bytes that start tokens.
+    fn starts(&mut self, masks: Masks) -> u64 {
+        let escaped = self.escaped(masks.backslash);
+        let quote = masks.quote & !escaped;
+        // Set from an opening quote up to, not including, its closing one.
+        let in_string = prefix_xor(quote) ^ self.in_string;
+        self.in_string = ((in_string as i64) >> 63) as u64;
+
+        let scalar = !(masks.op | masks.whitespace | quote | in_string);
+        let follows_scalar = scalar << 1 | self.scalar;
+        self.scalar = scalar >> 63;
+
+        (masks.op & !in_string) | quote | (scalar & !follows_scalar)
+    }
+
+    /// Returns the bits of the bytes escaped by a backslash, the second of
+    /// every pair in a run of backslashes.
+    fn escaped(&mut self, backslash: u64) -> u64 {
+        const EVEN_BITS: u64 = 0x5555_5555_5555_5555;
+        let backslash = backslash & !self.escaped;
+        let follows_escape = backslash << 1 | self.escaped;
+        // Runs starting on an odd bit, carried by the addition to their
+        // end, flip the even and odd bits of the runs.
+        let odd_starts = backslash & !EVEN_BITS & !follows_escape;
+        let (even_starts, overflow) = odd_starts.overflowing_add(backslash);
+        self.escaped = overflow as u64;
+        (EVEN_BITS ^ (even_starts << 1)) & follows_escape
+    }
+}
+
+/// Sets every bit to the XOR of it and all the lower ones.
+fn prefix_xor(mut bits: u64) -> u64 {
+    for shift in [1, 2, 4, 8, 16, 32] {
+        bits ^= bits << shift;
+    }
+    bits
+}
+
+fn classify_scalar(block: &[u8; BLOCK]) -> Masks {
+    let mut masks = Masks::default();
+    for (i, &byte) in block.iter().enumerate() {
+        let bit = 1 << i;
+        match byte {
+            b'"' => masks.quote |= bit,
+            b'\\' => masks.backslash |= bit,
+            b'{' | b'}' | b'[' | b']' | b',' | b':' => masks.op |= bit,
+            b' ' | b'\t' | b'\n' | b'\r' => masks.whitespace |= bit,
+            _ => {}
+        }
+    }
+    masks
+}
+
+#[cfg(target_arch = "x86_64")]
+fn classify_sse2(block: &[u8; BLOCK]) -> Masks {
+    use std::arch::x86_64::*;
+
+    let mut masks = Masks::default();
+    for (i, lane) in block.chunks_exact(16).enumerate() {
+        // SAFETY: SSE2 is part of x86_64, and the load reads the 16 bytes
+        // of the lane, unaligned.
+        let (quote, backslash, op, whitespace) = unsafe {
+            let bytes = _mm_loadu_si128(lane.as_ptr().cast());
+            let eq = |c: u8| _mm_cmpeq_epi8(bytes, _mm_set1_epi8(c as i8));
+            let any = |chars: &[u8]| {
+                chars
+                    .iter()
+                    .fold(_mm_setzero_si128(), |acc, &c| _mm_or_si128(acc, eq(c)))
+            };
+            let bits = |v| _mm_movemask_epi8(v) as u16 as u64;
+            (
+                bits(eq(b'"')),
+                bits(eq(b'\\')),
+                bits(any(b"{}[],:")),
+                bits(any(b" \t\n\r")),
+            )
+        };
+        let shift = 16 * i;
+        masks.quote |= quote << shift;
+        masks.backslash |= backslash << shift;
+        masks.op |= op << shift;
+        masks.whitespace |= whitespace << shift;
+    }
+    masks
+}
+
+#[cfg(target_arch = "x86_64")]
+fn classify_avx2(block: &[u8; BLOCK]) -> Masks {
+    // SAFETY: only selected when the processor has AVX2.
+    unsafe { classify_avx2_unchecked(block) }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn classify_avx2_unchecked(block: &[u8; BLOCK]) -> Masks {
+    use std::arch::x86_64::*;
+
+    let mut masks = Masks::default();
+    for (i, lane) in block.chunks_exact(32).enumerate() {
+        // SAFETY: the load reads the 32 bytes of the lane, unaligned.
+        let bytes = unsafe { _mm256_loadu_si256(lane.as_ptr().cast()) };
+        let eq = |c: u8| _mm256_cmpeq_epi8(bytes, _mm256_set1_epi8(c as i8));
+        let any = |chars: &[u8]| {
+            chars.iter().fold(_mm256_setzero_si256(), |acc, &c| {
+                _mm256_or_si256(acc, eq(c))
+            })
+        };
+        let bits = |v| _mm256_movemask_epi8(v) as u32 as u64;
+        let shift = 32 * i;
+        masks.quote |= bits(eq(b'"')) << shift;
+        masks.backslash |= bits(eq(b'\\')) << shift;
+        masks.op |= bits(any(b"{}[],:")) << shift;
+        masks.whitespace |= bits(any(b" \t\n\r")) << shift;
+    }
+    masks
+}
+
+#[cfg(target_arch = "aarch64")]
+fn classify_neon(block: &[u8; BLOCK]) -> Masks {
+    use std::arch::aarch64::*;
+
+    // SAFETY: NEON is part of aarch64, and the loads read the four lanes of
+    // 16 bytes of the block.
+    unsafe {
+        let lanes = [0, 16, 32, 48].map(|i| vld1q_u8(block[i..].as_ptr()));
+        let weights: [u8; 16] = [1, 2, 4, 8, 16, 32, 64, 128, 1, 2, 4, 8, 16, 32, 64, 128];
+        let weights = vld1q_u8(weights.as_ptr());
+        // Keeps one bit per matching byte and adds them up pairwise into
+        // the 64 bits of the block, as there is no movemask.
+        let bits = |matches: [uint8x16_t; 4]| {
+            let [a, b, c, d] = matches.map(|m| vandq_u8(m, weights));
+            let sum = vpaddq_u8(vpaddq_u8(a, b), vpaddq_u8(c, d));
+            let sum = vpaddq_u8(sum, sum);
+            vgetq_lane_u64::<0>(vreinterpretq_u64_u8(sum))
+        };
+        let eq = |c: u8| lanes.map(|lane| vceqq_u8(lane, vdupq_n_u8(c)));
+        let any = |chars: &[u8]| {
+            lanes.map(|lane| {
+                chars.iter().fold(vdupq_n_u8(0), |acc, &c| {
+                    vorrq_u8(acc, vceqq_u8(lane, vdupq_n_u8(c)))
+                })
+            })
+        };
+        Masks {
+            quote: bits(eq(b'"')),
+            backslash: bits(eq(b'\\')),
+            op: bits(any(b"{}[],:")),
+            whitespace: bits(any(b" \t\n\r")),
+        }
+    }
+}
+
+/// Tokens read at the offsets found by the structural scan. Structural
+/// characters and strings without escapes are read from the offsets alone;
+/// the tokenizer reads the rest, so the tokens and errors are its own.
+pub(crate) struct StructuralTokens<'a> {
+    input: &'a str,
+    indices: Vec<u32>,
+    next: usize,
+    /// Where to go on with the tokenizer, when a token it read is followed
+    /// by more than whitespace before the next offset.
+    resume: Option<usize>,
+    lone_surrogates: LoneSurrogatePolicy,
+    allow_control_chars: bool,
+}
+
+impl<'a> StructuralTokens<'a> {
+    pub(crate) fn new(
+        input: &'a str,
+        simd: Simd,
+        lone_surrogates: LoneSurrogatePolicy,
+        allow_control_chars: bool,
+    ) -> Self {
+        Self {
+            input,
+            indices: structural_indices(input, simd),
+            next: 0,
+            resume: None,
+            lone_surrogates,
+            allow_control_chars,
+        }
+    }
+
+    pub(crate) fn skip_spanned(&mut self) -> Result<(Skipped, Span), TokenizeError> {
+        if let Some(offset) = self.resume.take() {
+            return self.tokenize_at(offset);
+        }
+        let Some(&start) = self.indices.get(self.next) else {
+            let end = self.input.len();
+            return Ok((Skipped::Eof, Span::new(end, end)));
+        };
+        let start = start as usize;
+        self.next += 1;
+        let skipped = match self.input.as_bytes()[start] {
+            b'{' => Skipped::OpenBrace,
+            b'}' => Skipped::CloseBrace,
+            b'[' => Skipped::OpenBracket,
+            b']' => Skipped::CloseBracket,
+            b',' => Skipped::Comma,
+            b':' => Skipped::Colon,
+            b'"' => return self.string_at(start),
+            _ => return self.tokenize_at(start),
+        };
+        Ok((skipped, Span::new(start, start + 1)))
+    }
+
+    pub(crate) fn peek_skipped(&mut self) -> Result<Skipped, TokenizeError> {
+        let (next, resume) = (self.next, self.resume);
+        let skipped = self.skip_spanned();
+        (self.next, self.resume) = (next, resume);
+        skipped.map(|(skipped, _)| skipped)
+    }
+
+    /// Reads the string opening at `start`, which the next offset closes
+    /// unless the string runs to the end of the input. Strings with escapes
+    /// or control characters are left to the tokenizer.
+    fn string_at(&mut self, start: usize) -> Result<(Skipped, Span), TokenizeError> {
+        let bytes = self.input.as_bytes();
+        if let Some(&end) = self.indices.get(self.next) {
+            let end = end as usize;
+            let plain = |&b: &u8| b != b'\\' && (b >= b' ' || self.allow_control_chars);
+            if bytes[end] == b'"' && bytes[start + 1..end].iter().all(plain) {
+                self.next += 1;
+                return Ok((
+                    Skipped::String { escaped: false },
+                    Span::new(start, end + 1),
+                ));
+            }
+        }
+        self.tokenize_at(start)
+    }
+
+    /// Reads the token at `offset` with the tokenizer, and skips the offsets
+    /// inside it.
+    fn tokenize_at(&mut self, offset: usize) -> Result<(Skipped, Span), TokenizeError> {
+        let mut tokenizer = Tokenizer::new(&self.input[offset..])
+            .lone_surrogates(self.lone_surrogates)
+            .allow_control_chars(self.allow_control_chars);
+        let (skipped, span) = tokenizer.skip_spanned()?;
+        let span = Span::new(offset + span.start, offset + span.end);
+        while self
+            .indices
+            .get(self.next)
+            .is_some_and(|&index| (index as usize) < span.end)
+        {
+            self.next += 1;
+        }
+        let next = self
+            .indices
+            .get(self.next)
+            .map_or(self.input.len(), |&index| index as usize);
+        let gap = &self.input.as_bytes()[span.end..next];
+        if !gap
+            .iter()
+            .all(|b| matches!(b, b' ' | b'\t' | b'\n' | b'\r'))
+        {
+            self.resume = Some(span.end);
+        }
+        Ok((skipped, span))
+    }
+}
@@ -0,0 +1,514 @@
+use super::structural::StructuralTokens;
+use super::tokenize::Skipped;
+use super::{
+    LoneSurrogatePolicy, Map, Number, Parser, ParsingError, PathSegment, Span, Token,
+    TokenizeError, Tokenizer, Value, ValueError,
+};
+use std::borrow::Cow;
+
+/// A document checked in full but not built: one entry per value and key,
+/// in document order, pointing into the text. Values are decoded only when
+/// a `Cursor` reads them, so reading a few fields of a large document
+/// allocates nothing but the tape.
+///
+/// ```
+/// use haversine::parse::Parser;
+///
+/// let input = r#"{"pairs": [{"x0": 1.5}, {"x0": -2}], "radius": 1}"#;
+/// let tape = Parser::new(input).parse_tape().unwrap().unwrap();
+/// let x0: Vec<f64> = tape
+///     .get("pairs")
+///     .unwrap()
+///     .iter()
+///     .filter_map(|pair| pair.get("x0")?.as_f64())
+///     .collect();
+/// assert_eq!(vec![1.5, -2.0], x0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Tape<'a> {
+    input: &'a str,
+    entries: Vec<Entry>,
+    lone_surrogates: LoneSurrogatePolicy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Null,
+    Bool(bool),
+    Number,
+    String { escaped: bool },
+    Array,
+    Object,
+}
+
+/// A value or key of the tape. Scalars keep the span of their text, arrays
+/// and objects the index of the entry after their last one, so they can be
+/// skipped in one step.
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    kind: Kind,
+    start: usize,
+    end: usize,
+}
+
+impl<'a> Tape<'a> {
+    /// Cursor at the root value.
+    pub fn root(&self) -> Cursor<'_> {
+        Cursor {
+            tape: self,
+            index: 0,
+        }
+    }
+
+    /// Value at `key` of the root object, the first if the key repeats.
+    pub fn get(&self, key: &str) -> Option<Cursor<'_>> {
+        self.root().get(key)
+    }
+
+    /// Number of values and keys in the document.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Index of the entry after the value at `index`.
+    fn next(&self, index: usize) -> usize {
+        let entry = &self.entries[index];
+        match entry.kind {
+            Kind::Array | Kind::Object => entry.end,
+            _ => index + 1,
+        }
+    }
+
+    fn text(&self, index: usize) -> &'a str {
+        let entry = &self.entries[index];
+        &self.input[entry.start..entry.end]
+    }
+}
+
+/// Position of a value in a `Tape`, reading it on demand.
+#[derive(Debug, Clone, Copy)]
+pub struct Cursor<'t> {
+    tape: &'t Tape<'t>,
+    index: usize,
+}
+
+impl<'t> Cursor<'t> {
+    fn kind(&self) -> Kind {
+        self.tape.entries[self.index].kind
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.kind() == Kind::Null
+    }
+
+    pub fn is_array(&self) -> bool {
+        self.kind() == Kind::Array
+    }
+
+    pub fn is_object(&self) -> bool {
+        self.kind() == Kind::Object
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.kind() {
+            Kind::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    /// Converts the number literal, as the parser would.
+    pub fn as_number(&self) -> Option<Number> {
+        match self.kind() {
+            Kind::Number => Number::from_literal(self.tape.text(self.index)),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        self.as_number().map(|n| n.as_f64())
+    }
+
+    /// Borrows the string from the text, unless it has escape sequences to
+    /// decode.
+    pub fn as_str(&self) -> Option<Cow<'t, str>> {
+        match self.kind() {
+            Kind::String { escaped } => Some(self.tape.string(self.index, escaped)),
+            _ => None,
+        }
+    }
+
+    /// Value at `key` of an object, the first if the key repeats.
+    pub fn get(&self, key: &str) -> Option<Cursor<'t>> {
+        self.members()
+            .find(|(member, _)| member == key)
+            .map(|(_, value)| value)
+    }
+
+    /// Element at `index` of an array.
+    pub fn at(&self, index: usize) -> Option<Cursor<'t>> {
+        self.iter().nth(index)
+    }
+
+    /// Elements of an array, none for other values.
+    pub fn iter(&self) -> Elements<'t> {
+        let (next, end) = match self.kind() {
+            Kind::Array => (self.index + 1, self.tape.entries[self.index].end),
+            _ => (0, 0),
+        };
+        Elements {
+            tape: self.tape,
+            next,
+            end,
+        }
+    }
+
+    /// Keys and values of an object, none for other values.
+    pub fn members(&self) -> Members<'t> {
+        let (next, end) = match self.kind() {
+            Kind::Object => (self.index + 1, self.tape.entries[self.index].end),
+            _ => (0, 0),
+        };
+        Members {
+            tape: self.tape,
+            next,
+            end,
+        }
+    }
+
+    /// Builds the value, with its children, as `Parser::parse` would with
+    /// duplicate keys resolved by the first.
+    pub fn to_value(&self) -> Value {
+        match self.kind() {
+            Kind::Null => Value::Null,
+            Kind::Bool(b) => Value::Bool(b),
+            Kind::Number => Value::Number(self.as_number().expect("Checked number")),
+            Kind::String { .. } => Value::String(self.as_str().expect("String").into_owned()),
+            Kind::Array => Value::Array(self.iter().map(|element| element.to_value()).collect()),
+            Kind::Object => {
+                let mut map = Map::new();
+                for (key, value) in self.members() {
+                    if map.get(&key).is_none() {
+                        map.insert(key.into_owned(), value.to_value());
+                    }
+                }
+                Value::Object(map)
+            }
+        }
+    }
+}
+
+impl Tape<'_> {
+    /// Decodes the string entry at `index`.
+    fn string(&self, index: usize, escaped: bool) -> Cow<'_, str> {
+        let text = self.text(index);
+        if !escaped {
+            return Cow::Borrowed(&text[1..text.len() - 1]);
+        }
+        // Control characters were checked when the tape was built.
+        let mut tokenizer = Tokenizer::new(text)
+            .lone_surrogates(self.lone_surrogates)
+            .allow_control_chars(true);
+        match tokenizer.next_token() {
+            Ok(Token::String(string)) => Cow::Owned(string),
+            _ => unreachable!("Strings are checked when the tape is built"),
+        }
+    }
+}
+
+/// Iterator over the elements of an array, from `Cursor::iter`.
+#[derive(Debug, Clone)]
+pub struct Elements<'t> {
+    tape: &'t Tape<'t>,
+    next: usize,
+    end: usize,
+}
+
+impl<'t> Iterator for Elements<'t> {
+    type Item = Cursor<'t>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        (self.next < self.end).then(|| {
+            let index = self.next;
+            self.next = self.tape.next(index);
+            Cursor {
+                tape: self.tape,
+                index,
+            }
+        })
+    }
+}
+
+/// Iterator over the members of an object, from `Cursor::members`.
+#[derive(Debug, Clone)]
+pub struct Members<'t> {
+    tape: &'t Tape<'t>,
+    next: usize,
+    end: usize,
+}
+
+impl<'t> Iterator for Members<'t> {
+    type Item = (Cow<'t, str>, Cursor<'t>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        (self.next < self.end).then(|| {
+            let key = self.next;
+            let Kind::String { escaped } = self.tape.entries[key].kind else {
+                unreachable!("Keys are strings");
+            };
+            self.next = self.tape.next(key + 1);
+            let value = Cursor {
+                tape: self.tape,
+                index: key + 1,
+            };
+            (self.tape.string(key, escaped), value)
+        })
+    }
+}
+
+impl<'a> Parser<'a> {
+    /// Checks the document and records where its values are, without
+    /// building them. The errors are those of `parse`, but duplicate keys
+    /// are all kept, whatever the policy, and reading a key finds the first.
+    /// Returns `None` if the input holds no value.
+    pub fn parse_tape(self) -> Result<Option<Tape<'a>>, ParsingError> {
+        self.check_size()?;
+        let rest = self.tokenizer.rest();
+        let lone_surrogates = self.tokenizer.lone_surrogate_policy();
+        let allow_control_chars = self.tokenizer.allows_control_chars();
+        // The structural scan starts after a byte order mark, the tokenizer
+        // at the start of the input, so their spans index different texts.
+        let (input, entries) = match self.structural_scan {
+            Some(simd) if u32::try_from(rest.len()).is_ok() => {
+                let tokens =
+                    StructuralTokens::new(rest, simd, lone_surrogates, allow_control_chars);
+                (
+                    rest,
+                    TapeBuilder::new(rest, tokens, self.max_depth).build()?,
+                )
+            }
+            _ => {
+                let input = self.tokenizer.input();
+                let builder = TapeBuilder::new(input, self.tokenizer, self.max_depth);
+                (input, builder.build()?)
+            }
+        };
+        Ok(entries.map(|entries| Tape {
+            input,
+            entries,
+            lone_surrogates,
+        }))
+    }
+}
+
+/// Where the tape builder reads its tokens from.
+trait SkipTokens {
+    fn skip_spanned(&mut self) -> Result<(Skipped, Span), TokenizeError>;
+    fn peek_skipped(&mut self) -> Result<Skipped, TokenizeError>;
+}
+
+impl SkipTokens for Tokenizer<'_> {
+    fn skip_spanned(&mut self) -> Result<(Skipped, Span), TokenizeError> {
+        Tokenizer::skip_spanned(self)
+    }
+
+    fn peek_skipped(&mut self) -> Result<Skipped, TokenizeError> {
+        Tokenizer::peek_skipped(self)
+    }
+}
+
+impl SkipTokens for StructuralTokens<'_> {
+    fn skip_spanned(&mut self) -> Result<(Skipped, Span), TokenizeError> {
+        StructuralTokens::skip_spanned(self)
+    }
+
+    fn peek_skipped(&mut self) -> Result<Skipped, TokenizeError> {
+        StructuralTokens::peek_skipped(self)
+    }
+}
+
+struct TapeBuilder<'a, S> {
+    input: &'a str,
+    tokens: S,
+    entries: Vec<Entry>,
+    max_depth: usize,
+    depth: usize,
+}
+
+impl<'a, S: SkipTokens> TapeBuilder<'a, S> {
+    fn new(input: &'a str, tokens: S, max_depth: usize) -> Self {
+        Self {
+            input,
+            tokens,
+            entries: Vec::new(),
+            max_depth,
+            depth: 0,
+        }
+    }
+
+    /// Returns the entries of the document, `None` if it holds no value.
+    fn build(mut self) -> Result<Option<Vec<Entry>>, ParsingError> {
+        if !self.value()? {
+            return Ok(None);
+        }
+        if self.tokens.skip_spanned()?.0 != Skipped::Eof {
+            return Err(ParsingError::ExtraData);
+        }
+        Ok(Some(self.entries))
+    }
+
+    /// Runs `build` on the contents of an array or object one level deeper.
+    fn nested(
+        &mut self,
+        build: impl FnOnce(&mut Self) -> Result<(), ParsingError>,
+    ) -> Result<(), ParsingError> {
+        if self.depth == self.max_depth {
+            return Err(ParsingError::TooDeep);
+        }
+        self.depth += 1;
+        let built = build(self);
+        self.depth -= 1;
+        built
+    }
+
+    /// Appends the next value to the tape, returning `false` at the end of
+    /// the input.
+    fn value(&mut self) -> Result<bool, ParsingError> {
+        let (skipped, span) = self.tokens.skip_spanned()?;
+        let kind = match skipped {
+            Skipped::Eof => return Ok(false),
+            Skipped::Null => Kind::Null,
+            Skipped::Bool(b) => Kind::Bool(b),
+            Skipped::Number => Kind::Number,
+            Skipped::String { escaped } => Kind::String { escaped },
+            Skipped::OpenBracket => Kind::Array,
+            Skipped::OpenBrace => Kind::Object,
+            _ => {
+                return Err(ParsingError::StartingToken(token(
+                    self.input, skipped, span,
+                )));
+            }
+        };
+        let index = self.entries.len();
+        self.entries.push(Entry {
+            kind,
+            start: span.start,
+            end: span.end,
+        });
+        match kind {
+            Kind::Array => self.nested(Self::array)?,
+            Kind::Object => self.nested(Self::object)?,
+            _ => return Ok(true),
+        }
+        self.entries[index].end = self.entries.len();
+        Ok(true)
+    }
+
+    /// Appends the elements of an array, up to its closing bracket.
+    fn array(&mut self) -> Result<(), ParsingError> {
+        if self.tokens.peek_skipped()? == Skipped::CloseBracket {
+            self.tokens.skip_spanned()?;
+            return Ok(());
+        }
+
+        for index in 0.. {
+            let element = self
+                .value()
+                .map_err(|e| e.inside(PathSegment::Index(index)))?;
+            if !element {
+                return Err(ParsingError::ReachedEOF(Token::OpenBracket));
+            }
+
+            match self.tokens.skip_spanned()? {
+                (Skipped::Comma, _) => {
+                    if self.tokens.peek_skipped()? == Skipped::CloseBracket {
+                        return Err(ParsingError::TrailingComma);
+                    }
+                }
+                (Skipped::CloseBracket, _) => return Ok(()),
+                (Skipped::Eof, _) => return Err(ParsingError::ReachedEOF(Token::OpenBracket)),
+                (skipped, span) => {
+                    return Err(ParsingError::TokenAfterValue(token(
+                        self.input, skipped, span,
+                    )));
+                }
+            }
+        }
+        unreachable!("The loop returns")
+    }
+
+    /// Appends the keys and values of an object, up to its closing brace.
+    fn object(&mut self) -> Result<(), ParsingError> {
+        if self.tokens.peek_skipped()? == Skipped::CloseBrace {
+            self.tokens.skip_spanned()?;
+            return Ok(());
+        }
+
+        loop {
+            let key = match self.tokens.skip_spanned()? {
+                (Skipped::String { escaped }, span) => {
+                    self.entries.push(Entry {
+                        kind: Kind::String { escaped },
+                        start: span.start,
+                        end: span.end,
+                    });
+                    self.entries.len() - 1
+                }
+                (Skipped::Eof, _) => return Err(ParsingError::ReachedEOF(Token::OpenBrace)),
+                (skipped, span) => match Value::try_from(token(self.input, skipped, span)) {
+                    Ok(value) => return Err(ParsingError::InvalidKey(value)),
+                    Err(ValueError::TryFromToken(token)) => {
+                        return Err(ParsingError::StartingToken(token));
+                    }
+                    Err(_) => unreachable!("Only tokens fail to convert"),
+                },
+            };
+
+            match self.tokens.skip_spanned()?.0 {
+                Skipped::Colon => {}
+                Skipped::Eof => return Err(ParsingError::ReachedEOF(Token::OpenBrace)),
+                _ => return Err(ParsingError::MissingColon),
+            }
+
+            let value = self.value().map_err(|e| {
+                let entry = &self.entries[key];
+                let span = Span::new(entry.start, entry.end);
+                let Token::String(key) = token(self.input, Skipped::String { escaped: true }, span)
+                else {
+                    unreachable!("Keys are strings");
+                };
+                e.inside(PathSegment::Key(key))
+            })?;
+            if !value {
+                return Err(ParsingError::ReachedEOF(Token::OpenBrace));
+            }
+
+            match self.tokens.skip_spanned()? {
+                (Skipped::Comma, _) => {
+                    if self.tokens.peek_skipped()? == Skipped::CloseBrace {
+                        return Err(ParsingError::TrailingComma);
+                    }
+                }
+                (Skipped::CloseBrace, _) => return Ok(()),
+                (Skipped::Eof, _) => return Err(ParsingError::ReachedEOF(Token::OpenBrace)),
+                (skipped, span) => {
+                    return Err(ParsingError::TokenAfterValue(token(
+                        self.input, skipped, span,
+                    )));
+                }
+            }
+        }
+    }
+}
+
+/// Builds the token of a skipped one, for an error.
+fn token(input: &str, skipped: Skipped, span: Span) -> Token {
+    Tokenizer::new(span.source(input))
+        .lone_surrogates(LoneSurrogatePolicy::Replace)
+        .allow_control_chars(true)
+        .next_token()
+        .unwrap_or_else(|_| unreachable!("{skipped:?} was checked"))
+}
@@ -1,7 +1,12 @@
-use std::collections::HashMap;
-
 use super::parser::ParsingError;
-use super::{Parser, Token, Value};
+use super::tokenize::TokenizeError;
+use super::{
+    Change, DecodeError, Diagnostic, DuplicateKeyPolicy, Encoding, Event, FeedParser, KeyPath,
+    Limits, LoneSurrogatePolicy, Map, Number, NumberRangePolicy, Parser, PathSegment, Simd, Span,
+    SpannedKind, Token, Tokenizer, Value, decode, detect_encoding, parse, parse_source,
+    structural_indices, tokens,
+};
+use crate::cancel::CancelToken;
 
 #[test]
 fn test_null() {
@@ -31,25 +36,25 @@ fn test_string() {
 #[test]
 fn test_positive_int() {
     let parser = Parser::new("12");
-    assert_eq!(Ok(Some(Value::Number(12.0))), parser.parse());
+    assert_eq!(Ok(Some(Value::Number(Number::U64(12)))), parser.parse());
 }
 
 #[test]
 fn test_positive_decimal() {
     let parser = Parser::new("12.5");
-    assert_eq!(Ok(Some(Value::Number(12.5))), parser.parse());
+    assert_eq!(Ok(Some(Value::Number(Number::F64(12.5)))), parser.parse());
 }
 
 #[test]
 fn test_negative_int() {
     let parser = Parser::new("-120");
-    assert_eq!(Ok(Some(Value::Number(-120.0))), parser.parse());
+    assert_eq!(Ok(Some(Value::Number(Number::I64(-120)))), parser.parse());
 }
 
 #[test]
 fn test_negative_float() {
     let parser = Parser::new("-12.90");
-    assert_eq!(Ok(Some(Value::Number(-12.9))), parser.parse());
+    assert_eq!(Ok(Some(Value::Number(Number::F64(-12.9)))), parser.parse());
 }
 
 #[test]
@@ -62,9 +67,9 @@ fn test_array_empty() {
 fn test_int_array() {
     let parser = Parser::new("[1, 2, 3]");
     let array = Value::Array(vec![
-        Value::Number(1.0),
-        Value::Number(2.0),
-        Value::Number(3.0),
+        Value::Number(Number::U64(1)),
+        Value::Number(Number::U64(2)),
+        Value::Number(Number::U64(3)),
     ]);
     assert_eq!(Ok(Some(array)), parser.parse());
 }
@@ -73,7 +78,7 @@ fn test_int_array() {
 fn test_array_missing_comma() {
     let parser = Parser::new("[1, 2 3] ");
     assert_eq!(
-        Err(ParsingError::TokenAfterValue(Token::Number(3.0))),
+        Err(ParsingError::TokenAfterValue(Token::Number(Number::U64(3)))),
         parser.parse()
     );
 }
@@ -96,15 +101,15 @@ fn test_array_trailing_comma() {
 #[test]
 fn test_object_empty() {
     let parser = Parser::new("{}");
-    assert_eq!(Ok(Some(Value::Object(HashMap::new()))), parser.parse());
+    assert_eq!(Ok(Some(Value::Object(Map::new()))), parser.parse());
 }
 
 #[test]
 fn test_object() {
     let parser = Parser::new("{\"one\": 1, \"two\": 2}");
-    let mut map = HashMap::new();
-    map.insert("one".into(), Value::Number(1.0));
-    map.insert("two".into(), Value::Number(2.0));
+    let mut map = Map::new();
+    map.insert("one".into(), Value::Number(Number::U64(1)));
+    map.insert("two".into(), Value::Number(Number::U64(2)));
     let object = Value::Object(map);
     assert_eq!(Ok(Some(object)), parser.parse());
 }
@@ -119,14 +124,14 @@ fn test_object_trailing_comma() {
 fn test_array_mixed() {
     let parser = Parser::new(" [{\"one\": 1, \"two\": 2 } , [1, true, false] ,null ,\"string\"]");
 
-    let mut map = HashMap::new();
-    map.insert("one".into(), Value::Number(1.0));
-    map.insert("two".into(), Value::Number(2.0));
+    let mut map = Map::new();
+    map.insert("one".into(), Value::Number(Number::U64(1)));
+    map.insert("two".into(), Value::Number(Number::U64(2)));
 
     let array = Value::Array(vec![
         Value::Object(map),
         Value::Array(vec![
-            Value::Number(1.0),
+            Value::Number(Number::U64(1)),
             Value::Bool(true),
             Value::Bool(false),
         ]),
@@ -143,18 +148,1527 @@ fn test_object_mixed_spaced() {
         " {\"object\": {\"one\": 1, \"two\": 2 } , \"array\": [1, 2] , \"number\": 3 }",
     );
 
-    let mut map_in = HashMap::new();
-    map_in.insert("one".into(), Value::Number(1.0));
-    map_in.insert("two".into(), Value::Number(2.0));
+    let mut map_in = Map::new();
+    map_in.insert("one".into(), Value::Number(Number::U64(1)));
+    map_in.insert("two".into(), Value::Number(Number::U64(2)));
 
-    let mut map_out = HashMap::new();
+    let mut map_out = Map::new();
     map_out.insert("object".into(), Value::Object(map_in));
     map_out.insert(
         "array".into(),
-        Value::Array(vec![Value::Number(1.0), Value::Number(2.0)]),
+        Value::Array(vec![
+            Value::Number(Number::U64(1)),
+            Value::Number(Number::U64(2)),
+        ]),
     );
-    map_out.insert("number".into(), Value::Number(3.0));
+    map_out.insert("number".into(), Value::Number(Number::U64(3)));
 
     let object = Value::Object(map_out);
     assert_eq!(Ok(Some(object)), parser.parse());
 }
+
+#[test]
+fn test_object_duplicate_key_error() {
+    let parser = Parser::new("{\"one\": 1, \"one\": 2}");
+    assert_eq!(
+        Err(ParsingError::DuplicateObjectKey("one".into())),
+        parser.parse()
+    );
+}
+
+#[test]
+fn test_object_duplicate_key_first_wins() {
+    let parser =
+        Parser::new("{\"one\": 1, \"one\": 2}").duplicate_keys(DuplicateKeyPolicy::FirstWins);
+    let mut map = Map::new();
+    map.insert("one".into(), Value::Number(Number::U64(1)));
+    assert_eq!(Ok(Some(Value::Object(map))), parser.parse());
+}
+
+#[test]
+fn test_object_duplicate_key_last_wins() {
+    let parser =
+        Parser::new("{\"one\": 1, \"one\": 2}").duplicate_keys(DuplicateKeyPolicy::LastWins);
+    let mut map = Map::new();
+    map.insert("one".into(), Value::Number(Number::U64(2)));
+    assert_eq!(Ok(Some(Value::Object(map))), parser.parse());
+}
+
+#[test]
+fn test_value_builder() {
+    let mut pairs = Value::array();
+    pairs.push(Value::object().insert("x0", 1.5).insert("y0", -2i64));
+    pairs.push(vec![Value::from(true), Value::Null]);
+    let document = Value::object()
+        .insert("radius", 6372.8)
+        .insert("name", "pairs")
+        .insert("pairs", pairs);
+
+    let parser = Parser::new(
+        "{\"radius\": 6372.8, \"name\": \"pairs\", \"pairs\": [{\"x0\": 1.5, \"y0\": -2}, [true, null]]}",
+    );
+    assert_eq!(Ok(Some(document)), parser.parse());
+}
+
+#[test]
+fn test_value_index() {
+    let document = Value::object()
+        .insert("radius", 6372.8)
+        .insert("pairs", vec![Value::from(1.0), Value::from(2.0)]);
+
+    assert_eq!(Value::Number(Number::F64(6372.8)), document["radius"]);
+    assert_eq!(Value::Number(Number::F64(2.0)), document["pairs"][1]);
+    assert_eq!(Value::Null, document["pairs"][2]);
+    assert_eq!(Value::Null, document["missing"]["nested"]);
+}
+
+#[test]
+fn test_string_escapes() {
+    let parser = Parser::new(r#""\"\\\/\b\f\n\r\t""#);
+    let string = Value::String("\"\\/\u{8}\u{c}\n\r\t".into());
+    assert_eq!(Ok(Some(string)), parser.parse());
+}
+
+#[test]
+fn test_string_escaped_quote_in_object() {
+    let parser = Parser::new(r#"{"say \"hi\"": "a\"b"}"#);
+    let object = Value::object().insert("say \"hi\"", "a\"b");
+    assert_eq!(Ok(Some(object)), parser.parse());
+}
+
+#[test]
+fn test_string_invalid_escape() {
+    let parser = Parser::new(r#""\x""#);
+    assert_eq!(
+        Err(ParsingError::Tokenize(TokenizeError::InvalidEscape('x'))),
+        parser.parse()
+    );
+}
+
+#[test]
+fn test_string_unicode_escape() {
+    let parser = Parser::new(r#""\u0041\u00e9\u20AC""#);
+    assert_eq!(Ok(Some(Value::String("Aé€".into()))), parser.parse());
+}
+
+#[test]
+fn test_string_invalid_unicode_escape() {
+    let parser = Parser::new(r#""\u12G4""#);
+    assert_eq!(
+        Err(ParsingError::Tokenize(TokenizeError::InvalidUnicodeEscape(
+            "12G".into()
+        ))),
+        parser.parse()
+    );
+}
+
+#[test]
+fn test_string_surrogate_pair() {
+    let parser = Parser::new(r#""\uD83D\uDE00""#);
+    assert_eq!(Ok(Some(Value::String("😀".into()))), parser.parse());
+}
+
+#[test]
+fn test_string_lone_high_surrogate() {
+    let parser = Parser::new(r#""\uD83D""#);
+    assert_eq!(
+        Err(ParsingError::Tokenize(TokenizeError::LoneSurrogate(0xD83D))),
+        parser.parse()
+    );
+}
+
+#[test]
+fn test_string_lone_low_surrogate() {
+    let parser = Parser::new(r#""\uDE00""#);
+    assert_eq!(
+        Err(ParsingError::Tokenize(TokenizeError::LoneSurrogate(0xDE00))),
+        parser.parse()
+    );
+}
+
+#[test]
+fn test_string_high_surrogate_followed_by_non_surrogate() {
+    let parser = Parser::new(r#""\uD83D\u0041""#);
+    assert_eq!(
+        Err(ParsingError::Tokenize(TokenizeError::LoneSurrogate(0xD83D))),
+        parser.parse()
+    );
+}
+
+#[test]
+fn test_string_lone_surrogates_replaced() {
+    let parse = |input| {
+        Parser::new(input)
+            .lone_surrogates(LoneSurrogatePolicy::Replace)
+            .parse()
+    };
+    assert_eq!(
+        Ok(Some(Value::String("\u{FFFD}x".into()))),
+        parse(r#""\uD83Dx""#)
+    );
+    assert_eq!(
+        Ok(Some(Value::String("\u{FFFD}A".into()))),
+        parse(r#""\uD83D\u0041""#)
+    );
+    // A low surrogate before a high one is not a pair.
+    assert_eq!(
+        Ok(Some(Value::String("\u{FFFD}\u{FFFD}".into()))),
+        parse(r#""\uDE00\uD83D""#)
+    );
+    assert_eq!(
+        Ok(Some(Value::String("😀\u{FFFD}".into()))),
+        parse(r#""\uD83D\uDE00\uDE00""#)
+    );
+}
+
+#[test]
+fn test_nested_error_path() {
+    let parser = Parser::new(r#"{"pairs": [{"x0": 1}, {"x0": 2, "y1": nul}]}"#);
+    let path = KeyPath(vec![
+        PathSegment::Key("pairs".into()),
+        PathSegment::Index(1),
+        PathSegment::Key("y1".into()),
+    ]);
+    let error = ParsingError::Tokenize(TokenizeError::InvalidNull);
+    assert_eq!(
+        Err(ParsingError::Nested(path.clone(), Box::new(error))),
+        parser.parse()
+    );
+    assert_eq!(r#""pairs"[1]."y1""#, path.to_string());
+}
+
+#[test]
+fn test_nested_error_path_in_arrays() {
+    let parser = Parser::new("[[1], [2, [3, 4 5]]]");
+    let path = KeyPath(vec![PathSegment::Index(1), PathSegment::Index(1)]);
+    let error = ParsingError::TokenAfterValue(Token::Number(Number::U64(5)));
+    assert_eq!(
+        Err(ParsingError::Nested(path.clone(), Box::new(error))),
+        parser.parse()
+    );
+    assert_eq!("[1][1]", path.to_string());
+}
+
+#[test]
+fn test_serialize_compact() {
+    let value = Value::Array(vec![
+        Value::Null,
+        Value::Bool(true),
+        Value::Number(Number::F64(-12.5)),
+        Value::Number(Number::F64(3.0)),
+        Value::String("a \"quoted\"\n\\ \u{1}".into()),
+        Value::object().insert("one", 1.0),
+        Value::array(),
+        Value::object(),
+    ]);
+    assert_eq!(
+        r#"[null,true,-12.5,3.0,"a \"quoted\"\n\\ \u0001",{"one":1.0},[],{}]"#,
+        value.to_json()
+    );
+}
+
+#[test]
+fn test_serialize_pretty() {
+    let value = Value::object().insert("pairs", vec![Value::from(1.0), Value::array()]);
+    assert_eq!(
+        "{\n  \"pairs\": [\n    1.0,\n    []\n  ]\n}",
+        value.to_json_pretty(2)
+    );
+}
+
+#[test]
+fn test_display_tree() {
+    let value = value(r#"{"pairs": [{"x0": 1.5, "y0": null}, {"x0": 2}], "ok": [true, []]}"#);
+    let expected = [
+        "object {2}",
+        "  \"pairs\": array [2]",
+        "    [0]: object {2}",
+        "      \"x0\": 1.5",
+        "      \"y0\": null",
+        "    [1]: object {1}",
+        "      \"x0\": 2",
+        "  \"ok\": array [2]",
+        "    [0]: true",
+        "    [1]: array [0]",
+    ];
+    assert_eq!(expected.join("\n") + "\n", value.display_tree().to_string());
+
+    let shallow = value.display_tree().max_depth(1).max_items(1).to_string();
+    assert_eq!(
+        "object {2}\n  \"pairs\": array [2]\n  ... 1 more\n",
+        shallow
+    );
+}
+
+#[test]
+fn test_display_tree_truncates_strings() {
+    let long = "é".repeat(50);
+    let tree = Value::String(long).display_tree().to_string();
+    assert_eq!(format!("\"{}\"... (50 characters)\n", "é".repeat(40)), tree);
+}
+
+#[test]
+fn test_serialize_non_finite_as_null() {
+    let value = Value::Array(vec![
+        Value::Number(Number::F64(f64::INFINITY)),
+        Value::Number(Number::F64(f64::NAN)),
+    ]);
+    assert_eq!("[null,null]", value.to_json());
+}
+
+#[test]
+fn test_serialize_round_trip() {
+    let input = r#"{"pairs": [{"x0": 1.25, "y0": -0.1}, [true, null, "é\t"]], "radius": 6372.8}"#;
+    let value = Parser::new(input).parse().unwrap().unwrap();
+    let reparsed = Parser::new(&value.to_json()).parse().unwrap().unwrap();
+    assert_eq!(value, reparsed);
+}
+
+/// A pairs file as written by the generator, with `indent=2`.
+const GENERATED: &str = r#"{
+  "pairs": [
+    {
+      "x0": -131.62887211953557,
+      "y0": 62.53807264870187,
+      "x1": 94.95886283158103,
+      "y1": -44.0875753669041
+    },
+    {
+      "x0": -1.6433686469012514,
+      "y0": -9.091608338027143,
+      "x1": 54.573470180194676,
+      "y1": 51.97020320439236
+    }
+  ],
+  "avg_dist": 12531.143784137957,
+  "radius": 6372.8
+}"#;
+
+#[test]
+fn test_serialize_generated_file_byte_identical() {
+    let value = Parser::new(GENERATED).parse().unwrap().unwrap();
+    assert_eq!(GENERATED, value.to_json_pretty(2));
+}
+
+#[test]
+fn test_serialize_keeps_key_order() {
+    let input = r#"{"z":1,"a":2,"m":{"y":true,"b":null},"k":[]}"#;
+    let value = Parser::new(input).parse().unwrap().unwrap();
+    assert_eq!(input, value.to_json());
+}
+
+#[test]
+fn test_serialize_is_stable_across_round_trips() {
+    let first = Parser::new(GENERATED).parse().unwrap().unwrap().to_json();
+    let second = Parser::new(&first).parse().unwrap().unwrap().to_json();
+    assert_eq!(first, second);
+}
+
+#[test]
+fn test_map_large_object() {
+    let keys: Vec<String> = (0..20).rev().map(|i| format!("key{i}")).collect();
+    let map: Map = keys
+        .iter()
+        .map(|key| (key.clone(), Value::from(key.as_str())))
+        .collect();
+
+    assert_eq!(20, map.len());
+    for key in &keys {
+        assert_eq!(Some(&Value::from(key.as_str())), map.get(key));
+    }
+    let order: Vec<&String> = map.iter().map(|(key, _)| key).collect();
+    assert_eq!(keys.iter().collect::<Vec<_>>(), order);
+}
+
+#[test]
+fn test_map_insert_existing_keeps_position() {
+    let mut map = Map::new();
+    map.insert("one".into(), Value::Number(Number::F64(1.0)));
+    map.insert("two".into(), Value::Number(Number::F64(2.0)));
+    assert_eq!(
+        Some(Value::Number(Number::F64(1.0))),
+        map.insert("one".into(), Value::Number(Number::F64(3.0)))
+    );
+    assert_eq!(r#"{"one":3.0,"two":2.0}"#, Value::Object(map).to_json());
+}
+
+#[test]
+fn test_map_equality_ignores_order() {
+    let a = Value::object().insert("one", 1.0).insert("two", 2.0);
+    let b = Value::object().insert("two", 2.0).insert("one", 1.0);
+    assert_eq!(a, b);
+    assert_ne!(a, Value::object().insert("one", 1.0));
+}
+
+/// Feeds the input in chunks of `size` bytes and finishes.
+fn feed_in_chunks(input: &str, size: usize) -> Result<Vec<Event>, ParsingError> {
+    let mut parser = FeedParser::new();
+    let mut events = Vec::new();
+    for chunk in input.as_bytes().chunks(size) {
+        events.extend(parser.feed(chunk)?);
+    }
+    events.extend(parser.finish()?);
+    Ok(events)
+}
+
+#[test]
+fn test_feed_events() {
+    let input = r#"{"pairs": [{"x0": -1.5e2, "ok": true}, null], "name": "caf\u00e9"}"#;
+    let expected = vec![
+        Event::StartObject,
+        Event::Key("pairs".into()),
+        Event::StartArray,
+        Event::StartObject,
+        Event::Key("x0".into()),
+        Event::Number(Number::F64(-150.0)),
+        Event::Key("ok".into()),
+        Event::Bool(true),
+        Event::EndObject,
+        Event::Null,
+        Event::EndArray,
+        Event::Key("name".into()),
+        Event::String("café".into()),
+        Event::EndObject,
+    ];
+    for size in 1..=input.len() {
+        assert_eq!(
+            Ok(expected.clone()),
+            feed_in_chunks(input, size),
+            "chunks of {size}"
+        );
+    }
+}
+
+#[test]
+fn test_feed_spanned() {
+    let input = "\u{feff}{\"a\": [1, \"x\"],\n \"b\": 23}";
+    let at = |token| input.find(token).unwrap();
+    let expected = vec![
+        (at("{"), Event::StartObject),
+        (at("\"a\""), Event::Key("a".into())),
+        (at("["), Event::StartArray),
+        (at("1"), Event::Number(Number::U64(1))),
+        (at("\"x\""), Event::String("x".into())),
+        (at("]"), Event::EndArray),
+        (at("\"b\""), Event::Key("b".into())),
+        (at("23"), Event::Number(Number::U64(23))),
+        (at("}"), Event::EndObject),
+    ];
+    for size in 1..=input.len() {
+        let mut parser = FeedParser::new();
+        let mut events = Vec::new();
+        for chunk in input.as_bytes().chunks(size) {
+            events.extend(parser.feed_spanned(chunk).unwrap());
+        }
+        events.extend(parser.finish_spanned().unwrap());
+        assert_eq!(expected, events, "chunks of {size}");
+    }
+}
+
+#[test]
+fn test_feed_split_character() {
+    let input = "[\"\u{1F600}\"]";
+    let expected = vec![
+        Event::StartArray,
+        Event::String("\u{1F600}".into()),
+        Event::EndArray,
+    ];
+    assert_eq!(Ok(expected), feed_in_chunks(input, 1));
+}
+
+#[test]
+fn test_feed_top_level_number_completed_by_finish() {
+    let mut parser = FeedParser::new();
+    assert_eq!(Ok(vec![]), parser.feed(b" 12"));
+    assert_eq!(Ok(vec![]), parser.feed(b"3"));
+    assert_eq!(Ok(vec![Event::Number(Number::U64(123))]), parser.finish());
+}
+
+#[test]
+fn test_feed_errors() {
+    assert_eq!(Err(ParsingError::TrailingComma), feed_in_chunks("[1,]", 1));
+    assert_eq!(
+        Err(ParsingError::MissingColon),
+        feed_in_chunks(r#"{"a" 1}"#, 2)
+    );
+    assert_eq!(Err(ParsingError::ExtraData), feed_in_chunks("[] []", 3));
+    assert_eq!(
+        Err(ParsingError::ReachedEOF(Token::OpenBrace)),
+        feed_in_chunks(r#"{"a":"#, 1)
+    );
+    assert_eq!(
+        Err(ParsingError::Tokenize(TokenizeError::InvalidTrue)),
+        feed_in_chunks("[tru]", 1)
+    );
+    assert_eq!(
+        Err(ParsingError::Tokenize(TokenizeError::InvalidUtf8)),
+        FeedParser::new().feed(b"[\"\xff\"]")
+    );
+}
+
+#[test]
+fn test_feed_empty_input() {
+    assert_eq!(Ok(vec![]), feed_in_chunks("  \n", 1));
+}
+
+#[test]
+fn test_token_spans() {
+    let input = " [true, \"a\\u0062\"]";
+    let spans: Vec<_> = tokens(input)
+        .map(|spanned| spanned.unwrap().1.source(input))
+        .collect();
+    assert_eq!(vec!["[", "true", ",", "\"a\\u0062\"", "]"], spans);
+}
+
+#[test]
+fn test_tokens_stop_at_error() {
+    let tokens: Vec<_> = tokens("[tru, 1]").collect();
+    assert_eq!(2, tokens.len());
+    assert_eq!(Err(TokenizeError::InvalidTrue), tokens[1]);
+}
+
+#[test]
+fn test_tokenizer_iterator_options() {
+    let input = r#"["\ud800", 1.50]"#;
+    let tokens: Vec<_> = Tokenizer::new(input)
+        .lone_surrogates(LoneSurrogatePolicy::Replace)
+        .raw_numbers(true)
+        .map(|spanned| spanned.unwrap().0)
+        .collect();
+    assert_eq!(Token::String("\u{fffd}".into()), tokens[1]);
+    assert_eq!(Token::Number(Number::Raw("1.50".into())), tokens[3]);
+}
+
+#[test]
+fn test_tokenizer_fused_after_error() {
+    let mut tokenizer = Tokenizer::new("[@, 1]");
+    assert!(tokenizer.next().unwrap().is_ok());
+    assert_eq!(
+        Some(Err(TokenizeError::UnexpectedChar('@'))),
+        tokenizer.next()
+    );
+    assert_eq!(None, tokenizer.next());
+    assert_eq!(None, tokenizer.next());
+}
+
+#[test]
+fn test_tokenizer_peek_then_next() {
+    let mut tokenizer = Tokenizer::new(r#"  "key": 1"#);
+    assert_eq!(Ok(&Token::String("key".into())), tokenizer.peek_next());
+    assert_eq!(2, tokenizer.offset());
+    assert_eq!(r#""key": 1"#, tokenizer.rest());
+    assert_eq!(
+        (Ok(Token::String("key".into())), Span::new(2, 7)),
+        tokenizer.next_with_span()
+    );
+    assert_eq!(Ok(Token::Colon), tokenizer.next_token());
+}
+
+#[test]
+fn test_tokenizer_skips_past_peeked_error() {
+    let mut tokenizer = Tokenizer::new("[nul, 2]");
+    assert_eq!(Ok(Token::OpenBracket), tokenizer.next_token());
+    assert_eq!(Err(TokenizeError::InvalidNull), tokenizer.peek_next());
+    tokenizer.skip_to_delimiter();
+    assert_eq!(Ok(Token::Comma), tokenizer.next_token());
+    assert_eq!(Ok(Token::Number(Number::U64(2))), tokenizer.next_token());
+}
+
+#[test]
+fn test_parse_with_spans() {
+    let input = r#"{"pairs": [ {"x0": -1.5} ], "x0": null}"#;
+    let value = Parser::new(input).parse_with_spans().unwrap().unwrap();
+    assert_eq!(Span::new(0, input.len()), value.span);
+
+    let SpannedKind::Object(members) = &value.kind else {
+        panic!("Expected an object, got {value:?}");
+    };
+    assert_eq!("\"pairs\"", members[0].key_span.source(input));
+    assert_eq!("[ {\"x0\": -1.5} ]", members[0].value.span.source(input));
+    let SpannedKind::Array(items) = &members[0].value.kind else {
+        panic!("Expected an array, got {:?}", members[0].value);
+    };
+    let SpannedKind::Object(pair) = &items[0].kind else {
+        panic!("Expected an object, got {:?}", items[0]);
+    };
+    assert_eq!("-1.5", pair[0].value.span.source(input));
+    assert_eq!("null", members[1].value.span.source(input));
+
+    assert_eq!(Parser::new(input).parse().unwrap(), Some(value.to_value()));
+}
+
+#[test]
+fn test_parse_with_spans_keeps_duplicate_keys() {
+    let value = Parser::new(r#"{"a": 1, "a": 2}"#)
+        .parse_with_spans()
+        .unwrap()
+        .unwrap();
+    let SpannedKind::Object(members) = &value.kind else {
+        panic!("Expected an object, got {value:?}");
+    };
+    assert_eq!(2, members.len());
+    assert_eq!(Value::object().insert("a", 2u64), value.to_value());
+}
+
+#[test]
+fn test_parse_with_spans_errors() {
+    let parse = |input| Parser::new(input).parse_with_spans();
+    assert_eq!(Err(ParsingError::TrailingComma), parse("[1,]"));
+    assert_eq!(Err(ParsingError::ExtraData), parse("1 2"));
+    assert_eq!(
+        Err(ParsingError::ReachedEOF(Token::OpenBrace)),
+        parse(r#"{"a":"#)
+    );
+}
+
+#[test]
+fn test_sort_keys() {
+    let mut value = Parser::new(r#"{"b": [{"d": 1, "c": 2}], "a": null}"#)
+        .parse()
+        .unwrap()
+        .unwrap();
+    value.sort_keys();
+    assert_eq!(r#"{"a":null,"b":[{"c":2,"d":1}]}"#, value.to_json());
+}
+
+#[test]
+fn test_sort_keys_large_object() {
+    let mut map: Map = (0..20)
+        .rev()
+        .map(|i| (format!("k{i:02}"), Value::from(i as u64)))
+        .collect();
+    map.sort_keys();
+    assert_eq!(Some("k00"), map.iter().next().map(|(k, _)| k.as_str()));
+    assert_eq!(Some(&Value::Number(Number::U64(13))), map.get("k13"));
+}
+
+#[test]
+fn test_number_grammar() {
+    for valid in ["0", "-0", "12", "1.5", "-0.25e-3", "1E+2", "10e5"] {
+        assert!(Parser::new(valid).parse().is_ok(), "{valid}");
+    }
+    for invalid in [
+        "-inf", "-nan", "01", "-", "1.", "-.5", "1e", "1e+", "0x10", "1.5.2",
+    ] {
+        assert_eq!(
+            Err(ParsingError::Tokenize(TokenizeError::InvalidNumber(
+                invalid.into()
+            ))),
+            Parser::new(invalid).parse(),
+        );
+    }
+}
+
+#[test]
+fn test_number_integer_and_float() {
+    let value = Parser::new("[3, 3.0, -3, -0, 1e2]")
+        .parse()
+        .unwrap()
+        .unwrap();
+    let numbers = [
+        Number::U64(3),
+        Number::F64(3.0),
+        Number::I64(-3),
+        Number::F64(-0.0),
+        Number::F64(100.0),
+    ];
+    assert_eq!(Value::Array(numbers.map(Value::Number).to_vec()), value);
+    assert_ne!(Value::from(3u64), Value::from(3.0));
+    assert_eq!("[3,3.0,-3,-0.0,100.0]", value.to_json());
+}
+
+#[test]
+fn test_number_64_bit_integers_exact() {
+    let input = format!("[{},{}]", u64::MAX, i64::MIN);
+    let value = Parser::new(&input).parse().unwrap().unwrap();
+    assert_eq!(Value::from(u64::MAX), value[0]);
+    assert_eq!(Value::from(i64::MIN), value[1]);
+    assert_eq!(input, value.to_json());
+
+    let overflow = Parser::new("18446744073709551616").parse();
+    assert_eq!(Ok(Some(Value::from(2f64.powi(64)))), overflow);
+}
+
+#[test]
+fn test_number_accessors() {
+    assert_eq!(Some(-3), Number::I64(-3).as_i64());
+    assert_eq!(None, Number::I64(-3).as_u64());
+    assert_eq!(None, Number::U64(u64::MAX).as_i64());
+    assert_eq!(None, Number::F64(3.0).as_i64());
+    assert_eq!(3.0, Number::U64(3).as_f64());
+    assert_eq!(Number::U64(3), Number::from(3i64));
+}
+
+#[test]
+fn test_object_value_reaches_eof() {
+    assert_eq!(
+        Err(ParsingError::ReachedEOF(Token::OpenBrace)),
+        Parser::new(r#"{"a":"#).parse()
+    );
+}
+
+#[test]
+fn test_max_depth() {
+    let nested = |depth| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+    assert!(Parser::new(&nested(128)).parse().is_ok());
+    assert!(matches!(
+        Parser::new(&nested(129)).parse(),
+        Err(ParsingError::Nested(_, error)) if *error == ParsingError::TooDeep
+    ));
+    assert!(Parser::new(&nested(129)).max_depth(200).parse().is_ok());
+    assert_eq!(
+        Err(ParsingError::TooDeep),
+        FeedParser::new().feed(nested(129).as_bytes())
+    );
+}
+
+#[test]
+fn test_feed_invalid_utf8_after_error() {
+    let input = b"[x, \xff]";
+    assert_eq!(
+        Err(ParsingError::Tokenize(TokenizeError::UnexpectedChar('x'))),
+        FeedParser::new().feed(input)
+    );
+}
+
+/// An error with the line and column it was found at.
+type LocatedError = (ParsingError, (usize, usize));
+
+/// Parses with error recovery, returning the errors with their positions.
+fn recover(input: &str) -> (Option<Value>, Vec<LocatedError>) {
+    let (value, diagnostics) = Parser::new(input).parse_recovering();
+    let errors = diagnostics
+        .into_iter()
+        .map(|diagnostic: Diagnostic| {
+            let position = diagnostic.line_column(input);
+            (diagnostic.error, position)
+        })
+        .collect();
+    (value, errors)
+}
+
+#[test]
+fn test_recover_valid_document() {
+    let input = r#"{"pairs": [{"x0": 1.5}], "radius": 6372.8}"#;
+    assert_eq!(
+        (Parser::new(input).parse().unwrap(), vec![]),
+        recover(input)
+    );
+}
+
+#[test]
+fn test_recover_collects_every_error() {
+    let input =
+        "{\"pairs\": [\n  {\"x0\": tru, \"y0\": 1},\n  {\"x0\" 1, \"y0\": 2,}\n], \"radius\": 01}";
+    let (value, errors) = recover(input);
+    assert_eq!(
+        vec![
+            (ParsingError::Tokenize(TokenizeError::InvalidTrue), (2, 10)),
+            (ParsingError::MissingColon, (3, 9)),
+            (ParsingError::TrailingComma, (3, 19)),
+            (
+                ParsingError::Tokenize(TokenizeError::InvalidNumber("01".into())),
+                (4, 14)
+            ),
+        ],
+        errors
+    );
+
+    let pair = |x0: Option<u64>, y0: u64| {
+        let mut pair = Value::object();
+        if let Some(x0) = x0 {
+            pair = pair.insert("x0", x0);
+        }
+        pair.insert("y0", y0)
+    };
+    let expected =
+        Value::object().insert("pairs", Value::Array(vec![pair(None, 1), pair(Some(1), 2)]));
+    assert_eq!(Some(expected), value);
+}
+
+#[test]
+fn test_recover_missing_comma_and_bracket() {
+    let (value, errors) = recover("[1 2, [3, 4}");
+    assert_eq!(
+        vec![
+            (
+                ParsingError::TokenAfterValue(Token::Number(Number::U64(2))),
+                (1, 4)
+            ),
+            (ParsingError::TokenAfterValue(Token::CloseBrace), (1, 12)),
+            (ParsingError::ReachedEOF(Token::OpenBracket), (1, 13)),
+        ],
+        errors
+    );
+    assert_eq!(
+        Some(Value::Array(vec![
+            1u64.into(),
+            2u64.into(),
+            Value::Array(vec![3u64.into(), 4u64.into()])
+        ])),
+        value
+    );
+}
+
+#[test]
+fn test_recover_unclosed_object_in_array() {
+    let (value, errors) = recover(r#"[{"a": 1, {"a": 2}]"#);
+    assert_eq!(
+        vec![(ParsingError::StartingToken(Token::OpenBrace), (1, 11))],
+        errors
+    );
+    let object = |a: u64| Value::object().insert("a", a);
+    assert_eq!(Some(Value::Array(vec![object(1), object(2)])), value);
+}
+
+#[test]
+fn test_recover_extra_data() {
+    assert_eq!(
+        (Some(Value::Null), vec![(ParsingError::ExtraData, (2, 1))]),
+        recover("null\n]")
+    );
+}
+
+#[test]
+fn test_raw_numbers_round_trip_unchanged() {
+    let input = r#"{"a":1.50,"b":-0,"c":1E+2,"d":123456789012345678901234567890,"e":1e400}"#;
+    let value = Parser::new(input)
+        .raw_numbers(true)
+        .parse()
+        .unwrap()
+        .unwrap();
+    assert_eq!(Value::Number(Number::Raw("1.50".into())), value["a"]);
+    assert_eq!(input, value.to_json());
+
+    let mut parser = FeedParser::new().raw_numbers(true);
+    let mut events = parser.feed(b"[1.5").unwrap();
+    events.extend(parser.feed(b"0]").unwrap());
+    let number = Number::Raw("1.50".into());
+    assert_eq!(
+        vec![Event::StartArray, Event::Number(number), Event::EndArray],
+        events
+    );
+}
+
+#[test]
+fn test_raw_numbers_converted_on_access() {
+    let raw = |literal: &str| Number::Raw(literal.into());
+    assert_eq!(1.5, raw("1.50").as_f64());
+    assert_eq!(Some(-3), raw("-3").as_i64());
+    assert_eq!(Some(u64::MAX), raw("18446744073709551615").as_u64());
+    assert!(raw("3.0").is_f64());
+    assert_eq!(Number::F64(1.5), raw("1.50"));
+    assert_eq!(raw("100"), raw("100"));
+    assert_ne!(raw("100"), raw("1e2"));
+}
+
+#[test]
+fn test_tape_navigates_without_building() {
+    let input = r#"{"a": [1, {"b": [true, null]}, "s"], "c": -2.5e1}"#;
+    let tape = Parser::new(input).parse_tape().unwrap().unwrap();
+    assert_eq!(12, tape.len());
+    let a = tape.get("a").unwrap();
+    assert!(a.is_array());
+    assert_eq!(3, a.iter().count());
+    assert_eq!(Some(Number::U64(1)), a.at(0).unwrap().as_number());
+    let b = a.at(1).unwrap().get("b").unwrap();
+    assert_eq!(Some(true), b.at(0).unwrap().as_bool());
+    assert!(b.at(1).unwrap().is_null());
+    assert_eq!(None, b.at(2).map(|c| c.is_null()));
+    assert_eq!("s", a.at(2).unwrap().as_str().unwrap());
+    assert_eq!(Some(-25.0), tape.get("c").unwrap().as_f64());
+    assert!(tape.get("missing").is_none());
+    assert!(a.get("b").is_none());
+    assert_eq!(0, tape.root().iter().count());
+}
+
+#[test]
+fn test_tape_strings_borrowed_unless_escaped() {
+    use std::borrow::Cow;
+    let input = r#"{"plain": "text", "esc\u0061ped": "a\nb😀"}"#;
+    let tape = Parser::new(input).parse_tape().unwrap().unwrap();
+    let keys: Vec<_> = tape.root().members().map(|(key, _)| key).collect();
+    assert!(matches!(keys[0], Cow::Borrowed("plain")));
+    assert!(matches!(&keys[1], Cow::Owned(key) if key == "escaped"));
+    assert!(matches!(
+        tape.get("plain").unwrap().as_str(),
+        Some(Cow::Borrowed("text"))
+    ));
+    assert_eq!("a\nb😀", tape.get("escaped").unwrap().as_str().unwrap());
+}
+
+#[test]
+fn test_tape_to_value_matches_parse() {
+    let input = r#"[{"x0": 1, "y0": 2.5, "k": [[], {}, "\"q\""]}, -0, 1e400, false]"#;
+    let tape = Parser::new(input).parse_tape().unwrap().unwrap();
+    let parser = Parser::new(input).out_of_range(NumberRangePolicy::KeepInfinite);
+    assert_eq!(parser.parse().unwrap().unwrap(), tape.root().to_value());
+}
+
+#[test]
+fn test_tape_duplicate_keys_first_wins() {
+    let input = r#"{"k": 1, "k": 2}"#;
+    let tape = Parser::new(input).parse_tape().unwrap().unwrap();
+    assert_eq!(Some(1.0), tape.get("k").unwrap().as_f64());
+    assert_eq!(2, tape.root().members().count());
+    let first = Parser::new(input).duplicate_keys(DuplicateKeyPolicy::FirstWins);
+    assert_eq!(first.parse().unwrap().unwrap(), tape.root().to_value());
+}
+
+#[test]
+fn test_tape_errors() {
+    assert!(Parser::new(" ").parse_tape().unwrap().is_none());
+    for input in [
+        "[1,]",
+        "[1 2]",
+        r#"{"a" 1}"#,
+        r#"{"a": [1, {"b": tru}]}"#,
+        r#"{1: 2}"#,
+        "[01]",
+        r#"["\x"]"#,
+        r#"["\ud800"]"#,
+        "[] []",
+        "[",
+        "]",
+    ] {
+        assert_eq!(
+            Parser::new(input).parse_with_spans().unwrap_err(),
+            Parser::new(input).parse_tape().unwrap_err(),
+            "{input}"
+        );
+    }
+    let deep = "[".repeat(200) + &"]".repeat(200);
+    assert!(Parser::new(&deep).parse_tape().is_err());
+    assert!(Parser::new(&deep).max_depth(200).parse_tape().is_ok());
+}
+
+/// Offsets where tokens start, found byte by byte.
+fn naive_structural_indices(input: &str) -> Vec<u32> {
+    let (mut in_string, mut escaped, mut scalar) = (false, false, false);
+    let mut indices = Vec::new();
+    for (i, &byte) in input.as_bytes().iter().enumerate() {
+        let starts = if in_string {
+            let closes = byte == b'"' && !escaped;
+            escaped = byte == b'\\' && !escaped;
+            in_string = !closes;
+            closes
+        } else {
+            let starts = match byte {
+                b'"' if !escaped => {
+                    in_string = true;
+                    scalar = false;
+                    true
+                }
+                b'{' | b'}' | b'[' | b']' | b',' | b':' | b' ' | b'\t' | b'\n' | b'\r' => {
+                    scalar = false;
+                    !byte.is_ascii_whitespace()
+                }
+                _ => !std::mem::replace(&mut scalar, true),
+            };
+            escaped = byte == b'\\' && !escaped;
+            starts
+        };
+        if starts {
+            indices.push(i as u32);
+        }
+    }
+    indices
+}
+
+#[test]
+fn test_structural_indices() {
+    assert_eq!(
+        vec![0, 1, 3, 4, 5, 6, 7, 9, 12, 13],
+        structural_indices(r#"{"a":[1, tru]}"#, Simd::Scalar)
+    );
+    let mut inputs = vec![
+        String::new(),
+        r#"{"a": "b\"c", "d\\": [1, -2.5e3, true, null], "\\\"": {}}"#.to_string(),
+        r#"["\"#.to_string(),
+        "1 2\"x\" \\\"".to_string(),
+    ];
+    // Runs of backslashes and strings crossing the edges of the blocks.
+    for offset in 55..70 {
+        for run in 0..6 {
+            let backslashes = "\\".repeat(run);
+            inputs.push(format!(
+                "{}\"{backslashes}\" , 12 \"é\":[]",
+                " ".repeat(offset)
+            ));
+            inputs.push(format!("[{}{backslashes}\"x\", 3]", "1".repeat(offset)));
+        }
+    }
+    for input in &inputs {
+        for simd in Simd::available() {
+            assert_eq!(
+                naive_structural_indices(input),
+                structural_indices(input, simd),
+                "{simd:?} {input:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_structural_scan_tape_matches_tokenizer() {
+    let long_string = format!(
+        r#"{{"{}": ["{}\n", 1.5e-3, {{"k\"\\": false}}]}}"#,
+        "k".repeat(70),
+        "v".repeat(130)
+    );
+    for input in [
+        long_string.as_str(),
+        r#"[{"x0": 1, "y0": 2.5, "k": [[], {}, "\"q\""]}, -0, 1e400, false]"#,
+        " \t\n[ null ,true ] ",
+        "[1,]",
+        "[1 2]",
+        "[1x]",
+        "[1\u{0}]",
+        "[tru]",
+        "[truex]",
+        r#"{"a" 1}"#,
+        r#"{"a": [1, {"b": tru}]}"#,
+        r#"{1: 2}"#,
+        "[01]",
+        r#"["\x"]"#,
+        r#"["\ud800"]"#,
+        r#"["abc"#,
+        r#"["ab\"#,
+        "[] []",
+        "[",
+        "]",
+        "",
+    ] {
+        let expected = Parser::new(input).parse_tape();
+        for simd in Simd::available() {
+            let tape = Parser::new(input).structural_scan(simd).parse_tape();
+            match (&expected, &tape) {
+                (Ok(Some(expected)), Ok(Some(tape))) => {
+                    assert_eq!(expected.len(), tape.len(), "{simd:?} {input}");
+                    assert_eq!(
+                        expected.root().to_value(),
+                        tape.root().to_value(),
+                        "{simd:?} {input}"
+                    );
+                }
+                (Ok(None), Ok(None)) => {}
+                (Err(expected), Err(error)) => assert_eq!(expected, error, "{simd:?} {input}"),
+                _ => panic!(
+                    "{simd:?} {input}: {:?}",
+                    tape.map(|tape| tape.map(|tape| tape.len()))
+                ),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_iter_array_at_yields_elements_and_document() {
+    let input = r#"{"radius": 1, "pairs": [{"x": 1}, [2], "three"], "avg": [4]}"#;
+    let mut values = Parser::new(input).iter_array_at("/pairs");
+    let elements: Vec<_> = values.by_ref().map(Result::unwrap).collect();
+    let Some(Value::Object(mut expected)) = parse(input).unwrap() else {
+        panic!("Expected an object");
+    };
+    let Some(Value::Array(pairs)) = expected.insert("pairs".into(), Value::Array(Vec::new()))
+    else {
+        panic!("Expected pairs");
+    };
+    assert_eq!(pairs, elements);
+    assert_eq!(Some(&Value::Object(expected)), values.document());
+
+    let input = r#"[0, {"a/b": {"~": [[], [1, 2]]}}, 3]"#;
+    let elements: Vec<_> = Parser::new(input)
+        .iter_array_at("/1/a~1b/~0/1")
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(Some(Value::Array(elements)), parse("[1, 2]").unwrap());
+    let mut empty = Parser::new("[]").iter_array_at("");
+    assert!(empty.next().is_none());
+    assert_eq!(Some(Value::Array(Vec::new())), empty.into_document());
+}
+
+#[test]
+fn test_iter_array_at_errors() {
+    for (input, pointer) in [
+        (r#"{"pairs": [1, tru]}"#, "/pairs"),
+        (r#"{"a": {"b": [1, {"c": x}]}}"#, "/a/b"),
+        (r#"{"pairs": [1,]}"#, "/pairs"),
+        (r#"{"pairs": [1], "radius": tru}"#, "/pairs"),
+        (r#"{"radius": tru, "pairs": []}"#, "/pairs"),
+        (r#"{"pairs": [], "pairs": []}"#, "/pairs"),
+        (r#"{"pairs": [1]} 2"#, "/pairs"),
+        (r#"{"pairs": [1"#, "/pairs"),
+    ] {
+        let error = Parser::new(input)
+            .iter_array_at(pointer)
+            .find_map(Result::err)
+            .unwrap();
+        assert_eq!(parse(input).unwrap_err(), error, "{input}");
+    }
+    for (input, pointer) in [
+        (r#"{"pairs": {}}"#, "/pairs"),
+        (r#"{"other": []}"#, "/pairs"),
+        ("[[]]", "/1"),
+        ("[[]]", "/x"),
+        ("[]", "pairs"),
+    ] {
+        let mut values = Parser::new(input).iter_array_at(pointer);
+        let error = ParsingError::NoArrayAt(pointer.into());
+        assert_eq!(Some(Err(error)), values.next(), "{input}");
+        assert!(values.next().is_none());
+    }
+    let deep = "[".repeat(200) + &"]".repeat(200);
+    let mut values = Parser::new(&deep).iter_array_at("");
+    assert!(values.any(|value| value.is_err()));
+}
+
+#[test]
+fn test_iter_array_at_cancelled() {
+    let input = format!("{{\"pairs\": [{}0]}}", "0, ".repeat(999));
+    let token = CancelToken::new();
+    let mut values = Parser::new(&input)
+        .iter_array_at("/pairs")
+        .cancel(token.clone());
+    assert_eq!(100, values.by_ref().take(100).filter(Result::is_ok).count());
+    token.cancel();
+    // The token is checked every few elements, not at every one.
+    let read = values.by_ref().take_while(Result::is_ok).count();
+    assert!(read < 900, "{read}");
+    assert!(values.next().is_none());
+    assert!(values.document().is_none());
+
+    let values = Parser::new(&input)
+        .iter_array_at("/pairs")
+        .cancel(CancelToken::new());
+    assert_eq!(1000, values.filter(Result::is_ok).count());
+}
+
+/// Cases named as in JSONTestSuite: `y_` inputs must parse, `n_` inputs
+/// must not.
+const STRING_CASES: &[(&str, &str)] = &[
+    ("y_string_allowed_escapes", r#"["\"\\\/\b\f\n\r\t"]"#),
+    ("y_string_escaped_control_character", r#"["\u0012"]"#),
+    ("y_string_escaped_noncharacter", r#"["\uFFFF"]"#),
+    ("y_string_accepted_surrogate_pair", r#"["\uD801\udc37"]"#),
+    ("y_string_utf8", "[\"\u{20ac}\u{1d11e}\"]"),
+    ("y_string_unescaped_char_delete", "[\"\u{7f}\"]"),
+    ("y_string_space", r#"" ""#),
+    ("n_string_unescaped_newline", "[\"new\nline\"]"),
+    ("n_string_unescaped_tab", "[\"\t\"]"),
+    ("n_string_unescaped_ctrl_char", "[\"a\u{0}a\"]"),
+    ("n_string_unescaped_in_key", "{\"a\u{1f}\": 1}"),
+    ("n_string_escape_x", r#"["\x00"]"#),
+    ("n_string_backslash_00", "[\"\\\u{0}\"]"),
+    ("n_string_single_quote", "['single quote']"),
+    ("n_string_unicode_CapitalU", r#""\UA66D""#),
+    ("n_string_incomplete_escape", r#"["\"]"#),
+];
+
+/// Parses `input` with the value, tape, structural scan and feed parsers,
+/// checking they agree on whether it is valid.
+fn parses_everywhere(input: &str, allow_control_chars: bool) -> bool {
+    let parser = || Parser::new(input).allow_control_chars(allow_control_chars);
+    let parsed = parser().parse().is_ok();
+    let tape = parser().parse_tape().is_ok();
+    let scanned = parser()
+        .structural_scan(Simd::detect())
+        .parse_tape()
+        .is_ok();
+    let mut feed = FeedParser::new().allow_control_chars(allow_control_chars);
+    let fed = feed
+        .feed(input.as_bytes())
+        .and_then(|_| feed.finish())
+        .is_ok();
+    assert_eq!(
+        [parsed; 3],
+        [tape, scanned, fed],
+        "The parsers disagree on {input:?}"
+    );
+    parsed
+}
+
+#[test]
+fn test_string_cases() {
+    for (name, input) in STRING_CASES {
+        assert_eq!(
+            name.starts_with("y_"),
+            parses_everywhere(input, false),
+            "{name}"
+        );
+    }
+}
+
+#[test]
+fn test_unescaped_control_chars() {
+    assert_eq!(
+        Err(ParsingError::Tokenize(TokenizeError::ControlChar('\n'))),
+        Parser::new("\"new\nline\"").parse()
+    );
+    for name in ["n_string_unescaped_newline", "n_string_unescaped_tab"] {
+        let (_, input) = STRING_CASES.iter().find(|(case, _)| *case == name).unwrap();
+        assert!(parses_everywhere(input, true), "{name}");
+    }
+    let tape = Parser::new("[\"a\u{0}b\\u0062\"]")
+        .allow_control_chars(true)
+        .parse_tape()
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        Value::Array(vec![Value::String("a\u{0}bb".into())]),
+        tape.root().to_value()
+    );
+}
+
+#[test]
+fn test_bom_skipped() {
+    let input = "\u{feff}[1]";
+    let expected = Value::Array(vec![Value::Number(Number::U64(1))]);
+    assert_eq!(Ok(Some(expected.clone())), Parser::new(input).parse());
+    let tape = Parser::new(input).parse_tape().unwrap().unwrap();
+    assert_eq!(expected, tape.root().to_value());
+    for size in 1..=4 {
+        assert_eq!(
+            Ok(vec![
+                Event::StartArray,
+                Event::Number(Number::U64(1)),
+                Event::EndArray
+            ]),
+            feed_in_chunks(input, size),
+            "chunks of {size}"
+        );
+    }
+
+    let (_, with_bom) = Parser::new("\u{feff}[1,]").parse_recovering();
+    let (_, without) = Parser::new("[1,]").parse_recovering();
+    assert_eq!(
+        without[0].line_column("[1,]"),
+        with_bom[0].line_column("\u{feff}[1,]")
+    );
+}
+
+#[test]
+fn test_detect_encoding() {
+    let cases: [(&[u8], Encoding); 9] = [
+        (b"{}", Encoding::Utf8),
+        (b"\xef\xbb\xbf{}", Encoding::Utf8),
+        (b"\xff\xfe{\0}\0", Encoding::Utf16Le),
+        (b"\xfe\xff\0{\0}", Encoding::Utf16Be),
+        (b"{\0}\0", Encoding::Utf16Le),
+        (b"\0{\0}", Encoding::Utf16Be),
+        (b"\xff\xfe\0\0{\0\0\0", Encoding::Utf32Le),
+        (b"\0\0\0{", Encoding::Utf32Be),
+        (b"1", Encoding::Utf8),
+    ];
+    for (bytes, encoding) in cases {
+        assert_eq!(encoding, detect_encoding(bytes), "{bytes:?}");
+    }
+}
+
+#[test]
+fn test_decode() {
+    let text = "[\"caf\u{e9} \u{1d11e}\"]";
+    let utf16 = |le: bool| {
+        let units = text.encode_utf16();
+        let bytes = units.flat_map(|unit| {
+            if le {
+                unit.to_le_bytes()
+            } else {
+                unit.to_be_bytes()
+            }
+        });
+        bytes.collect::<Vec<_>>()
+    };
+    let utf32_be: Vec<u8> = text
+        .chars()
+        .flat_map(|c| (c as u32).to_be_bytes())
+        .collect();
+    let mut utf16_le_bom = vec![0xff, 0xfe];
+    utf16_le_bom.extend(utf16(true));
+
+    assert_eq!(Ok(text.to_string()), decode(utf16(true)));
+    assert_eq!(Ok(text.to_string()), decode(utf16(false)));
+    assert_eq!(Ok(text.to_string()), decode(utf16_le_bom));
+    assert_eq!(Ok(text.to_string()), decode(utf32_be));
+    assert_eq!(
+        Ok(text.to_string()),
+        decode(format!("\u{feff}{text}").into())
+    );
+
+    let lone = vec![b'"', 0, 0x00, 0xdc, b'"', 0];
+    assert_eq!(
+        Err(DecodeError {
+            encoding: Encoding::Utf16Le,
+            offset: 2
+        }),
+        decode(lone)
+    );
+    assert_eq!(
+        Err(DecodeError {
+            encoding: Encoding::Utf16Le,
+            offset: 2
+        }),
+        decode(b"1\0 ".to_vec())
+    );
+}
+
+#[test]
+fn test_parse_source_transcodes() {
+    let mut utf16_le_bom = vec![0xff, 0xfe];
+    utf16_le_bom.extend("{\"pairs\": []}".encode_utf16().flat_map(u16::to_le_bytes));
+    let Some(Value::Object(members)) = parse_source(&mut utf16_le_bom).unwrap() else {
+        panic!("Expected an object");
+    };
+    assert_eq!(Some(&Value::Array(Vec::new())), members.get("pairs"));
+}
+
+#[test]
+fn test_feed_rejects_utf16() {
+    let mut parser = FeedParser::new();
+    assert_eq!(Ok(vec![]), parser.feed(b"["));
+    assert_eq!(
+        Err(ParsingError::Tokenize(TokenizeError::UnsupportedEncoding(
+            Encoding::Utf16Le
+        ))),
+        parser.feed(b"\x001\0]\0")
+    );
+}
+
+fn value(input: &str) -> Value {
+    parse(input).unwrap().unwrap()
+}
+
+#[test]
+fn test_diff() {
+    let a = value(r#"{"radius": 6371, "pairs": [{"x0": 1.5}, {"x0": 2}], "seed": 1}"#);
+    let b = value(r#"{"radius": 6371, "pairs": [{"x0": 1.25}], "count": 1, "seed": "1"}"#);
+    let key = |key: &str| PathSegment::Key(key.into());
+    assert_eq!(
+        vec![
+            Change::Modified {
+                path: KeyPath(vec![key("pairs"), PathSegment::Index(0), key("x0")]),
+                from: Value::Number(Number::F64(1.5)),
+                to: Value::Number(Number::F64(1.25)),
+            },
+            Change::Removed {
+                path: KeyPath(vec![key("pairs"), PathSegment::Index(1)]),
+                value: value(r#"{"x0": 2}"#),
+            },
+            Change::Modified {
+                path: KeyPath(vec![key("seed")]),
+                from: Value::Number(Number::U64(1)),
+                to: Value::String("1".into()),
+            },
+            Change::Added {
+                path: KeyPath(vec![key("count")]),
+                value: Value::Number(Number::U64(1)),
+            },
+        ],
+        a.diff(&b)
+    );
+    assert!(a.diff(&a).is_empty());
+    assert_eq!(1, value("[]").diff(&value("[1]")).len());
+    assert_eq!(
+        "~ the root: 1 -> true",
+        value("1").diff(&value("true"))[0].to_string()
+    );
+    assert_eq!(
+        r#"+ "b"[1]: null"#,
+        value(r#"{"b": [0]}"#).diff(&value(r#"{"b": [0, null]}"#))[0].to_string()
+    );
+}
+
+#[test]
+fn test_diff_within_tolerance() {
+    let a = value(r#"{"avg": 1000.0, "n": 3, "m": 1.0}"#);
+    let b = value(r#"{"avg": 1000.0000001, "n": 3, "m": 1}"#);
+    assert_eq!(2, a.diff(&b).len());
+    // The float and the integer are equal by value within a tolerance.
+    assert!(a.diff_within(&b, 1e-9).is_empty());
+    assert_eq!(1, a.diff_within(&b, 1e-12).len());
+    let integers = value("[1, 2]").diff_within(&value("[1, 3]"), 1.0);
+    assert_eq!(1, integers.len());
+}
+
+#[test]
+fn test_canonical_numbers() {
+    let cases = [
+        ("1.0", "1"),
+        ("-0.0", "0"),
+        ("100", "100"),
+        ("1e21", "1e+21"),
+        ("123e18", "123000000000000000000"),
+        ("0.000001", "0.000001"),
+        ("1e-7", "1e-7"),
+        ("-1.5e-9", "-1.5e-9"),
+        ("6372.8", "6372.8"),
+        ("0.1", "0.1"),
+        ("9007199254740993", "9007199254740992"),
+        ("1.7976931348623157e308", "1.7976931348623157e+308"),
+        ("5e-324", "5e-324"),
+    ];
+    for (input, expected) in cases {
+        assert_eq!(expected, value(input).to_canonical_json(), "{input}");
+    }
+}
+
+#[test]
+fn test_canonical_json() {
+    // Keys order by UTF-16 code units: U+FB01 sorts after U+1F600.
+    let a = value("{\"\u{fb01}\": 1, \"\u{1f600}\": 2, \"b\": [1.50, \"\\u0041\\t\"], \"a\": {}}");
+    assert_eq!(
+        "{\"a\":{},\"b\":[1.5,\"A\\t\"],\"\u{1f600}\":2,\"\u{fb01}\":1}",
+        a.to_canonical_json()
+    );
+    let b =
+        value("{ \"a\": {}, \"b\": [15e-1, \"A\\u0009\"], \"\u{1f600}\": 2.0, \"\u{fb01}\": 1 }");
+    assert_eq!(a.to_canonical_json(), b.to_canonical_json());
+}
+
+#[test]
+fn test_parse_located() {
+    let input = "{\"pairs\": [1, 2,]}";
+    let diagnostic = Parser::new(input).parse_located().unwrap_err();
+    assert_eq!(Parser::new(input).parse().unwrap_err(), diagnostic.error);
+    assert_eq!(",", diagnostic.span.source(input));
+    assert_eq!(
+        Ok(Some(Value::from(1.0))),
+        Parser::new("1.0").parse_located()
+    );
+}
+
+#[test]
+fn test_snippet() {
+    // Only the two lines before the error are shown, and tabs before it are
+    // kept under it.
+    let input = "[\n1,\n2,\n\t\"a\" 3,\n\n]";
+    let diagnostic = Parser::new(input).parse_located().unwrap_err();
+    assert_eq!(
+        "  |\n2 | 1,\n3 | 2,\n4 | \t\"a\" 3,\n  | \t    ^\n",
+        diagnostic.snippet(input)
+    );
+
+    // An error at the end of the input gets one caret past the last line.
+    let input = "\u{feff}[1,\n";
+    let diagnostic = Parser::new(input).parse_located().unwrap_err();
+    assert_eq!("  |\n1 | [1,\n2 |\n  | ^\n", diagnostic.snippet(input));
+}
+
+#[test]
+fn test_number_out_of_range() {
+    for literal in [
+        "1e400",
+        "-1e400",
+        "1e-400",
+        "-0.5e-400",
+        "1".repeat(400).as_str(),
+    ] {
+        assert_eq!(
+            Err(ParsingError::NumberOutOfRange(literal.into())),
+            Parser::new(literal).parse(),
+            "{literal}"
+        );
+    }
+    // Zero, however written, and the smallest subnormal are in range.
+    for literal in ["0e-400", "-0.000e999", "5e-324"] {
+        assert!(Parser::new(literal).parse().is_ok(), "{literal}");
+    }
+
+    let parse = |literal, policy| match Parser::new(literal).out_of_range(policy).parse() {
+        Ok(Some(Value::Number(Number::F64(n)))) => n,
+        other => panic!("{literal} parsed as {other:?}"),
+    };
+    assert_eq!(f64::MAX, parse("1e400", NumberRangePolicy::Clamp));
+    assert_eq!(-f64::MAX, parse("-1e400", NumberRangePolicy::Clamp));
+    assert_eq!(0.0, parse("1e-400", NumberRangePolicy::Clamp));
+    assert_eq!(
+        f64::NEG_INFINITY,
+        parse("-1e400", NumberRangePolicy::KeepInfinite)
+    );
+    assert!(parse("-1e-400", NumberRangePolicy::KeepInfinite).is_sign_negative());
+}
+
+#[test]
+fn test_feed_number_out_of_range() {
+    let mut parser = FeedParser::new();
+    assert_eq!(
+        Err(ParsingError::NumberOutOfRange("1e400".into())),
+        parser.feed(b"[1e400]")
+    );
+    let mut parser = FeedParser::new().out_of_range(NumberRangePolicy::Clamp);
+    let events = parser.feed(b"[1e4").and_then(|mut events| {
+        events.extend(parser.feed(b"00]")?);
+        Ok(events)
+    });
+    assert_eq!(
+        Ok(vec![
+            Event::StartArray,
+            Event::Number(Number::F64(f64::MAX)),
+            Event::EndArray
+        ]),
+        events
+    );
+}
+
+#[test]
+fn test_limits() {
+    let input = r#"{"name": "abcd", "pairs": [1, 2, 3]}"#;
+    let parse = |limits| Parser::new(input).limits(limits).parse();
+    let limited = |edit: fn(&mut Limits)| {
+        let mut limits = Limits::default();
+        edit(&mut limits);
+        limits
+    };
+    assert!(parse(Limits::default()).is_ok());
+    assert_eq!(
+        Err(ParsingError::DocumentTooLarge(10)),
+        parse(limited(|limits| limits.max_size = 10))
+    );
+    assert_eq!(
+        Err(ParsingError::StringTooLong(3)),
+        parse(limited(|limits| limits.max_string_len = 3)).map_err(|error| match error {
+            ParsingError::Nested(_, error) => *error,
+            error => error,
+        })
+    );
+    let path = KeyPath(vec![PathSegment::Key("pairs".into())]);
+    assert_eq!(
+        Err(ParsingError::Nested(
+            path,
+            Box::new(ParsingError::ArrayTooLong(2))
+        )),
+        parse(limited(|limits| limits.max_array_len = 2))
+    );
+    // The object, two keys, a string, the array and its three numbers.
+    assert!(parse(limited(|limits| limits.max_values = 8)).is_ok());
+    assert!(matches!(
+        parse(limited(|limits| limits.max_values = 7)),
+        Err(ParsingError::Nested(_, error)) if *error == ParsingError::TooManyValues(7)
+    ));
+}
+
+#[test]
+fn test_limits_agree_across_parsers() {
+    let input = r#"[{"k": "abcd"}, [1, 2, 3], 4]"#;
+    let cases = [
+        (|limits: &mut Limits| limits.max_size = 20) as fn(&mut Limits),
+        |limits| limits.max_string_len = 3,
+        |limits| limits.max_array_len = 2,
+        |limits| limits.max_values = 8,
+    ];
+    for edit in cases {
+        let mut limits = Limits::default();
+        edit(&mut limits);
+        let parsed = Parser::new(input).limits(limits).parse();
+        let spanned = Parser::new(input).limits(limits).parse_with_spans();
+        let mut feed = FeedParser::new().limits(limits);
+        let fed = feed.feed(input.as_bytes());
+        let root = |error: ParsingError| match error {
+            ParsingError::Nested(_, error) => *error,
+            error => error,
+        };
+        let error = root(parsed.unwrap_err());
+        assert_eq!(error, root(spanned.unwrap_err()), "{limits:?}");
+        assert_eq!(error, fed.unwrap_err(), "{limits:?}");
+    }
+}
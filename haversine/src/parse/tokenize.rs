@@ -1,10 +1,12 @@
+use super::{BOM, Encoding, Number, Span};
+use std::fmt;
 use std::str::Chars;
 
 const EOF_CHAR: char = '\0';
 
 /// Token for JSON parser
 #[derive(Debug, Clone, PartialEq)]
-pub(crate) enum Token {
+pub enum Token {
     /// End of file
     Eof,
     /// Open brace `{`
@@ -26,42 +28,361 @@ pub(crate) enum Token {
     /// String
     String(String),
     /// Number
-    Number(f64),
+    Number(Number),
+}
+
+impl fmt::Display for Token {
+    /// Formats the token as it appears in JSON text.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Eof => write!(f, "end of input"),
+            Token::OpenBrace => write!(f, "'{{'"),
+            Token::CloseBrace => write!(f, "'}}'"),
+            Token::OpenBracket => write!(f, "'['"),
+            Token::CloseBracket => write!(f, "']'"),
+            Token::Comma => write!(f, "','"),
+            Token::Colon => write!(f, "':'"),
+            Token::Null => write!(f, "null"),
+            Token::Bool(b) => write!(f, "{b}"),
+            Token::String(s) => write!(f, "{s:?}"),
+            Token::Number(n) => write!(f, "{n}"),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
-pub(crate) enum TokenizeError {
+#[non_exhaustive]
+pub enum TokenizeError {
     InvalidNull,
     InvalidTrue,
     InvalidFalse,
     InvalidNumber(String),
     ReachedEOF(&'static str),
     UnexpectedChar(char),
+    InvalidEscape(char),
+    InvalidUnicodeEscape(String),
+    LoneSurrogate(u16),
+    /// A control character, below U+0020, written unescaped in a string.
+    ControlChar(char),
+    /// The input fed to a `FeedParser` is not valid UTF-8.
+    InvalidUtf8,
+    /// The input fed to a `FeedParser` is in another encoding than UTF-8.
+    UnsupportedEncoding(Encoding),
+    /// A number too large or too small in magnitude for an `f64`, with
+    /// `NumberRangePolicy::Error`.
+    NumberOutOfRange(String),
+}
+
+impl fmt::Display for TokenizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenizeError::InvalidNull => write!(f, "invalid literal, expected null"),
+            TokenizeError::InvalidTrue => write!(f, "invalid literal, expected true"),
+            TokenizeError::InvalidFalse => write!(f, "invalid literal, expected false"),
+            TokenizeError::InvalidNumber(number) => write!(f, "invalid number {number:?}"),
+            TokenizeError::ReachedEOF(expected) => {
+                write!(f, "unexpected end of input, expected '{expected}'")
+            }
+            TokenizeError::UnexpectedChar(c) => write!(f, "unexpected character {c:?}"),
+            TokenizeError::InvalidEscape(c) => write!(f, "invalid escape \\{c}"),
+            TokenizeError::InvalidUnicodeEscape(escape) => {
+                write!(f, "invalid unicode escape \\u{escape}")
+            }
+            TokenizeError::LoneSurrogate(code) => write!(f, "lone surrogate \\u{code:04x}"),
+            TokenizeError::ControlChar(c) => {
+                write!(f, "unescaped control character {c:?} in string")
+            }
+            TokenizeError::InvalidUtf8 => write!(f, "input is not valid UTF-8"),
+            TokenizeError::UnsupportedEncoding(encoding) => {
+                write!(f, "input is {encoding}, not UTF-8")
+            }
+            TokenizeError::NumberOutOfRange(number) => {
+                write!(f, "number {number} is out of the range of f64")
+            }
+        }
+    }
 }
 
-/// visit: https://www.json.org/json-en.html
-pub(crate) struct Tokenizer<'a> {
+impl std::error::Error for TokenizeError {}
+
+/// Token read by `Tokenizer::skip_token`, which checks scalars without
+/// building their value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Skipped {
+    Eof,
+    OpenBrace,
+    CloseBrace,
+    OpenBracket,
+    CloseBracket,
+    Comma,
+    Colon,
+    Null,
+    Bool(bool),
+    Number,
+    /// A string, which needs decoding if it has escape sequences.
+    String {
+        escaped: bool,
+    },
+}
+
+/// What the tokenizer does with a `\u` escape of a UTF-16 surrogate that is
+/// not part of a high-low surrogate pair.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LoneSurrogatePolicy {
+    /// Fail with `TokenizeError::LoneSurrogate`.
+    #[default]
+    Error,
+    /// Replace it with U+FFFD REPLACEMENT CHARACTER.
+    Replace,
+}
+
+/// What the tokenizer does with a number whose magnitude is too large for
+/// an `f64`, which would read as infinite, or too small, which would read
+/// as zero although its digits are not all zero.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum NumberRangePolicy {
+    /// Fail with `TokenizeError::NumberOutOfRange`.
+    #[default]
+    Error,
+    /// Replace a number too large with the largest finite `f64` of its sign,
+    /// and one too small with zero.
+    Clamp,
+    /// Replace a number too large with the infinity of its sign, and one
+    /// too small with zero, as `str::parse` does.
+    KeepInfinite,
+}
+
+/// Lexer of JSON text, as given at https://www.json.org/json-en.html.
+///
+/// As an iterator it yields every token with its span, up to the end of the
+/// input or the first error, without `Token::Eof`.
+///
+/// ```
+/// use haversine::parse::Tokenizer;
+///
+/// let input = "[1, null]";
+/// let sources: Vec<_> = Tokenizer::new(input)
+///     .map(|spanned| spanned.map(|(_, span)| span.source(input)))
+///     .collect::<Result<_, _>>()
+///     .unwrap();
+/// assert_eq!(vec!["[", "1", ",", "null", "]"], sources);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Tokenizer<'a> {
+    input: &'a str,
     inner: Chars<'a>,
     prev_char: Option<char>,
+    /// The next token and its span, when it was peeked. Peeking lexes a
+    /// token once, for the read that follows to take.
+    peeked: Option<(Result<Token, TokenizeError>, Span)>,
+    /// The same for the next skipped token.
+    peeked_skipped: Option<(Result<Skipped, TokenizeError>, Span)>,
+    lone_surrogates: LoneSurrogatePolicy,
+    out_of_range: NumberRangePolicy,
+    allow_control_chars: bool,
+    raw_numbers: bool,
+    bumped_past_end: bool,
+    /// Whether the iterator has reached the end or an error.
+    done: bool,
 }
 
 impl<'a> Tokenizer<'a> {
-    pub(crate) fn new(input: &'a str) -> Self {
+    pub fn new(input: &'a str) -> Self {
         Self {
+            input,
             inner: input.chars(),
             prev_char: None,
+            peeked: None,
+            peeked_skipped: None,
+            lone_surrogates: LoneSurrogatePolicy::default(),
+            out_of_range: NumberRangePolicy::default(),
+            allow_control_chars: false,
+            raw_numbers: false,
+            bumped_past_end: false,
+            done: false,
         }
     }
 
-    pub(crate) fn peek_next(&mut self) -> Result<Token, TokenizeError> {
-        let chars = self.inner.clone();
-        let token = self.next_token();
-        self.inner = chars;
-        token
+    /// Sets how escaped lone surrogates in strings are handled.
+    pub fn lone_surrogates(mut self, policy: LoneSurrogatePolicy) -> Self {
+        self.lone_surrogates = policy;
+        self
     }
 
-    pub(crate) fn next_token(&mut self) -> Result<Token, TokenizeError> {
+    /// Sets how numbers out of the range of `f64` are handled. Raw numbers
+    /// are not checked.
+    pub fn out_of_range(mut self, policy: NumberRangePolicy) -> Self {
+        self.out_of_range = policy;
+        self
+    }
+
+    /// Skips a byte order mark at the start of the input.
+    pub(crate) fn skip_bom(mut self) -> Self {
+        if let Some(rest) = self.input.strip_prefix(BOM) {
+            self.inner = rest.chars();
+        }
+        self
+    }
+
+    /// The whole input, which spans index, before any byte order mark.
+    pub(crate) fn input(&self) -> &'a str {
+        self.input
+    }
+
+    pub(crate) fn lone_surrogate_policy(&self) -> LoneSurrogatePolicy {
+        self.lone_surrogates
+    }
+
+    /// Accepts control characters written unescaped in strings, which JSON
+    /// forbids, rather than failing with `TokenizeError::ControlChar`.
+    pub fn allow_control_chars(mut self, allow: bool) -> Self {
+        self.allow_control_chars = allow;
+        self
+    }
+
+    pub(crate) fn allows_control_chars(&self) -> bool {
+        self.allow_control_chars
+    }
+
+    /// Sets whether numbers are kept as `Number::Raw` literals.
+    pub fn raw_numbers(mut self, raw: bool) -> Self {
+        self.raw_numbers = raw;
+        self
+    }
+
+    /// Returns the input that has not been consumed yet, from the peeked
+    /// token if there is one.
+    pub fn rest(&self) -> &'a str {
+        &self.input[self.offset()..]
+    }
+
+    /// Returns the byte offset of the peeked token if there is one, or else
+    /// of the next character in the input.
+    pub fn offset(&self) -> usize {
+        match (&self.peeked, &self.peeked_skipped) {
+            (Some((_, span)), _) | (_, Some((_, span))) => span.start,
+            (None, None) => self.lex_offset(),
+        }
+    }
+
+    /// Byte offset of the next character not lexed yet.
+    fn lex_offset(&self) -> usize {
+        self.input.len() - self.inner.as_str().len()
+    }
+
+    /// Goes back to the start of a peeked token, dropping it, for the input
+    /// to be read another way.
+    fn unpeek(&mut self) {
+        let span = match (self.peeked.take(), self.peeked_skipped.take()) {
+            (Some((_, span)), _) | (_, Some((_, span))) => span,
+            (None, None) => return,
+        };
+        self.inner = self.input[span.start..].chars();
+    }
+
+    /// Returns the next token with the span of its source text.
+    pub(crate) fn next_spanned(&mut self) -> Result<(Token, Span), TokenizeError> {
+        let (token, span) = self.next_with_span();
+        Ok((token?, span))
+    }
+
+    /// Returns the next token, or the error, with the span of the text read
+    /// for it.
+    pub(crate) fn next_with_span(&mut self) -> (Result<Token, TokenizeError>, Span) {
+        if let Some(next) = self.peeked.take() {
+            return next;
+        }
+        self.unpeek();
         self.eat_whitespace();
+        let start = self.lex_offset();
+        let token = self.lex_token();
+        (token, Span::new(start, self.lex_offset()))
+    }
+
+    /// Returns the next token like `next_with_span` without consuming it.
+    pub(crate) fn peek_with_span(&mut self) -> (Result<&Token, TokenizeError>, Span) {
+        if self.peeked.is_none() {
+            let next = self.next_with_span();
+            self.peeked = Some(next);
+        }
+        let (token, span) = self.peeked.as_ref().expect("Peeked token");
+        (token.as_ref().map_err(Clone::clone), *span)
+    }
+
+    /// Skips to the next `,`, `]` or `}`, past the text of an invalid token.
+    pub(crate) fn skip_to_delimiter(&mut self) {
+        self.unpeek();
+        self.eat_while(|c| !matches!(c, ',' | ']' | '}'));
+    }
+
+    /// Checks if a token ran out of input before it was complete, so that
+    /// more input could have completed it.
+    pub(crate) fn bumped_past_end(&self) -> bool {
+        self.bumped_past_end
+    }
+
+    /// Returns the next token without consuming it.
+    pub(crate) fn peek_next(&mut self) -> Result<&Token, TokenizeError> {
+        self.peek_with_span().0
+    }
+
+    /// Reads the next token like `next_token`, with the same errors, but
+    /// without allocating its value, and returns the span of its text.
+    pub(crate) fn skip_spanned(&mut self) -> Result<(Skipped, Span), TokenizeError> {
+        if let Some((skipped, span)) = self.peeked_skipped.take() {
+            return Ok((skipped?, span));
+        }
+        self.unpeek();
+        self.eat_whitespace();
+        let start = self.lex_offset();
+        let text = self.rest();
+        let skipped = match self.bump() {
+            None => Skipped::Eof,
+            Some('[') => Skipped::OpenBracket,
+            Some(']') => Skipped::CloseBracket,
+            Some('{') => Skipped::OpenBrace,
+            Some('}') => Skipped::CloseBrace,
+            Some(',') => Skipped::Comma,
+            Some(':') => Skipped::Colon,
+            Some('n') => self.next_null().map(|_| Skipped::Null)?,
+            Some('t') => self.next_true().map(|_| Skipped::Bool(true))?,
+            Some('f') => self.next_false().map(|_| Skipped::Bool(false))?,
+            Some('\"') => self.skip_string()?,
+            Some('0'..='9' | '-') => {
+                self.eat_while(|c| !is_whitespace(c) && !matches!(c, ',' | ']' | '}'));
+                let literal = &text[..self.lex_offset() - start];
+                if !is_json_number(literal) {
+                    return Err(TokenizeError::InvalidNumber(literal.into()));
+                }
+                Skipped::Number
+            }
+            Some(c) => return Err(TokenizeError::UnexpectedChar(c)),
+        };
+        Ok((skipped, Span::new(start, self.lex_offset())))
+    }
+
+    /// Returns the next token like `skip_spanned` without consuming it.
+    pub(crate) fn peek_skipped(&mut self) -> Result<Skipped, TokenizeError> {
+        if self.peeked_skipped.is_none() {
+            let start = self.offset();
+            let next = match self.skip_spanned() {
+                Ok((skipped, span)) => (Ok(skipped), span),
+                Err(error) => (Err(error), Span::new(start, self.lex_offset())),
+            };
+            self.peeked_skipped = Some(next);
+        }
+        let (skipped, _) = self.peeked_skipped.as_ref().expect("Peeked token");
+        skipped.clone()
+    }
+
+    pub(crate) fn next_token(&mut self) -> Result<Token, TokenizeError> {
+        self.next_with_span().0
+    }
+
+    /// Lexes the token at the start of the input left, after whitespace.
+    fn lex_token(&mut self) -> Result<Token, TokenizeError> {
         match self.bump() {
             None => Ok(Token::Eof),
             Some('[') => Ok(Token::OpenBracket),
@@ -110,30 +431,145 @@ impl<'a> Tokenizer<'a> {
         debug_assert!(matches!(self.prev_char, None | Some('\"')));
         let mut string = String::new();
         while let Some(c) = self.bump() {
-            if c == '\"' {
-                return Ok(Token::String(string));
+            match c {
+                '\"' => return Ok(Token::String(string)),
+                '\\' => self.next_escape(&mut string)?,
+                c if c < ' ' && !self.allow_control_chars => {
+                    return Err(TokenizeError::ControlChar(c));
+                }
+                c => string.push(c),
             }
-            string.push(c);
         }
         Err(TokenizeError::ReachedEOF("\""))
     }
 
+    /// Checks a string up to its closing quote. Only escape sequences are
+    /// decoded, into a scratch buffer, to check them.
+    fn skip_string(&mut self) -> Result<Skipped, TokenizeError> {
+        let mut escaped = false;
+        let mut scratch = String::new();
+        while let Some(c) = self.bump() {
+            match c {
+                '\"' => return Ok(Skipped::String { escaped }),
+                '\\' => {
+                    escaped = true;
+                    self.next_escape(&mut scratch)?;
+                    scratch.clear();
+                }
+                c if c < ' ' && !self.allow_control_chars => {
+                    return Err(TokenizeError::ControlChar(c));
+                }
+                _ => {}
+            }
+        }
+        Err(TokenizeError::ReachedEOF("\""))
+    }
+
+    /// Pushes the character of the escape sequence following a backslash.
+    fn next_escape(&mut self, string: &mut String) -> Result<(), TokenizeError> {
+        let c = match self.bump() {
+            Some('\"') => '\"',
+            Some('\\') => '\\',
+            Some('/') => '/',
+            Some('b') => '\u{8}',
+            Some('f') => '\u{c}',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('u') => self.next_unicode_escape()?,
+            Some(c) => return Err(TokenizeError::InvalidEscape(c)),
+            None => return Err(TokenizeError::ReachedEOF("\"")),
+        };
+        string.push(c);
+        Ok(())
+    }
+
+    /// Decodes the character of a `\uXXXX` escape, combining a high surrogate
+    /// with an immediately following escaped low surrogate.
+    fn next_unicode_escape(&mut self) -> Result<char, TokenizeError> {
+        let unit = self.next_hex_code_unit()?;
+        match unit {
+            0xD800..=0xDBFF => {
+                // Only a `\u` escape of a low surrogate completes the pair;
+                // anything else is left for the string to consume.
+                let rest = self.inner.clone();
+                if self.bump() == Some('\\') && self.bump() == Some('u') {
+                    let low = self.next_hex_code_unit()?;
+                    if let 0xDC00..=0xDFFF = low {
+                        let high = u32::from(unit - 0xD800) << 10;
+                        let code_point = 0x10000 + (high | u32::from(low - 0xDC00));
+                        return Ok(char::from_u32(code_point).expect("Valid surrogate pair"));
+                    }
+                }
+                self.inner = rest;
+                self.lone_surrogate(unit)
+            }
+            0xDC00..=0xDFFF => self.lone_surrogate(unit),
+            _ => Ok(char::from_u32(u32::from(unit)).expect("Not a surrogate")),
+        }
+    }
+
+    /// Reads the four hexadecimal digits of a `\uXXXX` escape.
+    fn next_hex_code_unit(&mut self) -> Result<u16, TokenizeError> {
+        let mut digits = String::new();
+        for _ in 0..4 {
+            match self.bump() {
+                Some(c) if c.is_ascii_hexdigit() => digits.push(c),
+                Some(c) => {
+                    digits.push(c);
+                    return Err(TokenizeError::InvalidUnicodeEscape(digits));
+                }
+                None => return Err(TokenizeError::ReachedEOF("\"")),
+            }
+        }
+        Ok(u16::from_str_radix(&digits, 16).expect("Hexadecimal digits"))
+    }
+
+    fn lone_surrogate(&self, unit: u16) -> Result<char, TokenizeError> {
+        match self.lone_surrogates {
+            LoneSurrogatePolicy::Error => Err(TokenizeError::LoneSurrogate(unit)),
+            LoneSurrogatePolicy::Replace => Ok(char::REPLACEMENT_CHARACTER),
+        }
+    }
+
     fn next_number(&mut self, first_digit: char) -> Result<Token, TokenizeError> {
         // TODO: Debug assert the previous digit
         let mut string = format!("{first_digit}");
         loop {
             let next_char = self.peek_next_char();
             if is_whitespace(next_char) || matches!(next_char, ',' | ']' | '}' | EOF_CHAR) {
-                let num = string
-                    .parse()
-                    .map_err(|_| TokenizeError::InvalidNumber(string))?;
-                return Ok(Token::Number(num));
+                if !is_json_number(&string) {
+                    return Err(TokenizeError::InvalidNumber(string));
+                }
+                if self.raw_numbers {
+                    return Ok(Token::Number(Number::Raw(string)));
+                }
+                return match Number::from_literal(&string) {
+                    Some(number) => self.in_range(number, string).map(Token::Number),
+                    None => Err(TokenizeError::InvalidNumber(string)),
+                };
             }
             let _ = self.inner.next();
             string.push(next_char);
         }
     }
 
+    /// Applies the range policy to a number converted from `literal`.
+    fn in_range(&self, number: Number, literal: String) -> Result<Number, TokenizeError> {
+        let Number::F64(n) = number else {
+            return Ok(number);
+        };
+        let digits = literal.split(['e', 'E']).next().unwrap_or_default();
+        let underflow = n == 0.0 && digits.contains(|c| matches!(c, '1'..='9'));
+        match self.out_of_range {
+            NumberRangePolicy::Error if n.is_infinite() || underflow => {
+                Err(TokenizeError::NumberOutOfRange(literal))
+            }
+            NumberRangePolicy::Clamp if n.is_infinite() => Ok(Number::F64(f64::MAX.copysign(n))),
+            _ => Ok(number),
+        }
+    }
+
     /// Eats the whitespace.
     fn eat_whitespace(&mut self) {
         self.eat_while(is_whitespace);
@@ -148,6 +584,7 @@ impl<'a> Tokenizer<'a> {
     fn bump(&mut self) -> Option<char> {
         let next = self.inner.next();
         self.prev_char = next;
+        self.bumped_past_end |= next.is_none();
         next
     }
 
@@ -165,7 +602,74 @@ impl<'a> Tokenizer<'a> {
     }
 }
 
+/// Checks the number grammar of JSON, which is stricter than that of `f64`:
+/// no `inf` or `nan`, no leading zeros and digits on both sides of the point.
+pub(crate) fn is_json_number(s: &str) -> bool {
+    let digits = |s: &str| s.len() - s.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+
+    let s = s.strip_prefix('-').unwrap_or(s);
+    let int = digits(s);
+    if int == 0 || (int > 1 && s.starts_with('0')) {
+        return false;
+    }
+    let mut rest = &s[int..];
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let n = digits(fraction);
+        if n == 0 {
+            return false;
+        }
+        rest = &fraction[n..];
+    }
+    if let Some(exponent) = rest.strip_prefix(['e', 'E']) {
+        let exponent = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+        let n = digits(exponent);
+        if n == 0 {
+            return false;
+        }
+        rest = &exponent[n..];
+    }
+    rest.is_empty()
+}
+
+impl Iterator for Tokenizer<'_> {
+    type Item = Result<(Token, Span), TokenizeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.next_spanned() {
+            Ok((Token::Eof, _)) => {
+                self.done = true;
+                None
+            }
+            Ok(spanned) => Some(Ok(spanned)),
+            Err(error) => {
+                self.done = true;
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+impl std::iter::FusedIterator for Tokenizer<'_> {}
+
 /// Checks if character 'c' is a whitespace.
 fn is_whitespace(c: char) -> bool {
     matches!(c, ' ' | '\t' | '\n' | '\r')
 }
+
+/// Splits the input into tokens with their spans, up to the end of the input
+/// or the first error.
+///
+/// ```
+/// use haversine::parse::{Number, Span, Token, tokens};
+///
+/// let input = r#"{"x0": 1.5}"#;
+/// let (token, span) = tokens(input).nth(3).unwrap().unwrap();
+/// assert_eq!(Token::Number(Number::F64(1.5)), token);
+/// assert_eq!("1.5", span.source(input));
+/// ```
+pub fn tokens(input: &str) -> Tokenizer<'_> {
+    Tokenizer::new(input)
+}
@@ -0,0 +1,127 @@
+use super::Value;
+use std::fmt;
+
+/// Items of an array or members of an object shown by default, before the
+/// rest are counted on one line.
+pub const DEFAULT_TREE_ITEMS: usize = 10;
+/// Characters of a string shown before it is cut short.
+const MAX_STRING_CHARS: usize = 40;
+
+impl Value {
+    /// Returns an indented tree view of the value, a line per value with
+    /// the length of each array and object, for eyeballing a document.
+    ///
+    /// ```
+    /// use haversine::parse::{Value, parse};
+    ///
+    /// let value = parse(r#"{"pairs": [{"x0": 1.5}], "name": "test"}"#).unwrap().unwrap();
+    /// let tree = value.display_tree().max_depth(1).to_string();
+    /// assert_eq!("object {2}\n  \"pairs\": array [1]\n  \"name\": \"test\"\n", tree);
+    /// ```
+    pub fn display_tree(&self) -> Tree<'_> {
+        Tree {
+            value: self,
+            max_depth: None,
+            max_items: DEFAULT_TREE_ITEMS,
+        }
+    }
+}
+
+/// Tree view of a `Value`, written by its `Display`. Strings longer than
+/// 40 characters are cut short, with their length.
+#[derive(Debug, Clone, Copy)]
+pub struct Tree<'a> {
+    value: &'a Value,
+    max_depth: Option<usize>,
+    max_items: usize,
+}
+
+impl Tree<'_> {
+    /// Sets the deepest level whose values are shown, the root being 0.
+    /// Arrays and objects at that level show only their length.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Sets how many items of each array and members of each object are
+    /// shown.
+    pub fn max_items(mut self, items: usize) -> Self {
+        self.max_items = items;
+        self
+    }
+
+    fn node(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        label: Option<&dyn fmt::Display>,
+        value: &Value,
+        depth: usize,
+    ) -> fmt::Result {
+        write!(f, "{:1$}", "", 2 * depth)?;
+        if let Some(label) = label {
+            write!(f, "{label}: ")?;
+        }
+        let len = match value {
+            Value::Null => return writeln!(f, "null"),
+            Value::Bool(b) => return writeln!(f, "{b}"),
+            Value::Number(n) => return writeln!(f, "{n}"),
+            Value::String(s) => return writeln!(f, "{}", Truncated(s)),
+            Value::Array(items) => {
+                writeln!(f, "array [{}]", items.len())?;
+                items.len()
+            }
+            Value::Object(members) => {
+                writeln!(f, "object {{{}}}", members.len())?;
+                members.len()
+            }
+        };
+        if self.max_depth.is_some_and(|max| depth >= max) {
+            return Ok(());
+        }
+        match value {
+            Value::Array(items) => {
+                for (i, item) in items.iter().take(self.max_items).enumerate() {
+                    self.node(f, Some(&format_args!("[{i}]")), item, depth + 1)?;
+                }
+            }
+            Value::Object(members) => {
+                for (key, member) in members.iter().take(self.max_items) {
+                    self.node(f, Some(&format_args!("{key:?}")), member, depth + 1)?;
+                }
+            }
+            _ => unreachable!("Scalars return above"),
+        }
+        if len > self.max_items {
+            writeln!(
+                f,
+                "{:2$}... {} more",
+                "",
+                len - self.max_items,
+                2 * (depth + 1)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Tree<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.node(f, None, self.value, 0)
+    }
+}
+
+/// A string quoted as JSON, cut short after `MAX_STRING_CHARS`.
+struct Truncated<'a>(&'a str);
+
+impl fmt::Display for Truncated<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.char_indices().nth(MAX_STRING_CHARS) {
+            Some((end, _)) => {
+                let chars = self.0.chars().count();
+                write!(f, "{:?}... ({chars} characters)", &self.0[..end])
+            }
+            None => write!(f, "{:?}", self.0),
+        }
+    }
+}
@@ -1,23 +1,36 @@
-use super::Token;
-use std::collections::HashMap;
+use super::{Map, Number, Token};
+use std::fmt;
+use std::ops::Index;
 
 /// It represents a JSON value.
 #[derive(Debug, Clone, PartialEq)]
-pub(crate) enum Value {
+pub enum Value {
     Null,
     Bool(bool),
-    Number(f64),
+    Number(Number),
     String(String),
     Array(Vec<Value>),
-    Object(HashMap<String, Value>),
+    Object(Map),
 }
 
 #[derive(Debug, Clone, PartialEq)]
-pub(crate) enum ValueError {
+#[non_exhaustive]
+pub enum ValueError {
     TryFromToken(Token),
     TryIntof64,
 }
 
+impl fmt::Display for ValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValueError::TryFromToken(token) => write!(f, "{token} is not a value"),
+            ValueError::TryIntof64 => write!(f, "the value is not a number"),
+        }
+    }
+}
+
+impl std::error::Error for ValueError {}
+
 impl TryFrom<Token> for Value {
     type Error = ValueError;
 
@@ -37,8 +50,143 @@ impl TryInto<f64> for &Value {
 
     fn try_into(self) -> Result<f64, Self::Error> {
         match self {
-            Value::Number(n) => Ok(*n),
+            Value::Number(n) => Ok(n.as_f64()),
             _ => Err(ValueError::TryIntof64),
         }
     }
 }
+
+/// Returned when indexing a missing key or out of bounds index.
+static NULL: Value = Value::Null;
+
+// Builders for output documents, used by the generator and tests.
+impl Value {
+    /// Creates an empty object.
+    pub fn object() -> Self {
+        Value::Object(Map::new())
+    }
+
+    /// Creates an empty array.
+    pub fn array() -> Self {
+        Value::Array(Vec::new())
+    }
+
+    /// Inserts `value` under `key`, returning the object for chaining.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is not an object.
+    pub fn insert(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        match &mut self {
+            Value::Object(map) => {
+                map.insert(key.into(), value.into());
+            }
+            other => panic!("Cannot insert a key into {other:?}"),
+        }
+        self
+    }
+
+    /// Appends `value` to the end of the array.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is not an array.
+    pub fn push(&mut self, value: impl Into<Value>) {
+        match self {
+            Value::Array(items) => items.push(value.into()),
+            other => panic!("Cannot push into {other:?}"),
+        }
+    }
+
+    /// Reorders the members of every object in the value by key.
+    pub fn sort_keys(&mut self) {
+        match self {
+            Value::Array(items) => items.iter_mut().for_each(Value::sort_keys),
+            Value::Object(map) => {
+                map.sort_keys();
+                map.values_mut().for_each(Value::sort_keys);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Self {
+        Value::Number(n.into())
+    }
+}
+
+impl From<i64> for Value {
+    fn from(n: i64) -> Self {
+        Value::Number(n.into())
+    }
+}
+
+impl From<u64> for Value {
+    fn from(n: u64) -> Self {
+        Value::Number(n.into())
+    }
+}
+
+impl From<Number> for Value {
+    fn from(n: Number) -> Self {
+        Value::Number(n)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.into())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(items: Vec<Value>) -> Self {
+        Value::Array(items)
+    }
+}
+
+impl From<Map> for Value {
+    fn from(map: Map) -> Self {
+        Value::Object(map)
+    }
+}
+
+impl Index<&str> for Value {
+    type Output = Value;
+
+    /// Returns the value under `key`, or `Value::Null` if the value is not
+    /// an object or has no such key.
+    fn index(&self, key: &str) -> &Value {
+        match self {
+            Value::Object(map) => map.get(key).unwrap_or(&NULL),
+            _ => &NULL,
+        }
+    }
+}
+
+impl Index<usize> for Value {
+    type Output = Value;
+
+    /// Returns the item at `index`, or `Value::Null` if the value is not an
+    /// array or the index is out of bounds.
+    fn index(&self, index: usize) -> &Value {
+        match self {
+            Value::Array(items) => items.get(index).unwrap_or(&NULL),
+            _ => &NULL,
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use crate::geo::{AngleUnit, final_bearing, initial_bearing, midpoint};
+use crate::pairs::Pairs;
+use std::fmt;
+use std::io::{self, Write};
+
+/// File format of the per-pair output.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum PerPairFormat {
+    /// An array with one object per pair.
+    Json,
+    /// One line per pair, with a header line.
+    Csv,
+    /// Raw little-endian `f64`s, one record per pair.
+    F64,
+}
+
+impl PerPairFormat {
+    /// Picks the format from the extension of `path`.
+    pub fn from_path(path: &str) -> Option<Self> {
+        match path.rsplit_once('.')?.1 {
+            "json" => Some(PerPairFormat::Json),
+            "csv" => Some(PerPairFormat::Csv),
+            "f64" => Some(PerPairFormat::F64),
+            _ => None,
+        }
+    }
+}
+
+/// What is written for each pair, besides its distance.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PerPairOptions {
+    pub format: PerPairFormat,
+    /// Write the input coordinates of the pair.
+    pub coordinates: bool,
+    /// Write the initial and final bearing of the pair.
+    pub bearings: bool,
+    /// Write the midpoint of the great-circle arc of the pair.
+    pub midpoints: bool,
+    /// Unit of the coordinates, in which the bearings and midpoints are
+    /// written too.
+    pub angle_unit: AngleUnit,
+}
+
+impl PerPairOptions {
+    /// Names of the fields of a record, in the order they are written.
+    fn fields(&self) -> Vec<&'static str> {
+        let mut fields = Vec::new();
+        if self.coordinates {
+            fields.extend(["x0", "y0", "x1", "y1"]);
+        }
+        fields.push("distance");
+        if self.bearings {
+            fields.extend(["initial_bearing", "final_bearing"]);
+        }
+        if self.midpoints {
+            fields.extend(["mid_x", "mid_y"]);
+        }
+        fields
+    }
+
+    /// Values of the fields of the record for the pair at `index`.
+    fn record(&self, pairs: &Pairs, distances: &[f64], index: usize) -> Vec<f64> {
+        let (x0, y0, x1, y1) = (
+            pairs.x0[index],
+            pairs.y0[index],
+            pairs.x1[index],
+            pairs.y1[index],
+        );
+        let mut record = Vec::new();
+        if self.coordinates {
+            record.extend([x0, y0, x1, y1]);
+        }
+        record.push(distances[index]);
+        let unit = self.angle_unit;
+        let [x0, y0, x1, y1] = [x0, y0, x1, y1].map(|angle| unit.to_degrees(angle));
+        if self.bearings {
+            record.extend(
+                [
+                    initial_bearing(x0, y0, x1, y1),
+                    final_bearing(x0, y0, x1, y1),
+                ]
+                .map(|bearing| unit.from_degrees(bearing)),
+            );
+        }
+        if self.midpoints {
+            let (mid_x, mid_y) = midpoint(x0, y0, x1, y1);
+            record.extend([mid_x, mid_y].map(|angle| unit.from_degrees(angle)));
+        }
+        record
+    }
+}
+
+/// Writes a record for every pair with its computed distance.
+pub fn write(
+    mut writer: impl Write,
+    pairs: &Pairs,
+    distances: &[f64],
+    options: &PerPairOptions,
+) -> io::Result<()> {
+    debug_assert_eq!(pairs.len(), distances.len());
+    let fields = options.fields();
+
+    match options.format {
+        PerPairFormat::Json => {
+            write!(writer, "[")?;
+            for index in 0..distances.len() {
+                let separator = if index == 0 { "" } else { "," };
+                write!(writer, "{separator}\n  {{")?;
+                let record = options.record(pairs, distances, index);
+                for (i, (field, value)) in fields.iter().zip(record).enumerate() {
+                    let separator = if i == 0 { "" } else { ", " };
+                    write!(writer, "{separator}\"{field}\": {}", JsonNumber(value))?;
+                }
+                write!(writer, "}}")?;
+            }
+            writeln!(writer, "\n]")?;
+        }
+        PerPairFormat::Csv => {
+            writeln!(writer, "{}", fields.join(","))?;
+            for index in 0..distances.len() {
+                let record = options.record(pairs, distances, index);
+                let line: Vec<String> = record.iter().map(f64::to_string).collect();
+                writeln!(writer, "{}", line.join(","))?;
+            }
+        }
+        PerPairFormat::F64 => {
+            for index in 0..distances.len() {
+                for value in options.record(pairs, distances, index) {
+                    writer.write_all(&value.to_le_bytes())?;
+                }
+            }
+        }
+    }
+    writer.flush()
+}
+
+/// Writes a GeoJSON feature collection with a `LineString` feature from the
+/// first to the second point of every pair, with its index and computed
+/// distance as properties.
+pub fn write_geojson(writer: impl Write, pairs: &Pairs, distances: &[f64]) -> io::Result<()> {
+    write_geojson_in(writer, pairs, distances, AngleUnit::Degrees)
+}
+
+/// `write_geojson` for coordinates in `unit`. GeoJSON positions are in
+/// degrees, so they are converted.
+pub fn write_geojson_in(
+    mut writer: impl Write,
+    pairs: &Pairs,
+    distances: &[f64],
+    unit: AngleUnit,
+) -> io::Result<()> {
+    debug_assert_eq!(pairs.len(), distances.len());
+    let degrees = |angles: &[f64], index: usize| unit.to_degrees(angles[index]);
+    write!(writer, "{{\"type\": \"FeatureCollection\", \"features\": [")?;
+    for (index, distance) in distances.iter().enumerate() {
+        let separator = if index == 0 { "" } else { "," };
+        let coordinates = format!(
+            "[[{}, {}], [{}, {}]]",
+            degrees(&pairs.x0, index),
+            degrees(&pairs.y0, index),
+            degrees(&pairs.x1, index),
+            degrees(&pairs.y1, index)
+        );
+        let distance = JsonNumber(*distance);
+        write!(
+            writer,
+            "{separator}\n  {{\"type\": \"Feature\", \
+             \"geometry\": {{\"type\": \"LineString\", \"coordinates\": {coordinates}}}, \
+             \"properties\": {{\"index\": {index}, \"distance\": {distance}}}}}"
+        )?;
+    }
+    writeln!(writer, "\n]}}")?;
+    writer.flush()
+}
+
+/// A number written as JSON: `null` if it is not finite, as `to_json`
+/// writes it, since JSON has no NaN or infinities.
+struct JsonNumber(f64);
+
+impl fmt::Display for JsonNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_finite() {
+            write!(f, "{}", self.0)
+        } else {
+            f.write_str("null")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,216 @@
+use super::{PerPairFormat, PerPairOptions, write, write_geojson, write_geojson_in};
+use crate::geo::AngleUnit;
+use crate::pairs::Pairs;
+use crate::parse::parse;
+
+fn pairs() -> Pairs {
+    let mut pairs = Pairs::default();
+    pairs.push(0.0, 0.0, 90.0, 0.0);
+    pairs.push(10.0, 20.0, 10.0, 20.0);
+    pairs
+}
+
+fn written(options: &PerPairOptions) -> Vec<u8> {
+    let mut output = Vec::new();
+    write(&mut output, &pairs(), &[1.5, 0.0], options).unwrap();
+    output
+}
+
+#[test]
+fn test_format_from_path() {
+    assert_eq!(
+        Some(PerPairFormat::Json),
+        PerPairFormat::from_path("out.json")
+    );
+    assert_eq!(
+        Some(PerPairFormat::Csv),
+        PerPairFormat::from_path("a.b/out.csv")
+    );
+    assert_eq!(
+        Some(PerPairFormat::F64),
+        PerPairFormat::from_path("out.f64")
+    );
+    assert_eq!(None, PerPairFormat::from_path("out"));
+}
+
+#[test]
+fn test_csv_distances() {
+    let options = PerPairOptions {
+        format: PerPairFormat::Csv,
+        coordinates: false,
+        bearings: false,
+        midpoints: false,
+        angle_unit: AngleUnit::Degrees,
+    };
+    assert_eq!(b"distance\n1.5\n0\n".to_vec(), written(&options));
+}
+
+#[test]
+fn test_csv_coordinates_and_bearings() {
+    let options = PerPairOptions {
+        format: PerPairFormat::Csv,
+        coordinates: true,
+        bearings: true,
+        midpoints: false,
+        angle_unit: AngleUnit::Degrees,
+    };
+    let expected = "x0,y0,x1,y1,distance,initial_bearing,final_bearing\n\
+                    0,0,90,0,1.5,90,90\n\
+                    10,20,10,20,0,0,180\n";
+    assert_eq!(expected, String::from_utf8(written(&options)).unwrap());
+}
+
+#[test]
+fn test_json_distances() {
+    let options = PerPairOptions {
+        format: PerPairFormat::Json,
+        coordinates: false,
+        bearings: false,
+        midpoints: false,
+        angle_unit: AngleUnit::Degrees,
+    };
+    let expected = "[\n  {\"distance\": 1.5},\n  {\"distance\": 0}\n]\n";
+    assert_eq!(expected, String::from_utf8(written(&options)).unwrap());
+}
+
+#[test]
+fn test_json_non_finite_as_null() {
+    let options = PerPairOptions {
+        format: PerPairFormat::Json,
+        coordinates: false,
+        bearings: false,
+        midpoints: false,
+        angle_unit: AngleUnit::Degrees,
+    };
+    let mut output = Vec::new();
+    write(&mut output, &pairs(), &[f64::NAN, f64::INFINITY], &options).unwrap();
+    let expected = "[\n  {\"distance\": null},\n  {\"distance\": null}\n]\n";
+    assert_eq!(expected, String::from_utf8(output).unwrap());
+
+    let mut output = Vec::new();
+    write_geojson(&mut output, &pairs(), &[f64::NAN, f64::NEG_INFINITY]).unwrap();
+    assert!(parse(std::str::from_utf8(&output).unwrap()).is_ok());
+}
+
+#[test]
+fn test_f64_records() {
+    let options = PerPairOptions {
+        format: PerPairFormat::F64,
+        coordinates: true,
+        bearings: false,
+        midpoints: false,
+        angle_unit: AngleUnit::Degrees,
+    };
+    let output = written(&options);
+    let values: Vec<f64> = output
+        .chunks_exact(8)
+        .map(|bytes| f64::from_le_bytes(bytes.try_into().unwrap()))
+        .collect();
+    assert_eq!(
+        vec![0.0, 0.0, 90.0, 0.0, 1.5, 10.0, 20.0, 10.0, 20.0, 0.0],
+        values
+    );
+}
+
+#[test]
+fn test_f64_midpoints() {
+    let options = PerPairOptions {
+        format: PerPairFormat::F64,
+        coordinates: false,
+        bearings: false,
+        midpoints: true,
+        angle_unit: AngleUnit::Degrees,
+    };
+    let output = written(&options);
+    let values: Vec<f64> = output
+        .chunks_exact(8)
+        .map(|bytes| f64::from_le_bytes(bytes.try_into().unwrap()))
+        .collect();
+    let expected = [1.5, 45.0, 0.0, 0.0, 10.0, 20.0];
+    assert_eq!(expected.len(), values.len());
+    for (expected, value) in expected.iter().zip(&values) {
+        assert!(
+            (expected - value).abs() < 1e-12,
+            "{expected} versus {value}"
+        );
+    }
+}
+
+#[test]
+fn test_geojson_line_strings() {
+    let mut output = Vec::new();
+    write_geojson(&mut output, &pairs(), &[1.5, 0.0]).unwrap();
+    let written = parse(std::str::from_utf8(&output).unwrap()).unwrap();
+    let expected = parse(
+        r#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature",
+             "geometry": {"type": "LineString", "coordinates": [[0, 0], [90, 0]]},
+             "properties": {"index": 0, "distance": 1.5}},
+            {"type": "Feature",
+             "geometry": {"type": "LineString", "coordinates": [[10, 20], [10, 20]]},
+             "properties": {"index": 1, "distance": 0}}
+        ]}"#,
+    )
+    .unwrap();
+    assert_eq!(expected, written);
+}
+
+#[test]
+fn test_bearings_and_midpoints_in_angle_unit() {
+    let options = PerPairOptions {
+        format: PerPairFormat::F64,
+        coordinates: false,
+        bearings: true,
+        midpoints: true,
+        angle_unit: AngleUnit::Degrees,
+    };
+    let mut gradians = Pairs::default();
+    for pair in pairs().iter() {
+        let [x0, y0, x1, y1] = [pair.p0.lon, pair.p0.lat, pair.p1.lon, pair.p1.lat]
+            .map(|angle| AngleUnit::Gradians.from_degrees(angle));
+        gradians.push(x0, y0, x1, y1);
+    }
+    let mut output = Vec::new();
+    let in_gradians = PerPairOptions {
+        angle_unit: AngleUnit::Gradians,
+        ..options
+    };
+    write(&mut output, &gradians, &[1.5, 0.0], &in_gradians).unwrap();
+    let values = output
+        .chunks_exact(8)
+        .map(|bytes| f64::from_le_bytes(bytes.try_into().unwrap()));
+    let degrees = written(&options);
+    let expected = degrees.chunks_exact(8).enumerate().map(|(index, bytes)| {
+        let value = f64::from_le_bytes(bytes.try_into().unwrap());
+        // The distance, then the bearings and midpoint in the unit.
+        match index % 5 {
+            0 => value,
+            _ => AngleUnit::Gradians.from_degrees(value),
+        }
+    });
+    for (expected, value) in expected.zip(values) {
+        assert!((expected - value).abs() < 1e-9, "{expected} versus {value}");
+    }
+}
+
+#[test]
+fn test_geojson_in_degrees() {
+    let mut gradians = Pairs::default();
+    gradians.push(0.0, 0.0, 100.0, 0.0);
+    gradians.push(-50.0, 50.0, 200.0, -100.0);
+    let mut output = Vec::new();
+    write_geojson_in(&mut output, &gradians, &[1.5, 0.0], AngleUnit::Gradians).unwrap();
+    let written = parse(std::str::from_utf8(&output).unwrap()).unwrap();
+    let expected = parse(
+        r#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature",
+             "geometry": {"type": "LineString", "coordinates": [[0, 0], [90, 0]]},
+             "properties": {"index": 0, "distance": 1.5}},
+            {"type": "Feature",
+             "geometry": {"type": "LineString", "coordinates": [[-45, 45], [180, -90]]},
+             "properties": {"index": 1, "distance": 0}}
+        ]}"#,
+    )
+    .unwrap();
+    assert_eq!(expected, written);
+}
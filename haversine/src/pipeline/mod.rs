@@ -0,0 +1,1308 @@
+//! The read, parse and compute flow of `compute` as a builder, for programs
+//! that embed it rather than run the command line tool.
+
+use crate::affinity;
+use crate::answers::Answers;
+use crate::cancel::CancelToken;
+use crate::checkpoint::Checkpoint;
+use crate::columnar;
+use crate::compute::{ComputeDriver, ComputeResult, Precision, Reduction};
+use crate::geo::{
+    AngleUnit, BoundingBox, DistanceFn, DistanceUnit, Kernel, Point, RadiusModel,
+    radians_to_degrees, spherical_centroid,
+};
+use crate::math::Math;
+use crate::memory::{self, MemoryStats};
+use crate::pairs::{
+    Canonicalized, CountMismatch, Pairs, PairsF32, PairsReader, SampleSize, Sampler, check_count,
+    parse_fast_schema, routes_from_values,
+};
+use crate::parse::{
+    DuplicateKeyPolicy, Encoding, Limits, LoneSurrogatePolicy, Map, NumberRangePolicy, Parser,
+    ParsingError, Simd, Value, detect_encoding,
+};
+use crate::per_pair::{self, PerPairOptions};
+use crate::reader::{self, DEFAULT_BUFFER_SIZE, InputSource, IoSource, IoStrategy, Overlap};
+use crate::rng::Algorithm;
+use crate::sink::FileSink;
+use crate::stats::{Estimate, Histogram, Stats};
+use crate::timer::read_cpu_timer;
+use crate::validate::relative_error;
+use crate::{Error, Result};
+use std::borrow::Cow;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+mod outcome;
+pub use outcome::{AnswersCheck, AverageCheck, Baseline, RunError};
+
+/// Decimal digits of the coordinates of the routes, those of Google's
+/// encoding.
+const POLYLINE_PRECISION: u32 = 5;
+
+/// Largest relative error of the stored average, or of an answer, accepted
+/// by default.
+const DEFAULT_TOLERANCE: f64 = 1e-6;
+
+/// How the pairs of the input are laid out.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    /// One object with the `pairs` array and the other members.
+    #[default]
+    Json,
+    /// JSON Lines: one pair object per line and nothing else, read as a
+    /// stream.
+    Ndjson,
+    /// The binary layout of `export-columnar`, loaded without parsing.
+    Columnar,
+}
+
+/// What is done with coordinates out of range, past 180 degrees of
+/// longitude or 90 of latitude.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Coordinates {
+    /// Left as they are read.
+    #[default]
+    AsRead,
+    /// Longitudes wrapped and latitudes clamped, as by
+    /// `Pairs::canonicalize_in`.
+    Canonicalize,
+    /// Rejected, as by `Pairs::check_coordinates_in`.
+    Strict,
+}
+
+/// Reads a pairs file, parses it and computes its average distance.
+///
+/// ```
+/// use haversine::geo::Kernel;
+/// use haversine::pipeline::{InputFormat, Pipeline};
+///
+/// let result = Pipeline::new()
+///     .text(r#"{"pairs": [{"x0": 0, "y0": 0, "x1": 90, "y1": 0}], "radius": 2, "avg_dist": 0}"#)
+///     .format(InputFormat::Json)
+///     .kernel(Kernel::CachedTrig)
+///     .threads(2)
+///     .run()
+///     .unwrap();
+/// assert_eq!(1, result.n_pairs);
+/// assert!((result.average - std::f64::consts::PI).abs() < 1e-12);
+/// assert_eq!(Some(0.0), result.stored_average);
+/// ```
+///
+/// `run` is `load` followed by `run_loaded`, for callers that report
+/// between the two.
+pub struct Pipeline {
+    origin: Option<Origin>,
+    format: InputFormat,
+    io: IoStrategy,
+    read_buffer: usize,
+    huge_pages: bool,
+    stream: bool,
+    tape: bool,
+    simd: bool,
+    /// Whether the fast schema is tried, `None` for quietly.
+    fast_schema: Option<bool>,
+    transcode: bool,
+    limits: Limits,
+    duplicate_keys: DuplicateKeyPolicy,
+    lone_surrogates: LoneSurrogatePolicy,
+    out_of_range: NumberRangePolicy,
+    allow_control_chars: bool,
+    sample: Option<(SampleSize, u64)>,
+    radius: Option<f64>,
+    units: Option<DistanceUnit>,
+    angle_unit: AngleUnit,
+    coordinates: Coordinates,
+    bbox: Option<BoundingBox>,
+    interleave: bool,
+    precision: Precision,
+    kernel: Kernel,
+    distance: Option<Box<dyn DistanceFn>>,
+    math: Math,
+    threads: usize,
+    reduction: Reduction,
+    pin_threads: bool,
+    wide_accumulator: bool,
+    keep_distances: bool,
+    cancel: Option<CancelToken>,
+    read_progress: Option<Arc<AtomicUsize>>,
+    /// Path of the checkpoints and how often they are saved.
+    checkpoint: Option<(PathBuf, Duration)>,
+    resume: bool,
+    tolerance: f64,
+    answers: Option<PathBuf>,
+    stats: bool,
+    histogram: Option<usize>,
+    per_pair: Option<(PathBuf, PerPairOptions)>,
+    geojson: Option<PathBuf>,
+    memory_stats: bool,
+}
+
+/// Where the pipeline reads from.
+enum Origin {
+    File(PathBuf),
+    Stdin,
+    Source(Box<dyn InputSource>),
+}
+
+/// Outcome of a `Pipeline` run.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct RunResult {
+    pub n_pairs: usize,
+    /// Radius of the sphere the distances were computed on.
+    pub radius: f64,
+    pub average: f64,
+    /// Average stored in the input, if it has one.
+    pub stored_average: Option<f64>,
+    /// Distance of every pair, if requested.
+    pub distances: Vec<f64>,
+    /// Whether the run was cancelled while computing. The other fields then
+    /// cover the pairs computed, `n_pairs` of them.
+    pub cancelled: bool,
+    /// Bytes of the input.
+    pub bytes: u64,
+    /// Time reading the input. JSON Lines are parsed as they are read, so
+    /// their time is all parsing.
+    pub read_time: Duration,
+    pub parse_time: Duration,
+    pub compute_time: Duration,
+    /// CPU timer ticks computing, cycles where it counts them.
+    pub compute_cycles: u64,
+    /// Memory counters of the computing, if asked for.
+    pub compute_memory: Option<MemoryStats>,
+    /// Pair the run resumed from, if it resumed from a checkpoint.
+    pub resumed_from: Option<usize>,
+    /// Checkpoints saved during the run.
+    pub saved_checkpoints: usize,
+    /// Average accumulated in double-double arithmetic, if asked for.
+    pub wide_average: Option<f64>,
+    /// Pairs the sample was drawn from, if sampling.
+    pub population: Option<usize>,
+    /// Average of all the pairs estimated from the sample, unless it is
+    /// empty.
+    pub estimate: Option<Estimate>,
+    pub check: AverageCheck,
+    /// The distances checked against the answers file, if there is one.
+    pub answers: Option<AnswersCheck>,
+    /// The `f64` run of an `f32` one.
+    pub baseline: Option<Baseline>,
+    /// Statistics of the distances, if asked for and there are any.
+    pub stats: Option<Stats>,
+    pub histogram: Option<Histogram>,
+    /// Spherical centroid of both points of every pair, in degrees, along
+    /// with the statistics.
+    pub centroid: Option<Point>,
+}
+
+impl RunResult {
+    /// Whether the computed average disproves the stored one, which only
+    /// the distances of all the pairs can.
+    pub fn mismatch(&self) -> bool {
+        matches!(self.check, AverageCheck::Outside(_)) && self.population.is_none()
+    }
+}
+
+/// The input of a `Pipeline` once read and parsed, ready to compute.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct Loaded {
+    /// Members of the document other than the pairs; for a columnar file,
+    /// those of its header.
+    pub members: Map,
+    /// The pairs to compute, with the segments of the routes after those
+    /// of the input.
+    pub pairs: Pairs,
+    /// Radius of the sphere, in the unit of the distances.
+    pub radius: f64,
+    /// Average stored in the input, if it has one.
+    pub stored_average: Option<f64>,
+    /// Routes of the document, in degrees.
+    pub routes: Vec<Vec<Point>>,
+    /// Pairs the sample was drawn from, if sampling. `pairs` holds the
+    /// sample.
+    pub population: Option<usize>,
+    /// Pairs left out by the bounding box, if there is one.
+    pub excluded: Option<usize>,
+    /// Bytes of the input.
+    pub bytes: u64,
+    /// Time reading the input, zero where it is parsed as it is read.
+    pub read_time: Duration,
+    pub parse_time: Duration,
+    /// How a file or stdin was read, `None` for other sources.
+    pub io: Option<IoStrategy>,
+    /// How much of the reading was hidden behind parsing, known for sync
+    /// I/O only.
+    pub overlap: Option<Overlap>,
+    pub warnings: Vec<Warning>,
+    pairs_f32: Option<PairsF32>,
+}
+
+/// Something a load did otherwise than asked, or found amiss in the input
+/// without failing on it. Displayed as a sentence, to be logged as is.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Warning {
+    /// The file could not be read with the I/O asked for, so it was read
+    /// with sync I/O.
+    IoFallback(IoStrategy, io::Error),
+    /// The input does not fit the fast schema, so the generic parser read
+    /// it.
+    NotFastSchema,
+    /// The count hint of the document is not the number of pairs.
+    CountMismatch(CountMismatch),
+    /// Coordinates out of range were canonicalized.
+    Canonicalized(Canonicalized),
+    /// The coordinates could not be backed by huge pages.
+    NoHugePages,
+    /// The coordinates could not be spread over the NUMA nodes.
+    NotInterleaved,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::IoFallback(strategy, error) => write!(
+                f,
+                "Cannot read the input with {} I/O ({error}), using sync",
+                strategy.name()
+            ),
+            Warning::NotFastSchema => write!(
+                f,
+                "The input does not fit the fast schema, using the generic parser"
+            ),
+            Warning::CountMismatch(mismatch) => write!(
+                f,
+                "The count hint declares {} pairs, but {} were parsed",
+                mismatch.declared, mismatch.parsed
+            ),
+            Warning::Canonicalized(canonical) => write!(
+                f,
+                "Wrapped {} longitudes and clamped {} latitudes out of range; {} coordinates are not finite",
+                canonical.wrapped, canonical.clamped, canonical.non_finite
+            ),
+            Warning::NoHugePages => write!(f, "Could not back the coordinates with huge pages"),
+            Warning::NotInterleaved => write!(
+                f,
+                "Could not interleave the coordinates over the NUMA nodes"
+            ),
+        }
+    }
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self {
+            origin: None,
+            format: InputFormat::default(),
+            io: IoStrategy::default(),
+            read_buffer: DEFAULT_BUFFER_SIZE,
+            huge_pages: false,
+            stream: false,
+            tape: false,
+            simd: false,
+            fast_schema: None,
+            transcode: true,
+            limits: Limits::default(),
+            duplicate_keys: DuplicateKeyPolicy::default(),
+            lone_surrogates: LoneSurrogatePolicy::default(),
+            out_of_range: NumberRangePolicy::default(),
+            allow_control_chars: false,
+            sample: None,
+            radius: None,
+            units: None,
+            angle_unit: AngleUnit::default(),
+            coordinates: Coordinates::default(),
+            bbox: None,
+            interleave: false,
+            precision: Precision::default(),
+            kernel: Kernel::default(),
+            distance: None,
+            math: Math::default(),
+            threads: 1,
+            reduction: Reduction::default(),
+            pin_threads: false,
+            wide_accumulator: false,
+            keep_distances: false,
+            cancel: None,
+            read_progress: None,
+            checkpoint: None,
+            resume: false,
+            tolerance: DEFAULT_TOLERANCE,
+            answers: None,
+            stats: false,
+            histogram: None,
+            per_pair: None,
+            geojson: None,
+            memory_stats: false,
+        }
+    }
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the pairs from the file at `path`, in the way set by `io`.
+    pub fn input(mut self, path: impl Into<PathBuf>) -> Self {
+        self.origin = Some(Origin::File(path.into()));
+        self
+    }
+
+    /// Reads the pairs from stdin, on a background thread.
+    pub fn stdin(mut self) -> Self {
+        self.origin = Some(Origin::Stdin);
+        self
+    }
+
+    /// Reads the pairs from `text` instead of a file.
+    pub fn text(self, text: impl Into<String>) -> Self {
+        self.source(text.into().into_bytes())
+    }
+
+    /// Reads the pairs from `source`, such as a decompression stream.
+    pub fn source(mut self, source: impl InputSource + 'static) -> Self {
+        self.origin = Some(Origin::Source(Box::new(source)));
+        self
+    }
+
+    pub fn format(mut self, format: InputFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Sets how a file is read. Where the system does not offer it, the
+    /// file is read with sync I/O and a `Warning::IoFallback` given.
+    pub fn io(mut self, strategy: IoStrategy) -> Self {
+        self.io = strategy;
+        self
+    }
+
+    /// Sets the size of the buffers a file or stdin is read into.
+    pub fn read_buffer(mut self, size: usize) -> Self {
+        self.read_buffer = size;
+        self
+    }
+
+    /// Backs the read buffers and the coordinates with huge pages.
+    pub fn huge_pages(mut self, enabled: bool) -> Self {
+        self.huge_pages = enabled;
+        self
+    }
+
+    /// Parses a JSON document in chunks as it is read, rather than reading
+    /// it whole first.
+    pub fn stream(mut self, enabled: bool) -> Self {
+        self.stream = enabled;
+        self
+    }
+
+    /// Reads the pairs of a JSON document from a tape of it rather than
+    /// from a `Value` per pair. The first of duplicate keys wins, and
+    /// numbers out of range are not checked.
+    pub fn tape(mut self, enabled: bool) -> Self {
+        self.tape = enabled;
+        self
+    }
+
+    /// Finds where the tokens of the tape start with a vectorized first
+    /// pass.
+    pub fn simd(mut self, enabled: bool) -> Self {
+        self.simd = enabled;
+        self
+    }
+
+    /// Sets whether the parser specialized for the known pairs schema is
+    /// tried first. By default it is, quietly; asked for, a
+    /// `Warning::NotFastSchema` tells when the input does not fit. It
+    /// checks no limits, so is not tried with them.
+    pub fn fast_schema(mut self, enabled: bool) -> Self {
+        self.fast_schema = Some(enabled);
+        self
+    }
+
+    /// Converts a JSON document in UTF-16 or UTF-32 to UTF-8, on by
+    /// default. Otherwise such a document is an `Error::Encoding`.
+    pub fn transcode(mut self, enabled: bool) -> Self {
+        self.transcode = enabled;
+        self
+    }
+
+    /// Bounds what the parsers accept, for input from untrusted sources.
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn duplicate_keys(mut self, policy: DuplicateKeyPolicy) -> Self {
+        self.duplicate_keys = policy;
+        self
+    }
+
+    pub fn lone_surrogates(mut self, policy: LoneSurrogatePolicy) -> Self {
+        self.lone_surrogates = policy;
+        self
+    }
+
+    pub fn out_of_range(mut self, policy: NumberRangePolicy) -> Self {
+        self.out_of_range = policy;
+        self
+    }
+
+    pub fn allow_control_chars(mut self, allow: bool) -> Self {
+        self.allow_control_chars = allow;
+        self
+    }
+
+    /// Computes over a random sample of the pairs, drawn with `seed` as
+    /// they are read where they are streamed.
+    pub fn sample(mut self, size: SampleSize, seed: u64) -> Self {
+        self.sample = Some((size, seed));
+        self
+    }
+
+    /// Sets the radius of the sphere, in place of that of the input. JSON
+    /// Lines have none, so without it the mean radius of the Earth is
+    /// taken.
+    pub fn radius(mut self, radius: f64) -> Self {
+        self.radius = Some(radius);
+        self
+    }
+
+    /// Sets the unit of the distances. The radius is taken to be in
+    /// kilometers, and converted to it.
+    pub fn units(mut self, unit: DistanceUnit) -> Self {
+        self.units = Some(unit);
+        self
+    }
+
+    /// Sets the unit of the coordinates of the pairs. The routes are in
+    /// degrees whatever it is.
+    pub fn angle_unit(mut self, unit: AngleUnit) -> Self {
+        self.angle_unit = unit;
+        self
+    }
+
+    pub fn coordinates(mut self, coordinates: Coordinates) -> Self {
+        self.coordinates = coordinates;
+        self
+    }
+
+    /// Leaves out the pairs with a point outside `bbox`, which is in
+    /// degrees whatever the angle unit.
+    pub fn bbox(mut self, bbox: BoundingBox) -> Self {
+        self.bbox = Some(bbox);
+        self
+    }
+
+    /// Spreads the coordinates over the NUMA nodes once loaded.
+    pub fn interleave(mut self, enabled: bool) -> Self {
+        self.interleave = enabled;
+        self
+    }
+
+    /// Sets the precision of the kernel. `f32` computes the haversine from
+    /// a copy of the coordinates made at load.
+    pub fn precision(mut self, precision: Precision) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    pub fn kernel(mut self, kernel: Kernel) -> Self {
+        self.kernel = kernel;
+        self
+    }
+
+    /// Computes the distances with `distance` in place of the kernel, as
+    /// `ComputeDriver::run_distance` does. The radius of the run is still
+    /// reported, but only `distance` decides the sphere it measures on.
+    pub fn distance(mut self, distance: impl DistanceFn + 'static) -> Self {
+        self.distance = Some(Box::new(distance));
+        self
+    }
+
+    pub fn math(mut self, math: Math) -> Self {
+        self.math = math;
+        self
+    }
+
+    /// Sets the number of threads, as `ComputeDriver::threads` does.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    pub fn reduction(mut self, reduction: Reduction) -> Self {
+        self.reduction = reduction;
+        self
+    }
+
+    /// Pins the threads to cores, as `ComputeDriver::pin_threads` does.
+    pub fn pin_threads(mut self, enabled: bool) -> Self {
+        self.pin_threads = enabled;
+        self
+    }
+
+    /// Also accumulates the sum in double-double arithmetic.
+    pub fn wide_accumulator(mut self, enabled: bool) -> Self {
+        self.wide_accumulator = enabled;
+        self
+    }
+
+    /// Keeps the distance of every pair in `RunResult::distances`. They are
+    /// kept anyway when sampling, checking answers, writing statistics or
+    /// per-pair files.
+    pub fn keep_distances(mut self, enabled: bool) -> Self {
+        self.keep_distances = enabled;
+        self
+    }
+
+    /// Stops the run, while parsing or computing, once `token` is
+    /// cancelled.
+    pub fn cancel(mut self, token: CancelToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Adds the bytes read to `counter` as they are read, for another
+    /// thread to report. A mapped file is read all at once, uncounted.
+    pub fn read_progress(mut self, counter: Arc<AtomicUsize>) -> Self {
+        self.read_progress = Some(counter);
+        self
+    }
+
+    /// Saves the state of the run to `path` about every `interval`, and
+    /// removes it once the run ends uncancelled. Only the kernels in `f64`
+    /// save checkpoints.
+    pub fn checkpoint(mut self, path: impl Into<PathBuf>, interval: Duration) -> Self {
+        self.checkpoint = Some((path.into(), interval));
+        self
+    }
+
+    /// Resumes from the checkpoint, if there is one. One saved by a run of
+    /// other pairs or settings is a `RunError::ForeignCheckpoint`.
+    pub fn resume(mut self, enabled: bool) -> Self {
+        self.resume = enabled;
+        self
+    }
+
+    /// Sets the largest relative error of the stored average, and of the
+    /// answers, accepted.
+    pub fn tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Checks the distances against the answers file at `path`.
+    pub fn answers(mut self, path: impl Into<PathBuf>) -> Self {
+        self.answers = Some(path.into());
+        self
+    }
+
+    /// Computes the statistics of the distances and the centroid of the
+    /// points.
+    pub fn stats(mut self, enabled: bool) -> Self {
+        self.stats = enabled;
+        self
+    }
+
+    /// Computes a histogram of the distances in `buckets`, with the
+    /// statistics.
+    pub fn histogram(mut self, buckets: usize) -> Self {
+        self.histogram = Some(buckets);
+        self
+    }
+
+    /// Writes the distance of every pair to `path` as `options` say, with
+    /// the coordinates in the angle unit of the pipeline.
+    pub fn per_pair(mut self, path: impl Into<PathBuf>, options: PerPairOptions) -> Self {
+        self.per_pair = Some((path.into(), options));
+        self
+    }
+
+    /// Writes the pairs and their distances to `path` as GeoJSON.
+    pub fn export_geojson(mut self, path: impl Into<PathBuf>) -> Self {
+        self.geojson = Some(path.into());
+        self
+    }
+
+    /// Counts the memory of the computing in `RunResult::compute_memory`,
+    /// resetting the peak of live allocations first.
+    pub fn memory_stats(mut self, enabled: bool) -> Self {
+        self.memory_stats = enabled;
+        self
+    }
+
+    /// Runs the pipeline. Input that is not a pairs file of the format, or
+    /// whose radius or average is not a number, is an error.
+    pub fn run(&mut self) -> Result<RunResult> {
+        let loaded = self.load()?;
+        Ok(self.run_loaded(&loaded, &AtomicUsize::new(0))?)
+    }
+
+    /// Computes the distances of `loaded` as `compute` does, from and to
+    /// the checkpoint if there is one, and checks and reports on them as
+    /// the pipeline is set to. The distances of a cancelled run may not be
+    /// those of the first pairs, so it is not checked, and writes no
+    /// per-pair files.
+    pub fn run_loaded(
+        &self,
+        loaded: &Loaded,
+        processed: &AtomicUsize,
+    ) -> Result<RunResult, RunError> {
+        // Only the f64 kernels save checkpoints.
+        let checkpoint = self
+            .checkpoint
+            .as_ref()
+            .filter(|_| self.distance.is_none() && loaded.pairs_f32.is_none());
+        let resume = match checkpoint {
+            Some((path, _)) if self.resume => self.resume_from(loaded, path)?,
+            _ => None,
+        };
+
+        let memory_start = self.memory_stats.then(|| {
+            memory::reset_peak_live();
+            MemoryStats::now()
+        });
+        let start = Instant::now();
+        let start_cycles = read_cpu_timer();
+        let mut saved_checkpoints = 0;
+        let result = match checkpoint {
+            Some((path, interval)) => {
+                // A checkpoint that cannot be saved fails the run once it
+                // ends, with no more tried.
+                let mut failed = None;
+                let mut save = |checkpoint: &Checkpoint| {
+                    if failed.is_none() {
+                        match checkpoint.write(path) {
+                            Ok(()) => saved_checkpoints += 1,
+                            Err(error) => failed = Some(error),
+                        }
+                    }
+                };
+                let result = self.compute_checkpointed(
+                    loaded,
+                    processed,
+                    resume.as_ref(),
+                    *interval,
+                    &mut save,
+                );
+                if let Some(error) = failed {
+                    return Err(RunError::SaveCheckpoint(error));
+                }
+                result
+            }
+            None => self.compute(loaded, processed),
+        };
+        let compute_time = start.elapsed();
+        let compute_cycles = read_cpu_timer() - start_cycles;
+        let compute_memory = memory_start.map(|start| MemoryStats::now().since(&start));
+        // A cancelled run is left to be resumed.
+        if let Some((path, _)) = checkpoint.filter(|_| !result.cancelled) {
+            match fs::remove_file(path) {
+                Err(error) if error.kind() != io::ErrorKind::NotFound => {
+                    return Err(RunError::RemoveCheckpoint(error));
+                }
+                _ => {}
+            }
+        }
+
+        let baseline = (loaded.pairs_f32.is_some() && self.distance.is_none() && !result.cancelled)
+            .then(|| self.baseline(loaded, result.average, compute_time));
+        let estimate = loaded
+            .population
+            .and_then(|population| Estimate::new(&result.distances, population));
+        let check = self.check_average(loaded, &result, estimate.as_ref());
+        let answers = match &self.answers {
+            Some(path) if !result.cancelled => {
+                Some(self.check_answers(path, &result.distances, result.average)?)
+            }
+            _ => None,
+        };
+        let stats = (self.stats || self.histogram.is_some())
+            .then(|| Stats::new(&result.distances))
+            .flatten();
+        let histogram = self
+            .histogram
+            .zip(stats.as_ref())
+            .map(|(buckets, stats)| Histogram::new(&result.distances, stats, buckets));
+        let centroid = stats
+            .as_ref()
+            .and_then(|_| centroid(&loaded.pairs, self.angle_unit));
+        if !result.cancelled {
+            self.write_per_pair(loaded, &result.distances)?;
+        }
+
+        Ok(RunResult {
+            n_pairs: result.n_pairs,
+            radius: loaded.radius,
+            average: result.average,
+            stored_average: loaded.stored_average,
+            distances: result.distances,
+            cancelled: result.cancelled,
+            bytes: loaded.bytes,
+            read_time: loaded.read_time,
+            parse_time: loaded.parse_time,
+            compute_time,
+            compute_cycles,
+            compute_memory,
+            resumed_from: resume.map(|checkpoint| checkpoint.position),
+            saved_checkpoints,
+            wide_average: result.wide_average,
+            population: loaded.population,
+            estimate,
+            check,
+            answers,
+            baseline,
+            stats,
+            histogram,
+            centroid,
+        })
+    }
+
+    /// Reads and parses the input, and prepares its pairs to compute.
+    pub fn load(&mut self) -> Result<Loaded> {
+        let mut origin = self.origin.take().ok_or_else(|| {
+            Error::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the pipeline has no input",
+            ))
+        })?;
+        let loaded = match &mut origin {
+            Origin::File(path) => IoSource::open(path, self.io, self.read_buffer, self.huge_pages)
+                .map_err(Error::from)
+                .and_then(|source| self.load_io(source)),
+            Origin::Stdin => self.load_io(IoSource::stdin(self.read_buffer, self.huge_pages)),
+            Origin::Source(source) => self.load_from(source),
+        };
+        // Put back, so a file can be run again.
+        self.origin = Some(origin);
+        loaded
+    }
+
+    /// Computes the distances of `loaded`, storing the number of pairs
+    /// computed so far in `processed` as `ComputeDriver::run_with_progress`
+    /// does.
+    pub fn compute(&self, loaded: &Loaded, processed: &AtomicUsize) -> ComputeResult {
+        let driver = self.driver(loaded.radius);
+        match (&self.distance, &loaded.pairs_f32) {
+            (Some(distance), _) => {
+                driver.run_distance_with_progress(&loaded.pairs, distance.as_ref(), processed)
+            }
+            (None, Some(pairs)) => driver.run_f32_with_progress(pairs, processed),
+            (None, None) => driver.run_with_progress(&loaded.pairs, processed),
+        }
+    }
+
+    /// Computes like `compute` with the kernel in `f64`, passing `save` the
+    /// state of the run about every `interval`, as
+    /// `ComputeDriver::run_checkpointed` does from `resume`.
+    pub fn compute_checkpointed(
+        &self,
+        loaded: &Loaded,
+        processed: &AtomicUsize,
+        resume: Option<&Checkpoint>,
+        interval: Duration,
+        save: &mut dyn FnMut(&Checkpoint),
+    ) -> ComputeResult {
+        self.driver(loaded.radius).run_checkpointed(
+            &loaded.pairs,
+            processed,
+            resume,
+            interval,
+            save,
+        )
+    }
+
+    /// Identifies the pairs and settings of a run, for its checkpoints.
+    pub fn fingerprint(&self, loaded: &Loaded) -> u64 {
+        self.driver(loaded.radius).fingerprint(&loaded.pairs)
+    }
+
+    /// Reads the checkpoint at `path`, if there is one, checking it is of
+    /// the pairs and settings of `loaded`.
+    fn resume_from(&self, loaded: &Loaded, path: &Path) -> Result<Option<Checkpoint>, RunError> {
+        match Checkpoint::read(path).map_err(RunError::Resume)? {
+            Some(checkpoint) if checkpoint.fingerprint == self.fingerprint(loaded) => {
+                Ok(Some(checkpoint))
+            }
+            Some(_) => Err(RunError::ForeignCheckpoint),
+            None => Ok(None),
+        }
+    }
+
+    /// Computes `loaded` again with the kernel in `f64`, the standard
+    /// library and one thread, to compare an `f32` run taking
+    /// `compute_time` to average `average` with.
+    fn baseline(&self, loaded: &Loaded, average: f64, compute_time: Duration) -> Baseline {
+        let start = Instant::now();
+        let baseline = ComputeDriver::new(loaded.radius)
+            .angle_unit(self.angle_unit)
+            .run(&loaded.pairs)
+            .average;
+        let baseline_time = start.elapsed();
+        Baseline {
+            average: baseline,
+            compute_time: baseline_time,
+            speedup: baseline_time.as_secs_f64() / compute_time.as_secs_f64(),
+            relative_error: relative_error(baseline, average),
+        }
+    }
+
+    /// Checks the stored average against the computed one, or the estimate
+    /// of a sample. Only the distances of all the pairs on the sphere of
+    /// the input, in its unit, can be checked against it.
+    fn check_average(
+        &self,
+        loaded: &Loaded,
+        result: &ComputeResult,
+        estimate: Option<&Estimate>,
+    ) -> AverageCheck {
+        let checkable = self.radius.is_none()
+            && self.units.is_none()
+            && self.bbox.is_none()
+            && self.distance.is_none()
+            && !result.cancelled;
+        let Some(stored) = loaded.stored_average.filter(|_| checkable) else {
+            return AverageCheck::Unchecked;
+        };
+        let within = match (loaded.population, estimate) {
+            (Some(_), Some(estimate)) => estimate.contains(stored),
+            (Some(_), None) => return AverageCheck::Unchecked,
+            (None, _) => {
+                let error = relative_error(stored, result.average);
+                !error.is_nan() && error <= self.tolerance
+            }
+        };
+        match within {
+            true => AverageCheck::Within(stored),
+            false => AverageCheck::Outside(stored),
+        }
+    }
+
+    fn check_answers(
+        &self,
+        path: &Path,
+        distances: &[f64],
+        average: f64,
+    ) -> Result<AnswersCheck, RunError> {
+        let answers = File::open(path)
+            .and_then(Answers::read)
+            .map_err(RunError::Answers)?;
+        let (off, first) = answers.check(distances, self.tolerance);
+        Ok(AnswersCheck {
+            off,
+            total: answers.distances.len().max(distances.len()),
+            first,
+            average_difference: answers.average - average,
+        })
+    }
+
+    /// Writes the per-pair and GeoJSON files, if asked for.
+    fn write_per_pair(&self, loaded: &Loaded, distances: &[f64]) -> Result<(), RunError> {
+        if let Some((path, options)) = &self.per_pair {
+            let options = PerPairOptions {
+                angle_unit: self.angle_unit,
+                ..*options
+            };
+            FileSink::create(path)
+                .and_then(|file| per_pair::write(file, &loaded.pairs, distances, &options))
+                .map_err(RunError::PerPair)?;
+        }
+        if let Some(path) = &self.geojson {
+            FileSink::create(path)
+                .and_then(|file| {
+                    per_pair::write_geojson_in(file, &loaded.pairs, distances, self.angle_unit)
+                })
+                .map_err(RunError::GeoJson)?;
+        }
+        Ok(())
+    }
+
+    fn driver(&self, radius: f64) -> ComputeDriver {
+        let keep_distances = self.keep_distances
+            || self.sample.is_some()
+            || self.answers.is_some()
+            || self.stats
+            || self.histogram.is_some()
+            || self.per_pair.is_some()
+            || self.geojson.is_some();
+        let driver = ComputeDriver::new(radius)
+            .kernel(self.kernel)
+            .math(self.math.clone())
+            .threads(self.threads)
+            .reduction(self.reduction)
+            .pin_threads(self.pin_threads)
+            .angle_unit(self.angle_unit)
+            .wide_accumulator(self.wide_accumulator)
+            .keep_distances(keep_distances);
+        match &self.cancel {
+            Some(token) => driver.cancel(token.clone()),
+            None => driver,
+        }
+    }
+
+    fn load_io(&self, mut source: IoSource) -> Result<Loaded> {
+        let fallback = source.take_fallback();
+        let mut loaded = self.load_from(&mut source)?;
+        loaded.io = Some(source.strategy());
+        loaded.overlap = source.overlap();
+        if let Some(error) = fallback {
+            loaded
+                .warnings
+                .insert(0, Warning::IoFallback(self.io, error));
+        }
+        Ok(loaded)
+    }
+
+    fn load_from(&self, input: &mut impl Input) -> Result<Loaded> {
+        let start = Instant::now();
+        // The size of a file is checked before reading it, as it may be too
+        // large to hold. The parsers check it again, for stdin.
+        if let Some(size) = input.size()
+            && size > self.limits.max_size as u64
+            && self.format != InputFormat::Ndjson
+        {
+            return Err(ParsingError::DocumentTooLarge(self.limits.max_size).into());
+        }
+        let mut sampler = self
+            .sample
+            .map(|(size, seed)| Sampler::new(size, Algorithm::default().seeded(seed)));
+        let mut warnings = Vec::new();
+        let mut pairs = Pairs::default();
+        let mut each = |pair| match &mut sampler {
+            Some(sampler) => sampler.offer(pair),
+            None => pairs.push_pair(pair),
+        };
+        let progress = self.read_progress.as_deref();
+        let (members, bytes, read_time) = match self.format {
+            InputFormat::Ndjson => {
+                let mut input = Counted::new(input.reader()?, progress);
+                self.pairs_reader().read_lines_each(&mut input, &mut each)?;
+                (Map::new(), input.count, Duration::ZERO)
+            }
+            InputFormat::Columnar => {
+                let mut input = Counted::new(input.reader()?, progress);
+                let (header, read) = columnar::read(&mut input)?;
+                pairs = read;
+                let mut members = Map::new();
+                members.insert("radius".into(), header.radius.into());
+                if let Some(average) = header.avg_dist {
+                    members.insert("avg_dist".into(), average.into());
+                }
+                (members, input.count, start.elapsed())
+            }
+            InputFormat::Json if self.stream => {
+                let mut input = Counted::new(input.reader()?, progress);
+                let (members, _) = self.pairs_reader().read_each(&mut input, &mut each)?;
+                (members, input.count, Duration::ZERO)
+            }
+            InputFormat::Json => {
+                let text = self.read_text(input)?;
+                let read_time = start.elapsed();
+                let (members, read) = self.parse_text(&text, &mut warnings)?;
+                pairs = read;
+                (members, text.len() as u64, read_time)
+            }
+        };
+        // A streamed sample is drawn as the pairs are read, leaving none here.
+        let population = sampler.map(|mut sampler| {
+            pairs.iter().for_each(|pair| sampler.offer(pair));
+            let population = sampler.seen();
+            pairs = sampler.into_pairs();
+            population
+        });
+
+        let number = |key: &'static str| match members.get(key) {
+            Some(Value::Number(number)) => Ok(Some(number.as_f64())),
+            Some(_) => Err(Error::MissingField(key)),
+            None => Ok(None),
+        };
+        let stored_average = number("avg_dist")?;
+        let radius = match (self.radius, self.format) {
+            (Some(radius), _) => radius,
+            (None, InputFormat::Ndjson) => RadiusModel::Mean.kilometers(),
+            (None, _) => number("radius")?.ok_or(Error::MissingField("radius"))?,
+        };
+        let radius = self.units.unwrap_or_default().convert_kilometers(radius);
+        if let Some(mismatch) = check_count(&members, &pairs).filter(|_| population.is_none()) {
+            warnings.push(Warning::CountMismatch(mismatch));
+        }
+
+        let routes = match members.get("routes") {
+            Some(Value::Array(values)) => routes_from_values(values, POLYLINE_PRECISION)?,
+            Some(_) => return Err(Error::MissingField("routes")),
+            None => Vec::new(),
+        };
+        let unit = self.angle_unit;
+        for route in &routes {
+            let route: Vec<_> = route
+                .iter()
+                .map(|point| Point::new(unit.from_degrees(point.lon), unit.from_degrees(point.lat)))
+                .collect();
+            pairs.push_route(&route);
+        }
+        match self.coordinates {
+            Coordinates::AsRead => {}
+            Coordinates::Canonicalize => {
+                let canonical = pairs.canonicalize_in(unit);
+                if canonical != Canonicalized::default() {
+                    warnings.push(Warning::Canonicalized(canonical));
+                }
+            }
+            Coordinates::Strict => pairs.check_coordinates_in(unit)?,
+        }
+        let excluded = self.bbox.map(|bbox| pairs.retain_within_in(&bbox, unit));
+        if self.huge_pages {
+            let (copy, advised) = pairs.to_huge_pages();
+            pairs = copy;
+            if !advised {
+                warnings.push(Warning::NoHugePages);
+            }
+        }
+        if self.interleave {
+            let buffers = [&pairs.x0, &pairs.y0, &pairs.x1, &pairs.y1];
+            if !buffers.iter().all(|buffer| affinity::interleave(buffer)) {
+                warnings.push(Warning::NotInterleaved);
+            }
+        }
+        let pairs_f32 = (self.precision == Precision::F32).then(|| PairsF32::from(&pairs));
+
+        Ok(Loaded {
+            members,
+            pairs,
+            radius,
+            stored_average,
+            routes,
+            population,
+            excluded,
+            bytes,
+            read_time,
+            parse_time: start.elapsed() - read_time,
+            io: None,
+            overlap: None,
+            warnings,
+            pairs_f32,
+        })
+    }
+
+    /// Reads the whole input as UTF-8, transcoding it if asked to.
+    fn read_text<'i>(&self, input: &'i mut impl Input) -> Result<Cow<'i, str>> {
+        let bytes = match self.read_progress.as_deref() {
+            Some(progress) if !input.is_mapped() => {
+                let mut bytes = Vec::new();
+                Counted::new(input.reader()?, Some(progress)).read_to_end(&mut bytes)?;
+                Cow::Owned(bytes)
+            }
+            _ => input.read_all()?,
+        };
+        match detect_encoding(&bytes) {
+            Encoding::Utf8 => {}
+            encoding if !self.transcode => return Err(Error::Encoding(encoding)),
+            _ => {}
+        }
+        Ok(reader::text(bytes)?)
+    }
+
+    fn parse_text(&self, text: &str, warnings: &mut Vec<Warning>) -> Result<(Map, Pairs)> {
+        // The fast schema reader checks no limits.
+        let fast_schema = self.fast_schema != Some(false) && self.limits == Limits::default();
+        let fast = fast_schema.then(|| parse_fast_schema(text)).flatten();
+        if fast_schema && self.fast_schema.is_some() && fast.is_none() {
+            warnings.push(Warning::NotFastSchema);
+        }
+        match fast {
+            Some(document) => Ok(document),
+            None if self.tape => self.parse_tape(text),
+            None => self.parse_document(text),
+        }
+    }
+
+    /// Reader of the pairs, stopped by the cancel token.
+    fn pairs_reader(&self) -> PairsReader {
+        let reader = PairsReader::new()
+            .duplicate_keys(self.duplicate_keys)
+            .lone_surrogates(self.lone_surrogates)
+            .out_of_range(self.out_of_range)
+            .limits(self.limits)
+            .allow_control_chars(self.allow_control_chars);
+        match &self.cancel {
+            Some(token) => reader.cancel(token.clone()),
+            None => reader,
+        }
+    }
+
+    /// Parses a pairs document with the generic parser, one pair at a time.
+    fn parse_document(&self, text: &str) -> Result<(Map, Pairs)> {
+        let values = Parser::new(text)
+            .duplicate_keys(self.duplicate_keys)
+            .lone_surrogates(self.lone_surrogates)
+            .out_of_range(self.out_of_range)
+            .limits(self.limits)
+            .allow_control_chars(self.allow_control_chars)
+            .iter_array_at("/pairs");
+        let mut values = match &self.cancel {
+            Some(token) => values.cancel(token.clone()),
+            None => values,
+        };
+        let mut pairs = Pairs::default();
+        for value in values.by_ref() {
+            pairs.push_value(&value?)?;
+        }
+        match values.into_document() {
+            Some(Value::Object(members)) => Ok((members, pairs)),
+            _ => Err(Error::NotAnObject),
+        }
+    }
+
+    /// Parses a pairs document through a tape, building values only for
+    /// the members other than `pairs`.
+    fn parse_tape(&self, text: &str) -> Result<(Map, Pairs)> {
+        let mut parser = Parser::new(text)
+            .lone_surrogates(self.lone_surrogates)
+            .limits(self.limits)
+            .allow_control_chars(self.allow_control_chars);
+        if self.simd {
+            parser = parser.structural_scan(Simd::detect());
+        }
+        let tape = match parser.parse_tape()? {
+            Some(tape) if tape.root().is_object() => tape,
+            _ => return Err(Error::NotAnObject),
+        };
+        let mut members = Map::new();
+        for (key, value) in tape.root().members() {
+            if key != "pairs" && members.get(&key).is_none() {
+                members.insert(key.into_owned(), value.to_value());
+            }
+        }
+        match tape.get("pairs") {
+            Some(array) if array.is_array() => Ok((members, Pairs::from_tape(array)?)),
+            _ => Err(ParsingError::NoArrayAt("/pairs".into()).into()),
+        }
+    }
+}
+
+impl fmt::Debug for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pipeline")
+            .field("format", &self.format)
+            .field("io", &self.io)
+            .field("stream", &self.stream)
+            .field("tape", &self.tape)
+            .field("limits", &self.limits)
+            .field("sample", &self.sample)
+            .field("radius", &self.radius)
+            .field("units", &self.units)
+            .field("angle_unit", &self.angle_unit)
+            .field("coordinates", &self.coordinates)
+            .field("precision", &self.precision)
+            .field("kernel", &self.kernel)
+            .field("distance", &self.distance.is_some())
+            .field("math", &self.math)
+            .field("threads", &self.threads)
+            .field("reduction", &self.reduction)
+            .field("wide_accumulator", &self.wide_accumulator)
+            .field("keep_distances", &self.keep_distances)
+            .field("cancel", &self.cancel)
+            .field("checkpoint", &self.checkpoint)
+            .field("resume", &self.resume)
+            .field("tolerance", &self.tolerance)
+            .field("answers", &self.answers)
+            .field("stats", &self.stats)
+            .field("histogram", &self.histogram)
+            .field("per_pair", &self.per_pair)
+            .field("geojson", &self.geojson)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Spherical centroid of both points of every pair, in degrees whatever the
+/// unit of the coordinates.
+fn centroid(pairs: &Pairs, unit: AngleUnit) -> Option<Point> {
+    let degrees = |angle| radians_to_degrees(unit.to_radians(angle));
+    let points = pairs.iter().flat_map(|pair| [pair.p0, pair.p1]);
+    spherical_centroid(points.map(|point| Point::new(degrees(point.lon), degrees(point.lat))))
+}
+
+/// An input the pipeline reads: a file or stdin opened with an `IoSource`,
+/// or any `InputSource`.
+trait Input {
+    fn reader(&mut self) -> io::Result<Box<dyn Read + '_>>;
+    fn read_all(&mut self) -> io::Result<Cow<'_, [u8]>>;
+    fn size(&self) -> Option<u64>;
+    fn is_mapped(&self) -> bool;
+}
+
+impl Input for IoSource {
+    fn reader(&mut self) -> io::Result<Box<dyn Read + '_>> {
+        Ok(IoSource::reader(self))
+    }
+
+    fn read_all(&mut self) -> io::Result<Cow<'_, [u8]>> {
+        IoSource::read_all(self)
+    }
+
+    fn size(&self) -> Option<u64> {
+        IoSource::size(self)
+    }
+
+    fn is_mapped(&self) -> bool {
+        IoSource::is_mapped(self)
+    }
+}
+
+impl Input for Box<dyn InputSource> {
+    fn reader(&mut self) -> io::Result<Box<dyn Read + '_>> {
+        Ok(self.open()?)
+    }
+
+    fn read_all(&mut self) -> io::Result<Cow<'_, [u8]>> {
+        self.as_mut().read_all()
+    }
+
+    fn size(&self) -> Option<u64> {
+        self.as_ref().size()
+    }
+
+    fn is_mapped(&self) -> bool {
+        false
+    }
+}
+
+/// A reader counting the bytes read through it, and adding them to a
+/// progress counter, if any.
+struct Counted<'p, R> {
+    inner: R,
+    count: u64,
+    progress: Option<&'p AtomicUsize>,
+}
+
+impl<'p, R> Counted<'p, R> {
+    fn new(inner: R, progress: Option<&'p AtomicUsize>) -> Self {
+        Self {
+            inner,
+            count: 0,
+            progress,
+        }
+    }
+}
+
+impl<R: Read> Read for Counted<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.count += read as u64;
+        if let Some(progress) = self.progress {
+            progress.fetch_add(read, Ordering::Relaxed);
+        }
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,104 @@
+use crate::answers::Mismatch;
+use crate::checkpoint::CheckpointError;
+use std::time::Duration;
+use std::{fmt, io};
+
+/// How the computed average compares with the one stored in the input.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum AverageCheck {
+    /// Not checked: the input stores no average, or the distances are not
+    /// all those it averages, being on another sphere or in another unit,
+    /// of the pairs in a box or of a cancelled run. A sample is checked
+    /// only if it is not empty.
+    #[default]
+    Unchecked,
+    /// The stored average is within the tolerance of the computed one or,
+    /// for a sample, within the 95% interval of its estimate.
+    Within(f64),
+    /// The stored average is not. A mismatch, unless the pairs are a
+    /// sample, which cannot disprove it.
+    Outside(f64),
+}
+
+/// The distances of a run checked against an answers file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnswersCheck {
+    /// Distances off by more than the tolerance, missing or extra ones
+    /// included.
+    pub off: usize,
+    /// Distances checked: the answers or the computed ones, whichever are
+    /// more.
+    pub total: usize,
+    /// The first distance off, if any is.
+    pub first: Option<Mismatch>,
+    /// The average of the answers less the computed one.
+    pub average_difference: f64,
+}
+
+/// The `f64` run an `f32` one is compared with, with the standard library
+/// and one thread.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Baseline {
+    pub average: f64,
+    pub compute_time: Duration,
+    /// Its computing time over that of the `f32` run.
+    pub speedup: f64,
+    /// Error of the `f32` average relative to its own.
+    pub relative_error: f64,
+}
+
+/// A file of a run other than its input that could not be read or
+/// written.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum RunError {
+    /// The checkpoint to resume from could not be read.
+    Resume(CheckpointError),
+    /// The checkpoint to resume from was saved by a run of another input or
+    /// settings.
+    ForeignCheckpoint,
+    /// A checkpoint could not be saved. The run goes on without saving and
+    /// fails once it ends.
+    SaveCheckpoint(io::Error),
+    /// The checkpoint of a finished run could not be removed.
+    RemoveCheckpoint(io::Error),
+    /// The answers file could not be read.
+    Answers(io::Error),
+    /// The per-pair file could not be created or written.
+    PerPair(io::Error),
+    /// The GeoJSON file could not be created or written.
+    GeoJson(io::Error),
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunError::Resume(error) => write!(f, "{error}"),
+            RunError::ForeignCheckpoint => {
+                write!(f, "the checkpoint belongs to another input or settings")
+            }
+            RunError::SaveCheckpoint(_) => write!(f, "failed to save a checkpoint"),
+            RunError::RemoveCheckpoint(_) => write!(f, "failed to remove the checkpoint"),
+            RunError::Answers(_) => write!(f, "failed to read the answers"),
+            RunError::PerPair(_) => write!(f, "failed to write the per-pair file"),
+            RunError::GeoJson(_) => write!(f, "failed to write the GeoJSON file"),
+        }
+    }
+}
+
+impl std::error::Error for RunError {
+    /// A wrapped checkpoint error is formatted by `Display` already, so
+    /// only its own source is returned.
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RunError::Resume(error) => error.source(),
+            RunError::ForeignCheckpoint => None,
+            RunError::SaveCheckpoint(error)
+            | RunError::RemoveCheckpoint(error)
+            | RunError::Answers(error)
+            | RunError::PerPair(error)
+            | RunError::GeoJson(error) => Some(error),
+        }
+    }
+}
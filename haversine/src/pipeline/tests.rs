@@ -0,0 +1,450 @@
+use super::{AverageCheck, Coordinates, InputFormat, Pipeline, RunError, Warning};
+use crate::Error;
+use crate::answers::Answers;
+use crate::checkpoint::Checkpoint;
+use crate::columnar;
+use crate::compute::{ComputeDriver, Precision};
+use crate::generate::{self, Distribution, generate_pairs};
+use crate::geo::{AngleUnit, BoundingBox, DistanceUnit, Kernel, Point, RadiusModel};
+use crate::pairs::SampleSize;
+use crate::parse::{Encoding, Limits, ParsingError};
+use crate::per_pair::{PerPairFormat, PerPairOptions};
+use crate::reader::{IoStrategy, ReaderSource};
+use crate::rng::Xoshiro256PlusPlus;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+const NDJSON: &str = "{\"x0\": 0, \"y0\": 0, \"x1\": 90, \"y1\": 0}\n\
+                      {\"x0\": 0, \"y0\": 0, \"x1\": 0, \"y1\": 90}\n";
+
+#[test]
+fn test_json_matches_compute_driver() {
+    let pairs = generate_pairs(&mut Xoshiro256PlusPlus::new(9), 100, Distribution::Uniform);
+    let text = generate::document(&pairs, 6372.8).to_json();
+    let expected = ComputeDriver::new(6372.8).run(&pairs).average;
+
+    let result = Pipeline::new()
+        .text(text.clone())
+        .keep_distances(true)
+        .run()
+        .unwrap();
+    assert_eq!(100, result.n_pairs);
+    assert_eq!(6372.8, result.radius);
+    assert_eq!(expected, result.average);
+    assert_eq!(100, result.distances.len());
+    assert!(result.stored_average.is_some());
+    assert_eq!(text.len() as u64, result.bytes);
+    assert!(!result.cancelled);
+
+    let path = std::env::temp_dir().join(format!("pipeline-{}.json", std::process::id()));
+    std::fs::write(&path, &text).unwrap();
+    let from_file = Pipeline::new()
+        .input(&path)
+        .kernel(Kernel::Fma)
+        .threads(3)
+        .radius(1.0)
+        .run();
+    std::fs::remove_file(path).unwrap();
+    let from_file = from_file.unwrap();
+    assert_eq!(1.0, from_file.radius);
+    assert!((from_file.average - expected / 6372.8).abs() < 1e-12);
+}
+
+#[test]
+fn test_ndjson_and_columnar() {
+    let result = Pipeline::new()
+        .text(NDJSON)
+        .format(InputFormat::Ndjson)
+        .run()
+        .unwrap();
+    assert_eq!(2, result.n_pairs);
+    assert_eq!(RadiusModel::Mean.kilometers(), result.radius);
+    assert_eq!(None, result.stored_average);
+
+    let pairs = generate_pairs(&mut Xoshiro256PlusPlus::new(2), 10, Distribution::Uniform);
+    let mut bytes = Vec::new();
+    columnar::write(&mut bytes, &pairs, 3.0, Some(1.5)).unwrap();
+    let path = std::env::temp_dir().join(format!("pipeline-{}.col", std::process::id()));
+    std::fs::write(&path, &bytes).unwrap();
+    let result = Pipeline::new()
+        .input(&path)
+        .format(InputFormat::Columnar)
+        .run();
+    std::fs::remove_file(path).unwrap();
+    let result = result.unwrap();
+    assert_eq!(10, result.n_pairs);
+    assert_eq!(3.0, result.radius);
+    assert_eq!(Some(1.5), result.stored_average);
+    assert_eq!(bytes.len() as u64, result.bytes);
+}
+
+#[test]
+fn test_errors() {
+    assert!(matches!(Pipeline::new().run(), Err(Error::Io(_))));
+    let no_radius = r#"{"pairs": [{"x0": 0, "y0": 0, "x1": 1, "y1": 1}]}"#;
+    assert!(matches!(
+        Pipeline::new().text(no_radius).run(),
+        Err(Error::MissingField("radius"))
+    ));
+    let limits = Limits {
+        max_array_len: 1,
+        ..Limits::default()
+    };
+    let two = r#"{"pairs": [{"x0": 0, "y0": 0, "x1": 1, "y1": 1}, {"x0": 0, "y0": 0, "x1": 1, "y1": 1}], "radius": 1}"#;
+    assert!(Pipeline::new().text(two).run().is_ok());
+    assert!(matches!(
+        Pipeline::new().text(two).limits(limits).run(),
+        Err(Error::Parsing(ParsingError::Nested(_, error))) if *error == ParsingError::ArrayTooLong(1)
+    ));
+}
+
+#[test]
+fn test_reader_source() {
+    let mut pipeline = Pipeline::new()
+        .source(ReaderSource(NDJSON.as_bytes()))
+        .format(InputFormat::Ndjson)
+        .radius(1.0);
+    let result = pipeline.run().unwrap();
+    assert_eq!(2, result.n_pairs);
+    assert_eq!(NDJSON.len() as u64, result.bytes);
+    assert!((result.average - std::f64::consts::FRAC_PI_2).abs() < 1e-12);
+    // The reader was read to its end.
+    assert_eq!(0, pipeline.run().unwrap().n_pairs);
+}
+
+#[test]
+fn test_distance_replaces_kernel() {
+    let result = Pipeline::new()
+        .text(NDJSON)
+        .format(InputFormat::Ndjson)
+        .radius(1.0)
+        .distance(|_: Point, p1: Point| p1.lon + p1.lat)
+        .run()
+        .unwrap();
+    assert_eq!(2, result.n_pairs);
+    assert_eq!(90.0, result.average);
+}
+
+#[test]
+fn test_parsers_load_the_same() {
+    let pairs = generate_pairs(&mut Xoshiro256PlusPlus::new(4), 50, Distribution::Uniform);
+    let text = generate::document(&pairs, 2.0).to_json();
+    let path = std::env::temp_dir().join(format!("pipeline-parsers-{}.json", std::process::id()));
+    std::fs::write(&path, &text).unwrap();
+    let load = |pipeline: Pipeline| pipeline.input(&path).load().unwrap();
+    let expected = load(Pipeline::new().fast_schema(false));
+    for loaded in [
+        load(Pipeline::new()),
+        load(Pipeline::new().fast_schema(false).tape(true)),
+        load(Pipeline::new().fast_schema(false).tape(true).simd(true)),
+        load(Pipeline::new().stream(true)),
+        load(Pipeline::new().io(IoStrategy::Mmap)),
+        load(Pipeline::new().io(IoStrategy::Async)),
+    ] {
+        assert_eq!(expected.pairs, loaded.pairs);
+        assert_eq!(2.0, loaded.radius);
+        assert_eq!(expected.stored_average, loaded.stored_average);
+        assert_eq!(text.len() as u64, loaded.bytes);
+    }
+    let counter = Arc::new(AtomicUsize::new(0));
+    let loaded = load(Pipeline::new().read_progress(counter.clone()));
+    assert_eq!(Some(IoStrategy::Sync), loaded.io);
+    assert!(loaded.overlap.is_some());
+    assert_eq!(text.len(), counter.load(Ordering::Relaxed));
+    std::fs::remove_file(path).unwrap();
+
+    // Only numbers fit the fast schema, which is only warned of if asked
+    // for.
+    let text = r#"{"pairs": [], "radius": 1, "name": "none"}"#;
+    let loaded = Pipeline::new().text(text).load().unwrap();
+    assert_eq!(None, loaded.io);
+    assert!(loaded.warnings.is_empty());
+    let loaded = Pipeline::new().text(text).fast_schema(true).load().unwrap();
+    assert!(matches!(loaded.warnings[..], [Warning::NotFastSchema]));
+}
+
+#[test]
+fn test_load_prepares_the_pairs() {
+    // The route adds the pair of its two points.
+    let text = r#"{"pairs": [{"x0": 190, "y0": 0, "x1": 10, "y1": 95}, {"x0": 50, "y0": 50, "x1": 51, "y1": 51}],
+        "radius": 1, "avg_dist": 0, "count": 3, "routes": ["_p~iF~ps|U_ulLnnqC"]}"#;
+    let loaded = Pipeline::new()
+        .text(text)
+        .coordinates(Coordinates::Canonicalize)
+        .units(DistanceUnit::Meters)
+        .load()
+        .unwrap();
+    assert_eq!(1000.0, loaded.radius);
+    assert_eq!(1, loaded.routes.len());
+    assert_eq!(3, loaded.pairs.len());
+    assert_eq!(-170.0, loaded.pairs.x0[0]);
+    assert!(matches!(
+        loaded.warnings[..],
+        [Warning::CountMismatch(_), Warning::Canonicalized(canonical)] if canonical.wrapped == 1 && canonical.clamped == 1
+    ));
+
+    let strict = Pipeline::new()
+        .text(text)
+        .coordinates(Coordinates::Strict)
+        .load();
+    assert!(matches!(strict, Err(Error::Pairs(_))));
+
+    let bbox = BoundingBox::new(40.0, 40.0, 60.0, 60.0).unwrap();
+    let loaded = Pipeline::new().text(text).bbox(bbox).load().unwrap();
+    assert_eq!(Some(2), loaded.excluded);
+    assert_eq!(1, loaded.pairs.len());
+
+    let pairs = generate_pairs(&mut Xoshiro256PlusPlus::new(5), 100, Distribution::Uniform);
+    let text = generate::document(&pairs, 1.0).to_json();
+    for stream in [false, true] {
+        let loaded = Pipeline::new()
+            .text(text.clone())
+            .stream(stream)
+            .sample(SampleSize::Count(10), 7)
+            .load()
+            .unwrap();
+        assert_eq!(Some(100), loaded.population);
+        assert_eq!(10, loaded.pairs.len());
+    }
+
+    let routes = r#"{"pairs": [], "radius": 1, "routes": 1}"#;
+    assert!(matches!(
+        Pipeline::new().text(routes).load(),
+        Err(Error::MissingField("routes"))
+    ));
+}
+
+#[test]
+fn test_load_errors() {
+    let mut utf16 = vec![0xff, 0xfe];
+    utf16.extend(r#"{"pairs": [], "radius": 1}"#.encode_utf16().flat_map(u16::to_le_bytes));
+    assert!(Pipeline::new().source(utf16.clone()).load().is_ok());
+    assert!(matches!(
+        Pipeline::new().source(utf16).transcode(false).load(),
+        Err(Error::Encoding(Encoding::Utf16Le))
+    ));
+
+    let limits = Limits {
+        max_size: 4,
+        ..Limits::default()
+    };
+    assert!(matches!(
+        Pipeline::new().text("[1, 2]").limits(limits).load(),
+        Err(Error::Parsing(ParsingError::DocumentTooLarge(4)))
+    ));
+    assert!(matches!(
+        Pipeline::new().text("[1, 2]").tape(true).load(),
+        Err(Error::NotAnObject)
+    ));
+    assert!(matches!(
+        Pipeline::new().text(r#"{"radius": 1}"#).tape(true).load(),
+        Err(Error::Parsing(ParsingError::NoArrayAt(_)))
+    ));
+}
+
+#[test]
+fn test_compute_phases() {
+    let pairs = generate_pairs(&mut Xoshiro256PlusPlus::new(6), 200, Distribution::Uniform);
+    let text = generate::document(&pairs, 3.0).to_json();
+    let mut pipeline = Pipeline::new().text(text).angle_unit(AngleUnit::Degrees);
+    let loaded = pipeline.load().unwrap();
+    let processed = AtomicUsize::new(0);
+    let result = pipeline.compute(&loaded, &processed);
+    assert_eq!(ComputeDriver::new(3.0).run(&pairs).average, result.average);
+    assert_eq!(200, processed.load(Ordering::Relaxed));
+
+    let mut saved = Vec::new();
+    let checkpointed = pipeline.compute_checkpointed(
+        &loaded,
+        &AtomicUsize::new(0),
+        None,
+        Duration::ZERO,
+        &mut |checkpoint: &Checkpoint| saved.push(*checkpoint),
+    );
+    assert_eq!(result.average, checkpointed.average);
+    assert!(
+        saved
+            .iter()
+            .all(|checkpoint| checkpoint.fingerprint == pipeline.fingerprint(&loaded))
+    );
+
+    let mut f32 = pipeline.precision(Precision::F32);
+    let loaded = f32.load().unwrap();
+    let result = f32.compute(&loaded, &AtomicUsize::new(0));
+    assert!((result.average - checkpointed.average).abs() < 1e-4 * checkpointed.average);
+}
+
+#[test]
+fn test_run_checks_the_stored_average() {
+    let pairs = generate_pairs(&mut Xoshiro256PlusPlus::new(7), 200, Distribution::Uniform);
+    let text = generate::document(&pairs, 2.0).to_json();
+    let result = Pipeline::new().text(text.clone()).run().unwrap();
+    assert!(matches!(result.check, AverageCheck::Within(_)));
+    assert!(!result.mismatch());
+
+    let wrong = r#"{"pairs": [{"x0": 0, "y0": 0, "x1": 90, "y1": 0}], "radius": 1, "avg_dist": 1}"#;
+    let result = Pipeline::new().text(wrong).run().unwrap();
+    assert_eq!(AverageCheck::Outside(1.0), result.check);
+    assert!(result.mismatch());
+    let result = Pipeline::new().text(wrong).tolerance(1.0).run().unwrap();
+    assert_eq!(AverageCheck::Within(1.0), result.check);
+    // The stored average is of the distances on the sphere of the input.
+    let result = Pipeline::new().text(wrong).radius(2.0).run().unwrap();
+    assert_eq!(AverageCheck::Unchecked, result.check);
+    let result = Pipeline::new()
+        .text(wrong)
+        .units(DistanceUnit::Kilometers)
+        .run()
+        .unwrap();
+    assert_eq!(AverageCheck::Unchecked, result.check);
+
+    // A sample is checked against its interval, and cannot disprove the
+    // stored average.
+    let result = Pipeline::new()
+        .text(text)
+        .sample(SampleSize::Count(50), 3)
+        .run()
+        .unwrap();
+    assert_eq!(Some(200), result.population);
+    let estimate = result.estimate.as_ref().unwrap();
+    assert_eq!(50, result.distances.len());
+    let stored = result.stored_average.unwrap();
+    match estimate.contains(stored) {
+        true => assert_eq!(AverageCheck::Within(stored), result.check),
+        false => assert_eq!(AverageCheck::Outside(stored), result.check),
+    }
+    let result = Pipeline::new()
+        .text(wrong)
+        .sample(SampleSize::Count(1), 3)
+        .tolerance(0.0)
+        .run()
+        .unwrap();
+    assert!(!result.mismatch());
+}
+
+#[test]
+fn test_run_resumes_and_removes_the_checkpoint() {
+    let pairs = generate_pairs(&mut Xoshiro256PlusPlus::new(8), 300, Distribution::Uniform);
+    let text = generate::document(&pairs, 1.0).to_json();
+    let path = std::env::temp_dir().join(format!("pipeline-{}.checkpoint", std::process::id()));
+    let mut pipeline = Pipeline::new()
+        .text(text)
+        .checkpoint(&path, Duration::ZERO)
+        .resume(true);
+    let loaded = pipeline.load().unwrap();
+    let distances = ComputeDriver::new(1.0)
+        .keep_distances(true)
+        .run(&pairs)
+        .distances;
+    let expected = distances.iter().sum::<f64>() / 300.0;
+    let sum = distances[..100].iter().sum();
+    let halfway = Checkpoint {
+        fingerprint: pipeline.fingerprint(&loaded),
+        position: 100,
+        sum,
+        wide_sum: (sum, 0.0),
+        output_len: 0,
+    };
+    halfway.write(&path).unwrap();
+
+    let result = pipeline.run_loaded(&loaded, &AtomicUsize::new(0)).unwrap();
+    assert_eq!(Some(100), result.resumed_from);
+    assert!((result.average - expected).abs() < 1e-12);
+    assert!(!path.exists());
+    // Without a checkpoint, the run starts from the beginning.
+    let result = pipeline.run_loaded(&loaded, &AtomicUsize::new(0)).unwrap();
+    assert_eq!(None, result.resumed_from);
+    assert!(!path.exists());
+
+    Checkpoint {
+        fingerprint: halfway.fingerprint ^ 1,
+        ..halfway
+    }
+    .write(&path)
+    .unwrap();
+    let foreign = pipeline.run_loaded(&loaded, &AtomicUsize::new(0));
+    std::fs::write(&path, "not a checkpoint").unwrap();
+    let malformed = pipeline.run_loaded(&loaded, &AtomicUsize::new(0));
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(foreign, Err(RunError::ForeignCheckpoint)));
+    assert!(matches!(malformed, Err(RunError::Resume(_))));
+
+    // Checkpoints are saved every so many pairs, so only a long run saves
+    // one.
+    let long = NDJSON.repeat(35_000);
+    let result = Pipeline::new()
+        .text(long.clone())
+        .format(InputFormat::Ndjson)
+        .checkpoint(&path, Duration::ZERO)
+        .run()
+        .unwrap();
+    assert!(result.saved_checkpoints > 0);
+    assert!(!path.exists());
+    let unsaved = Pipeline::new()
+        .text(long)
+        .format(InputFormat::Ndjson)
+        .checkpoint(path.join("checkpoint"), Duration::ZERO)
+        .run();
+    assert!(matches!(
+        unsaved,
+        Err(Error::Run(RunError::SaveCheckpoint(_)))
+    ));
+}
+
+#[test]
+fn test_run_reports_on_the_distances() {
+    let pairs = generate_pairs(&mut Xoshiro256PlusPlus::new(9), 100, Distribution::Uniform);
+    let text = generate::document(&pairs, 1.0).to_json();
+    let dir = std::env::temp_dir();
+    let id = std::process::id();
+    let answers = dir.join(format!("pipeline-{id}.answers"));
+    let per_pair = dir.join(format!("pipeline-{id}.f64"));
+    let geojson = dir.join(format!("pipeline-{id}.geojson"));
+    let mut file = std::fs::File::create(&answers).unwrap();
+    Answers::compute(&pairs, 1.0).write(&mut file).unwrap();
+    let options = PerPairOptions {
+        format: PerPairFormat::F64,
+        coordinates: false,
+        bearings: false,
+        midpoints: false,
+        angle_unit: AngleUnit::Degrees,
+    };
+
+    let result = Pipeline::new()
+        .text(text.clone())
+        .answers(&answers)
+        .histogram(4)
+        .per_pair(&per_pair, options)
+        .export_geojson(&geojson)
+        .run();
+    let per_pair_len = std::fs::metadata(&per_pair).map(|meta| meta.len());
+    let geojson_written = geojson.exists();
+    for path in [&answers, &per_pair, &geojson] {
+        std::fs::remove_file(path).unwrap();
+    }
+    let result = result.unwrap();
+    let checked = result.answers.unwrap();
+    assert_eq!((0, 100, None), (checked.off, checked.total, checked.first));
+    assert_eq!(100, result.stats.as_ref().unwrap().count);
+    assert_eq!(4, result.histogram.as_ref().unwrap().counts.len());
+    assert!(result.centroid.is_some());
+    assert_eq!(800, per_pair_len.unwrap());
+    assert!(geojson_written);
+
+    let missing = Pipeline::new().text(text.clone()).answers(&answers).run();
+    assert!(matches!(missing, Err(Error::Run(RunError::Answers(_)))));
+
+    let result = Pipeline::new()
+        .text(text)
+        .precision(Precision::F32)
+        .run()
+        .unwrap();
+    let baseline = result.baseline.unwrap();
+    assert_eq!(
+        ComputeDriver::new(1.0).run(&pairs).average,
+        baseline.average
+    );
+    assert!(baseline.relative_error < 1e-4);
+    assert!(result.stats.is_none() && result.answers.is_none());
+}
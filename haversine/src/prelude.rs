@@ -0,0 +1,17 @@
+//! The types and functions most programs need, for a glob import:
+//!
+//! ```
+//! use haversine::prelude::*;
+//!
+//! let pairs = parse(r#"[{"x0": 0, "y0": 0, "x1": 90, "y1": 0}]"#).unwrap().unwrap();
+//! let Value::Array(values) = pairs else { unreachable!() };
+//! let pairs = Pairs::from_values(&values).unwrap();
+//! let result = ComputeDriver::new(1.0).run(&pairs);
+//! assert_eq!(result.average, haversine_distance(1.0, 0.0, 0.0, 90.0, 0.0));
+//! ```
+
+pub use crate::Error;
+pub use crate::compute::{ComputeDriver, ComputeResult};
+pub use crate::geo::{Point, haversine_distance};
+pub use crate::pairs::{Pair, Pairs};
+pub use crate::parse::{Value, parse};
@@ -0,0 +1,151 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Time between two progress lines.
+const REFRESH: Duration = Duration::from_millis(500);
+
+/// What a progress counter counts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Unit {
+    Bytes,
+    Pairs,
+}
+
+/// Prints a progress line to stderr on a background thread until finished,
+/// reading a counter other code advances.
+pub(crate) struct Progress {
+    done: Arc<AtomicUsize>,
+    stop: Arc<AtomicBool>,
+    reporter: JoinHandle<()>,
+}
+
+impl Progress {
+    /// Starts reporting. Without a `total`, neither a percentage nor an ETA
+    /// can be given.
+    pub(crate) fn start(label: &'static str, unit: Unit, total: Option<usize>) -> Self {
+        let done = Arc::new(AtomicUsize::new(0));
+        let stop = Arc::new(AtomicBool::new(false));
+        let reporter = {
+            let (done, stop) = (done.clone(), stop.clone());
+            thread::spawn(move || {
+                let start = Instant::now();
+                loop {
+                    thread::park_timeout(REFRESH);
+                    let finished = stop.load(Ordering::Acquire);
+                    let line = line(label, unit, done.load(Ordering::Relaxed), total, start);
+                    if finished {
+                        eprintln!("\r{line}\x1b[K");
+                        return;
+                    }
+                    eprint!("\r{line}\x1b[K");
+                }
+            })
+        };
+        Self {
+            done,
+            stop,
+            reporter,
+        }
+    }
+
+    /// Counter of the work done so far.
+    pub(crate) fn counter(&self) -> &AtomicUsize {
+        &self.done
+    }
+
+    /// The counter itself, for a `Pipeline` to advance.
+    pub(crate) fn shared_counter(&self) -> Arc<AtomicUsize> {
+        Arc::clone(&self.done)
+    }
+
+    /// Prints the final line and stops the background thread.
+    pub(crate) fn finish(self) {
+        self.stop.store(true, Ordering::Release);
+        self.reporter.thread().unpark();
+        let _ = self.reporter.join();
+    }
+}
+
+/// Formats e.g. `Read 1.2 GiB of 3.5 GiB (34%), 410.3 MiB/s, ETA 6s`.
+fn line(label: &str, unit: Unit, done: usize, total: Option<usize>, start: Instant) -> String {
+    let seconds = start.elapsed().as_secs_f64();
+    let rate = done as f64 / seconds.max(1e-9);
+    let mut line = format!("{label} {}", amount(unit, done as f64));
+    if let Some(total) = total {
+        line += &format!(" of {}", amount(unit, total as f64));
+    }
+    if unit == Unit::Pairs {
+        line += " pairs";
+    }
+    if let Some(total) = total
+        && total > 0
+    {
+        line += &format!(" ({:.0}%)", 100.0 * done as f64 / total as f64);
+    }
+    line += &format!(", {}", amount(unit, rate));
+    line += if unit == Unit::Pairs {
+        " pairs/s"
+    } else {
+        "/s"
+    };
+    if let Some(total) = total
+        && done > 0
+    {
+        let eta = total.saturating_sub(done) as f64 / rate;
+        line += &format!(", ETA {eta:.0}s");
+    }
+    line
+}
+
+/// Formats a number of bytes with a binary unit, and of pairs as is.
+fn amount(unit: Unit, n: f64) -> String {
+    match unit {
+        Unit::Pairs => format!("{n:.0}"),
+        Unit::Bytes => bytes(n),
+    }
+}
+
+/// Formats the bytes of input parsed per second, with the cycles per byte
+/// if the CPU timer counts `cycles`.
+pub(crate) fn parsing_throughput(bytes: usize, time: Duration, cycles: Option<u64>) -> String {
+    let mut line = format!(
+        "Parsing throughput: {}/s of input",
+        self::bytes(bytes as f64 / time.as_secs_f64())
+    );
+    if let Some(cycles) = cycles {
+        line += &format!(", {:.2} cycles/byte", cycles as f64 / bytes.max(1) as f64);
+    }
+    line
+}
+
+/// Formats the bytes of coordinates read and the pairs computed per second,
+/// with the cycles per pair if the CPU timer counts `cycles`.
+pub(crate) fn computing_throughput(n_pairs: usize, time: Duration, cycles: Option<u64>) -> String {
+    let bytes = n_pairs * 4 * size_of::<f64>();
+    let mut line = format!(
+        "Computing throughput: {}/s, {:.0} pairs/s",
+        self::bytes(bytes as f64 / time.as_secs_f64()),
+        n_pairs as f64 / time.as_secs_f64()
+    );
+    if let Some(cycles) = cycles {
+        line += &format!(", {:.1} cycles/pair", cycles as f64 / n_pairs.max(1) as f64);
+    }
+    line
+}
+
+/// Formats a number of bytes with a binary unit, like `1.2 GiB`.
+pub(crate) fn bytes(n: f64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut n = n;
+    let mut i = 0;
+    while n >= 1024.0 && i + 1 < units.len() {
+        n /= 1024.0;
+        i += 1;
+    }
+    format!("{n:.1} {}", units[i])
+}
+
+#[cfg(test)]
+mod tests;
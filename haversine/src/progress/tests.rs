@@ -0,0 +1,46 @@
+use super::{bytes, computing_throughput, parsing_throughput};
+use std::time::Duration;
+
+const MIB: usize = 1024 * 1024;
+
+#[test]
+fn test_bytes() {
+    assert_eq!("0.0 B", bytes(0.0));
+    assert_eq!("1023.0 B", bytes(1023.0));
+    assert_eq!("1.5 KiB", bytes(1536.0));
+    assert_eq!("2.0 GiB", bytes(2.0 * 1024.0 * MIB as f64));
+    // Past the largest unit, the number grows.
+    assert_eq!("1024.0 TiB", bytes(1024f64.powi(5)));
+}
+
+#[test]
+fn test_parsing_throughput() {
+    let time = Duration::from_secs(2);
+    assert_eq!(
+        "Parsing throughput: 1.5 MiB/s of input, 2.00 cycles/byte",
+        parsing_throughput(3 * MIB, time, Some(6 * MIB as u64))
+    );
+    assert_eq!(
+        "Parsing throughput: 1.5 MiB/s of input",
+        parsing_throughput(3 * MIB, time, None)
+    );
+}
+
+#[test]
+fn test_computing_throughput() {
+    // Each pair reads four coordinates of 8 bytes.
+    let time = Duration::from_millis(500);
+    assert_eq!(
+        "Computing throughput: 64.0 KiB/s, 2048 pairs/s, 2.5 cycles/pair",
+        computing_throughput(1024, time, Some(2560))
+    );
+    assert_eq!(
+        "Computing throughput: 64.0 KiB/s, 2048 pairs/s",
+        computing_throughput(1024, time, None)
+    );
+    // No pairs take all the cycles as one.
+    assert_eq!(
+        "Computing throughput: 0.0 B/s, 0 pairs/s, 7.0 cycles/pair",
+        computing_throughput(0, time, Some(7))
+    );
+}
@@ -0,0 +1,198 @@
+use std::fs::File;
+use std::io;
+use std::ops::Deref;
+
+/// A file mapped read-only into memory, read as a byte slice without
+/// copying it.
+///
+/// The file must not change while it is mapped: the slice would change
+/// under its readers, and a truncated file faults when its lost pages are
+/// touched.
+pub struct Mmap {
+    start: *const u8,
+    len: usize,
+    /// None for an empty file, of which nothing is mapped.
+    mapping: Option<sys::Mapping>,
+}
+
+// SAFETY: the mapping is read-only and owned by the `Mmap` alone, like the
+// bytes of a `Box<[u8]>`.
+unsafe impl Send for Mmap {}
+// SAFETY: as above; the bytes are only read.
+unsafe impl Sync for Mmap {}
+
+impl Mmap {
+    /// Maps the whole of `file`, which must be open for reading.
+    pub fn map(file: &File) -> io::Result<Self> {
+        let len = usize::try_from(file.metadata()?.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::OutOfMemory, "file too large to map"))?;
+        if len == 0 {
+            return Ok(Self {
+                start: std::ptr::NonNull::dangling().as_ptr(),
+                len,
+                mapping: None,
+            });
+        }
+        let (start, mapping) = sys::map(file, len)?;
+        Ok(Self {
+            start,
+            len,
+            mapping: Some(mapping),
+        })
+    }
+}
+
+impl Deref for Mmap {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        // SAFETY: `start` points to `len` mapped bytes, or is dangling and
+        // aligned with `len` zero, and they live as long as `self`.
+        unsafe { std::slice::from_raw_parts(self.start, self.len) }
+    }
+}
+
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        if let Some(mapping) = &self.mapping {
+            sys::unmap(self.start, self.len, mapping);
+        }
+    }
+}
+
+#[cfg(unix)]
+mod sys {
+    use std::ffi::{c_int, c_long, c_void};
+    use std::fs::File;
+    use std::io;
+    use std::os::fd::AsRawFd;
+
+    const PROT_READ: c_int = 1;
+    const MAP_PRIVATE: c_int = 2;
+    const MAP_FAILED: *mut c_void = !0 as *mut c_void;
+
+    /// Nothing besides the address is needed to unmap.
+    pub(super) struct Mapping;
+
+    unsafe extern "C" {
+        fn mmap(
+            address: *mut c_void,
+            length: usize,
+            protection: c_int,
+            flags: c_int,
+            fd: c_int,
+            offset: c_long,
+        ) -> *mut c_void;
+        fn munmap(address: *mut c_void, length: usize) -> c_int;
+    }
+
+    pub(super) fn map(file: &File, len: usize) -> io::Result<(*const u8, Mapping)> {
+        // SAFETY: a new private read-only mapping of a descriptor open for
+        // reading, placed where the system chooses, overlaps nothing.
+        let start = unsafe {
+            mmap(
+                std::ptr::null_mut(),
+                len,
+                PROT_READ,
+                MAP_PRIVATE,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if start == MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok((start as *const u8, Mapping))
+    }
+
+    pub(super) fn unmap(start: *const u8, len: usize, _mapping: &Mapping) {
+        // SAFETY: the range is the one mapped by `map`, and its slices do
+        // not outlive the `Mmap` being dropped.
+        unsafe { munmap(start as *mut c_void, len) };
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    use std::ffi::c_void;
+    use std::fs::File;
+    use std::io;
+    use std::os::windows::io::AsRawHandle;
+
+    const PAGE_READONLY: u32 = 0x02;
+    const FILE_MAP_READ: u32 = 0x04;
+
+    /// The file mapping object the view belongs to.
+    pub(super) struct Mapping(*mut c_void);
+
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn CreateFileMappingW(
+            file: *mut c_void,
+            attributes: *const c_void,
+            protect: u32,
+            size_high: u32,
+            size_low: u32,
+            name: *const u16,
+        ) -> *mut c_void;
+        fn MapViewOfFile(
+            mapping: *mut c_void,
+            access: u32,
+            offset_high: u32,
+            offset_low: u32,
+            len: usize,
+        ) -> *mut c_void;
+        fn UnmapViewOfFile(address: *const c_void) -> i32;
+        fn CloseHandle(handle: *mut c_void) -> i32;
+    }
+
+    pub(super) fn map(file: &File, _len: usize) -> io::Result<(*const u8, Mapping)> {
+        // SAFETY: the handle is that of an open file; a size of zero maps
+        // the whole of it, and the mapping has no name to share it by.
+        let mapping = unsafe {
+            CreateFileMappingW(
+                file.as_raw_handle(),
+                std::ptr::null(),
+                PAGE_READONLY,
+                0,
+                0,
+                std::ptr::null(),
+            )
+        };
+        if mapping.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: `mapping` is the valid mapping object just created.
+        let start = unsafe { MapViewOfFile(mapping, FILE_MAP_READ, 0, 0, 0) };
+        if start.is_null() {
+            let error = io::Error::last_os_error();
+            // SAFETY: the mapping is closed once, having no view.
+            unsafe { CloseHandle(mapping) };
+            return Err(error);
+        }
+        Ok((start as *const u8, Mapping(mapping)))
+    }
+
+    pub(super) fn unmap(start: *const u8, _len: usize, mapping: &Mapping) {
+        // SAFETY: the view and the mapping are those created by `map`, and
+        // its slices do not outlive the `Mmap` being dropped.
+        unsafe {
+            UnmapViewOfFile(start as *const c_void);
+            CloseHandle(mapping.0);
+        }
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod sys {
+    use std::fs::File;
+    use std::io;
+
+    pub(super) struct Mapping;
+
+    pub(super) fn map(_file: &File, _len: usize) -> io::Result<(*const u8, Mapping)> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    pub(super) fn unmap(_start: *const u8, _len: usize, _mapping: &Mapping) {}
+}
@@ -0,0 +1,213 @@
+//! Reading that overlaps with the work on what was read: a background
+//! thread fills one buffer while the caller consumes the other. Files can
+//! also be read with several reads in flight, or mapped into memory, and
+//! any input opened through an `InputSource`.
+
+mod mmap;
+mod ring;
+mod source;
+
+pub use mmap::Mmap;
+pub use ring::{AsyncFileReader, DEFAULT_DEPTH};
+pub(crate) use source::text;
+pub use source::{FileSource, InputSource, IoSource, ReaderSource, StdinSource};
+
+use crate::memory;
+use std::io::{self, BufRead, Read};
+use std::mem;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{Receiver, SyncSender, sync_channel};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Size of each of the two buffers unless set otherwise.
+pub const DEFAULT_BUFFER_SIZE: usize = 1 << 20;
+
+/// How a file is read.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum IoStrategy {
+    /// Blocking reads on a background thread, with a `DoubleBufferedReader`.
+    #[default]
+    Sync,
+    /// Several reads in flight at once, with an `AsyncFileReader`.
+    Async,
+    /// The file mapped into memory, with an `Mmap`.
+    Mmap,
+}
+
+impl IoStrategy {
+    /// Parses `sync`, `async` or `mmap`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "sync" => Some(IoStrategy::Sync),
+            "async" => Some(IoStrategy::Async),
+            "mmap" => Some(IoStrategy::Mmap),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            IoStrategy::Sync => "sync",
+            IoStrategy::Async => "async",
+            IoStrategy::Mmap => "mmap",
+        }
+    }
+}
+
+/// Reader whose input is read ahead on a background thread, into the one of
+/// its two buffers the caller is not consuming.
+///
+/// The thread stops at the end of the input, at the first error, or when
+/// the reader is dropped and it next finishes a buffer.
+pub struct DoubleBufferedReader {
+    filled: Receiver<io::Result<Vec<u8>>>,
+    free: SyncSender<Vec<u8>>,
+    current: Vec<u8>,
+    position: usize,
+    done: bool,
+    waiting: Duration,
+    /// Nanoseconds the thread spent reading.
+    reading: Arc<AtomicU64>,
+}
+
+impl DoubleBufferedReader {
+    pub fn new<R: Read + Send + 'static>(inner: R) -> Self {
+        Self::with_buffer_size(inner, DEFAULT_BUFFER_SIZE)
+    }
+
+    /// Creates a reader with two buffers of `size` bytes, at least one.
+    pub fn with_buffer_size<R: Read + Send + 'static>(inner: R, size: usize) -> Self {
+        Self::build(inner, size, false)
+    }
+
+    /// Creates a reader like `with_buffer_size`, with the buffers advised to
+    /// be backed by huge pages. See `memory::advise_huge_pages`.
+    pub fn with_huge_pages<R: Read + Send + 'static>(inner: R, size: usize) -> Self {
+        Self::build(inner, size, true)
+    }
+
+    fn build<R: Read + Send + 'static>(mut inner: R, size: usize, huge_pages: bool) -> Self {
+        let size = size.max(1);
+        let (filled_sender, filled) = sync_channel(1);
+        let (free, free_receiver) = sync_channel::<Vec<u8>>(2);
+        for _ in 0..2 {
+            let buffer = Vec::with_capacity(size);
+            if huge_pages {
+                memory::advise_huge_pages(&buffer);
+            }
+            free.send(buffer).expect("The receiver is alive");
+        }
+        let reading = Arc::new(AtomicU64::new(0));
+        let thread_reading = Arc::clone(&reading);
+        thread::spawn(move || {
+            while let Ok(mut buffer) = free_receiver.recv() {
+                let start = Instant::now();
+                let filled = fill(&mut inner, &mut buffer, size);
+                let elapsed = start.elapsed().as_nanos() as u64;
+                thread_reading.fetch_add(elapsed, Ordering::Relaxed);
+                let stop = !matches!(filled, Ok(true));
+                if filled_sender.send(filled.map(|_| buffer)).is_err() || stop {
+                    break;
+                }
+            }
+        });
+        Self {
+            filled,
+            free,
+            current: Vec::new(),
+            position: 0,
+            done: false,
+            waiting: Duration::ZERO,
+            reading,
+        }
+    }
+
+    /// How much of the reading so far was hidden behind the caller's work.
+    pub fn overlap(&self) -> Overlap {
+        Overlap {
+            reading: Duration::from_nanos(self.reading.load(Ordering::Relaxed)),
+            waiting: self.waiting,
+        }
+    }
+}
+
+/// Reads into `buffer` until it holds `size` bytes or the input ends,
+/// returning whether anything was read.
+fn fill(inner: &mut impl Read, buffer: &mut Vec<u8>, size: usize) -> io::Result<bool> {
+    buffer.resize(size, 0);
+    let mut len = 0;
+    while len < size {
+        match inner.read(&mut buffer[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+            Err(error) => return Err(error),
+        }
+    }
+    buffer.truncate(len);
+    Ok(len > 0)
+}
+
+impl BufRead for DoubleBufferedReader {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.position == self.current.len() && !self.done {
+            let start = Instant::now();
+            let next = self.filled.recv();
+            self.waiting += start.elapsed();
+            match next {
+                Ok(Ok(buffer)) => {
+                    self.done = buffer.is_empty();
+                    let used = mem::replace(&mut self.current, buffer);
+                    self.position = 0;
+                    // Only fails once the thread has stopped.
+                    let _ = self.free.send(used);
+                }
+                Ok(Err(error)) => {
+                    self.done = true;
+                    return Err(error);
+                }
+                Err(_) => self.done = true,
+            }
+        }
+        Ok(&self.current[self.position..])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.position = (self.position + amount).min(self.current.len());
+    }
+}
+
+impl Read for DoubleBufferedReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+/// Time the background thread spent reading, and the caller spent waiting
+/// for it.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Overlap {
+    pub reading: Duration,
+    pub waiting: Duration,
+}
+
+impl Overlap {
+    /// Share of the reading time the caller did not wait through, from 0
+    /// when reading and working took turns to 1 when the work hid it all.
+    pub fn efficiency(&self) -> f64 {
+        if self.reading.is_zero() {
+            return 1.0;
+        }
+        (1.0 - self.waiting.as_secs_f64() / self.reading.as_secs_f64()).clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests;
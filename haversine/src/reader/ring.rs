@@ -0,0 +1,667 @@
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Read};
+use std::path::Path;
+
+/// Reads in flight at once unless set otherwise.
+pub const DEFAULT_DEPTH: usize = 4;
+
+/// Reader of a file with several reads in flight at once, submitted to the
+/// system's asynchronous interface: io_uring on Linux, overlapped I/O on
+/// Windows. Elsewhere, opening one fails as unsupported.
+///
+/// Each read fills one of its buffers from the next offset of the file;
+/// the buffers are returned in the order of the file, and read again from
+/// further on once consumed, so the device always has work queued.
+pub struct AsyncFileReader {
+    queue: sys::Queue,
+    buffers: Vec<Vec<u8>>,
+    /// Offset of the file each buffer was last read from.
+    offsets: Vec<u64>,
+    /// Buffers being read, in the order of their offsets.
+    in_flight: VecDeque<usize>,
+    free: Vec<usize>,
+    /// Offset of the next read.
+    offset: u64,
+    current: Option<usize>,
+    filled: usize,
+    position: usize,
+    done: bool,
+}
+
+impl AsyncFileReader {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::with_buffers(path, super::DEFAULT_BUFFER_SIZE, DEFAULT_DEPTH)
+    }
+
+    /// Opens a reader with `depth` buffers of `size` bytes, at least one of
+    /// each.
+    pub fn with_buffers(path: impl AsRef<Path>, size: usize, depth: usize) -> io::Result<Self> {
+        let depth = depth.max(1);
+        Ok(Self {
+            queue: sys::Queue::open(path.as_ref(), depth)?,
+            buffers: vec![vec![0; size.max(1)]; depth],
+            offsets: vec![0; depth],
+            in_flight: VecDeque::with_capacity(depth),
+            free: (0..depth).rev().collect(),
+            offset: 0,
+            current: None,
+            filled: 0,
+            position: 0,
+            done: false,
+        })
+    }
+
+    /// Reads the free buffers from the next offsets.
+    fn submit_free(&mut self) -> io::Result<()> {
+        while let Some(index) = self.free.pop() {
+            let buffer = &mut self.buffers[index];
+            // SAFETY: the buffer is neither moved nor touched until its read
+            // is waited for, which `drain` does before the buffers drop.
+            let submitted = unsafe {
+                self.queue
+                    .submit(index, buffer.as_mut_ptr(), buffer.len(), self.offset)
+            };
+            if let Err(error) = submitted {
+                self.free.push(index);
+                return Err(error);
+            }
+            self.offsets[index] = self.offset;
+            self.offset += buffer.len() as u64;
+            self.in_flight.push_back(index);
+        }
+        Ok(())
+    }
+
+    /// Waits for the reads in flight, dropping what they read.
+    fn drain(&mut self) {
+        while let Some(index) = self.in_flight.pop_front() {
+            let _ = self.queue.wait(index);
+            self.free.push(index);
+        }
+    }
+}
+
+impl BufRead for AsyncFileReader {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        while self.position == self.filled && !self.done {
+            if let Some(index) = self.current.take() {
+                self.free.push(index);
+            }
+            if let Err(error) = self.submit_free() {
+                self.done = true;
+                return Err(error);
+            }
+            let Some(index) = self.in_flight.pop_front() else {
+                self.done = true;
+                break;
+            };
+            let read = self.queue.wait(index);
+            self.current = Some(index);
+            self.position = 0;
+            self.filled = 0;
+            match read {
+                Ok(0) => self.done = true,
+                Ok(n) => {
+                    self.filled = n;
+                    // The reads after a short one started past what it left,
+                    // so they are read again from where it stopped.
+                    if n < self.buffers[index].len() {
+                        self.offset = self.offsets[index] + n as u64;
+                        self.drain();
+                    }
+                }
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {
+                    self.offset = self.offsets[index];
+                    self.drain();
+                }
+                Err(error) => {
+                    self.done = true;
+                    return Err(error);
+                }
+            }
+        }
+        Ok(match self.current {
+            Some(index) => &self.buffers[index][self.position..self.filled],
+            None => &[],
+        })
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.position = (self.position + amount).min(self.filled);
+    }
+}
+
+impl Read for AsyncFileReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl Drop for AsyncFileReader {
+    fn drop(&mut self) {
+        // The system still writes to the buffers being read.
+        self.drain();
+    }
+}
+
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+mod sys {
+    use std::ffi::{c_int, c_long, c_uint, c_void};
+    use std::fs::File;
+    use std::io;
+    use std::os::fd::{AsRawFd, FromRawFd};
+    use std::path::Path;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    // The same numbers on both targets, from the shared system call table.
+    const SYS_IO_URING_SETUP: c_long = 425;
+    const SYS_IO_URING_ENTER: c_long = 426;
+
+    const IORING_OFF_SQ_RING: c_long = 0;
+    const IORING_OFF_CQ_RING: c_long = 0x800_0000;
+    const IORING_OFF_SQES: c_long = 0x1000_0000;
+    const IORING_ENTER_GETEVENTS: c_uint = 1;
+    const IORING_OP_READ: u8 = 22;
+
+    const PROT_READ: c_int = 1;
+    const PROT_WRITE: c_int = 2;
+    const MAP_SHARED: c_int = 1;
+    const MAP_POPULATE: c_int = 0x8000;
+    const MAP_FAILED: *mut c_void = !0 as *mut c_void;
+
+    /// `struct io_sqring_offsets`.
+    #[repr(C)]
+    #[derive(Default)]
+    struct SqringOffsets {
+        head: u32,
+        tail: u32,
+        ring_mask: u32,
+        ring_entries: u32,
+        flags: u32,
+        dropped: u32,
+        array: u32,
+        resv1: u32,
+        user_addr: u64,
+    }
+
+    /// `struct io_cqring_offsets`.
+    #[repr(C)]
+    #[derive(Default)]
+    struct CqringOffsets {
+        head: u32,
+        tail: u32,
+        ring_mask: u32,
+        ring_entries: u32,
+        overflow: u32,
+        cqes: u32,
+        flags: u32,
+        resv1: u32,
+        user_addr: u64,
+    }
+
+    /// `struct io_uring_params`.
+    #[repr(C)]
+    #[derive(Default)]
+    struct Params {
+        sq_entries: u32,
+        cq_entries: u32,
+        flags: u32,
+        sq_thread_cpu: u32,
+        sq_thread_idle: u32,
+        features: u32,
+        wq_fd: u32,
+        resv: [u32; 3],
+        sq_off: SqringOffsets,
+        cq_off: CqringOffsets,
+    }
+
+    /// `struct io_uring_sqe`, with its unions as the members a read uses.
+    #[repr(C)]
+    #[derive(Default)]
+    struct Sqe {
+        opcode: u8,
+        flags: u8,
+        ioprio: u16,
+        fd: i32,
+        off: u64,
+        addr: u64,
+        len: u32,
+        rw_flags: u32,
+        user_data: u64,
+        buf_index: u16,
+        personality: u16,
+        splice_fd_in: i32,
+        addr3: u64,
+        pad: u64,
+    }
+
+    /// `struct io_uring_cqe`.
+    #[repr(C)]
+    struct Cqe {
+        user_data: u64,
+        res: i32,
+        flags: u32,
+    }
+
+    unsafe extern "C" {
+        fn mmap(
+            address: *mut c_void,
+            length: usize,
+            protection: c_int,
+            flags: c_int,
+            fd: c_int,
+            offset: c_long,
+        ) -> *mut c_void;
+        fn munmap(address: *mut c_void, length: usize) -> c_int;
+        fn syscall(number: c_long, ...) -> c_long;
+    }
+
+    /// Memory shared with the kernel, unmapped on drop.
+    struct Region {
+        start: *mut u8,
+        len: usize,
+    }
+
+    impl Region {
+        fn map(ring: &File, len: usize, offset: c_long) -> io::Result<Self> {
+            // SAFETY: a new shared mapping of the ring, where the system
+            // chooses, overlaps nothing.
+            let start = unsafe {
+                mmap(
+                    std::ptr::null_mut(),
+                    len,
+                    PROT_READ | PROT_WRITE,
+                    MAP_SHARED | MAP_POPULATE,
+                    ring.as_raw_fd(),
+                    offset,
+                )
+            };
+            if start == MAP_FAILED {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Self {
+                start: start.cast(),
+                len,
+            })
+        }
+
+        /// The word at `offset`, which the kernel also reads or writes.
+        fn word(&self, offset: u32) -> &AtomicU32 {
+            // SAFETY: the offsets come from the kernel, to aligned words
+            // within the region, which lives as long as the reference.
+            unsafe { &*self.start.add(offset as usize).cast::<AtomicU32>() }
+        }
+    }
+
+    impl Drop for Region {
+        fn drop(&mut self) {
+            // SAFETY: the region is the one mapped, and no reference into it
+            // outlives it.
+            unsafe { munmap(self.start.cast(), self.len) };
+        }
+    }
+
+    /// An io_uring reading one file, with its completions kept until the
+    /// reads they finish are waited for.
+    pub(super) struct Queue {
+        file: File,
+        sq: Region,
+        cq: Region,
+        sqes: Region,
+        params: Params,
+        ring: File,
+        results: Vec<Option<i32>>,
+    }
+
+    impl Queue {
+        pub(super) fn open(path: &Path, depth: usize) -> io::Result<Self> {
+            let file = File::open(path)?;
+            let mut params = Params::default();
+            // SAFETY: `params` is a valid, zeroed parameter structure the
+            // kernel fills in.
+            let fd = unsafe {
+                syscall(
+                    SYS_IO_URING_SETUP,
+                    depth as c_uint,
+                    &mut params as *mut Params,
+                )
+            };
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            // SAFETY: a non-negative result is a new descriptor owned by
+            // nothing else.
+            let ring = unsafe { File::from_raw_fd(fd as c_int) };
+            let sq_len = params.sq_off.array as usize + params.sq_entries as usize * 4;
+            let cq_len =
+                params.cq_off.cqes as usize + params.cq_entries as usize * size_of::<Cqe>();
+            let sqes_len = params.sq_entries as usize * size_of::<Sqe>();
+            Ok(Self {
+                sq: Region::map(&ring, sq_len, IORING_OFF_SQ_RING)?,
+                cq: Region::map(&ring, cq_len, IORING_OFF_CQ_RING)?,
+                sqes: Region::map(&ring, sqes_len, IORING_OFF_SQES)?,
+                file,
+                params,
+                ring,
+                results: vec![None; depth],
+            })
+        }
+
+        /// Starts reading `len` bytes at `offset` into `buffer`, identified
+        /// by `slot`, below the depth the queue was opened with.
+        ///
+        /// # Safety
+        ///
+        /// The buffer must stay valid and untouched until `wait(slot)`.
+        pub(super) unsafe fn submit(
+            &mut self,
+            slot: usize,
+            buffer: *mut u8,
+            len: usize,
+            offset: u64,
+        ) -> io::Result<()> {
+            let sq_off = &self.params.sq_off;
+            let tail = self.sq.word(sq_off.tail).load(Ordering::Relaxed);
+            let index = tail & self.sq.word(sq_off.ring_mask).load(Ordering::Relaxed);
+            let sqe = Sqe {
+                opcode: IORING_OP_READ,
+                fd: self.file.as_raw_fd(),
+                off: offset,
+                addr: buffer as u64,
+                len: len.min(u32::MAX as usize) as u32,
+                user_data: slot as u64,
+                ..Default::default()
+            };
+            // SAFETY: `index` is masked to the entries of the ring, and the
+            // entry is free, as no more reads are in flight than it has.
+            unsafe {
+                self.sqes.start.cast::<Sqe>().add(index as usize).write(sqe);
+                self.sq
+                    .start
+                    .add(sq_off.array as usize)
+                    .cast::<u32>()
+                    .add(index as usize)
+                    .write(index);
+            }
+            self.sq
+                .word(sq_off.tail)
+                .store(tail.wrapping_add(1), Ordering::Release);
+            self.enter(1, 0, 0)
+        }
+
+        /// Waits for the read of `slot` to finish, returning how much it
+        /// read.
+        pub(super) fn wait(&mut self, slot: usize) -> io::Result<usize> {
+            loop {
+                self.reap();
+                if let Some(result) = self.results[slot].take() {
+                    return match result {
+                        ..0 => Err(io::Error::from_raw_os_error(-result)),
+                        n => Ok(n as usize),
+                    };
+                }
+                match self.enter(0, 1, IORING_ENTER_GETEVENTS) {
+                    Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+                    result => result?,
+                }
+            }
+        }
+
+        /// Keeps the results of the finished reads.
+        fn reap(&mut self) {
+            let cq_off = &self.params.cq_off;
+            let head = self.cq.word(cq_off.head);
+            let mask = self.cq.word(cq_off.ring_mask).load(Ordering::Relaxed);
+            let tail = self.cq.word(cq_off.tail).load(Ordering::Acquire);
+            let mut next = head.load(Ordering::Relaxed);
+            while next != tail {
+                // SAFETY: the entries between the head and the tail were
+                // written by the kernel, which leaves them until the head
+                // moves past them.
+                let cqe = unsafe {
+                    self.cq
+                        .start
+                        .add(cq_off.cqes as usize)
+                        .cast::<Cqe>()
+                        .add((next & mask) as usize)
+                        .read()
+                };
+                if let Some(result) = self.results.get_mut(cqe.user_data as usize) {
+                    *result = Some(cqe.res);
+                }
+                next = next.wrapping_add(1);
+            }
+            head.store(next, Ordering::Release);
+        }
+
+        fn enter(&self, submit: c_uint, complete: c_uint, flags: c_uint) -> io::Result<()> {
+            // SAFETY: the ring is open, and no signal mask is passed.
+            let result = unsafe {
+                syscall(
+                    SYS_IO_URING_ENTER,
+                    self.ring.as_raw_fd(),
+                    submit,
+                    complete,
+                    flags,
+                    std::ptr::null::<c_void>(),
+                    0usize,
+                )
+            };
+            if result < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    use std::ffi::c_void;
+    use std::fs::{File, OpenOptions};
+    use std::io;
+    use std::os::windows::fs::OpenOptionsExt;
+    use std::os::windows::io::AsRawHandle;
+    use std::path::Path;
+
+    const FILE_FLAG_OVERLAPPED: u32 = 0x4000_0000;
+    const ERROR_HANDLE_EOF: i32 = 38;
+    const ERROR_IO_PENDING: i32 = 997;
+
+    /// `OVERLAPPED`, with its offset as the two halves.
+    #[repr(C)]
+    struct Overlapped {
+        internal: usize,
+        internal_high: usize,
+        offset: u32,
+        offset_high: u32,
+        event: *mut c_void,
+    }
+
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn ReadFile(
+            file: *mut c_void,
+            buffer: *mut u8,
+            len: u32,
+            read: *mut u32,
+            overlapped: *mut Overlapped,
+        ) -> i32;
+        fn GetOverlappedResult(
+            file: *mut c_void,
+            overlapped: *mut Overlapped,
+            read: *mut u32,
+            wait: i32,
+        ) -> i32;
+        fn CreateEventW(
+            attributes: *const c_void,
+            manual_reset: i32,
+            initial_state: i32,
+            name: *const u16,
+        ) -> *mut c_void;
+        fn CloseHandle(handle: *mut c_void) -> i32;
+    }
+
+    /// A file opened for overlapped reads, with a structure and an event
+    /// per read in flight.
+    pub(super) struct Queue {
+        file: File,
+        // Boxed, as the system writes to them while they are in flight.
+        overlapped: Box<[Overlapped]>,
+        /// Reads that found the end of the file as they were submitted.
+        at_end: Vec<bool>,
+    }
+
+    impl Queue {
+        pub(super) fn open(path: &Path, depth: usize) -> io::Result<Self> {
+            let file = OpenOptions::new()
+                .read(true)
+                .custom_flags(FILE_FLAG_OVERLAPPED)
+                .open(path)?;
+            let mut overlapped = Vec::with_capacity(depth);
+            for _ in 0..depth {
+                // SAFETY: an unnamed manual-reset event, owned by the queue.
+                let event = unsafe { CreateEventW(std::ptr::null(), 1, 0, std::ptr::null()) };
+                if event.is_null() {
+                    let error = io::Error::last_os_error();
+                    close_events(&overlapped);
+                    return Err(error);
+                }
+                overlapped.push(Overlapped {
+                    internal: 0,
+                    internal_high: 0,
+                    offset: 0,
+                    offset_high: 0,
+                    event,
+                });
+            }
+            Ok(Self {
+                file,
+                overlapped: overlapped.into_boxed_slice(),
+                at_end: vec![false; depth],
+            })
+        }
+
+        /// Starts reading `len` bytes at `offset` into `buffer`, identified
+        /// by `slot`, below the depth the queue was opened with.
+        ///
+        /// # Safety
+        ///
+        /// The buffer must stay valid and untouched until `wait(slot)`.
+        pub(super) unsafe fn submit(
+            &mut self,
+            slot: usize,
+            buffer: *mut u8,
+            len: usize,
+            offset: u64,
+        ) -> io::Result<()> {
+            let overlapped = &mut self.overlapped[slot];
+            overlapped.internal = 0;
+            overlapped.internal_high = 0;
+            overlapped.offset = offset as u32;
+            overlapped.offset_high = (offset >> 32) as u32;
+            // SAFETY: the file was opened for overlapped reads, and the
+            // buffer and the structure outlive the read, which `wait`
+            // finishes.
+            let started = unsafe {
+                ReadFile(
+                    self.file.as_raw_handle(),
+                    buffer,
+                    len.min(u32::MAX as usize) as u32,
+                    std::ptr::null_mut(),
+                    overlapped,
+                )
+            };
+            if started == 0 {
+                let error = io::Error::last_os_error();
+                match error.raw_os_error() {
+                    Some(ERROR_IO_PENDING) => {}
+                    Some(ERROR_HANDLE_EOF) => self.at_end[slot] = true,
+                    _ => return Err(error),
+                }
+            }
+            Ok(())
+        }
+
+        /// Waits for the read of `slot` to finish, returning how much it
+        /// read.
+        pub(super) fn wait(&mut self, slot: usize) -> io::Result<usize> {
+            if std::mem::take(&mut self.at_end[slot]) {
+                return Ok(0);
+            }
+            let mut read = 0;
+            // SAFETY: the structure is that of a read started by `submit`.
+            let finished = unsafe {
+                GetOverlappedResult(
+                    self.file.as_raw_handle(),
+                    &mut self.overlapped[slot],
+                    &mut read,
+                    1,
+                )
+            };
+            if finished == 0 {
+                let error = io::Error::last_os_error();
+                if error.raw_os_error() == Some(ERROR_HANDLE_EOF) {
+                    return Ok(0);
+                }
+                return Err(error);
+            }
+            Ok(read as usize)
+        }
+    }
+
+    impl Drop for Queue {
+        fn drop(&mut self) {
+            close_events(&self.overlapped);
+        }
+    }
+
+    fn close_events(overlapped: &[Overlapped]) {
+        for overlapped in overlapped {
+            // SAFETY: each event was created by `open` and is closed once,
+            // with no read left waiting on it.
+            unsafe { CloseHandle(overlapped.event) };
+        }
+    }
+}
+
+#[cfg(not(any(
+    all(
+        target_os = "linux",
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    ),
+    windows
+)))]
+mod sys {
+    use std::io;
+    use std::path::Path;
+
+    pub(super) enum Queue {}
+
+    impl Queue {
+        pub(super) fn open(_path: &Path, _depth: usize) -> io::Result<Self> {
+            Err(io::ErrorKind::Unsupported.into())
+        }
+
+        pub(super) unsafe fn submit(
+            &mut self,
+            _slot: usize,
+            _buffer: *mut u8,
+            _len: usize,
+            _offset: u64,
+        ) -> io::Result<()> {
+            match *self {}
+        }
+
+        pub(super) fn wait(&mut self, _slot: usize) -> io::Result<usize> {
+            match *self {}
+        }
+    }
+}
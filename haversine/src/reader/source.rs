@@ -0,0 +1,293 @@
+use super::{AsyncFileReader, DEFAULT_DEPTH, DoubleBufferedReader, IoStrategy, Mmap, Overlap};
+use crate::parse::{Encoding, decode, detect_encoding};
+use std::borrow::Cow;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// Where an input is read from: a file, a mapped file, stdin, bytes in
+/// memory or any reader, such as a decompression stream.
+///
+/// ```
+/// use haversine::reader::InputSource;
+///
+/// let mut source = b"[1, 2]".as_slice();
+/// assert_eq!(Some(6), source.size());
+/// assert_eq!("[1, 2]", source.read_text().unwrap());
+/// ```
+pub trait InputSource {
+    /// Opens the input for reading. Files and bytes are read from the
+    /// start on every open, stdin and readers from where the last read
+    /// stopped.
+    fn open(&mut self) -> io::Result<Box<dyn Read + Send + '_>>;
+
+    /// Size of the input in bytes, if known before reading it.
+    fn size(&self) -> Option<u64> {
+        None
+    }
+
+    /// Reads the whole input, borrowing it where the source holds it.
+    fn read_all(&mut self) -> io::Result<Cow<'_, [u8]>> {
+        let mut bytes = Vec::new();
+        self.open()?.read_to_end(&mut bytes)?;
+        Ok(Cow::Owned(bytes))
+    }
+
+    /// Reads the whole input as text. UTF-16 and UTF-32, told apart as by
+    /// `detect_encoding`, are transcoded to UTF-8; input that is not valid
+    /// in its encoding is `InvalidData`.
+    fn read_text(&mut self) -> io::Result<Cow<'_, str>> {
+        text(self.read_all()?)
+    }
+}
+
+/// Converts the bytes of an input to text, as `InputSource::read_text`
+/// does.
+pub(crate) fn text(bytes: Cow<'_, [u8]>) -> io::Result<Cow<'_, str>> {
+    let invalid = |error| io::Error::new(io::ErrorKind::InvalidData, error);
+    if detect_encoding(&bytes) != Encoding::Utf8 {
+        return decode(bytes.into_owned())
+            .map(Cow::Owned)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error));
+    }
+    match bytes {
+        Cow::Borrowed(bytes) => std::str::from_utf8(bytes)
+            .map(Cow::Borrowed)
+            .map_err(invalid),
+        Cow::Owned(bytes) => String::from_utf8(bytes)
+            .map(Cow::Owned)
+            .map_err(|error| invalid(error.utf8_error())),
+    }
+}
+
+/// The file at a path, opened on every `open`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileSource(PathBuf);
+
+impl FileSource {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self(path.into())
+    }
+}
+
+impl InputSource for FileSource {
+    fn open(&mut self) -> io::Result<Box<dyn Read + Send + '_>> {
+        Ok(Box::new(File::open(&self.0)?))
+    }
+
+    fn size(&self) -> Option<u64> {
+        std::fs::metadata(&self.0)
+            .ok()
+            .map(|metadata| metadata.len())
+    }
+}
+
+/// Standard input.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StdinSource;
+
+impl InputSource for StdinSource {
+    fn open(&mut self) -> io::Result<Box<dyn Read + Send + '_>> {
+        Ok(Box::new(io::stdin()))
+    }
+}
+
+/// Any reader, read once.
+#[derive(Debug)]
+pub struct ReaderSource<R>(pub R);
+
+impl<R: Read + Send> InputSource for ReaderSource<R> {
+    fn open(&mut self) -> io::Result<Box<dyn Read + Send + '_>> {
+        Ok(Box::new(&mut self.0))
+    }
+}
+
+/// A file, or stdin, opened for the way of reading it asked for, or read
+/// on a background thread where the system does not offer that. Like a
+/// `ReaderSource`, it is read once.
+pub struct IoSource {
+    reader: IoReader,
+    size: Option<u64>,
+    fallback: Option<io::Error>,
+}
+
+enum IoReader {
+    Buffered(DoubleBufferedReader),
+    Async(Box<AsyncFileReader>),
+    Mapped(Mmap),
+}
+
+impl IoReader {
+    fn reader(&mut self) -> Box<dyn Read + '_> {
+        match self {
+            IoReader::Buffered(reader) => Box::new(reader),
+            IoReader::Async(reader) => Box::new(reader.as_mut()),
+            IoReader::Mapped(map) => Box::new(&map[..]),
+        }
+    }
+}
+
+impl IoSource {
+    /// Opens the file at `path` with `strategy`. The buffers of the
+    /// readers are of `buffer_size` bytes, backed by huge pages for sync
+    /// I/O if `huge_pages`.
+    pub fn open(
+        path: impl AsRef<Path>,
+        strategy: IoStrategy,
+        buffer_size: usize,
+        huge_pages: bool,
+    ) -> io::Result<Self> {
+        let path = path.as_ref();
+        let size = std::fs::metadata(path).ok().map(|metadata| metadata.len());
+        let fallback = match strategy {
+            IoStrategy::Async => {
+                match AsyncFileReader::with_buffers(path, buffer_size, DEFAULT_DEPTH) {
+                    Ok(reader) => return Ok(Self::new(IoReader::Async(Box::new(reader)), size)),
+                    Err(error) => Some(error),
+                }
+            }
+            IoStrategy::Mmap => match Mmap::map(&File::open(path)?) {
+                Ok(map) => return Ok(Self::new(IoReader::Mapped(map), size)),
+                Err(error) => Some(error),
+            },
+            _ => None,
+        };
+        let file = File::open(path)?;
+        Ok(Self {
+            fallback,
+            ..Self::buffered(file, size, buffer_size, huge_pages)
+        })
+    }
+
+    /// Reads stdin on a background thread, as `open` does a file with sync
+    /// I/O.
+    pub fn stdin(buffer_size: usize, huge_pages: bool) -> Self {
+        Self::buffered(io::stdin(), None, buffer_size, huge_pages)
+    }
+
+    fn new(reader: IoReader, size: Option<u64>) -> Self {
+        Self {
+            reader,
+            size,
+            fallback: None,
+        }
+    }
+
+    fn buffered(
+        input: impl Read + Send + 'static,
+        size: Option<u64>,
+        buffer_size: usize,
+        huge_pages: bool,
+    ) -> Self {
+        let reader = match huge_pages {
+            true => DoubleBufferedReader::with_huge_pages(input, buffer_size),
+            false => DoubleBufferedReader::with_buffer_size(input, buffer_size),
+        };
+        Self::new(IoReader::Buffered(reader), size)
+    }
+
+    /// The way the input is read, sync where the one asked for failed.
+    pub fn strategy(&self) -> IoStrategy {
+        match self.reader {
+            IoReader::Buffered(_) => IoStrategy::Sync,
+            IoReader::Async(_) => IoStrategy::Async,
+            IoReader::Mapped(_) => IoStrategy::Mmap,
+        }
+    }
+
+    /// Takes the error the way asked for failed with, if it did.
+    pub fn take_fallback(&mut self) -> Option<io::Error> {
+        self.fallback.take()
+    }
+
+    /// How much of the reading was hidden behind the work on what was
+    /// read, known for sync I/O only.
+    pub fn overlap(&self) -> Option<Overlap> {
+        match &self.reader {
+            IoReader::Buffered(reader) => Some(reader.overlap()),
+            _ => None,
+        }
+    }
+
+    /// Size of the input in bytes, if known before reading it.
+    pub fn size(&self) -> Option<u64> {
+        self.size
+    }
+
+    /// The input as a reader. Unlike those of an `InputSource`, it may not
+    /// be sent to another thread.
+    pub fn reader(&mut self) -> Box<dyn Read + '_> {
+        self.reader.reader()
+    }
+
+    /// Reads the whole input, borrowing a mapped file rather than copying
+    /// it.
+    pub fn read_all(&mut self) -> io::Result<Cow<'_, [u8]>> {
+        match &mut self.reader {
+            IoReader::Mapped(map) => Ok(Cow::Borrowed(map)),
+            reader => {
+                let mut bytes = Vec::new();
+                reader.reader().read_to_end(&mut bytes)?;
+                Ok(Cow::Owned(bytes))
+            }
+        }
+    }
+
+    /// Whether the input is a mapped file, read all at once.
+    pub fn is_mapped(&self) -> bool {
+        matches!(self.reader, IoReader::Mapped(_))
+    }
+}
+
+impl fmt::Debug for IoSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IoSource")
+            .field("strategy", &self.strategy())
+            .field("size", &self.size)
+            .field("fallback", &self.fallback)
+            .finish_non_exhaustive()
+    }
+}
+
+impl InputSource for Mmap {
+    fn open(&mut self) -> io::Result<Box<dyn Read + Send + '_>> {
+        Ok(Box::new(&self[..]))
+    }
+
+    fn size(&self) -> Option<u64> {
+        Some(self.len() as u64)
+    }
+
+    fn read_all(&mut self) -> io::Result<Cow<'_, [u8]>> {
+        Ok(Cow::Borrowed(self))
+    }
+}
+
+impl InputSource for &[u8] {
+    fn open(&mut self) -> io::Result<Box<dyn Read + Send + '_>> {
+        Ok(Box::new(*self))
+    }
+
+    fn size(&self) -> Option<u64> {
+        Some(self.len() as u64)
+    }
+
+    fn read_all(&mut self) -> io::Result<Cow<'_, [u8]>> {
+        Ok(Cow::Borrowed(self))
+    }
+}
+
+impl InputSource for Vec<u8> {
+    fn open(&mut self) -> io::Result<Box<dyn Read + Send + '_>> {
+        Ok(Box::new(self.as_slice()))
+    }
+
+    fn size(&self) -> Option<u64> {
+        Some(self.len() as u64)
+    }
+
+    fn read_all(&mut self) -> io::Result<Cow<'_, [u8]>> {
+        Ok(Cow::Borrowed(self))
+    }
+}
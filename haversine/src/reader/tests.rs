@@ -0,0 +1,205 @@
+use super::{
+    AsyncFileReader, DoubleBufferedReader, FileSource, InputSource, IoSource, IoStrategy, Mmap,
+    Overlap, ReaderSource,
+};
+use std::borrow::Cow;
+use std::fs::{self, File};
+use std::io::{self, BufRead, Read};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Reader returning its bytes a few at a time, then an error if given one.
+struct Trickle {
+    bytes: Vec<u8>,
+    position: usize,
+    error: Option<io::ErrorKind>,
+}
+
+impl Read for Trickle {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position == self.bytes.len() {
+            return match self.error.take() {
+                Some(kind) => Err(kind.into()),
+                None => Ok(0),
+            };
+        }
+        let n = buf.len().min(3).min(self.bytes.len() - self.position);
+        buf[..n].copy_from_slice(&self.bytes[self.position..][..n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+fn trickle(bytes: &[u8], error: Option<io::ErrorKind>) -> Trickle {
+    Trickle {
+        bytes: bytes.to_vec(),
+        position: 0,
+        error,
+    }
+}
+
+#[test]
+fn test_reads_everything_in_order() {
+    let bytes: Vec<u8> = (0..10_000).map(|i| (i * 7 % 251) as u8).collect();
+    for size in [1, 5, 64, 4096, 1 << 20] {
+        let mut reader = DoubleBufferedReader::with_buffer_size(trickle(&bytes, None), size);
+        let mut read = Vec::new();
+        reader.read_to_end(&mut read).unwrap();
+        assert_eq!(bytes, read, "buffer size {size}");
+        assert_eq!(0, reader.read(&mut [0; 8]).unwrap());
+    }
+}
+
+#[test]
+fn test_buf_read_lines() {
+    let reader = DoubleBufferedReader::with_buffer_size(trickle(b"one\ntwo\nthree", None), 4);
+    let lines: Vec<_> = reader.lines().collect::<Result<_, _>>().unwrap();
+    assert_eq!(vec!["one", "two", "three"], lines);
+}
+
+#[test]
+fn test_error_after_the_bytes_before_it() {
+    let source = trickle(b"abcdef", Some(io::ErrorKind::BrokenPipe));
+    let mut reader = DoubleBufferedReader::with_buffer_size(source, 4);
+    let mut read = [0; 4];
+    reader.read_exact(&mut read).unwrap();
+    assert_eq!(b"abcd", &read);
+    // The buffer holding "ef" failed to fill, so its bytes are lost with it.
+    let error = reader.read(&mut read).unwrap_err();
+    assert_eq!(io::ErrorKind::BrokenPipe, error.kind());
+    assert_eq!(0, reader.read(&mut read).unwrap());
+}
+
+#[test]
+fn test_overlap_efficiency() {
+    let overlap = |reading, waiting| Overlap {
+        reading: Duration::from_millis(reading),
+        waiting: Duration::from_millis(waiting),
+    };
+    assert_eq!(1.0, overlap(0, 0).efficiency());
+    assert_eq!(0.75, overlap(100, 25).efficiency());
+    assert_eq!(0.0, overlap(100, 150).efficiency());
+}
+
+/// Writes `bytes` to a file of the temporary directory unique to the test.
+fn temp_file(name: &str, bytes: &[u8]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("reader-{name}-{}", std::process::id()));
+    fs::write(&path, bytes).unwrap();
+    path
+}
+
+#[test]
+fn test_async_reads_everything_in_order() {
+    let bytes: Vec<u8> = (0..10_000).map(|i| (i * 7 % 251) as u8).collect();
+    let path = temp_file("async", &bytes);
+    for (size, depth) in [(1, 1), (5, 3), (4096, 4), (1 << 20, 2)] {
+        let mut reader = match AsyncFileReader::with_buffers(&path, size, depth) {
+            Ok(reader) => reader,
+            // Not on this platform, or not allowed in this sandbox.
+            Err(_) => break,
+        };
+        let mut read = Vec::new();
+        reader.read_to_end(&mut read).unwrap();
+        assert_eq!(bytes, read, "buffer size {size}, depth {depth}");
+        assert_eq!(0, reader.read(&mut [0; 8]).unwrap());
+    }
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_async_stops_early() {
+    let path = temp_file("async-early", &[1; 100_000]);
+    if let Ok(mut reader) = AsyncFileReader::with_buffers(&path, 1000, 4) {
+        let mut read = [0; 10];
+        reader.read_exact(&mut read).unwrap();
+        assert_eq!([1; 10], read);
+        // Dropped with reads in flight.
+    }
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_mmap() {
+    let path = temp_file("mmap", b"{\"pairs\": []}");
+    let file = File::open(&path).unwrap();
+    if let Ok(map) = Mmap::map(&file) {
+        assert_eq!(b"{\"pairs\": []}", &map[..]);
+    }
+    fs::write(&path, b"").unwrap();
+    let file = File::open(&path).unwrap();
+    assert!(Mmap::map(&file).unwrap().is_empty());
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_io_strategy_from_name() {
+    for strategy in [IoStrategy::Sync, IoStrategy::Async, IoStrategy::Mmap] {
+        assert_eq!(Some(strategy), IoStrategy::from_name(strategy.name()));
+    }
+    assert_eq!(None, IoStrategy::from_name("direct"));
+}
+
+#[test]
+fn test_input_sources_read_the_same() {
+    let bytes = b"{\"pairs\": []}".to_vec();
+    let path = temp_file("source", &bytes);
+    let mut file = FileSource::new(&path);
+    assert_eq!(Some(bytes.len() as u64), file.size());
+    assert_eq!(bytes, file.read_all().unwrap().as_ref());
+    // A file is read from the start on every open.
+    assert_eq!("{\"pairs\": []}", file.read_text().unwrap());
+
+    let mut map = Mmap::map(&File::open(&path).unwrap()).unwrap();
+    assert!(matches!(map.read_all().unwrap(), Cow::Borrowed(read) if read == bytes));
+    fs::remove_file(path).unwrap();
+
+    let mut slice = bytes.as_slice();
+    let mut read = Vec::new();
+    slice.open().unwrap().read_to_end(&mut read).unwrap();
+    assert_eq!(bytes, read);
+
+    // A reader is read once.
+    let mut reader = ReaderSource(Trickle {
+        bytes: bytes.clone(),
+        position: 0,
+        error: None,
+    });
+    assert_eq!(None, reader.size());
+    assert_eq!(bytes, reader.read_all().unwrap().as_ref());
+    assert!(reader.read_all().unwrap().is_empty());
+
+    let mut invalid = vec![b'[', 0xff];
+    assert_eq!(
+        io::ErrorKind::InvalidData,
+        invalid.read_text().unwrap_err().kind()
+    );
+    let mut utf16 = vec![0xff, 0xfe];
+    utf16.extend("[\"é\"]".encode_utf16().flat_map(u16::to_le_bytes));
+    assert_eq!("[\"é\"]", utf16.read_text().unwrap());
+    let mut unpaired = vec![0xff, 0xfe, 0x00, 0xd8];
+    assert_eq!(
+        io::ErrorKind::InvalidData,
+        unpaired.read_text().unwrap_err().kind()
+    );
+}
+
+#[test]
+fn test_io_sources_read_the_same() {
+    let bytes: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+    let path = temp_file("io-source", &bytes);
+    for strategy in [IoStrategy::Sync, IoStrategy::Async, IoStrategy::Mmap] {
+        let mut source = IoSource::open(&path, strategy, 4096, false).unwrap();
+        assert_eq!(Some(bytes.len() as u64), source.size());
+        // Where the strategy is not offered, the file is read with sync I/O.
+        let fallback = source.take_fallback();
+        assert_eq!(fallback.is_some(), source.strategy() != strategy);
+        assert_eq!(source.is_mapped(), source.strategy() == IoStrategy::Mmap);
+        assert_eq!(
+            source.strategy() == IoStrategy::Sync,
+            source.overlap().is_some()
+        );
+        assert_eq!(bytes, source.read_all().unwrap().as_ref());
+    }
+    fs::remove_file(&path).unwrap();
+    assert!(IoSource::open(&path, IoStrategy::Mmap, 4096, false).is_err());
+}
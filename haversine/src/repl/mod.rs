@@ -0,0 +1,273 @@
+//! An interactive calculator of distances, reading a command or a JSON
+//! snippet per line and writing its result.
+
+use std::io::{self, BufRead, IsTerminal};
+
+use haversine::compute::ComputeDriver;
+use haversine::geo::{
+    DistanceUnit, RadiusModel, destination, final_bearing, haversine_distance, initial_bearing,
+};
+use haversine::pairs::{Pair, Pairs};
+use haversine::parse::{Parser, Value};
+use haversine::sink::OutputSink;
+
+use crate::cli::ReplArgs;
+
+const HELP: &str = "\
+Points are given as a latitude and a longitude in degrees.
+  dist LAT LON LAT LON        Great-circle distance of two points
+  bearing LAT LON LAT LON     Initial and final bearings from the first point
+  dest LAT LON BEARING DIST   Point reached from a point at a bearing
+  radius [MODEL|KM]           Show or set the radius: mean, equatorial,
+                              polar, authalic or kilometers
+  units [km|m|mi|nmi]         Show or set the unit of the distances
+  help                        Print this help
+  quit                        Leave, as does the end of the input
+A JSON snippet is evaluated: a pair object gives its distance, an array of
+pairs or a pairs file their average, checked against any stored avg_dist,
+and any other value is written back.";
+
+/// A line of input.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Line<'a> {
+    /// Nothing but whitespace.
+    Empty,
+    Quit,
+    Command(Command<'a>),
+}
+
+/// A command of a line, with its arguments parsed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Command<'a> {
+    /// Latitude and longitude of two points.
+    Dist([f64; 4]),
+    Bearing([f64; 4]),
+    /// Latitude and longitude of a point, a bearing and a distance.
+    Dest([f64; 4]),
+    /// Show the radius, or set it in kilometers.
+    Radius(Option<f64>),
+    /// Show the unit, or set it.
+    Units(Option<DistanceUnit>),
+    Help,
+    /// A JSON snippet to evaluate.
+    Json(&'a str),
+}
+
+impl<'a> Line<'a> {
+    fn parse(line: &'a str) -> Result<Self, String> {
+        let line = line.trim();
+        if line.starts_with(['{', '[', '"']) {
+            return Ok(Line::Command(Command::Json(line)));
+        }
+        let mut words = line.split_whitespace();
+        let Some(command) = words.next() else {
+            return Ok(Line::Empty);
+        };
+        let words: Vec<_> = words.collect();
+        let command = match command {
+            "quit" | "exit" if words.is_empty() => return Ok(Line::Quit),
+            "dist" => Command::Dist(numbers(&words)?),
+            "bearing" => Command::Bearing(numbers(&words)?),
+            "dest" => Command::Dest(numbers(&words)?),
+            "radius" => match words[..] {
+                [] => Command::Radius(None),
+                [value] => RadiusModel::from_name(value)
+                    .map(RadiusModel::kilometers)
+                    .or_else(|| value.parse().ok().filter(|&radius: &f64| radius > 0.0))
+                    .map(|radius| Command::Radius(Some(radius)))
+                    .ok_or_else(|| format!("invalid radius {value:?}"))?,
+                _ => return Err("expected a radius model or kilometers".into()),
+            },
+            "units" => match words[..] {
+                [] => Command::Units(None),
+                [value] => DistanceUnit::from_name(value)
+                    .map(|units| Command::Units(Some(units)))
+                    .ok_or_else(|| format!("invalid unit {value:?}"))?,
+                _ => return Err("expected km, m, mi or nmi".into()),
+            },
+            "help" => Command::Help,
+            command => return Err(format!("unknown command {command:?}, see help")),
+        };
+        Ok(Line::Command(command))
+    }
+}
+
+struct Session {
+    radius: f64,
+    units: DistanceUnit,
+}
+
+/// Reads commands from stdin until `quit` or the end of the input,
+/// prompting for them when stdin is a terminal.
+pub(crate) fn run(output: &mut dyn OutputSink, args: ReplArgs) -> io::Result<()> {
+    let mut session = Session::new(&args);
+    let interactive = io::stdin().is_terminal();
+    session.serve(io::stdin().lock(), output, interactive)
+}
+
+impl Session {
+    fn new(args: &ReplArgs) -> Self {
+        Self {
+            radius: args.radius_model.unwrap_or(RadiusModel::Mean).kilometers(),
+            units: args.units.unwrap_or_default(),
+        }
+    }
+
+    /// Answers the lines of `input` on `output` until `quit` or the end of
+    /// the input, prompting for each if `prompt`.
+    fn serve(
+        &mut self,
+        input: impl BufRead,
+        output: &mut dyn OutputSink,
+        prompt: bool,
+    ) -> io::Result<()> {
+        let mut lines = input.lines();
+        loop {
+            if prompt {
+                write!(output, "> ")?;
+                output.flush()?;
+            }
+            let line = match lines.next() {
+                Some(Ok(line)) => line,
+                Some(Err(error)) => return writeln!(output, "error: {error}"),
+                None => return Ok(()),
+            };
+            let answer = match Line::parse(&line) {
+                Ok(Line::Empty) => continue,
+                Ok(Line::Quit) => return Ok(()),
+                Ok(Line::Command(command)) => self.eval(command),
+                Err(message) => Err(message),
+            };
+            match answer {
+                Ok(answer) => writeln!(output, "{answer}")?,
+                Err(message) => writeln!(output, "error: {message}")?,
+            }
+        }
+    }
+
+    fn eval(&mut self, command: Command) -> Result<String, String> {
+        match command {
+            Command::Dist([lat_0, lon_0, lat_1, lon_1]) => {
+                Ok(self.distance(haversine_distance(self.radius, lon_0, lat_0, lon_1, lat_1)))
+            }
+            Command::Bearing([lat_0, lon_0, lat_1, lon_1]) => Ok(format!(
+                "initial {}°, final {}°",
+                initial_bearing(lon_0, lat_0, lon_1, lat_1),
+                final_bearing(lon_0, lat_0, lon_1, lat_1)
+            )),
+            Command::Dest([lat, lon, bearing, distance]) => {
+                let kilometers = distance / self.units.convert_kilometers(1.0);
+                let (lon, lat) = destination(self.radius, lon, lat, bearing, kilometers);
+                Ok(format!("{lat} {lon}"))
+            }
+            Command::Radius(radius) => {
+                if let Some(radius) = radius {
+                    self.radius = radius;
+                }
+                Ok(format!("{} km", self.radius))
+            }
+            Command::Units(units) => {
+                if let Some(units) = units {
+                    self.units = units;
+                }
+                Ok(self.units.symbol().to_string())
+            }
+            Command::Help => Ok(HELP.to_string()),
+            Command::Json(snippet) => self.eval_json(snippet),
+        }
+    }
+
+    fn eval_json(&self, line: &str) -> Result<String, String> {
+        let value = match Parser::new(line).parse() {
+            Ok(Some(value)) => value,
+            Ok(None) => return Err("expected a JSON value".into()),
+            Err(error) => return Err(format!("invalid JSON: {error}")),
+        };
+        match &value {
+            Value::Object(object) if object.get("x0").is_some() => {
+                let Pair { p0, p1 } = pairs(std::slice::from_ref(&value))?
+                    .iter()
+                    .next()
+                    .expect("One pair");
+                Ok(self.distance(haversine_distance(
+                    self.radius,
+                    p0.lon,
+                    p0.lat,
+                    p1.lon,
+                    p1.lat,
+                )))
+            }
+            Value::Array(values) => self.average(&pairs(values)?, None, None),
+            Value::Object(object) => match object.get("pairs") {
+                Some(Value::Array(values)) => {
+                    let number = |key| match object.get(key) {
+                        Some(Value::Number(number)) => Some(number.as_f64()),
+                        _ => None,
+                    };
+                    self.average(&pairs(values)?, number("radius"), number("avg_dist"))
+                }
+                _ => Ok(value.to_json()),
+            },
+            value => Ok(value.to_json()),
+        }
+    }
+
+    /// The average distance of pairs, on the sphere of `radius` if the
+    /// document has one, compared to its stored average.
+    fn average(
+        &self,
+        pairs: &Pairs,
+        radius: Option<f64>,
+        stored: Option<f64>,
+    ) -> Result<String, String> {
+        if pairs.is_empty() {
+            return Err("expected at least one pair".into());
+        }
+        let average = ComputeDriver::new(radius.unwrap_or(self.radius))
+            .run(pairs)
+            .average;
+        let count = match pairs.len() {
+            1 => "1 pair".to_string(),
+            n => format!("{n} pairs"),
+        };
+        let mut output = match radius {
+            Some(_) => format!("{count}, average {average}"),
+            None => format!("{count}, average {}", self.distance(average)),
+        };
+        if let Some(stored) = stored {
+            let error = (average - stored).abs();
+            output += &format!(" (stored {stored}, difference {error})");
+        }
+        Ok(output)
+    }
+
+    /// A distance in kilometers, in the unit of the session.
+    fn distance(&self, kilometers: f64) -> String {
+        format!(
+            "{} {}",
+            self.units.convert_kilometers(kilometers),
+            self.units.symbol()
+        )
+    }
+}
+
+fn pairs(values: &[Value]) -> Result<Pairs, String> {
+    Pairs::from_values(values).map_err(|error| error.to_string())
+}
+
+/// Parses the arguments of a command taking `N` numbers.
+fn numbers<const N: usize>(words: &[&str]) -> Result<[f64; N], String> {
+    if words.len() != N {
+        return Err(format!("expected {N} numbers"));
+    }
+    let mut numbers = [0.0; N];
+    for (number, word) in numbers.iter_mut().zip(words) {
+        *number = word
+            .parse()
+            .map_err(|_| format!("invalid number {word:?}"))?;
+    }
+    Ok(numbers)
+}
+
+#[cfg(test)]
+mod tests;
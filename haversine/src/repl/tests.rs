@@ -0,0 +1,186 @@
+use super::{Command, Line, Session};
+use crate::cli::ReplArgs;
+use haversine::geo::{DistanceUnit, RadiusModel, haversine_distance};
+use haversine::sink::BufferSink;
+
+fn session() -> Session {
+    Session::new(&ReplArgs {
+        radius_model: Some(RadiusModel::Custom(1.0)),
+        units: None,
+    })
+}
+
+/// A quarter of the equator of the unit sphere.
+fn quarter() -> f64 {
+    haversine_distance(1.0, 0.0, 0.0, 90.0, 0.0)
+}
+
+fn eval(session: &mut Session, line: &str) -> Result<String, String> {
+    match Line::parse(line)? {
+        Line::Command(command) => session.eval(command),
+        line => panic!("Expected a command, got {line:?}"),
+    }
+}
+
+#[test]
+fn test_parse_commands() {
+    assert_eq!(Ok(Line::Empty), Line::parse("  \t"));
+    assert_eq!(Ok(Line::Quit), Line::parse(" quit "));
+    assert_eq!(Ok(Line::Quit), Line::parse("exit"));
+    assert_eq!(
+        Ok(Line::Command(Command::Dist([1.0, 2.0, -3.5, 4e1]))),
+        Line::parse("dist 1 2  -3.5 4e1")
+    );
+    assert_eq!(
+        Ok(Line::Command(Command::Radius(Some(
+            RadiusModel::Polar.kilometers()
+        )))),
+        Line::parse("radius polar")
+    );
+    assert_eq!(
+        Ok(Line::Command(Command::Radius(Some(1737.4)))),
+        Line::parse("radius 1737.4")
+    );
+    assert_eq!(
+        Ok(Line::Command(Command::Units(Some(DistanceUnit::Miles)))),
+        Line::parse("units mi")
+    );
+    assert_eq!(
+        Ok(Line::Command(Command::Json(r#"{"a": 1}"#))),
+        Line::parse(r#"  {"a": 1} "#)
+    );
+}
+
+#[test]
+fn test_parse_wrong_argument_count() {
+    for line in ["dist 1 2 3", "bearing 1 2 3 4 5", "dest"] {
+        assert_eq!(Err("expected 4 numbers".to_string()), Line::parse(line));
+    }
+    assert_eq!(
+        Err("expected a radius model or kilometers".to_string()),
+        Line::parse("radius mean polar")
+    );
+    assert_eq!(
+        Err("expected km, m, mi or nmi".to_string()),
+        Line::parse("units km m")
+    );
+    assert_eq!(
+        Err(r#"unknown command "quit", see help"#.to_string()),
+        Line::parse("quit now")
+    );
+}
+
+#[test]
+fn test_parse_invalid_values() {
+    assert_eq!(
+        Err(r#"invalid number "north""#.to_string()),
+        Line::parse("dist 1 north 3 4")
+    );
+    for radius in ["-1", "0", "moon", "NaN"] {
+        assert_eq!(
+            Err(format!("invalid radius {radius:?}")),
+            Line::parse(&format!("radius {radius}"))
+        );
+    }
+    assert_eq!(
+        Err(r#"invalid unit "furlong""#.to_string()),
+        Line::parse("units furlong")
+    );
+    assert_eq!(
+        Err(r#"unknown command "distance", see help"#.to_string()),
+        Line::parse("distance 1 2 3 4")
+    );
+}
+
+#[test]
+fn test_eval_radius_and_units() {
+    let mut session = session();
+    assert_eq!(Ok("1 km".to_string()), eval(&mut session, "radius"));
+    assert_eq!(Ok("km".to_string()), eval(&mut session, "units"));
+    let quarter = quarter();
+    assert_eq!(
+        Ok(format!("{quarter} km")),
+        eval(&mut session, "dist 0 0 0 90")
+    );
+
+    assert_eq!(Ok("2 km".to_string()), eval(&mut session, "radius 2"));
+    assert_eq!(Ok("m".to_string()), eval(&mut session, "units m"));
+    assert_eq!(Ok("m".to_string()), eval(&mut session, "units"));
+    let meters = DistanceUnit::Meters.convert_kilometers(2.0 * quarter);
+    assert_eq!(
+        Ok(format!("{meters} m")),
+        eval(&mut session, "dist 0 0 0 90")
+    );
+
+    // An invalid value leaves the setting as it was.
+    assert!(eval(&mut session, "radius -2").is_err());
+    assert_eq!(Ok("2 km".to_string()), eval(&mut session, "radius"));
+    let mean = RadiusModel::Mean.kilometers();
+    assert_eq!(Ok(format!("{mean} km")), eval(&mut session, "radius mean"));
+}
+
+#[test]
+fn test_eval_json() {
+    let mut session = session();
+    assert_eq!(
+        Ok("0 km".to_string()),
+        eval(&mut session, r#"{"x0": 10, "y0": 20, "x1": 10, "y1": 20}"#)
+    );
+    let pair = r#"{"x0": 0, "y0": 0, "x1": 90, "y1": 0}"#;
+    let quarter = quarter();
+    assert_eq!(
+        Ok(format!("2 pairs, average {} km", quarter / 2.0)),
+        eval(
+            &mut session,
+            &format!(r#"[{pair}, {{"x0": 5, "y0": 5, "x1": 5, "y1": 5}}]"#)
+        )
+    );
+    // A pairs file is on its own sphere, and checked against its average.
+    assert_eq!(
+        Ok(format!(
+            "1 pair, average {} (stored 3, difference {})",
+            2.0 * quarter,
+            2.0 * quarter - 3.0
+        )),
+        eval(
+            &mut session,
+            &format!(r#"{{"pairs": [{pair}], "radius": 2, "avg_dist": 3}}"#)
+        )
+    );
+    // Other values are written back.
+    assert_eq!(
+        Ok(r#"{"a":[1,2]}"#.to_string()),
+        eval(&mut session, r#"{"a": [1, 2]}"#)
+    );
+    assert_eq!(Ok(r#""text""#.to_string()), eval(&mut session, r#""text""#));
+
+    assert_eq!(
+        Err("expected at least one pair".to_string()),
+        eval(&mut session, "[]")
+    );
+    assert!(eval(&mut session, "[1, 2]").is_err());
+    assert!(eval(&mut session, r#"{"x0": 1}"#).is_err());
+    assert!(
+        eval(&mut session, r#"{"x0": 1,"#)
+            .unwrap_err()
+            .starts_with("invalid JSON: ")
+    );
+}
+
+#[test]
+fn test_serve() {
+    let input = "radius 2\n\n  dist 0 0 0 0\nbogus\ndist 1\nquit\ndist 0 0 0 0\n";
+    let mut sink = BufferSink::new();
+    session().serve(input.as_bytes(), &mut sink, false).unwrap();
+    assert_eq!(
+        "2 km\n0 km\nerror: unknown command \"bogus\", see help\nerror: expected 4 numbers\n",
+        sink.as_str().unwrap()
+    );
+
+    // Prompted, up to the end of the input.
+    let mut sink = BufferSink::new();
+    session()
+        .serve("units\n".as_bytes(), &mut sink, true)
+        .unwrap();
+    assert_eq!("> km\n> ", sink.as_str().unwrap());
+}
@@ -0,0 +1,87 @@
+use super::RepetitionTester;
+use haversine::sink::OutputSink;
+use std::hint::black_box;
+use std::io;
+
+/// Size of the word moved by each read or write.
+const WORD: usize = std::mem::size_of::<u64>();
+
+/// Memory access pattern of a bandwidth test.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Access {
+    Read,
+    Write,
+}
+
+/// Reads every `stride`-th word of the working set until `total` words
+/// have been read.
+fn read(working_set: &[u64], stride: usize, total: usize) -> u64 {
+    let mut sum = 0u64;
+    let mut done = 0;
+    while done < total {
+        for offset in 0..stride {
+            for &word in working_set.iter().skip(offset).step_by(stride) {
+                sum = sum.wrapping_add(word);
+            }
+        }
+        done += working_set.len();
+    }
+    sum
+}
+
+/// Writes every `stride`-th word of the working set until `total` words
+/// have been written.
+fn write(working_set: &mut [u64], stride: usize, total: usize) {
+    let mut done = 0;
+    while done < total {
+        for offset in 0..stride {
+            for word in working_set.iter_mut().skip(offset).step_by(stride) {
+                *word = done as u64;
+            }
+        }
+        done += working_set.len();
+    }
+}
+
+/// Measures read or write bandwidth over each working-set size, moving
+/// `total_bytes` per repetition. A `stride` of 1 is a sequential scan;
+/// larger strides touch the words of the working set out of order.
+pub(crate) fn run(
+    output: &mut dyn OutputSink,
+    access: Access,
+    sizes: &[usize],
+    stride: usize,
+    total_bytes: usize,
+    seconds: f64,
+) -> io::Result<()> {
+    let stride = stride.max(1);
+    for &size in sizes {
+        let words = (size / WORD).max(1);
+        let total = total_bytes.max(size) / WORD;
+        let mut working_set = vec![1u64; words];
+
+        let label = format!("{access:?} {} (stride {stride})", format_size(size));
+        let bytes = total.div_ceil(words) * words * WORD;
+        let tester = RepetitionTester::new(label, bytes as u64, seconds);
+        let results = match access {
+            Access::Read => tester.run(|| {
+                black_box(read(black_box(&working_set), stride, total));
+            }),
+            Access::Write => tester.run(|| {
+                write(black_box(&mut working_set), stride, total);
+            }),
+        };
+        tester.write(output, &results)?;
+    }
+    Ok(())
+}
+
+/// Formats a byte count with a binary unit suffix.
+pub(crate) fn format_size(size: usize) -> String {
+    match size {
+        s if s >= 1 << 30 && s % (1 << 30) == 0 => format!("{}G", s >> 30),
+        s if s >= 1 << 20 && s % (1 << 20) == 0 => format!("{}M", s >> 20),
+        s if s >= 1 << 10 && s % (1 << 10) == 0 => format!("{}K", s >> 10),
+        s => format!("{s}B"),
+    }
+}
@@ -0,0 +1,102 @@
+use haversine::memory;
+use haversine::pipeline::Pipeline;
+use haversine::sink::OutputSink;
+use std::fs::File;
+use std::io;
+use std::time::Duration;
+
+/// Times of each stage of one run.
+#[derive(Debug, Clone, Copy, Default)]
+struct Stages {
+    read: Duration,
+    parse: Duration,
+    compute: Duration,
+}
+
+impl Stages {
+    fn total(&self) -> Duration {
+        self.read + self.parse + self.compute
+    }
+
+    fn write(&self, output: &mut dyn OutputSink, label: &str) -> io::Result<()> {
+        writeln!(
+            output,
+            "{label:<10}  read {:>10.3}ms  parse {:>10.3}ms  compute {:>10.3}ms  total {:>10.3}ms",
+            milliseconds(self.read),
+            milliseconds(self.parse),
+            milliseconds(self.compute),
+            milliseconds(self.total())
+        )
+    }
+}
+
+/// Times of the runs of a file: the first (cold) apart from the others
+/// (warm), which find the file in the page cache.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Runs {
+    cold: Stages,
+    warm: Vec<Stages>,
+    /// Whether the system could not be advised to drop the file from the
+    /// page cache before the first run.
+    hint_unavailable: bool,
+}
+
+/// Reads, parses and computes the file at `path` `runs` times.
+///
+/// With `drop_cache`, the system is advised to drop the file from the page
+/// cache before the first run only, so it reads from the disk while the
+/// others still show the cache. Without it the first run may be warm too,
+/// if the file was read recently.
+pub(crate) fn run(path: &str, runs: usize, drop_cache: bool) -> haversine::Result<Runs> {
+    let mut times = Runs {
+        hint_unavailable: drop_cache && !memory::advise_drop_from_page_cache(&File::open(path)?),
+        ..Runs::default()
+    };
+    for run in 0..runs {
+        let result = Pipeline::new().input(path).run()?;
+        let stages = Stages {
+            read: result.read_time,
+            parse: result.parse_time,
+            compute: result.compute_time,
+        };
+        match run {
+            0 => times.cold = stages,
+            _ => times.warm.push(stages),
+        }
+    }
+    Ok(times)
+}
+
+impl Runs {
+    /// Writes the cold run apart from the best and mean of the warm ones.
+    pub(crate) fn write(&self, output: &mut dyn OutputSink) -> io::Result<()> {
+        if self.hint_unavailable {
+            writeln!(
+                output,
+                "The page cache hint is unavailable; the first run may be warm"
+            )?;
+        }
+        self.cold.write(output, "cold")?;
+        let warm = &self.warm;
+        let Some(best) = warm.iter().min_by_key(|stages| stages.total()).copied() else {
+            return Ok(());
+        };
+        let count = warm.len() as u32;
+        let mean = Stages {
+            read: warm.iter().map(|stages| stages.read).sum::<Duration>() / count,
+            parse: warm.iter().map(|stages| stages.parse).sum::<Duration>() / count,
+            compute: warm.iter().map(|stages| stages.compute).sum::<Duration>() / count,
+        };
+        best.write(output, "warm best")?;
+        mean.write(output, "warm mean")?;
+        writeln!(
+            output,
+            "The cold run took {:.2}x the best warm one",
+            self.cold.total().as_secs_f64() / best.total().as_secs_f64()
+        )
+    }
+}
+
+fn milliseconds(time: Duration) -> f64 {
+    time.as_secs_f64() * 1e3
+}
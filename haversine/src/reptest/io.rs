@@ -0,0 +1,87 @@
+use super::RepetitionTester;
+use haversine::reader::{AsyncFileReader, DEFAULT_DEPTH, DoubleBufferedReader, Mmap};
+use haversine::sink::OutputSink;
+use std::fs::File;
+use std::hint::black_box;
+use std::io::{self, Read};
+
+/// Reads a whole file with a buffer of the given size, returning the
+/// bytes read.
+type ReadFile = fn(&str, usize) -> io::Result<usize>;
+
+/// Compares reading the whole file at `path`, of `bytes` bytes, with each
+/// strategy of `--io`, each repetition from the start, then writes their
+/// best throughput side by side, relative to the synchronous reads. A
+/// strategy this system does not offer is left out.
+pub(crate) fn run(
+    output: &mut dyn OutputSink,
+    path: &str,
+    bytes: u64,
+    buffer_size: usize,
+    seconds: f64,
+) -> io::Result<()> {
+    let strategies: [(&str, ReadFile); 3] = [
+        ("sync", read_sync),
+        ("async", read_async),
+        ("mmap", read_mapped),
+    ];
+
+    let mut summary = Vec::with_capacity(strategies.len());
+    for (label, read) in strategies {
+        if let Err(error) = read(path, buffer_size) {
+            writeln!(output, "--- {label} --- unavailable: {error}")?;
+            continue;
+        }
+        let tester = RepetitionTester::new(label, bytes, seconds);
+        let results = tester.run(|| {
+            black_box(read(black_box(path), buffer_size).expect("Failed to read file"));
+        });
+        tester.write(output, &results)?;
+        summary.push((label, tester.gigabytes_per_second(results.min_time)));
+    }
+
+    writeln!(output, "--- Summary (best of each) ---")?;
+    let reference = summary.first().map_or(1.0, |(_, speed)| *speed);
+    for (label, speed) in summary {
+        writeln!(
+            output,
+            "{label:<5}  {speed:>7.3}gb/s  {:>5.2}x",
+            speed / reference
+        )?;
+    }
+    Ok(())
+}
+
+/// Reads to the end into a reused buffer, returning the bytes read.
+fn drain(mut reader: impl Read, buffer_size: usize) -> io::Result<usize> {
+    let mut buffer = vec![0; buffer_size];
+    let mut total = 0;
+    loop {
+        match reader.read(&mut buffer)? {
+            0 => return Ok(total),
+            n => total += n,
+        }
+    }
+}
+
+fn read_sync(path: &str, buffer_size: usize) -> io::Result<usize> {
+    let reader = DoubleBufferedReader::with_buffer_size(File::open(path)?, buffer_size);
+    drain(reader, buffer_size)
+}
+
+fn read_async(path: &str, buffer_size: usize) -> io::Result<usize> {
+    let reader = AsyncFileReader::with_buffers(path, buffer_size, DEFAULT_DEPTH)?;
+    drain(reader, buffer_size)
+}
+
+/// Copies the map out as the readers copy their buffers, so each strategy
+/// does the same work on the bytes.
+fn read_mapped(path: &str, buffer_size: usize) -> io::Result<usize> {
+    let map = Mmap::map(&File::open(path)?)?;
+    let mut buffer = vec![0; buffer_size];
+    for chunk in map.chunks(buffer_size) {
+        buffer[..chunk.len()].copy_from_slice(chunk);
+        black_box(&buffer);
+    }
+    Ok(map.len())
+}
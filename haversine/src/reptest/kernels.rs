@@ -0,0 +1,63 @@
+use super::RepetitionTester;
+use haversine::compute::ComputeDriver;
+use haversine::geo::Kernel;
+use haversine::pairs::Pairs;
+use haversine::sink::OutputSink;
+use std::hint::black_box;
+use std::io;
+
+/// Bytes of coordinates read per pair.
+const PAIR_BYTES: u64 = 4 * std::mem::size_of::<f64>() as u64;
+
+const KERNELS: [(&str, Kernel); 7] = [
+    ("haversine", Kernel::Haversine),
+    ("cached-trig", Kernel::CachedTrig),
+    ("dot", Kernel::Dot),
+    ("fma", Kernel::Fma),
+    ("fast-math", Kernel::FastMath),
+    ("vincenty", Kernel::Vincenty),
+    ("cosine", Kernel::Cosine),
+];
+
+/// Compares computing the distances of the pairs with each kernel on one
+/// thread, then writes their best time relative to the haversine kernel
+/// with the largest difference of a distance from that kernel's.
+pub(crate) fn run(
+    output: &mut dyn OutputSink,
+    pairs: &Pairs,
+    radius: f64,
+    seconds: f64,
+) -> io::Result<()> {
+    let n_pairs = pairs.len() as u64;
+    let driver = ComputeDriver::new(radius).threads(1);
+    let baseline = driver.clone().keep_distances(true).run(pairs).distances;
+
+    let mut summary = Vec::with_capacity(KERNELS.len());
+    for (label, kernel) in KERNELS {
+        let driver = driver.clone().kernel(kernel);
+        let tester = RepetitionTester::new(label, n_pairs * PAIR_BYTES, seconds);
+        let results = tester.run(|| {
+            black_box(driver.run(black_box(pairs)));
+        });
+        tester.write(output, &results)?;
+        let distances = driver.keep_distances(true).run(pairs).distances;
+        let difference = baseline
+            .iter()
+            .zip(&distances)
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0, f64::max);
+        summary.push((label, results.min_time, difference));
+    }
+
+    writeln!(output, "--- Summary (best of each) ---")?;
+    let reference = summary.first().map_or(1, |(_, time, _)| *time) as f64;
+    for (label, time, difference) in summary {
+        writeln!(
+            output,
+            "{label:<11}  {:>6.2} cycles/pair  {:>5.2}x  largest difference {difference:e}",
+            time as f64 / n_pairs.max(1) as f64,
+            reference / time as f64
+        )?;
+    }
+    Ok(())
+}
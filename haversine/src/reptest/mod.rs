@@ -0,0 +1,152 @@
+use crate::progress;
+use haversine::memory::{self, AllocationStats, MemoryStats};
+use haversine::sink::OutputSink;
+use haversine::timer::{estimate_cpu_timer_freq, read_cpu_timer};
+use std::time::Duration;
+
+pub(crate) mod bandwidth;
+pub(crate) mod cache;
+pub(crate) mod io;
+pub(crate) mod kernels;
+pub(crate) mod parse;
+pub(crate) mod structural;
+pub(crate) mod traversal;
+
+/// Results of a repetition test, in CPU timer ticks, with the memory
+/// counters summed over the repetitions, but for the peak live bytes which
+/// are the largest of any repetition.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct TestResults {
+    pub(crate) count: u64,
+    pub(crate) total_time: u64,
+    pub(crate) min_time: u64,
+    pub(crate) max_time: u64,
+    pub(crate) page_faults: Option<u64>,
+    pub(crate) allocations: Option<AllocationStats>,
+}
+
+/// Runs a test repeatedly until no new minimum time has been seen for a
+/// while, so the minimum approaches the best the machine can do.
+pub(crate) struct RepetitionTester {
+    label: String,
+    bytes: u64,
+    timer_freq: u64,
+    try_for: u64,
+}
+
+impl RepetitionTester {
+    /// Creates a tester for a test that processes `bytes` bytes per
+    /// repetition, stopping after `seconds` without a new minimum.
+    pub(crate) fn new(label: impl Into<String>, bytes: u64, seconds: f64) -> Self {
+        let timer_freq = estimate_cpu_timer_freq(Duration::from_millis(100));
+        Self {
+            label: label.into(),
+            bytes,
+            timer_freq,
+            try_for: (seconds * timer_freq as f64) as u64,
+        }
+    }
+
+    pub(crate) fn run(&self, mut test: impl FnMut()) -> TestResults {
+        let mut results = TestResults {
+            count: 0,
+            total_time: 0,
+            min_time: u64::MAX,
+            max_time: 0,
+            page_faults: Some(0),
+            allocations: Some(AllocationStats::default()),
+        };
+
+        let mut last_min_found = read_cpu_timer();
+        loop {
+            // The counters are read outside the timed region, as reading
+            // the OS ones is a system call.
+            memory::reset_peak_live();
+            let memory_start = MemoryStats::now();
+            let start = read_cpu_timer();
+            test();
+            let end = read_cpu_timer();
+            let elapsed = end - start;
+            let memory = MemoryStats::now().since(&memory_start);
+
+            results.count += 1;
+            results.page_faults = results
+                .page_faults
+                .zip(memory.page_faults)
+                .map(|(a, b)| a + b);
+            results.allocations =
+                results
+                    .allocations
+                    .zip(memory.allocations)
+                    .map(|(total, run)| AllocationStats {
+                        count: total.count + run.count,
+                        bytes: total.bytes + run.bytes,
+                        peak_live: total.peak_live.max(run.peak_live),
+                    });
+            results.total_time += elapsed;
+            results.max_time = results.max_time.max(elapsed);
+            if elapsed < results.min_time {
+                results.min_time = elapsed;
+                last_min_found = end;
+            }
+
+            if end - last_min_found > self.try_for {
+                return results;
+            }
+        }
+    }
+
+    /// Writes the min, max and average time of `results` with the bandwidth
+    /// each of them corresponds to.
+    pub(crate) fn write(
+        &self,
+        output: &mut dyn OutputSink,
+        results: &TestResults,
+    ) -> std::io::Result<()> {
+        writeln!(output, "--- {} ---", self.label)?;
+        let avg_time = results.total_time / results.count.max(1);
+        self.write_time(output, "Min", results.min_time)?;
+        self.write_time(output, "Max", results.max_time)?;
+        self.write_time(output, "Avg", avg_time)?;
+        let runs = results.count.max(1);
+        if let Some(allocations) = results.allocations {
+            writeln!(
+                output,
+                "Allocations: {:.1} of {} per run, peak live {}",
+                allocations.count as f64 / runs as f64,
+                progress::bytes((allocations.bytes / runs) as f64),
+                progress::bytes(allocations.peak_live as f64)
+            )?;
+        }
+        if let Some(faults) = results.page_faults {
+            let per_run = faults as f64 / runs as f64;
+            let mut line = format!("Page faults: {per_run:.1} per run");
+            if faults > 0 {
+                line += &format!(" ({:.1} KiB/fault)", self.bytes as f64 / per_run / 1024.0);
+            }
+            writeln!(output, "{line}")?;
+        }
+        Ok(())
+    }
+
+    /// Bandwidth of a repetition that took `time` ticks.
+    pub(crate) fn gigabytes_per_second(&self, time: u64) -> f64 {
+        let seconds = time as f64 / self.timer_freq as f64;
+        self.bytes as f64 / (1024.0 * 1024.0 * 1024.0) / seconds
+    }
+
+    fn write_time(
+        &self,
+        output: &mut dyn OutputSink,
+        label: &str,
+        time: u64,
+    ) -> std::io::Result<()> {
+        let seconds = time as f64 / self.timer_freq as f64;
+        writeln!(
+            output,
+            "{label}: {time} ({:.6}ms) {:.3}gb/s",
+            seconds * 1000.0,
+            self.gigabytes_per_second(time)
+        )
+    }
+}
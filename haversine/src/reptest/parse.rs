@@ -0,0 +1,103 @@
+use super::RepetitionTester;
+use haversine::parse::{FeedParser, Parser, Simd};
+use haversine::sink::OutputSink;
+use std::hint::black_box;
+use std::io;
+
+/// Size of the chunks the push parser is fed.
+const FEED_CHUNK: usize = 64 * 1024;
+
+/// A labelled parse of the input.
+type Test<'a> = (&'a str, Box<dyn Fn() + 'a>);
+
+/// Compares the parsers of this crate over `input`, and those of other
+/// crates built in as features, then writes their best throughput side by
+/// side, relative to building the `Value` DOM.
+pub(crate) fn run(output: &mut dyn OutputSink, input: &str, seconds: f64) -> io::Result<()> {
+    let bytes = input.len() as u64;
+    let simd = Simd::detect();
+    let simd_label = format!("Tape ({})", simd.name());
+    let mut tests: Vec<Test> = vec![
+        (
+            "Value",
+            Box::new(|| {
+                black_box(Parser::new(black_box(input)).parse().expect("Invalid JSON"));
+            }),
+        ),
+        (
+            "Tape (tokenizer)",
+            Box::new(|| {
+                let tape = Parser::new(black_box(input)).parse_tape();
+                black_box(tape.expect("Invalid JSON"));
+            }),
+        ),
+        (
+            &simd_label,
+            Box::new(move || {
+                let parser = Parser::new(black_box(input)).structural_scan(simd);
+                black_box(parser.parse_tape().expect("Invalid JSON"));
+            }),
+        ),
+        (
+            "Events (feed)",
+            Box::new(|| {
+                let mut parser = FeedParser::new();
+                for chunk in black_box(input).as_bytes().chunks(FEED_CHUNK) {
+                    black_box(parser.feed(chunk).expect("Invalid JSON"));
+                }
+                black_box(parser.finish().expect("Invalid JSON"));
+            }),
+        ),
+    ];
+    tests.extend(baselines(input));
+
+    let mut summary = Vec::with_capacity(tests.len());
+    for (label, test) in &tests {
+        let tester = RepetitionTester::new(*label, bytes, seconds);
+        let results = tester.run(test);
+        tester.write(output, &results)?;
+        summary.push((*label, tester.gigabytes_per_second(results.min_time)));
+    }
+
+    writeln!(output, "--- Summary (best of each) ---")?;
+    let width = summary
+        .iter()
+        .map(|(label, _)| label.len())
+        .max()
+        .unwrap_or(0);
+    let reference = summary[0].1;
+    for (label, speed) in summary {
+        writeln!(
+            output,
+            "{label:<width$}  {speed:>7.3}gb/s  {:>5.2}x",
+            speed / reference
+        )?;
+    }
+    Ok(())
+}
+
+/// Parses of other crates, as the reference points of ours.
+#[cfg(feature = "bench-serde-json")]
+fn baselines(input: &str) -> Vec<Test<'_>> {
+    vec![
+        (
+            "serde_json::Value",
+            Box::new(|| {
+                let value = serde_json::from_str::<serde_json::Value>(black_box(input));
+                black_box(value.expect("Invalid JSON"));
+            }),
+        ),
+        (
+            "serde_json (ignored)",
+            Box::new(|| {
+                let value = serde_json::from_str::<serde::de::IgnoredAny>(black_box(input));
+                black_box(value.expect("Invalid JSON"));
+            }),
+        ),
+    ]
+}
+
+#[cfg(not(feature = "bench-serde-json"))]
+fn baselines(_input: &str) -> Vec<Test<'_>> {
+    Vec::new()
+}
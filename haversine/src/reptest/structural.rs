@@ -0,0 +1,39 @@
+use super::RepetitionTester;
+use haversine::parse::{Parser, Simd, structural_indices};
+use haversine::sink::OutputSink;
+use std::hint::black_box;
+use std::io;
+
+/// Compares finding the token starts of `input` with each instruction set
+/// this machine has, then building its tape with the tokenizer alone and
+/// with the fastest first pass.
+pub(crate) fn run(output: &mut dyn OutputSink, input: &str, seconds: f64) -> io::Result<()> {
+    let bytes = input.len() as u64;
+    for simd in Simd::available() {
+        let tester =
+            RepetitionTester::new(format!("Structural scan ({})", simd.name()), bytes, seconds);
+        let results = tester.run(|| {
+            black_box(structural_indices(black_box(input), simd));
+        });
+        tester.write(output, &results)?;
+    }
+
+    let simd = Simd::detect();
+    let tests: [(String, Option<Simd>); 2] = [
+        ("Tape (tokenizer)".into(), None),
+        (format!("Tape ({})", simd.name()), Some(simd)),
+    ];
+    for (label, simd) in tests {
+        let tester = RepetitionTester::new(label, bytes, seconds);
+        let results = tester.run(|| {
+            let parser = Parser::new(black_box(input));
+            let parser = match simd {
+                Some(simd) => parser.structural_scan(simd),
+                None => parser,
+            };
+            black_box(parser.parse_tape().expect("Invalid JSON"));
+        });
+        tester.write(output, &results)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use super::RepetitionTester;
+use haversine::geo::haversine_distance;
+use haversine::pairs::Pairs;
+use haversine::parse::Value;
+use haversine::sink::OutputSink;
+use std::hint::black_box;
+use std::io;
+
+/// Bytes of coordinates read per pair.
+const PAIR_BYTES: u64 = 4 * std::mem::size_of::<f64>() as u64;
+
+/// Reads the coordinates of a pair object in the DOM.
+fn dom_pair(pair: &Value) -> (f64, f64, f64, f64) {
+    let Value::Object(object) = pair else {
+        panic!("Invalid pair");
+    };
+    let coordinate = |key| {
+        object
+            .get(key)
+            .expect("Expected to exist")
+            .try_into()
+            .expect("Is number")
+    };
+    (
+        coordinate("x0"),
+        coordinate("y0"),
+        coordinate("x1"),
+        coordinate("y1"),
+    )
+}
+
+fn dom_sum(values: &[Value]) -> f64 {
+    values
+        .iter()
+        .map(dom_pair)
+        .map(|(x0, y0, x1, y1)| x0 + y0 + x1 + y1)
+        .sum()
+}
+
+fn soa_sum(pairs: &Pairs) -> f64 {
+    (0..pairs.len())
+        .map(|i| pairs.x0[i] + pairs.y0[i] + pairs.x1[i] + pairs.y1[i])
+        .sum()
+}
+
+fn dom_haversine(values: &[Value], radius: f64) -> f64 {
+    values
+        .iter()
+        .map(dom_pair)
+        .map(|(x0, y0, x1, y1)| haversine_distance(radius, x0, y0, x1, y1))
+        .sum()
+}
+
+fn soa_haversine(pairs: &Pairs, radius: f64) -> f64 {
+    (0..pairs.len())
+        .map(|i| haversine_distance(radius, pairs.x0[i], pairs.y0[i], pairs.x1[i], pairs.y1[i]))
+        .sum()
+}
+
+/// Compares walking the `Value` DOM for the pairs against scanning the
+/// SoA arrays, both for the bare traversal and with the distance computed.
+pub(crate) fn run(
+    output: &mut dyn OutputSink,
+    values: &[Value],
+    radius: f64,
+    seconds: f64,
+) -> io::Result<()> {
+    let pairs = Pairs::from_values(values).expect("Invalid pairs file");
+    let n_pairs = pairs.len() as u64;
+    let bytes = n_pairs * PAIR_BYTES;
+
+    let tests: [(&str, &dyn Fn() -> f64); 4] = [
+        ("DOM walk", &|| dom_sum(black_box(values))),
+        ("SoA scan", &|| soa_sum(black_box(&pairs))),
+        ("DOM haversine", &|| {
+            dom_haversine(black_box(values), radius)
+        }),
+        ("SoA haversine", &|| {
+            soa_haversine(black_box(&pairs), radius)
+        }),
+    ];
+    for (label, test) in tests {
+        let tester = RepetitionTester::new(label, bytes, seconds);
+        let results = tester.run(|| {
+            black_box(test());
+        });
+        tester.write(output, &results)?;
+        writeln!(
+            output,
+            "Cycles/pair: {:.2}",
+            results.min_time as f64 / n_pairs.max(1) as f64
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,144 @@
+//! Seedable pseudo-random number generators. They use integer arithmetic
+//! only, so a seed gives the same numbers on every platform.
+
+/// Source of random numbers.
+pub trait Rng {
+    fn next_u64(&mut self) -> u64;
+
+    /// Returns a number in `0..n`.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// Returns a number in `[low, high)`, from the top 53 bits of the next
+    /// `u64`.
+    fn uniform(&mut self, low: f64, high: f64) -> f64 {
+        let unit = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        low + unit * (high - low)
+    }
+}
+
+/// Splitmix64, fast but of low statistical quality. It expands the seeds of
+/// the other generators.
+#[derive(Debug, Clone)]
+pub struct SplitMix64(u64);
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+}
+
+impl Rng for SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+/// Xoshiro256++ by Blackman and Vigna.
+#[derive(Debug, Clone)]
+pub struct Xoshiro256PlusPlus([u64; 4]);
+
+impl Xoshiro256PlusPlus {
+    /// Seeds the state with the first outputs of splitmix64, as its authors
+    /// recommend.
+    pub fn new(seed: u64) -> Self {
+        let mut seeder = SplitMix64::new(seed);
+        Self([(); 4].map(|_| seeder.next_u64()))
+    }
+}
+
+impl Rng for Xoshiro256PlusPlus {
+    fn next_u64(&mut self) -> u64 {
+        let s = &mut self.0;
+        let result = s[0].wrapping_add(s[3]).rotate_left(23).wrapping_add(s[0]);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+}
+
+/// PCG64, the 128-bit state XSL RR variant by O'Neill.
+#[derive(Debug, Clone)]
+pub struct Pcg64 {
+    state: u128,
+    increment: u128,
+}
+
+const PCG_MULTIPLIER: u128 = 0x2360_ed05_1fc6_5da4_4385_df64_9fcc_f645;
+
+impl Pcg64 {
+    /// Takes the initial state and stream from the first outputs of
+    /// splitmix64.
+    pub fn new(seed: u64) -> Self {
+        let mut seeder = SplitMix64::new(seed);
+        let mut wide = || (seeder.next_u64() as u128) << 64 | seeder.next_u64() as u128;
+        let (state, stream) = (wide(), wide());
+        Self::with_stream(state, stream)
+    }
+
+    /// Seeds the generator as the reference `pcg64_srandom_r` does.
+    pub fn with_stream(state: u128, stream: u128) -> Self {
+        let mut rng = Self {
+            state: 0,
+            increment: stream << 1 | 1,
+        };
+        rng.step();
+        rng.state = rng.state.wrapping_add(state);
+        rng.step();
+        rng
+    }
+
+    fn step(&mut self) {
+        self.state = self
+            .state
+            .wrapping_mul(PCG_MULTIPLIER)
+            .wrapping_add(self.increment);
+    }
+}
+
+impl Rng for Pcg64 {
+    fn next_u64(&mut self) -> u64 {
+        self.step();
+        let folded = (self.state >> 64) as u64 ^ self.state as u64;
+        folded.rotate_right((self.state >> 122) as u32)
+    }
+}
+
+/// Generator selected by name, such as on the command line.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum Algorithm {
+    #[default]
+    Xoshiro256PlusPlus,
+    Pcg64,
+}
+
+impl Algorithm {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "xoshiro256++" | "xoshiro" => Some(Algorithm::Xoshiro256PlusPlus),
+            "pcg64" | "pcg" => Some(Algorithm::Pcg64),
+            _ => None,
+        }
+    }
+
+    pub fn seeded(self, seed: u64) -> Box<dyn Rng> {
+        match self {
+            Algorithm::Xoshiro256PlusPlus => Box::new(Xoshiro256PlusPlus::new(seed)),
+            Algorithm::Pcg64 => Box::new(Pcg64::new(seed)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
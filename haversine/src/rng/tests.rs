@@ -0,0 +1,64 @@
+use super::{Algorithm, Pcg64, Rng, SplitMix64, Xoshiro256PlusPlus};
+
+fn first<const N: usize>(rng: &mut dyn Rng) -> [u64; N] {
+    [(); N].map(|_| rng.next_u64())
+}
+
+#[test]
+fn test_xoshiro_known_outputs() {
+    assert_eq!(
+        [
+            0x5317_5d61_490b_23df,
+            0x61da_6f3d_c380_d507,
+            0x5c0f_df91_ec9a_7bfc
+        ],
+        first(&mut Xoshiro256PlusPlus::new(0))
+    );
+    assert_eq!(
+        [
+            0xcfc5_d07f_6f03_c29b,
+            0xbf42_4132_963f_e08d,
+            0x19a3_7d57_57aa_f520
+        ],
+        first(&mut Xoshiro256PlusPlus::new(1))
+    );
+}
+
+#[test]
+fn test_pcg_known_outputs() {
+    // The first outputs of the reference implementation's demo.
+    assert_eq!(
+        [0x86b1_da1d_7206_2b68, 0x1304_aa46_c985_3d39],
+        first(&mut Pcg64::with_stream(42, 54))
+    );
+    assert_eq!(
+        [
+            0xcb40_115c_bf8d_9cb4,
+            0x0c1c_3da5_7af3_c3e9,
+            0xddab_dc20_25f5_a5d4
+        ],
+        first(&mut Pcg64::new(0))
+    );
+}
+
+#[test]
+fn test_uniform_within_range() {
+    let mut rng = SplitMix64::new(3);
+    for _ in 0..1000 {
+        let n = rng.uniform(-90.0, 90.0);
+        assert!((-90.0..90.0).contains(&n), "{n}");
+    }
+}
+
+#[test]
+fn test_algorithm_from_name() {
+    assert_eq!(
+        Some(Algorithm::Xoshiro256PlusPlus),
+        Algorithm::from_name("xoshiro256++")
+    );
+    assert_eq!(Some(Algorithm::Pcg64), Algorithm::from_name("pcg64"));
+    assert_eq!(None, Algorithm::from_name("mt19937"));
+    let mut a = Algorithm::Pcg64.seeded(7);
+    let mut b = Algorithm::Pcg64.seeded(7);
+    assert_eq!(first::<4>(a.as_mut()), first::<4>(b.as_mut()));
+}
@@ -0,0 +1,89 @@
+//! The length of the path through the points of a file, measured as they
+//! are read, for `compute --mode route`.
+
+use std::io;
+
+use haversine::geo::{AngleUnit, Point, haversine_distance_radians_with};
+use haversine::math::StdMath;
+use haversine::sink::OutputSink;
+
+/// A path measured on the unit sphere, as the radius of a file may come
+/// after its points, and scaled once it is known.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Route {
+    unit: AngleUnit,
+    last: Option<Point>,
+    points: usize,
+    length: f64,
+    /// The length of each segment, if kept.
+    segments: Option<Vec<f64>>,
+}
+
+impl Route {
+    /// A path through points in `unit`, without points yet, keeping the
+    /// length of each segment if `per_segment`.
+    pub(crate) fn new(per_segment: bool, unit: AngleUnit) -> Self {
+        Self {
+            unit,
+            segments: per_segment.then(Vec::new),
+            ..Self::default()
+        }
+    }
+
+    /// Extends the path to `point`.
+    pub(crate) fn push(&mut self, point: Point) {
+        if let Some(from) = self.last.replace(point) {
+            let [phi_0, theta_0, phi_1, theta_1] =
+                [from.lon, from.lat, point.lon, point.lat].map(|angle| self.unit.to_radians(angle));
+            let segment =
+                haversine_distance_radians_with(&StdMath, 1.0, phi_0, theta_0, phi_1, theta_1);
+            self.length += segment;
+            if let Some(segments) = &mut self.segments {
+                segments.push(segment);
+            }
+        }
+        self.points += 1;
+    }
+
+    /// The length of the path on a sphere of `radius`.
+    pub(crate) fn length(&self, radius: f64) -> f64 {
+        radius * self.length
+    }
+
+    /// The length of each segment on a sphere of `radius` with that of the
+    /// path up to its end, if they are kept.
+    pub(crate) fn segments(&self, radius: f64) -> Option<Vec<(f64, f64)>> {
+        let mut cumulative = 0.0;
+        self.segments.as_ref().map(|segments| {
+            segments
+                .iter()
+                .map(|segment| {
+                    cumulative += radius * segment;
+                    (radius * segment, cumulative)
+                })
+                .collect()
+        })
+    }
+
+    /// Writes the segments as CSV if they are kept, then the number of
+    /// points, the radius and the length of the path in `unit`.
+    pub(crate) fn write(
+        &self,
+        output: &mut dyn OutputSink,
+        radius: f64,
+        unit: &str,
+    ) -> io::Result<()> {
+        if let Some(segments) = self.segments(radius) {
+            writeln!(output, "segment,distance,cumulative")?;
+            for (index, (distance, cumulative)) in segments.into_iter().enumerate() {
+                writeln!(output, "{index},{distance},{cumulative}")?;
+            }
+        }
+        writeln!(output, "Number of points: {}", self.points)?;
+        writeln!(output, "Radius: {radius}")?;
+        writeln!(output, "Route length: {} {unit}", self.length(radius))
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,121 @@
+use super::Route;
+use haversine::geo::{AngleUnit, Point, path_length};
+use haversine::sink::BufferSink;
+use std::f64::consts::PI;
+
+const EPSILON: f64 = 1e-9;
+
+fn assert_close(expected: f64, actual: f64) {
+    assert!(
+        (expected - actual).abs() < EPSILON,
+        "expected {expected}, got {actual}"
+    );
+}
+
+/// A quarter of the equator, then up to the pole.
+fn route(per_segment: bool) -> Route {
+    let mut route = Route::new(per_segment, AngleUnit::Degrees);
+    for point in [
+        Point::new(0.0, 0.0),
+        Point::new(90.0, 0.0),
+        Point::new(90.0, 90.0),
+    ] {
+        route.push(point);
+    }
+    route
+}
+
+#[test]
+fn test_route_length() {
+    let route = route(false);
+    assert_eq!(3, route.points);
+    assert_close(PI, route.length(1.0));
+    // Measured on the unit sphere, and scaled to the radius read later.
+    assert_close(2.0 * PI, route.length(2.0));
+    assert_eq!(None, route.segments(1.0));
+
+    let points = [
+        Point::new(-0.1276, 51.5072),
+        Point::new(2.3522, 48.8566),
+        Point::new(13.405, 52.52),
+        Point::new(-73.9857, 40.7484),
+    ];
+    let mut route = Route::new(false, AngleUnit::Degrees);
+    for point in points {
+        route.push(point);
+    }
+    assert_close(path_length(6371.0, &points), route.length(6371.0));
+}
+
+#[test]
+fn test_route_in_angle_unit() {
+    let degrees = route(true);
+    for unit in [AngleUnit::Radians, AngleUnit::Gradians] {
+        let mut route = Route::new(true, unit);
+        for point in [
+            Point::new(0.0, 0.0),
+            Point::new(90.0, 0.0),
+            Point::new(90.0, 90.0),
+        ] {
+            route.push(Point::new(
+                unit.from_degrees(point.lon),
+                unit.from_degrees(point.lat),
+            ));
+        }
+        assert_close(degrees.length(1.0), route.length(1.0));
+        let segments = route.segments(1.0).unwrap();
+        for (expected, actual) in degrees.segments(1.0).unwrap().iter().zip(&segments) {
+            assert_close(expected.0, actual.0);
+        }
+    }
+}
+
+#[test]
+fn test_route_without_segments() {
+    let empty = Route::new(true, AngleUnit::Degrees);
+    assert_eq!(0, empty.points);
+    assert_eq!(0.0, empty.length(1.0));
+    let mut single = Route::new(true, AngleUnit::Degrees);
+    single.push(Point::new(10.0, 20.0));
+    assert_eq!(1, single.points);
+    assert_eq!(0.0, single.length(1.0));
+    assert_eq!(Some(Vec::new()), single.segments(1.0));
+}
+
+#[test]
+fn test_route_segments() {
+    let segments = route(true).segments(2.0).unwrap();
+    assert_eq!(2, segments.len());
+    assert_close(PI, segments[0].0);
+    assert_close(PI, segments[0].1);
+    assert_close(PI, segments[1].0);
+    assert_close(2.0 * PI, segments[1].1);
+}
+
+#[test]
+fn test_route_write() {
+    let mut sink = BufferSink::new();
+    let mut single = Route::new(false, AngleUnit::Degrees);
+    single.push(Point::new(10.0, 20.0));
+    single.write(&mut sink, 6371.0, "km").unwrap();
+    assert_eq!(
+        "Number of points: 1\nRadius: 6371\nRoute length: 0 km\n",
+        sink.as_str().unwrap()
+    );
+
+    let mut sink = BufferSink::new();
+    route(true).write(&mut sink, 1.0, "km").unwrap();
+    let text = sink.as_str().unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!("segment,distance,cumulative", lines[0]);
+    for (index, cumulative) in [(0, PI / 2.0), (1, PI)] {
+        let fields: Vec<&str> = lines[index + 1].split(',').collect();
+        assert_eq!(index.to_string(), fields[0]);
+        assert_close(PI / 2.0, fields[1].parse().unwrap());
+        assert_close(cumulative, fields[2].parse().unwrap());
+    }
+    assert_eq!("Number of points: 3", lines[3]);
+    assert_eq!("Radius: 1", lines[4]);
+    assert!(lines[5].starts_with("Route length: 3.14159265358979"));
+    assert!(lines[5].ends_with(" km"));
+}
@@ -0,0 +1,312 @@
+//! Declarative checks of the shape of a JSON document: the kind of each
+//! value, the keys an object must have, the range of a number and the
+//! elements of an array.
+//!
+//! ```
+//! use haversine::parse::parse;
+//! use haversine::schema::Schema;
+//!
+//! let schema = Schema::object()
+//!     .required("name", Schema::string())
+//!     .required("scores", Schema::array(Schema::number().min(0.0)).min_items(1));
+//! let document = parse(r#"{"name": "a", "scores": [1, -2]}"#).unwrap().unwrap();
+//! let violations = schema.validate(&document);
+//! assert_eq!("\"scores\"[1]: -2 is below the minimum 0", violations[0].to_string());
+//! ```
+
+use crate::parse::{KeyPath, PathSegment, Value};
+use std::fmt;
+use std::ops::Bound;
+
+/// Kind of a JSON value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Null,
+    Bool,
+    Number,
+    String,
+    Array,
+    Object,
+}
+
+impl Kind {
+    pub fn of(value: &Value) -> Self {
+        match value {
+            Value::Null => Kind::Null,
+            Value::Bool(_) => Kind::Bool,
+            Value::Number(_) => Kind::Number,
+            Value::String(_) => Kind::String,
+            Value::Array(_) => Kind::Array,
+            Value::Object(_) => Kind::Object,
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Kind::Null => "null",
+            Kind::Bool => "a boolean",
+            Kind::Number => "a number",
+            Kind::String => "a string",
+            Kind::Array => "an array",
+            Kind::Object => "an object",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A member of an object schema.
+#[derive(Debug, Clone, PartialEq)]
+struct Field {
+    key: String,
+    schema: Schema,
+    required: bool,
+}
+
+/// What a value must be. The constructors choose its kind, or any kind for
+/// `any`, and the builder methods add constraints, each of which applies to
+/// values of one kind only.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schema {
+    kind: Option<Kind>,
+    min: Bound<f64>,
+    max: Option<f64>,
+    items: Option<Box<Schema>>,
+    min_items: usize,
+    fields: Vec<Field>,
+}
+
+impl Schema {
+    fn of_kind(kind: Option<Kind>) -> Self {
+        Self {
+            kind,
+            min: Bound::Unbounded,
+            max: None,
+            items: None,
+            min_items: 0,
+            fields: Vec::new(),
+        }
+    }
+
+    /// Accepts every value.
+    pub fn any() -> Self {
+        Self::of_kind(None)
+    }
+
+    pub fn null() -> Self {
+        Self::of_kind(Some(Kind::Null))
+    }
+
+    pub fn bool() -> Self {
+        Self::of_kind(Some(Kind::Bool))
+    }
+
+    pub fn number() -> Self {
+        Self::of_kind(Some(Kind::Number))
+    }
+
+    pub fn string() -> Self {
+        Self::of_kind(Some(Kind::String))
+    }
+
+    /// An array whose elements all match `items`.
+    pub fn array(items: Schema) -> Self {
+        Self {
+            items: Some(Box::new(items)),
+            ..Self::of_kind(Some(Kind::Array))
+        }
+    }
+
+    /// An object, with any members unless some are added by `required` or
+    /// `optional`. Members without a schema are not checked.
+    pub fn object() -> Self {
+        Self::of_kind(Some(Kind::Object))
+    }
+
+    /// Numbers must be at least `min`.
+    pub fn min(mut self, min: f64) -> Self {
+        self.min = Bound::Included(min);
+        self
+    }
+
+    /// Numbers must be greater than `min`.
+    pub fn exclusive_min(mut self, min: f64) -> Self {
+        self.min = Bound::Excluded(min);
+        self
+    }
+
+    /// Numbers must be at most `max`.
+    pub fn max(mut self, max: f64) -> Self {
+        self.max = Some(max);
+        self
+    }
+
+    /// Arrays must have at least `min_items` elements.
+    pub fn min_items(mut self, min_items: usize) -> Self {
+        self.min_items = min_items;
+        self
+    }
+
+    /// Objects must have a member `key` matching `schema`.
+    pub fn required(mut self, key: &str, schema: Schema) -> Self {
+        self.fields.push(Field {
+            key: key.into(),
+            schema,
+            required: true,
+        });
+        self
+    }
+
+    /// Objects may have a member `key`, which must then match `schema`.
+    pub fn optional(mut self, key: &str, schema: Schema) -> Self {
+        self.fields.push(Field {
+            key: key.into(),
+            schema,
+            required: false,
+        });
+        self
+    }
+
+    /// The ways `value` does not match the schema, in document order of the
+    /// schema's members and the array elements.
+    pub fn validate(&self, value: &Value) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        self.check(value, &mut Vec::new(), &mut violations);
+        violations
+    }
+
+    fn check(&self, value: &Value, path: &mut Vec<PathSegment>, violations: &mut Vec<Violation>) {
+        let mut violation = |problem| {
+            violations.push(Violation {
+                path: KeyPath(path.clone()),
+                problem,
+            })
+        };
+        let found = Kind::of(value);
+        match self.kind {
+            Some(expected) if expected != found => {
+                return violation(Problem::WrongKind { expected, found });
+            }
+            _ => {}
+        }
+        match value {
+            Value::Number(number) => {
+                let value = number.as_f64();
+                let below = match self.min {
+                    Bound::Included(min) => (value < min).then_some((min, false)),
+                    Bound::Excluded(min) => (value <= min).then_some((min, true)),
+                    Bound::Unbounded => None,
+                };
+                if let Some((min, exclusive)) = below {
+                    violation(Problem::BelowMinimum {
+                        value,
+                        min,
+                        exclusive,
+                    });
+                }
+                if let Some(max) = self.max
+                    && value > max
+                {
+                    violation(Problem::AboveMaximum { value, max });
+                }
+            }
+            Value::Array(values) => {
+                if values.len() < self.min_items {
+                    violation(Problem::TooFewItems {
+                        len: values.len(),
+                        min: self.min_items,
+                    });
+                }
+                if let Some(items) = &self.items {
+                    for (index, item) in values.iter().enumerate() {
+                        path.push(PathSegment::Index(index));
+                        items.check(item, path, violations);
+                        path.pop();
+                    }
+                }
+            }
+            Value::Object(map) => {
+                for field in &self.fields {
+                    path.push(PathSegment::Key(field.key.clone()));
+                    match map.get(&field.key) {
+                        Some(member) => field.schema.check(member, path, violations),
+                        None if field.required => violations.push(Violation {
+                            path: KeyPath(path.clone()),
+                            problem: Problem::Missing,
+                        }),
+                        None => {}
+                    }
+                    path.pop();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A value that does not match its schema.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    /// Where the value is, or would be if it is missing.
+    pub path: KeyPath,
+    pub problem: Problem,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Problem {
+    /// A required member is missing.
+    Missing,
+    WrongKind {
+        expected: Kind,
+        found: Kind,
+    },
+    /// A number is below `min`, or equal to it if it is `exclusive`.
+    BelowMinimum {
+        value: f64,
+        min: f64,
+        exclusive: bool,
+    },
+    AboveMaximum {
+        value: f64,
+        max: f64,
+    },
+    TooFewItems {
+        len: usize,
+        min: usize,
+    },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.0.is_empty() {
+            write!(f, "the document: ")?;
+        } else {
+            write!(f, "{}: ", self.path)?;
+        }
+        match &self.problem {
+            Problem::Missing => write!(f, "missing"),
+            Problem::WrongKind { expected, found } => {
+                write!(f, "expected {expected}, found {found}")
+            }
+            Problem::BelowMinimum {
+                value,
+                min,
+                exclusive: true,
+            } => write!(f, "{value} is not above {min}"),
+            Problem::BelowMinimum { value, min, .. } => {
+                write!(f, "{value} is below the minimum {min}")
+            }
+            Problem::AboveMaximum { value, max } => {
+                write!(f, "{value} is above the maximum {max}")
+            }
+            Problem::TooFewItems { len, min } => {
+                write!(f, "{len} elements, expected at least {min}")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,88 @@
+use super::*;
+use crate::parse::parse;
+
+fn document(input: &str) -> Value {
+    parse(input).unwrap().unwrap()
+}
+
+fn key(key: &str) -> PathSegment {
+    PathSegment::Key(key.into())
+}
+
+#[test]
+fn test_valid() {
+    let schema = Schema::object()
+        .required("name", Schema::string())
+        .optional("tags", Schema::array(Schema::string()))
+        .required("anything", Schema::any());
+    let value = document(r#"{"name": "a", "anything": [null], "extra": 1}"#);
+    assert_eq!(Vec::<Violation>::new(), schema.validate(&value));
+    let value = document(r#"{"name": "a", "anything": 2, "tags": ["b", "c"]}"#);
+    assert!(schema.validate(&value).is_empty());
+}
+
+#[test]
+fn test_violations() {
+    let schema = Schema::object()
+        .required("name", Schema::string())
+        .required("ok", Schema::bool())
+        .required(
+            "points",
+            Schema::array(Schema::number().exclusive_min(0.0).max(10.0)).min_items(3),
+        );
+    let value = document(r#"{"ok": null, "points": [0, 5, 11]}"#);
+    let violations = schema.validate(&value);
+    let expected = vec![
+        Violation {
+            path: KeyPath(vec![key("name")]),
+            problem: Problem::Missing,
+        },
+        Violation {
+            path: KeyPath(vec![key("ok")]),
+            problem: Problem::WrongKind {
+                expected: Kind::Bool,
+                found: Kind::Null,
+            },
+        },
+        Violation {
+            path: KeyPath(vec![key("points"), PathSegment::Index(0)]),
+            problem: Problem::BelowMinimum {
+                value: 0.0,
+                min: 0.0,
+                exclusive: true,
+            },
+        },
+        Violation {
+            path: KeyPath(vec![key("points"), PathSegment::Index(2)]),
+            problem: Problem::AboveMaximum {
+                value: 11.0,
+                max: 10.0,
+            },
+        },
+    ];
+    assert_eq!(expected, violations);
+    assert_eq!(
+        "\"ok\": expected a boolean, found null",
+        violations[1].to_string()
+    );
+    assert_eq!("\"points\"[0]: 0 is not above 0", violations[2].to_string());
+
+    let short = document(r#"{"name": "a", "ok": true, "points": [1]}"#);
+    assert_eq!(
+        vec![Violation {
+            path: KeyPath(vec![key("points")]),
+            problem: Problem::TooFewItems { len: 1, min: 3 },
+        }],
+        schema.validate(&short)
+    );
+}
+
+#[test]
+fn test_wrong_root() {
+    let violations = Schema::object().validate(&document("[1]"));
+    assert_eq!(
+        "the document: expected an object, found an array",
+        violations[0].to_string()
+    );
+    assert_eq!(1, violations.len());
+}
@@ -0,0 +1,165 @@
+//! `Serialize` and `Deserialize` for the crate's values, behind the `serde`
+//! feature.
+
+use crate::geo::Point;
+use crate::pairs::Pair;
+use crate::parse::{Map, Number, Value};
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, SerializeStruct, Serializer};
+use std::fmt;
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::Null => serializer.serialize_unit(),
+            Value::Bool(b) => serializer.serialize_bool(*b),
+            Value::Number(n) => match n.resolve() {
+                Number::I64(n) => serializer.serialize_i64(n),
+                Number::U64(n) => serializer.serialize_u64(n),
+                n => serializer.serialize_f64(n.as_f64()),
+            },
+            Value::String(s) => serializer.serialize_str(s),
+            Value::Array(items) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for item in items {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            Value::Object(map) => {
+                let mut members = serializer.serialize_map(Some(map.len()))?;
+                for (key, value) in map {
+                    members.serialize_entry(key, value)?;
+                }
+                members.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a JSON value")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        Value::deserialize(deserializer)
+    }
+
+    fn visit_bool<E: de::Error>(self, b: bool) -> Result<Value, E> {
+        Ok(Value::Bool(b))
+    }
+
+    fn visit_i64<E: de::Error>(self, n: i64) -> Result<Value, E> {
+        Ok(Value::from(n))
+    }
+
+    fn visit_u64<E: de::Error>(self, n: u64) -> Result<Value, E> {
+        Ok(Value::from(n))
+    }
+
+    fn visit_f64<E: de::Error>(self, n: f64) -> Result<Value, E> {
+        Ok(Value::from(n))
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<Value, E> {
+        Ok(Value::String(s.into()))
+    }
+
+    fn visit_string<E: de::Error>(self, s: String) -> Result<Value, E> {
+        Ok(Value::String(s))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut items = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Value::Array(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Value, A::Error> {
+        let mut map = Map::new();
+        while let Some((key, value)) = access.next_entry()? {
+            map.insert(key, value);
+        }
+        Ok(Value::Object(map))
+    }
+}
+
+/// Field names of a pair, as in a pairs file.
+const PAIR_FIELDS: [&str; 4] = ["x0", "y0", "x1", "y1"];
+
+/// A pair is written as an object with the fields of a pairs file.
+impl Serialize for Pair {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut pair = serializer.serialize_struct("Pair", PAIR_FIELDS.len())?;
+        pair.serialize_field("x0", &self.p0.lon)?;
+        pair.serialize_field("y0", &self.p0.lat)?;
+        pair.serialize_field("x1", &self.p1.lon)?;
+        pair.serialize_field("y1", &self.p1.lat)?;
+        pair.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Pair {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_struct("Pair", &PAIR_FIELDS, PairVisitor)
+    }
+}
+
+struct PairVisitor;
+
+impl<'de> Visitor<'de> for PairVisitor {
+    type Value = Pair;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("an object with x0, y0, x1 and y1")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Pair, A::Error> {
+        let mut coordinates = [None; 4];
+        while let Some(key) = access.next_key::<String>()? {
+            match PAIR_FIELDS.iter().position(|field| *field == key) {
+                Some(i) if coordinates[i].is_some() => {
+                    return Err(de::Error::duplicate_field(PAIR_FIELDS[i]));
+                }
+                Some(i) => coordinates[i] = Some(access.next_value::<f64>()?),
+                None => {
+                    access.next_value::<de::IgnoredAny>()?;
+                }
+            }
+        }
+
+        let mut coordinate = |i: usize| {
+            coordinates[i]
+                .take()
+                .ok_or_else(|| de::Error::missing_field(PAIR_FIELDS[i]))
+        };
+        Ok(Pair::new(
+            Point::new(coordinate(0)?, coordinate(1)?),
+            Point::new(coordinate(2)?, coordinate(3)?),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests;
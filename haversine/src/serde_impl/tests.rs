@@ -0,0 +1,33 @@
+use crate::geo::Point;
+use crate::pairs::Pair;
+use crate::parse::{Parser, Value};
+
+#[test]
+fn test_value_round_trip() {
+    let input = r#"{"pairs":[{"x0":1.5,"y0":-2.0}],"name":"a","ok":true,"none":null}"#;
+    let value: Value = serde_json::from_str(input).unwrap();
+    assert_eq!(Parser::new(input).parse().unwrap().unwrap(), value);
+    assert_eq!(input, serde_json::to_string(&value).unwrap());
+}
+
+#[test]
+fn test_value_integers_exact() {
+    let input = r#"[18446744073709551615,-9223372036854775808,3,3.0]"#;
+    let value: Value = serde_json::from_str(input).unwrap();
+    assert_eq!(Parser::new(input).parse().unwrap().unwrap(), value);
+    assert_eq!(input, serde_json::to_string(&value).unwrap());
+}
+
+#[test]
+fn test_pair_round_trip() {
+    let pair = Pair::new(Point::new(1.5, -2.0), Point::new(3.0, 4.25));
+    let json = serde_json::to_string(&pair).unwrap();
+    assert_eq!(r#"{"x0":1.5,"y0":-2.0,"x1":3.0,"y1":4.25}"#, json);
+    assert_eq!(pair, serde_json::from_str(&json).unwrap());
+}
+
+#[test]
+fn test_pair_missing_field() {
+    let error = serde_json::from_str::<Pair>(r#"{"x0":1,"y0":2,"x1":3}"#).unwrap_err();
+    assert!(error.to_string().contains("missing field `y1`"));
+}
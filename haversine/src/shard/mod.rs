@@ -0,0 +1,117 @@
+//! Splitting a pairs document into shards and merging shards back, so the
+//! parts of a huge file can be processed apart.
+
+use crate::checksum;
+use crate::compute::ComputeDriver;
+use crate::pairs::Pairs;
+use crate::parse::{Map, Value};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum ShardError {
+    /// Splitting into no shards, or merging none.
+    NoShards,
+    /// The header of shard `index` has no numeric `radius`.
+    MissingRadius { index: usize },
+    /// Shard `index` is on a sphere of another radius than the first.
+    RadiusMismatch {
+        index: usize,
+        radius: f64,
+        expected: f64,
+    },
+}
+
+impl fmt::Display for ShardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShardError::NoShards => write!(f, "expected at least one shard"),
+            ShardError::MissingRadius { index } => {
+                write!(f, "expected a number at radius of shard {index}")
+            }
+            ShardError::RadiusMismatch {
+                index,
+                radius,
+                expected,
+            } => write!(
+                f,
+                "shard {index} has radius {radius}, but the first has {expected}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ShardError {}
+
+/// Splits the pairs of a document, whose other members are `header`, into
+/// `shards` documents of nearly equal size, in order. No shard is empty, so
+/// there are at most as many as pairs. Each keeps the members of `header`,
+/// with `avg_dist` computed over its pairs, the `count` hint, if any, set
+/// to their number, and the checksum, if any, to that of the shard.
+pub fn split(header: &Map, pairs: &Pairs, shards: usize) -> Result<Vec<Value>, ShardError> {
+    if shards == 0 {
+        return Err(ShardError::NoShards);
+    }
+    let radius = radius(header, 0)?;
+    let shards = shards.min(pairs.len()).max(1);
+    let n = pairs.len();
+    Ok((0..shards)
+        .map(|shard| {
+            let range = n * shard / shards..n * (shard + 1) / shards;
+            document(header, &pairs.slice(range), radius)
+        })
+        .collect())
+}
+
+/// Merges shards, given as their headers and pairs, back into one document
+/// with their pairs in order. The header is that of the first shard, with
+/// `avg_dist`, and the `count` hint and checksum if any, of all the pairs.
+/// The shards must agree on the radius.
+pub fn merge(shards: &[(Map, Pairs)]) -> Result<Value, ShardError> {
+    let (header, _) = shards.first().ok_or(ShardError::NoShards)?;
+    let expected = radius(header, 0)?;
+    let mut pairs = Pairs::with_capacity(shards.iter().map(|(_, pairs)| pairs.len()).sum());
+    for (index, (header, shard)) in shards.iter().enumerate() {
+        let radius = radius(header, index)?;
+        if radius != expected {
+            return Err(ShardError::RadiusMismatch {
+                index,
+                radius,
+                expected,
+            });
+        }
+        pairs.extend_from(shard);
+    }
+    Ok(document(header, &pairs, expected))
+}
+
+/// The `radius` of the header of shard `index`.
+fn radius(header: &Map, index: usize) -> Result<f64, ShardError> {
+    header
+        .get("radius")
+        .and_then(|radius| radius.try_into().ok())
+        .ok_or(ShardError::MissingRadius { index })
+}
+
+/// Builds a pairs document of `pairs` with the members of `header` after
+/// them, updated for the pairs.
+fn document(header: &Map, pairs: &Pairs, radius: f64) -> Value {
+    let mut object = Map::new();
+    object.insert("pairs".into(), pairs.to_values().into());
+    for (key, value) in header.iter().filter(|(key, _)| *key != "pairs") {
+        object.insert(key.clone(), value.clone());
+    }
+    let average = ComputeDriver::new(radius).run(pairs).average;
+    object.insert("avg_dist".into(), average.into());
+    if object.get("count").is_some() {
+        object.insert("count".into(), (pairs.len() as u64).into());
+    }
+    let mut document = Value::Object(object);
+    if header.get(checksum::FIELD).is_some() {
+        checksum::seal(&mut document);
+    }
+    document
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,115 @@
+use super::{ShardError, merge, split};
+use crate::checksum;
+use crate::compute::ComputeDriver;
+use crate::generate::{self, Distribution, generate_pairs};
+use crate::pairs::Pairs;
+use crate::parse::{Map, Value};
+use crate::rng::Xoshiro256PlusPlus;
+
+fn header(radius: f64) -> Map {
+    let Value::Object(header) = Value::object()
+        .insert("avg_dist", 0.0)
+        .insert("radius", radius)
+        .insert("count", 0u64)
+        .insert("source", "test")
+    else {
+        unreachable!()
+    };
+    header
+}
+
+/// The header and pairs of a pairs document.
+fn parts(document: &Value) -> (Map, Pairs) {
+    let Value::Object(object) = document else {
+        panic!("Expected an object");
+    };
+    let Some(Value::Array(values)) = object.get("pairs") else {
+        panic!("Expected a pairs array");
+    };
+    let mut header = Map::new();
+    for (key, value) in object.iter().filter(|(key, _)| *key != "pairs") {
+        header.insert(key.clone(), value.clone());
+    }
+    (header, Pairs::from_values(values).unwrap())
+}
+
+#[test]
+fn test_split_keeps_header() {
+    let pairs = generate_pairs(&mut Xoshiro256PlusPlus::new(1), 10, Distribution::Uniform);
+    let shards = split(&header(6372.8), &pairs, 3).unwrap();
+    assert_eq!(3, shards.len());
+
+    let mut sizes = Vec::new();
+    for shard in &shards {
+        let (header, shard_pairs) = parts(shard);
+        let average = ComputeDriver::new(6372.8).run(&shard_pairs).average;
+        assert_eq!(Some(&Value::from(average)), header.get("avg_dist"));
+        assert_eq!(
+            Some(&Value::from(shard_pairs.len() as u64)),
+            header.get("count")
+        );
+        assert_eq!(Some(&Value::from("test")), header.get("source"));
+        sizes.push(shard_pairs.len());
+    }
+    assert_eq!(vec![3, 3, 4], sizes);
+}
+
+#[test]
+fn test_split_never_empty() {
+    let pairs = generate_pairs(&mut Xoshiro256PlusPlus::new(2), 2, Distribution::Uniform);
+    let shards = split(&header(1.0), &pairs, 5).unwrap();
+    assert_eq!(2, shards.len());
+    assert_eq!(Err(ShardError::NoShards), split(&header(1.0), &pairs, 0));
+}
+
+#[test]
+fn test_merge_inverts_split() {
+    let pairs = generate_pairs(&mut Xoshiro256PlusPlus::new(3), 25, Distribution::Uniform);
+    let header = header(6372.8);
+    let shards: Vec<_> = split(&header, &pairs, 4)
+        .unwrap()
+        .iter()
+        .map(parts)
+        .collect();
+    let merged = merge(&shards).unwrap();
+    let whole = split(&header, &pairs, 1).unwrap().remove(0);
+    assert_eq!(whole, merged);
+    assert_eq!(pairs, parts(&merged).1);
+}
+
+#[test]
+fn test_merge_errors() {
+    assert_eq!(Err(ShardError::NoShards), merge(&[]));
+    let pairs = generate_pairs(&mut Xoshiro256PlusPlus::new(4), 2, Distribution::Uniform);
+    let shards = [(header(1.0), pairs.clone()), (header(2.0), pairs.clone())];
+    assert_eq!(
+        Err(ShardError::RadiusMismatch {
+            index: 1,
+            radius: 2.0,
+            expected: 1.0
+        }),
+        merge(&shards)
+    );
+    assert_eq!(
+        Err(ShardError::MissingRadius { index: 0 }),
+        merge(&[(Map::new(), pairs)])
+    );
+}
+
+#[test]
+fn test_shards_resealed() {
+    let pairs = generate_pairs(&mut Xoshiro256PlusPlus::new(5), 9, Distribution::Uniform);
+    let (header, _) = parts(&generate::document(&pairs, 1.0));
+    let shards = split(&header, &pairs, 3).unwrap();
+    for shard in &shards {
+        assert_eq!(
+            shard[checksum::FIELD],
+            Value::from(checksum::checksum(shard))
+        );
+    }
+    let shards: Vec<_> = shards.iter().map(parts).collect();
+    assert_eq!(generate::document(&pairs, 1.0), merge(&shards).unwrap());
+    // Shards of documents without a checksum get none.
+    let shard = split(&self::header(1.0), &pairs, 1).unwrap().remove(0);
+    assert!(parts(&shard).0.get(checksum::FIELD).is_none());
+}
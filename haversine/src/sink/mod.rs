@@ -0,0 +1,155 @@
+//! Destinations of the reports and per-pair records the subcommands write,
+//! so a program embedding them can capture what they write rather than
+//! scrape the printed text.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Where reports and records are written.
+///
+/// ```
+/// use haversine::sink::{BufferSink, OutputSink};
+/// use haversine::stats::Stats;
+///
+/// let mut sink = BufferSink::new();
+/// Stats::new(&[1.0, 3.0]).unwrap().write(&mut sink).unwrap();
+/// assert!(sink.as_str().unwrap().contains("Mean: 2\n"));
+/// assert!(sink.captured().is_some());
+/// ```
+pub trait OutputSink: Write {
+    /// The bytes written so far, if the sink keeps them.
+    fn captured(&self) -> Option<&[u8]> {
+        None
+    }
+}
+
+/// Standard output, locked for each write so the output of threads does
+/// not interleave within one.
+#[derive(Debug, Default)]
+pub struct StdoutSink;
+
+impl Write for StdoutSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        io::stdout().lock().write(buf)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        io::stdout().lock().write_all(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+}
+
+impl OutputSink for StdoutSink {}
+
+/// A file, created or truncated, written through a buffer.
+#[derive(Debug)]
+pub struct FileSink(BufWriter<File>);
+
+impl FileSink {
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        File::create(path).map(|file| Self(BufWriter::new(file)))
+    }
+}
+
+impl Write for FileSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.0.write_all(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl OutputSink for FileSink {}
+
+/// Memory, keeping everything written.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BufferSink(Vec<u8>);
+
+impl BufferSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// The bytes written as text, or `None` if they are not UTF-8, as
+    /// with the `f64` per-pair format.
+    pub fn as_str(&self) -> Option<&str> {
+        std::str::from_utf8(&self.0).ok()
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+impl Write for BufferSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl OutputSink for BufferSink {
+    fn captured(&self) -> Option<&[u8]> {
+        Some(&self.0)
+    }
+}
+
+/// Nowhere, counting the bytes written, to time a run without its output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NullSink {
+    pub written: u64,
+}
+
+impl Write for NullSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.written += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl OutputSink for NullSink {}
+
+impl<S: OutputSink + ?Sized> OutputSink for &mut S {
+    fn captured(&self) -> Option<&[u8]> {
+        (**self).captured()
+    }
+}
+
+impl<S: OutputSink + ?Sized> OutputSink for Box<S> {
+    fn captured(&self) -> Option<&[u8]> {
+        (**self).captured()
+    }
+}
+
+/// Opens the sink of an output path of the command line: standard output
+/// for `-`, else the file at `path`.
+pub fn open(path: &str) -> io::Result<Box<dyn OutputSink>> {
+    match path {
+        "-" => Ok(Box::new(StdoutSink)),
+        path => Ok(Box::new(FileSink::create(path)?)),
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,58 @@
+use super::{BufferSink, FileSink, NullSink, OutputSink, StdoutSink, open};
+use crate::geo::AngleUnit;
+use crate::pairs::Pairs;
+use crate::per_pair::{self, PerPairFormat, PerPairOptions};
+use std::io::Write;
+
+#[test]
+fn test_buffer_sink_captures() {
+    let mut sink = BufferSink::new();
+    writeln!(sink, "Mean: {}", 2.5).unwrap();
+    assert_eq!(Some("Mean: 2.5\n"), sink.as_str());
+    assert_eq!(Some(b"Mean: 2.5\n".as_slice()), sink.captured());
+
+    let mut boxed: Box<dyn OutputSink> = Box::new(sink);
+    write!(boxed, "more").unwrap();
+    assert_eq!(Some(b"Mean: 2.5\nmore".as_slice()), boxed.captured());
+    assert_eq!(None, StdoutSink.captured());
+}
+
+#[test]
+fn test_per_pair_records_to_sinks() {
+    let pairs = Pairs {
+        x0: vec![0.0, 10.0],
+        y0: vec![0.0, 20.0],
+        x1: vec![90.0, 30.0],
+        y1: vec![0.0, 40.0],
+    };
+    let options = PerPairOptions {
+        format: PerPairFormat::Csv,
+        coordinates: false,
+        bearings: false,
+        midpoints: false,
+        angle_unit: AngleUnit::Degrees,
+    };
+    let mut buffer = BufferSink::new();
+    per_pair::write(&mut buffer, &pairs, &[1.5, 2.5], &options).unwrap();
+    assert_eq!(Some("distance\n1.5\n2.5\n"), buffer.as_str());
+
+    let mut null = NullSink::default();
+    per_pair::write(&mut null, &pairs, &[1.5, 2.5], &options).unwrap();
+    assert_eq!(buffer.bytes().len() as u64, null.written);
+}
+
+#[test]
+fn test_file_sink() {
+    let path = std::env::temp_dir().join(format!("sink-{}.txt", std::process::id()));
+    let mut sink = FileSink::create(&path).unwrap();
+    writeln!(sink, "line").unwrap();
+    sink.flush().unwrap();
+    assert_eq!(None, sink.captured());
+    assert_eq!("line\n", std::fs::read_to_string(&path).unwrap());
+
+    let mut sink = open(path.to_str().unwrap()).unwrap();
+    write!(sink, "again").unwrap();
+    drop(sink);
+    assert_eq!("again", std::fs::read_to_string(&path).unwrap());
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,185 @@
+use crate::parse::Value;
+use crate::sink::OutputSink;
+use std::io;
+
+/// Width, in characters, of the longest bar of the histogram.
+const BAR_WIDTH: usize = 50;
+
+/// Standard normal quantile of a two-sided 95% confidence interval.
+const Z_95: f64 = 1.959_963_984_540_054;
+
+/// Summary statistics of the computed distances.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub stddev: f64,
+    pub median: f64,
+}
+
+/// Number of distances in equally wide buckets between the minimum and
+/// maximum distance.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    pub min: f64,
+    pub max: f64,
+    pub counts: Vec<usize>,
+}
+
+impl Stats {
+    /// Calculates the statistics, or `None` if there are no distances.
+    pub fn new(distances: &[f64]) -> Option<Self> {
+        let count = distances.len();
+        if count == 0 {
+            return None;
+        }
+
+        let mean = distances.iter().sum::<f64>() / count as f64;
+        let variance = distances.iter().map(|d| (d - mean).powi(2)).sum::<f64>() / count as f64;
+
+        let mut sorted = distances.to_vec();
+        sorted.sort_unstable_by(f64::total_cmp);
+        let median = if count % 2 == 1 {
+            sorted[count / 2]
+        } else {
+            (sorted[count / 2 - 1] + sorted[count / 2]) / 2.0
+        };
+
+        Some(Self {
+            count,
+            min: sorted[0],
+            max: sorted[count - 1],
+            mean,
+            stddev: variance.sqrt(),
+            median,
+        })
+    }
+
+    /// Writes the statistics to `sink`, one per line.
+    pub fn write(&self, mut sink: impl OutputSink) -> io::Result<()> {
+        writeln!(sink, "--- Stats ---")?;
+        writeln!(sink, "Count: {}", self.count)?;
+        writeln!(sink, "Min: {}", self.min)?;
+        writeln!(sink, "Max: {}", self.max)?;
+        writeln!(sink, "Mean: {}", self.mean)?;
+        writeln!(sink, "Stddev: {}", self.stddev)?;
+        writeln!(sink, "Median: {}", self.median)
+    }
+
+    pub fn to_value(&self) -> Value {
+        Value::object()
+            .insert("count", self.count as f64)
+            .insert("min", self.min)
+            .insert("max", self.max)
+            .insert("mean", self.mean)
+            .insert("stddev", self.stddev)
+            .insert("median", self.median)
+    }
+}
+
+impl Histogram {
+    /// Sorts the distances into `buckets` buckets. The maximum distance
+    /// falls into the last bucket.
+    pub fn new(distances: &[f64], stats: &Stats, buckets: usize) -> Self {
+        let buckets = buckets.max(1);
+        let mut counts = vec![0; buckets];
+        let width = (stats.max - stats.min) / buckets as f64;
+        for distance in distances {
+            let bucket = if width > 0.0 {
+                ((distance - stats.min) / width) as usize
+            } else {
+                0
+            };
+            counts[bucket.min(buckets - 1)] += 1;
+        }
+        Self {
+            min: stats.min,
+            max: stats.max,
+            counts,
+        }
+    }
+
+    /// Lower and upper bound of the bucket at `index`.
+    fn bounds(&self, index: usize) -> (f64, f64) {
+        let width = (self.max - self.min) / self.counts.len() as f64;
+        (
+            self.min + index as f64 * width,
+            self.min + (index + 1) as f64 * width,
+        )
+    }
+
+    /// Renders one line per bucket with a bar proportional to its count.
+    pub fn render(&self) -> String {
+        let largest = self.counts.iter().copied().max().unwrap_or(0).max(1);
+        let mut lines = Vec::new();
+        for (index, &count) in self.counts.iter().enumerate() {
+            let (lower, upper) = self.bounds(index);
+            let bar = "#".repeat(count * BAR_WIDTH / largest);
+            lines.push(format!("[{lower:>12.3}, {upper:>12.3}) {count:>10} {bar}"));
+        }
+        lines.join("\n")
+    }
+
+    pub fn to_value(&self) -> Value {
+        let mut counts = Value::array();
+        for &count in &self.counts {
+            counts.push(count as f64);
+        }
+        Value::object()
+            .insert("min", self.min)
+            .insert("max", self.max)
+            .insert("counts", counts)
+    }
+}
+
+/// Mean distance of all the pairs estimated from a random sample of them,
+/// with a 95% confidence interval.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Estimate {
+    pub mean: f64,
+    /// Half the width of the interval around the mean.
+    pub margin: f64,
+    pub sampled: usize,
+    pub population: usize,
+}
+
+impl Estimate {
+    /// Estimates from the distances of a sample drawn without replacement
+    /// from `population` pairs, or `None` if the sample is empty. The
+    /// interval narrows as the sample covers more of the population, to
+    /// nothing when it is all of it. A single distance of a larger
+    /// population gives an unbounded interval.
+    pub fn new(distances: &[f64], population: usize) -> Option<Self> {
+        let sampled = distances.len();
+        if sampled == 0 {
+            return None;
+        }
+        let mean = distances.iter().sum::<f64>() / sampled as f64;
+        let margin = if sampled >= population {
+            0.0
+        } else if sampled == 1 {
+            f64::INFINITY
+        } else {
+            let variance =
+                distances.iter().map(|d| (d - mean).powi(2)).sum::<f64>() / (sampled - 1) as f64;
+            let correction = (population - sampled) as f64 / (population - 1) as f64;
+            Z_95 * (variance / sampled as f64 * correction).sqrt()
+        };
+        Some(Self {
+            mean,
+            margin,
+            sampled,
+            population,
+        })
+    }
+
+    /// Whether `mean` is within the interval.
+    pub fn contains(&self, mean: f64) -> bool {
+        (mean - self.mean).abs() <= self.margin
+    }
+}
+
+#[cfg(test)]
+mod tests;
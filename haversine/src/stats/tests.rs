@@ -0,0 +1,75 @@
+use super::{Estimate, Histogram, Stats};
+
+#[test]
+fn test_stats_empty() {
+    assert_eq!(None, Stats::new(&[]));
+}
+
+#[test]
+fn test_stats_odd_count() {
+    let stats = Stats::new(&[4.0, 1.0, 7.0]).unwrap();
+    assert_eq!(3, stats.count);
+    assert_eq!(1.0, stats.min);
+    assert_eq!(7.0, stats.max);
+    assert_eq!(4.0, stats.mean);
+    assert_eq!(4.0, stats.median);
+    assert_eq!(6.0f64.sqrt(), stats.stddev);
+}
+
+#[test]
+fn test_stats_even_count_median() {
+    let stats = Stats::new(&[2.0, 8.0, 4.0, 6.0]).unwrap();
+    assert_eq!(5.0, stats.median);
+    assert_eq!(5.0, stats.mean);
+}
+
+#[test]
+fn test_histogram_buckets() {
+    let distances = [0.0, 1.0, 2.0, 2.5, 9.9, 10.0];
+    let stats = Stats::new(&distances).unwrap();
+    let histogram = Histogram::new(&distances, &stats, 5);
+    assert_eq!(vec![2, 2, 0, 0, 2], histogram.counts);
+}
+
+#[test]
+fn test_histogram_identical_distances() {
+    let distances = [3.0, 3.0, 3.0];
+    let stats = Stats::new(&distances).unwrap();
+    let histogram = Histogram::new(&distances, &stats, 4);
+    assert_eq!(vec![3, 0, 0, 0], histogram.counts);
+}
+
+#[test]
+fn test_histogram_render() {
+    let distances = [0.0, 1.0, 1.5, 2.0];
+    let stats = Stats::new(&distances).unwrap();
+    let rendered = Histogram::new(&distances, &stats, 2).render();
+    let lines: Vec<&str> = rendered.lines().collect();
+    assert_eq!(2, lines.len());
+    assert!(lines[0].ends_with(&format!("{:>10} {}", 1, "#".repeat(16))));
+    assert!(lines[1].ends_with(&format!("{:>10} {}", 3, "#".repeat(50))));
+}
+
+#[test]
+fn test_estimate_interval() {
+    assert_eq!(None, Estimate::new(&[], 10));
+
+    let estimate = Estimate::new(&[1.0, 3.0], 1_000_000).unwrap();
+    assert_eq!(2.0, estimate.mean);
+    let standard_error = 2.0f64.sqrt() / 2.0f64.sqrt();
+    assert!((estimate.margin - 1.96 * standard_error).abs() < 1e-3);
+    assert!(estimate.contains(2.9));
+    assert!(!estimate.contains(4.0));
+
+    let whole = Estimate::new(&[1.0, 3.0], 2).unwrap();
+    assert_eq!(0.0, whole.margin);
+    assert!(Estimate::new(&[1.0], 2).unwrap().margin.is_infinite());
+}
+
+#[test]
+fn test_estimate_narrows_with_population_coverage() {
+    let distances: Vec<f64> = (0..100).map(f64::from).collect();
+    let small = Estimate::new(&distances, 200).unwrap();
+    let large = Estimate::new(&distances, 1_000_000).unwrap();
+    assert!(small.margin < large.margin);
+}
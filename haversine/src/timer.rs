@@ -0,0 +1,30 @@
+use std::time::{Duration, Instant};
+
+/// Whether `read_cpu_timer` counts CPU cycles, rather than nanoseconds.
+pub const CYCLE_TIMER: bool = cfg!(target_arch = "x86_64");
+
+/// Reads the CPU time-stamp counter.
+#[cfg(target_arch = "x86_64")]
+pub fn read_cpu_timer() -> u64 {
+    // SAFETY: `rdtsc` is available on every x86_64 processor.
+    unsafe { std::arch::x86_64::_rdtsc() }
+}
+
+/// Reads a monotonic nanosecond clock, used where there is no time-stamp counter.
+#[cfg(not(target_arch = "x86_64"))]
+pub fn read_cpu_timer() -> u64 {
+    use std::sync::OnceLock;
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+    EPOCH.get_or_init(Instant::now).elapsed().as_nanos() as u64
+}
+
+/// Estimates the number of CPU timer ticks per second by measuring it
+/// against the OS clock for `wait`.
+pub fn estimate_cpu_timer_freq(wait: Duration) -> u64 {
+    let cpu_start = read_cpu_timer();
+    let os_start = Instant::now();
+    while os_start.elapsed() < wait {}
+    let cpu_elapsed = read_cpu_timer() - cpu_start;
+    let os_elapsed = os_start.elapsed().as_secs_f64();
+    (cpu_elapsed as f64 / os_elapsed) as u64
+}
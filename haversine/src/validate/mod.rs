@@ -0,0 +1,334 @@
+use crate::checksum;
+use crate::compute::ComputeDriver;
+use crate::dd::DoubleDouble;
+use crate::geo::reference;
+use crate::pairs::{CountMismatch, Pairs, PairsError, check_count};
+use crate::parse::{KeyPath, PathSegment, Value};
+use crate::schema::{Problem, Schema, Violation};
+use crate::sink::OutputSink;
+use std::{fmt, io};
+
+/// Number of issues printed before the rest are only counted.
+const MAX_PRINTED_ISSUES: usize = 20;
+
+/// A problem found in a pairs file.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Issue {
+    NotAnObject,
+    NoPairs,
+    MissingField(&'static str),
+    InvalidRadius(f64),
+    InvalidPair(PairsError),
+    LatitudeOutOfRange(KeyPath, f64),
+    LongitudeOutOfRange(KeyPath, f64),
+    CountMismatch(CountMismatch),
+    /// The stored checksum is not that of the document.
+    ChecksumMismatch {
+        stored: String,
+        computed: String,
+    },
+}
+
+impl Issue {
+    /// Whether the issue is reported without failing the validation.
+    pub fn is_warning(&self) -> bool {
+        matches!(self, Issue::CountMismatch(_))
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::NotAnObject => write!(f, "the document is not an object"),
+            Issue::NoPairs => write!(f, "the pairs array is empty"),
+            Issue::MissingField(field) => write!(f, "missing or invalid field {field:?}"),
+            Issue::InvalidRadius(radius) => write!(f, "radius {radius} is not positive"),
+            Issue::InvalidPair(error) => write!(f, "{error}"),
+            Issue::LatitudeOutOfRange(path, value) => {
+                write!(f, "latitude {value} at {path} is outside [-90, 90]")
+            }
+            Issue::LongitudeOutOfRange(path, value) => {
+                write!(f, "longitude {value} at {path} is outside [-180, 180]")
+            }
+            Issue::CountMismatch(mismatch) => write!(
+                f,
+                "the count hint declares {} pairs, but {} were parsed",
+                mismatch.declared, mismatch.parsed
+            ),
+            Issue::ChecksumMismatch { stored, computed } => write!(
+                f,
+                "the checksum is {stored}, but the content hashes to {computed}"
+            ),
+        }
+    }
+}
+
+/// Outcome of validating a pairs file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub n_pairs: usize,
+    pub stored_average: Option<f64>,
+    pub computed_average: Option<f64>,
+    /// Whether the stored checksum matches, or `None` without one.
+    pub checksum_verified: Option<bool>,
+    pub issues: Vec<Issue>,
+    /// Errors of the computed distances against double-double ones, from
+    /// `validate_reference`.
+    pub pair_errors: Option<PairErrors>,
+}
+
+/// Errors of the distance the default kernel computes for each pair,
+/// relative to that computed in double-double arithmetic.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PairErrors {
+    /// Average of the double-double distances, rounded once.
+    pub reference_average: f64,
+    /// Largest relative error of a pair.
+    pub max: f64,
+    /// Index of the pair with the largest error.
+    pub worst_pair: usize,
+    pub mean: f64,
+}
+
+impl PairErrors {
+    /// Compares the distances of `pairs` on a sphere of `radius` with
+    /// those computed in double-double arithmetic.
+    pub fn compute(pairs: &Pairs, radius: f64) -> Self {
+        let computed = ComputeDriver::new(radius)
+            .keep_distances(true)
+            .run(pairs)
+            .distances;
+        let mut sum = DoubleDouble::default();
+        let mut errors = Self {
+            reference_average: 0.0,
+            max: 0.0,
+            worst_pair: 0,
+            mean: 0.0,
+        };
+        for (index, (pair, computed)) in pairs.iter().zip(computed).enumerate() {
+            let (p0, p1) = (pair.p0, pair.p1);
+            let distance = reference::distance(radius, p0.lon, p0.lat, p1.lon, p1.lat);
+            sum = sum.add_dd(distance);
+            let error = relative_error(distance.to_f64(), computed);
+            if error > errors.max {
+                errors.max = error;
+                errors.worst_pair = index;
+            }
+            errors.mean += error;
+        }
+        let n_pairs = pairs.len().max(1) as f64;
+        errors.reference_average = sum.div_f64(n_pairs).to_f64();
+        errors.mean /= n_pairs;
+        errors
+    }
+}
+
+impl Report {
+    pub fn absolute_error(&self) -> Option<f64> {
+        Some((self.computed_average? - self.stored_average?).abs())
+    }
+
+    pub fn relative_error(&self) -> Option<f64> {
+        Some(relative_error(self.stored_average?, self.computed_average?))
+    }
+
+    /// Whether the file has issues other than warnings.
+    pub fn has_errors(&self) -> bool {
+        !self.issues.iter().all(Issue::is_warning)
+    }
+
+    /// Whether the file has no issues other than warnings and the stored
+    /// average matches the computed one within the relative `tolerance`.
+    pub fn passed(&self, tolerance: f64) -> bool {
+        !self.has_errors()
+            && self
+                .relative_error()
+                .is_some_and(|error| error <= tolerance)
+    }
+
+    /// Writes the report to `sink`, one finding per line, ending with
+    /// whether the file passed within `tolerance`.
+    pub fn write(&self, mut sink: impl OutputSink, tolerance: f64) -> io::Result<()> {
+        writeln!(sink, "Number of pairs: {}", self.n_pairs)?;
+        if let Some(stored) = self.stored_average {
+            writeln!(sink, "Stored average: {stored}")?;
+        }
+        if let Some(computed) = self.computed_average {
+            writeln!(sink, "Computed average: {computed}")?;
+        }
+        if let (Some(absolute), Some(relative)) = (self.absolute_error(), self.relative_error()) {
+            writeln!(sink, "Absolute error: {absolute}")?;
+            writeln!(sink, "Relative error: {relative} (tolerance {tolerance})")?;
+        }
+        if let Some(errors) = self.pair_errors {
+            writeln!(sink, "Reference average: {}", errors.reference_average)?;
+            writeln!(
+                sink,
+                "Largest pair error: {:e} (pair {})",
+                errors.max, errors.worst_pair
+            )?;
+            writeln!(sink, "Mean pair error: {:e}", errors.mean)?;
+        }
+        if let Some(verified) = self.checksum_verified {
+            writeln!(
+                sink,
+                "Checksum: {}",
+                if verified { "verified" } else { "mismatch" }
+            )?;
+        }
+        if !self.issues.is_empty() {
+            writeln!(sink, "Issues: {}", self.issues.len())?;
+            for issue in self.issues.iter().take(MAX_PRINTED_ISSUES) {
+                let kind = if issue.is_warning() {
+                    "warning"
+                } else {
+                    "error"
+                };
+                writeln!(sink, "  - {kind}: {issue}")?;
+            }
+            if self.issues.len() > MAX_PRINTED_ISSUES {
+                writeln!(
+                    sink,
+                    "  ... and {} more",
+                    self.issues.len() - MAX_PRINTED_ISSUES
+                )?;
+            }
+        }
+        let result = if self.passed(tolerance) {
+            "PASS"
+        } else {
+            "FAIL"
+        };
+        writeln!(sink, "Result: {result}")
+    }
+}
+
+/// Error of `computed` relative to `stored`, or the absolute error if the
+/// stored value is zero.
+pub fn relative_error(stored: f64, computed: f64) -> f64 {
+    let absolute = (computed - stored).abs();
+    if stored == 0.0 {
+        absolute
+    } else {
+        absolute / stored.abs()
+    }
+}
+
+/// Schema of a pairs file: an object with a non-empty `pairs` array of
+/// pair objects whose longitudes are within [-180, 180] and latitudes within
+/// [-90, 90], a positive `radius` and an `avg_dist`.
+pub fn pairs_schema() -> Schema {
+    let longitude = Schema::number().min(-180.0).max(180.0);
+    let latitude = Schema::number().min(-90.0).max(90.0);
+    let pair = Schema::object()
+        .required("x0", longitude.clone())
+        .required("y0", latitude.clone())
+        .required("x1", longitude)
+        .required("y1", latitude);
+    Schema::object()
+        .optional(checksum::FIELD, Schema::string())
+        .required("avg_dist", Schema::number())
+        .required("radius", Schema::number().exclusive_min(0.0))
+        .required("pairs", Schema::array(pair).min_items(1))
+}
+
+/// Checks a parsed pairs file against `pairs_schema`, and recomputes its
+/// average distance and, if it has one, its checksum.
+pub fn validate(document: &Value) -> Report {
+    check(document, false)
+}
+
+/// Checks a parsed pairs file as `validate` does, and also recomputes the
+/// distance of every pair in double-double arithmetic to report the errors
+/// of the computed ones in `pair_errors`. Far slower than `validate`.
+pub fn validate_reference(document: &Value) -> Report {
+    check(document, true)
+}
+
+fn check(document: &Value, reference: bool) -> Report {
+    let mut report = Report {
+        n_pairs: 0,
+        stored_average: None,
+        computed_average: None,
+        checksum_verified: None,
+        issues: Vec::new(),
+        pair_errors: None,
+    };
+
+    let Value::Object(object) = document else {
+        report.issues.push(Issue::NotAnObject);
+        return report;
+    };
+
+    if let Some(Value::String(stored)) = object.get(checksum::FIELD) {
+        let computed = checksum::checksum(document);
+        let verified = *stored == computed;
+        report.checksum_verified = Some(verified);
+        if !verified {
+            report.issues.push(Issue::ChecksumMismatch {
+                stored: stored.clone(),
+                computed,
+            });
+        }
+    }
+
+    let violations = pairs_schema().validate(document);
+    report.issues.extend(violations.into_iter().map(issue));
+    let number = |key| match object.get(key) {
+        Some(Value::Number(number)) => Some(number.as_f64()),
+        _ => None,
+    };
+    report.stored_average = number("avg_dist");
+    let radius = number("radius").filter(|&radius| radius > 0.0);
+
+    let Some(Value::Array(values)) = object.get("pairs") else {
+        return report;
+    };
+    let Ok(pairs) = Pairs::from_values(values) else {
+        return report;
+    };
+    report.n_pairs = pairs.len();
+    if pairs.is_empty() {
+        return report;
+    }
+    if let Some(mismatch) = check_count(object, &pairs) {
+        report.issues.push(Issue::CountMismatch(mismatch));
+    }
+    report.computed_average = radius.map(|radius| ComputeDriver::new(radius).run(&pairs).average);
+    if reference {
+        report.pair_errors = radius.map(|radius| PairErrors::compute(&pairs, radius));
+    }
+    report
+}
+
+/// The issue of a violation of `pairs_schema`.
+fn issue(violation: Violation) -> Issue {
+    let Violation { path, problem } = violation;
+    match (&path.0[..], problem) {
+        ([], _) => Issue::NotAnObject,
+        ([PathSegment::Key(key)], Problem::BelowMinimum { value, .. }) if key == "radius" => {
+            Issue::InvalidRadius(value)
+        }
+        ([PathSegment::Key(key)], Problem::TooFewItems { .. }) if key == "pairs" => Issue::NoPairs,
+        ([PathSegment::Key(key)], _) => Issue::MissingField(match key.as_str() {
+            "avg_dist" => "avg_dist",
+            "radius" => "radius",
+            "pairs" => "pairs",
+            _ => checksum::FIELD,
+        }),
+        ([_, _], _) => Issue::InvalidPair(PairsError::InvalidPair(path)),
+        ([_, _, PathSegment::Key(key)], Problem::BelowMinimum { value, .. })
+        | ([_, _, PathSegment::Key(key)], Problem::AboveMaximum { value, .. }) => {
+            if key.starts_with('x') {
+                Issue::LongitudeOutOfRange(path, value)
+            } else {
+                Issue::LatitudeOutOfRange(path, value)
+            }
+        }
+        _ => Issue::InvalidPair(PairsError::MissingCoordinate(path)),
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,169 @@
+use super::{Issue, PairErrors, pairs_schema, relative_error, validate, validate_reference};
+use crate::generate::{self, Distribution, generate_pairs};
+use crate::geo::haversine_distance;
+use crate::pairs::{CountMismatch, PairsError};
+use crate::parse::{KeyPath, Parser, PathSegment, Value};
+use crate::rng::Xoshiro256PlusPlus;
+
+const TOLERANCE: f64 = 1e-6;
+
+fn document(input: &str) -> Value {
+    Parser::new(input).parse().unwrap().unwrap()
+}
+
+#[test]
+fn test_valid_file() {
+    let avg = haversine_distance(1.0, 0.0, 0.0, 90.0, 0.0);
+    let input = format!(
+        r#"{{"pairs": [{{"x0": 0, "y0": 0, "x1": 90, "y1": 0}}], "radius": 1, "avg_dist": {avg}}}"#
+    );
+    let report = validate(&document(&input));
+    assert_eq!(Vec::<Issue>::new(), report.issues);
+    assert_eq!(Some(0.0), report.absolute_error());
+    assert!(report.passed(TOLERANCE));
+}
+
+#[test]
+fn test_average_mismatch() {
+    let input =
+        r#"{"pairs": [{"x0": 0, "y0": 0, "x1": 90, "y1": 0}], "radius": 1, "avg_dist": 1.5}"#;
+    let report = validate(&document(input));
+    assert!(report.issues.is_empty());
+    assert!(!report.has_errors());
+    assert!(report.relative_error().unwrap() > TOLERANCE);
+    assert!(!report.passed(TOLERANCE));
+}
+
+#[test]
+fn test_relative_error() {
+    assert_eq!(0.5, relative_error(-2.0, -1.0));
+    assert_eq!(0.25, relative_error(0.0, -0.25));
+}
+
+#[test]
+fn test_out_of_range_coordinates() {
+    let input =
+        r#"{"pairs": [{"x0": 181, "y0": 0, "x1": 0, "y1": -91}], "radius": 1, "avg_dist": 0}"#;
+    let report = validate(&document(input));
+    let path = |key: &str| {
+        KeyPath(vec![
+            PathSegment::Key("pairs".into()),
+            PathSegment::Index(0),
+            PathSegment::Key(key.into()),
+        ])
+    };
+    assert_eq!(
+        vec![
+            Issue::LongitudeOutOfRange(path("x0"), 181.0),
+            Issue::LatitudeOutOfRange(path("y1"), -91.0),
+        ],
+        report.issues
+    );
+    assert!(report.has_errors());
+    assert!(!report.passed(TOLERANCE));
+}
+
+#[test]
+fn test_invalid_radius_and_missing_average() {
+    let input = r#"{"pairs": [], "radius": 0}"#;
+    let report = validate(&document(input));
+    assert_eq!(
+        vec![
+            Issue::MissingField("avg_dist"),
+            Issue::InvalidRadius(0.0),
+            Issue::NoPairs
+        ],
+        report.issues
+    );
+    assert_eq!(None, report.computed_average);
+}
+
+#[test]
+fn test_count_mismatch_reported() {
+    let input = r#"{"count": 2, "pairs": [{"x0": 0, "y0": 0, "x1": 0, "y1": 0}], "radius": 1, "avg_dist": 0}"#;
+    let report = validate(&document(input));
+    let mismatch = CountMismatch {
+        declared: 2,
+        parsed: 1,
+    };
+    assert_eq!(vec![Issue::CountMismatch(mismatch)], report.issues);
+    assert!(report.passed(TOLERANCE));
+}
+
+#[test]
+fn test_invalid_pairs() {
+    let input = r#"{"pairs": [{"x0": 0, "y0": 0, "x1": 0}, 3], "radius": 1, "avg_dist": 0}"#;
+    let report = validate(&document(input));
+    let path = |segments: &[PathSegment]| {
+        let mut path = vec![PathSegment::Key("pairs".into())];
+        path.extend_from_slice(segments);
+        KeyPath(path)
+    };
+    assert_eq!(
+        vec![
+            Issue::InvalidPair(PairsError::MissingCoordinate(path(&[
+                PathSegment::Index(0),
+                PathSegment::Key("y1".into())
+            ]))),
+            Issue::InvalidPair(PairsError::InvalidPair(path(&[PathSegment::Index(1)]))),
+        ],
+        report.issues
+    );
+    assert_eq!(None, report.computed_average);
+}
+
+#[test]
+fn test_pairs_schema() {
+    let input = r#"{"pairs": [{"x0": 0, "y0": 0, "x1": 0, "y1": 0}], "radius": 1, "avg_dist": 0}"#;
+    assert!(pairs_schema().validate(&document(input)).is_empty());
+    assert_eq!(3, pairs_schema().validate(&document("{}")).len());
+}
+
+#[test]
+fn test_not_an_object() {
+    let report = validate(&document("[1, 2]"));
+    assert_eq!(vec![Issue::NotAnObject], report.issues);
+}
+
+#[test]
+fn test_checksum_verified() {
+    let pairs = generate_pairs(&mut Xoshiro256PlusPlus::new(4), 5, Distribution::Uniform);
+    let generated = generate::document(&pairs, 1.0);
+    let report = validate(&document(&generated.to_json_pretty(2)));
+    assert_eq!(Vec::<Issue>::new(), report.issues);
+    assert_eq!(Some(true), report.checksum_verified);
+
+    let tampered = generated.to_json().replacen("\"x0\":", "\"x0\":1", 1);
+    let report = validate(&document(&tampered));
+    assert_eq!(Some(false), report.checksum_verified);
+    assert!(matches!(
+        report.issues[..],
+        [Issue::ChecksumMismatch { .. }]
+    ));
+    assert!(report.has_errors());
+}
+
+#[test]
+fn test_checksum_not_a_string() {
+    let input = r#"{"pairs": [{"x0": 0, "y0": 0, "x1": 0, "y1": 0}], "radius": 1, "avg_dist": 0, "checksum": 1}"#;
+    let report = validate(&document(input));
+    assert_eq!(vec![Issue::MissingField("checksum")], report.issues);
+    assert_eq!(None, report.checksum_verified);
+}
+
+#[test]
+fn test_validate_reference_pair_errors() {
+    let pairs = generate_pairs(&mut Xoshiro256PlusPlus::new(6), 50, Distribution::Uniform);
+    let generated = generate::document(&pairs, 6372.8);
+    let document = document(&generated.to_json());
+    assert_eq!(None, validate(&document).pair_errors);
+
+    let report = validate_reference(&document);
+    let errors = report.pair_errors.unwrap();
+    assert_eq!(errors, PairErrors::compute(&pairs, 6372.8));
+    assert!(errors.worst_pair < 50);
+    assert!(errors.mean <= errors.max);
+    assert!(errors.max < 1e-12, "{}", errors.max);
+    let average = report.computed_average.unwrap();
+    assert!(relative_error(errors.reference_average, average) < 1e-12);
+}
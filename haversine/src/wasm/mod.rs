@@ -0,0 +1,235 @@
+//! Functions exported to JavaScript when the library is built for
+//! WebAssembly, for a browser to parse pairs documents and compute their
+//! distances:
+//!
+//! ```text
+//! cargo rustc --lib --release --target wasm32-unknown-unknown --crate-type cdylib
+//! ```
+//!
+//! The target has no clock, and `Instant::now` panics on it, so the code
+//! these functions reach only reads it for timeouts and checkpoints, which
+//! they never set. Check changes to that code with:
+//!
+//! ```text
+//! cargo check --target wasm32-unknown-unknown --lib
+//! ```
+//!
+//! They take and return plain numbers and addresses in the module's
+//! memory, so no bindings are needed. A document is written into memory
+//! from `haversine_alloc` and parsed into a handle, whose columns the
+//! caller can view in place as `Float64Array`s:
+//!
+//! ```text
+//! const bytes = new TextEncoder().encode(json);
+//! const ptr = exports.haversine_alloc(bytes.length);
+//! new Uint8Array(exports.memory.buffer, ptr, bytes.length).set(bytes);
+//! const pairs = exports.haversine_parse_pairs(ptr, bytes.length);
+//! exports.haversine_free(ptr, bytes.length);
+//! const n = exports.haversine_pairs_len(pairs);
+//! const x0 = new Float64Array(exports.memory.buffer, exports.haversine_pairs_column(pairs, 0), n);
+//! ```
+//!
+//! Errors are returned as a null handle or NaN, with the message read from
+//! `haversine_error_ptr` and `haversine_error_len`. The functions of
+//! [`crate::ffi`], such as `haversine_distance`, are exported as well.
+
+use crate::compute::ComputeDriver;
+use crate::ffi::{set_error, with_error};
+use crate::geo::haversine_distance as distance;
+use crate::pairs::Pairs;
+use crate::parse::{Parser, Value};
+
+/// A parsed pairs document.
+pub struct PairsDocument {
+    pairs: Pairs,
+    radius: Option<f64>,
+    avg_dist: Option<f64>,
+}
+
+/// Reserves `len` bytes, to be released with `haversine_free`.
+#[unsafe(no_mangle)]
+pub extern "C" fn haversine_alloc(len: usize) -> *mut u8 {
+    Box::<[u8]>::into_raw(vec![0; len].into_boxed_slice()).cast()
+}
+
+/// Releases bytes reserved by `haversine_alloc`.
+///
+/// # Safety
+///
+/// `ptr` and `len` must be those of a reservation not yet released.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn haversine_free(ptr: *mut u8, len: usize) {
+    // SAFETY: the caller passes back a boxed slice of `len` bytes.
+    drop(unsafe { Box::from_raw(std::ptr::slice_from_raw_parts_mut(ptr, len)) });
+}
+
+/// Parses the pairs document of `len` UTF-8 bytes at `ptr`, returning a
+/// handle to be released with `haversine_pairs_free`, or null if it is not
+/// a valid pairs document.
+///
+/// # Safety
+///
+/// `ptr` must point to `len` readable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn haversine_parse_pairs(ptr: *const u8, len: usize) -> *mut PairsDocument {
+    // SAFETY: the caller guarantees `len` readable bytes.
+    let bytes = unsafe { std::slice::from_raw_parts(ptr, len) };
+    match parse_pairs(bytes) {
+        Ok(document) => Box::into_raw(Box::new(document)),
+        Err(message) => {
+            set_error(message);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+fn parse_pairs(bytes: &[u8]) -> Result<PairsDocument, String> {
+    let text = std::str::from_utf8(bytes).map_err(|error| error.to_string())?;
+    let document = match Parser::new(text).parse() {
+        Ok(Some(document)) => document,
+        Ok(None) => return Err("the document is empty".into()),
+        Err(error) => return Err(error.to_string()),
+    };
+    let Value::Object(object) = document else {
+        return Err("expected an object".into());
+    };
+    let Some(Value::Array(values)) = object.get("pairs") else {
+        return Err("expected a pairs array".into());
+    };
+    let number = |key| match object.get(key) {
+        Some(Value::Number(number)) => Some(number.as_f64()),
+        _ => None,
+    };
+    Ok(PairsDocument {
+        pairs: Pairs::from_values(values).map_err(|error| error.to_string())?,
+        radius: number("radius"),
+        avg_dist: number("avg_dist"),
+    })
+}
+
+/// Releases a document returned by `haversine_parse_pairs`.
+///
+/// # Safety
+///
+/// `document` must be a handle not yet released, or null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn haversine_pairs_free(document: *mut PairsDocument) {
+    if !document.is_null() {
+        // SAFETY: the handle was boxed by `haversine_parse_pairs`.
+        drop(unsafe { Box::from_raw(document) });
+    }
+}
+
+/// Number of pairs of a document.
+///
+/// # Safety
+///
+/// `document` must be a live handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn haversine_pairs_len(document: *const PairsDocument) -> usize {
+    // SAFETY: the caller passes a live handle.
+    unsafe { &*document }.pairs.len()
+}
+
+/// Address of column `index` of a document, of `x0`, `y0`, `x1` and `y1`
+/// in that order, holding `haversine_pairs_len` numbers; null for another
+/// index. It is valid until the document is released.
+///
+/// # Safety
+///
+/// `document` must be a live handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn haversine_pairs_column(
+    document: *const PairsDocument,
+    index: u32,
+) -> *const f64 {
+    // SAFETY: the caller passes a live handle.
+    let pairs = &unsafe { &*document }.pairs;
+    match index {
+        0 => pairs.x0.as_ptr(),
+        1 => pairs.y0.as_ptr(),
+        2 => pairs.x1.as_ptr(),
+        3 => pairs.y1.as_ptr(),
+        _ => std::ptr::null(),
+    }
+}
+
+/// The `radius` of a document, or NaN if it has none.
+///
+/// # Safety
+///
+/// `document` must be a live handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn haversine_pairs_radius(document: *const PairsDocument) -> f64 {
+    // SAFETY: the caller passes a live handle.
+    unsafe { &*document }.radius.unwrap_or(f64::NAN)
+}
+
+/// The `avg_dist` stored in a document, or NaN if it has none.
+///
+/// # Safety
+///
+/// `document` must be a live handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn haversine_pairs_stored_average(document: *const PairsDocument) -> f64 {
+    // SAFETY: the caller passes a live handle.
+    unsafe { &*document }.avg_dist.unwrap_or(f64::NAN)
+}
+
+/// The average distance of the pairs of a document on a sphere of
+/// `radius`, NaN if there are none.
+///
+/// # Safety
+///
+/// `document` must be a live handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn haversine_pairs_average(
+    document: *const PairsDocument,
+    radius: f64,
+) -> f64 {
+    // SAFETY: the caller passes a live handle.
+    let pairs = &unsafe { &*document }.pairs;
+    if pairs.is_empty() {
+        set_error("the pairs array is empty");
+        return f64::NAN;
+    }
+    ComputeDriver::new(radius).run(pairs).average
+}
+
+/// Writes the distance of every pair of a document, on a sphere of
+/// `radius`, to `out`.
+///
+/// # Safety
+///
+/// `document` must be a live handle, and `out` must point to room for
+/// `haversine_pairs_len` numbers.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn haversine_pairs_distances(
+    document: *const PairsDocument,
+    radius: f64,
+    out: *mut f64,
+) {
+    // SAFETY: the caller passes a live handle.
+    let pairs = &unsafe { &*document }.pairs;
+    // SAFETY: the caller guarantees room for a distance per pair.
+    let out = unsafe { std::slice::from_raw_parts_mut(out, pairs.len()) };
+    for (out, pair) in out.iter_mut().zip(pairs.iter()) {
+        *out = distance(radius, pair.p0.lon, pair.p0.lat, pair.p1.lon, pair.p1.lat);
+    }
+}
+
+/// Address of the UTF-8 message of the last error, valid until the next
+/// error.
+#[unsafe(no_mangle)]
+pub extern "C" fn haversine_error_ptr() -> *const u8 {
+    with_error(|error| error.as_ptr().cast())
+}
+
+/// Length in bytes of the message of the last error.
+#[unsafe(no_mangle)]
+pub extern "C" fn haversine_error_len() -> usize {
+    with_error(|error| error.as_bytes().len())
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,65 @@
+use super::*;
+use crate::geo::haversine_distance as distance;
+
+/// Copies a document into memory of the module and parses it.
+fn parse(json: &str) -> *mut PairsDocument {
+    let ptr = haversine_alloc(json.len());
+    // SAFETY: the reservation holds `json.len()` bytes, and is released
+    // once parsed.
+    unsafe {
+        std::ptr::copy_nonoverlapping(json.as_ptr(), ptr, json.len());
+        let document = haversine_parse_pairs(ptr, json.len());
+        haversine_free(ptr, json.len());
+        document
+    }
+}
+
+fn last_error() -> String {
+    // SAFETY: the message stays valid until the next error.
+    let bytes = unsafe { std::slice::from_raw_parts(haversine_error_ptr(), haversine_error_len()) };
+    String::from_utf8(bytes.to_vec()).unwrap()
+}
+
+#[test]
+fn test_parse_and_compute() {
+    let json = r#"{"pairs": [{"x0": 0, "y0": 0, "x1": 90, "y1": 0},
+        {"x0": 10, "y0": 20, "x1": 30, "y1": 40}], "radius": 2, "avg_dist": 3}"#;
+    let document = parse(json);
+    assert!(!document.is_null());
+    // SAFETY: the document is live until released at the end.
+    unsafe {
+        assert_eq!(2, haversine_pairs_len(document));
+        assert_eq!(2.0, haversine_pairs_radius(document));
+        assert_eq!(3.0, haversine_pairs_stored_average(document));
+        let y1 = std::slice::from_raw_parts(haversine_pairs_column(document, 3), 2);
+        assert_eq!([0.0, 40.0], y1);
+        assert!(haversine_pairs_column(document, 4).is_null());
+
+        let mut distances = [0.0; 2];
+        haversine_pairs_distances(document, 2.0, distances.as_mut_ptr());
+        assert_eq!(distance(2.0, 0.0, 0.0, 90.0, 0.0), distances[0]);
+        assert_eq!(distance(2.0, 10.0, 20.0, 30.0, 40.0), distances[1]);
+        let average = haversine_pairs_average(document, 2.0);
+        assert!((average - (distances[0] + distances[1]) / 2.0).abs() < 1e-12);
+        haversine_pairs_free(document);
+    }
+}
+
+#[test]
+fn test_errors() {
+    assert!(parse("[1, 2]").is_null());
+    assert_eq!("expected an object", last_error());
+    assert!(parse(r#"{"pairs": [{"x0": 0}]}"#).is_null());
+    assert!(last_error().contains("y0"), "{}", last_error());
+    assert!(parse("{").is_null());
+
+    let document = parse(r#"{"pairs": []}"#);
+    // SAFETY: the document is live until released.
+    unsafe {
+        assert!(haversine_pairs_radius(document).is_nan());
+        assert!(haversine_pairs_average(document, 1.0).is_nan());
+        assert_eq!("the pairs array is empty", last_error());
+        haversine_pairs_free(document);
+        haversine_pairs_free(std::ptr::null_mut());
+    }
+}